@@ -0,0 +1,386 @@
+use crate::encoding::Transform;
+
+/// Everything the user can trigger from the command palette or a key binding.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Action {
+    CommandPalette,
+    Help,
+    Suspend,
+    Quit,
+    ResendLast,
+    RetryWhenAllowed,
+    SendWithoutSchemaChecks,
+    SaveRequest,
+    FindRequest,
+    ToggleCollection,
+    CollapseAllFolders,
+    FilterByTag,
+    ImportPostman,
+    ImportInsomnia,
+    ValidateBody,
+    ExportPostman,
+    ImportRequestFile,
+    ExportHttpFile,
+    ExportHurl,
+    ExportMarkdown,
+    ExportHistoryMarkdown,
+    SearchHistory,
+    FilterHistoryByEnvironment,
+    SaveExchange,
+    FocusResponses,
+    ToggleResponseHeaders,
+    SortResponseHeaders,
+    ToggleWrap,
+    CycleHistoryGrouping,
+    CycleJsonView,
+    CopyResponseHeader,
+    ToggleReadableHtml,
+    SaveBaseline,
+    LoadComparison,
+    CompareWithLoaded,
+    GoToLine,
+    EditBodyExternally,
+    OpenResponseWith,
+    ExportAsCode,
+    PinnedExchanges,
+    ClearHistory,
+    Statistics,
+    ErrorDetails,
+    RecentErrors,
+    RetryLastFailure,
+    CancelRequest,
+    CheckConnectivity,
+    PreviewTemplating,
+    BulkEditParams,
+    ToggleHeaderTable,
+    DecodeJwt,
+    Transform(Transform, Scope),
+    Undo,
+    PreviewRequest,
+    RevealSecrets,
+    UnlockSecrets,
+    ChangePassphrase,
+    SwitchWorkspace,
+    CorsCheck,
+    ReplayLast,
+    LoadTest,
+    RunFolder,
+    LastRun,
+    SwitchEnvironment,
+    SwitchTheme,
+    ToggleConditional,
+    ShowValidators,
+    Mocks,
+    ServerControl,
+    RequestSettings,
+    SetConnectTo,
+    Settings,
+    SetRedirectPolicy,
+    CycleAddressPreference,
+    FlushDnsCache,
+    ResetByteCounters,
+    ToggleConsole,
+    ToggleServerTraffic,
+    ClearServerTraffic,
+    CopyConsole,
+}
+
+/// How much of the focused field an editor action works on.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Scope {
+    Word,
+    Field,
+}
+
+impl Action {
+    pub const ALL: [Action; 87] = [
+        Action::CommandPalette,
+        Action::Help,
+        Action::Suspend,
+        Action::Quit,
+        Action::ResendLast,
+        Action::RetryWhenAllowed,
+        Action::SendWithoutSchemaChecks,
+        Action::SaveRequest,
+        Action::FindRequest,
+        Action::ToggleCollection,
+        Action::CollapseAllFolders,
+        Action::FilterByTag,
+        Action::ImportPostman,
+        Action::ImportInsomnia,
+        Action::ValidateBody,
+        Action::ExportPostman,
+        Action::ImportRequestFile,
+        Action::ExportHttpFile,
+        Action::ExportHurl,
+        Action::ExportMarkdown,
+        Action::ExportHistoryMarkdown,
+        Action::SearchHistory,
+        Action::FilterHistoryByEnvironment,
+        Action::SaveExchange,
+        Action::FocusResponses,
+        Action::ToggleResponseHeaders,
+        Action::SortResponseHeaders,
+        Action::ToggleWrap,
+        Action::CycleHistoryGrouping,
+        Action::CycleJsonView,
+        Action::CopyResponseHeader,
+        Action::ToggleReadableHtml,
+        Action::SaveBaseline,
+        Action::LoadComparison,
+        Action::CompareWithLoaded,
+        Action::GoToLine,
+        Action::EditBodyExternally,
+        Action::OpenResponseWith,
+        Action::ExportAsCode,
+        Action::PinnedExchanges,
+        Action::ClearHistory,
+        Action::Statistics,
+        Action::ErrorDetails,
+        Action::RecentErrors,
+        Action::RetryLastFailure,
+        Action::CancelRequest,
+        Action::CheckConnectivity,
+        Action::PreviewTemplating,
+        Action::BulkEditParams,
+        Action::ToggleHeaderTable,
+        Action::DecodeJwt,
+        Action::Transform(Transform::Base64Encode, Scope::Word),
+        Action::Transform(Transform::Base64Encode, Scope::Field),
+        Action::Transform(Transform::Base64Decode, Scope::Word),
+        Action::Transform(Transform::Base64Decode, Scope::Field),
+        Action::Transform(Transform::UrlEncode, Scope::Word),
+        Action::Transform(Transform::UrlEncode, Scope::Field),
+        Action::Transform(Transform::UrlDecode, Scope::Word),
+        Action::Transform(Transform::UrlDecode, Scope::Field),
+        Action::Undo,
+        Action::PreviewRequest,
+        Action::RevealSecrets,
+        Action::UnlockSecrets,
+        Action::ChangePassphrase,
+        Action::SwitchWorkspace,
+        Action::CorsCheck,
+        Action::ReplayLast,
+        Action::LoadTest,
+        Action::RunFolder,
+        Action::LastRun,
+        Action::SwitchEnvironment,
+        Action::SwitchTheme,
+        Action::ToggleConditional,
+        Action::ShowValidators,
+        Action::Mocks,
+        Action::ServerControl,
+        Action::RequestSettings,
+        Action::SetConnectTo,
+        Action::Settings,
+        Action::SetRedirectPolicy,
+        Action::CycleAddressPreference,
+        Action::FlushDnsCache,
+        Action::ResetByteCounters,
+        Action::ToggleConsole,
+        Action::ToggleServerTraffic,
+        Action::ClearServerTraffic,
+        Action::CopyConsole,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::CommandPalette => "Command palette",
+            Action::Help => "Help",
+            Action::Suspend => "Suspend",
+            Action::Quit => "Quit",
+            Action::ResendLast => "Resend last request",
+            Action::SendWithoutSchemaChecks => "Send without schema checks",
+            Action::RetryWhenAllowed => "Retry when allowed",
+            Action::SaveRequest => "Save request",
+            Action::FindRequest => "Find saved request",
+            Action::ToggleCollection => "Toggle collection pane",
+            Action::CollapseAllFolders => "Collapse all folders",
+            Action::FilterByTag => "Filter by tag",
+            Action::ImportPostman => "Import Postman collection",
+            Action::ImportInsomnia => "Import Insomnia export",
+            Action::ValidateBody => "Validate body against schema",
+            Action::ExportPostman => "Export Postman collection",
+            Action::ImportRequestFile => "Import .http or .hurl file",
+            Action::ExportHttpFile => "Export as .http file",
+            Action::ExportHurl => "Export as hurl file",
+            Action::ExportMarkdown => "Export exchange as Markdown",
+            Action::ExportHistoryMarkdown => "Export history as Markdown",
+            Action::SearchHistory => "Search history",
+            Action::FilterHistoryByEnvironment => "Filter history by environment",
+            Action::SaveExchange => "Save exchange to file",
+            Action::FocusResponses => "Focus responses",
+            Action::ToggleResponseHeaders => "Toggle response headers",
+            Action::SortResponseHeaders => "Sort response headers",
+            Action::ToggleWrap => "Toggle response wrap",
+            Action::CycleHistoryGrouping => "Group history",
+            Action::CycleJsonView => "Switch JSON view",
+            Action::CopyResponseHeader => "Copy response header",
+            Action::ToggleReadableHtml => "Toggle readable HTML",
+            Action::SaveBaseline => "Save response as baseline",
+            Action::LoadComparison => "Load response file to compare",
+            Action::CompareWithLoaded => "Compare with loaded response",
+            Action::GoToLine => "Go to line",
+            Action::EditBodyExternally => "Edit body in external editor",
+            Action::OpenResponseWith => "Open response with…",
+            Action::ExportAsCode => "Export as code",
+            Action::PinnedExchanges => "Pinned exchanges",
+            Action::ClearHistory => "Clear history",
+            Action::Statistics => "Endpoint statistics",
+            Action::ErrorDetails => "Error details",
+            Action::RecentErrors => "Recent errors",
+            Action::RetryLastFailure => "Retry last failure",
+            Action::CancelRequest => "Cancel request",
+            Action::CheckConnectivity => "Check connectivity",
+            Action::PreviewTemplating => "Preview resolved templating",
+            Action::BulkEditParams => "Bulk edit query parameters",
+            Action::ToggleHeaderTable => "Toggle headers table",
+            Action::DecodeJwt => "Decode JWT",
+            Action::Transform(Transform::Base64Encode, Scope::Word) => "Base64 encode word",
+            Action::Transform(Transform::Base64Encode, Scope::Field) => "Base64 encode field",
+            Action::Transform(Transform::Base64Decode, Scope::Word) => "Base64 decode word",
+            Action::Transform(Transform::Base64Decode, Scope::Field) => "Base64 decode field",
+            Action::Transform(Transform::UrlEncode, Scope::Word) => "URL encode word",
+            Action::Transform(Transform::UrlEncode, Scope::Field) => "URL encode field",
+            Action::Transform(Transform::UrlDecode, Scope::Word) => "URL decode word",
+            Action::Transform(Transform::UrlDecode, Scope::Field) => "URL decode field",
+            Action::Undo => "Undo",
+            Action::PreviewRequest => "Preview raw request",
+            Action::RevealSecrets => "Reveal secrets",
+            Action::UnlockSecrets => "Unlock secrets",
+            Action::ChangePassphrase => "Change secrets passphrase",
+            Action::SwitchWorkspace => "Switch workspace",
+            Action::CorsCheck => "CORS check",
+            Action::ReplayLast => "Replay last request",
+            Action::LoadTest => "Load test",
+            Action::RunFolder => "Run folder",
+            Action::LastRun => "Last collection run",
+            Action::SwitchEnvironment => "Switch environment",
+            Action::SwitchTheme => "Switch theme",
+            Action::ToggleConditional => "Toggle conditional headers",
+            Action::ShowValidators => "Show cached validators",
+            Action::Mocks => "Mock endpoints",
+            Action::ServerControl => "Test server faults",
+            Action::RequestSettings => "Request settings",
+            Action::SetConnectTo => "Set connect-to override",
+            Action::Settings => "Settings",
+            Action::SetRedirectPolicy => "Set redirect policy",
+            Action::CycleAddressPreference => "Cycle IPv4/IPv6 preference",
+            Action::FlushDnsCache => "Flush DNS cache",
+            Action::ResetByteCounters => "Reset byte counters",
+            Action::ToggleConsole => "Toggle console",
+            Action::ToggleServerTraffic => "Toggle server traffic",
+            Action::ClearServerTraffic => "Clear server traffic",
+            Action::CopyConsole => "Copy console",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Action::CommandPalette => "Search and run any command",
+            Action::Help => "List the key bindings",
+            Action::Suspend => "Return to the shell; `fg` resumes, with requests in flight still arriving",
+            Action::Quit => "Exit, keeping the editor contents for next time (start with --fresh to skip them)",
+            Action::ResendLast => "Send exactly what was sent last, or the editor if nothing has been sent",
+            Action::SendWithoutSchemaChecks => "Send the editor's request this once without checking its body or response against their schemas",
+            Action::RetryWhenAllowed => "Resend a rate-limited request once its Retry-After has passed; again to cancel",
+            Action::SaveRequest => "Add the editor contents to the folder selected in the collection pane",
+            Action::FindRequest => "Fuzzy search saved requests by name, URL or method",
+            Action::ToggleCollection => "Browse saved requests by folder (n: new folder, r: rename, d: delete, m: move)",
+            Action::CollapseAllFolders => "Fold every folder in the collection pane",
+            Action::FilterByTag => "Show only saved requests carrying a tag, in the collection pane and the finder",
+            Action::ImportPostman => "Add a v2.1 collection file, folders included, under a folder of its name",
+            Action::ImportInsomnia => "Add a v4 export's requests under a folder of its workspace's name, and its environments",
+            Action::ValidateBody => "Check the body against the JSON Schema set in the request settings",
+            Action::ExportPostman => "Write the whole collection as a v2.1 collection file",
+            Action::ImportRequestFile => "Add the requests of a REST Client or hurl file as a new folder",
+            Action::ExportHttpFile => "Write the selected folder, or everything, in VS Code REST Client format",
+            Action::ExportHurl => "Write the selected folder, or everything, in hurl format",
+            Action::ExportMarkdown => "The newest request and response, for pasting into a bug report",
+            Action::ExportHistoryMarkdown => "A range of exchanges as one document with a table of contents",
+            Action::SearchHistory => "Find sent exchanges by URL, status or request id, e.g. one from a server log line",
+            Action::FilterHistoryByEnvironment => "Only show responses to requests sent with one environment",
+            Action::SaveExchange => "The newest request and response as raw HTTP, or as the history entry when the file ends in .json",
+            Action::FocusResponses => "Move keys to the responses pane (H: headers, s: sort, Up/Down: pick header, j k: pick exchange, *: pin it, y: copy, v: HTML view, i: copy request id, g: group, [ ]: section, Enter: collapse)",
+            Action::ToggleResponseHeaders => "Show response headers in full or just their count",
+            Action::SortResponseHeaders => "List response headers alphabetically or as received",
+            Action::CycleJsonView => "Show JSON responses formatted, as a foldable tree, or as received",
+            Action::ToggleWrap => "Soft-wrap response bodies, or cut them and scroll sideways with Left/Right",
+            Action::CycleHistoryGrouping => "Sort responses into sections by host, by saved request, or not at all ([ ]: pick a section, Enter: collapse)",
+            Action::CopyResponseHeader => "Copy \"Name: value\" of the highlighted header of the newest response",
+            Action::ToggleReadableHtml => "Show HTML responses as plain text with tags stripped, or as received",
+            Action::SaveBaseline => "Pin the newest response to its saved request; later runs are compared with it",
+            Action::LoadComparison => "Read a raw HTTP response or a bare body from a file and compare the newest response with it",
+            Action::CompareWithLoaded => "Compare the newest response with the response file loaded last",
+            Action::GoToLine => "Move the cursor to the start of a line of the body",
+            Action::EditBodyExternally => "Open the body in $VISUAL or $EDITOR, then take back what's saved there",
+            Action::OpenResponseWith => "Show the newest response's body in $PAGER or less -R, or the program set in the settings",
+            Action::ExportAsCode => "The request as Python requests, JavaScript fetch or Rust reqwest code, copied to the clipboard",
+            Action::PinnedExchanges => "List the pinned exchanges to view or unpin them",
+            Action::ClearHistory => "Forget sent exchanges; pinned ones only after a second confirmation",
+            Action::Statistics => "Runs, error rate and latency of each method and URL in history, slowest at p95 first",
+            Action::ErrorDetails => "The error in the status line in full, with what caused it and a hint where there is one",
+            Action::RecentErrors => "The last few errors, with when they happened",
+            Action::RetryLastFailure => "Send the last request that got no response again, exactly as it went out",
+            Action::CancelRequest => "Stop sending the newest request still out, closing its connection; history notes how far it got",
+            Action::CheckConnectivity => "Look up and connect to the request's host and port, without sending anything",
+            Action::PreviewTemplating => "The request with each placeholder's value marked, the values used and any not found",
+            Action::BulkEditParams => "Edit the URL's query as one key=value per line; // disables a line",
+            Action::ToggleHeaderTable => "Edit headers as rows with an enabled box, or as text",
+            Action::DecodeJwt => "Token under the cursor, the Authorization header, or the latest response",
+            Action::Transform(_, Scope::Word) => "Text around the cursor up to the nearest whitespace",
+            Action::Transform(_, Scope::Field) => "The whole focused field",
+            Action::Undo => "Revert the last transform, cut or paste",
+            Action::PreviewRequest => "Resolve placeholders; the next send uses the previewed values",
+            Action::RevealSecrets => "Show masked credentials in one view, or mask them again",
+            Action::UnlockSecrets => "Decrypt the secret variables of the environments file with its passphrase",
+            Action::ChangePassphrase => "Encrypt every secret variable again under a new passphrase",
+            Action::SwitchWorkspace => "Open, create, rename or delete a workspace, each with its own collection, environments and history",
+            Action::CorsCheck => "Send a preflight OPTIONS to the URL and judge what a browser would allow",
+            Action::ReplayLast => "Send the last request again, with its original or the current environment, reusing or regenerating {{$...}} values",
+            Action::LoadTest => "Send the editor's request from many workers at once, then summarise latency and statuses",
+            Action::RunFolder => "Send every saved request in the selected folder, or the whole collection, one after another",
+            Action::LastRun => "Show the results of the latest collection run, this session's or a saved one",
+            Action::SwitchEnvironment => "Choose the variables used for {{name}} placeholders",
+            Action::SwitchTheme => "Try the built-in color schemes, shown as you move through them, and keep one",
+            Action::ToggleConditional => "Send If-None-Match/If-Modified-Since from remembered ETag/Last-Modified",
+            Action::ShowValidators => "List and clear the ETag/Last-Modified values stored per URL",
+            Action::Mocks => "Canned responses served by the built-in test server; new paths are served after a restart",
+            Action::ServerControl => "Make the built-in test server slow, flaky or hung, to try out timeouts and retries",
+            Action::RequestSettings => "Timeout, redirects and retries for this request, each inheriting the config unless set",
+            Action::Settings => "Edit config.toml's options and apply them",
+            Action::SetConnectTo => "Dial another host:port while keeping the URL's host in the Host header",
+            Action::SetRedirectPolicy => "Follow this request's redirects up to some hops, or not at all, and whether credentials cross hosts",
+            Action::CycleAddressPreference => "As resolved, prefer IPv4, or prefer IPv6 when a host has both",
+            Action::FlushDnsCache => "Look every host name up afresh, e.g. after editing /etc/hosts",
+            Action::ResetByteCounters => "Start the status bar's sent and received totals from zero",
+            Action::ToggleConsole => "Show the verbose transcript of each request (PageUp/PageDown to scroll)",
+            Action::ToggleServerTraffic => "Show the requests the built-in test server received, newest first",
+            Action::ClearServerTraffic => "Forget the requests the built-in test server received so far",
+            Action::CopyConsole => "Copy the whole console transcript to the clipboard",
+        }
+    }
+
+    /// The name used for this action in the `[keys]` section of the config, e.g. `resend_last`.
+    pub fn id(&self) -> String {
+        let mut id = String::new();
+        for c in self.name().chars() {
+            if c.is_ascii_alphanumeric() {
+                id.push(c.to_ascii_lowercase());
+            } else if !id.is_empty() && !id.ends_with('_') {
+                id.push('_');
+            }
+        }
+        id.trim_end_matches('_').to_string()
+    }
+
+    /// Actions whose name contains `filter`, case-insensitively.
+    pub fn matching(filter: &str) -> Vec<Action> {
+        let filter = filter.to_lowercase();
+        Action::ALL
+            .into_iter()
+            .filter(|action| *action != Action::CommandPalette)
+            .filter(|action| action.name().to_lowercase().contains(&filter))
+            .collect()
+    }
+}
@@ -0,0 +1,158 @@
+use std::{fmt, io};
+
+use chrono::{DateTime, Local};
+
+use crate::wire::WireError;
+
+/// Errors kept for the recent errors list, oldest dropped first.
+pub const RECENT_ERRORS: usize = 20;
+
+const REFUSED_HINT: &str = "Nothing is listening there; check the host and port, and that the server is running";
+const READ_TIMEOUT_HINT: &str = "Raise read_timeout_secs in the settings if the server is just slow to send";
+const TIMEOUT_HINT: &str = "Raise timeout_secs in the settings, or the request's own timeout, if the server is just slow";
+pub const LOOKUP_HINT: &str = "Check the host name for typos, or flush the DNS cache if it just changed";
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ErrorKind {
+    /// Couldn't reach the server or it broke off the exchange.
+    Network,
+    Url,
+    /// Something about the request as written: its body, placeholders or hooks.
+    Request,
+    /// The server answered, though not the way the request hoped.
+    Response,
+    File,
+    Other,
+}
+
+impl ErrorKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            ErrorKind::Network => "network",
+            ErrorKind::Url => "URL",
+            ErrorKind::Request => "request",
+            ErrorKind::Response => "response",
+            ErrorKind::File => "file",
+            ErrorKind::Other => "error",
+        }
+    }
+}
+
+/// What went wrong, for the status line and the details popup. `summary` is the line shown;
+/// `detail` goes below it once expanded, and `hint` says what might fix it.
+#[derive(Clone, Debug)]
+pub struct AppError {
+    pub summary: String,
+    pub detail: Option<String>,
+    pub kind: ErrorKind,
+    pub hint: Option<String>,
+    pub at: DateTime<Local>,
+}
+
+impl AppError {
+    pub fn new(kind: ErrorKind, summary: impl Into<String>) -> Self {
+        Self { summary: summary.into(), detail: None, kind, hint: None, at: Local::now() }
+    }
+
+    pub fn detail(mut self, detail: impl Into<String>) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
+
+    pub fn hint(mut self, hint: impl Into<String>) -> Self {
+        self.hint = Some(hint.into());
+        self
+    }
+
+    /// A request that stopped on its connection to `peer`, with what the error suggests about
+    /// why.
+    pub fn wire(error: &WireError, peer: &str) -> Self {
+        let hint = match error {
+            WireError::ReadTimeout(_) | WireError::WriteTimeout(_) => Some(READ_TIMEOUT_HINT),
+            WireError::Deadline(_) => Some(TIMEOUT_HINT),
+            WireError::Io(e) => io_hint(e.kind()),
+            _ => None,
+        };
+        Self { hint: hint.map(String::from), ..Self::new(ErrorKind::Network, error.describe(peer)) }
+    }
+
+    /// Whether there's more to it than the summary line shows.
+    pub fn has_more(&self) -> bool {
+        self.detail.is_some() || self.hint.is_some()
+    }
+
+    /// The messages a chain like `Couldn't save history: /path: Permission denied` wraps, outermost
+    /// first. A single message when there's no chain.
+    pub fn chain(&self) -> Vec<&str> {
+        self.summary.split(": ").filter(|part| !part.is_empty()).collect()
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.summary)
+    }
+}
+
+/// A plain message, sorted into a kind with a hint for the common ones. A message of several
+/// lines keeps the first as its summary.
+impl From<String> for AppError {
+    fn from(message: String) -> Self {
+        let (summary, detail) = match message.split_once('\n') {
+            Some((summary, detail)) => (summary.to_string(), Some(detail.to_string())),
+            None => (message, None),
+        };
+        let lowercase = summary.to_lowercase();
+        let has = |words: &[&str]| words.iter().any(|word| lowercase.contains(word));
+        let (kind, hint) = if has(&["connection refused"]) {
+            (ErrorKind::Network, Some(REFUSED_HINT))
+        } else if has(&["timed out"]) {
+            (ErrorKind::Network, Some(TIMEOUT_HINT))
+        } else if has(&["failed to lookup", "name or service not known", "nodename nor servname", "no such host"]) {
+            (ErrorKind::Network, Some(LOOKUP_HINT))
+        } else if has(&["invalid url", "://"]) && has(&["isn't supported", "scheme"]) {
+            (ErrorKind::Url, Some("Only http:// URLs can be sent"))
+        } else if has(&["invalid url"]) {
+            (ErrorKind::Url, Some("A URL looks like http://host:port/path; {{variables}} are filled in first"))
+        } else if has(&["permission denied"]) {
+            (ErrorKind::File, Some("Check the permissions of the file and the directory it's in"))
+        } else if has(&["no such file", "not found (os error"]) {
+            (ErrorKind::File, Some("Relative paths start from the directory the app was started in"))
+        } else if has(&["couldn't write", "couldn't read", "couldn't save", "couldn't load"]) {
+            (ErrorKind::File, None)
+        } else if has(&["dynamic variable", "placeholder", "hook", "json", "schema"]) {
+            (ErrorKind::Request, None)
+        } else {
+            (ErrorKind::Other, None)
+        };
+        Self { summary, detail, kind, hint: hint.map(String::from), at: Local::now() }
+    }
+}
+
+impl From<&str> for AppError {
+    fn from(message: &str) -> Self {
+        Self::from(message.to_string())
+    }
+}
+
+/// What might get a connection through that failed with `kind`.
+pub fn io_hint(kind: io::ErrorKind) -> Option<&'static str> {
+    match kind {
+        io::ErrorKind::ConnectionRefused => Some(REFUSED_HINT),
+        io::ErrorKind::TimedOut => Some(TIMEOUT_HINT),
+        _ => None,
+    }
+}
+
+/// What a response suggests went wrong with the request, for statuses with a likely cause.
+pub fn response_hint(status: Option<u16>, body: &str, has_content_type: bool, sent_body: bool) -> Option<AppError> {
+    match status? {
+        415 if sent_body && !has_content_type => Some(
+            AppError::new(ErrorKind::Response, "415 Unsupported Media Type: the server didn't accept the body").hint("Add a Content-Type header, like Content-Type: application/json"),
+        ),
+        400 if body.to_lowercase().contains("plain http request was sent to https port") => Some(
+            AppError::new(ErrorKind::Response, "400: the server expected TLS on that port").detail(body.trim().to_string()).hint("Did you mean https://? Requests go out as plain HTTP, so use the server's plain HTTP port"),
+        ),
+        _ => None,
+    }
+}
@@ -0,0 +1,116 @@
+use serde_json::Value;
+
+use crate::{config::BaselineConfig, history::ResponseRecord};
+
+/// Differences listed before the rest are summarised as a count.
+const MAX_DIFFERENCES: usize = 20;
+
+/// How `actual` differs from the pinned `baseline`, one line per difference; empty when it
+/// matches. JSON bodies are compared structurally by path, anything else line by line.
+pub fn compare(baseline: &ResponseRecord, actual: &ResponseRecord, rules: &BaselineConfig) -> Vec<String> {
+    let mut differences = vec![];
+    if baseline.status != actual.status {
+        let code = |status: Option<u16>| status.map_or("???".to_string(), |code| code.to_string());
+        differences.push(format!("status: {} → {}", code(baseline.status), code(actual.status)));
+    }
+    compare_headers(baseline, actual, rules, &mut differences);
+    match (serde_json::from_str::<Value>(&baseline.body), serde_json::from_str::<Value>(&actual.body)) {
+        (Ok(expected), Ok(found)) => compare_json("", &expected, &found, rules, &mut differences),
+        _ => compare_text(&baseline.body, &actual.body, &mut differences),
+    }
+    if differences.len() > MAX_DIFFERENCES {
+        let more = differences.len() - MAX_DIFFERENCES;
+        differences.truncate(MAX_DIFFERENCES);
+        differences.push(format!("… and {} more", more));
+    }
+    differences
+}
+
+fn compare_headers(baseline: &ResponseRecord, actual: &ResponseRecord, rules: &BaselineConfig, differences: &mut Vec<String>) {
+    let ignored = |name: &str| rules.ignore_headers.iter().any(|ignored| ignored.eq_ignore_ascii_case(name));
+    let find = |headers: &[(String, String)], name: &str| {
+        headers.iter().find(|(other, _)| other.eq_ignore_ascii_case(name)).map(|(_, value)| value.clone())
+    };
+    for (name, expected) in baseline.headers.iter().filter(|(name, _)| !ignored(name)) {
+        match find(&actual.headers, name) {
+            Some(found) if found == *expected => {}
+            Some(found) => differences.push(format!("~ header {}: {} → {}", name, expected, found)),
+            None => differences.push(format!("- header {}: {}", name, expected)),
+        }
+    }
+    for (name, found) in actual.headers.iter().filter(|(name, _)| !ignored(name)) {
+        if find(&baseline.headers, name).is_none() {
+            differences.push(format!("+ header {}: {}", name, found));
+        }
+    }
+}
+
+fn compare_json(path: &str, expected: &Value, found: &Value, rules: &BaselineConfig, differences: &mut Vec<String>) {
+    if is_ignored(path, &rules.ignore_paths) {
+        return;
+    }
+    let shown = if path.is_empty() {"."} else {path};
+    match (expected, found) {
+        (Value::Object(expected), Value::Object(found)) => {
+            for (key, value) in expected {
+                let child = format!("{}.{}", path, key);
+                match found.get(key) {
+                    Some(other) => compare_json(&child, value, other, rules, differences),
+                    None if !is_ignored(&child, &rules.ignore_paths) => differences.push(format!("- {}: {}", child, value)),
+                    None => {}
+                }
+            }
+            for (key, value) in found.iter().filter(|(key, _)| !expected.contains_key(*key)) {
+                let child = format!("{}.{}", path, key);
+                if !is_ignored(&child, &rules.ignore_paths) {
+                    differences.push(format!("+ {}: {}", child, value));
+                }
+            }
+        }
+        (Value::Array(expected), Value::Array(found)) => {
+            for (i, (value, other)) in expected.iter().zip(found).enumerate() {
+                compare_json(&format!("{}[{}]", path, i), value, other, rules, differences);
+            }
+            if expected.len() != found.len() {
+                differences.push(format!("~ {}: {} items → {}", shown, expected.len(), found.len()));
+            }
+        }
+        _ if expected != found => differences.push(format!("~ {}: {} → {}", shown, expected, found)),
+        _ => {}
+    }
+}
+
+/// Whether `path`, e.g. `.items[3].id`, is covered by an ignore rule. Rules match the path
+/// itself or anything under it, and `[*]` in a rule matches any index.
+fn is_ignored(path: &str, rules: &[String]) -> bool {
+    let mut general = String::with_capacity(path.len());
+    let mut rest = path;
+    while let Some(open) = rest.find('[') {
+        general.push_str(&rest[..open]);
+        let close = rest[open..].find(']').map_or(rest.len(), |close| open + close + 1);
+        general.push_str("[*]");
+        rest = &rest[close..];
+    }
+    general.push_str(rest);
+    rules.iter().any(|rule| {
+        [path, general.as_str()].iter().any(|path| {
+            path.strip_prefix(rule.as_str()).is_some_and(|after| after.is_empty() || after.starts_with(['.', '[']))
+        })
+    })
+}
+
+fn compare_text(expected: &str, found: &str, differences: &mut Vec<String>) {
+    if expected == found {
+        return;
+    }
+    let expected_lines: Vec<&str> = expected.lines().collect();
+    let found_lines: Vec<&str> = found.lines().collect();
+    let first = expected_lines.iter().zip(&found_lines).position(|(a, b)| a != b).unwrap_or(expected_lines.len().min(found_lines.len()));
+    differences.push(format!("body differs from line {} ({} lines → {})", first + 1, expected_lines.len(), found_lines.len()));
+    if let Some(line) = expected_lines.get(first) {
+        differences.push(format!("- {}", line));
+    }
+    if let Some(line) = found_lines.get(first) {
+        differences.push(format!("+ {}", line));
+    }
+}
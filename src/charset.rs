@@ -0,0 +1,154 @@
+/// How far into an HTML body a `<meta charset>` is looked for; the spec puts it in the first
+/// 1024 bytes.
+const META_WINDOW: usize = 1024;
+
+/// Code points of windows-1252's 0x80 to 0x9F, where it differs from ISO-8859-1. The five
+/// unassigned bytes decode as their C1 control, as browsers do.
+const WINDOWS_1252_HIGH: [u32; 32] = [
+    0x20AC, 0x81, 0x201A, 0x0192, 0x201E, 0x2026, 0x2020, 0x2021, 0x02C6, 0x2030, 0x0160, 0x2039, 0x0152, 0x8D, 0x017D, 0x8F,
+    0x90, 0x2018, 0x2019, 0x201C, 0x201D, 0x2022, 0x2013, 0x2014, 0x02DC, 0x2122, 0x0161, 0x203A, 0x0153, 0x9D, 0x017E, 0x0178,
+];
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Encoding {
+    Utf8,
+    Latin1,
+    Windows1252,
+    Utf16Le,
+    Utf16Be,
+}
+
+/// A charset named by a response, and where.
+pub struct Declared {
+    pub name: String,
+    /// `Content-Type` or `<meta>`.
+    pub source: &'static str,
+    /// `None` for a charset that can't be decoded here.
+    pub encoding: Option<Encoding>,
+}
+
+/// The charset of a body: the `Content-Type` parameter, or for HTML without one, a `<meta>` tag
+/// near the start of the body.
+pub fn declared(content_type: Option<&str>, body: &str) -> Option<Declared> {
+    let from_header = content_type.and_then(parameter);
+    let (name, source) = match from_header {
+        Some(name) => (name, "Content-Type"),
+        None if content_type.is_none_or(|value| value.to_ascii_lowercase().contains("html")) => (meta(body)?, "<meta>"),
+        None => return None,
+    };
+    Some(Declared { encoding: encoding(&name), name, source })
+}
+
+/// `charset=...` from a `Content-Type` value, unquoted and lowercased.
+fn parameter(content_type: &str) -> Option<String> {
+    content_type.split(';').skip(1).find_map(|param| {
+        let (key, value) = param.split_once('=')?;
+        key.trim().eq_ignore_ascii_case("charset").then(|| value.trim().trim_matches(['"', '\'']).to_ascii_lowercase())
+    })
+}
+
+/// The charset of `<meta charset="...">` or `<meta http-equiv="Content-Type" content="...">`.
+fn meta(body: &str) -> Option<String> {
+    let end = (0..=META_WINDOW.min(body.len())).rev().find(|&i| body.is_char_boundary(i)).unwrap_or(0);
+    let head = body[..end].to_ascii_lowercase();
+    let mut rest = head.as_str();
+    while let Some(start) = rest.find("<meta") {
+        let tag = &rest[start..];
+        let tag = &tag[..tag.find('>').unwrap_or(tag.len())];
+        if let Some(at) = tag.find("charset=") {
+            let value = tag[at + "charset=".len()..].trim_start_matches(['"', '\'']);
+            let name: String = value.chars().take_while(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ':')).collect();
+            if !name.is_empty() {
+                return Some(name);
+            }
+        }
+        rest = &rest[start + "<meta".len()..];
+    }
+    None
+}
+
+/// The encodings decoded here, under the labels servers commonly use for them.
+pub fn encoding(name: &str) -> Option<Encoding> {
+    match name.to_ascii_lowercase().as_str() {
+        "utf-8" | "utf8" | "us-ascii" | "ascii" => Some(Encoding::Utf8),
+        "iso-8859-1" | "iso8859-1" | "iso_8859-1" | "latin1" | "latin-1" | "l1" => Some(Encoding::Latin1),
+        "windows-1252" | "cp1252" | "x-cp1252" => Some(Encoding::Windows1252),
+        "utf-16le" => Some(Encoding::Utf16Le),
+        "utf-16be" | "utf-16" => Some(Encoding::Utf16Be),
+        _ => None,
+    }
+}
+
+/// Decodes a response body by the charset its `Content-Type`, or for HTML its `<meta>`,
+/// declares. A charset that can't be decoded here, or none, reads as UTF-8.
+pub fn decode_body(content_type: Option<&str>, bytes: &[u8]) -> String {
+    let start = decode(&bytes[..bytes.len().min(META_WINDOW)], None);
+    decode(bytes, declared(content_type, &start).and_then(|declared| declared.encoding))
+}
+
+/// Decodes bytes to text. A byte order mark wins over `encoding`, and bytes that don't fit the
+/// encoding become U+FFFD. Without an encoding, that's lossy UTF-8.
+pub fn decode(bytes: &[u8], encoding: Option<Encoding>) -> String {
+    let (encoding, bytes) = match bytes {
+        [0xEF, 0xBB, 0xBF, rest @ ..] => (Some(Encoding::Utf8), rest),
+        [0xFF, 0xFE, rest @ ..] => (Some(Encoding::Utf16Le), rest),
+        [0xFE, 0xFF, rest @ ..] => (Some(Encoding::Utf16Be), rest),
+        _ => (encoding, bytes),
+    };
+    match encoding.unwrap_or(Encoding::Utf8) {
+        Encoding::Utf8 => String::from_utf8_lossy(bytes).into_owned(),
+        Encoding::Latin1 => bytes.iter().map(|&byte| byte as char).collect(),
+        Encoding::Windows1252 => bytes
+            .iter()
+            .map(|&byte| match byte {
+                0x80..=0x9F => char::from_u32(WINDOWS_1252_HIGH[byte as usize - 0x80]).unwrap_or('\u{FFFD}'),
+                _ => byte as char,
+            })
+            .collect(),
+        Encoding::Utf16Le | Encoding::Utf16Be => {
+            let units = bytes.chunks(2).map(|pair| match (pair, encoding) {
+                ([low, high], Some(Encoding::Utf16Le)) => u16::from_le_bytes([*low, *high]),
+                ([high, low], _) => u16::from_be_bytes([*high, *low]),
+                _ => 0xFFFD,
+            });
+            char::decode_utf16(units).map(|unit| unit.unwrap_or('\u{FFFD}')).collect()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_latin1_by_the_content_type() {
+        assert_eq!(decode_body(Some("text/plain; charset=ISO-8859-1"), b"caf\xe9 \xa35"), "café £5");
+        assert_eq!(decode_body(Some("text/plain; charset=\"windows-1252\""), b"\x93quoted\x94 \x80"), "\u{201C}quoted\u{201D} €");
+    }
+
+    #[test]
+    fn decodes_by_a_meta_tag_when_html_has_no_charset_parameter() {
+        let body = b"<html><head><meta charset=\"latin1\"></head><body>na\xefve</body></html>";
+        assert!(decode_body(Some("text/html"), body).contains("naïve"));
+        assert!(decode_body(None, body).contains("naïve"));
+    }
+
+    #[test]
+    fn a_byte_order_mark_wins() {
+        let le = [0xFF, 0xFE, b'h', 0, b'i', 0, 0xAC, 0x20];
+        assert_eq!(decode_body(Some("text/plain"), &le), "hi€");
+        let be = [0xFE, 0xFF, 0, b'h', 0, b'i'];
+        assert_eq!(decode_body(Some("text/plain; charset=iso-8859-1"), &be), "hi");
+        assert_eq!(decode_body(None, b"\xEF\xBB\xBFcaf\xC3\xA9"), "café");
+    }
+
+    #[test]
+    fn a_wrong_or_unknown_declaration_is_survivable() {
+        // Declared UTF-8 but really Latin-1: the bad byte shows as U+FFFD, the rest is kept.
+        assert_eq!(decode_body(Some("text/plain; charset=utf-8"), b"caf\xe9"), "caf\u{FFFD}");
+        // Declared Latin-1 but really UTF-8: mojibake, but nothing lost.
+        assert_eq!(decode_body(Some("text/plain; charset=latin1"), "café".as_bytes()), "cafÃ©");
+        assert_eq!(decode_body(Some("text/plain; charset=koi8-r"), b"plain"), "plain");
+        assert!(declared(Some("text/plain; charset=koi8-r"), "").is_some_and(|declared| declared.encoding.is_none()));
+    }
+}
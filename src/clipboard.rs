@@ -0,0 +1,41 @@
+use std::{
+    io::{self, Write},
+    process::{Command, Stdio},
+};
+
+use crate::encoding::base64_encode;
+
+const CLIPBOARD_COMMANDS: [(&str, &[&str]); 4] = [
+    ("pbcopy", &[]),
+    ("wl-copy", &[]),
+    ("xclip", &["-selection", "clipboard"]),
+    ("xsel", &["--clipboard", "--input"]),
+];
+
+/// Copies text with the first clipboard tool that works, falling back to the OSC 52 escape so
+/// copying still works over SSH.
+pub fn copy(text: &str) -> io::Result<()> {
+    for (program, args) in CLIPBOARD_COMMANDS {
+        if copy_with(program, args, text).is_ok() {
+            return Ok(());
+        }
+    }
+    let mut stdout = io::stdout();
+    write!(stdout, "\x1b]52;c;{}\x07", base64_encode(text.as_bytes()))?;
+    stdout.flush()
+}
+
+fn copy_with(program: &str, args: &[&str], text: &str) -> io::Result<()> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    child.stdin.take().expect("stdin is piped").write_all(text.as_bytes())?;
+    if child.wait()?.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!("{} failed", program)))
+    }
+}
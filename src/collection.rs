@@ -0,0 +1,263 @@
+use std::{collections::BTreeSet, fs, io, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{fuzzy, history::ResponseRecord, mock::Mock, request::RequestSpec};
+
+/// A named request kept in the collection file.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SavedRequest {
+    pub name: String,
+    /// Path of the folder holding this request, empty at the top level.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub folder: Vec<String>,
+    pub request: RequestSpec,
+    /// Unix seconds of the last time it was loaded or saved, used to order search results.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_used: Option<i64>,
+    /// A known-good response later runs are compared against.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub baseline: Option<ResponseRecord>,
+    /// Free-form notes, possibly several lines.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Labels that cut across folders, like `auth` or `slow`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+impl SavedRequest {
+    /// Whether the request carries `tag`; every request does when there's no tag to match.
+    pub fn has_tag(&self, tag: Option<&str>) -> bool {
+        tag.is_none_or(|tag| self.tags.iter().any(|own| own == tag))
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Clone, Debug)]
+pub struct Collection {
+    /// Every folder as its path from the top level, listed explicitly so empty folders survive
+    /// a round trip.
+    #[serde(default)]
+    pub folders: Vec<Vec<String>>,
+    #[serde(default)]
+    pub requests: Vec<SavedRequest>,
+    /// Endpoints the built-in test server fakes.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub mocks: Vec<Mock>,
+}
+
+/// One line of the collection pane.
+#[derive(Clone, PartialEq, Debug)]
+pub enum TreeRow {
+    Folder { path: Vec<String>, collapsed: bool },
+    Request(usize),
+}
+
+/// Which part of a saved request a search matched, best first.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum MatchedField {
+    Name,
+    Url,
+    Method,
+    /// The tags joined by spaces.
+    Tags,
+}
+
+/// A search hit, with the matched char positions for highlighting.
+pub struct Found {
+    pub index: usize,
+    pub field: MatchedField,
+    pub positions: Vec<usize>,
+}
+
+pub fn folder_label(path: &[String]) -> String {
+    if path.is_empty() {"(top level)".to_string()} else {path.join(" / ")}
+}
+
+impl Collection {
+    /// Adds a folder along with any missing parents.
+    pub fn add_folder(&mut self, path: &[String]) {
+        for depth in 1..=path.len() {
+            if !self.folders.iter().any(|folder| folder == &path[..depth]) {
+                self.folders.push(path[..depth].to_vec());
+            }
+        }
+    }
+
+    /// Saves `request` under `name` in `folder`, replacing a request of the same name there.
+    pub fn save_request(&mut self, folder: Vec<String>, name: String, request: RequestSpec, now: i64) {
+        self.add_folder(&folder);
+        match self.requests.iter_mut().find(|existing| existing.name == name && existing.folder == folder) {
+            Some(existing) => {
+                existing.request = request;
+                existing.last_used = Some(now);
+            }
+            None => self.requests.push(SavedRequest { name, folder, request, last_used: Some(now), baseline: None, description: None, tags: vec![] }),
+        }
+    }
+
+    pub fn position(&self, folder: &[String], name: &str) -> Option<usize> {
+        self.requests.iter().position(|saved| saved.folder == folder && saved.name == name)
+    }
+
+    pub fn move_request(&mut self, index: usize, folder: Vec<String>) {
+        self.add_folder(&folder);
+        if let Some(saved) = self.requests.get_mut(index) {
+            saved.folder = folder;
+        }
+    }
+
+    pub fn rename_request(&mut self, index: usize, name: String) -> Result<(), String> {
+        let folder = &self.requests[index].folder;
+        if self.position(folder, &name).is_some_and(|existing| existing != index) {
+            return Err(format!("{} already holds a request called {}", folder_label(folder), name));
+        }
+        self.requests[index].name = name;
+        Ok(())
+    }
+
+    /// Swaps a request with its neighbour in the same folder, returning where it ended up.
+    pub fn shift_request(&mut self, index: usize, up: bool) -> Option<usize> {
+        let folder = &self.requests[index].folder;
+        let mut siblings = self.requests.iter().enumerate().filter(|(_, saved)| saved.folder == *folder).map(|(i, _)| i);
+        let neighbour = if up {
+            siblings.take_while(|&i| i < index).last()
+        } else {
+            siblings.find(|&i| i > index)
+        }?;
+        self.requests.swap(index, neighbour);
+        Some(neighbour)
+    }
+
+    /// Every tag in use, sorted.
+    pub fn tags(&self) -> Vec<String> {
+        let tags: BTreeSet<&String> = self.requests.iter().flat_map(|saved| &saved.tags).collect();
+        tags.into_iter().cloned().collect()
+    }
+
+    /// Requests in `path` or any folder below it.
+    pub fn requests_under<'a>(&'a self, path: &'a [String]) -> impl Iterator<Item = &'a SavedRequest> {
+        self.requests.iter().filter(move |saved| saved.folder.starts_with(path))
+    }
+
+    /// Renames the last segment of `path`, carrying its subfolders and requests along.
+    pub fn rename_folder(&mut self, path: &[String], name: String) -> Result<(), String> {
+        let Some((_, parent)) = path.split_last() else { return Err("The top level can't be renamed".to_string()) };
+        let mut renamed = parent.to_vec();
+        renamed.push(name);
+        if self.folders.contains(&renamed) {
+            return Err(format!("{} already exists", folder_label(&renamed)));
+        }
+        let rebase = |folder: &mut Vec<String>| {
+            if folder.starts_with(path) {
+                folder.splice(..path.len(), renamed.iter().cloned());
+            }
+        };
+        self.folders.iter_mut().for_each(rebase);
+        self.requests.iter_mut().for_each(|saved| rebase(&mut saved.folder));
+        Ok(())
+    }
+
+    /// Removes a folder with everything inside it, returning the requests that went with it.
+    pub fn delete_folder(&mut self, path: &[String]) -> Vec<SavedRequest> {
+        self.folders.retain(|folder| !folder.starts_with(path));
+        let (removed, kept) = self.requests.drain(..).partition(|saved| saved.folder.starts_with(path));
+        self.requests = kept;
+        removed
+    }
+
+    /// The rows of the collection pane: each folder's subfolders first, then its requests,
+    /// skipping the contents of collapsed folders. With a `tag`, only requests carrying it are
+    /// listed, along with the folders leading to them.
+    pub fn tree(&self, collapsed: &BTreeSet<Vec<String>>, tag: Option<&str>) -> Vec<TreeRow> {
+        let mut rows = vec![];
+        self.push_rows(&[], collapsed, tag, &mut rows);
+        rows
+    }
+
+    fn push_rows(&self, parent: &[String], collapsed: &BTreeSet<Vec<String>>, tag: Option<&str>, rows: &mut Vec<TreeRow>) {
+        for folder in self.folders.iter().filter(|folder| folder.len() == parent.len() + 1 && folder.starts_with(parent)) {
+            if tag.is_some() && !self.requests_under(folder).any(|saved| saved.has_tag(tag)) {
+                continue;
+            }
+            let is_collapsed = collapsed.contains(folder);
+            rows.push(TreeRow::Folder { path: folder.clone(), collapsed: is_collapsed });
+            if !is_collapsed {
+                self.push_rows(folder, collapsed, tag, rows);
+            }
+        }
+        rows.extend(
+            self.requests
+                .iter()
+                .enumerate()
+                .filter(|(_, saved)| saved.folder == parent && saved.has_tag(tag))
+                .map(|(index, _)| TreeRow::Request(index)),
+        );
+    }
+
+    /// Fuzzy-matches `query` against each request's name, URL, method and tags across all
+    /// folders, among the requests carrying `tag` if given. A name match beats any URL match,
+    /// and so on down that list; within a field the higher score wins and the more recently
+    /// used request breaks ties.
+    pub fn find(&self, query: &str, tag: Option<&str>) -> Vec<Found> {
+        let mut found: Vec<(Found, i64)> = self
+            .requests
+            .iter()
+            .enumerate()
+            .filter(|(_, saved)| saved.has_tag(tag))
+            .filter_map(|(index, saved)| {
+                let tags = saved.tags.join(" ");
+                let found = [
+                    (MatchedField::Name, saved.name.as_str()),
+                    (MatchedField::Url, saved.request.url.as_str()),
+                    (MatchedField::Method, saved.request.method.as_str()),
+                    (MatchedField::Tags, tags.as_str()),
+                ]
+                .into_iter()
+                .find_map(|(field, text)| fuzzy::score(query, text).map(|(score, positions)| (Found { index, field, positions }, score)));
+                found
+            })
+            .collect();
+        found.sort_by(|(a, a_score), (b, b_score)| {
+            a.field
+                .cmp(&b.field)
+                .then(b_score.cmp(a_score))
+                .then(self.requests[b.index].last_used.cmp(&self.requests[a.index].last_used))
+        });
+        found.into_iter().map(|(found, _)| found).collect()
+    }
+}
+
+impl TreeRow {
+    pub fn depth(&self, collection: &Collection) -> usize {
+        match self {
+            TreeRow::Folder { path, .. } => path.len() - 1,
+            TreeRow::Request(index) => collection.requests[*index].folder.len(),
+        }
+    }
+}
+
+/// Loads the collection file, treating a missing file as an empty collection.
+pub fn load(path: &Path) -> Result<Collection, String> {
+    let mut collection: Collection = match fs::read_to_string(path) {
+        Ok(contents) => serde_json::from_str(&contents).map_err(|e| format!("{}: {}", path.display(), e))?,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Collection::default(),
+        Err(e) => return Err(format!("{}: {}", path.display(), e)),
+    };
+    // Hand-edited files may name a folder only on its requests.
+    let used: Vec<Vec<String>> = collection.requests.iter().map(|saved| saved.folder.clone()).collect();
+    for folder in used {
+        collection.add_folder(&folder);
+    }
+    Ok(collection)
+}
+
+pub fn save(path: &Path, collection: &Collection) -> Result<(), String> {
+    let write = || -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(collection)?)
+    };
+    write().map_err(|e| format!("{}: {}", path.display(), e))
+}
@@ -0,0 +1,30 @@
+use crate::request::header_value;
+
+/// Cache validators remembered from a response, replayed as conditional request headers.
+#[derive(Clone, Debug)]
+pub struct Validators {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+impl Validators {
+    pub fn from_headers(headers: &[(String, String)]) -> Option<Self> {
+        let validators = Validators {
+            etag: header_value(headers, "ETag").map(str::to_string),
+            last_modified: header_value(headers, "Last-Modified").map(str::to_string),
+        };
+        (validators.etag.is_some() || validators.last_modified.is_some()).then_some(validators)
+    }
+
+    /// Adds `If-None-Match`/`If-Modified-Since`, leaving alone any the user wrote themselves.
+    pub fn apply(&self, headers: &mut Vec<(String, String)>) {
+        let conditions = [("If-None-Match", &self.etag), ("If-Modified-Since", &self.last_modified)];
+        for (name, value) in conditions {
+            if let Some(value) = value {
+                if header_value(headers, name).is_none() {
+                    headers.push((name.to_string(), value.clone()));
+                }
+            }
+        }
+    }
+}
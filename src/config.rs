@@ -0,0 +1,272 @@
+use std::{collections::BTreeMap, env, fs, io, path::PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{host_rules::HostRule, redirect, theme::Preset, workspace};
+
+/// Where tui_postman keeps its files: `$XDG_CONFIG_HOME/tui_postman`, falling back to
+/// `~/.config/tui_postman`.
+pub fn config_dir() -> PathBuf {
+    let base = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .unwrap_or_else(|| PathBuf::from("."));
+    base.join("tui_postman")
+}
+
+pub fn environments_path() -> PathBuf {
+    workspace::data_dir().join("environments.json")
+}
+
+pub fn collection_path() -> PathBuf {
+    workspace::data_dir().join("collection.json")
+}
+
+pub fn history_path() -> PathBuf {
+    workspace::data_dir().join("history.json")
+}
+
+pub fn session_path() -> PathBuf {
+    workspace::data_dir().join("session.json")
+}
+
+pub fn drafts_path() -> PathBuf {
+    workspace::data_dir().join("drafts.json")
+}
+
+/// Collection runs, a file each.
+pub fn runs_dir() -> PathBuf {
+    workspace::data_dir().join("runs")
+}
+
+pub fn config_path() -> PathBuf {
+    config_dir().join("config.toml")
+}
+
+/// Settings read from `config.toml`. Every field has a default so a partial file is fine.
+#[derive(Serialize, Deserialize, Default, Clone, Debug)]
+#[serde(default)]
+pub struct Config {
+    pub request_log: RequestLogConfig,
+    /// Key binding overrides by action id, e.g. `resend_last = "ctrl+r"`.
+    pub keys: BTreeMap<String, String>,
+    /// Use the no-color theme even when `NO_COLOR` isn't set.
+    pub no_color: bool,
+    /// The color scheme, unless `no_color` or `NO_COLOR` turns color off.
+    pub theme: Preset,
+    /// Show the method, path and environment in the terminal's title. Off by default since
+    /// multiplexers may set the title too.
+    pub terminal_title: bool,
+    pub baseline: BaselineConfig,
+    pub rate_limit: RateLimitConfig,
+    /// For requests without a policy of their own.
+    pub redirects: redirect::Policy,
+    /// Soft-wrap response bodies instead of scrolling them sideways.
+    pub wrap_responses: bool,
+    pub history_grouping: HistoryGrouping,
+    /// Seconds to wait for a response before giving up on it, for requests without a timeout of
+    /// their own. No limit when unset.
+    pub timeout_secs: Option<u64>,
+    /// Seconds to wait for the connection alone, so an unreachable server fails fast while a
+    /// slow one still gets all of `timeout_secs`. No limit when unset.
+    pub connect_timeout_secs: Option<u64>,
+    /// Seconds to spend connecting in all when a host has several addresses and they're tried
+    /// one after another. No limit when unset.
+    pub connect_budget_secs: Option<u64>,
+    /// Seconds the server may go without sending or taking a byte once connected, so one that
+    /// stalls is given up on. 30 when unset.
+    pub read_timeout_secs: Option<u64>,
+    /// Attach an `X-Request-Id` with a fresh UUID to requests that don't set one, for requests
+    /// without a choice of their own.
+    pub request_id: bool,
+    pub test_server: TestServerConfig,
+    /// Headers and auth for requests to matching hosts, beneath the request's own headers.
+    pub hosts: Vec<HostRule>,
+    /// Header names masked like `Authorization`, on top of the built-in ones.
+    pub secret_headers: Vec<String>,
+    pub dns: DnsConfig,
+    pub responses: ResponsesConfig,
+    pub runner: RunnerConfig,
+}
+
+/// How the responses pane sorts history into collapsible sections.
+#[derive(Serialize, Deserialize, Clone, Copy, Default, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum HistoryGrouping {
+    /// Newest first, no sections.
+    #[default]
+    None,
+    Host,
+    /// By the saved request each exchange was sent from.
+    Saved,
+}
+
+impl HistoryGrouping {
+    pub fn name(self) -> &'static str {
+        match self {
+            HistoryGrouping::None => "none",
+            HistoryGrouping::Host => "host",
+            HistoryGrouping::Saved => "saved",
+        }
+    }
+
+    pub fn parse(input: &str) -> Result<Self, String> {
+        match input.trim() {
+            "none" | "" => Ok(HistoryGrouping::None),
+            "host" => Ok(HistoryGrouping::Host),
+            "saved" => Ok(HistoryGrouping::Saved),
+            other => Err(format!("Expected none, host or saved, not {}", other)),
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            HistoryGrouping::None => HistoryGrouping::Host,
+            HistoryGrouping::Host => HistoryGrouping::Saved,
+            HistoryGrouping::Saved => HistoryGrouping::None,
+        }
+    }
+}
+
+/// Ways the built-in test server misbehaves on purpose, to try out timeouts and retries.
+/// Requests can ask for the same with `delay_ms`, `fail`, `stall`, `stall_body` and `cut` query
+/// parameters.
+#[derive(Serialize, Deserialize, Clone, Copy, Default, PartialEq, Debug)]
+#[serde(default)]
+pub struct TestServerConfig {
+    /// Wait before answering each request.
+    pub delay_ms: u64,
+    /// Share of requests, 0 to 100, answered with an error instead.
+    pub fail_percent: u8,
+    /// Hold every request for minutes before answering, as a hung server would.
+    pub stall: bool,
+    /// Send the headers and half the body, then hold the rest back for minutes.
+    pub stall_body: bool,
+    /// Send the headers and half the body, then close the connection.
+    pub cut_body: bool,
+}
+
+/// How a scheduled retry of a rate-limited request carries on.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct RateLimitConfig {
+    /// Times a scheduled retry that's answered with 429 or 503 again is rescheduled without
+    /// asking, using the new `Retry-After`.
+    pub max_auto_retries: usize,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self { max_auto_retries: 3 }
+    }
+}
+
+/// The in-process cache of host name lookups.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct DnsConfig {
+    /// Seconds a looked-up address is reused; 0 looks every name up each time. Failed lookups
+    /// are retried after 5 seconds at most.
+    pub ttl_secs: u64,
+}
+
+impl Default for DnsConfig {
+    fn default() -> Self {
+        Self { ttl_secs: 60 }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct ResponsesConfig {
+    /// Largest body kept, in MB, for requests without a limit of their own; anything past it is
+    /// dropped. 0 keeps any size.
+    pub max_size_mb: u64,
+    /// What "open response with" runs on the body: a pager reading it on stdin, or with `{}` in
+    /// it, a command given the path of a file holding it. `$PAGER`, else `less -R`, when unset.
+    pub open_with: Option<String>,
+    /// Fold a request sent several times in a row into one history entry with a run count.
+    pub collapse_repeats: bool,
+}
+
+impl Default for ResponsesConfig {
+    fn default() -> Self {
+        Self { max_size_mb: 50, open_with: None, collapse_repeats: true }
+    }
+}
+
+/// When a collection run gives up on the rest of its requests.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+#[serde(default)]
+pub struct RunnerConfig {
+    /// After a response that a hook, the baseline or the response schema finds fault with.
+    pub stop_on_failure: bool,
+    /// After a request that got no response.
+    pub stop_on_error: bool,
+}
+
+impl Default for RunnerConfig {
+    fn default() -> Self {
+        Self { stop_on_failure: false, stop_on_error: true }
+    }
+}
+
+/// Differences that don't count when comparing a response with its saved baseline.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct BaselineConfig {
+    /// Header names, case-insensitive.
+    pub ignore_headers: Vec<String>,
+    /// JSON paths like `.timestamp` or `.items[*].id`; everything under a path is ignored too.
+    pub ignore_paths: Vec<String>,
+}
+
+impl Default for BaselineConfig {
+    fn default() -> Self {
+        Self {
+            ignore_headers: ["Date", "Age", "Expires", "Set-Cookie"].map(String::from).to_vec(),
+            ignore_paths: vec![],
+        }
+    }
+}
+
+/// An append-only JSON-lines record of every request sent, rotated by size.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct RequestLogConfig {
+    pub enabled: bool,
+    pub path: PathBuf,
+    pub max_megabytes: u64,
+    /// How many files to keep, counting the one being written.
+    pub keep_files: usize,
+}
+
+impl Default for RequestLogConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            path: config_dir().join("requests.log"),
+            max_megabytes: 10,
+            keep_files: 5,
+        }
+    }
+}
+
+/// Loads `config.toml`, treating a missing file as all defaults.
+pub fn load() -> Result<Config, String> {
+    let path = config_path();
+    match fs::read_to_string(&path) {
+        Ok(contents) => toml::from_str(&contents).map_err(|e| format!("{}: {}", path.display(), e)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Config::default()),
+        Err(e) => Err(format!("{}: {}", path.display(), e)),
+    }
+}
+
+/// Writes `config.toml` from scratch, so comments and layout of a hand-written file are lost.
+pub fn save(config: &Config) -> Result<(), String> {
+    let path = config_path();
+    let contents = toml::to_string_pretty(config).map_err(|e| e.to_string())?;
+    fs::create_dir_all(config_dir())
+        .and_then(|()| fs::write(&path, contents))
+        .map_err(|e| format!("{}: {}", path.display(), e))
+}
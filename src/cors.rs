@@ -0,0 +1,104 @@
+use crate::request::header_value;
+
+/// What a browser would ask in a preflight: the page's origin and the cross-origin request it
+/// wants to make.
+#[derive(Clone, Debug)]
+pub struct Preflight {
+    pub origin: String,
+    pub method: String,
+    pub headers: Vec<String>,
+}
+
+/// Methods a browser sends without asking for them in a preflight.
+const SAFELISTED_METHODS: [&str; 3] = ["GET", "HEAD", "POST"];
+
+impl Preflight {
+    /// Reads `origin method [header,header...]`, e.g. `https://app.example.com PUT Content-Type,Authorization`.
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let mut words = input.split_whitespace();
+        let (Some(origin), Some(method)) = (words.next(), words.next()) else {
+            return Err("expected an origin and a method, then optionally comma-separated headers".to_string());
+        };
+        let headers = words
+            .flat_map(|word| word.split(','))
+            .map(str::trim)
+            .filter(|header| !header.is_empty())
+            .map(str::to_string)
+            .collect();
+        Ok(Self { origin: origin.to_string(), method: method.to_ascii_uppercase(), headers })
+    }
+
+    /// The request headers of the OPTIONS request, one per line as the editor holds them.
+    pub fn request_headers(&self) -> String {
+        let mut headers = format!("Origin: {}\nAccess-Control-Request-Method: {}", self.origin, self.method);
+        if !self.headers.is_empty() {
+            headers.push_str(&format!("\nAccess-Control-Request-Headers: {}", self.headers.join(", ")));
+        }
+        headers
+    }
+}
+
+/// One line of the verdict: what the server said about something, and whether what was
+/// asked for is allowed, where that applies.
+pub struct Row {
+    pub label: String,
+    pub value: String,
+    pub allowed: Option<bool>,
+}
+
+/// Judges a preflight response the way a browser would. Fails when the response has no CORS
+/// headers at all.
+pub fn verdict(preflight: &Preflight, headers: &[(String, String)]) -> Result<Vec<Row>, String> {
+    if !headers.iter().any(|(name, _)| name.to_ascii_lowercase().starts_with("access-control-")) {
+        return Err("no CORS headers present".to_string());
+    }
+    let header = |name| header_value(headers, name).map(str::trim);
+    let list = |name| -> Vec<String> {
+        header(name).map_or(vec![], |value| value.split(',').map(|item| item.trim().to_string()).filter(|item| !item.is_empty()).collect())
+    };
+    let credentials = header("Access-Control-Allow-Credentials") == Some("true");
+    // With credentials, `*` is taken literally rather than as a wildcard.
+    let wildcard = |items: &[String]| !credentials && items.iter().any(|item| item == "*");
+
+    let mut rows = vec![];
+    let origin = header("Access-Control-Allow-Origin");
+    rows.push(Row {
+        label: "Allowed origin".to_string(),
+        value: origin.unwrap_or("(not set)").to_string(),
+        allowed: Some(origin == Some(preflight.origin.as_str()) || (origin == Some("*") && !credentials)),
+    });
+
+    let methods = list("Access-Control-Allow-Methods");
+    let method_allowed = SAFELISTED_METHODS.contains(&preflight.method.as_str())
+        || wildcard(&methods)
+        || methods.iter().any(|method| method.eq_ignore_ascii_case(&preflight.method));
+    rows.push(Row {
+        label: format!("Method {}", preflight.method),
+        value: header("Access-Control-Allow-Methods").unwrap_or("(not set)").to_string(),
+        allowed: Some(method_allowed),
+    });
+
+    let allowed_headers = list("Access-Control-Allow-Headers");
+    for requested in &preflight.headers {
+        // A wildcard never covers Authorization.
+        let allowed = (wildcard(&allowed_headers) && !requested.eq_ignore_ascii_case("Authorization"))
+            || allowed_headers.iter().any(|header| header.eq_ignore_ascii_case(requested));
+        rows.push(Row {
+            label: format!("Header {}", requested),
+            value: header("Access-Control-Allow-Headers").unwrap_or("(not set)").to_string(),
+            allowed: Some(allowed),
+        });
+    }
+
+    rows.push(Row {
+        label: "Max age".to_string(),
+        value: header("Access-Control-Max-Age").map_or("(not set, browsers cache for 5s)".to_string(), |age| format!("{}s", age)),
+        allowed: None,
+    });
+    rows.push(Row {
+        label: "Credentials".to_string(),
+        value: if credentials {"supported".to_string()} else {"not supported".to_string()},
+        allowed: None,
+    });
+    Ok(rows)
+}
@@ -0,0 +1,221 @@
+use crate::{
+    encoding,
+    request::{RequestSpec, ResolvedRequest},
+};
+
+/// Whether pasted text is a curl command rather than a URL.
+pub fn looks_like_curl(text: &str) -> bool {
+    let text = text.trim_start();
+    text.strip_prefix("curl").is_some_and(|rest| rest.starts_with(char::is_whitespace) || rest.starts_with('\\'))
+}
+
+/// Options that take no value and change nothing about the request itself.
+const IGNORED_FLAGS: [&str; 16] = [
+    "-s", "--silent", "-S", "--show-error", "-L", "--location", "-k", "--insecure", "-v", "--verbose",
+    "-i", "--include", "--compressed", "-f", "--fail", "-sS",
+];
+
+/// Builds a request from a curl command line, as a shell would split it: quotes, backslash
+/// escapes and line continuations are honoured.
+pub fn parse(command: &str) -> Result<RequestSpec, String> {
+    let words = split(command)?;
+    let mut words = words.into_iter().skip(1);
+    let mut method = None;
+    let mut url = None;
+    let mut headers: Vec<String> = vec![];
+    let mut body: Option<String> = None;
+    let mut connect_to = None;
+    let mut timeout_secs = None;
+    while let Some(word) = words.next() {
+        let mut value = |option: &str| words.next().ok_or_else(|| format!("{} needs a value", option));
+        match word.as_str() {
+            "-X" | "--request" => method = Some(value(&word)?.to_ascii_uppercase()),
+            "-H" | "--header" => headers.push(value(&word)?),
+            "-d" | "--data" | "--data-raw" | "--data-binary" | "--data-ascii" => {
+                let data = value(&word)?;
+                // Repeated data options are joined the way curl joins them.
+                body = Some(match body {
+                    Some(body) => format!("{}&{}", body, data),
+                    None => data,
+                });
+            }
+            "--json" => {
+                body = Some(value(&word)?);
+                headers.push("Content-Type: application/json".to_string());
+                headers.push("Accept: application/json".to_string());
+            }
+            "-u" | "--user" => {
+                let credentials = value(&word)?;
+                headers.push(format!("Authorization: Basic {}", encoding::base64_encode(credentials.as_bytes())));
+            }
+            "-I" | "--head" => method = Some("HEAD".to_string()),
+            "--url" => url = Some(value(&word)?),
+            "--connect-to" => {
+                // HOST1:PORT1:HOST2:PORT2, of which only the destination is kept.
+                let target = value(&word)?;
+                let parts: Vec<&str> = target.splitn(3, ':').collect();
+                connect_to = parts.get(2).map(|destination| destination.to_string());
+            }
+            "-m" | "--max-time" => {
+                let seconds = value(&word)?;
+                // curl takes fractions; timeouts here are whole seconds.
+                let seconds: f64 = seconds.parse().map_err(|_| format!("{:?} isn't a number of seconds", seconds))?;
+                timeout_secs = Some(seconds.ceil() as u64);
+            }
+            flag if IGNORED_FLAGS.contains(&flag) => {}
+            option if option.starts_with('-') && option.len() > 1 => return Err(format!("unsupported curl option {}", option)),
+            _ if url.is_none() => url = Some(word),
+            _ => return Err(format!("more than one URL ({})", word)),
+        }
+    }
+    let url = url.ok_or("the command has no URL")?;
+    let method = method.unwrap_or_else(|| if body.is_some() {"POST".to_string()} else {"GET".to_string()});
+    Ok(RequestSpec {
+        method,
+        url,
+        headers: headers.join("\n"),
+        body: body.unwrap_or_default(),
+        connect_to,
+        hooks: vec![],
+        after_response: vec![],
+        redirects: None,
+        timeout_secs,
+        max_retries: None,
+        request_id: None,
+        body_schema: None,
+        response_schema: None,
+        expect_continue: None,
+        max_response_mb: None,
+    })
+}
+
+/// `request` as a curl command sent to `scheme://`, an option per line, that `parse` reads back
+/// as the same request. curl works out `Host` and `Content-Length` itself, so they're left out.
+pub fn command(request: &ResolvedRequest, scheme: &str) -> String {
+    let method = request.method.to_ascii_uppercase();
+    let mut lines = vec![format!("curl {}", shell_quote(&format!("{}://{}{}", scheme, request.address, request.endpoint)))];
+    match (method.as_str(), request.body.is_empty()) {
+        ("GET", true) | ("POST", false) => {}
+        ("HEAD", true) => lines.push("-I".to_string()),
+        _ => lines.push(format!("-X {}", method)),
+    }
+    if let Some(target) = &request.connect_to {
+        lines.push(format!("--connect-to {}", shell_quote(&format!("::{}", target))));
+    }
+    for (name, value) in request.headers.iter().filter(|(name, _)| !["Host", "Content-Length"].iter().any(|own| name.eq_ignore_ascii_case(own))) {
+        lines.push(format!("-H {}", shell_quote(&format!("{}: {}", name, value))));
+    }
+    if !request.body.is_empty() {
+        lines.push(format!("--data-raw {}", shell_quote(&request.body)));
+    }
+    lines.join(" \\\n  ")
+}
+
+/// `text` in single quotes, where a shell takes everything literally but the quote itself.
+fn shell_quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', "'\\''"))
+}
+
+/// Splits a command into words like a POSIX shell, without expanding anything.
+fn split(command: &str) -> Result<Vec<String>, String> {
+    let mut words = vec![];
+    let mut word: Option<String> = None;
+    let mut chars = command.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                // A continuation joins the lines.
+                Some('\n') => {}
+                Some('\r') if chars.peek() == Some(&'\n') => {
+                    chars.next();
+                }
+                Some(escaped) => word.get_or_insert_with(String::new).push(escaped),
+                None => {}
+            },
+            '\'' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => word.push(c),
+                        None => return Err("unclosed single quote".to_string()),
+                    }
+                }
+            }
+            '"' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        // Inside double quotes a backslash only escapes these.
+                        Some('\\') if matches!(chars.peek(), Some('"' | '\\' | '$' | '`' | '\n')) => {
+                            let escaped = chars.next().unwrap_or_default();
+                            if escaped != '\n' {
+                                word.push(escaped);
+                            }
+                        }
+                        Some(c) => word.push(c),
+                        None => return Err("unclosed double quote".to_string()),
+                    }
+                }
+            }
+            c if c.is_whitespace() => words.extend(word.take()),
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn continuations_join_lines_and_quoted_json_stays_whole() {
+        let command = "curl -X PUT 'https://api.test/users/1?expand=true' \\\n  -H 'Content-Type: application/json' \\\r\n  -H \"Authorization: Bearer $TOKEN\" \\\n  --data-raw '{\n  \"name\": \"Ada \\\\ Lovelace\",\n  \"tags\": [\"a b\", \"it'\\''s\"]\n}'";
+        let spec = parse(command).unwrap();
+        assert_eq!((spec.method.as_str(), spec.url.as_str()), ("PUT", "https://api.test/users/1?expand=true"));
+        assert_eq!(spec.headers, "Content-Type: application/json\nAuthorization: Bearer $TOKEN");
+        assert_eq!(spec.body, "{\n  \"name\": \"Ada \\\\ Lovelace\",\n  \"tags\": [\"a b\", \"it's\"]\n}");
+        assert!(serde_json::from_str::<serde_json::Value>(&spec.body).is_ok());
+    }
+
+    #[test]
+    fn double_quoted_json_keeps_escaped_quotes() {
+        let spec = parse("curl https://api.test \\\n -d \"{\\\"id\\\": 1, \\\"note\\\": \\\"\\$5 off\\\"}\"").unwrap();
+        assert_eq!((spec.method.as_str(), spec.body.as_str()), ("POST", r#"{"id": 1, "note": "$5 off"}"#));
+        assert_eq!(parse("curl 'https://api.test").unwrap_err(), "unclosed single quote");
+    }
+
+    #[test]
+    fn exported_commands_import_as_the_same_request() {
+        let request = ResolvedRequest {
+            method: "PATCH".to_string(),
+            scheme: "https".to_string(),
+            address: "api.test".to_string(),
+            dial_address: "api.test:443".to_string(),
+            connect_to: Some("127.0.0.1:8443".to_string()),
+            endpoint: "/users/1?q=it's%20here&x=$(id)".to_string(),
+            headers: [("Host", "api.test"), ("Content-Type", "application/json"), ("X-Note", "say \"hi\" `id` \\ it's"), ("Content-Length", "44")]
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+            body: "{\n  \"name\": \"O'Brien\",\n  \"path\": \"C:\\\\tmp\"\n}".to_string(),
+            host_defaults: vec![],
+        };
+        let exported = command(&request, "https");
+        assert!(exported.lines().count() > 3, "one option per line:\n{}", exported);
+        let spec = parse(&exported).unwrap();
+        assert_eq!((spec.method.as_str(), spec.url.as_str()), ("PATCH", "https://api.test/users/1?q=it's%20here&x=$(id)"));
+        assert_eq!(spec.headers, "Content-Type: application/json\nX-Note: say \"hi\" `id` \\ it's");
+        assert_eq!(spec.body, request.body);
+        assert_eq!(spec.connect_to.as_deref(), Some("127.0.0.1:8443"));
+
+        let get = ResolvedRequest { method: "GET".to_string(), connect_to: None, headers: vec![], body: String::new(), ..request.clone() };
+        assert_eq!(command(&get, "http"), "curl 'http://api.test/users/1?q=it'\\''s%20here&x=$(id)'");
+        assert_eq!(parse(&command(&get, "http")).unwrap().method, "GET");
+        let head = ResolvedRequest { method: "head".to_string(), ..get };
+        assert_eq!(parse(&command(&head, "http")).unwrap().method, "HEAD");
+    }
+}
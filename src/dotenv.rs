@@ -0,0 +1,86 @@
+use std::{collections::HashMap, fmt, fs, path::{Path, PathBuf}};
+
+#[derive(Debug)]
+pub struct DotenvError {
+    pub path: PathBuf,
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for DotenvError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.line == 0 {
+            write!(f, "{}: {}", self.path.display(), self.message)
+        } else {
+            write!(f, "{}:{}: {}", self.path.display(), self.line, self.message)
+        }
+    }
+}
+
+pub fn load(path: &Path) -> Result<HashMap<String, String>, DotenvError> {
+    let contents = fs::read_to_string(path).map_err(|e| DotenvError {
+        path: path.to_path_buf(),
+        line: 0,
+        message: e.to_string(),
+    })?;
+    parse(&contents).map_err(|(line, message)| DotenvError { path: path.to_path_buf(), line, message })
+}
+
+/// Parses `KEY=value` lines, allowing `export` prefixes, `#` comments, and single or double
+/// quoted values. Errors carry the 1-based line number.
+pub fn parse(contents: &str) -> Result<HashMap<String, String>, (usize, String)> {
+    let mut variables = HashMap::new();
+    for (i, line) in contents.lines().enumerate() {
+        let line_number = i + 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").map(str::trim_start).unwrap_or(line);
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| (line_number, "expected KEY=value".to_string()))?;
+        let key = key.trim();
+        if !is_valid_key(key) {
+            return Err((line_number, format!("invalid variable name {:?}", key)));
+        }
+        let value = parse_value(value.trim()).map_err(|message| (line_number, message))?;
+        variables.insert(key.to_string(), value);
+    }
+    Ok(variables)
+}
+
+fn is_valid_key(key: &str) -> bool {
+    let mut chars = key.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+}
+
+fn parse_value(raw: &str) -> Result<String, String> {
+    if let Some(rest) = raw.strip_prefix('\'') {
+        let end = rest.find('\'').ok_or("unterminated single quote")?;
+        return Ok(rest[..end].to_string());
+    }
+    if let Some(rest) = raw.strip_prefix('"') {
+        let mut value = String::new();
+        let mut chars = rest.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' => return Ok(value),
+                '\\' => match chars.next() {
+                    Some('n') => value.push('\n'),
+                    Some('t') => value.push('\t'),
+                    Some(other) => value.push(other),
+                    None => break,
+                },
+                _ => value.push(c),
+            }
+        }
+        return Err("unterminated double quote".to_string());
+    }
+    let value = match raw.find(" #") {
+        Some(comment) => &raw[..comment],
+        None => raw,
+    };
+    Ok(value.trim().to_string())
+}
@@ -0,0 +1,74 @@
+/// Zero-based line and column, in characters, of the cursor `index` characters into `text`.
+pub fn position(text: &str, index: usize) -> (usize, usize) {
+    let mut line = 0;
+    let mut column = 0;
+    for c in text.chars().take(index) {
+        if c == '\n' {
+            line += 1;
+            column = 0;
+        } else {
+            column += 1;
+        }
+    }
+    (line, column)
+}
+
+/// Character index where zero-based `line` starts, or `None` if `text` is shorter.
+pub fn line_start(text: &str, line: usize) -> Option<usize> {
+    if line == 0 {
+        return Some(0);
+    }
+    text.chars()
+        .enumerate()
+        .filter(|(_, c)| *c == '\n')
+        .nth(line - 1)
+        .map(|(i, _)| i + 1)
+}
+
+/// Character indices of the start and end (before any newline) of the line holding `index`.
+pub fn line_bounds(text: &str, index: usize) -> (usize, usize) {
+    let chars: Vec<char> = text.chars().collect();
+    let index = index.min(chars.len());
+    let start = chars[..index].iter().rposition(|c| *c == '\n').map_or(0, |i| i + 1);
+    let end = chars[index..].iter().position(|c| *c == '\n').map_or(chars.len(), |i| index + i);
+    (start, end)
+}
+
+pub fn line_count(text: &str) -> usize {
+    text.split('\n').count()
+}
+
+/// Columns the line-number gutter takes: the widest number plus a separating space.
+pub fn gutter_width(line_count: usize) -> u16 {
+    line_count.to_string().len() as u16 + 1
+}
+
+/// Bodies longer than this, in bytes, aren't checked as they're typed.
+pub const CHECK_LIMIT: usize = 256 * 1024;
+
+/// Where and why a body fails to parse. Line and column are one-based, as servers report them.
+#[derive(Clone, PartialEq, Debug)]
+pub struct SyntaxError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+/// Whether the body is meant as JSON: the Content-Type says so or, without one, it opens like JSON.
+pub fn is_json(content_type: Option<&str>, body: &str) -> bool {
+    match content_type {
+        Some(content_type) => {
+            let mime = content_type.split(';').next().unwrap_or("").trim().to_ascii_lowercase();
+            mime.ends_with("/json") || mime.ends_with("+json")
+        }
+        None => body.trim_start().starts_with(['{', '[']),
+    }
+}
+
+pub fn check_json(body: &str) -> Option<SyntaxError> {
+    let e = serde_json::from_str::<serde_json::Value>(body).err()?;
+    let message = e.to_string();
+    // serde_json appends the location to the message; it's shown separately.
+    let message = message.rsplit_once(" at line ").map_or(message.as_str(), |(message, _)| message);
+    Some(SyntaxError { line: e.line(), column: e.column(), message: message.to_string() })
+}
@@ -0,0 +1,145 @@
+use std::fmt;
+
+const BASE64_ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+const BASE64_URL_ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+#[derive(Debug, PartialEq)]
+pub enum DecodeError {
+    UnexpectedCharacter(char),
+    BadLength,
+    BadEscape(String),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DecodeError::UnexpectedCharacter(c) => write!(f, "invalid character {:?}", c),
+            DecodeError::BadLength => write!(f, "invalid length"),
+            DecodeError::BadEscape(escape) => write!(f, "invalid percent escape {:?}", escape),
+        }
+    }
+}
+
+/// The text transforms offered in the editors. Each one maps the targeted text to raw bytes so
+/// the caller can decide what to do with output that isn't printable.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Transform {
+    Base64Encode,
+    Base64Decode,
+    UrlEncode,
+    UrlDecode,
+}
+
+impl Transform {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Transform::Base64Encode => "Base64 encode",
+            Transform::Base64Decode => "Base64 decode",
+            Transform::UrlEncode => "URL encode",
+            Transform::UrlDecode => "URL decode",
+        }
+    }
+
+    pub fn apply(&self, input: &str) -> Result<Vec<u8>, DecodeError> {
+        match self {
+            Transform::Base64Encode => Ok(base64_encode(input.as_bytes()).into_bytes()),
+            Transform::Base64Decode => base64_decode(input),
+            Transform::UrlEncode => Ok(percent_encode(input).into_bytes()),
+            Transform::UrlDecode => percent_decode(input),
+        }
+    }
+}
+
+pub fn base64_encode(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let buffer = chunk.iter().enumerate().fold(0u32, |acc, (i, &b)| acc | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(BASE64_ALPHABET[(buffer >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// Decodes standard base64, also accepting the URL-safe alphabet since both turn up in headers.
+pub fn base64_decode(input: &str) -> Result<Vec<u8>, DecodeError> {
+    let input = input.trim();
+    if input.contains(['-', '_']) {
+        decode_with(input, BASE64_URL_ALPHABET)
+    } else {
+        decode_with(input, BASE64_ALPHABET)
+    }
+}
+
+/// Decodes unpadded (or padded) base64url, as used by JWTs.
+pub fn base64_url_decode(input: &str) -> Result<Vec<u8>, DecodeError> {
+    decode_with(input, BASE64_URL_ALPHABET)
+}
+
+fn decode_with(input: &str, alphabet: &[u8; 64]) -> Result<Vec<u8>, DecodeError> {
+    let trimmed = input.trim_end_matches('=');
+    if trimmed.len() % 4 == 1 {
+        return Err(DecodeError::BadLength);
+    }
+
+    let mut out = Vec::with_capacity(trimmed.len() * 3 / 4);
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+    for c in trimmed.chars() {
+        let value = alphabet
+            .iter()
+            .position(|&a| a as char == c)
+            .ok_or(DecodeError::UnexpectedCharacter(c))?;
+        buffer = (buffer << 6) | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+    Ok(out)
+}
+
+fn is_unreserved(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b"-._~".contains(&b)
+}
+
+/// Percent-encodes everything outside RFC 3986's unreserved set.
+pub fn percent_encode(input: &str) -> String {
+    let mut out = String::with_capacity(input.len());
+    for b in input.bytes() {
+        if is_unreserved(b) {
+            out.push(b as char);
+        } else {
+            out.push_str(&format!("%{:02X}", b));
+        }
+    }
+    out
+}
+
+pub fn percent_decode(input: &str) -> Result<Vec<u8>, DecodeError> {
+    let bytes = input.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let escape = input.get(i..i + 3).unwrap_or(&input[i..]);
+            let value = escape
+                .get(1..)
+                .filter(|hex| hex.len() == 2)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                .ok_or_else(|| DecodeError::BadEscape(escape.to_string()))?;
+            out.push(value);
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    Ok(out)
+}
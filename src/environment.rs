@@ -0,0 +1,160 @@
+use std::{collections::{BTreeMap, BTreeSet, HashMap}, fs, io, path::{Path, PathBuf}};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{dotenv::{self, DotenvError}, vault::{self, Key, Vault}};
+
+/// A named set of variables for `{{name}}` placeholders, optionally seeded from a dotenv file.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Environment {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dotenv: Option<PathBuf>,
+    #[serde(default)]
+    pub variables: BTreeMap<String, String>,
+    /// Names of variables holding credentials, masked wherever they're shown and encrypted on
+    /// disk once a passphrase is given.
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub secrets: BTreeSet<String>,
+    /// Sealed values of secret variables, which are left out of `variables` on disk.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub encrypted: BTreeMap<String, String>,
+}
+
+/// The file as written once secrets are encrypted. A plain list of environments is read too.
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum EnvironmentsFile {
+    Vaulted { vault: Vault, environments: Vec<Environment> },
+    Plain(Vec<Environment>),
+}
+
+impl Environment {
+    /// Reads the dotenv file (if any) afresh and overlays the variables defined in the app,
+    /// which win on name collisions.
+    pub fn resolve_variables(&self) -> Result<HashMap<String, String>, DotenvError> {
+        let mut variables = match &self.dotenv {
+            Some(path) => dotenv::load(path)?,
+            None => HashMap::new(),
+        };
+        variables.extend(self.variables.iter().map(|(k, v)| (k.clone(), v.clone())));
+        Ok(variables)
+    }
+
+    /// Secret variables with a sealed value but none in memory, waiting on the passphrase.
+    pub fn locked(&self) -> impl Iterator<Item = &String> {
+        self.encrypted.keys().filter(|name| !self.variables.contains_key(*name))
+    }
+}
+
+/// Loads the environments file, treating a missing file as having none defined. Secret
+/// values stay sealed.
+pub fn load(path: &Path) -> Result<(Vec<Environment>, Option<Vault>), String> {
+    match fs::read_to_string(path) {
+        Ok(contents) => match serde_json::from_str(&contents) {
+            Ok(EnvironmentsFile::Vaulted { vault, environments }) => Ok((environments, Some(vault))),
+            Ok(EnvironmentsFile::Plain(environments)) => Ok((environments, None)),
+            Err(e) => Err(format!("{}: {}", path.display(), e)),
+        },
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok((vec![], None)),
+        Err(e) => Err(format!("{}: {}", path.display(), e)),
+    }
+}
+
+/// Writes the environments with secret variables only in sealed form, through a temporary file
+/// so a crash can't leave half of them behind. Secret values in memory are sealed afresh with
+/// `key`, which has to be `vault`'s; without it, a secret that isn't locked would be lost, so
+/// nothing is written.
+pub fn save(path: &Path, environments: &[Environment], vault: Option<&Vault>, key: Option<&Key>) -> Result<(), String> {
+    let mut sealed = Vec::with_capacity(environments.len());
+    for env in environments {
+        let mut env = env.clone();
+        let secret = |name: &String| env.secrets.contains(name) || env.encrypted.contains_key(name);
+        let plain: Vec<String> = env.variables.keys().filter(|name| secret(name)).cloned().collect();
+        for name in plain {
+            let Some(key) = key.filter(|_| vault.is_some()) else {
+                return Err(format!("{} in {} would be lost unencrypted; unlock the secrets or choose a passphrase first", name, env.name));
+            };
+            let value = env.variables.remove(&name).unwrap_or_default();
+            env.encrypted.insert(name, vault::seal(key, &value));
+        }
+        sealed.push(env);
+    }
+    let environments = sealed;
+    let file = match vault {
+        Some(vault) => EnvironmentsFile::Vaulted { vault: vault.clone(), environments },
+        None => EnvironmentsFile::Plain(environments),
+    };
+    let json = serde_json::to_string_pretty(&file).map_err(|e| e.to_string())?;
+    let temporary = path.with_extension("json.tmp");
+    fs::write(&temporary, json).and_then(|()| fs::rename(&temporary, path)).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A vault that derives its key in one round, so tests don't wait on PBKDF2.
+    fn quick_vault() -> Vault {
+        Vault { iterations: 1, ..Vault::generate() }
+    }
+
+    fn with_secret(value: Option<&str>, sealed: Option<String>) -> Environment {
+        Environment {
+            name: "dev".to_string(),
+            dotenv: None,
+            variables: value.map(|value| ("token".to_string(), value.to_string())).into_iter().collect(),
+            secrets: BTreeSet::from(["token".to_string()]),
+            encrypted: sealed.map(|sealed| ("token".to_string(), sealed)).into_iter().collect(),
+        }
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("tui_postman-{}-{}.json", name, std::process::id()))
+    }
+
+    #[test]
+    fn refuses_to_drop_a_secret_without_a_vault() {
+        let path = temp_path("no-vault");
+        let _ = fs::remove_file(&path);
+        let error = save(&path, &[with_secret(Some("hunter2"), None)], None, None).unwrap_err();
+        assert!(error.contains("token"), "{}", error);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn refuses_to_drop_a_secret_while_locked() {
+        let path = temp_path("locked");
+        fs::write(&path, "[]").unwrap();
+        let vault = quick_vault();
+        assert!(save(&path, &[with_secret(Some("hunter2"), None)], Some(&vault), None).is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "[]");
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn keeps_locked_secrets_sealed() {
+        let path = temp_path("still-locked");
+        let vault = quick_vault();
+        let key = vault.key("passphrase").unwrap();
+        let sealed = vault::seal(&key, "hunter2");
+        save(&path, &[with_secret(None, Some(sealed.clone()))], Some(&vault), None).unwrap();
+        let (environments, _) = load(&path).unwrap();
+        assert_eq!(environments[0].encrypted["token"], sealed);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn seals_secrets_with_the_key() {
+        let path = temp_path("unlocked");
+        let vault = quick_vault();
+        let key = vault.key("passphrase").unwrap();
+        save(&path, &[with_secret(Some("hunter2"), None)], Some(&vault), Some(&key)).unwrap();
+        assert!(!fs::read_to_string(&path).unwrap().contains("hunter2"));
+        let (environments, saved_vault) = load(&path).unwrap();
+        assert!(saved_vault.is_some());
+        assert!(environments[0].variables.is_empty());
+        assert_eq!(vault::open(&key, &environments[0].encrypted["token"]).unwrap(), "hunter2");
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,138 @@
+use chrono::DateTime;
+
+use crate::{
+    charset::{self, Encoding},
+    history::{HistoryEntry, ResponseRecord},
+    request::{header_value, parse_headers},
+    secrets, status,
+};
+
+/// Between the request and the response in a raw exchange file.
+const DELIMITER: &str = "==================== RESPONSE ====================";
+
+/// The request and response as they'd look on the wire, one after the other. Credential headers
+/// are redacted unless `include_secrets`.
+pub fn raw(entry: &HistoryEntry, include_secrets: bool) -> String {
+    let mut headers = parse_headers(&entry.request.headers);
+    if !include_secrets {
+        headers = secrets::redact_headers(&headers);
+    }
+    let mut out = message(&format!("{} {} HTTP/1.1", entry.request.method, entry.request.url), &headers, &entry.request.body);
+    out.push('\n');
+    out.push_str(DELIMITER);
+    out.push_str("\n\n");
+    match (&entry.response, &entry.error) {
+        (Some(response), _) => {
+            let status = match response.status {
+                Some(code) => format!("HTTP/1.1 {} {}", code, status::reason(code).unwrap_or(&response.reason)),
+                None => format!("HTTP/1.1 {}", response.reason),
+            };
+            let headers = if include_secrets {response.headers.clone()} else {secrets::redact_headers(&response.headers)};
+            out.push_str(&message(&status, &headers, &response.body));
+        }
+        (None, Some(error)) => out.push_str(&format!("No response: {}\n", error)),
+        (None, None) => out.push_str("No response yet.\n"),
+    }
+    out
+}
+
+/// The history entry itself, as kept in the history file, with credential headers redacted
+/// unless `include_secrets`.
+pub fn json(entry: &HistoryEntry, include_secrets: bool) -> Result<String, String> {
+    let mut entry = entry.clone();
+    if !include_secrets {
+        entry.request.headers = entry.request.headers
+            .lines()
+            .map(|line| match line.split_once(':') {
+                Some((name, value)) if secrets::is_secret_header(name.trim_start_matches('/').trim()) => {
+                    format!("{}: {}", name.trim_end(), secrets::mask(value.trim()))
+                }
+                _ => line.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        if let Some(response) = &mut entry.response {
+            response.headers = secrets::redact_headers(&response.headers);
+        }
+    }
+    serde_json::to_string_pretty(&entry).map_err(|e| e.to_string())
+}
+
+/// A file name like `get-users-42-20240501-093000.txt` from the method, the URL's path and
+/// when the request was sent.
+pub fn default_name(entry: &HistoryEntry) -> String {
+    let url = entry.request.url.trim();
+    let after_scheme = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let path = after_scheme.find('/').map_or("", |i| &after_scheme[i..]);
+    let path = path.split(['?', '#']).next().unwrap_or_default();
+    let mut name = entry.request.method.to_lowercase();
+    for segment in path.split('/').filter(|segment| !segment.is_empty()) {
+        let segment: String = segment
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' {c.to_ascii_lowercase()} else {'_'})
+            .collect();
+        name.push('-');
+        name.push_str(&segment);
+    }
+    // Leaves room for the timestamp on file systems with short name limits.
+    name.truncate(80);
+    if let Ok(sent) = DateTime::parse_from_rfc3339(&entry.timestamp) {
+        name.push_str(&sent.format("-%Y%m%d-%H%M%S").to_string());
+    }
+    name.push_str(".txt");
+    name
+}
+
+/// A response from a file: a raw HTTP response, the response half of a file written by `raw`,
+/// or just a body, which leaves the status and headers empty. CRLF line endings are fine.
+pub fn parse_response(text: &str) -> ResponseRecord {
+    let text = text.replace("\r\n", "\n");
+    let text = match text.split_once(&format!("\n{}\n", DELIMITER)) {
+        Some((_, response)) => response.trim_start_matches('\n'),
+        None => text.as_str(),
+    };
+    let Some(status_line) = text.lines().next().filter(|line| line.starts_with("HTTP/")) else {
+        return ResponseRecord { status: None, reason: String::new(), headers: vec![], body: text.to_string(), raw: None, truncated_from: None };
+    };
+    let mut parts = status_line.splitn(3, ' ').skip(1);
+    let status = parts.next().and_then(|code| code.parse().ok());
+    let reason = parts.next().unwrap_or_default().to_string();
+    let rest = &text[status_line.len()..];
+    let rest = rest.strip_prefix('\n').unwrap_or(rest);
+    let (head, body) = match rest.strip_prefix('\n') {
+        Some(body) => ("", body),
+        None => rest.split_once("\n\n").unwrap_or((rest, "")),
+    };
+    let headers = head.lines()
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+        .collect();
+    ResponseRecord { status, reason, headers, body: body.to_string(), raw: None, truncated_from: None }
+}
+
+/// A response file as `parse_response` reads it, decoded by the charset its headers or HTML
+/// declare. Anything but UTF-8 is decoded again once the headers have been read.
+pub fn read_response(bytes: &[u8]) -> ResponseRecord {
+    let response = parse_response(&charset::decode(bytes, None));
+    let declared = charset::declared(header_value(&response.headers, "Content-Type"), &response.body);
+    match declared.and_then(|declared| declared.encoding).filter(|encoding| *encoding != Encoding::Utf8) {
+        Some(encoding) => parse_response(&charset::decode(bytes, Some(encoding))),
+        None => response,
+    }
+}
+
+/// A start line, headers, then the body after a blank line.
+fn message(start_line: &str, headers: &[(String, String)], body: &str) -> String {
+    let mut text = start_line.to_string();
+    for (name, value) in headers {
+        text.push_str(&format!("\n{}: {}", name, value));
+    }
+    text.push_str("\n\n");
+    if !body.is_empty() {
+        text.push_str(body);
+        if !body.ends_with('\n') {
+            text.push('\n');
+        }
+    }
+    text
+}
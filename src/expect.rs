@@ -0,0 +1,67 @@
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::wire::{Connection, Head, WireError};
+
+/// How long to wait for `100 Continue` when `on` is all that's said.
+const DEFAULT_WAIT_MS: u64 = 1000;
+
+/// Sending `Expect: 100-continue` and holding the body back until the server agrees to it.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub struct ExpectContinue {
+    pub wait_ms: u64,
+    /// Send the body anyway after a 417 or no answer in time, since plenty of servers ignore
+    /// the header.
+    pub fallback: bool,
+}
+
+/// What the server said to the request's head.
+pub enum Answer {
+    Continue,
+    /// A final response before the body, meaning the server doesn't want it.
+    Final(Head),
+    /// Nothing within the wait.
+    Silent,
+}
+
+impl ExpectContinue {
+    /// Reads `on` or a wait like `500ms`, optionally followed by `strict` to give up rather than
+    /// send the body after a 417 or no answer.
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let mut words = input.split_whitespace();
+        let wait_ms = match words.next() {
+            Some("on") => DEFAULT_WAIT_MS,
+            Some(wait) => wait.trim_end_matches("ms").parse().map_err(|_| format!("{:?} isn't \"on\" or a wait in milliseconds", wait))?,
+            None => return Err("expected \"on\" or a wait in milliseconds".to_string()),
+        };
+        match words.next() {
+            None => Ok(Self { wait_ms, fallback: true }),
+            Some("strict") => Ok(Self { wait_ms, fallback: false }),
+            Some(word) => Err(format!("unexpected {:?}; only \"strict\" may follow the wait", word)),
+        }
+    }
+
+    /// The setting in the form `parse` reads.
+    pub fn describe(&self) -> String {
+        match self.fallback {
+            true => format!("{}ms", self.wait_ms),
+            false => format!("{}ms strict", self.wait_ms),
+        }
+    }
+}
+
+/// Waits up to `wait` for the server's answer to a head sent with `Expect: 100-continue` on
+/// `connection`, before its body.
+pub fn await_continue(connection: &mut Connection, wait: Duration) -> Result<Answer, WireError> {
+    let until = Instant::now() + wait;
+    loop {
+        match connection.read_head(Some(until))? {
+            None => return Ok(Answer::Silent),
+            Some(head) if head.status == 100 => return Ok(Answer::Continue),
+            // Other interim responses, like 103 Early Hints, don't answer the expectation.
+            Some(head) if (102..200).contains(&head.status) => {}
+            Some(head) => return Ok(Answer::Final(head)),
+        }
+    }
+}
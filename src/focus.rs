@@ -0,0 +1,35 @@
+/// Where keys land: a field of the request editor or one of the panes beside it. Exactly one
+/// has focus at a time.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Focus {
+    Method,
+    Url,
+    Headers,
+    Body,
+    Responses,
+    Collection,
+}
+
+impl Focus {
+    /// The order Tab moves through, and Shift+Tab back through.
+    pub const ORDER: [Focus; 6] = [Focus::Method, Focus::Url, Focus::Headers, Focus::Body, Focus::Responses, Focus::Collection];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Focus::Method => "method",
+            Focus::Url => "URL",
+            Focus::Headers => "headers",
+            Focus::Body => "body",
+            Focus::Responses => "responses",
+            Focus::Collection => "collection",
+        }
+    }
+
+    /// The next place in `ORDER`, or the one before, skipping the collection while it's hidden.
+    pub fn cycle(self, forward: bool, collection_shown: bool) -> Focus {
+        let order: Vec<Focus> = Focus::ORDER.into_iter().filter(|focus| collection_shown || *focus != Focus::Collection).collect();
+        let at = order.iter().position(|focus| *focus == self).unwrap_or_default();
+        let step = if forward {1} else {order.len() - 1};
+        order[(at + step) % order.len()]
+    }
+}
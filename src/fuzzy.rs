@@ -0,0 +1,40 @@
+/// Matches `pattern` against `text` as a case-insensitive subsequence, returning a score and the
+/// char positions that matched. Consecutive runs and matches at word starts score higher; gaps
+/// cost a little. An empty pattern matches everything with a score of zero.
+pub fn score(pattern: &str, text: &str) -> Option<(i64, Vec<usize>)> {
+    let pattern: Vec<char> = pattern.chars().filter(|c| !c.is_whitespace()).collect();
+    let text: Vec<char> = text.chars().collect();
+    let Some(first) = pattern.first() else { return Some((0, vec![])) };
+    // Matching greedily from every place the first char occurs finds "api" in
+    // "localhost/api" rather than settling for the scattered match starting at "lo(a)".
+    (0..text.len())
+        .filter(|&start| same(text[start], *first))
+        .filter_map(|start| score_from(&pattern, &text, start))
+        .max_by_key(|(score, _)| *score)
+}
+
+fn same(a: char, b: char) -> bool {
+    a.to_lowercase().eq(b.to_lowercase())
+}
+
+fn score_from(pattern: &[char], text: &[char], start: usize) -> Option<(i64, Vec<usize>)> {
+    let mut positions: Vec<usize> = vec![];
+    let mut score = -(start.min(10) as i64);
+    let mut next = start;
+    for &wanted in pattern {
+        let offset = text[next..].iter().position(|&c| same(c, wanted))?;
+        let at = next + offset;
+        score += 1;
+        if positions.last().is_some_and(|last| last + 1 == at) {
+            score += 5;
+        } else if at == 0 || !text[at - 1].is_alphanumeric() {
+            score += 8;
+        }
+        if !positions.is_empty() {
+            score -= offset.min(10) as i64;
+        }
+        positions.push(at);
+        next = at + 1;
+    }
+    Some((score, positions))
+}
@@ -0,0 +1,342 @@
+use std::{fs, io, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{redirect::Hop, request::{header_value, RequestSpec}, schema::SchemaCheck, status, template::Generated};
+
+/// Bumped whenever the history file changes shape; `load` upgrades older versions.
+pub const HISTORY_VERSION: u32 = 1;
+
+/// History entries kept on disk besides pinned ones, oldest dropped first.
+pub const HISTORY_LIMIT: usize = 500;
+
+/// Response headers that carry an id to find the request by in server logs.
+const CORRELATION_HEADERS: [&str; 3] = ["X-Request-Id", "X-Correlation-Id", "traceparent"];
+
+/// The correlation headers among `headers`, as sent.
+pub fn correlation_ids(headers: &[(String, String)]) -> Vec<(String, String)> {
+    headers.iter()
+        .filter(|(name, _)| CORRELATION_HEADERS.iter().any(|known| known.eq_ignore_ascii_case(name)))
+        .cloned()
+        .collect()
+}
+
+/// What came back, as it's kept and saved.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ResponseRecord {
+    pub status: Option<u16>,
+    /// The reason phrase from the status line, e.g. `Not Found`.
+    pub reason: String,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+    /// The body's bytes as they came, when decoding them by their charset gave anything but
+    /// their UTF-8 reading.
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "base64_bytes")]
+    pub raw: Option<Vec<u8>>,
+    /// How big the body was when reading it stopped at the size limit: its `Content-Length`, or
+    /// the bytes in by then.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub truncated_from: Option<usize>,
+}
+
+/// Raw bodies as base64 on disk rather than a list of numbers.
+mod base64_bytes {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    use crate::encoding::{base64_decode, base64_encode};
+
+    pub fn serialize<S: Serializer>(bytes: &Option<Vec<u8>>, serializer: S) -> Result<S::Ok, S::Error> {
+        match bytes {
+            Some(bytes) => serializer.serialize_str(&base64_encode(bytes)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Vec<u8>>, D::Error> {
+        Option::<String>::deserialize(deserializer)?.map(|text| base64_decode(&text).map_err(D::Error::custom)).transpose()
+    }
+}
+
+impl ResponseRecord {
+    /// Why an empty body is expected, for a response that by definition has none.
+    pub fn no_body_reason(&self, method: &str) -> Option<String> {
+        if !self.body.is_empty() {
+            return None;
+        }
+        if method.eq_ignore_ascii_case("HEAD") {
+            return Some("HEAD request".to_string());
+        }
+        match self.status {
+            Some(code @ (204 | 304)) => Some(format!("{} {}", code, status::reason(code).unwrap_or_default())),
+            _ if header_value(&self.headers, "Content-Length").is_some_and(|length| length.trim() == "0") => {
+                Some("Content-Length: 0".to_string())
+            }
+            _ => None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Default, Debug)]
+pub struct Timing {
+    pub latency_ms: u64,
+    /// Resolving the host, summed over any redirects.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dns_ms: Option<u64>,
+    /// The addresses came from the DNS cache; on a redirect, for the last hop.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub dns_cached: bool,
+    /// From connecting to the last byte of the response, summed over any redirects.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exchange_ms: Option<u64>,
+    /// Opening the connection, summed over any redirects.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub connect_ms: Option<u64>,
+    /// From the request starting to go out, body included, to the response's head being in;
+    /// for the last hop.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ttfb_ms: Option<u64>,
+    /// Reading the response body, for the last hop.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub download_ms: Option<u64>,
+    /// Request and response sizes as they'd be on the wire, start line and headers included,
+    /// summed over any redirects.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sent_bytes: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub received_bytes: Option<u64>,
+}
+
+/// One exchange: the request as written, and whatever came of it once the worker reports back.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct HistoryEntry {
+    pub id: u64,
+    /// RFC 3339, when the request was sent.
+    pub timestamp: String,
+    pub request: RequestSpec,
+    /// Dynamic values the request was sent with.
+    #[serde(default)]
+    pub generated: Vec<Generated>,
+    /// `host[:port]` the request was addressed to.
+    pub host: String,
+    /// The address that actually answered, which differs from `host` under a connect-to override.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub peer: Option<String>,
+    #[serde(default)]
+    pub overridden: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response: Option<ResponseRecord>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timing: Option<Timing>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Folder and name of the saved request this was sent from.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub saved: Option<(Vec<String>, String)>,
+    /// Redirects followed to get the response, in order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub redirects: Vec<Hop>,
+    /// Why the response, a redirect itself, wasn't followed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub redirect_stopped: Option<String>,
+    /// The `X-Request-Id` the request went out with.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    /// Correlation headers of the response, like `X-Request-Id` and `traceparent`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub correlation: Vec<(String, String)>,
+    /// Name of the environment the request was resolved in.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub environment: Option<String>,
+    /// Values of the variables the request used, credentials redacted.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub variables: Vec<(String, String)>,
+    /// How an automatic token refresh after a 401 went, on the rejected request and its retry.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_refresh: Option<String>,
+    /// How the response held up against the request's response schema.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema_check: Option<SchemaCheck>,
+    /// Kept at the top of the responses pane, and through trimming and clearing.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub pinned: bool,
+    /// Earlier attempts at this exchange that failed before it was retried: when each was sent
+    /// and why it failed.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub failed_attempts: Vec<(String, String)>,
+    /// Runs of the same request sent just before this one, newest first, folded into it.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub repeats: Vec<Repeat>,
+}
+
+/// How an earlier run of a repeated request went, kept on the entry of the latest run.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Repeat {
+    pub id: u64,
+    pub timestamp: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    /// The reason phrase, for a response without a status code.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub reason: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<u64>,
+}
+
+impl HistoryEntry {
+    pub fn is_pending(&self) -> bool {
+        self.response.is_none() && self.error.is_none()
+    }
+
+    /// Times the request was sent in a row, this run included.
+    pub fn runs(&self) -> usize {
+        self.repeats.len() + 1
+    }
+
+    /// Same method, URL, headers and body, resolved in the same environment.
+    fn repeats_request_of(&self, other: &HistoryEntry) -> bool {
+        self.request.method == other.request.method
+            && self.request.url == other.request.url
+            && self.request.headers == other.request.headers
+            && self.request.body == other.request.body
+            && self.environment == other.environment
+    }
+
+    fn as_repeat(&self) -> Repeat {
+        Repeat {
+            id: self.id,
+            timestamp: self.timestamp.clone(),
+            status: self.response.as_ref().and_then(|response| response.status),
+            reason: self.response.as_ref().map(|response| response.reason.clone()).unwrap_or_default(),
+            error: self.error.clone(),
+            latency_ms: self.timing.map(|timing| timing.latency_ms),
+        }
+    }
+}
+
+/// Folds the entry sent just before `id` into it when both are settled runs of the same
+/// request and neither is pinned, returning the id of the entry folded away.
+pub fn fold_repeat(entries: &mut Vec<HistoryEntry>, id: u64) -> Option<u64> {
+    let index = entries.iter().position(|entry| entry.id == id).filter(|index| *index > 0)?;
+    let (earlier, entry) = (&entries[index - 1], &entries[index]);
+    let foldable = !earlier.pinned && !entry.pinned && !earlier.is_pending() && !entry.is_pending() && entry.repeats_request_of(earlier);
+    if !foldable {
+        return None;
+    }
+    let earlier = entries.remove(index - 1);
+    let entry = &mut entries[index - 1];
+    entry.repeats = std::iter::once(earlier.as_repeat()).chain(earlier.repeats).collect();
+    Some(earlier.id)
+}
+
+#[derive(Serialize, Deserialize)]
+struct HistoryFile {
+    version: u32,
+    entries: Vec<HistoryEntry>,
+}
+
+/// Loads the history file, treating a missing file as no history.
+pub fn load(path: &Path) -> Result<Vec<HistoryEntry>, String> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(format!("{}: {}", path.display(), e)),
+    };
+    let file: HistoryFile = serde_json::from_str(&contents).map_err(|e| format!("{}: {}", path.display(), e))?;
+    if file.version > HISTORY_VERSION {
+        return Err(format!("{} was written by a newer version (format {})", path.display(), file.version));
+    }
+    Ok(file.entries)
+}
+
+pub fn save(path: &Path, entries: &[HistoryEntry]) -> Result<(), String> {
+    let mut unpinned = entries.iter().filter(|entry| !entry.pinned).count();
+    let file = HistoryFile {
+        version: HISTORY_VERSION,
+        entries: entries
+            .iter()
+            .filter(|entry| {
+                let keep = entry.pinned || unpinned <= HISTORY_LIMIT;
+                if !entry.pinned {
+                    unpinned -= 1;
+                }
+                keep
+            })
+            .cloned()
+            .collect(),
+    };
+    let write = || -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_string(&file)?)
+    };
+    write().map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(id: u64, url: &str, status: Option<u16>) -> HistoryEntry {
+        let json = serde_json::json!({
+            "id": id,
+            "timestamp": format!("2026-01-01T00:00:0{}Z", id),
+            "request": {"method": "GET", "url": url, "headers": "Accept: */*", "body": ""},
+            "host": "api.test",
+            "timing": {"latency_ms": 10 * id},
+        });
+        let mut entry: HistoryEntry = serde_json::from_value(json).unwrap();
+        match status {
+            Some(status) => entry.response = Some(ResponseRecord { status: Some(status), reason: String::new(), headers: vec![], body: String::new(), raw: None, truncated_from: None }),
+            None => entry.error = Some("connection refused".to_string()),
+        }
+        entry
+    }
+
+    #[test]
+    fn runs_of_the_same_request_fold_into_the_latest() {
+        let mut entries = vec![entry(1, "http://api.test/other", Some(200)), entry(2, "http://api.test/a", Some(200))];
+        for (id, status) in [(3, None), (4, Some(503))] {
+            entries.push(entry(id, "http://api.test/a", status));
+            assert_eq!(fold_repeat(&mut entries, id), Some(id - 1));
+        }
+        assert_eq!(entries.iter().map(|entry| entry.id).collect::<Vec<_>>(), [1, 4]);
+        let folded: Vec<(u64, Option<u16>, bool)> = entries[1].repeats.iter().map(|repeat| (repeat.id, repeat.status, repeat.error.is_some())).collect();
+        assert_eq!(folded, [(3, None, true), (2, Some(200), false)]);
+        assert_eq!((entries[1].runs(), entries[1].repeats[1].latency_ms), (3, Some(20)));
+        assert_eq!(fold_repeat(&mut entries, 1), None, "nothing before the first");
+    }
+
+    #[test]
+    fn different_requests_pending_and_pinned_entries_stay_apart() {
+        let mut entries = vec![entry(1, "http://api.test/a", Some(200)), entry(2, "http://api.test/a?page=2", Some(200))];
+        assert_eq!(fold_repeat(&mut entries, 2), None);
+        entries.push(entry(3, "http://api.test/a?page=2", Some(200)));
+        entries[2].request.body = "{}".to_string();
+        assert_eq!(fold_repeat(&mut entries, 3), None, "the body is part of the request");
+        entries[2].request.body.clear();
+        entries[2].environment = Some("staging".to_string());
+        assert_eq!(fold_repeat(&mut entries, 3), None, "so is the environment");
+        entries[2].environment = None;
+        entries[1].pinned = true;
+        assert_eq!(fold_repeat(&mut entries, 3), None);
+        entries[1].pinned = false;
+        entries[1].response = None;
+        entries[1].error = None;
+        assert_eq!(fold_repeat(&mut entries, 3), None, "still waiting on a response");
+    }
+
+    #[test]
+    fn repeats_are_saved_and_loaded() {
+        let mut entries = vec![entry(1, "http://api.test/a", Some(200)), entry(2, "http://api.test/a", Some(201))];
+        fold_repeat(&mut entries, 2);
+        let path = std::env::temp_dir().join(format!("tui_postman-history-{}.json", std::process::id()));
+        save(&path, &entries).unwrap();
+        let loaded = load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.len(), 1);
+        assert_eq!((loaded[0].repeats[0].id, loaded[0].repeats[0].status), (1, Some(200)));
+    }
+}
@@ -0,0 +1,264 @@
+use chrono::{SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    digest,
+    encoding::base64_encode,
+    request::ResolvedRequest,
+    template::Resolver,
+};
+
+/// A header computed just before a saved request is sent, for values templates can't produce.
+/// Hooks live in the request's `hooks` list in the collection file, e.g.
+/// `{"type": "hmac_sha256", "header": "X-Signature", "secret": "{{api_secret}}"}`.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Hook {
+    /// HMAC-SHA256 of the body keyed with `secret`, which may use placeholders.
+    HmacSha256 {
+        header: String,
+        secret: String,
+        #[serde(default)]
+        encoding: DigestEncoding,
+        /// Put before the digest, e.g. `sha256=`.
+        #[serde(default)]
+        prefix: String,
+    },
+    /// SHA-256 of the body.
+    ContentHash {
+        header: String,
+        #[serde(default)]
+        encoding: DigestEncoding,
+        #[serde(default)]
+        prefix: String,
+    },
+    Timestamp {
+        header: String,
+        #[serde(default)]
+        format: TimestampFormat,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Default, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum DigestEncoding {
+    #[default]
+    Hex,
+    Base64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Default, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum TimestampFormat {
+    #[default]
+    UnixSeconds,
+    UnixMillis,
+    Rfc3339,
+}
+
+impl Hook {
+    pub fn header(&self) -> &str {
+        match self {
+            Hook::HmacSha256 { header, .. } | Hook::ContentHash { header, .. } | Hook::Timestamp { header, .. } => header,
+        }
+    }
+
+    /// Sets the hook's header on `request`, replacing any value it already had.
+    pub fn apply(&self, request: &mut ResolvedRequest, resolver: &mut Resolver) -> Result<(), String> {
+        let value = match self {
+            Hook::HmacSha256 { secret, encoding, prefix, .. } => {
+                let secret = resolver.resolve(secret).map_err(|e| e.to_string())?;
+                if secret.is_empty() {
+                    return Err("the secret is empty".to_string());
+                }
+                if secret.contains("{{") {
+                    return Err(format!("the secret {} isn't defined in the active environment", secret));
+                }
+                format!("{}{}", prefix, encode(&digest::hmac_sha256(secret.as_bytes(), request.body.as_bytes()), *encoding))
+            }
+            Hook::ContentHash { encoding, prefix, .. } => {
+                format!("{}{}", prefix, encode(&digest::sha256(request.body.as_bytes()), *encoding))
+            }
+            Hook::Timestamp { format, .. } => {
+                let now = Utc::now();
+                match format {
+                    TimestampFormat::UnixSeconds => now.timestamp().to_string(),
+                    TimestampFormat::UnixMillis => now.timestamp_millis().to_string(),
+                    TimestampFormat::Rfc3339 => now.to_rfc3339_opts(SecondsFormat::Secs, true),
+                }
+            }
+        };
+        let header = self.header();
+        if header.trim().is_empty() || header.contains(':') {
+            return Err(format!("{:?} isn't a valid header name", header));
+        }
+        match request.headers.iter_mut().find(|(name, _)| name.eq_ignore_ascii_case(header)) {
+            Some((_, existing)) => *existing = value,
+            None => request.headers.push((header.to_string(), value)),
+        }
+        Ok(())
+    }
+}
+
+fn encode(digest: &[u8], encoding: DigestEncoding) -> String {
+    match encoding {
+        DigestEncoding::Hex => digest::hex(digest),
+        DigestEncoding::Base64 => base64_encode(digest),
+    }
+}
+
+/// Something done after a saved request gets a response, listed in the request's
+/// `after_response` in the collection file, e.g. `{"type": "set_variable", "name": "token",
+/// "json_path": ".data.token"}`.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PostHook {
+    SaveBody { path: String },
+    /// Sets a variable of the session from the response's JSON body.
+    SetVariable { name: String, json_path: String },
+    /// Sets a variable of the session from a response header, e.g. `Location` after a 201.
+    /// The name is matched case-insensitively.
+    SetVariableFromHeader {
+        name: String,
+        header: String,
+        #[serde(default)]
+        occurrence: Occurrence,
+    },
+    /// Sends the saved request called `request`, only for the given status if there is one and
+    /// only if every condition in `when` holds.
+    Trigger {
+        request: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        if_status: Option<u16>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        when: Vec<Condition>,
+    },
+}
+
+/// Something a response must satisfy for a trigger to fire, e.g.
+/// `{"check": "json_equals", "json_path": ".state", "value": "ready"}`.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "check", rename_all = "snake_case")]
+pub enum Condition {
+    /// The status is from `from` to `to`, both included.
+    StatusIn { from: u16, to: u16 },
+    JsonEquals { json_path: String, value: Value },
+    HeaderExists { header: String },
+}
+
+impl Condition {
+    /// Why the response doesn't satisfy the condition, or `None` if it does.
+    pub fn unmet(&self, status: Option<u16>, headers: &[(String, String)], body: &str) -> Option<String> {
+        match self {
+            Condition::StatusIn { from, to } => match status {
+                Some(status) if (*from..=*to).contains(&status) => None,
+                Some(status) => Some(format!("status {} isn't {} to {}", status, from, to)),
+                None => Some(format!("no status to compare with {} to {}", from, to)),
+            },
+            Condition::JsonEquals { json_path: path, value } => {
+                let body = match serde_json::from_str::<Value>(body) {
+                    Ok(body) => body,
+                    Err(e) => return Some(format!("body isn't JSON: {}", e)),
+                };
+                match json_path(&body, path) {
+                    Ok(found) if found == value => None,
+                    Ok(found) => Some(format!("{} is {}, not {}", path, found, value)),
+                    Err(e) => Some(e),
+                }
+            }
+            Condition::HeaderExists { header } => {
+                match headers.iter().any(|(name, _)| name.eq_ignore_ascii_case(header)) {
+                    true => None,
+                    false => Some(format!("no {} header", header)),
+                }
+            }
+        }
+    }
+}
+
+/// Which value to take from a header sent more than once.
+#[derive(Serialize, Deserialize, Clone, Copy, Default, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Occurrence {
+    #[default]
+    First,
+    Last,
+    /// Every value, joined with `, ` as a list header would be.
+    Joined,
+}
+
+impl Occurrence {
+    /// The value to use from `headers`, or `None` if the header isn't there.
+    pub fn pick(self, headers: &[(String, String)], name: &str) -> Option<String> {
+        let mut values = headers.iter().filter(|(header, _)| header.eq_ignore_ascii_case(name)).map(|(_, value)| value.as_str());
+        match self {
+            Occurrence::First => values.next().map(str::to_string),
+            Occurrence::Last => values.next_back().map(str::to_string),
+            Occurrence::Joined => {
+                let values: Vec<&str> = values.collect();
+                (!values.is_empty()).then(|| values.join(", "))
+            }
+        }
+    }
+}
+
+/// How one post-response hook went, shown under the response.
+#[derive(Clone, Debug)]
+pub struct HookOutcome {
+    pub ok: bool,
+    pub message: String,
+}
+
+impl HookOutcome {
+    pub fn ok(message: String) -> Self {
+        Self { ok: true, message }
+    }
+
+    pub fn failed(message: String) -> Self {
+        Self { ok: false, message }
+    }
+}
+
+/// The value at a path like `.data.items[0].id` (a leading `$` is allowed). Keys that aren't
+/// plain words go in brackets as JSON strings, like `.headers["content-type"]`; `.` alone is
+/// the whole document.
+pub fn json_path<'a>(value: &'a Value, path: &str) -> Result<&'a Value, String> {
+    let mut current = value;
+    let mut rest = path.trim().trim_start_matches('$');
+    if rest == "." {
+        return Ok(value);
+    }
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix("[\"") {
+            let (key, len) = quoted_key(after).ok_or_else(|| format!("unclosed quoted key in {}", path))?;
+            current = current.get(&key).ok_or_else(|| format!("no field {:?} in {}", key, path))?;
+            rest = after[len..].strip_prefix(']').ok_or_else(|| format!("expected ] after {:?} in {}", key, path))?;
+        } else if let Some(after) = rest.strip_prefix('[') {
+            let close = after.find(']').ok_or_else(|| format!("unclosed [ in {}", path))?;
+            let index: usize = after[..close].trim().parse().map_err(|_| format!("{:?} isn't an array index", &after[..close]))?;
+            current = current.get(index).ok_or_else(|| format!("no item {} in {}", index, path))?;
+            rest = &after[close + 1..];
+        } else {
+            let after = rest.strip_prefix('.').unwrap_or(rest);
+            let end = after.find(['.', '[']).unwrap_or(after.len());
+            let key = &after[..end];
+            current = current.get(key).ok_or_else(|| format!("no field {:?} in {}", key, path))?;
+            rest = &after[end..];
+        }
+    }
+    Ok(current)
+}
+
+/// The key of a quoted bracket, given the text after its opening quote, and how many bytes
+/// that took including the closing quote.
+fn quoted_key(after_quote: &str) -> Option<(String, usize)> {
+    let mut escaped = false;
+    let end = after_quote.char_indices().find_map(|(i, c)| {
+        let closes = c == '"' && !escaped;
+        escaped = c == '\\' && !escaped;
+        closes.then_some(i)
+    })?;
+    let key = serde_json::from_str(&format!("\"{}\"", &after_quote[..end])).ok()?;
+    Some((key, end + 1))
+}
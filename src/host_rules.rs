@@ -0,0 +1,236 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    encoding,
+    request::header_value,
+    template::{Resolver, TemplateError},
+};
+
+/// Headers and auth added to requests for matching hosts. `pattern` is a host name, `*.example.com`
+/// for any subdomain of `example.com`, or `*` for every host. Values may use `{{...}}` placeholders.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct HostRule {
+    pub pattern: String,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth: Option<HostAuth>,
+}
+
+/// Sent as an `Authorization` header.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum HostAuth {
+    Bearer {
+        token: String,
+        /// Gets a new token when a request is answered with 401, then sends it again once.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        refresh: Option<TokenRefresh>,
+    },
+    Basic { username: String, password: String },
+}
+
+/// Where a new bearer token comes from, e.g. `{ request = "login", json_path = ".access_token",
+/// variable = "token" }` with the rule's token written as `{{token}}`.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TokenRefresh {
+    #[serde(flatten)]
+    pub source: TokenSource,
+    /// Where the token is in the JSON response.
+    pub json_path: String,
+    /// Set to the new token.
+    pub variable: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(untagged)]
+pub enum TokenSource {
+    /// The saved request with this name.
+    SavedRequest { request: String },
+    /// A form POSTed to a token endpoint, like `grant_type=client_credentials&client_id={{id}}`.
+    Endpoint {
+        url: String,
+        #[serde(default)]
+        body: String,
+    },
+}
+
+/// The headers one rule added to a request.
+#[derive(Clone, Debug)]
+pub struct Contribution {
+    /// Index of the rule in the list it was applied from.
+    pub rule: usize,
+    pub pattern: String,
+    pub headers: Vec<String>,
+}
+
+/// How to refresh the token of a request whose `Authorization` came from a rule that can.
+pub fn refresh_for<'a>(rules: &'a [HostRule], contributions: &[Contribution]) -> Option<&'a TokenRefresh> {
+    let contribution = contributions.iter().find(|contribution| contribution.headers.iter().any(|name| name == "Authorization"))?;
+    match &rules.get(contribution.rule)?.auth {
+        Some(HostAuth::Bearer { refresh, .. }) => refresh.as_ref(),
+        _ => None,
+    }
+}
+
+/// How closely `pattern` matches `host`, bigger being closer: exact names beat wildcards, which
+/// beat `*`, and longer wildcards beat shorter ones. `None` when it doesn't match.
+pub fn specificity(pattern: &str, host: &str) -> Option<(u8, usize)> {
+    let pattern = pattern.trim().to_ascii_lowercase();
+    let host = host.to_ascii_lowercase();
+    if pattern == "*" {
+        return Some((0, 0));
+    }
+    match pattern.strip_prefix("*.") {
+        Some(domain) => host
+            .strip_suffix(domain)
+            .is_some_and(|subdomain| subdomain.len() > 1 && subdomain.ends_with('.'))
+            .then_some((1, domain.len())),
+        None => (pattern == host).then_some((2, pattern.len())),
+    }
+}
+
+/// Adds the headers of every rule matching `host` that `headers` doesn't have yet, closest
+/// rule first, so the request's own values win over all of them.
+pub fn apply(
+    rules: &[HostRule],
+    host: &str,
+    headers: &mut Vec<(String, String)>,
+    resolver: &mut Resolver,
+) -> Result<Vec<Contribution>, TemplateError> {
+    let mut matching: Vec<(_, usize, &HostRule)> = rules
+        .iter()
+        .enumerate()
+        .filter_map(|(index, rule)| Some((specificity(&rule.pattern, host)?, index, rule)))
+        .collect();
+    // Stable, so among equally close rules the one listed first wins.
+    matching.sort_by(|(a, _, _), (b, _, _)| b.cmp(a));
+    let mut contributions = vec![];
+    for (_, index, rule) in matching {
+        let mut added = vec![];
+        for (name, value) in &rule.headers {
+            if header_value(headers, name).is_none() {
+                headers.push((name.clone(), resolver.resolve(value)?));
+                added.push(name.clone());
+            }
+        }
+        if let Some(auth) = &rule.auth {
+            if header_value(headers, "Authorization").is_none() {
+                let value = match auth {
+                    HostAuth::Bearer { token, .. } => format!("Bearer {}", resolver.resolve(token)?),
+                    HostAuth::Basic { username, password } => {
+                        let credentials = format!("{}:{}", resolver.resolve(username)?, resolver.resolve(password)?);
+                        format!("Basic {}", encoding::base64_encode(credentials.as_bytes()))
+                    }
+                };
+                headers.push(("Authorization".to_string(), value));
+                added.push("Authorization".to_string());
+            }
+        }
+        if !added.is_empty() {
+            contributions.push(Contribution { rule: index, pattern: rule.pattern.clone(), headers: added });
+        }
+    }
+    Ok(contributions)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    fn rule(pattern: &str, headers: &[(&str, &str)], auth: Option<HostAuth>) -> HostRule {
+        let headers = headers.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect();
+        HostRule { pattern: pattern.to_string(), headers, auth }
+    }
+
+    fn bearer(token: &str) -> Option<HostAuth> {
+        Some(HostAuth::Bearer { token: token.to_string(), refresh: None })
+    }
+
+    #[test]
+    fn exact_beats_wildcard_beats_global() {
+        let host = "api.Example.com";
+        let exact = specificity("API.example.com ", host).unwrap();
+        let wildcard = specificity("*.example.com", host).unwrap();
+        let global = specificity("*", host).unwrap();
+        assert!(exact > wildcard && wildcard > global);
+        assert!(specificity("*.api.example.com", "v1.api.example.com") > specificity("*.example.com", "v1.api.example.com"));
+        assert_eq!(specificity("*.example.com", "example.com"), None);
+        assert_eq!(specificity("*.example.com", "badexample.com"), None);
+        assert_eq!(specificity("example.com", "api.example.com"), None);
+    }
+
+    #[test]
+    fn closest_rule_wins_each_header_and_the_request_wins_over_all() {
+        let rules = [
+            rule("*", &[("X-Env", "global"), ("X-Global", "1")], bearer("global")),
+            rule("*.example.com", &[("X-Env", "wildcard"), ("X-Team", "core")], None),
+            rule("api.example.com", &[("X-Env", "exact")], None),
+            rule("other.test", &[("X-Other", "1")], None),
+        ];
+        let mut headers = vec![("x-team".to_string(), "mine".to_string())];
+        let variables = HashMap::new();
+        let contributions = apply(&rules, "api.example.com", &mut headers, &mut Resolver::new(&variables, None)).unwrap();
+        let sent: Vec<(&str, &str)> = headers.iter().map(|(name, value)| (name.as_str(), value.as_str())).collect();
+        assert_eq!(sent, [("x-team", "mine"), ("X-Env", "exact"), ("X-Global", "1"), ("Authorization", "Bearer global")]);
+        let by_rule: Vec<(usize, &str, Vec<&str>)> = contributions
+            .iter()
+            .map(|contribution| (contribution.rule, contribution.pattern.as_str(), contribution.headers.iter().map(String::as_str).collect()))
+            .collect();
+        assert_eq!(by_rule, [(2, "api.example.com", vec!["X-Env"]), (0, "*", vec!["X-Global", "Authorization"])]);
+    }
+
+    #[test]
+    fn equally_close_rules_go_in_listed_order() {
+        let rules = [rule("*.example.com", &[("X-Env", "first")], None), rule("*.example.com", &[("X-Env", "second")], None)];
+        let mut headers = vec![];
+        let variables = HashMap::new();
+        apply(&rules, "a.example.com", &mut headers, &mut Resolver::new(&variables, None)).unwrap();
+        assert_eq!(headers, [("X-Env".to_string(), "first".to_string())]);
+    }
+
+    #[test]
+    fn placeholders_are_filled_in() {
+        let rules = [
+            rule("api.test", &[("X-Tenant", "{{tenant}}-{{region}}")], Some(HostAuth::Basic { username: "{{user}}".to_string(), password: "p:w".to_string() })),
+            rule("*", &[], bearer("{{token}}")),
+        ];
+        let variables = HashMap::from([("tenant", "acme"), ("region", "eu"), ("user", "me"), ("token", "t0k")].map(|(name, value)| (name.to_string(), value.to_string())));
+        let mut headers = vec![];
+        apply(&rules, "api.test", &mut headers, &mut Resolver::new(&variables, None)).unwrap();
+        assert_eq!(headers, [
+            ("X-Tenant".to_string(), "acme-eu".to_string()),
+            ("Authorization".to_string(), format!("Basic {}", encoding::base64_encode(b"me:p:w"))),
+        ]);
+        let mut headers = vec![];
+        apply(&rules, "else.test", &mut headers, &mut Resolver::new(&variables, None)).unwrap();
+        assert_eq!(headers, [("Authorization".to_string(), "Bearer t0k".to_string())]);
+        let bad = [rule("*", &[("X-Id", "{{$nope}}")], None)];
+        let error = apply(&bad, "api.test", &mut vec![], &mut Resolver::new(&variables, None)).unwrap_err();
+        assert_eq!(error.to_string(), "unknown dynamic variable {{$nope}}");
+    }
+
+    #[test]
+    fn refresh_comes_from_the_rule_that_set_authorization() {
+        let refresh = TokenRefresh {
+            source: TokenSource::SavedRequest { request: "login".to_string() },
+            json_path: ".access_token".to_string(),
+            variable: "token".to_string(),
+        };
+        let rules = [
+            rule("*", &[("X-Trace", "1")], None),
+            rule("api.test", &[], Some(HostAuth::Bearer { token: "{{token}}".to_string(), refresh: Some(refresh) })),
+        ];
+        let variables = HashMap::new();
+        let mut headers = vec![];
+        let contributions = apply(&rules, "api.test", &mut headers, &mut Resolver::new(&variables, None)).unwrap();
+        assert_eq!(refresh_for(&rules, &contributions).map(|refresh| refresh.variable.as_str()), Some("token"));
+        let mut headers = vec![("Authorization".to_string(), "Bearer own".to_string())];
+        let contributions = apply(&rules, "api.test", &mut headers, &mut Resolver::new(&variables, None)).unwrap();
+        assert!(refresh_for(&rules, &contributions).is_none());
+    }
+}
@@ -0,0 +1,160 @@
+/// One line of the readable view of a page.
+#[derive(PartialEq, Debug)]
+pub enum Block {
+    Heading(String),
+    Item(String),
+    Text(String),
+}
+
+/// Tags that start a new line in the readable view.
+const BLOCK_TAGS: [&str; 24] = [
+    "address", "article", "aside", "blockquote", "br", "dd", "div", "dl", "dt", "footer", "form", "header",
+    "hr", "li", "main", "nav", "ol", "p", "pre", "section", "table", "title", "tr", "ul",
+];
+
+/// Tags whose contents are never shown.
+const HIDDEN_TAGS: [&str; 4] = ["script", "style", "noscript", "template"];
+
+pub fn looks_like_html(content_type: Option<&str>, body: &str) -> bool {
+    let by_type = content_type.is_some_and(|content_type| {
+        content_type.split(';').next().unwrap_or("").trim().eq_ignore_ascii_case("text/html")
+    });
+    let start = body.trim_start().get(..15).unwrap_or("").to_ascii_lowercase();
+    by_type || start.starts_with("<!doctype html") || start.starts_with("<html")
+}
+
+/// The text of a page with tags stripped, entities decoded and whitespace collapsed, broken
+/// into lines at block elements. Script and style contents are dropped.
+pub fn readable(html: &str) -> Vec<Block> {
+    let mut blocks = vec![];
+    let mut current = String::new();
+    let mut kind = BlockKind::Text;
+    let mut rest = html;
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix("<!--") {
+            rest = after.find("-->").map_or("", |end| &after[end + 3..]);
+            continue;
+        }
+        let Some(after) = rest.strip_prefix('<') else {
+            let end = rest.find('<').unwrap_or(rest.len());
+            push_text(&mut current, &decode_entities(&rest[..end]));
+            rest = &rest[end..];
+            continue;
+        };
+        let Some(end) = after.find('>') else {
+            push_text(&mut current, &decode_entities(rest));
+            break;
+        };
+        let tag = &after[..end];
+        rest = &after[end + 1..];
+        let closing = tag.starts_with('/');
+        let name = tag
+            .trim_start_matches('/')
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .next()
+            .unwrap_or("")
+            .to_ascii_lowercase();
+        if !closing && HIDDEN_TAGS.contains(&name.as_str()) {
+            // Skip to the matching close tag, whatever its case.
+            let close = format!("</{}", name);
+            rest = rest.to_ascii_lowercase().find(&close).map_or("", |at| {
+                let after_close = &rest[at..];
+                after_close.find('>').map_or("", |end| &after_close[end + 1..])
+            });
+            continue;
+        }
+        let heading = matches!(name.as_str(), "h1" | "h2" | "h3" | "h4" | "h5" | "h6");
+        if heading || BLOCK_TAGS.contains(&name.as_str()) {
+            flush(&mut blocks, &mut current, kind);
+            kind = match (closing, name.as_str()) {
+                (false, "li") => BlockKind::Item,
+                (false, _) if heading => BlockKind::Heading,
+                _ => BlockKind::Text,
+            };
+        } else if matches!(name.as_str(), "td" | "th") && !closing && !current.is_empty() {
+            push_text(&mut current, " | ");
+        }
+    }
+    flush(&mut blocks, &mut current, kind);
+    blocks
+}
+
+#[derive(Clone, Copy)]
+enum BlockKind {
+    Heading,
+    Item,
+    Text,
+}
+
+fn flush(blocks: &mut Vec<Block>, current: &mut String, kind: BlockKind) {
+    let text = std::mem::take(current).trim().to_string();
+    if text.is_empty() {
+        return;
+    }
+    blocks.push(match kind {
+        BlockKind::Heading => Block::Heading(text),
+        BlockKind::Item => Block::Item(text),
+        BlockKind::Text => Block::Text(text),
+    });
+}
+
+/// Appends text, collapsing runs of whitespace (including across calls) to one space.
+fn push_text(current: &mut String, text: &str) {
+    for c in text.chars() {
+        if c.is_whitespace() {
+            if !current.is_empty() && !current.ends_with(' ') {
+                current.push(' ');
+            }
+        } else {
+            current.push(c);
+        }
+    }
+}
+
+pub fn decode_entities(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(at) = rest.find('&') {
+        decoded.push_str(&rest[..at]);
+        rest = &rest[at..];
+        let entity = rest[1..].find(';').filter(|&end| end <= 10).map(|end| &rest[1..end + 1]);
+        match entity.and_then(decode_entity) {
+            Some(c) => {
+                decoded.push(c);
+                rest = &rest[entity.map_or(0, str::len) + 2..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+fn decode_entity(entity: &str) -> Option<char> {
+    if let Some(number) = entity.strip_prefix('#') {
+        let code = match number.strip_prefix(['x', 'X']) {
+            Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+            None => number.parse().ok()?,
+        };
+        return char::from_u32(code);
+    }
+    Some(match entity {
+        "amp" => '&',
+        "lt" => '<',
+        "gt" => '>',
+        "quot" => '"',
+        "apos" => '\'',
+        "nbsp" => ' ',
+        "copy" => '©',
+        "reg" => '®',
+        "hellip" => '…',
+        "mdash" => '—',
+        "ndash" => '–',
+        "laquo" => '«',
+        "raquo" => '»',
+        _ => return None,
+    })
+}
@@ -0,0 +1,265 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use serde_json::Value;
+
+use crate::{
+    collection::{Collection, SavedRequest},
+    encoding,
+    environment::Environment,
+    request::{method_index, method_name, RequestSpec},
+    template, yaml,
+};
+
+/// An Insomnia v4 export converted to ours: request groups become folders, requests saved
+/// requests and environments environments. Whatever else is in it is counted in `skipped`.
+pub struct Imported {
+    pub name: String,
+    pub collection: Collection,
+    pub environments: Vec<Environment>,
+    /// By resource type, or by what was left out of a request, like `multipart body`.
+    pub skipped: BTreeMap<String, usize>,
+}
+
+/// Reads the JSON export or its YAML flavor.
+pub fn import(text: &str) -> Result<Imported, String> {
+    let root: Value = match text.trim_start().starts_with('{') {
+        true => serde_json::from_str(text).map_err(|e| e.to_string())?,
+        false => yaml::parse(text)?,
+    };
+    if root["_type"].as_str() != Some("export") {
+        return Err("not an Insomnia export: no \"_type\": \"export\"".to_string());
+    }
+    if root["__export_format"].as_u64() != Some(4) {
+        return Err(format!("only v4 exports can be imported, not format {}", root["__export_format"]));
+    }
+    let resources = root["resources"].as_array().ok_or("the export has no \"resources\" list")?;
+    let by_id: HashMap<&str, &Value> = resources.iter().filter_map(|resource| Some((resource["_id"].as_str()?, resource))).collect();
+    let workspaces: Vec<&Value> = resources.iter().filter(|resource| resource["_type"] == "workspace").collect();
+    let mut imported = Imported {
+        name: match workspaces[..] {
+            [workspace] => text_of(&workspace["name"]).unwrap_or_else(|| "Insomnia import".to_string()),
+            _ => "Insomnia import".to_string(),
+        },
+        collection: Collection::default(),
+        environments: environments(resources, &by_id),
+        skipped: BTreeMap::new(),
+    };
+    // Exports list resources in no particular order; the sort key is their order in the sidebar.
+    let mut sorted: Vec<&Value> = resources.iter().collect();
+    sorted.sort_by(|a, b| sort_key(a).total_cmp(&sort_key(b)));
+    for resource in sorted {
+        let folder = folder(resource, &by_id, workspaces.len() > 1);
+        let name = text_of(&resource["name"]).unwrap_or_else(|| "Untitled".to_string());
+        match resource["_type"].as_str().unwrap_or("untyped") {
+            "request_group" => imported.collection.add_folder(&[folder, vec![name]].concat()),
+            "request" => {
+                imported.collection.add_folder(&folder);
+                let request = import_request(resource, &mut imported.skipped);
+                let description = text_of(&resource["description"]).filter(|text| !text.is_empty());
+                imported.collection.requests.push(SavedRequest { name, folder, request, last_used: None, baseline: None, description, tags: vec![] });
+            }
+            "workspace" | "environment" => {}
+            other => *imported.skipped.entry(other.to_string()).or_default() += 1,
+        }
+    }
+    Ok(imported)
+}
+
+fn sort_key(resource: &Value) -> f64 {
+    resource["metaSortKey"].as_f64().unwrap_or_default()
+}
+
+/// Names, numbers and booleans all come out as text, YAML being loose about which is which.
+fn text_of(value: &Value) -> Option<String> {
+    match value {
+        Value::String(text) => Some(text.clone()),
+        Value::Number(_) | Value::Bool(_) => Some(value.to_string()),
+        _ => None,
+    }
+}
+
+/// The request groups a resource is in, outermost first. With several workspaces in the
+/// export, each gets a folder of its own.
+fn folder(resource: &Value, by_id: &HashMap<&str, &Value>, several_workspaces: bool) -> Vec<String> {
+    let mut path = vec![];
+    let mut parent = resource["parentId"].as_str();
+    // Bounded, in case a damaged export has groups that are each other's parents.
+    for _ in 0..64 {
+        let Some(group) = parent.and_then(|id| by_id.get(id)) else { break };
+        let name = text_of(&group["name"]).unwrap_or_else(|| "Untitled".to_string());
+        match group["_type"].as_str() {
+            Some("request_group") => path.push(name),
+            Some("workspace") if several_workspaces => {
+                path.push(name);
+                break;
+            }
+            _ => break,
+        }
+        parent = group["parentId"].as_str();
+    }
+    path.reverse();
+    path
+}
+
+fn import_request(resource: &Value, skipped: &mut BTreeMap<String, usize>) -> RequestSpec {
+    let mut url = rewrite(&text_of(&resource["url"]).unwrap_or_default());
+    let query: Vec<String> = enabled(&resource["parameters"]).map(|(name, value)| format!("{}={}", name, value)).collect();
+    if !query.is_empty() {
+        url.push(if url.contains('?') {'&'} else {'?'});
+        url.push_str(&query.join("&"));
+    }
+    let mut headers: Vec<String> = enabled(&resource["headers"]).map(|(name, value)| format!("{}: {}", name, value)).collect();
+    let body = &resource["body"];
+    let mime = body["mimeType"].as_str().unwrap_or_default();
+    let text = match mime {
+        "application/x-www-form-urlencoded" => enabled(&body["params"]).map(|(name, value)| format!("{}={}", name, value)).collect::<Vec<_>>().join("&"),
+        "multipart/form-data" => {
+            *skipped.entry("multipart body".to_string()).or_default() += 1;
+            String::new()
+        }
+        _ => rewrite(body["text"].as_str().unwrap_or_default()),
+    };
+    let has_header = |headers: &[String], name: &str| headers.iter().any(|header| header.split(':').next().is_some_and(|own| own.trim().eq_ignore_ascii_case(name)));
+    if !text.is_empty() && !mime.is_empty() && !has_header(&headers, "Content-Type") {
+        headers.push(format!("Content-Type: {}", mime));
+    }
+    if !has_header(&headers, "Authorization") {
+        headers.extend(authorization(&resource["authentication"], skipped));
+    }
+    let method = resource["method"].as_str().unwrap_or("GET");
+    RequestSpec {
+        method: method_name(method_index(method)),
+        url,
+        headers: headers.join("\n"),
+        body: text,
+        connect_to: None,
+        hooks: vec![],
+        after_response: vec![],
+        redirects: None,
+        timeout_secs: None,
+        max_retries: None,
+        request_id: None,
+        body_schema: None,
+        response_schema: None,
+        expect_continue: None,
+        max_response_mb: None,
+    }
+}
+
+/// Name and value of each entry in a list of headers, parameters or form fields that isn't
+/// disabled.
+fn enabled(list: &Value) -> impl Iterator<Item = (String, String)> + '_ {
+    list.as_array()
+        .into_iter()
+        .flatten()
+        .filter(|entry| entry["disabled"].as_bool() != Some(true))
+        .filter_map(|entry| {
+            let name = text_of(&entry["name"]).filter(|name| !name.is_empty())?;
+            Some((rewrite(&name), rewrite(&text_of(&entry["value"]).unwrap_or_default())))
+        })
+}
+
+/// The `Authorization` header for bearer or basic auth. Basic auth is encoded here, so it's
+/// only imported when it doesn't use variables.
+fn authorization(auth: &Value, skipped: &mut BTreeMap<String, usize>) -> Option<String> {
+    if auth["disabled"].as_bool() == Some(true) {
+        return None;
+    }
+    let mut skip = |what: String| *skipped.entry(what).or_default() += 1;
+    match auth["type"].as_str()? {
+        "none" => None,
+        "bearer" => {
+            let prefix = auth["prefix"].as_str().filter(|prefix| !prefix.is_empty()).unwrap_or("Bearer");
+            Some(format!("Authorization: {} {}", prefix, rewrite(auth["token"].as_str().unwrap_or_default())))
+        }
+        "basic" => {
+            let credentials = format!("{}:{}", auth["username"].as_str().unwrap_or_default(), auth["password"].as_str().unwrap_or_default());
+            if credentials.contains("{{") || credentials.contains("{%") {
+                skip("basic auth with variables".to_string());
+                return None;
+            }
+            Some(format!("Authorization: Basic {}", encoding::base64_encode(credentials.as_bytes())))
+        }
+        other => {
+            skip(format!("{} auth", other));
+            None
+        }
+    }
+}
+
+/// Insomnia's `{{ _.name }}` and `{{ _['name'] }}` as `{{name}}`, and its `uuid` and `now`
+/// template tags as the matching dynamic values. Other tags are left as they are.
+fn rewrite(text: &str) -> String {
+    let text = template::map_placeholders(text, |expression| {
+        let name = expression.strip_prefix("_.").unwrap_or(expression);
+        match name.strip_prefix("_[").and_then(|rest| rest.strip_suffix(']')) {
+            Some(quoted) => quoted.trim_matches(['\'', '"']).to_string(),
+            None => name.to_string(),
+        }
+    });
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text.as_str();
+    while let Some(open) = rest.find("{%") {
+        let Some(close) = rest[open..].find("%}") else { break };
+        out.push_str(&rest[..open]);
+        let tag = &rest[open..open + close + 2];
+        let mut words = tag[2..tag.len() - 2].split_whitespace();
+        let replacement = match (words.next(), words.next().map(|word| word.trim_matches(['\'', '"', ',']))) {
+            (Some("uuid"), _) => "{{$uuid}}",
+            (Some("now"), Some("unix")) => "{{$timestamp}}",
+            (Some("now"), Some("iso-8601") | None) => "{{$isoTimestamp}}",
+            _ => tag,
+        };
+        out.push_str(replacement);
+        rest = &rest[open + close + 2..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Each sub environment on top of its base environment, or the base environment alone when it
+/// has none.
+fn environments(resources: &[Value], by_id: &HashMap<&str, &Value>) -> Vec<Environment> {
+    let is_environment = |id: Option<&str>| id.and_then(|id| by_id.get(id)).is_some_and(|parent| parent["_type"] == "environment");
+    let all: Vec<&Value> = resources.iter().filter(|resource| resource["_type"] == "environment").collect();
+    let mut environments = vec![];
+    for base in all.iter().filter(|env| !is_environment(env["parentId"].as_str())) {
+        let mut base_variables = BTreeMap::new();
+        flatten(&base["data"], "", &mut base_variables);
+        let subs: Vec<&&Value> = all.iter().filter(|env| env["parentId"].as_str().is_some() && env["parentId"] == base["_id"]).collect();
+        if subs.is_empty() && !base_variables.is_empty() {
+            let name = text_of(&base["name"]).unwrap_or_else(|| "Base Environment".to_string());
+            environments.push(environment(name, base_variables.clone()));
+        }
+        for sub in subs {
+            let mut variables = base_variables.clone();
+            flatten(&sub["data"], "", &mut variables);
+            environments.push(environment(text_of(&sub["name"]).unwrap_or_else(|| "Untitled".to_string()), variables));
+        }
+    }
+    environments
+}
+
+fn environment(name: String, variables: BTreeMap<String, String>) -> Environment {
+    Environment { name, dotenv: None, variables, secrets: BTreeSet::new(), encrypted: BTreeMap::new() }
+}
+
+/// Nested data as `outer.inner` names, which is how Insomnia refers to them.
+fn flatten(data: &Value, prefix: &str, variables: &mut BTreeMap<String, String>) {
+    let Some(object) = data.as_object() else { return };
+    for (key, value) in object {
+        let name = format!("{}{}", prefix, key);
+        match value {
+            Value::Object(_) => flatten(value, &format!("{}.", name), variables),
+            Value::String(text) => {
+                variables.insert(name, rewrite(text));
+            }
+            Value::Null => {
+                variables.insert(name, String::new());
+            }
+            other => {
+                variables.insert(name, other.to_string());
+            }
+        }
+    }
+}
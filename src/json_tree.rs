@@ -0,0 +1,182 @@
+use std::collections::HashSet;
+
+use serde_json::Value;
+
+/// One step from a JSON value to one of its children.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+/// A visible line of the tree.
+pub struct Row {
+    pub path: Vec<Segment>,
+    /// Scalars as JSON, containers as their size.
+    pub summary: String,
+    pub container: bool,
+    pub expanded: bool,
+}
+
+/// A JSON document shown as a tree whose objects and arrays fold. Only the children of
+/// expanded nodes are ever turned into rows, so a huge document costs little until opened up.
+pub struct Tree {
+    root: Value,
+    expanded: HashSet<Vec<Segment>>,
+    rows: Vec<Row>,
+    pub selected: usize,
+}
+
+impl Tree {
+    /// The tree of `body` with only the top level open, or `None` if it isn't JSON.
+    pub fn parse(body: &str) -> Option<Self> {
+        let root: Value = serde_json::from_str(body).ok()?;
+        let mut tree = Self { root, expanded: HashSet::from([vec![]]), rows: vec![], selected: 0 };
+        tree.rebuild();
+        Some(tree)
+    }
+
+    pub fn rows(&self) -> &[Row] {
+        &self.rows
+    }
+
+    pub fn up(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn down(&mut self) {
+        self.selected = (self.selected + 1).min(self.rows.len().saturating_sub(1));
+    }
+
+    /// Folds or unfolds the selected object or array.
+    pub fn toggle(&mut self) {
+        let Some(row) = self.rows.get(self.selected).filter(|row| row.container) else { return };
+        let path = row.path.clone();
+        if !self.expanded.remove(&path) {
+            self.expanded.insert(path);
+        }
+        self.rebuild();
+    }
+
+    pub fn expand(&mut self) {
+        if self.rows.get(self.selected).is_some_and(|row| row.container && !row.expanded) {
+            self.toggle();
+        }
+    }
+
+    /// Folds the selected node if it's open, otherwise moves to its parent.
+    pub fn collapse(&mut self) {
+        let Some(row) = self.rows.get(self.selected) else { return };
+        if row.container && row.expanded {
+            self.toggle();
+        } else if let Some((_, parent)) = row.path.split_last() {
+            let parent = parent.to_vec();
+            self.selected = self.rows.iter().position(|row| row.path == parent).unwrap_or(self.selected);
+        }
+    }
+
+    pub fn selected_value(&self) -> Option<&Value> {
+        let row = self.rows.get(self.selected)?;
+        row.path.iter().try_fold(&self.root, |value, segment| match segment {
+            Segment::Key(key) => value.get(key),
+            Segment::Index(index) => value.get(index),
+        })
+    }
+
+    /// Where the selected node is, like `.items[2].user.email`.
+    pub fn selected_path(&self) -> String {
+        self.rows.get(self.selected).map_or(String::new(), |row| path_string(&row.path))
+    }
+
+    fn rebuild(&mut self) {
+        let mut rows = vec![];
+        push_rows(&self.root, vec![], &self.expanded, &mut rows);
+        self.rows = rows;
+        self.selected = self.selected.min(self.rows.len().saturating_sub(1));
+    }
+}
+
+fn push_rows(value: &Value, path: Vec<Segment>, expanded: &HashSet<Vec<Segment>>, rows: &mut Vec<Row>) {
+    let is_open = expanded.contains(&path);
+    let (summary, container) = match value {
+        Value::Object(map) => (format!("{{}} {} key{}", map.len(), if map.len() == 1 {""} else {"s"}), true),
+        Value::Array(items) => (format!("[] {} item{}", items.len(), if items.len() == 1 {""} else {"s"}), true),
+        scalar => (scalar.to_string(), false),
+    };
+    rows.push(Row { path: path.clone(), summary, container, expanded: container && is_open });
+    if !is_open {
+        return;
+    }
+    let child = |segment| {
+        let mut child = path.clone();
+        child.push(segment);
+        child
+    };
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                push_rows(value, child(Segment::Key(key.clone())), expanded, rows);
+            }
+        }
+        Value::Array(items) => {
+            for (index, value) in items.iter().enumerate() {
+                push_rows(value, child(Segment::Index(index)), expanded, rows);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// jq-style, as `hooks::json_path` reads it: `.key` for plain keys, `["odd key"]` otherwise,
+/// `[2]` for array items.
+pub fn path_string(path: &[Segment]) -> String {
+    if path.is_empty() {
+        return ".".to_string();
+    }
+    path.iter()
+        .map(|segment| match segment {
+            Segment::Key(key) if !key.is_empty() && key.chars().all(|c| c.is_alphanumeric() || c == '_') => format!(".{}", key),
+            Segment::Key(key) => format!("[{}]", Value::from(key.as_str())),
+            Segment::Index(index) => format!("[{}]", index),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hooks;
+
+    fn key(key: &str) -> Segment {
+        Segment::Key(key.to_string())
+    }
+
+    #[test]
+    fn odd_keys_are_quoted_and_indexes_bracketed() {
+        assert_eq!(path_string(&[]), ".");
+        assert_eq!(path_string(&[key("items"), Segment::Index(2), key("user_id")]), ".items[2].user_id");
+        assert_eq!(path_string(&[key("a.b"), key("first name")]), r#"["a.b"]["first name"]"#);
+        assert_eq!(path_string(&[key(r#"say "hi""#), key(r"back\slash")]), r#"["say \"hi\""]["back\\slash"]"#);
+        assert_eq!(path_string(&[key(""), key("a]b"), key("x[0]")]), r#"[""]["a]b"]["x[0]"]"#);
+        assert_eq!(path_string(&[key("matrix"), Segment::Index(0), Segment::Index(10), key("ünï")]), ".matrix[0][10].ünï");
+    }
+
+    #[test]
+    fn every_path_shown_leads_back_to_its_value() {
+        let body = r#"{"a.b": {"first name": [1, {"say \"hi\"": true}]}, "": {"x[0]": null, "a]b": "c"}, "0": [[], [{"tab\there": 2}]]}"#;
+        let mut tree = Tree::parse(body).unwrap();
+        let mut row = 0;
+        while row < tree.rows().len() {
+            tree.selected = row;
+            tree.expand();
+            row += 1;
+        }
+        assert_eq!(tree.rows().len(), 14);
+        let root: Value = serde_json::from_str(body).unwrap();
+        for row in 0..tree.rows().len() {
+            tree.selected = row;
+            let path = tree.selected_path();
+            assert_eq!(hooks::json_path(&root, &path).ok(), tree.selected_value(), "{}", path);
+        }
+    }
+}
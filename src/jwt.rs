@@ -0,0 +1,151 @@
+use std::fmt;
+
+use chrono::DateTime;
+use serde_json::Value;
+
+use crate::encoding::base64_url_decode;
+
+/// A JWT split into its decoded header and payload. The signature is checked for
+/// well-formedness only, never verified.
+pub struct Jwt {
+    pub header: Value,
+    pub payload: Value,
+}
+
+#[derive(Debug)]
+pub enum JwtError {
+    NotThreeParts(usize),
+    InvalidBase64Url(&'static str),
+    InvalidJson(&'static str, String),
+}
+
+impl fmt::Display for JwtError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            JwtError::NotThreeParts(found) => write!(f, "not 3 dot-separated parts (found {})", found),
+            JwtError::InvalidBase64Url(part) => write!(f, "invalid base64url in the {}", part),
+            JwtError::InvalidJson(part, e) => write!(f, "the {} is not valid JSON: {}", part, e),
+        }
+    }
+}
+
+#[derive(PartialEq)]
+pub enum Validity {
+    Valid,
+    Expired,
+    NotYetValid,
+    NoExpiry,
+}
+
+/// Claims holding NumericDate values that are worth showing as dates.
+pub const TIME_CLAIMS: [&str; 3] = ["exp", "iat", "nbf"];
+
+pub fn decode(token: &str) -> Result<Jwt, JwtError> {
+    let parts: Vec<&str> = token.trim().split('.').collect();
+    if parts.len() != 3 {
+        return Err(JwtError::NotThreeParts(parts.len()));
+    }
+    let header = decode_part(parts[0], "header")?;
+    let payload = decode_part(parts[1], "payload")?;
+    base64_url_decode(parts[2]).map_err(|_| JwtError::InvalidBase64Url("signature"))?;
+    Ok(Jwt { header, payload })
+}
+
+fn decode_part(part: &str, name: &'static str) -> Result<Value, JwtError> {
+    let bytes = base64_url_decode(part).map_err(|_| JwtError::InvalidBase64Url(name))?;
+    serde_json::from_slice(&bytes).map_err(|e| JwtError::InvalidJson(name, e.to_string()))
+}
+
+impl Jwt {
+    pub fn claim_time(&self, claim: &str) -> Option<i64> {
+        self.payload.get(claim).and_then(|v| v.as_f64()).map(|secs| secs as i64)
+    }
+
+    pub fn validity(&self, now: i64) -> Validity {
+        if self.claim_time("nbf").is_some_and(|nbf| now < nbf) {
+            return Validity::NotYetValid;
+        }
+        match self.claim_time("exp") {
+            Some(exp) if now >= exp => Validity::Expired,
+            Some(_) => Validity::Valid,
+            None => Validity::NoExpiry,
+        }
+    }
+}
+
+fn is_token_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.'
+}
+
+/// A cheap shape check: three base64url segments with a JSON-object header (`eyJ` is `{"`).
+pub fn looks_like_jwt(candidate: &str) -> bool {
+    candidate.starts_with("eyJ")
+        && candidate.split('.').count() == 3
+        && candidate.chars().all(is_token_char)
+}
+
+/// Finds the first JWT-shaped token in arbitrary text such as a JSON response body.
+pub fn find_in_text(text: &str) -> Option<&str> {
+    text.split(|c| !is_token_char(c)).find(|word| looks_like_jwt(word))
+}
+
+/// Pulls the token out of an `Authorization: Bearer <token>` line in the headers input.
+pub fn find_in_headers(headers: &str) -> Option<&str> {
+    headers.lines().find_map(|line| {
+        let (name, value) = line.split_once(':')?;
+        if !name.trim().eq_ignore_ascii_case("authorization") {
+            return None;
+        }
+        let value = value.trim();
+        let token = value.strip_prefix("Bearer ").or_else(|| value.strip_prefix("bearer ")).unwrap_or(value);
+        Some(token.trim())
+    })
+}
+
+pub fn format_timestamp(secs: i64, now: i64) -> String {
+    let date = DateTime::from_timestamp(secs, 0)
+        .map(|d| d.format("%Y-%m-%d %H:%M:%S UTC").to_string())
+        .unwrap_or_else(|| "out of range".to_string());
+    // Claims are whatever the token says, so the difference can be past what an i64 holds.
+    let delta = secs.saturating_sub(now);
+    let relative = if delta >= 0 {
+        format!("in {}", format_duration(delta.unsigned_abs()))
+    } else {
+        format!("{} ago", format_duration(delta.unsigned_abs()))
+    };
+    format!("{} ({})", date, relative)
+}
+
+fn format_duration(secs: u64) -> String {
+    let (days, hours, minutes) = (secs / 86_400, secs % 86_400 / 3_600, secs % 3_600 / 60);
+    if days > 0 {
+        format!("{}d {}h", days, hours)
+    } else if hours > 0 {
+        format!("{}h {}m", hours, minutes)
+    } else if minutes > 0 {
+        format!("{}m {}s", minutes, secs % 60)
+    } else {
+        format!("{}s", secs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timestamps_read_relative_to_now() {
+        assert_eq!(format_timestamp(90_000, 0), "1970-01-02 01:00:00 UTC (in 1d 1h)");
+        assert_eq!(format_timestamp(0, 150), "1970-01-01 00:00:00 UTC (2m 30s ago)");
+        assert_eq!(format_timestamp(60, 60), "1970-01-01 00:01:00 UTC (in 0s)");
+    }
+
+    #[test]
+    fn extreme_claims_are_out_of_range_without_overflowing() {
+        let now = 1_700_000_000;
+        assert!(format_timestamp(i64::MIN, now).starts_with("out of range ("));
+        assert!(format_timestamp(i64::MIN, now).ends_with(" ago)"));
+        assert!(format_timestamp(i64::MAX, -now).starts_with("out of range (in "));
+        assert_eq!(format_timestamp(i64::MIN, i64::MAX), "out of range (106751991167300d 15h ago)");
+    }
+}
@@ -0,0 +1,146 @@
+use std::{collections::BTreeMap, fmt};
+
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::action::Action;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct KeyBinding {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl KeyBinding {
+    pub const fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        Self { code, modifiers }
+    }
+
+    /// Parses bindings like `ctrl+r`, `alt+z`, `f2`, or `shift+tab`.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut parts: Vec<&str> = text.split('+').map(str::trim).collect();
+        let key = parts.pop().filter(|key| !key.is_empty()).ok_or_else(|| format!("empty key binding {:?}", text))?;
+        for modifier in parts {
+            modifiers |= match modifier.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" | "meta" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => return Err(format!("unknown modifier {:?} in {:?}", modifier, text)),
+            };
+        }
+        let code = match key.to_ascii_lowercase().as_str() {
+            "enter" => KeyCode::Enter,
+            "esc" | "escape" => KeyCode::Esc,
+            "tab" => KeyCode::Tab,
+            "backspace" => KeyCode::Backspace,
+            "delete" | "del" => KeyCode::Delete,
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "pageup" => KeyCode::PageUp,
+            "pagedown" => KeyCode::PageDown,
+            "space" => KeyCode::Char(' '),
+            lower => match lower.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+                Some(n) if key.len() > 1 => KeyCode::F(n),
+                _ if key.chars().count() == 1 => KeyCode::Char(key.chars().next().unwrap_or(' ')),
+                _ => return Err(format!("unknown key {:?} in {:?}", key, text)),
+            },
+        };
+        Ok(Self { code, modifiers })
+    }
+
+    /// Terminals disagree on whether Shift+letter arrives as `R`, `shift+r` or both, so for
+    /// characters either an uppercase letter or the Shift modifier counts as shifted.
+    pub fn matches(&self, key: &KeyEvent) -> bool {
+        match (self.code, key.code) {
+            (KeyCode::Char(bound), KeyCode::Char(pressed)) => {
+                let shifted = |c: char, m: KeyModifiers| c.is_uppercase() || m.contains(KeyModifiers::SHIFT);
+                bound.eq_ignore_ascii_case(&pressed)
+                    && shifted(bound, self.modifiers) == shifted(pressed, key.modifiers)
+                    && self.modifiers - KeyModifiers::SHIFT == key.modifiers - KeyModifiers::SHIFT
+            }
+            (bound, pressed) => bound == pressed && self.modifiers == key.modifiers,
+        }
+    }
+}
+
+impl fmt::Display for KeyBinding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (modifier, name) in [(KeyModifiers::CONTROL, "Ctrl+"), (KeyModifiers::ALT, "Alt+"), (KeyModifiers::SHIFT, "Shift+")] {
+            if self.modifiers.contains(modifier) {
+                write!(f, "{}", name)?;
+            }
+        }
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "Space"),
+            KeyCode::Char(c) if self.modifiers.is_empty() => write!(f, "{}", c),
+            KeyCode::Char(c) => write!(f, "{}", c.to_ascii_uppercase()),
+            KeyCode::F(n) => write!(f, "F{}", n),
+            code => write!(f, "{:?}", code),
+        }
+    }
+}
+
+const DEFAULT_BINDINGS: [(KeyBinding, Action); 16] = [
+    (KeyBinding::new(KeyCode::Char('p'), KeyModifiers::CONTROL), Action::CommandPalette),
+    (KeyBinding::new(KeyCode::F(1), KeyModifiers::NONE), Action::Help),
+    (KeyBinding::new(KeyCode::Char('r'), KeyModifiers::CONTROL), Action::ResendLast),
+    (KeyBinding::new(KeyCode::Char('r'), KeyModifiers::ALT), Action::RetryWhenAllowed),
+    (KeyBinding::new(KeyCode::Char('f'), KeyModifiers::CONTROL), Action::FindRequest),
+    (KeyBinding::new(KeyCode::Char('z'), KeyModifiers::ALT), Action::Undo),
+    (KeyBinding::new(KeyCode::F(2), KeyModifiers::NONE), Action::ToggleConsole),
+    (KeyBinding::new(KeyCode::F(3), KeyModifiers::NONE), Action::ToggleCollection),
+    (KeyBinding::new(KeyCode::F(4), KeyModifiers::NONE), Action::FocusResponses),
+    (KeyBinding::new(KeyCode::F(5), KeyModifiers::NONE), Action::RequestSettings),
+    (KeyBinding::new(KeyCode::F(6), KeyModifiers::NONE), Action::RetryLastFailure),
+    (KeyBinding::new(KeyCode::Char('g'), KeyModifiers::CONTROL), Action::GoToLine),
+    (KeyBinding::new(KeyCode::Char('e'), KeyModifiers::CONTROL), Action::EditBodyExternally),
+    (KeyBinding::new(KeyCode::Char('z'), KeyModifiers::CONTROL), Action::Suspend),
+    (KeyBinding::new(KeyCode::Char('x'), KeyModifiers::ALT), Action::ErrorDetails),
+    (KeyBinding::new(KeyCode::Char('q'), KeyModifiers::CONTROL), Action::Quit),
+];
+
+/// Global shortcuts, which work whichever field is focused.
+pub struct Keymap {
+    bindings: Vec<(KeyBinding, Action)>,
+}
+
+impl Keymap {
+    /// The default bindings with the `[keys]` section of the config applied on top. Entries map
+    /// action ids to a key, e.g. `resend_last = "ctrl+r"`; an empty key unbinds the action.
+    pub fn from_config(keys: &BTreeMap<String, String>) -> (Self, Vec<String>) {
+        let mut bindings = DEFAULT_BINDINGS.to_vec();
+        let mut errors = vec![];
+        for (id, key) in keys {
+            let Some(action) = Action::ALL.into_iter().find(|action| action.id() == *id) else {
+                errors.push(format!("unknown action {:?} in [keys]", id));
+                continue;
+            };
+            bindings.retain(|(_, bound)| *bound != action);
+            if key.trim().is_empty() {
+                continue;
+            }
+            match KeyBinding::parse(key) {
+                Ok(binding) => {
+                    bindings.retain(|(existing, _)| *existing != binding);
+                    bindings.push((binding, action));
+                }
+                Err(e) => errors.push(e),
+            }
+        }
+        (Self { bindings }, errors)
+    }
+
+    pub fn action_for(&self, key: &KeyEvent) -> Option<Action> {
+        self.bindings.iter().find(|(binding, _)| binding.matches(key)).map(|(_, action)| *action)
+    }
+
+    pub fn binding_for(&self, action: Action) -> Option<KeyBinding> {
+        self.bindings.iter().find(|(_, bound)| *bound == action).map(|(binding, _)| *binding)
+    }
+
+    pub fn bindings(&self) -> &[(KeyBinding, Action)] {
+        &self.bindings
+    }
+}
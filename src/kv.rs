@@ -0,0 +1,119 @@
+/// One key/value row of a structured editor. Disabled rows are kept but not sent.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Row {
+    pub enabled: bool,
+    pub key: String,
+    /// `None` for a bare key, like `debug` in `?debug&page=2`.
+    pub value: Option<String>,
+}
+
+/// One row per line as `key<separator>value`, disabled rows behind `//`.
+pub fn to_bulk(rows: &[Row], separator: &str) -> String {
+    rows.iter()
+        .map(|row| {
+            let disabled = if row.enabled {""} else {"// "};
+            match &row.value {
+                Some(value) => format!("{}{}{}{}", disabled, row.key, separator, value),
+                None => format!("{}{}", disabled, row.key),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Reads rows written one per line as `key: value` or `key=value`, whichever separator comes
+/// first. Blank lines are skipped; every bad line is reported, numbered from one.
+pub fn parse_bulk(text: &str) -> Result<Vec<Row>, Vec<String>> {
+    let mut rows = vec![];
+    let mut errors = vec![];
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let (enabled, line) = match line.strip_prefix("//") {
+            Some(rest) => (false, rest.trim_start()),
+            None => (true, line),
+        };
+        let (key, value) = match line.find(['=', ':']) {
+            Some(at) => (line[..at].trim(), Some(line[at + 1..].trim().to_string())),
+            None => (line, None),
+        };
+        if key.is_empty() {
+            errors.push(format!("line {}: no key before the separator", number + 1));
+        } else if key.contains(char::is_whitespace) {
+            errors.push(format!("line {}: {:?} has spaces in its key", number + 1, key));
+        } else {
+            rows.push(Row { enabled, key: key.to_string(), value });
+        }
+    }
+    if errors.is_empty() {Ok(rows)} else {Err(errors)}
+}
+
+/// Header lines as rows. A line without a colon becomes a row without a value, which
+/// `to_bulk` writes back unchanged and which isn't sent.
+pub fn header_rows(text: &str) -> Vec<Row> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            let (enabled, line) = match line.strip_prefix("//") {
+                Some(rest) => (false, rest.trim_start()),
+                None => (true, line),
+            };
+            match line.split_once(':') {
+                Some((key, value)) => Row { enabled, key: key.trim().to_string(), value: Some(value.trim().to_string()) },
+                None => Row { enabled, key: line.to_string(), value: None },
+            }
+        })
+        .collect()
+}
+
+/// Splits a URL into everything before the query, the query, and the fragment (with its `#`).
+pub fn split_query(url: &str) -> (&str, &str, &str) {
+    let (rest, fragment) = url.find('#').map_or((url, ""), |at| url.split_at(at));
+    match rest.split_once('?') {
+        Some((base, query)) => (base, query, fragment),
+        None => (rest, "", fragment),
+    }
+}
+
+/// The parameters of a query string as written, without decoding, so they go back unchanged.
+pub fn query_rows(query: &str) -> Vec<Row> {
+    query.split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| match pair.split_once('=') {
+            Some((key, value)) => Row { enabled: true, key: key.to_string(), value: Some(value.to_string()) },
+            None => Row { enabled: true, key: pair.to_string(), value: None },
+        })
+        .collect()
+}
+
+pub fn query_string(rows: &[Row]) -> String {
+    rows.iter()
+        .filter(|row| row.enabled)
+        .map(|row| match &row.value {
+            Some(value) => format!("{}={}", row.key, value),
+            None => row.key.clone(),
+        })
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_every_bad_line_by_number() {
+        let errors = parse_bulk("page=1\n\n=2\nmy key=3\n// sort: asc").unwrap_err();
+        assert_eq!(errors, ["line 3: no key before the separator", "line 4: \"my key\" has spaces in its key"]);
+    }
+
+    #[test]
+    fn reads_disabled_and_valueless_rows() {
+        let rows = parse_bulk("page=1\n// sort: asc\nflag").unwrap();
+        let read: Vec<(bool, &str, Option<&str>)> = rows.iter().map(|row| (row.enabled, row.key.as_str(), row.value.as_deref())).collect();
+        assert_eq!(read, [(true, "page", Some("1")), (false, "sort", Some("asc")), (true, "flag", None)]);
+    }
+}
@@ -0,0 +1,420 @@
+use std::{
+    collections::BTreeMap,
+    net::{SocketAddr, TcpStream},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc::{self, Receiver, Sender},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    net::{AddressPreference, DnsCache},
+    request::ResolvedRequest,
+    status, url,
+    wire::{Closed, Connection, Limits, WireError},
+    worker::{ATTEMPT_TIMEOUT, DEFAULT_READ_TIMEOUT},
+};
+
+/// Bars in the latency histogram.
+pub const HISTOGRAM_BUCKETS: usize = 16;
+
+/// How many workers send the request at once, and for how long.
+#[derive(Clone, Copy, Debug)]
+pub struct Plan {
+    pub concurrency: usize,
+    pub limit: Limit,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum Limit {
+    Duration(Duration),
+    /// Requests across all workers.
+    Count(usize),
+}
+
+/// Reads a plan like `20 10s` (20 workers for ten seconds) or `20 500` (500 requests).
+pub fn parse_plan(input: &str) -> Result<Plan, String> {
+    let usage = || format!("expected workers and a duration or count, like \"20 10s\" or \"20 500\", not {:?}", input);
+    let mut words = input.split_whitespace();
+    let (Some(concurrency), Some(limit), None) = (words.next(), words.next(), words.next()) else {
+        return Err(usage());
+    };
+    let concurrency: usize = concurrency.parse().map_err(|_| usage())?;
+    let limit = match limit.strip_suffix('s') {
+        Some(seconds) => Limit::Duration(Duration::from_secs(seconds.parse().map_err(|_| usage())?)),
+        None => Limit::Count(limit.parse().map_err(|_| usage())?),
+    };
+    if concurrency == 0 || matches!(limit, Limit::Duration(Duration::ZERO) | Limit::Count(0)) {
+        return Err("workers and the duration or count must be more than zero".to_string());
+    }
+    Ok(Plan { concurrency, limit })
+}
+
+enum Sample {
+    Response { sent: Instant, status: u16, latency_ms: u64 },
+    Failed { sent: Instant, latency_ms: u64 },
+    /// A worker couldn't start: no address to send to.
+    Broken(String),
+    Done,
+}
+
+/// A load run in progress or finished. Workers stop at the plan's limit, or once `stop` is
+/// called, giving up on the request they're waiting on.
+pub struct Run {
+    pub plan: Plan,
+    pub target: String,
+    started: Instant,
+    /// Set when the last worker finished.
+    finished: Option<Instant>,
+    stop: Arc<AtomicBool>,
+    samples: Receiver<Sample>,
+    running: usize,
+    /// In the order they completed.
+    attempts: Vec<Attempt>,
+    statuses: BTreeMap<u16, usize>,
+    failures: usize,
+    pub problems: Vec<String>,
+}
+
+impl Run {
+    /// Without a connect or read timeout, workers use the worker's defaults, so a stop always
+    /// ends them in good time.
+    pub fn start(plan: Plan, request: ResolvedRequest, dns: Arc<DnsCache>, preference: AddressPreference, connect_timeout: Option<Duration>, read_timeout: Option<Duration>) -> Self {
+        let timeouts = Timeouts { connect: connect_timeout.unwrap_or(ATTEMPT_TIMEOUT), read: read_timeout.unwrap_or(DEFAULT_READ_TIMEOUT) };
+        let stop = Arc::new(AtomicBool::new(false));
+        let remaining = Arc::new(AtomicUsize::new(match plan.limit {
+            Limit::Count(count) => count,
+            Limit::Duration(_) => usize::MAX,
+        }));
+        let started = Instant::now();
+        let deadline = match plan.limit {
+            Limit::Duration(duration) => Some(started + duration),
+            Limit::Count(_) => None,
+        };
+        let (sender, samples) = mpsc::channel();
+        let request = Arc::new(request);
+        for _ in 0..plan.concurrency {
+            let (stop, remaining, request, sender, dns) = (stop.clone(), remaining.clone(), request.clone(), sender.clone(), dns.clone());
+            thread::spawn(move || {
+                work(&request, &dns, preference, timeouts, deadline, &stop, &remaining, &sender);
+                let _ = sender.send(Sample::Done);
+            });
+        }
+        Self {
+            plan,
+            target: request.url(),
+            started,
+            finished: None,
+            stop,
+            samples,
+            running: plan.concurrency,
+            attempts: vec![],
+            statuses: BTreeMap::new(),
+            failures: 0,
+            problems: vec![],
+        }
+    }
+
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished.is_some()
+    }
+
+    /// Takes in whatever the workers have reported since the last call.
+    pub fn collect(&mut self) {
+        while let Ok(sample) = self.samples.try_recv() {
+            match sample {
+                Sample::Response { sent, status, latency_ms } => {
+                    self.record(sent, Some(status), latency_ms);
+                    *self.statuses.entry(status).or_default() += 1;
+                }
+                Sample::Failed { sent, latency_ms } => {
+                    self.record(sent, None, latency_ms);
+                    self.failures += 1;
+                }
+                Sample::Broken(problem) => {
+                    if !self.problems.contains(&problem) {
+                        self.problems.push(problem);
+                    }
+                }
+                Sample::Done => {
+                    self.running -= 1;
+                    if self.running == 0 {
+                        self.finished = Some(Instant::now());
+                    }
+                }
+            }
+        }
+    }
+
+    fn record(&mut self, sent: Instant, status: Option<u16>, latency_ms: u64) {
+        let sent_ms = sent.saturating_duration_since(self.started).as_millis() as u64;
+        self.attempts.push(Attempt { sent_ms, latency_ms, status });
+    }
+
+    pub fn completed(&self) -> usize {
+        self.attempts.len()
+    }
+
+    pub fn latencies_ms(&self) -> Vec<u64> {
+        self.attempts.iter().map(|attempt| attempt.latency_ms).collect()
+    }
+
+    /// Every attempt as CSV: when it was sent relative to the start, its latency and status
+    /// (empty when there was no response).
+    pub fn csv(&self) -> String {
+        let mut csv = "sent_ms,latency_ms,status\n".to_string();
+        for attempt in &self.attempts {
+            let status = attempt.status.map(|status| status.to_string()).unwrap_or_default();
+            csv.push_str(&format!("{},{},{}\n", attempt.sent_ms, attempt.latency_ms, status));
+        }
+        csv
+    }
+
+    pub fn elapsed(&self, now: Instant) -> Duration {
+        self.finished.unwrap_or(now).duration_since(self.started)
+    }
+
+    pub fn requests_per_second(&self, now: Instant) -> f64 {
+        let seconds = self.elapsed(now).as_secs_f64();
+        if seconds > 0.0 {self.completed() as f64 / seconds} else {0.0}
+    }
+
+    /// Transport failures and 5xx responses as a share of everything completed.
+    pub fn error_rate(&self) -> f64 {
+        let server_errors: usize = self.statuses.range(500..).map(|(_, count)| count).sum();
+        if self.completed() == 0 {0.0} else {(self.failures + server_errors) as f64 / self.completed() as f64}
+    }
+
+    /// One line of progress for while the run goes on.
+    pub fn progress(&self, now: Instant) -> String {
+        let limit = match self.plan.limit {
+            Limit::Duration(duration) => format!("{:.0}s of {}s", self.elapsed(now).as_secs_f64(), duration.as_secs()),
+            Limit::Count(count) => format!("{} of {} requests", self.completed(), count),
+        };
+        format!(
+            "{} workers  |  {}  |  {} done  |  {:.1} req/s  |  {:.1}% errors",
+            self.running,
+            limit,
+            self.completed(),
+            self.requests_per_second(now),
+            self.error_rate() * 100.0,
+        )
+    }
+
+    /// Latency percentiles and how many of each status came back.
+    pub fn summary(&self) -> Vec<String> {
+        let mut lines = vec![];
+        let mut sorted = self.latencies_ms();
+        sorted.sort_unstable();
+        if let Some(max) = sorted.last() {
+            let percentile = |p: usize| sorted[(sorted.len() * p / 100).min(sorted.len() - 1)];
+            let mean = sorted.iter().sum::<u64>() as f64 / sorted.len() as f64;
+            lines.push(format!(
+                "Latency: min {} ms, mean {:.1} ms, p50 {} ms, p90 {} ms, p99 {} ms, max {} ms",
+                sorted[0], mean, percentile(50), percentile(90), percentile(99), max,
+            ));
+        }
+        let most = self.statuses.values().copied().max().unwrap_or(0);
+        for (status, count) in &self.statuses {
+            let bar = "█".repeat((count * 30).div_ceil(most));
+            lines.push(format!("{} {:<20} {:>7}  {}", status, status::reason(*status).unwrap_or(""), count, bar));
+        }
+        if self.failures > 0 {
+            lines.push(format!("--- {:<20} {:>7}", "no response", self.failures));
+        }
+        lines
+    }
+}
+
+/// One request of a run. `status` is `None` for a failure.
+struct Attempt {
+    sent_ms: u64,
+    latency_ms: u64,
+    status: Option<u16>,
+}
+
+/// A latency range, `from_ms` inclusive and `to_ms` exclusive, and how many attempts fell in it.
+#[derive(Clone, Copy, Debug)]
+pub struct Bucket {
+    pub from_ms: u64,
+    pub to_ms: u64,
+    pub count: u64,
+}
+
+/// Splits the range of `latencies_ms` into `buckets` equal ranges, at least a millisecond wide.
+pub fn histogram(latencies_ms: &[u64], buckets: usize) -> Vec<Bucket> {
+    let (Some(min), Some(max)) = (latencies_ms.iter().min(), latencies_ms.iter().max()) else {
+        return vec![];
+    };
+    let width = ((max - min) / buckets as u64 + 1).max(1);
+    let mut histogram: Vec<Bucket> = (0..buckets as u64)
+        .map(|i| Bucket { from_ms: min + i * width, to_ms: min + (i + 1) * width, count: 0 })
+        .collect();
+    for latency in latencies_ms {
+        histogram[((latency - min) / width) as usize].count += 1;
+    }
+    histogram
+}
+
+/// Mean latency over `points` consecutive stretches of the attempts, for a sparkline.
+pub fn over_time(latencies_ms: &[u64], points: usize) -> Vec<u64> {
+    if latencies_ms.is_empty() || points == 0 {
+        return vec![];
+    }
+    let chunk = latencies_ms.len().div_ceil(points);
+    latencies_ms.chunks(chunk).map(|chunk| chunk.iter().sum::<u64>() / chunk.len() as u64).collect()
+}
+
+#[derive(Clone, Copy)]
+struct Timeouts {
+    connect: Duration,
+    read: Duration,
+}
+
+#[allow(clippy::too_many_arguments)]
+fn work(
+    request: &ResolvedRequest,
+    dns: &DnsCache,
+    preference: AddressPreference,
+    timeouts: Timeouts,
+    deadline: Option<Instant>,
+    stop: &Arc<AtomicBool>,
+    remaining: &AtomicUsize,
+    samples: &Sender<Sample>,
+) {
+    if request.scheme == "https" {
+        let _ = samples.send(Sample::Broken(url::NO_TLS.to_string()));
+        return;
+    }
+    let address = match dns.resolve(request.peer(), preference).map(|resolved| resolved.addresses.first().copied()) {
+        Ok(Some(address)) => address,
+        Ok(None) => {
+            let _ = samples.send(Sample::Broken(format!("No address to send to for {}", request.peer())));
+            return;
+        }
+        Err(e) => {
+            let _ = samples.send(Sample::Broken(e.to_string()));
+            return;
+        }
+    };
+    while !stop.load(Ordering::Relaxed) && deadline.is_none_or(|deadline| Instant::now() < deadline) {
+        if remaining.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |left| left.checked_sub(1)).is_err() {
+            return;
+        }
+        let sent = Instant::now();
+        let result = send(request, address, timeouts, stop);
+        let latency_ms = sent.elapsed().as_millis() as u64;
+        // A request given up on by the stop isn't a failure of the server.
+        if stop.load(Ordering::Relaxed) {
+            return;
+        }
+        let sample = match result {
+            Ok(status) => Sample::Response { sent, status, latency_ms },
+            Err(_) => Sample::Failed { sent, latency_ms },
+        };
+        if samples.send(sample).is_err() {
+            return;
+        }
+    }
+}
+
+/// Sends `request` on a connection of its own and reads the response, giving its status.
+fn send(request: &ResolvedRequest, address: SocketAddr, timeouts: Timeouts, stop: &Arc<AtomicBool>) -> Result<u16, WireError> {
+    let stream = TcpStream::connect_timeout(&address, timeouts.connect)?;
+    let limits = Limits { idle: Some(timeouts.read), cancel: stop.clone(), ..Limits::default() };
+    let mut connection = Connection::new(stream, limits);
+    connection.write_head(request, false)?;
+    connection.write_body(request.body.as_bytes(), &mut |_| {})?;
+    loop {
+        let head = connection.read_head(None)?.ok_or(WireError::Closed(Closed::Unanswered))?;
+        if head.status >= 200 {
+            connection.read_body(&request.method, &head, &mut |_| {})?;
+            return Ok(head.status);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::{Read, Write}, net::TcpListener};
+
+    use super::*;
+
+    /// A server answering every connection with `reply`, or never when it's `None`.
+    fn server(reply: Option<&'static [u8]>) -> ResolvedRequest {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                thread::spawn(move || {
+                    let mut head = [0u8; 1024];
+                    let _ = stream.read(&mut head);
+                    match reply {
+                        Some(reply) => {
+                            let _ = stream.write_all(reply);
+                        }
+                        None => thread::sleep(Duration::from_secs(10)),
+                    }
+                });
+            }
+        });
+        ResolvedRequest {
+            method: "GET".to_string(),
+            scheme: "http".to_string(),
+            address: addr.clone(),
+            dial_address: addr.clone(),
+            connect_to: None,
+            endpoint: "/".to_string(),
+            headers: vec![("Host".to_string(), addr)],
+            body: String::new(),
+            host_defaults: vec![],
+        }
+    }
+
+    fn finish(run: &mut Run, within: Duration) {
+        let started = Instant::now();
+        while !run.is_finished() {
+            assert!(started.elapsed() < within, "still running after {:?}", within);
+            thread::sleep(Duration::from_millis(10));
+            run.collect();
+        }
+    }
+
+    #[test]
+    fn counts_the_statuses_the_server_sends() {
+        let request = server(Some(b"HTTP/1.1 503 Service Unavailable\r\nContent-Length: 4\r\n\r\nbusy"));
+        let plan = Plan { concurrency: 2, limit: Limit::Count(5) };
+        let mut run = Run::start(plan, request, Arc::default(), AddressPreference::AsResolved, None, None);
+        finish(&mut run, Duration::from_secs(5));
+        assert_eq!(run.completed(), 5);
+        assert_eq!(run.statuses, BTreeMap::from([(503, 5)]));
+        assert_eq!(run.error_rate(), 1.0);
+        assert!(run.summary().iter().any(|line| line.starts_with("503 Service Unavailable")), "{:?}", run.summary());
+    }
+
+    #[test]
+    fn a_stalled_server_is_a_read_timeout() {
+        let plan = Plan { concurrency: 1, limit: Limit::Count(1) };
+        let mut run = Run::start(plan, server(None), Arc::default(), AddressPreference::AsResolved, None, Some(Duration::from_millis(100)));
+        finish(&mut run, Duration::from_secs(5));
+        assert_eq!((run.completed(), run.failures), (1, 1));
+    }
+
+    #[test]
+    fn stopping_ends_workers_waiting_on_a_stalled_server() {
+        let plan = Plan { concurrency: 3, limit: Limit::Duration(Duration::from_secs(60)) };
+        let mut run = Run::start(plan, server(None), Arc::default(), AddressPreference::AsResolved, None, None);
+        thread::sleep(Duration::from_millis(200));
+        run.stop();
+        finish(&mut run, Duration::from_secs(2));
+        assert_eq!(run.completed(), 0, "requests given up on were counted");
+    }
+}
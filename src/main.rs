@@ -1,22 +1,131 @@
 mod tui;
 mod my_test_server;
+mod action;
+mod app_error;
+mod baseline;
+mod charset;
+mod clipboard;
+mod collection;
+mod conditional;
+mod config;
+mod cors;
+mod curl;
+mod digest;
+mod dotenv;
+mod editor;
+mod encoding;
+mod environment;
+mod exchange;
+mod expect;
+mod focus;
+mod fuzzy;
+mod history;
+mod hooks;
+mod host_rules;
+mod html;
+mod insomnia;
+mod json_tree;
+mod jwt;
+mod keymap;
+mod kv;
+mod load;
+mod markdown;
+mod mock;
+mod net;
+mod plaintext;
+mod postman;
+mod rate_limit;
+mod redirect;
+mod request;
+mod request_log;
+mod runner;
+mod schema;
+mod search;
+mod secrets;
+mod session;
+mod settings;
+mod snippet;
+mod stats;
+mod status;
+mod suspend;
+mod template;
+mod theme;
+mod title;
+mod url;
+mod vault;
+mod wire;
+mod worker;
+mod workspace;
+mod xml;
+mod yaml;
 
-use color_eyre::Result;
+use std::{env, fs, io, process};
+
+use color_eyre::{eyre::eyre, Result};
 use my_test_server::setup_my_server;
-use rust_http::client::HttpClient;
+use ratatui::crossterm::{event::{DisableBracketedPaste, EnableBracketedPaste}, execute};
 use tui::App;
 
 
 fn main() -> Result<()> {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if let Some(name) = option(&args, "--workspace") {
+        workspace::open(&name).map_err(|e| eyre!(e))?;
+    }
+
     let server_addr ="127.0.0.1:8004".to_string();
-    setup_my_server(&server_addr)?;
+    // The app reports a broken collection once it's up; the server just starts without mocks.
+    let mocks = collection::load(&config::collection_path()).map(|collection| collection.mocks).unwrap_or_default();
+    setup_my_server(&server_addr, &mocks)?;
 
-    let client_addr = "127.0.0.1:8005".to_string();
-    let client = HttpClient::new(&client_addr)?;
+    if let Some(path) = option(&args, "--run") {
+        return run_headless(&args, &path, App::headless(server_addr));
+    }
 
     color_eyre::install()?;
     let terminal = ratatui::init();
-    let app_result = App::new(client, server_addr).run(terminal);
+    execute!(io::stdout(), EnableBracketedPaste)?;
+    let fresh = args.iter().any(|arg| arg == "--fresh");
+    let app_result = App::new(server_addr, !fresh).run(terminal);
+    execute!(io::stdout(), DisableBracketedPaste)?;
     ratatui::restore();
     app_result
+}
+
+/// The value of `--name value` or `--name=value`.
+fn option(args: &[String], name: &str) -> Option<String> {
+    let prefix = format!("{}=", name);
+    args.iter()
+        .position(|arg| arg == name)
+        .map(|i| args.get(i + 1).cloned().unwrap_or_default())
+        .or_else(|| args.iter().find_map(|arg| arg.strip_prefix(prefix.as_str()).map(str::to_string)))
+}
+
+/// `--run folder/subfolder` runs that folder of the collection without the UI, `/` or an empty
+/// path the whole collection, in the environment named by `--env` if any. `--report junit`
+/// writes JUnit XML to `--report-file`, or to stdout; otherwise a line per request is printed.
+/// Exits with 1 unless every request passed.
+fn run_headless(args: &[String], path: &str, app: App) -> Result<()> {
+    let folder: Vec<String> = path.split('/').map(str::trim).filter(|part| !part.is_empty()).map(str::to_string).collect();
+    let report = option(args, "--report");
+    if report.as_deref().is_some_and(|format| format != "junit") {
+        return Err(eyre!("unknown report format {:?}; only junit is supported", report.unwrap_or_default()));
+    }
+    let (run, bodies) = app.run_headless(folder, option(args, "--env").as_deref()).map_err(|e| eyre!(e))?;
+    match (report, option(args, "--report-file")) {
+        (Some(_), Some(file)) => fs::write(&file, run.junit(&bodies)).map_err(|e| eyre!("Couldn't write {}: {}", file, e))?,
+        (Some(_), None) => print!("{}", run.junit(&bodies)),
+        (None, _) => {
+            for leg in &run.legs {
+                let status = leg.status.map_or(String::new(), |status| format!(" {}", status));
+                let latency = leg.latency_ms.map_or(String::new(), |ms| format!(" {} ms", ms));
+                println!("{:<8} {}{}{}", leg.outcome.label(), run.leg_label(leg), status, latency);
+                for problem in &leg.problems {
+                    println!("         {}", problem);
+                }
+            }
+        }
+    }
+    eprintln!("{}: {}", run.label(), run.summary());
+    process::exit(if run.passed() {0} else {1});
 }
\ No newline at end of file
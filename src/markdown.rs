@@ -0,0 +1,102 @@
+use crate::{
+    history::HistoryEntry,
+    request::parse_headers,
+    secrets, status,
+};
+
+/// One exchange as a Markdown section: request and response each as a fenced HTTP message,
+/// then timing. The saved request's description, if it was sent from one, goes under the
+/// heading. Credential headers are redacted unless `include_secrets`.
+pub fn exchange(entry: &HistoryEntry, description: Option<&str>, include_secrets: bool) -> String {
+    let mut out = format!("## {} {}\n\n", entry.request.method, entry.request.url);
+    if let Some(description) = description {
+        out.push_str(description.trim_end());
+        out.push_str("\n\n");
+    }
+    out.push_str(&format!("- Sent: {}\n", entry.timestamp));
+    if let Some(timing) = entry.timing {
+        out.push_str(&format!("- Time: {} ms\n", timing.latency_ms));
+        match (timing.dns_ms, timing.connect_ms, timing.ttfb_ms, timing.download_ms, timing.exchange_ms) {
+            (Some(dns), Some(connect), Some(ttfb), Some(download), _) => {
+                out.push_str(&format!("- DNS: {} ms, connect: {} ms, TTFB: {} ms, download: {} ms\n", dns, connect, ttfb, download));
+            }
+            (Some(dns), _, _, _, Some(exchange)) => out.push_str(&format!("- DNS: {} ms, connect to last byte: {} ms\n", dns, exchange)),
+            _ => {}
+        }
+        if let (Some(sent), Some(received)) = (timing.sent_bytes, timing.received_bytes) {
+            out.push_str(&format!("- Bytes: {} sent, {} received\n", sent, received));
+        }
+    }
+    if let Some(peer) = &entry.peer {
+        out.push_str(&format!("- Peer: {}{}\n", peer, if entry.overridden {" (connect-to override)"} else {""}));
+    }
+
+    let mut headers = parse_headers(&entry.request.headers);
+    if !include_secrets {
+        headers = secrets::redact_headers(&headers);
+    }
+    out.push_str("\n### Request\n\n");
+    out.push_str(&message(&format!("{} {} HTTP/1.1", entry.request.method, entry.request.url), &headers, &entry.request.body));
+
+    out.push_str("\n### Response\n\n");
+    match (&entry.response, &entry.error) {
+        (Some(response), _) => {
+            let status = match response.status {
+                Some(code) => format!("HTTP/1.1 {} {}", code, status::reason(code).unwrap_or(&response.reason)),
+                None => format!("HTTP/1.1 {}", response.reason),
+            };
+            let headers = if include_secrets {response.headers.clone()} else {secrets::redact_headers(&response.headers)};
+            out.push_str(&message(&status, &headers, &response.body));
+        }
+        (None, Some(error)) => out.push_str(&format!("No response: {}\n", error)),
+        (None, None) => out.push_str("No response yet.\n"),
+    }
+    out
+}
+
+/// Several exchanges as one document, oldest first, with a table of contents.
+pub fn report(entries: &[(&HistoryEntry, Option<&str>)], include_secrets: bool) -> String {
+    let mut out = String::from("# HTTP exchanges\n\n");
+    for (i, (entry, _)) in entries.iter().enumerate() {
+        let heading = format!("{} {}", entry.request.method, entry.request.url);
+        out.push_str(&format!("{}. [{}](#{}) — {}\n", i + 1, heading, anchor(&heading), entry.timestamp));
+    }
+    let timings = entries.iter().filter_map(|(entry, _)| entry.timing);
+    let (sent, received) = timings.fold((0, 0), |(sent, received), timing| {
+        (sent + timing.sent_bytes.unwrap_or_default(), received + timing.received_bytes.unwrap_or_default())
+    });
+    out.push_str(&format!("\nBytes in all: {} sent, {} received\n", sent, received));
+    for (entry, description) in entries {
+        out.push('\n');
+        out.push_str(&exchange(entry, *description, include_secrets));
+    }
+    out
+}
+
+/// A start line, headers and body in a fence long enough that the body can't close it.
+fn message(start_line: &str, headers: &[(String, String)], body: &str) -> String {
+    let mut text = start_line.to_string();
+    for (name, value) in headers {
+        text.push_str(&format!("\n{}: {}", name, value));
+    }
+    if !body.is_empty() {
+        text.push_str("\n\n");
+        text.push_str(body.trim_end());
+    }
+    let longest_run = text.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    let fence = "`".repeat(longest_run.max(2) + 1);
+    format!("{}http\n{}\n{}\n", fence, text, fence)
+}
+
+/// The anchor GitHub generates for a heading.
+fn anchor(heading: &str) -> String {
+    heading
+        .to_lowercase()
+        .chars()
+        .filter_map(|c| match c {
+            ' ' => Some('-'),
+            c if c.is_alphanumeric() || c == '-' || c == '_' => Some(c),
+            _ => None,
+        })
+        .collect()
+}
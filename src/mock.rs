@@ -0,0 +1,111 @@
+use std::sync::Mutex;
+
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+
+/// A canned response the built-in test server gives for one method and path.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct Mock {
+    pub method: String,
+    pub path: String,
+    pub status: u16,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub headers: Vec<(String, String)>,
+    #[serde(default)]
+    pub body: String,
+}
+
+/// Paths the test server answers itself.
+const BUILT_IN: [&str; 2] = ["/", "/echo"];
+
+/// The server's front looks mocks up here, so edits show up on the next hit.
+static MOCKS: Mutex<Vec<Mock>> = Mutex::new(vec![]);
+
+/// Hits not yet shown in the console.
+static HITS: Mutex<Vec<String>> = Mutex::new(vec![]);
+
+/// Replaces the mocks the server answers with.
+pub fn set(mocks: &[Mock]) {
+    *MOCKS.lock().unwrap_or_else(|e| e.into_inner()) = mocks.to_vec();
+}
+
+/// Console lines for hits since the last call.
+pub fn take_hits() -> Vec<String> {
+    std::mem::take(&mut *HITS.lock().unwrap_or_else(|e| e.into_inner()))
+}
+
+/// The mock for a `method` request to `target`, noting the hit for the console.
+pub fn find(method: &str, target: &str) -> Option<Mock> {
+    let path = target.split('?').next().unwrap_or_default();
+    let found = MOCKS.lock().unwrap_or_else(|e| e.into_inner()).iter().find(|mock| mock.method.eq_ignore_ascii_case(method) && mock.path == path).cloned()?;
+    let line = format!("mock hit: {} {} -> {}", method, target, found.status);
+    HITS.lock().unwrap_or_else(|e| e.into_inner()).push(format!("[{}] {}", Local::now().format("%H:%M:%S%.3f"), line));
+    Some(found)
+}
+
+/// A mock as edited in the UI: `METHOD /path STATUS`, then headers, a blank line and the body.
+pub fn to_text(mock: &Mock) -> String {
+    let mut text = format!("{} {} {}", mock.method, mock.path, mock.status);
+    for (name, value) in &mock.headers {
+        text.push_str(&format!("\n{}: {}", name, value));
+    }
+    text.push_str("\n\n");
+    text.push_str(&mock.body);
+    text
+}
+
+pub fn parse(text: &str) -> Result<Mock, String> {
+    let (head, body) = text.split_once("\n\n").unwrap_or((text, ""));
+    let mut lines = head.lines();
+    let first = lines.next().unwrap_or_default();
+    let [method, path, status] = first.split_whitespace().collect::<Vec<_>>()[..] else {
+        return Err("The first line should be METHOD /path STATUS, e.g. GET /users 200".to_string());
+    };
+    let method = method.to_uppercase();
+    if !method.bytes().all(|byte| byte.is_ascii_uppercase() || byte == b'-') {
+        return Err(format!("Not a method: {}", method));
+    }
+    if !path.starts_with('/') || path.contains('?') {
+        return Err(format!("A path starts with / and has no query: {}", path));
+    }
+    if BUILT_IN.contains(&path) {
+        return Err(format!("{} is served by the test server itself", path));
+    }
+    let status = match status.parse() {
+        Ok(status @ 200..=599) => status,
+        _ => return Err(format!("A mock answers with a status from 200 to 599, not {}", status)),
+    };
+    let mut headers = vec![];
+    for line in lines.filter(|line| !line.trim().is_empty()) {
+        let Some((name, value)) = line.split_once(':') else {
+            return Err(format!("Expected Name: value: {}", line));
+        };
+        headers.push((name.trim().to_string(), value.trim().to_string()));
+    }
+    Ok(Mock { method, path: path.to_string(), status, headers, body: body.to_string() })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn any_method_and_status_parses() {
+        let mock = parse("delete /users/1 204").unwrap();
+        assert_eq!((mock.method.as_str(), mock.status), ("DELETE", 204));
+        assert_eq!(parse("PATCH /users 422\nContent-Type: application/json\n\n{}").unwrap().status, 422);
+        assert_eq!(parse("PROPFIND /files 503").unwrap().method, "PROPFIND");
+        let round_trip = parse("PUT /users 201\nLocation: /users/2\n\ncreated").unwrap();
+        assert_eq!(parse(&to_text(&round_trip)).unwrap(), round_trip);
+    }
+
+    #[test]
+    fn bad_mocks_say_what_is_wrong() {
+        assert_eq!(parse("GET /users 600").unwrap_err(), "A mock answers with a status from 200 to 599, not 600");
+        assert_eq!(parse("GET /users 101").unwrap_err(), "A mock answers with a status from 200 to 599, not 101");
+        assert_eq!(parse("GET /users ok").unwrap_err(), "A mock answers with a status from 200 to 599, not ok");
+        assert_eq!(parse("G=T /users 200").unwrap_err(), "Not a method: G=T");
+        assert_eq!(parse("GET /echo 200").unwrap_err(), "/echo is served by the test server itself");
+        assert_eq!(parse("GET /users?id=1 200").unwrap_err(), "A path starts with / and has no query: /users?id=1");
+    }
+}
@@ -1,7 +1,236 @@
-use std::io::Error;
+use std::{
+    io::{self, BufRead, BufReader, ErrorKind, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::{mpsc::{self, Receiver, Sender}, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+use chrono::Local;
+use rand::Rng;
 
 use rust_http::{endpoints::{get, post}, http::{HttpRequest, HttpResponse}, response_codes::ResponseCode, server::Router};
 
+use crate::{
+    config::TestServerConfig,
+    mock::{self, Mock},
+    status,
+    wire::{Connection, Head, Limits},
+};
+
+/// How long a stalled request is held before it's answered after all, unless the client hangs
+/// up first.
+const STALL: Duration = Duration::from_secs(120);
+
+/// How long the router may take over a request passed on to it.
+const ROUTER_TIMEOUT: Duration = Duration::from_secs(30);
+
+const INJECTED_FAILURE: &str = "{\"error\": \"injected failure\"}";
+
+/// A request as the server received it.
+pub struct Received {
+    /// `HH:MM:SS.mmm`, local time.
+    pub timestamp: String,
+    pub method: String,
+    pub path: String,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+/// Where the front reports what it receives, once the app listens.
+static TRAFFIC: Mutex<Option<Sender<Received>>> = Mutex::new(None);
+
+/// Every request the server receives from now on.
+pub fn traffic() -> Receiver<Received> {
+    let (sender, receiver) = mpsc::channel();
+    *TRAFFIC.lock().unwrap_or_else(|e| e.into_inner()) = Some(sender);
+    receiver
+}
+
+/// Misbehaviour asked for from the app, before any query parameters of a request.
+static FAULTS: Mutex<TestServerConfig> = Mutex::new(TestServerConfig { delay_ms: 0, fail_percent: 0, stall: false, stall_body: false, cut_body: false });
+
+pub fn set_faults(faults: TestServerConfig) {
+    *FAULTS.lock().unwrap_or_else(|e| e.into_inner()) = faults;
+}
+
+pub fn faults() -> TestServerConfig {
+    *FAULTS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// `faults` with the `delay_ms`, `fail`, `stall`, `stall_body` and `cut` query parameters of
+/// `endpoint` applied.
+pub fn requested(mut faults: TestServerConfig, endpoint: &str) -> TestServerConfig {
+    let on = |value: &str| value != "0" && value != "false";
+    let query = endpoint.split_once('?').map_or("", |(_, query)| query);
+    for (name, value) in query.split('&').filter_map(|pair| pair.split_once('=')) {
+        match name {
+            "delay_ms" => faults.delay_ms = value.parse().unwrap_or(faults.delay_ms),
+            "fail" => faults.fail_percent = value.parse().unwrap_or(faults.fail_percent),
+            "stall" => faults.stall = on(value),
+            "stall_body" => faults.stall_body = on(value),
+            "cut" => faults.cut_body = on(value),
+            _ => {}
+        }
+    }
+    faults
+}
+
+/// Whether a request is picked to fail, `percent` times in a hundred.
+pub fn picked_to_fail(percent: u8) -> bool {
+    rand::thread_rng().gen_range(0..100) < percent
+}
+
+/// Where the router listens: the port after the server's own, behind the front.
+fn router_addr(server_addr: &str) -> String {
+    match server_addr.parse::<SocketAddr>() {
+        Ok(mut addr) => {
+            addr.set_port(addr.port().wrapping_add(1));
+            addr.to_string()
+        }
+        Err(_) => server_addr.to_string(),
+    }
+}
+
+/// Takes the server's connections on `listener`, reports each request to the app and passes
+/// it on to the router at `router`, misbehaving on the way as the faults ask. The router only
+/// takes whole responses, so delays, 500s, stalls and bodies cut short all happen here, and
+/// mocks are answered here too, with any method and status.
+pub fn front(listener: TcpListener, router: String) {
+    thread::spawn(move || {
+        for client in listener.incoming().flatten() {
+            let router = router.clone();
+            thread::spawn(move || {
+                let _ = pass_on(client, &router);
+            });
+        }
+    });
+}
+
+fn pass_on(client: TcpStream, router: &str) -> io::Result<()> {
+    let mut client = BufReader::new(client);
+    let (head, body) = read_request(&mut client)?;
+    let mut client = client.into_inner();
+    let mut lines = head.lines();
+    let request_line = lines.next().unwrap_or_default();
+    let (method, target) = request_line.split_once(' ').map(|(method, rest)| (method, rest.split(' ').next().unwrap_or_default())).unwrap_or_default();
+    let headers: Vec<(String, String)> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+        .collect();
+    record(Received {
+        timestamp: Local::now().format("%H:%M:%S%.3f").to_string(),
+        method: method.to_string(),
+        path: target.to_string(),
+        headers: headers.clone(),
+        body: String::from_utf8_lossy(&body).into_owned(),
+    });
+
+    let faults = requested(faults(), target);
+    if faults.stall && !hold(&mut client, STALL) {
+        return Ok(());
+    }
+    thread::sleep(Duration::from_millis(faults.delay_ms));
+    if picked_to_fail(faults.fail_percent) {
+        let failure = Head {
+            status: 500,
+            reason: "Internal Server Error".to_string(),
+            headers: vec![("Content-Type".to_string(), "application/json".to_string()), ("X-Injected-Failure".to_string(), "true".to_string())],
+            keep_alive: false,
+        };
+        client.write_all(&response_head(&failure, INJECTED_FAILURE.len()))?;
+        return client.write_all(INJECTED_FAILURE.as_bytes());
+    }
+
+    if let Some(mock) = mock::find(method, target) {
+        let head = Head { status: mock.status, reason: status::reason(mock.status).unwrap_or_default().to_string(), headers: mock.headers, keep_alive: false };
+        client.write_all(&response_head(&head, mock.body.len()))?;
+        return client.write_all(mock.body.as_bytes());
+    }
+
+    let mut upstream = TcpStream::connect(router)?;
+    let forwarded: String = head.lines().filter(|line| !line.to_ascii_lowercase().starts_with("expect:")).map(|line| format!("{}\r\n", line)).collect();
+    upstream.write_all(forwarded.as_bytes())?;
+    upstream.write_all(b"\r\n")?;
+    upstream.write_all(&body)?;
+    let limits = Limits { idle: Some(ROUTER_TIMEOUT), ..Limits::default() };
+    let mut connection = Connection::new(upstream, limits);
+    let (head, body) = loop {
+        let head = connection.read_head(None).map_err(io::Error::other)?.ok_or(ErrorKind::UnexpectedEof)?;
+        if head.status >= 200 {
+            let body = connection.read_body(method, &head, &mut |_| {}).map_err(io::Error::other)?;
+            break (head, body.bytes);
+        }
+    };
+    client.write_all(&response_head(&head, body.len()))?;
+    if !faults.stall_body && !faults.cut_body {
+        return client.write_all(&body);
+    }
+    // Half the body, then either nothing more for a while or the connection closed.
+    client.write_all(&body[..body.len() / 2])?;
+    if faults.stall_body {
+        hold(&mut client, STALL);
+    }
+    Ok(())
+}
+
+/// A request's head, up to its blank line, and its body, sent on as they came. A request
+/// holding its body back for `100 Continue` gets it.
+fn read_request(client: &mut BufReader<TcpStream>) -> io::Result<(String, Vec<u8>)> {
+    let mut head = String::new();
+    loop {
+        let mut line = String::new();
+        if client.read_line(&mut line)? == 0 {
+            return Err(ErrorKind::UnexpectedEof.into());
+        }
+        if line.trim_end().is_empty() {
+            break;
+        }
+        head.push_str(line.trim_end());
+        head.push('\n');
+    }
+    let header = |name: &str| head.lines().filter_map(|line| line.split_once(':')).find(|(key, _)| key.trim().eq_ignore_ascii_case(name)).map(|(_, value)| value.trim().to_string());
+    let length: usize = header("Content-Length").and_then(|length| length.parse().ok()).unwrap_or(0);
+    if length > 0 && header("Expect").is_some_and(|expect| expect.eq_ignore_ascii_case("100-continue")) {
+        client.get_mut().write_all(b"HTTP/1.1 100 Continue\r\n\r\n")?;
+    }
+    let mut body = vec![0; length];
+    client.read_exact(&mut body)?;
+    Ok((head, body))
+}
+
+/// The status line and headers of `head`, framed anew for a body of `length` bytes on a
+/// connection that closes after it.
+fn response_head(head: &Head, length: usize) -> Vec<u8> {
+    let mut out = format!("HTTP/1.1 {} {}\r\n", head.status, head.reason);
+    for (name, value) in head.headers.iter().filter(|(name, _)| !["content-length", "transfer-encoding", "connection"].contains(&name.to_ascii_lowercase().as_str())) {
+        out.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    out.push_str(&format!("Content-Length: {}\r\nConnection: close\r\n\r\n", length));
+    out.into_bytes()
+}
+
+/// Keeps `client` waiting for up to `time`. False when it hung up meanwhile, so the request
+/// needn't be answered.
+fn hold(client: &mut TcpStream, time: Duration) -> bool {
+    let until = Instant::now() + time;
+    let _ = client.set_read_timeout(Some(Duration::from_millis(100)));
+    let mut byte = [0u8; 1];
+    while Instant::now() < until {
+        match client.read(&mut byte) {
+            Ok(0) => return false,
+            Err(e) if !matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut | ErrorKind::Interrupted) => return false,
+            _ => {}
+        }
+    }
+    true
+}
+
+fn record(received: Received) {
+    if let Some(sender) = TRAFFIC.lock().unwrap_or_else(|e| e.into_inner()).as_ref() {
+        let _ = sender.send(received);
+    }
+}
 
 fn process(_: HttpRequest) -> HttpResponse {
     HttpResponse {
@@ -20,11 +249,182 @@ fn echo(req: HttpRequest) -> HttpResponse {
 }
 
 
-pub fn setup_my_server(server_addr: &String) -> Result<(), Error> {
-    let router = Router::new(&server_addr)?
+pub fn setup_my_server(server_addr: &String, mocks: &[Mock]) -> io::Result<()> {
+    let listener = TcpListener::bind(server_addr)?;
+    let router = Router::new(&router_addr(server_addr))?
         .add_endpoint("/".to_string(), get(process))
         .add_endpoint("/echo".to_string(), post(echo));
+    mock::set(mocks);
 
     router.server_loop()?; // goes off and spawns a thread running the server in the backgrond
+    front(listener, router_addr(server_addr));
     Ok(())
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        app_error::{AppError, ErrorKind as AppErrorKind},
+        history::{ResponseRecord, Timing},
+        net::AddressPreference,
+        redirect::Policy,
+        request::{header_value, ResolvedRequest},
+        worker::{Job, Transport, Worker, WorkerEvent},
+    };
+
+    const BODY: &str = "{\"Hello,\": \" World!\"}";
+
+    /// The server as the app talks to it: the front, with a stand-in for the router behind it
+    /// answering every request with `BODY`.
+    fn server() -> String {
+        let router = TcpListener::bind("127.0.0.1:0").unwrap();
+        let router_addr = router.local_addr().unwrap().to_string();
+        thread::spawn(move || {
+            for stream in router.incoming() {
+                let mut stream = BufReader::new(stream.unwrap());
+                let _ = read_request(&mut stream);
+                let reply = format!("HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nTransfer-Encoding: chunked\r\n\r\n{:x}\r\n{}\r\n0\r\n\r\n", BODY.len(), BODY);
+                let _ = stream.get_mut().write_all(reply.as_bytes());
+            }
+        });
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        front(listener, router_addr);
+        addr
+    }
+
+    /// Sends `GET endpoint` to `server` through a worker, as the app does, returning the result
+    /// and its timing.
+    fn send(server: &str, endpoint: &str, read_timeout: Option<Duration>, deadline: Option<Duration>) -> (Result<Box<ResponseRecord>, AppError>, Box<Timing>) {
+        send_as("GET", server, endpoint, read_timeout, deadline)
+    }
+
+    fn send_as(method: &str, server: &str, endpoint: &str, read_timeout: Option<Duration>, deadline: Option<Duration>) -> (Result<Box<ResponseRecord>, AppError>, Box<Timing>) {
+        let worker = Worker::spawn(None);
+        let request = ResolvedRequest {
+            method: method.to_string(),
+            scheme: "http".to_string(),
+            address: server.to_string(),
+            dial_address: server.to_string(),
+            connect_to: None,
+            endpoint: endpoint.to_string(),
+            headers: vec![("Host".to_string(), server.to_string())],
+            body: String::new(),
+            host_defaults: vec![],
+        };
+        let transport = Transport { preference: AddressPreference::AsResolved, expect: None, connect_timeout: None, connect_budget: None, read_timeout, deadline, max_body: None };
+        worker.submit(Job::Send { id: 1, request: Box::new(request), redirects: Policy::default(), transport });
+        loop {
+            if let WorkerEvent::Response { result, timing, .. } = worker.events.recv_timeout(Duration::from_secs(10)).unwrap() {
+                return (result, timing);
+            }
+        }
+    }
+
+    #[test]
+    fn query_parameters_override_the_configured_faults() {
+        let configured = TestServerConfig { delay_ms: 50, fail_percent: 10, stall: true, ..TestServerConfig::default() };
+        let faults = requested(configured, "/echo?delay_ms=200&fail=100&stall=0&stall_body=1&cut=true&other=1");
+        assert_eq!(faults, TestServerConfig { delay_ms: 200, fail_percent: 100, stall: false, stall_body: true, cut_body: true });
+        assert_eq!(requested(configured, "/echo"), configured);
+        assert_eq!(requested(configured, "/echo?delay_ms=soon&fail"), configured);
+    }
+
+    #[test]
+    fn the_share_failing_follows_the_percentage() {
+        assert!((0..200).all(|_| picked_to_fail(100)));
+        assert!((0..200).all(|_| !picked_to_fail(0)));
+        let failed = (0..2000).filter(|_| picked_to_fail(50)).count();
+        assert!((800..1200).contains(&failed), "{} of 2000 failed", failed);
+    }
+
+    #[test]
+    fn passes_requests_on_to_the_router() {
+        let (result, _) = send(&server(), "/", None, None);
+        let response = result.unwrap();
+        assert_eq!((response.status, response.body.as_str()), (Some(200), BODY));
+        assert_eq!(header_value(&response.headers, "Content-Type"), Some("application/json"));
+    }
+
+    #[test]
+    fn mocks_answer_any_method_with_any_status() {
+        let teapot = Mock { method: "DELETE".to_string(), path: "/mocked-teapot".to_string(), status: 418, headers: vec![("X-Mock".to_string(), "yes".to_string())], body: "short and stout".to_string() };
+        let gone = Mock { method: "PATCH".to_string(), path: "/mocked-gone".to_string(), status: 503, headers: vec![], body: String::new() };
+        mock::set(&[teapot, gone]);
+        let server = server();
+        let response = send_as("DELETE", &server, "/mocked-teapot?id=1", None, None).0.unwrap();
+        assert_eq!((response.status, response.body.as_str()), (Some(418), "short and stout"));
+        assert_eq!(header_value(&response.headers, "X-Mock"), Some("yes"));
+        assert_eq!(send_as("PATCH", &server, "/mocked-gone", None, None).0.unwrap().status, Some(503));
+        assert_eq!(send_as("GET", &server, "/mocked-teapot", None, None).0.unwrap().body, BODY, "other methods go to the router");
+        assert!(mock::take_hits().iter().any(|hit| hit.ends_with("mock hit: DELETE /mocked-teapot?id=1 -> 418")));
+    }
+
+    #[test]
+    fn a_delayed_answer_still_arrives_and_the_wait_is_timed() {
+        let (result, timing) = send(&server(), "/?delay_ms=200", Some(Duration::from_secs(2)), None);
+        assert_eq!(result.unwrap().body, BODY);
+        assert!(timing.latency_ms >= 200, "latency {} ms", timing.latency_ms);
+        assert!(timing.ttfb_ms.unwrap() >= 200, "TTFB {} ms", timing.ttfb_ms.unwrap());
+    }
+
+    #[test]
+    fn a_delay_past_the_read_timeout_fails_the_send() {
+        let server = server();
+        let (result, _) = send(&server, "/?delay_ms=500", Some(Duration::from_millis(100)), None);
+        let error = result.unwrap_err();
+        assert_eq!(error.summary, format!("Request to {} failed: Read timeout: nothing from the server for 100 ms", server));
+        assert_eq!(error.kind, AppErrorKind::Network);
+        assert!(error.hint.unwrap().contains("read_timeout_secs"));
+    }
+
+    #[test]
+    fn injected_failures_are_500s_in_the_share_asked_for() {
+        let server = server();
+        let always = send(&server, "/?fail=100", None, None).0.unwrap();
+        assert_eq!((always.status, always.body.as_str()), (Some(500), INJECTED_FAILURE));
+        assert_eq!(header_value(&always.headers, "X-Injected-Failure"), Some("true"));
+        assert_eq!(send(&server, "/?fail=0", None, None).0.unwrap().status, Some(200));
+        let failed = (0..40).filter(|_| send(&server, "/?fail=50", None, None).0.unwrap().status == Some(500)).count();
+        assert!((5..=35).contains(&failed), "{} of 40 failed", failed);
+    }
+
+    #[test]
+    fn a_stall_is_cut_off_by_the_deadline() {
+        let started = Instant::now();
+        let (result, _) = send(&server(), "/?stall=1", Some(Duration::from_secs(30)), Some(Duration::from_secs(1)));
+        let error = result.unwrap_err();
+        assert_eq!(error.summary, "Timed out: no full response within the 1s deadline");
+        assert!(error.hint.unwrap().contains("timeout_secs"));
+        assert!(started.elapsed() < Duration::from_secs(3), "took {:?}", started.elapsed());
+    }
+
+    #[test]
+    fn a_stall_after_the_headers_hits_the_read_timeout() {
+        let server = server();
+        let (result, timing) = send(&server, "/?stall_body=1", Some(Duration::from_millis(200)), None);
+        assert_eq!(result.unwrap_err().summary, format!("Request to {} failed: Read timeout: nothing from the server for 200 ms", server));
+        assert!(timing.ttfb_ms.is_some(), "the head arrived, so there's a TTFB");
+    }
+
+    #[test]
+    fn a_body_cut_short_is_an_error_saying_how_much_arrived() {
+        let server = server();
+        let (result, _) = send(&server, "/?cut=1", Some(Duration::from_secs(2)), None);
+        let expected = format!("Request to {} failed: the connection closed after {} of {} body bytes", server, BODY.len() / 2, BODY.len());
+        let error = result.unwrap_err();
+        assert_eq!((error.summary, error.hint), (expected, None));
+    }
+
+    #[test]
+    fn a_stalled_request_is_let_go_when_the_client_hangs_up() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (mut held, _) = listener.accept().unwrap();
+        drop(client);
+        let started = Instant::now();
+        assert!(!hold(&mut held, STALL));
+        assert!(started.elapsed() < Duration::from_secs(2), "held for {:?}", started.elapsed());
+    }
+}
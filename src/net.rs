@@ -0,0 +1,326 @@
+use std::{
+    collections::BTreeMap,
+    fmt, io,
+    net::{IpAddr, SocketAddr, TcpStream, ToSocketAddrs},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use crate::url::{parse_authority, Host, UrlError};
+
+#[derive(Debug)]
+pub enum ResolveError {
+    Url(UrlError),
+    Lookup { host: String, source: io::Error },
+}
+
+impl fmt::Display for ResolveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ResolveError::Url(e) => write!(f, "{}", e),
+            ResolveError::Lookup { host, .. } => write!(f, "DNS lookup failed for {}", host),
+        }
+    }
+}
+
+impl ResolveError {
+    /// The resolver's own wording, for the console.
+    pub fn detail(&self) -> Option<String> {
+        match self {
+            ResolveError::Url(_) => None,
+            ResolveError::Lookup { source, .. } => Some(source.to_string()),
+        }
+    }
+}
+
+/// How long a failed lookup is remembered at most, so a passing outage recovers quickly.
+const FAILURE_TTL: Duration = Duration::from_secs(5);
+
+/// What each host name looked up to and when, by lowercased name. Failures keep the error's
+/// kind and wording since `io::Error` can't be cloned.
+type Lookups = BTreeMap<String, (Instant, Result<Vec<IpAddr>, (io::ErrorKind, String)>)>;
+
+/// How long looked-up addresses are reused until the config says otherwise.
+const DEFAULT_TTL: Duration = Duration::from_secs(60);
+
+/// Host names and what they looked up to. The worker owns one, which load tests share and
+/// the UI flushes and sets the TTL of.
+pub struct DnsCache {
+    ttl: Mutex<Duration>,
+    lookups: Mutex<Lookups>,
+}
+
+impl Default for DnsCache {
+    fn default() -> Self {
+        Self { ttl: Mutex::new(DEFAULT_TTL), lookups: Mutex::default() }
+    }
+}
+
+/// Which address family to try first when a name resolves to both A and AAAA records.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum AddressPreference {
+    AsResolved,
+    PreferIpv4,
+    PreferIpv6,
+}
+
+impl AddressPreference {
+    pub fn next(self) -> Self {
+        match self {
+            AddressPreference::AsResolved => AddressPreference::PreferIpv4,
+            AddressPreference::PreferIpv4 => AddressPreference::PreferIpv6,
+            AddressPreference::PreferIpv6 => AddressPreference::AsResolved,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            AddressPreference::AsResolved => "as resolved",
+            AddressPreference::PreferIpv4 => "prefer IPv4",
+            AddressPreference::PreferIpv6 => "prefer IPv6",
+        }
+    }
+
+    fn sort(self, addresses: &mut [SocketAddr]) {
+        match self {
+            AddressPreference::AsResolved => {}
+            AddressPreference::PreferIpv4 => addresses.sort_by_key(|addr| addr.is_ipv6()),
+            AddressPreference::PreferIpv6 => addresses.sort_by_key(|addr| addr.is_ipv4()),
+        }
+    }
+}
+
+pub fn family(addr: &SocketAddr) -> &'static str {
+    if addr.is_ipv6() {"IPv6"} else {"IPv4"}
+}
+
+pub struct Resolved {
+    pub addresses: Vec<SocketAddr>,
+    /// Taken from the cache rather than looked up.
+    pub cached: bool,
+}
+
+impl DnsCache {
+    /// Resolves `host:port` into every A and AAAA address, ordered by `preference`. An authority
+    /// without a port can't be resolved here and yields no addresses; the caller dials it as
+    /// written.
+    pub fn resolve(&self, authority: &str, preference: AddressPreference) -> Result<Resolved, ResolveError> {
+        let (host, port) = parse_authority(authority).map_err(ResolveError::Url)?;
+        let Some(port) = port else { return Ok(Resolved { addresses: vec![], cached: false }) };
+        let (mut addresses, cached): (Vec<SocketAddr>, bool) = match host {
+            Host::Ipv4(ip) => (vec![SocketAddr::from((ip, port))], false),
+            Host::Ipv6(ip) => (vec![SocketAddr::from((ip, port))], false),
+            Host::Name(name) => {
+                let look_up = || (name.as_str(), port).to_socket_addrs().map(|addresses| addresses.map(|addr| addr.ip()).collect());
+                let (ips, cached) = self.lookup(&name, Instant::now(), look_up);
+                let ips = ips.map_err(|source| ResolveError::Lookup { host: name.clone(), source })?;
+                (ips.into_iter().map(|ip| SocketAddr::from((ip, port))).collect(), cached)
+            }
+        };
+        preference.sort(&mut addresses);
+        Ok(Resolved { addresses, cached })
+    }
+
+    /// The addresses of a host name as of `now`, from the cache while they're fresh, otherwise
+    /// from `look_up`, and whether they were cached.
+    fn lookup(&self, name: &str, now: Instant, look_up: impl FnOnce() -> io::Result<Vec<IpAddr>>) -> (io::Result<Vec<IpAddr>>, bool) {
+        let ttl = *self.ttl.lock().unwrap_or_else(|e| e.into_inner());
+        let key = name.to_ascii_lowercase();
+        if let Some((at, result)) = self.lookups.lock().unwrap_or_else(|e| e.into_inner()).get(&key) {
+            let lifetime = if result.is_ok() {ttl} else {ttl.min(FAILURE_TTL)};
+            if now.saturating_duration_since(*at) < lifetime {
+                return (result.clone().map_err(|(kind, message)| io::Error::new(kind, message)), true);
+            }
+        }
+        let result = look_up();
+        if !ttl.is_zero() {
+            let mut lookups = self.lookups.lock().unwrap_or_else(|e| e.into_inner());
+            lookups.retain(|_, (at, _)| now.saturating_duration_since(*at) < ttl);
+            lookups.insert(key, (now, result.as_ref().map(Vec::clone).map_err(|e| (e.kind(), e.to_string()))));
+        }
+        (result, false)
+    }
+
+    /// How long looked-up addresses are reused; zero looks every name up each time.
+    pub fn set_ttl(&self, ttl: Duration) {
+        *self.ttl.lock().unwrap_or_else(|e| e.into_inner()) = ttl;
+        if ttl.is_zero() {
+            self.flush();
+        }
+    }
+
+    /// Forgets every lookup, returning how many there were.
+    pub fn flush(&self) -> usize {
+        let mut lookups = self.lookups.lock().unwrap_or_else(|e| e.into_inner());
+        let flushed = lookups.len();
+        lookups.clear();
+        flushed
+    }
+}
+
+/// One stage of a connectivity check: what was tried, how it went and how long it took.
+pub struct Step {
+    pub name: &'static str,
+    pub outcome: Result<String, String>,
+    pub elapsed: Duration,
+}
+
+/// Checks whether `host:port` can be reached without sending a request: a fresh DNS lookup, then a
+/// TCP connect to each address in turn until one accepts. There's no TLS here, so for `https` that
+/// step is reported rather than tried.
+pub fn check_reachable(target: &str, https: bool, preference: AddressPreference, timeout: Duration) -> Vec<Step> {
+    let mut steps = vec![];
+    let started = Instant::now();
+    let (host, port) = match parse_authority(target) {
+        Ok((host, Some(port))) => (host, port),
+        Ok((_, None)) => {
+            steps.push(Step { name: "DNS", outcome: Err(format!("{} has no port to check", target)), elapsed: Duration::ZERO });
+            return steps;
+        }
+        Err(e) => {
+            steps.push(Step { name: "DNS", outcome: Err(e.to_string()), elapsed: Duration::ZERO });
+            return steps;
+        }
+    };
+    let mut addresses: Vec<SocketAddr> = match host {
+        Host::Ipv4(ip) => vec![SocketAddr::from((ip, port))],
+        Host::Ipv6(ip) => vec![SocketAddr::from((ip, port))],
+        // Looked up afresh rather than from the cache, since a stale answer is what's in doubt.
+        Host::Name(name) => match (name.as_str(), port).to_socket_addrs() {
+            Ok(found) => found.collect(),
+            Err(e) => {
+                steps.push(Step { name: "DNS", outcome: Err(format!("{} didn't resolve: {}", name, e)), elapsed: started.elapsed() });
+                return steps;
+            }
+        },
+    };
+    preference.sort(&mut addresses);
+    let listed: Vec<String> = addresses.iter().map(|addr| addr.ip().to_string()).collect();
+    steps.push(Step { name: "DNS", outcome: Ok(listed.join(", ")), elapsed: started.elapsed() });
+
+    let connecting = Instant::now();
+    let mut failures = vec![];
+    let mut connected = None;
+    for addr in &addresses {
+        match TcpStream::connect_timeout(addr, timeout) {
+            Ok(_) => {
+                connected = Some(*addr);
+                break;
+            }
+            Err(e) if matches!(e.kind(), io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock) => {
+                failures.push(format!("Connection to {} timed out after {} ms", addr, timeout.as_millis()));
+            }
+            Err(e) => failures.push(describe_failure(&e, &addr.to_string())),
+        }
+    }
+    let outcome = match connected {
+        Some(addr) if failures.is_empty() => Ok(format!("connected to {} ({})", addr, family(&addr))),
+        Some(addr) => Ok(format!("connected to {} ({}) after: {}", addr, family(&addr), failures.join("; "))),
+        None => Err(failures.join("; ")),
+    };
+    let reached = outcome.is_ok();
+    steps.push(Step { name: "TCP", outcome, elapsed: connecting.elapsed() });
+    if https && reached {
+        steps.push(Step {
+            name: "TLS",
+            outcome: Err("not tried: requests go out as plain HTTP, so there's no handshake to make".to_string()),
+            elapsed: Duration::ZERO,
+        });
+    }
+    steps
+}
+
+/// Explains a transport failure in a sentence, judged by the error's kind. Other errors keep
+/// their own wording.
+pub fn describe_failure(error: &io::Error, peer: &str) -> String {
+    match error.kind() {
+        io::ErrorKind::ConnectionRefused => format!("Connection refused — nothing is listening on {}", peer),
+        io::ErrorKind::TimedOut => format!("Connection to {} timed out", peer),
+        io::ErrorKind::ConnectionReset => format!("Connection reset by {} while reading the response", peer),
+        io::ErrorKind::BrokenPipe | io::ErrorKind::UnexpectedEof | io::ErrorKind::ConnectionAborted => {
+            format!("{} closed the connection before a full response arrived", peer)
+        }
+        io::ErrorKind::NetworkUnreachable | io::ErrorKind::HostUnreachable => format!("No route to {}", peer),
+        _ => format!("Request to {} failed: {}", peer, error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, net::Ipv4Addr};
+
+    use super::*;
+
+    fn ip(last: u8) -> IpAddr {
+        IpAddr::from(Ipv4Addr::new(10, 0, 0, last))
+    }
+
+    /// Looks `name` up `elapsed` after `start` with a resolver answering `answer`, returning what
+    /// came back, whether it was cached and whether the resolver was asked.
+    fn look_up(cache: &DnsCache, name: &str, start: Instant, elapsed: u64, answer: io::Result<Vec<IpAddr>>) -> (Result<Vec<IpAddr>, io::ErrorKind>, bool, bool) {
+        let asked = Cell::new(false);
+        let (result, cached) = cache.lookup(name, start + Duration::from_secs(elapsed), || {
+            asked.set(true);
+            answer
+        });
+        (result.map_err(|e| e.kind()), cached, asked.get())
+    }
+
+    #[test]
+    fn answers_are_reused_until_the_ttl_runs_out() {
+        let cache = DnsCache::default();
+        cache.set_ttl(Duration::from_secs(30));
+        let start = Instant::now();
+        assert_eq!(look_up(&cache, "api.test", start, 0, Ok(vec![ip(1)])), (Ok(vec![ip(1)]), false, true));
+        assert_eq!(look_up(&cache, "API.test", start, 29, Ok(vec![ip(2)])), (Ok(vec![ip(1)]), true, false), "names are matched without case");
+        assert_eq!(look_up(&cache, "api.test", start, 30, Ok(vec![ip(2)])), (Ok(vec![ip(2)]), false, true));
+        assert_eq!(cache.flush(), 1);
+        assert!(look_up(&cache, "api.test", start, 31, Ok(vec![ip(3)])).2, "flushed");
+    }
+
+    #[test]
+    fn failures_are_kept_for_five_seconds_at_most() {
+        let cache = DnsCache::default();
+        let start = Instant::now();
+        let failure = || Err(io::Error::new(io::ErrorKind::NotFound, "no such host"));
+        assert_eq!(look_up(&cache, "down.test", start, 0, failure()), (Err(io::ErrorKind::NotFound), false, true));
+        assert_eq!(look_up(&cache, "down.test", start, 4, Ok(vec![ip(1)])), (Err(io::ErrorKind::NotFound), true, false));
+        assert_eq!(look_up(&cache, "down.test", start, 5, Ok(vec![ip(1)])), (Ok(vec![ip(1)]), false, true), "recovered");
+        cache.set_ttl(Duration::from_secs(2));
+        assert!(look_up(&cache, "down.test", start, 10, failure()).2);
+        assert!(look_up(&cache, "down.test", start, 12, Ok(vec![ip(1)])).2, "a shorter TTL bounds failures too");
+    }
+
+    #[test]
+    fn a_zero_ttl_looks_up_every_time_and_empties_the_cache() {
+        let cache = DnsCache::default();
+        let start = Instant::now();
+        assert!(look_up(&cache, "api.test", start, 0, Ok(vec![ip(1)])).2);
+        cache.set_ttl(Duration::ZERO);
+        assert_eq!(cache.flush(), 0);
+        assert!(look_up(&cache, "api.test", start, 0, Ok(vec![ip(1)])).2);
+        assert_eq!(look_up(&cache, "api.test", start, 0, Ok(vec![ip(2)])), (Ok(vec![ip(2)]), false, true));
+    }
+
+    #[test]
+    fn every_address_of_a_name_is_kept_for_falling_back_to() {
+        let cache = DnsCache::default();
+        let v6: IpAddr = "fd00::1".parse().unwrap();
+        assert!(look_up(&cache, "multi.test", Instant::now(), 0, Ok(vec![v6, ip(1), ip(2)])).2);
+        let addresses = |preference| cache.resolve("multi.test:8080", preference).unwrap().addresses;
+        let ordered = |ips: [IpAddr; 3]| ips.map(|ip| SocketAddr::from((ip, 8080))).to_vec();
+        assert_eq!(addresses(AddressPreference::AsResolved), ordered([v6, ip(1), ip(2)]));
+        assert_eq!(addresses(AddressPreference::PreferIpv4), ordered([ip(1), ip(2), v6]));
+        assert_eq!(addresses(AddressPreference::PreferIpv6), ordered([v6, ip(1), ip(2)]));
+        assert!(cache.resolve("multi.test:8080", AddressPreference::AsResolved).unwrap().cached);
+        assert!(cache.resolve("multi.test", AddressPreference::AsResolved).unwrap().addresses.is_empty(), "no port to resolve with");
+    }
+
+    #[test]
+    fn caches_are_separate() {
+        let (one, other) = (DnsCache::default(), DnsCache::default());
+        assert!(look_up(&one, "api.test", Instant::now(), 0, Ok(vec![ip(1)])).2);
+        assert_eq!(other.flush(), 0);
+        assert_eq!(one.flush(), 1);
+    }
+}
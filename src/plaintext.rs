@@ -0,0 +1,357 @@
+use crate::{
+    collection::{folder_label, Collection, SavedRequest},
+    request::{parse_headers, RequestSpec},
+    template,
+};
+
+const METHODS: [&str; 7] = ["GET", "POST", "PUT", "DELETE", "PATCH", "HEAD", "OPTIONS"];
+
+/// Plain-text request files that live happily next to code.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Format {
+    /// VS Code REST Client, `.http`.
+    RestClient,
+    Hurl,
+}
+
+impl Format {
+    /// Judged by extension: `.hurl` is hurl, anything else REST Client.
+    pub fn from_path(path: &str) -> Self {
+        if path.to_ascii_lowercase().ends_with(".hurl") {Format::Hurl} else {Format::RestClient}
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Format::RestClient => "http",
+            Format::Hurl => "hurl",
+        }
+    }
+}
+
+/// Writes every request under `folder` in `format`. Subfolders are noted in a comment so they
+/// survive a round trip. Returns the text and any placeholders the format can't express, which
+/// are written as they are.
+pub fn export(collection: &Collection, folder: &[String], format: Format) -> (String, Vec<String>) {
+    let mut out = String::new();
+    let mut untranslated = vec![];
+    for saved in collection.requests_under(folder) {
+        let subfolder = &saved.folder[folder.len()..];
+        let mut translate = |text: &str| {
+            template::map_placeholders(text, |expression| {
+                to_format(expression, format).unwrap_or_else(|| {
+                    if !untranslated.iter().any(|known| known == expression) {
+                        untranslated.push(expression.to_string());
+                    }
+                    expression.to_string()
+                })
+            })
+        };
+        let request = &saved.request;
+        match format {
+            Format::RestClient => {
+                out.push_str(&format!("### {}\n# @name {}\n", saved.name, identifier(&saved.name)));
+                if !subfolder.is_empty() {
+                    out.push_str(&format!("# @folder {}\n", folder_label(subfolder)));
+                }
+            }
+            Format::Hurl => {
+                out.push_str(&format!("# {}\n", saved.name));
+                if !subfolder.is_empty() {
+                    out.push_str(&format!("# folder: {}\n", folder_label(subfolder)));
+                }
+            }
+        }
+        out.push_str(&format!("{} {}\n", request.method, translate(&request.url)));
+        for (name, value) in parse_headers(&request.headers) {
+            out.push_str(&format!("{}: {}\n", name, translate(&value)));
+        }
+        if !request.body.is_empty() {
+            let body = translate(&request.body);
+            match format {
+                Format::RestClient => out.push_str(&format!("\n{}\n", body)),
+                Format::Hurl => out.push_str(&format!("```\n{}\n```\n", body)),
+            }
+        }
+        out.push('\n');
+    }
+    (out, untranslated)
+}
+
+/// Reads requests written in `format`, folders taken from the comments `export` writes.
+pub fn import(text: &str, format: Format) -> Result<Collection, String> {
+    let requests = match format {
+        Format::RestClient => import_rest_client(text),
+        Format::Hurl => import_hurl(text),
+    };
+    let mut collection = Collection::default();
+    for (index, parsed) in requests.into_iter().enumerate() {
+        let mut request = parsed.request.ok_or_else(|| format!("request {} has no method and URL line", index + 1))?;
+        for text in [&mut request.url, &mut request.headers, &mut request.body] {
+            *text = template::map_placeholders(text, |expression| from_format(expression, format));
+        }
+        if !parsed.folder.is_empty() {
+            collection.add_folder(&parsed.folder);
+        }
+        collection.requests.push(SavedRequest {
+            name: parsed.name.unwrap_or_else(|| format!("Request {}", index + 1)),
+            folder: parsed.folder,
+            request,
+            last_used: None,
+            baseline: None,
+            description: None,
+            tags: vec![],
+        });
+    }
+    Ok(collection)
+}
+
+#[derive(Default)]
+struct Parsed {
+    name: Option<String>,
+    folder: Vec<String>,
+    request: Option<RequestSpec>,
+}
+
+fn import_rest_client(text: &str) -> Vec<Parsed> {
+    let mut parsed = vec![];
+    let mut blocks: Vec<(Option<&str>, Vec<&str>)> = vec![(None, vec![])];
+    for line in text.lines() {
+        match line.strip_prefix("###") {
+            Some(title) => blocks.push((Some(title.trim()).filter(|title| !title.is_empty()), vec![])),
+            None => blocks.last_mut().map_or((), |(_, lines)| lines.push(line)),
+        }
+    }
+    for (title, lines) in blocks {
+        let mut current = Parsed { name: title.map(str::to_string), ..Parsed::default() };
+        let mut lines = lines.into_iter().peekable();
+        while let Some(line) = lines.next_if(|line| {
+            let line = line.trim();
+            line.is_empty() || line.starts_with('#') || line.starts_with("//") || line.starts_with('@')
+        }) {
+            let comment = line.trim().trim_start_matches('#').trim_start_matches("//").trim();
+            if let Some(name) = comment.strip_prefix("@name ") {
+                current.name.get_or_insert_with(|| name.trim().to_string());
+            } else if let Some(folder) = comment.strip_prefix("@folder ") {
+                current.folder = split_folder(folder);
+            }
+        }
+        // A block of only comments, like a file's preamble, isn't a request.
+        let Some(request_line) = lines.next() else { continue };
+        let (method, url) = request_line_parts(request_line);
+        let headers: Vec<&str> = lines.by_ref().take_while(|line| !line.trim().is_empty()).collect();
+        let body: Vec<&str> = lines.collect();
+        let body = body.join("\n");
+        current.request = Some(spec(method, url, headers.join("\n"), body.trim_end_matches('\n').to_string()));
+        parsed.push(current);
+    }
+    parsed
+}
+
+fn import_hurl(text: &str) -> Vec<Parsed> {
+    let mut parsed: Vec<Parsed> = vec![];
+    let mut comments: Vec<&str> = vec![];
+    let mut lines = text.lines().peekable();
+    while let Some(line) = lines.next() {
+        let trimmed = line.trim();
+        if let Some(comment) = trimmed.strip_prefix('#') {
+            comments.push(comment.trim());
+            continue;
+        }
+        if !trimmed.split_once(' ').is_some_and(|(method, _)| METHODS.contains(&method)) {
+            // Response sections, asserts and the like aren't kept.
+            continue;
+        }
+        let mut current = Parsed::default();
+        for comment in comments.drain(..) {
+            match comment.strip_prefix("folder:") {
+                Some(folder) => current.folder = split_folder(folder),
+                None if current.name.is_none() && !comment.is_empty() => current.name = Some(comment.to_string()),
+                None => {}
+            }
+        }
+        let (method, url) = request_line_parts(trimmed);
+        let mut headers = vec![];
+        while let Some(header) = lines.next_if(|line| {
+            let line = line.trim();
+            line.contains(':') && !line.starts_with(['{', '[', '`', '#']) && !line.starts_with("HTTP")
+        }) {
+            headers.push(header.trim());
+        }
+        let mut body = String::new();
+        match lines.peek().map(|line| line.trim()) {
+            Some("```") | Some("```json") | Some("```xml") => {
+                lines.next();
+                let content: Vec<&str> = lines.by_ref().take_while(|line| line.trim_end() != "```").collect();
+                body = content.join("\n");
+            }
+            Some(line) if line.starts_with('`') && line.ends_with('`') && line.len() > 1 => {
+                body = line[1..line.len() - 1].to_string();
+                lines.next();
+            }
+            Some(line) if line.starts_with(['{', '[']) => {
+                let mut content = vec![];
+                while let Some(line) = lines.next_if(|line| !line.trim().is_empty() && !line.starts_with("HTTP")) {
+                    content.push(line);
+                }
+                body = content.join("\n");
+            }
+            _ => {}
+        }
+        current.request = Some(spec(method, url, headers.join("\n"), body));
+        parsed.push(current);
+    }
+    parsed
+}
+
+/// `METHOD url [HTTP/1.1]`, or a bare URL for a GET.
+fn request_line_parts(line: &str) -> (&str, &str) {
+    let line = line.trim();
+    let line = line.strip_suffix("HTTP/1.1").or_else(|| line.strip_suffix("HTTP/2")).unwrap_or(line).trim_end();
+    match line.split_once(' ') {
+        Some((method, url)) if METHODS.contains(&method) => (method, url.trim()),
+        _ => ("GET", line),
+    }
+}
+
+fn spec(method: &str, url: &str, headers: String, body: String) -> RequestSpec {
+    RequestSpec {
+        method: method.to_string(),
+        url: url.to_string(),
+        headers,
+        body,
+        connect_to: None,
+        hooks: vec![],
+        after_response: vec![],
+        redirects: None,
+        timeout_secs: None,
+        max_retries: None,
+        request_id: None,
+        body_schema: None,
+        response_schema: None,
+        expect_continue: None,
+        max_response_mb: None,
+    }
+}
+
+fn split_folder(label: &str) -> Vec<String> {
+    label.split(" / ").map(|part| part.trim().to_string()).filter(|part| !part.is_empty()).collect()
+}
+
+/// A REST Client request name: letters, digits, `_` and `-`.
+fn identifier(name: &str) -> String {
+    name.chars().map(|c| if c.is_alphanumeric() || c == '-' {c} else {'_'}).collect()
+}
+
+/// A placeholder expression in the format's own syntax, or `None` when it has no equivalent.
+fn to_format(expression: &str, format: Format) -> Option<String> {
+    if !expression.starts_with('$') {
+        return Some(expression.to_string());
+    }
+    let words: Vec<&str> = expression.split_whitespace().collect();
+    match (format, words.as_slice()) {
+        (Format::RestClient, ["$uuid"]) => Some("$guid".to_string()),
+        (Format::RestClient, ["$timestamp"]) => Some("$timestamp".to_string()),
+        (Format::RestClient, ["$isoTimestamp"]) => Some("$datetime iso8601".to_string()),
+        // REST Client's upper bound is exclusive, ours inclusive.
+        (Format::RestClient, ["$randomInt"]) => Some("$randomInt 0 1001".to_string()),
+        (Format::RestClient, ["$randomInt", min, max]) => max.parse::<i64>().ok().map(|max| format!("$randomInt {} {}", min, max + 1)),
+        (Format::Hurl, ["$uuid"]) => Some("newUuid".to_string()),
+        (Format::Hurl, ["$isoTimestamp"]) => Some("newDate".to_string()),
+        _ => None,
+    }
+}
+
+fn from_format(expression: &str, format: Format) -> String {
+    let words: Vec<&str> = expression.split_whitespace().collect();
+    match (format, words.as_slice()) {
+        (Format::RestClient, ["$guid"]) => "$uuid".to_string(),
+        (Format::RestClient, ["$datetime", "iso8601"]) => "$isoTimestamp".to_string(),
+        (Format::RestClient, ["$randomInt", min, max]) => match max.parse::<i64>() {
+            Ok(max) => format!("$randomInt {} {}", min, max - 1),
+            Err(_) => expression.to_string(),
+        },
+        (Format::Hurl, ["newUuid"]) => "$uuid".to_string(),
+        (Format::Hurl, ["newDate"]) => "$isoTimestamp".to_string(),
+        _ => expression.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn saved(name: &str, folder: &[&str], method: &str, url: &str, headers: &str, body: &str) -> SavedRequest {
+        SavedRequest {
+            name: name.to_string(),
+            folder: folder.iter().map(|part| part.to_string()).collect(),
+            request: spec(method, url, headers.to_string(), body.to_string()),
+            last_used: None,
+            baseline: None,
+            description: None,
+            tags: vec![],
+        }
+    }
+
+    fn collection() -> Collection {
+        let mut collection = Collection::default();
+        collection.add_folder(&["Users".to_string(), "Admin".to_string()]);
+        collection.requests = vec![
+            saved("List users", &["Users"], "GET", "{{base}}/users?page={{page}}", "Accept: application/json\nX-Trace: {{$uuid}}\nAuthorization: Bearer {{token}}", ""),
+            saved(
+                "Create admin",
+                &["Users", "Admin"],
+                "POST",
+                "{{base}}/admins",
+                "Content-Type: application/json\nX-Created: {{$isoTimestamp}}",
+                "{\n  \"name\": \"{{name}}\",\n  \"note\": \"keeps  spacing: and {braces}\",\n\n  \"id\": {{$uuid}}\n}",
+            ),
+            saved("Remove", &["Users"], "DELETE", "{{base}}/users/{{id}}", "", ""),
+        ];
+        collection
+    }
+
+    fn assert_round_trip(format: Format) {
+        let original = collection();
+        let (text, untranslated) = export(&original, &["Users".to_string()], format);
+        assert!(untranslated.is_empty(), "{:?}", untranslated);
+        let imported = import(&text, format).unwrap();
+        assert_eq!(imported.requests.len(), original.requests.len(), "{}", text);
+        for (imported, original) in imported.requests.iter().zip(&original.requests) {
+            assert_eq!(imported.name, original.name, "{}", text);
+            assert_eq!(imported.folder, original.folder[1..], "{}", text);
+            let (imported, original) = (&imported.request, &original.request);
+            assert_eq!((&imported.method, &imported.url), (&original.method, &original.url), "{}", text);
+            assert_eq!(imported.headers, original.headers, "header order and values, in\n{}", text);
+            assert_eq!(imported.body, original.body, "{}", text);
+        }
+    }
+
+    #[test]
+    fn rest_client_files_import_as_exported() {
+        assert_round_trip(Format::RestClient);
+        let (text, _) = export(&collection(), &[], Format::RestClient);
+        assert!(text.contains("### Create admin\n# @name Create_admin\n# @folder Users / Admin\nPOST {{base}}/admins\n"), "{}", text);
+        assert!(text.contains("X-Created: {{$datetime iso8601}}\n"), "{}", text);
+    }
+
+    #[test]
+    fn hurl_files_import_as_exported() {
+        assert_round_trip(Format::Hurl);
+        let (text, _) = export(&collection(), &[], Format::Hurl);
+        assert!(text.contains("# Create admin\n# folder: Users / Admin\nPOST {{base}}/admins\n"), "{}", text);
+        assert!(text.contains("X-Trace: {{newUuid}}\n"), "{}", text);
+    }
+
+    #[test]
+    fn placeholders_without_an_equivalent_are_reported() {
+        let mut collection = collection();
+        collection.requests[0].request.url = "{{base}}/users?n={{$randomInt 1 6}}".to_string();
+        let (text, untranslated) = export(&collection, &[], Format::Hurl);
+        assert_eq!(untranslated, ["$randomInt 1 6"]);
+        assert!(text.contains("{{$randomInt 1 6}}"));
+        let (text, untranslated) = export(&collection, &[], Format::RestClient);
+        assert!(untranslated.is_empty());
+        assert!(text.contains("{{$randomInt 1 7}}"), "REST Client's bound is exclusive");
+        assert_eq!(import(&text, Format::RestClient).unwrap().requests[0].request.url, "{{base}}/users?n={{$randomInt 1 6}}");
+    }
+}
@@ -0,0 +1,136 @@
+use serde_json::{json, Value};
+
+use crate::{
+    collection::{Collection, SavedRequest},
+    request::{method_index, method_name, parse_headers, RequestSpec},
+};
+
+const SCHEMA: &str = "https://schema.getpostman.com/json/collection/v2.1.0/collection.json";
+
+/// A Postman v2.1 collection converted to ours: folders become folders, requests become saved
+/// requests. Items that are neither are counted in `skipped`.
+pub struct Imported {
+    pub name: String,
+    pub collection: Collection,
+    pub skipped: usize,
+}
+
+pub fn import(json: &str) -> Result<Imported, String> {
+    let root: Value = serde_json::from_str(json).map_err(|e| e.to_string())?;
+    let items = root["item"].as_array().ok_or("not a Postman collection: no \"item\" list")?;
+    let mut imported = Imported {
+        name: root["info"]["name"].as_str().unwrap_or("Postman import").to_string(),
+        collection: Collection::default(),
+        skipped: 0,
+    };
+    import_items(items, &[], &mut imported);
+    Ok(imported)
+}
+
+fn import_items(items: &[Value], folder: &[String], imported: &mut Imported) {
+    for item in items {
+        let name = item["name"].as_str().unwrap_or("Untitled").to_string();
+        if let Some(children) = item["item"].as_array() {
+            let mut path = folder.to_vec();
+            path.push(name);
+            imported.collection.add_folder(&path);
+            import_items(children, &path, imported);
+        } else if let Some(request) = import_request(&item["request"]) {
+            let description = description(&item["request"]["description"]).or_else(|| description(&item["description"]));
+            imported.collection.requests.push(SavedRequest { name, folder: folder.to_vec(), request, last_used: None, baseline: None, description, tags: vec![] });
+        } else {
+            imported.skipped += 1;
+        }
+    }
+}
+
+/// A description is either a string or an object with the text in `content`.
+fn description(value: &Value) -> Option<String> {
+    value.as_str().or_else(|| value["content"].as_str()).filter(|text| !text.is_empty()).map(String::from)
+}
+
+fn import_request(request: &Value) -> Option<RequestSpec> {
+    // The spec allows a bare URL string as shorthand for a GET.
+    if let Some(url) = request.as_str() {
+        return Some(RequestSpec { method: method_name(0), url: url.to_string(), headers: String::new(), body: String::new(), connect_to: None, hooks: vec![], after_response: vec![], redirects: None, timeout_secs: None, max_retries: None, request_id: None, body_schema: None, response_schema: None, expect_continue: None, max_response_mb: None });
+    }
+    let url = match &request["url"] {
+        Value::String(url) => url.clone(),
+        url => url["raw"].as_str()?.to_string(),
+    };
+    let headers: Vec<String> = request["header"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|header| !header["disabled"].as_bool().unwrap_or(false))
+        .filter_map(|header| Some(format!("{}: {}", header["key"].as_str()?, header["value"].as_str().unwrap_or(""))))
+        .collect();
+    let body = &request["body"];
+    let body = match body["mode"].as_str() {
+        Some("raw") => body["raw"].as_str().unwrap_or("").to_string(),
+        Some("urlencoded") => body["urlencoded"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter(|field| !field["disabled"].as_bool().unwrap_or(false))
+            .filter_map(|field| Some(format!("{}={}", field["key"].as_str()?, field["value"].as_str().unwrap_or(""))))
+            .collect::<Vec<_>>()
+            .join("&"),
+        _ => String::new(),
+    };
+    let method = request["method"].as_str().unwrap_or("GET");
+    Some(RequestSpec {
+        method: method_name(method_index(method)),
+        url,
+        headers: headers.join("\n"),
+        body,
+        connect_to: None,
+        hooks: vec![],
+        after_response: vec![],
+        redirects: None,
+        timeout_secs: None,
+        max_retries: None,
+        request_id: None,
+        body_schema: None,
+        response_schema: None,
+        expect_continue: None,
+        max_response_mb: None,
+    })
+}
+
+/// Writes the collection as Postman v2.1 JSON, nesting folders as item groups.
+pub fn export(collection: &Collection, name: &str) -> String {
+    let root = json!({
+        "info": { "name": name, "schema": SCHEMA },
+        "item": export_items(collection, &[]),
+    });
+    serde_json::to_string_pretty(&root).unwrap_or_default()
+}
+
+fn export_items(collection: &Collection, parent: &[String]) -> Vec<Value> {
+    let mut items: Vec<Value> = collection
+        .folders
+        .iter()
+        .filter(|folder| folder.len() == parent.len() + 1 && folder.starts_with(parent))
+        .map(|folder| json!({ "name": folder[parent.len()], "item": export_items(collection, folder) }))
+        .collect();
+    items.extend(collection.requests.iter().filter(|saved| saved.folder == parent).map(|saved| {
+        let headers: Vec<Value> = parse_headers(&saved.request.headers)
+            .into_iter()
+            .map(|(key, value)| json!({ "key": key, "value": value }))
+            .collect();
+        let mut request = json!({
+            "method": saved.request.method,
+            "header": headers,
+            "url": { "raw": saved.request.url },
+        });
+        if !saved.request.body.is_empty() {
+            request["body"] = json!({ "mode": "raw", "raw": saved.request.body });
+        }
+        if let Some(description) = &saved.description {
+            request["description"] = json!(description);
+        }
+        json!({ "name": saved.name, "request": request })
+    }));
+    items
+}
@@ -0,0 +1,61 @@
+use chrono::{DateTime, NaiveDateTime, Utc};
+
+/// The obsolete HTTP-date forms recipients still have to read: RFC 850 and asctime. Both are in
+/// GMT.
+const OBSOLETE_DATES: [&str; 2] = ["%A, %d-%b-%y %H:%M:%S GMT", "%a %b %e %H:%M:%S %Y"];
+
+/// Statuses whose `Retry-After` says when to try again.
+pub fn is_rate_limited(status: Option<u16>) -> bool {
+    matches!(status, Some(429 | 503))
+}
+
+/// Seconds to wait according to a `Retry-After` header, which is either a number of seconds or
+/// an HTTP date. A date in the past means no wait.
+pub fn retry_after(headers: &[(String, String)], now: DateTime<Utc>) -> Option<u64> {
+    let value = headers.iter().find(|(name, _)| name.eq_ignore_ascii_case("Retry-After"))?.1.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(seconds);
+    }
+    let date = http_date(value)?;
+    Some((date - now).num_seconds().max(0) as u64)
+}
+
+fn http_date(value: &str) -> Option<DateTime<Utc>> {
+    if let Ok(date) = DateTime::parse_from_rfc2822(value) {
+        return Some(date.with_timezone(&Utc));
+    }
+    OBSOLETE_DATES.iter().find_map(|format| NaiveDateTime::parse_from_str(value, format).ok()).map(|date| date.and_utc())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wait(value: &str) -> Option<u64> {
+        let now = DateTime::parse_from_rfc3339("1994-11-06T08:49:07Z").unwrap().with_timezone(&Utc);
+        retry_after(&[("retry-after".to_string(), value.to_string())], now)
+    }
+
+    #[test]
+    fn delay_seconds() {
+        assert_eq!(wait("120"), Some(120));
+        assert_eq!(wait(" 0 "), Some(0));
+        assert_eq!(retry_after(&[], Utc::now()), None);
+    }
+
+    #[test]
+    fn every_http_date_form() {
+        assert_eq!(wait("Sun, 06 Nov 1994 08:49:37 GMT"), Some(30));
+        assert_eq!(wait("Sunday, 06-Nov-94 08:49:37 GMT"), Some(30));
+        assert_eq!(wait("Sun Nov  6 08:49:37 1994"), Some(30));
+        assert_eq!(wait("Wed Nov 16 08:49:07 1994"), Some(10 * 24 * 60 * 60));
+        assert_eq!(wait("Sat, 05 Nov 1994 08:49:37 GMT"), Some(0), "already past");
+    }
+
+    #[test]
+    fn bad_values_mean_no_wait_given() {
+        for value in ["", "-5", "1.5", "soon", "Sun, 06 Nov 1994", "06-Nov-94 08:49:37", "Sunday, 06-Nov-94 08:49:37 PST"] {
+            assert_eq!(wait(value), None, "{:?}", value);
+        }
+    }
+}
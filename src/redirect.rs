@@ -0,0 +1,270 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{request::ResolvedRequest, secrets, url::{self, Url}};
+
+/// Whether and how far redirects are followed. Set per request, falling back to `[redirects]`
+/// in `config.toml`.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(default)]
+pub struct Policy {
+    pub follow: bool,
+    pub max_hops: usize,
+    /// Drop credentials when a redirect moves to another origin (scheme, host or port), so they
+    /// only reach the server they were written for.
+    pub strip_auth_cross_origin: bool,
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Self { follow: true, max_hops: 10, strip_auth_cross_origin: true }
+    }
+}
+
+impl Policy {
+    /// Reads `off`, a hop count like `2`, or a hop count followed by `keep-auth`.
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let mut words = input.split_whitespace();
+        let policy = match words.next() {
+            Some("off") => Self { follow: false, ..Self::default() },
+            Some(hops) => Self {
+                max_hops: hops.parse().map_err(|_| format!("{:?} isn't a number of hops or \"off\"", hops))?,
+                ..Self::default()
+            },
+            None => return Err("expected \"off\" or a number of hops".to_string()),
+        };
+        match words.next() {
+            None => Ok(policy),
+            Some("keep-auth") if policy.follow => Ok(Self { strip_auth_cross_origin: false, ..policy }),
+            Some(word) => Err(format!("unexpected {:?}; only \"keep-auth\" may follow the hop count", word)),
+        }
+    }
+
+    /// The policy in the form `parse` reads.
+    pub fn describe(&self) -> String {
+        match (self.follow, self.strip_auth_cross_origin) {
+            (false, _) => "off".to_string(),
+            (true, true) => self.max_hops.to_string(),
+            (true, false) => format!("{} keep-auth", self.max_hops),
+        }
+    }
+}
+
+/// One redirect that was followed.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Hop {
+    pub status: u16,
+    /// The URL that answered with the redirect.
+    pub from: String,
+    pub to: String,
+    /// Credential headers left behind because the origin changed.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dropped: Vec<String>,
+}
+
+pub fn is_redirect(status: Option<u16>) -> bool {
+    matches!(status, Some(301 | 302 | 303 | 307 | 308))
+}
+
+/// The absolute URL `location` points to from `request`, per RFC 3986 section 5.2: a full URL,
+/// one without a scheme (`//host/path`), an absolute path, a query alone, or a path relative to
+/// the current one. Fragments are never sent, so they're dropped.
+fn resolve_location(request: &ResolvedRequest, location: &str) -> String {
+    let location = location.trim();
+    let location = location.split('#').next().unwrap_or("");
+    let has_scheme = location.split_once("://").is_some_and(|(scheme, _)| {
+        scheme.starts_with(|c: char| c.is_ascii_alphabetic()) && scheme.chars().all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c))
+    });
+    let path = request.endpoint.split(['?', '#']).next().unwrap_or("");
+    let base = format!("{}://{}", request.scheme, request.address);
+    if has_scheme {
+        location.to_string()
+    } else if location.starts_with("//") {
+        format!("{}:{}", request.scheme, location)
+    } else if location.starts_with('/') {
+        format!("{}{}", base, remove_dot_segments(location))
+    } else if location.is_empty() {
+        format!("{}{}", base, request.endpoint.split('#').next().unwrap_or(""))
+    } else if location.starts_with('?') {
+        format!("{}{}{}", base, path, location)
+    } else {
+        let directory = &path[..path.rfind('/').map_or(0, |i| i + 1)];
+        format!("{}{}", base, remove_dot_segments(&format!("{}{}", directory, location)))
+    }
+}
+
+/// Resolves `.` and `..` segments of an absolute path, keeping any query as it is.
+fn remove_dot_segments(path: &str) -> String {
+    let (path, query) = match path.split_once('?') {
+        Some((path, query)) => (path, Some(query)),
+        None => (path, None),
+    };
+    let mut segments: Vec<&str> = vec![];
+    let parts: Vec<&str> = path.split('/').skip(1).collect();
+    for (i, segment) in parts.iter().enumerate() {
+        let last = i == parts.len() - 1;
+        match *segment {
+            "." if last => segments.push(""),
+            "." => {}
+            ".." => {
+                segments.pop();
+                if last {
+                    segments.push("");
+                }
+            }
+            segment => segments.push(segment),
+        }
+    }
+    let mut out = format!("/{}", segments.join("/"));
+    if let Some(query) = query {
+        out.push('?');
+        out.push_str(query);
+    }
+    out
+}
+
+/// Scheme, host and port, the port filled in from the scheme when the URL leaves it out.
+fn origin(url: &Url) -> (String, String, u16) {
+    let scheme = url.scheme.as_deref().unwrap_or("http").to_ascii_lowercase();
+    let port = url.port.unwrap_or(if scheme == "https" { 443 } else { 80 });
+    (scheme, url.host.to_string().to_ascii_lowercase(), port)
+}
+
+/// The request to send after `request` was answered with a redirect to `location`. A 303, or a
+/// 301 or 302 to a POST, becomes a GET without a body, as browsers do.
+pub fn follow(request: &ResolvedRequest, status: u16, location: &str, policy: &Policy) -> Result<(ResolvedRequest, Hop), String> {
+    let target = resolve_location(request, location);
+    let url = url::parse(&target).map_err(|e| format!("Location {:?} isn't a usable URL: {}", location, e))?;
+    let from = url::parse(&format!("{}://{}", request.scheme, request.address)).map_err(|e| e.to_string())?;
+    let address = url.authority();
+    let cross_origin = origin(&url) != origin(&from);
+
+    let mut next = request.clone();
+    let mut dropped = vec![];
+    if cross_origin {
+        // A connect-to override is for the original host only.
+        next.connect_to = None;
+        if policy.strip_auth_cross_origin {
+            // Whatever host rules added was meant for the host they matched.
+            let from_rules: Vec<&String> = request.host_defaults.iter().flat_map(|contribution| &contribution.headers).collect();
+            next.headers.retain(|(name, _)| {
+                let credential = secrets::is_secret_header(name) || from_rules.iter().any(|header| header.eq_ignore_ascii_case(name));
+                if credential && !dropped.contains(name) {
+                    dropped.push(name.clone());
+                }
+                !credential
+            });
+            next.host_defaults.clear();
+        }
+    }
+    if status == 303 || (matches!(status, 301 | 302) && request.method.eq_ignore_ascii_case("POST")) {
+        next.method = "GET".to_string();
+        next.body.clear();
+        next.headers.retain(|(name, _)| !name.eq_ignore_ascii_case("Content-Type") && !name.eq_ignore_ascii_case("Content-Length"));
+    }
+    for (name, value) in &mut next.headers {
+        if name.eq_ignore_ascii_case("Host") {
+            *value = address.clone();
+        }
+    }
+    next.scheme = url.scheme.clone().unwrap_or_else(|| request.scheme.clone());
+    next.dial_address = url.dial_address();
+    next.address = address;
+    next.endpoint = url.path;
+    let hop = Hop { status, from: request.url(), to: next.url(), dropped };
+    Ok((next, hop))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::host_rules::Contribution;
+
+    fn request(url: &str, headers: &[(&str, &str)]) -> ResolvedRequest {
+        let parsed = url::parse(url).unwrap();
+        ResolvedRequest {
+            method: "GET".to_string(),
+            scheme: parsed.scheme.clone().unwrap_or_else(|| "http".to_string()),
+            address: parsed.authority(),
+            dial_address: parsed.dial_address(),
+            connect_to: None,
+            endpoint: parsed.path.clone(),
+            headers: headers.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect(),
+            body: String::new(),
+            host_defaults: vec![],
+        }
+    }
+
+    fn names(request: &ResolvedRequest) -> Vec<&str> {
+        request.headers.iter().map(|(name, _)| name.as_str()).collect()
+    }
+
+    #[test]
+    fn drops_every_credential_on_another_host() {
+        let mut original = request(
+            "http://api.example.com/a",
+            &[("Authorization", "Bearer t"), ("X-Api-Key", "k"), ("X-Tenant", "acme"), ("Accept", "*/*")],
+        );
+        original.host_defaults = vec![Contribution { rule: 0, pattern: "api.example.com".to_string(), headers: vec!["X-Tenant".to_string()] }];
+        let (next, hop) = follow(&original, 302, "http://other.example.com/b", &Policy::default()).unwrap();
+        assert_eq!(names(&next), ["Accept"]);
+        assert_eq!(hop.dropped, ["Authorization", "X-Api-Key", "X-Tenant"]);
+        assert!(next.host_defaults.is_empty());
+    }
+
+    #[test]
+    fn keeps_credentials_on_the_same_origin() {
+        let original = request("http://api.example.com/a", &[("Authorization", "Bearer t")]);
+        let (next, hop) = follow(&original, 302, "http://API.example.com:80/b", &Policy::default()).unwrap();
+        assert_eq!(names(&next), ["Authorization"]);
+        assert!(hop.dropped.is_empty());
+    }
+
+    #[test]
+    fn a_scheme_or_port_change_is_another_origin() {
+        let original = request("https://api.example.com/a", &[("Authorization", "Bearer t")]);
+        let (next, _) = follow(&original, 302, "http://api.example.com/a", &Policy::default()).unwrap();
+        assert!(next.headers.is_empty());
+        assert_eq!(next.scheme, "http");
+
+        let original = request("http://api.example.com/a", &[("Cookie", "s=1")]);
+        let (next, _) = follow(&original, 302, "http://api.example.com:8080/a", &Policy::default()).unwrap();
+        assert!(next.headers.is_empty());
+    }
+
+    #[test]
+    fn keep_auth_keeps_credentials() {
+        let original = request("http://api.example.com/a", &[("Authorization", "Bearer t")]);
+        let policy = Policy::parse("3 keep-auth").unwrap();
+        let (next, hop) = follow(&original, 302, "http://other.example.com/", &policy).unwrap();
+        assert_eq!(names(&next), ["Authorization"]);
+        assert!(hop.dropped.is_empty());
+    }
+
+    #[test]
+    fn resolves_relative_locations() {
+        let original = request("http://api.example.com/v1/users/list?page=2", &[]);
+        let target = |location| {
+            let (next, _) = follow(&original, 302, location, &Policy::default()).unwrap();
+            format!("{}://{}", next.scheme, next.url())
+        };
+        assert_eq!(target("//cdn.example.com/x"), "http://cdn.example.com/x");
+        assert_eq!(target("/root"), "http://api.example.com/root");
+        assert_eq!(target("?page=3"), "http://api.example.com/v1/users/list?page=3");
+        assert_eq!(target("detail?id=1"), "http://api.example.com/v1/users/detail?id=1");
+        assert_eq!(target("../groups"), "http://api.example.com/v1/groups");
+        assert_eq!(target("./"), "http://api.example.com/v1/users/");
+        assert_eq!(target("../../../up"), "http://api.example.com/up");
+        assert_eq!(target("/next#section"), "http://api.example.com/next");
+    }
+
+    #[test]
+    fn see_other_becomes_a_get_without_a_body() {
+        let mut original = request("http://api.example.com/form", &[("Content-Type", "application/json"), ("Accept", "*/*")]);
+        original.method = "POST".to_string();
+        original.body = "{}".to_string();
+        let (next, _) = follow(&original, 303, "/done", &Policy::default()).unwrap();
+        assert_eq!(next.method, "GET");
+        assert!(next.body.is_empty());
+        assert_eq!(names(&next), ["Accept"]);
+    }
+}
@@ -0,0 +1,209 @@
+use std::fmt;
+
+use rust_http::http::HTTP_METHODS;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    expect::ExpectContinue,
+    hooks::{Hook, PostHook},
+    host_rules::{self, Contribution, HostRule},
+    redirect,
+    schema::BodySchema,
+    template::{Resolver, TemplateError},
+    url::{self, UrlError},
+};
+
+/// A request exactly as the user wrote it, placeholders and all.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RequestSpec {
+    pub method: String,
+    pub url: String,
+    pub headers: String,
+    pub body: String,
+    /// Address to dial instead of the URL's host, like curl's `--connect-to`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub connect_to: Option<String>,
+    /// Run in order on the resolved request just before it's sent.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hooks: Vec<Hook>,
+    /// Run in order once a response arrives.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub after_response: Vec<PostHook>,
+    /// Overrides the redirect policy from the config.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub redirects: Option<redirect::Policy>,
+    /// Overrides the timeout from the config.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_secs: Option<u64>,
+    /// Overrides how many times a rate-limited retry is rescheduled.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_retries: Option<usize>,
+    /// Overrides whether a fresh `X-Request-Id` is attached.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<bool>,
+    /// Checked against the body before it's sent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body_schema: Option<BodySchema>,
+    /// What responses are expected to look like, as a path like `BodySchema`'s.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_schema: Option<String>,
+    /// Holds the body back until the server answers `Expect: 100-continue`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expect_continue: Option<ExpectContinue>,
+    /// Overrides the largest response body kept, in MB; 0 keeps any size.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_response_mb: Option<u64>,
+}
+
+/// A request with every placeholder substituted, ready to hand to the client.
+#[derive(Clone)]
+pub struct ResolvedRequest {
+    pub method: String,
+    /// `http` or `https`, lowercased; `http` when the URL doesn't say.
+    pub scheme: String,
+    /// `host[:port]` from the URL, as sent in the Host header.
+    pub address: String,
+    /// `host:port` to connect to, with the scheme's default port filled in.
+    pub dial_address: String,
+    pub connect_to: Option<String>,
+    pub endpoint: String,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+    /// Headers that came from host rules rather than the request.
+    pub host_defaults: Vec<Contribution>,
+}
+
+#[derive(Debug)]
+pub enum RequestError {
+    Template(TemplateError),
+    Url(UrlError),
+    /// A pre-request hook failed; the header it sets and why.
+    Hook { header: String, reason: String },
+}
+
+impl fmt::Display for RequestError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RequestError::Template(e) => write!(f, "{}", e),
+            RequestError::Url(e) => write!(f, "invalid URL: {}", e),
+            RequestError::Hook { header, reason } => write!(f, "pre-request hook for {} failed: {}", header, reason),
+        }
+    }
+}
+
+impl From<TemplateError> for RequestError {
+    fn from(e: TemplateError) -> Self {
+        RequestError::Template(e)
+    }
+}
+
+impl From<UrlError> for RequestError {
+    fn from(e: UrlError) -> Self {
+        RequestError::Url(e)
+    }
+}
+
+pub fn method_name(index: usize) -> String {
+    format!("{:.?}", HTTP_METHODS[index])
+}
+
+pub fn method_index(name: &str) -> usize {
+    (0..HTTP_METHODS.len())
+        .find(|&i| method_name(i).eq_ignore_ascii_case(name))
+        .unwrap_or(0)
+}
+
+/// Parses `Name: value` lines, skipping anything without a colon and lines disabled with `//`.
+pub fn parse_headers(text: &str) -> Vec<(String, String)> {
+    text.lines()
+        .filter(|line| !line.trim_start().starts_with("//"))
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+        .filter(|(name, _)| !name.is_empty())
+        .collect()
+}
+
+/// First value of a header, matching the name case-insensitively.
+pub fn header_value<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(header, _)| header.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.as_str())
+}
+
+impl RequestSpec {
+    /// Substitutes placeholders and adds the headers of `hosts` rules matching the URL's host,
+    /// before running the pre-request hooks.
+    pub fn resolve(&self, resolver: &mut Resolver, hosts: &[HostRule]) -> Result<ResolvedRequest, RequestError> {
+        let url = url::parse(&resolver.resolve(&self.url)?)?;
+        let address = url.authority();
+        let mut headers = parse_headers(&resolver.resolve(&self.headers)?);
+        if header_value(&headers, "Host").is_none() {
+            headers.insert(0, ("Host".to_string(), address.clone()));
+        }
+        let host_defaults = host_rules::apply(hosts, &url.host.to_string(), &mut headers, resolver)?;
+        let connect_to = match &self.connect_to {
+            Some(connect_to) => Some(resolver.resolve(connect_to)?),
+            None => None,
+        };
+        let mut resolved = ResolvedRequest {
+            method: self.method.clone(),
+            scheme: url.scheme.clone().unwrap_or_else(|| "http".to_string()),
+            dial_address: url.dial_address(),
+            address,
+            connect_to,
+            endpoint: url.path,
+            headers,
+            body: resolver.resolve(&self.body)?,
+            host_defaults,
+        };
+        for hook in &self.hooks {
+            hook.apply(&mut resolved, resolver)
+                .map_err(|reason| RequestError::Hook { header: hook.header().to_string(), reason })?;
+        }
+        resolved.set_content_length();
+        Ok(resolved)
+    }
+}
+
+impl ResolvedRequest {
+    pub fn url(&self) -> String {
+        format!("{}{}", self.address, self.endpoint)
+    }
+
+    /// The address the client actually connects to.
+    pub fn peer(&self) -> &str {
+        self.connect_to.as_deref().unwrap_or(&self.dial_address)
+    }
+
+    /// Adds `Content-Length` for the body as it will be sent, unless the request sets its own.
+    /// Methods that don't usually carry a body only get one when they have one.
+    fn set_content_length(&mut self) {
+        let carries_body = ["POST", "PUT", "PATCH"].iter().any(|method| self.method.eq_ignore_ascii_case(method));
+        if header_value(&self.headers, "Content-Length").is_none() && (carries_body || !self.body.is_empty()) {
+            self.headers.push(("Content-Length".to_string(), self.body.len().to_string()));
+        }
+    }
+
+    /// How a `Content-Length` the request sets itself is off from the body, which may well be
+    /// on purpose when testing a server.
+    pub fn content_length_mismatch(&self) -> Option<String> {
+        let declared = header_value(&self.headers, "Content-Length")?.trim();
+        let actual = self.body.len();
+        (declared.parse() != Ok(actual)).then(|| format!("Content-Length is {} but the body is {} bytes", declared, actual))
+    }
+
+    /// The request as it would appear on the wire, for previews.
+    pub fn raw(&self) -> String {
+        let mut raw = format!("{} {} HTTP/1.1\n", self.method, self.endpoint);
+        if let Some(connect_to) = &self.connect_to {
+            raw.insert_str(0, &format!("(connecting to {})\n", connect_to));
+        }
+        for (name, value) in &self.headers {
+            raw.push_str(&format!("{}: {}\n", name, value));
+        }
+        raw.push('\n');
+        raw.push_str(&self.body);
+        raw
+    }
+}
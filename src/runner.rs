@@ -0,0 +1,379 @@
+use std::{cmp::Reverse, collections::HashMap, fs, path::Path};
+
+use chrono::DateTime;
+use serde::{Deserialize, Serialize};
+
+use crate::{collection::folder_label, config::RunnerConfig};
+
+/// Entries in a summary's list of the slowest.
+pub const SLOWEST: usize = 5;
+
+/// Where one request of a run stands.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    Waiting,
+    Running,
+    Passed,
+    /// Answered, but a hook, the baseline or the response schema found fault with it.
+    Failed,
+    /// Not answered at all, or not sent.
+    Error,
+    /// Left out after the run stopped.
+    Skipped,
+}
+
+impl Outcome {
+    pub fn label(self) -> &'static str {
+        match self {
+            Outcome::Waiting => "waiting",
+            Outcome::Running => "running",
+            Outcome::Passed => "passed",
+            Outcome::Failed => "failed",
+            Outcome::Error => "error",
+            Outcome::Skipped => "skipped",
+        }
+    }
+}
+
+/// One saved request of a run and how it went.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Leg {
+    pub folder: Vec<String>,
+    pub name: String,
+    /// The history entry it was sent as.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<u64>,
+    pub outcome: Outcome,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<u64>,
+    /// What failed, or the error for a request that wasn't answered.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub problems: Vec<String>,
+}
+
+/// The saved requests of a folder sent one after another, each once the one before it is
+/// settled, so variables set by its hooks are there for the next.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Run {
+    pub folder: Vec<String>,
+    /// RFC 3339.
+    pub started: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub finished: Option<String>,
+    /// Why the run ended before its last request.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stopped: Option<String>,
+    pub legs: Vec<Leg>,
+}
+
+impl Run {
+    pub fn new(folder: Vec<String>, requests: Vec<(Vec<String>, String)>, started: String) -> Self {
+        let legs = requests
+            .into_iter()
+            .map(|(folder, name)| Leg { folder, name, id: None, outcome: Outcome::Waiting, status: None, latency_ms: None, problems: vec![] })
+            .collect();
+        Self { folder, started, finished: None, stopped: None, legs }
+    }
+
+    pub fn label(&self) -> String {
+        match self.folder.is_empty() {
+            true => "the whole collection".to_string(),
+            false => folder_label(&self.folder),
+        }
+    }
+
+    /// The leg sent last, while it's unanswered.
+    pub fn running(&self) -> Option<usize> {
+        self.legs.iter().position(|leg| leg.outcome == Outcome::Running)
+    }
+
+    pub fn next_waiting(&self) -> Option<usize> {
+        self.legs.iter().position(|leg| leg.outcome == Outcome::Waiting)
+    }
+
+    /// Records how a leg went, stopping the run if `policy` says to.
+    pub fn settle(&mut self, index: usize, outcome: Outcome, problems: Vec<String>, policy: RunnerConfig) {
+        let leg = &mut self.legs[index];
+        leg.outcome = outcome;
+        leg.problems = problems;
+        let stop = match outcome {
+            Outcome::Failed => policy.stop_on_failure,
+            Outcome::Error => policy.stop_on_error,
+            _ => false,
+        };
+        if stop {
+            let reason = format!("Stopped: {} {}", leg.name, if outcome == Outcome::Failed {"failed"} else {"got no response"});
+            self.stop(reason);
+        }
+    }
+
+    /// Skips whatever hasn't been sent yet. The request in flight still gets its result.
+    pub fn stop(&mut self, reason: String) {
+        for leg in self.legs.iter_mut().filter(|leg| leg.outcome == Outcome::Waiting) {
+            leg.outcome = Outcome::Skipped;
+        }
+        self.stopped.get_or_insert(reason);
+    }
+
+    pub fn is_finished(&self) -> bool {
+        !self.legs.iter().any(|leg| matches!(leg.outcome, Outcome::Waiting | Outcome::Running))
+    }
+
+    /// Legs that failed or got no response.
+    pub fn failing(&self) -> impl Iterator<Item = &Leg> {
+        self.legs.iter().filter(|leg| matches!(leg.outcome, Outcome::Failed | Outcome::Error))
+    }
+
+    /// Up to `count` answered legs, slowest first.
+    pub fn slowest(&self, count: usize) -> Vec<&Leg> {
+        let mut timed: Vec<&Leg> = self.legs.iter().filter(|leg| leg.latency_ms.is_some()).collect();
+        timed.sort_by_key(|leg| Reverse(leg.latency_ms));
+        timed.truncate(count);
+        timed
+    }
+
+    /// From start to finish, once finished.
+    pub fn wall_ms(&self) -> Option<i64> {
+        let started = DateTime::parse_from_rfc3339(&self.started).ok()?;
+        let finished = DateTime::parse_from_rfc3339(self.finished.as_deref()?).ok()?;
+        Some((finished - started).num_milliseconds())
+    }
+
+    /// A run of just the legs that failed or got no response, in the same order.
+    pub fn failed_again(&self, started: String) -> Self {
+        Self::new(self.folder.clone(), self.failing().map(|leg| (leg.folder.clone(), leg.name.clone())).collect(), started)
+    }
+
+    /// The leg's name, with its folder when that's below the run's.
+    pub fn leg_label(&self, leg: &Leg) -> String {
+        match leg.folder.strip_prefix(self.folder.as_slice()) {
+            Some([]) | None => leg.name.clone(),
+            Some(inner) => format!("{} / {}", folder_label(inner), leg.name),
+        }
+    }
+
+    /// The summary and every leg as Markdown tables, for a release checklist or the like.
+    pub fn markdown(&self) -> String {
+        let count = |outcome| self.legs.iter().filter(|leg| leg.outcome == outcome).count();
+        let mut out = format!("# Collection run: {}\n\n", self.label());
+        out.push_str(&format!("Started {}", self.started));
+        if let Some(ms) = self.wall_ms() {
+            out.push_str(&format!(", took {:.1}s", ms as f64 / 1000.0));
+        }
+        out.push_str(".\n");
+        if let Some(stopped) = &self.stopped {
+            out.push_str(&format!("\n{}.\n", stopped));
+        }
+        out.push_str("\n| Requests | Passed | Failed | No response | Skipped | Failed checks |\n|---|---|---|---|---|---|\n");
+        let problems: usize = self.legs.iter().filter(|leg| leg.outcome == Outcome::Failed).map(|leg| leg.problems.len()).sum();
+        out.push_str(&format!(
+            "| {} | {} | {} | {} | {} | {} |\n",
+            self.legs.len(), count(Outcome::Passed), count(Outcome::Failed), count(Outcome::Error), count(Outcome::Skipped), problems,
+        ));
+        let row = |leg: &Leg| format!(
+            "| {} | {} | {} | {} |",
+            cell(&self.leg_label(leg)),
+            leg.outcome.label(),
+            leg.status.map_or(String::new(), |status| status.to_string()),
+            leg.latency_ms.map_or(String::new(), |ms| format!("{} ms", ms)),
+        );
+        if self.failing().next().is_some() {
+            out.push_str("\n## Failing\n\n| Request | Result | Status | Time | Problems |\n|---|---|---|---|---|\n");
+            for leg in self.failing() {
+                out.push_str(&format!("{} {} |\n", row(leg), cell(&leg.problems.join("; "))));
+            }
+        }
+        let slowest = self.slowest(SLOWEST);
+        if !slowest.is_empty() {
+            out.push_str("\n## Slowest\n\n| Request | Result | Status | Time |\n|---|---|---|---|\n");
+            for leg in slowest {
+                out.push_str(&format!("{}\n", row(leg)));
+            }
+        }
+        out.push_str("\n## All requests\n\n| Request | Result | Status | Time |\n|---|---|---|---|\n");
+        for leg in &self.legs {
+            out.push_str(&format!("{}\n", row(leg)));
+        }
+        out
+    }
+
+    /// The run as a JUnit test suite: a test case per request, classed by its folder, with
+    /// failed checks as `<failure>` and requests without a response as `<error>`. `bodies`
+    /// holds the response bodies of failed requests by history id, included in the failure.
+    pub fn junit(&self, bodies: &HashMap<u64, String>) -> String {
+        let count = |outcome| self.legs.iter().filter(|leg| leg.outcome == outcome).count();
+        let seconds = |ms: i64| format!("{:.3}", ms as f64 / 1000.0);
+        let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        out.push_str(&format!(
+            "<testsuites>\n  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"{}\" skipped=\"{}\" time=\"{}\" timestamp=\"{}\">\n",
+            xml_escape(&self.label()),
+            self.legs.len(),
+            count(Outcome::Failed),
+            count(Outcome::Error),
+            count(Outcome::Skipped),
+            seconds(self.wall_ms().unwrap_or_default()),
+            xml_escape(&self.started),
+        ));
+        for leg in &self.legs {
+            let classname = match leg.folder.is_empty() {
+                true => "collection".to_string(),
+                false => leg.folder.join("."),
+            };
+            out.push_str(&format!(
+                "    <testcase classname=\"{}\" name=\"{}\" time=\"{}\"",
+                xml_escape(&classname),
+                xml_escape(&leg.name),
+                seconds(leg.latency_ms.unwrap_or_default() as i64),
+            ));
+            let message = leg.problems.first().map(String::as_str).unwrap_or_default();
+            match leg.outcome {
+                Outcome::Failed => {
+                    let mut detail = leg.problems.join("\n");
+                    if let Some(status) = leg.status {
+                        detail.push_str(&format!("\nStatus: {}", status));
+                    }
+                    if let Some(body) = leg.id.and_then(|id| bodies.get(&id)).filter(|body| !body.is_empty()) {
+                        detail.push_str(&format!("\nResponse body:\n{}", body));
+                    }
+                    out.push_str(&format!(
+                        ">\n      <failure message=\"{}\" type=\"check\">{}</failure>\n    </testcase>\n",
+                        xml_escape(message),
+                        xml_escape(&detail),
+                    ));
+                }
+                Outcome::Error => out.push_str(&format!(
+                    ">\n      <error message=\"{}\" type=\"transport\">{}</error>\n    </testcase>\n",
+                    xml_escape(message),
+                    xml_escape(&leg.problems.join("\n")),
+                )),
+                Outcome::Skipped | Outcome::Waiting | Outcome::Running => {
+                    let reason = self.stopped.as_deref().unwrap_or("not run");
+                    out.push_str(&format!(">\n      <skipped message=\"{}\"/>\n    </testcase>\n", xml_escape(reason)));
+                }
+                Outcome::Passed => out.push_str("/>\n"),
+            }
+        }
+        out.push_str("  </testsuite>\n</testsuites>\n");
+        out
+    }
+
+    /// Every request was answered and passed its checks.
+    pub fn passed(&self) -> bool {
+        self.legs.iter().all(|leg| leg.outcome == Outcome::Passed)
+    }
+
+    /// Like `4/6 done: 3 passed, 1 failed`.
+    pub fn summary(&self) -> String {
+        let count = |outcome| self.legs.iter().filter(|leg| leg.outcome == outcome).count();
+        let done = self.legs.iter().filter(|leg| !matches!(leg.outcome, Outcome::Waiting | Outcome::Running | Outcome::Skipped)).count();
+        let counts: Vec<String> = [Outcome::Passed, Outcome::Failed, Outcome::Error, Outcome::Skipped]
+            .into_iter()
+            .filter(|outcome| count(*outcome) > 0)
+            .map(|outcome| format!("{} {}", count(outcome), outcome.label()))
+            .collect();
+        match counts.is_empty() {
+            true => format!("{}/{} done", done, self.legs.len()),
+            false => format!("{}/{} done: {}", done, self.legs.len(), counts.join(", ")),
+        }
+    }
+}
+
+/// Text for a Markdown table cell, which can't hold pipes or line breaks as they are.
+fn cell(text: &str) -> String {
+    text.replace('|', "\\|").replace('\n', " ")
+}
+
+/// Text for an XML attribute or element. Characters XML 1.0 doesn't allow at all, like most
+/// control characters, become U+FFFD so the document stays well-formed.
+fn xml_escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            '\t' | '\n' | '\r' => out.push(c),
+            c if c < ' ' || c == '\u{FFFE}' || c == '\u{FFFF}' => out.push('\u{FFFD}'),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Writes a finished run as `<started>.json` in `dir`, returning the file's path.
+pub fn save(dir: &Path, run: &Run) -> Result<String, String> {
+    let name: String = run.started.chars().map(|c| if c.is_ascii_alphanumeric() || c == '-' {c} else {'_'}).collect();
+    let path = dir.join(format!("{}.json", name));
+    let json = serde_json::to_string_pretty(run).map_err(|e| e.to_string())?;
+    fs::create_dir_all(dir)
+        .and_then(|()| fs::write(&path, json))
+        .map_err(|e| format!("{}: {}", path.display(), e))?;
+    Ok(path.display().to_string())
+}
+
+/// The run saved last in `dir`, if any. Names sort by start time, so that's the last name.
+pub fn load_latest(dir: &Path) -> Result<Option<Run>, String> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(format!("{}: {}", dir.display(), e)),
+    };
+    let latest = entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "json"))
+        .max();
+    let Some(path) = latest else { return Ok(None) };
+    let text = fs::read_to_string(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    serde_json::from_str(&text).map(Some).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::xml::{self, Part};
+
+    fn leg(name: &str, outcome: Outcome, problems: &[&str]) -> Leg {
+        let problems = problems.iter().map(|problem| problem.to_string()).collect();
+        Leg { folder: vec!["Users & <Admins>".to_string()], name: name.to_string(), id: Some(7), outcome, status: Some(500), latency_ms: Some(1250), problems }
+    }
+
+    #[test]
+    fn markup_and_control_characters_are_escaped() {
+        assert_eq!(xml_escape(r#"<a href="x">Tom & 'Jerry'</a>"#), "&lt;a href=&quot;x&quot;&gt;Tom &amp; &apos;Jerry&apos;&lt;/a&gt;");
+        assert_eq!(xml_escape("bell\u{7}\u{0}\u{FFFE}"), "bell\u{FFFD}\u{FFFD}\u{FFFD}");
+        assert_eq!(xml_escape("tab\tline\r\nnext ünïcødé"), "tab\tline\r\nnext ünïcødé");
+    }
+
+    #[test]
+    fn the_junit_report_is_well_formed_whatever_the_names_and_bodies() {
+        let mut run = Run::new(vec![], vec![], "2026-01-01T00:00:00Z".to_string());
+        run.legs = vec![
+            leg("GET \"quoted\" <list>", Outcome::Passed, &[]),
+            leg("Create & check", Outcome::Failed, &["status was 500, not <201>", "second"]),
+            leg("Unreachable", Outcome::Error, &["connection refused\u{1b}[0m"]),
+            leg("Left out", Outcome::Skipped, &[]),
+        ];
+        run.stopped = Some("Stopped: Create & check failed".to_string());
+        let bodies = HashMap::from([(7, "<html>]]> & \u{0}</html>".to_string())]);
+        let report = run.junit(&bodies);
+
+        let lines = xml::format(&report).unwrap();
+        for (part, text) in lines.iter().flat_map(|line| &line.parts) {
+            if matches!(part, Part::Text | Part::AttrValue) {
+                assert!(!text.contains('<') && !text.contains("]]>"), "unescaped {:?} in {:?}", text, part);
+                assert!(text.split('&').skip(1).all(|after| ["amp;", "lt;", "gt;", "quot;", "apos;"].iter().any(|entity| after.starts_with(entity))), "bare & in {}", text);
+            }
+        }
+        assert!(!report.contains('\u{0}') && !report.contains('\u{1b}'));
+        assert!(report.contains("<testsuite name=\"the whole collection\" tests=\"4\" failures=\"1\" errors=\"1\" skipped=\"1\""));
+        assert!(report.contains("<testcase classname=\"Users &amp; &lt;Admins&gt;\" name=\"GET &quot;quoted&quot; &lt;list&gt;\" time=\"1.250\"/>"));
+        assert!(report.contains("<failure message=\"status was 500, not &lt;201&gt;\" type=\"check\">status was 500, not &lt;201&gt;\nsecond\nStatus: 500\nResponse body:\n&lt;html&gt;]]&gt; &amp; \u{FFFD}&lt;/html&gt;</failure>"));
+        assert!(report.contains("<skipped message=\"Stopped: Create &amp; check failed\"/>"));
+    }
+}
@@ -0,0 +1,506 @@
+use std::{fmt, fs};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    json_tree::{path_string, Segment},
+    search::Pattern,
+    yaml,
+};
+
+/// A JSON Schema a request's body is checked against before it's sent.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BodySchema {
+    /// A schema file, or a schema inside a bigger document after `#`: a JSON pointer like
+    /// `openapi.yaml#/components/schemas/User`, or just `openapi.yaml#User` for an OpenAPI
+    /// component.
+    pub path: String,
+    /// Refuses to send a body that doesn't match, rather than warning.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub strict: bool,
+}
+
+impl BodySchema {
+    /// As typed in the request settings: the path, then `strict` to block sending.
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let input = input.trim();
+        let (path, strict) = match input.strip_suffix("strict") {
+            Some(path) if path.ends_with(' ') => (path.trim(), true),
+            _ => (input, false),
+        };
+        if path.is_empty() || path.starts_with('#') {
+            return Err(format!("Expected a schema file: {}", input));
+        }
+        Ok(Self { path: path.to_string(), strict })
+    }
+
+    pub fn to_input(&self) -> String {
+        if self.strict {format!("{} strict", self.path)} else {self.path.clone()}
+    }
+
+    pub fn describe(&self) -> String {
+        if self.strict {format!("{} (strict)", self.path)} else {self.path.clone()}
+    }
+}
+
+/// Where in the body a constraint failed, and which.
+pub struct Violation {
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "`{}`: {}", self.path, self.message)
+    }
+}
+
+/// How a response held up against the schema it was expected to match.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SchemaCheck {
+    pub schema: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub violations: Vec<String>,
+    /// Why it couldn't be checked at all.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl SchemaCheck {
+    pub fn passed(&self) -> bool {
+        self.error.is_none() && self.violations.is_empty()
+    }
+}
+
+pub fn check_response(schema: &str, body: &str) -> SchemaCheck {
+    let (violations, error) = match validate(schema, body) {
+        Ok(violations) => (violations.iter().map(Violation::to_string).collect(), None),
+        Err(e) => (vec![], Some(e)),
+    };
+    SchemaCheck { schema: schema.to_string(), violations, error }
+}
+
+pub fn validate_body(schema: &BodySchema, body: &str) -> Result<Vec<Violation>, String> {
+    validate(&schema.path, body)
+}
+
+/// Reads the schema at `reference`, a file with an optional `#` fragment as in `BodySchema`,
+/// and checks `body` against it. Schema files may be JSON or YAML.
+fn validate(reference: &str, body: &str) -> Result<Vec<Violation>, String> {
+    let (file, fragment) = reference.split_once('#').unwrap_or((reference, ""));
+    let text = fs::read_to_string(file.trim()).map_err(|e| format!("{}: {}", file.trim(), e))?;
+    let document: Value = match text.trim_start().starts_with('{') {
+        true => serde_json::from_str(&text).map_err(|e| format!("{}: {}", file.trim(), e))?,
+        false => yaml::parse(&text).map_err(|e| format!("{}: {}", file.trim(), e))?,
+    };
+    let pointer = match fragment {
+        "" => String::new(),
+        pointer if pointer.starts_with('/') => pointer.to_string(),
+        component => format!("/components/schemas/{}", component),
+    };
+    let root = document.pointer(&pointer).ok_or_else(|| format!("{} has nothing at #{}", file.trim(), pointer))?;
+    let body: Value = serde_json::from_str(body).map_err(|e| format!("the body isn't JSON: {}", e))?;
+    let mut validator = Validator { document: &document, violations: vec![], depth: 0 };
+    validator.check(root, &body, &mut vec![]);
+    Ok(validator.violations)
+}
+
+/// `$ref`s that keep referring to each other give up past this depth.
+const MAX_DEPTH: usize = 64;
+
+struct Validator<'a> {
+    /// What `#/...` references point into.
+    document: &'a Value,
+    violations: Vec<Violation>,
+    depth: usize,
+}
+
+impl Validator<'_> {
+    fn fail(&mut self, path: &[Segment], message: String) {
+        self.violations.push(Violation { path: path_string(path), message });
+    }
+
+    /// Whether `value` matches `schema`, without reporting why not.
+    fn matches(&mut self, schema: &Value, value: &Value, path: &mut Vec<Segment>) -> bool {
+        let reported = std::mem::take(&mut self.violations);
+        self.check(schema, value, path);
+        let matched = self.violations.is_empty();
+        self.violations = reported;
+        matched
+    }
+
+    fn check(&mut self, schema: &Value, value: &Value, path: &mut Vec<Segment>) {
+        let schema = match schema {
+            Value::Bool(true) => return,
+            Value::Bool(false) => return self.fail(path, "no value is allowed here".to_string()),
+            Value::Object(schema) => schema,
+            _ => return,
+        };
+        if self.depth == MAX_DEPTH {
+            return self.fail(path, "the schema nests too deep; is a $ref referring to itself?".to_string());
+        }
+        self.depth += 1;
+        if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+            match reference.strip_prefix('#').and_then(|pointer| self.document.pointer(pointer)) {
+                Some(target) => self.check(target, value, path),
+                None => self.fail(path, format!("can't follow $ref {}", reference)),
+            }
+        }
+        self.check_type(schema, value, path);
+        if let Some(allowed) = schema.get("enum").and_then(Value::as_array) {
+            if !allowed.contains(value) {
+                let allowed: Vec<String> = allowed.iter().map(Value::to_string).collect();
+                self.fail(path, format!("expected one of {}, got {}", allowed.join(", "), value));
+            }
+        }
+        if let Some(expected) = schema.get("const") {
+            if expected != value {
+                self.fail(path, format!("expected {}, got {}", expected, value));
+            }
+        }
+        match value {
+            Value::String(text) => self.check_string(schema, text, path),
+            Value::Number(_) => self.check_number(schema, value.as_f64().unwrap_or_default(), path),
+            Value::Array(items) => self.check_array(schema, items, path),
+            Value::Object(object) => self.check_object(schema, object, path),
+            _ => {}
+        }
+        self.check_combinations(schema, value, path);
+        self.depth -= 1;
+    }
+
+    fn check_type(&mut self, schema: &serde_json::Map<String, Value>, value: &Value, path: &[Segment]) {
+        let expected: Vec<&str> = match schema.get("type") {
+            Some(Value::String(name)) => vec![name],
+            Some(Value::Array(names)) => names.iter().filter_map(Value::as_str).collect(),
+            _ => return,
+        };
+        let actual = kind(value);
+        // OpenAPI 3.0 writes nullable types this way.
+        let nullable = schema.get("nullable") == Some(&Value::Bool(true)) && actual == "null";
+        let fits = |name: &&str| *name == actual || (*name == "number" && actual == "integer");
+        if !nullable && !expected.iter().any(fits) {
+            self.fail(path, format!("expected {}, got {}", expected.join(" or "), actual));
+        }
+    }
+
+    fn check_string(&mut self, schema: &serde_json::Map<String, Value>, text: &str, path: &[Segment]) {
+        let length = text.chars().count() as u64;
+        if let Some(min) = schema.get("minLength").and_then(Value::as_u64).filter(|min| length < *min) {
+            self.fail(path, format!("expected at least {} characters, got {}", min, length));
+        }
+        if let Some(max) = schema.get("maxLength").and_then(Value::as_u64).filter(|max| length > *max) {
+            self.fail(path, format!("expected at most {} characters, got {}", max, length));
+        }
+        if let Some(pattern) = schema.get("pattern").and_then(Value::as_str) {
+            match Pattern::regex(pattern) {
+                Ok(regex) if regex.is_match(text) => {}
+                Ok(_) => self.fail(path, format!("doesn't match the pattern {}", pattern)),
+                Err(e) => self.fail(path, format!("the schema's pattern {} can't be checked: {}", pattern, e)),
+            }
+        }
+    }
+
+    fn check_number(&mut self, schema: &serde_json::Map<String, Value>, number: f64, path: &[Segment]) {
+        let bound = |name: &str| schema.get(name).and_then(Value::as_f64);
+        // Draft 4 made the exclusive bounds flags on `minimum` and `maximum`.
+        let flag = |name: &str| schema.get(name) == Some(&Value::Bool(true));
+        let (exclusive_min, exclusive_max) = (bound("exclusiveMinimum"), bound("exclusiveMaximum"));
+        match bound("minimum") {
+            Some(min) if flag("exclusiveMinimum") && number <= min => self.fail(path, format!("expected more than {}, got {}", min, number)),
+            Some(min) if number < min => self.fail(path, format!("expected at least {}, got {}", min, number)),
+            _ => {}
+        }
+        match bound("maximum") {
+            Some(max) if flag("exclusiveMaximum") && number >= max => self.fail(path, format!("expected less than {}, got {}", max, number)),
+            Some(max) if number > max => self.fail(path, format!("expected at most {}, got {}", max, number)),
+            _ => {}
+        }
+        if let Some(min) = exclusive_min.filter(|min| number <= *min) {
+            self.fail(path, format!("expected more than {}, got {}", min, number));
+        }
+        if let Some(max) = exclusive_max.filter(|max| number >= *max) {
+            self.fail(path, format!("expected less than {}, got {}", max, number));
+        }
+        if let Some(step) = bound("multipleOf").filter(|step| *step > 0.0) {
+            let quotient = number / step;
+            if (quotient - quotient.round()).abs() > 1e-9 {
+                self.fail(path, format!("expected a multiple of {}, got {}", step, number));
+            }
+        }
+    }
+
+    fn check_array(&mut self, schema: &serde_json::Map<String, Value>, items: &[Value], path: &mut Vec<Segment>) {
+        let count = items.len() as u64;
+        if let Some(min) = schema.get("minItems").and_then(Value::as_u64).filter(|min| count < *min) {
+            self.fail(path, format!("expected at least {} items, got {}", min, count));
+        }
+        if let Some(max) = schema.get("maxItems").and_then(Value::as_u64).filter(|max| count > *max) {
+            self.fail(path, format!("expected at most {} items, got {}", max, count));
+        }
+        if schema.get("uniqueItems") == Some(&Value::Bool(true)) {
+            let duplicate = (0..items.len()).find_map(|i| Some((i, (i + 1..items.len()).find(|j| items[*j] == items[i])?)));
+            if let Some((first, second)) = duplicate {
+                self.fail(path, format!("items {} and {} are the same, expected unique items", first, second));
+            }
+        }
+        // `prefixItems` since 2020-12, an array of `items` before that.
+        let tuple = schema.get("prefixItems").or_else(|| schema.get("items").filter(|items| items.is_array()));
+        let prefix = tuple.and_then(Value::as_array).map_or(&[][..], Vec::as_slice);
+        let rest = match tuple {
+            Some(_) if schema.contains_key("prefixItems") => schema.get("items"),
+            Some(_) => schema.get("additionalItems"),
+            None => schema.get("items"),
+        };
+        for (i, item) in items.iter().enumerate() {
+            let Some(item_schema) = prefix.get(i).or(rest) else { continue };
+            path.push(Segment::Index(i));
+            self.check(item_schema, item, path);
+            path.pop();
+        }
+        if let Some(contains) = schema.get("contains") {
+            let found = items.iter().enumerate().any(|(i, item)| {
+                path.push(Segment::Index(i));
+                let matched = self.matches(contains, item, path);
+                path.pop();
+                matched
+            });
+            if !found {
+                self.fail(path, "no item matches the schema's `contains`".to_string());
+            }
+        }
+    }
+
+    fn check_object(&mut self, schema: &serde_json::Map<String, Value>, object: &serde_json::Map<String, Value>, path: &mut Vec<Segment>) {
+        for required in schema.get("required").and_then(Value::as_array).into_iter().flatten().filter_map(Value::as_str) {
+            if !object.contains_key(required) {
+                self.fail(path, format!("missing required property {}", required));
+            }
+        }
+        let count = object.len() as u64;
+        if let Some(min) = schema.get("minProperties").and_then(Value::as_u64).filter(|min| count < *min) {
+            self.fail(path, format!("expected at least {} properties, got {}", min, count));
+        }
+        if let Some(max) = schema.get("maxProperties").and_then(Value::as_u64).filter(|max| count > *max) {
+            self.fail(path, format!("expected at most {} properties, got {}", max, count));
+        }
+        let properties = schema.get("properties").and_then(Value::as_object);
+        let patterns: Vec<(Pattern, &Value)> = schema
+            .get("patternProperties")
+            .and_then(Value::as_object)
+            .into_iter()
+            .flatten()
+            .filter_map(|(pattern, schema)| Some((Pattern::regex(pattern).ok()?, schema)))
+            .collect();
+        for (key, value) in object {
+            path.push(Segment::Key(key.clone()));
+            let mut known = false;
+            if let Some(property) = properties.and_then(|properties| properties.get(key)) {
+                known = true;
+                self.check(property, value, path);
+            }
+            for (pattern, property) in &patterns {
+                if pattern.is_match(key) {
+                    known = true;
+                    self.check(property, value, path);
+                }
+            }
+            match schema.get("additionalProperties") {
+                Some(Value::Bool(false)) if !known => {
+                    path.pop();
+                    self.fail(path, format!("unexpected property {}", key));
+                    continue;
+                }
+                Some(additional @ Value::Object(_)) if !known => self.check(additional, value, path),
+                _ => {}
+            }
+            path.pop();
+        }
+    }
+
+    fn check_combinations(&mut self, schema: &serde_json::Map<String, Value>, value: &Value, path: &mut Vec<Segment>) {
+        for all in schema.get("allOf").and_then(Value::as_array).into_iter().flatten() {
+            self.check(all, value, path);
+        }
+        if let Some(any) = schema.get("anyOf").and_then(Value::as_array) {
+            if !any.iter().any(|alternative| self.matches(alternative, value, path)) {
+                self.fail(path, format!("matches none of the {} anyOf schemas", any.len()));
+            }
+        }
+        if let Some(one) = schema.get("oneOf").and_then(Value::as_array) {
+            match one.iter().filter(|alternative| self.matches(alternative, value, path)).count() {
+                1 => {}
+                0 => self.fail(path, format!("matches none of the {} oneOf schemas", one.len())),
+                n => self.fail(path, format!("matches {} of the oneOf schemas, expected exactly one", n)),
+            }
+        }
+        if let Some(not) = schema.get("not") {
+            if self.matches(not, value, path) {
+                self.fail(path, "matches the schema under `not`".to_string());
+            }
+        }
+        if let Some(condition) = schema.get("if") {
+            let branch = if self.matches(condition, value, path) {"then"} else {"else"};
+            if let Some(branch) = schema.get(branch) {
+                self.check(branch, value, path);
+            }
+        }
+    }
+}
+
+/// The JSON Schema type name of a value, telling integers from other numbers.
+fn kind(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(number) if number.is_i64() || number.is_u64() || number.as_f64().is_some_and(|n| n.fract() == 0.0) => "integer",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn violations(document: &Value, body: Value) -> Vec<String> {
+        let mut validator = Validator { document, violations: vec![], depth: 0 };
+        validator.check(document, &body, &mut vec![]);
+        validator.violations.iter().map(Violation::to_string).collect()
+    }
+
+    fn temp_path(name: &str) -> String {
+        std::env::temp_dir().join(format!("tui_postman-{}-{}", std::process::id(), name)).to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn types() {
+        let schema = json!({"type": "object", "properties": {
+            "id": {"type": "integer"},
+            "price": {"type": "number"},
+            "note": {"type": ["string", "null"]},
+            "tag": {"type": "string", "nullable": true},
+        }});
+        assert!(violations(&schema, json!({"id": 3, "price": 3, "note": null, "tag": null})).is_empty());
+        assert!(violations(&schema, json!({"id": 3.0, "price": 2.5, "note": "x"})).is_empty());
+        assert_eq!(violations(&schema, json!({"id": 2.5, "price": "1", "note": 1})), [
+            "`.id`: expected integer, got number",
+            "`.note`: expected string or null, got integer",
+            "`.price`: expected number, got string",
+        ]);
+        assert_eq!(violations(&schema, json!([])), ["`.`: expected object, got array"]);
+    }
+
+    #[test]
+    fn required_and_additional_properties() {
+        let schema = json!({"required": ["name", "email"], "properties": {"name": {}}, "additionalProperties": false});
+        assert_eq!(violations(&schema, json!({"name": "a", "e-mail": "b"})), [
+            "`.`: missing required property email",
+            "`.`: unexpected property e-mail",
+        ]);
+        assert!(violations(&schema, json!("not an object")).is_empty());
+    }
+
+    #[test]
+    fn enum_and_const() {
+        let schema = json!({"properties": {"status": {"enum": ["open", "closed", null]}, "v": {"const": 2}}});
+        assert!(violations(&schema, json!({"status": null, "v": 2})).is_empty());
+        assert_eq!(violations(&schema, json!({"status": "Open", "v": 2.5})), [
+            "`.status`: expected one of \"open\", \"closed\", null, got \"Open\"",
+            "`.v`: expected 2, got 2.5",
+        ]);
+    }
+
+    #[test]
+    fn pattern_uses_the_search_regexes() {
+        let schema = json!({"properties": {
+            "sku": {"pattern": "^[A-Z]{2}-\\d+$"},
+            "bad": {"pattern": "(unclosed"},
+        }});
+        assert!(violations(&schema, json!({"sku": "AB-12"})).is_empty());
+        assert_eq!(violations(&schema, json!({"sku": "ab-12", "bad": "x"})), [
+            "`.bad`: the schema's pattern (unclosed can't be checked: Unclosed ( at 1",
+            "`.sku`: doesn't match the pattern ^[A-Z]{2}-\\d+$",
+        ]);
+    }
+
+    #[test]
+    fn items_prefix_items_and_contains() {
+        let list = json!({"items": {"type": "integer"}, "minItems": 1, "uniqueItems": true});
+        assert_eq!(violations(&list, json!([1, "2", 1])), [
+            "`.`: items 0 and 2 are the same, expected unique items",
+            "`[1]`: expected integer, got string",
+        ]);
+        assert_eq!(violations(&list, json!([])), ["`.`: expected at least 1 items, got 0"]);
+        let tuple = json!({"prefixItems": [{"type": "string"}], "items": {"type": "boolean"}, "contains": {"const": true}});
+        assert_eq!(violations(&tuple, json!(["a", false, 1])), [
+            "`[2]`: expected boolean, got integer",
+            "`.`: no item matches the schema's `contains`",
+        ]);
+        let draft_7 = json!({"items": [{"type": "string"}], "additionalItems": false});
+        assert_eq!(violations(&draft_7, json!(["a", "b"])), ["`[1]`: no value is allowed here"]);
+    }
+
+    #[test]
+    fn refs_resolve_within_the_document() {
+        let document = json!({
+            "$ref": "#/definitions/Order",
+            "definitions": {
+                "Order": {"type": "object", "required": ["lines"], "properties": {"lines": {"items": {"$ref": "#/definitions/Line"}}}},
+                "Line": {"properties": {"qty": {"type": "integer", "minimum": 1}}},
+            },
+        });
+        assert_eq!(violations(&document, json!({"lines": [{"qty": 1}, {"qty": 0}]})), ["`.lines[1].qty`: expected at least 1, got 0"]);
+        assert_eq!(violations(&json!({"$ref": "#/nowhere"}), json!(1)), ["`.`: can't follow $ref #/nowhere"]);
+    }
+
+    #[test]
+    fn self_referring_refs_give_up() {
+        let document = json!({"$ref": "#"});
+        let found = violations(&document, json!({}));
+        assert_eq!(found, ["`.`: the schema nests too deep; is a $ref referring to itself?"]);
+    }
+
+    #[test]
+    fn combinations() {
+        let schema = json!({
+            "oneOf": [{"type": "integer"}, {"minimum": 5}],
+            "not": {"const": 7},
+        });
+        assert!(violations(&schema, json!(2)).is_empty());
+        assert_eq!(violations(&schema, json!(6)), ["`.`: matches 2 of the oneOf schemas, expected exactly one"]);
+        assert_eq!(violations(&schema, json!(7)), [
+            "`.`: matches 2 of the oneOf schemas, expected exactly one",
+            "`.`: matches the schema under `not`",
+        ]);
+    }
+
+    #[test]
+    fn reads_components_out_of_an_openapi_file() {
+        let path = temp_path("openapi.yaml");
+        fs::write(&path, "openapi: 3.0.0\ncomponents:\n  schemas:\n    User:\n      type: object\n      required: [name]\n      properties:\n        name: {type: string}\n        age: {$ref: '#/components/schemas/Age'}\n    Age:\n      type: integer\n      minimum: 0\n").unwrap();
+        let check = check_response(&format!("{}#User", path), r#"{"name": "x", "age": -1}"#);
+        assert_eq!(check.violations, ["`.age`: expected at least 0, got -1"]);
+        let check = check_response(&format!("{}#/components/schemas/Age", path), "3");
+        assert!(check.passed());
+        let check = check_response(&format!("{}#Missing", path), "3");
+        assert_eq!(check.error.unwrap(), format!("{} has nothing at #/components/schemas/Missing", path));
+        let check = check_response(&path, "not json");
+        assert!(check.error.unwrap().starts_with("the body isn't JSON"));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn body_schema_input() {
+        let schema = BodySchema::parse(" api.yaml#User strict ").unwrap();
+        assert_eq!((schema.path.as_str(), schema.strict), ("api.yaml#User", true));
+        assert_eq!(schema.to_input(), "api.yaml#User strict");
+        assert!(!BodySchema::parse("strict.json").unwrap().strict);
+        assert!(BodySchema::parse("#User").is_err());
+    }
+}
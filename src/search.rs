@@ -0,0 +1,646 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver},
+        Arc,
+    },
+    thread,
+};
+
+/// Matches kept per search; past this the count is shown as a lower bound.
+pub const MAX_MATCHES: usize = 10_000;
+
+/// A compiled search: plain text is matched case-insensitively unless it has a capital letter,
+/// regexes as written unless they start with `(?i)`.
+///
+/// Regexes support literals, `.`, `[...]` classes with ranges and negation, `\d \w \s` and their
+/// negations, `\b`, `^ $`, groups, `|`, and `* + ? {m,n}` with lazy variants. They run on a
+/// Pike VM, so time grows with the text rather than exploding on patterns like `(a*)*b`.
+pub struct Pattern {
+    program: Vec<Inst>,
+    ignore_case: bool,
+}
+
+/// Where a match is: line index and byte range within the line.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Match {
+    pub line: usize,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Pattern {
+    pub fn text(query: &str) -> Self {
+        let program = query.chars().map(Inst::Char).chain([Inst::Match]).collect();
+        Self { program, ignore_case: !query.chars().any(char::is_uppercase) }
+    }
+
+    pub fn regex(pattern: &str) -> Result<Self, String> {
+        let (ignore_case, pattern) = match pattern.strip_prefix("(?i)") {
+            Some(rest) => (true, rest),
+            None => (false, pattern),
+        };
+        let mut parser = Parser { chars: pattern.chars().collect(), at: 0 };
+        let node = parser.alternation()?;
+        if let Some(c) = parser.peek() {
+            return Err(format!("Unmatched {} at {}", c, parser.at + 1));
+        }
+        let mut program = vec![];
+        emit(&node, &mut program)?;
+        program.push(Inst::Match);
+        Ok(Self { program, ignore_case })
+    }
+
+    /// Non-overlapping matches in `line`, leftmost first. Empty matches are skipped.
+    pub fn find_all(&self, line: &str) -> Vec<(usize, usize)> {
+        let chars: Vec<(usize, char)> = line.char_indices().collect();
+        let mut found = vec![];
+        let mut from = 0;
+        while from <= chars.len() {
+            let Some((start, end)) = self.find_at(&chars, from) else { break };
+            let byte = |at: usize| chars.get(at).map_or(line.len(), |(i, _)| *i);
+            if end > start {
+                found.push((byte(start), byte(end)));
+                from = end;
+            } else {
+                from = start + 1;
+            }
+        }
+        found
+    }
+
+    /// Whether the pattern matches anywhere in `text`, an empty match included.
+    pub fn is_match(&self, text: &str) -> bool {
+        let chars: Vec<(usize, char)> = text.char_indices().collect();
+        self.find_at(&chars, 0).is_some()
+    }
+
+    /// The leftmost match starting at or after `from`, as char positions.
+    fn find_at(&self, chars: &[(usize, char)], from: usize) -> Option<(usize, usize)> {
+        let mut current: Vec<(usize, usize)> = vec![];
+        let mut next = vec![];
+        let mut seen = vec![usize::MAX; self.program.len()];
+        let mut found = None;
+        for at in from..=chars.len() {
+            if found.is_none() {
+                self.add(&mut current, &mut seen, 0, at, at, chars);
+            }
+            if current.is_empty() {
+                if found.is_some() {
+                    break;
+                }
+                continue;
+            }
+            let c = chars.get(at).map(|(_, c)| *c);
+            for &(pc, start) in &current {
+                match &self.program[pc] {
+                    Inst::Match => {
+                        found = Some((start, at));
+                        // Threads after this one have lower priority.
+                        break;
+                    }
+                    inst => {
+                        if c.is_some_and(|c| inst.accepts(c, self.ignore_case)) {
+                            self.add(&mut next, &mut seen, pc + 1, start, at + 1, chars);
+                        }
+                    }
+                }
+            }
+            current.clear();
+            std::mem::swap(&mut current, &mut next);
+        }
+        found
+    }
+
+    /// Follows jumps, splits and assertions from `pc`, queueing the instructions that consume
+    /// a char (or match) in priority order. `seen` marks what's queued for position `at`.
+    fn add(&self, queue: &mut Vec<(usize, usize)>, seen: &mut [usize], pc: usize, start: usize, at: usize, chars: &[(usize, char)]) {
+        if seen[pc] == at {
+            return;
+        }
+        seen[pc] = at;
+        match self.program[pc] {
+            Inst::Jump(to) => self.add(queue, seen, to, start, at, chars),
+            Inst::Split(first, second) => {
+                self.add(queue, seen, first, start, at, chars);
+                self.add(queue, seen, second, start, at, chars);
+            }
+            Inst::Assert(assertion) => {
+                if assertion.holds(chars, at) {
+                    self.add(queue, seen, pc + 1, start, at, chars);
+                }
+            }
+            _ => queue.push((pc, start)),
+        }
+    }
+}
+
+/// Searches `lines` on a thread of its own, so a large body doesn't hold up drawing.
+pub struct Search {
+    pub matches: Vec<Match>,
+    /// The match `n` and `N` last moved to.
+    pub current: usize,
+    /// Set once the thread has reported.
+    pub done: bool,
+    results: Receiver<Vec<Match>>,
+    cancel: Arc<AtomicBool>,
+}
+
+impl Search {
+    pub fn start(pattern: Pattern, lines: Vec<String>) -> Self {
+        let (sender, results) = mpsc::channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let stop = cancel.clone();
+        thread::spawn(move || {
+            let mut matches = vec![];
+            for (index, line) in lines.iter().enumerate() {
+                if stop.load(Ordering::Relaxed) || matches.len() >= MAX_MATCHES {
+                    break;
+                }
+                matches.extend(pattern.find_all(line).into_iter().map(|(start, end)| Match { line: index, start, end }));
+            }
+            matches.truncate(MAX_MATCHES);
+            let _ = sender.send(matches);
+        });
+        Self { matches: vec![], current: 0, done: false, results, cancel }
+    }
+
+    /// Takes in the matches once the thread has finished.
+    pub fn collect(&mut self) {
+        if let Ok(matches) = self.results.try_recv() {
+            self.matches = matches;
+            self.done = true;
+        }
+    }
+
+    pub fn next(&mut self) {
+        if !self.matches.is_empty() {
+            self.current = (self.current + 1) % self.matches.len();
+        }
+    }
+
+    pub fn previous(&mut self) {
+        if !self.matches.is_empty() {
+            self.current = (self.current + self.matches.len() - 1) % self.matches.len();
+        }
+    }
+}
+
+impl Drop for Search {
+    fn drop(&mut self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+}
+
+enum Inst {
+    Char(char),
+    Any,
+    Class(Class),
+    Assert(Assertion),
+    /// Try the first target before the second.
+    Split(usize, usize),
+    Jump(usize),
+    Match,
+}
+
+impl Inst {
+    fn accepts(&self, c: char, ignore_case: bool) -> bool {
+        match self {
+            Inst::Char(expected) if ignore_case => lower(*expected) == lower(c),
+            Inst::Char(expected) => *expected == c,
+            Inst::Any => true,
+            Inst::Class(class) => class.contains(c, ignore_case),
+            _ => false,
+        }
+    }
+}
+
+fn lower(c: char) -> char {
+    c.to_lowercase().next().unwrap_or(c)
+}
+
+#[derive(Clone, Copy)]
+enum Assertion {
+    LineStart,
+    LineEnd,
+    WordBoundary,
+    NotWordBoundary,
+}
+
+impl Assertion {
+    fn holds(self, chars: &[(usize, char)], at: usize) -> bool {
+        let word = |at: Option<usize>| at.and_then(|at| chars.get(at)).is_some_and(|(_, c)| is_word(*c));
+        let boundary = word(at.checked_sub(1)) != word(Some(at));
+        match self {
+            Assertion::LineStart => at == 0,
+            Assertion::LineEnd => at == chars.len(),
+            Assertion::WordBoundary => boundary,
+            Assertion::NotWordBoundary => !boundary,
+        }
+    }
+}
+
+fn is_word(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+#[derive(Clone)]
+struct Class {
+    negated: bool,
+    ranges: Vec<(char, char)>,
+}
+
+impl Class {
+    fn contains(&self, c: char, ignore_case: bool) -> bool {
+        let within = |c: char| self.ranges.iter().any(|(low, high)| (*low..=*high).contains(&c));
+        let found = within(c) || (ignore_case && (c.to_lowercase().any(within) || c.to_uppercase().any(within)));
+        found != self.negated
+    }
+
+    /// `\d`, `\w`, `\s` and the capitals that negate them.
+    fn shorthand(c: char) -> Option<Self> {
+        let ranges = match c.to_ascii_lowercase() {
+            'd' => vec![('0', '9')],
+            'w' => vec![('a', 'z'), ('A', 'Z'), ('0', '9'), ('_', '_')],
+            's' => vec![(' ', ' '), ('\t', '\t'), ('\n', '\n'), ('\r', '\r'), ('\x0b', '\x0c')],
+            _ => return None,
+        };
+        Some(Self { negated: c.is_ascii_uppercase(), ranges })
+    }
+}
+
+#[derive(Clone)]
+enum Node {
+    Char(char),
+    Any,
+    Class(Class),
+    Assert(Assertion),
+    Concat(Vec<Node>),
+    Alternate(Vec<Node>),
+    Repeat { node: Box<Node>, min: usize, max: Option<usize>, greedy: bool },
+}
+
+/// Repeat counts above this are refused, since each copy is compiled separately.
+const MAX_REPEAT: usize = 1000;
+
+struct Parser {
+    chars: Vec<char>,
+    at: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.at).copied()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        let matched = self.peek() == Some(c);
+        if matched {
+            self.at += 1;
+        }
+        matched
+    }
+
+    fn alternation(&mut self) -> Result<Node, String> {
+        let mut branches = vec![self.concatenation()?];
+        while self.eat('|') {
+            branches.push(self.concatenation()?);
+        }
+        Ok(if branches.len() == 1 {branches.remove(0)} else {Node::Alternate(branches)})
+    }
+
+    fn concatenation(&mut self) -> Result<Node, String> {
+        let mut nodes = vec![];
+        while let Some(c) = self.peek() {
+            if c == '|' || c == ')' {
+                break;
+            }
+            let atom = self.atom()?;
+            nodes.push(self.repetition(atom)?);
+        }
+        Ok(Node::Concat(nodes))
+    }
+
+    fn atom(&mut self) -> Result<Node, String> {
+        let position = self.at + 1;
+        let Some(c) = self.peek() else { return Err("Pattern ends early".to_string()) };
+        self.at += 1;
+        Ok(match c {
+            '.' => Node::Any,
+            '^' => Node::Assert(Assertion::LineStart),
+            '$' => Node::Assert(Assertion::LineEnd),
+            '(' => {
+                // Groups only group; `(?:` is accepted for habit's sake.
+                if self.eat('?') && !self.eat(':') {
+                    return Err(format!("Unsupported group syntax at {}", position));
+                }
+                let inner = self.alternation()?;
+                if !self.eat(')') {
+                    return Err(format!("Unclosed ( at {}", position));
+                }
+                inner
+            }
+            '[' => Node::Class(self.class(position)?),
+            '\\' => match self.escape(position)? {
+                Escaped::Char(c) => Node::Char(c),
+                Escaped::Class(class) => Node::Class(class),
+                Escaped::Assert(assertion) => Node::Assert(assertion),
+            },
+            '*' | '+' | '?' => return Err(format!("Nothing to repeat before {} at {}", c, position)),
+            '{' if self.counted(self.at - 1).is_some() => return Err(format!("Nothing to repeat before {{ at {}", position)),
+            c => Node::Char(c),
+        })
+    }
+
+    fn repetition(&mut self, mut node: Node) -> Result<Node, String> {
+        loop {
+            let position = self.at + 1;
+            let (min, max) = match self.peek() {
+                Some('*') => (0, None),
+                Some('+') => (1, None),
+                Some('?') => (0, Some(1)),
+                Some('{') => match self.counted(self.at) {
+                    Some(Ok(range)) => range,
+                    Some(Err(e)) => return Err(e),
+                    None => return Ok(node),
+                },
+                _ => return Ok(node),
+            };
+            if !matches!(self.peek(), Some('{')) {
+                self.at += 1;
+            } else {
+                self.at = self.chars[self.at..].iter().position(|c| *c == '}').map_or(self.chars.len(), |end| self.at + end + 1);
+            }
+            if matches!(node, Node::Assert(_) | Node::Repeat { .. }) {
+                return Err(format!("Nothing to repeat at {}", position));
+            }
+            let greedy = !self.eat('?');
+            node = Node::Repeat { node: Box::new(node), min, max, greedy };
+        }
+    }
+
+    /// `{m}`, `{m,}` or `{m,n}` with its brace at `at`, without consuming it. `None` when the
+    /// brace doesn't start a count, so it's taken literally.
+    fn counted(&self, at: usize) -> Option<Result<(usize, Option<usize>), String>> {
+        let rest: String = self.chars.get(at + 1..)?.iter().collect();
+        let inside = &rest[..rest.find('}')?];
+        let (min, max) = match inside.split_once(',') {
+            Some((min, "")) => (min.parse().ok()?, None),
+            Some((min, max)) => (min.parse().ok()?, Some(max.parse().ok()?)),
+            None => {
+                let count = inside.parse().ok()?;
+                (count, Some(count))
+            }
+        };
+        Some(match max {
+            Some(max) if max < min => Err(format!("Repeat range {{{}}} goes backwards", inside)),
+            _ if max.unwrap_or(min) > MAX_REPEAT => Err(format!("Repeat counts are limited to {}", MAX_REPEAT)),
+            _ => Ok((min, max)),
+        })
+    }
+
+    fn class(&mut self, position: usize) -> Result<Class, String> {
+        let negated = self.eat('^');
+        let mut ranges = vec![];
+        let mut first = true;
+        loop {
+            let Some(c) = self.peek() else { return Err(format!("Unclosed [ at {}", position)) };
+            self.at += 1;
+            if c == ']' && !first {
+                break;
+            }
+            first = false;
+            let low = match c {
+                '\\' => match self.escape(self.at)? {
+                    Escaped::Char(c) => c,
+                    Escaped::Class(class) if !class.negated => {
+                        ranges.extend(class.ranges);
+                        continue;
+                    }
+                    _ => return Err(format!("Unsupported escape in [ at {}", position)),
+                },
+                c => c,
+            };
+            let high = if self.peek() == Some('-') && self.chars.get(self.at + 1).is_some_and(|c| *c != ']') {
+                self.at += 1;
+                let c = self.peek().unwrap_or(low);
+                self.at += 1;
+                let high = if c == '\\' {
+                    match self.escape(self.at)? {
+                        Escaped::Char(c) => c,
+                        _ => return Err(format!("Bad range in [ at {}", position)),
+                    }
+                } else {
+                    c
+                };
+                if high < low {
+                    return Err(format!("Range {}-{} goes backwards", low, high));
+                }
+                high
+            } else {
+                low
+            };
+            ranges.push((low, high));
+        }
+        Ok(Class { negated, ranges })
+    }
+
+    fn escape(&mut self, position: usize) -> Result<Escaped, String> {
+        let Some(c) = self.peek() else { return Err(format!("Trailing \\ at {}", position)) };
+        self.at += 1;
+        Ok(match c {
+            'n' => Escaped::Char('\n'),
+            't' => Escaped::Char('\t'),
+            'r' => Escaped::Char('\r'),
+            'b' => Escaped::Assert(Assertion::WordBoundary),
+            'B' => Escaped::Assert(Assertion::NotWordBoundary),
+            c if c.is_ascii_alphanumeric() => match Class::shorthand(c) {
+                Some(class) => Escaped::Class(class),
+                None => return Err(format!("Unknown escape \\{} at {}", c, position)),
+            },
+            c => Escaped::Char(c),
+        })
+    }
+}
+
+enum Escaped {
+    Char(char),
+    Class(Class),
+    Assert(Assertion),
+}
+
+/// Programs past this many instructions are refused, so `(x{1000}){1000}` can't eat memory.
+const MAX_PROGRAM: usize = 100_000;
+
+fn emit(node: &Node, program: &mut Vec<Inst>) -> Result<(), String> {
+    if program.len() > MAX_PROGRAM {
+        return Err("Pattern is too large".to_string());
+    }
+    match node {
+        Node::Char(c) => program.push(Inst::Char(*c)),
+        Node::Any => program.push(Inst::Any),
+        Node::Class(class) => program.push(Inst::Class(class.clone())),
+        Node::Assert(assertion) => program.push(Inst::Assert(*assertion)),
+        Node::Concat(nodes) => {
+            for node in nodes {
+                emit(node, program)?;
+            }
+        }
+        Node::Alternate(branches) => {
+            let mut jumps = vec![];
+            for (i, branch) in branches.iter().enumerate() {
+                if i + 1 < branches.len() {
+                    let split = program.len();
+                    program.push(Inst::Split(split + 1, 0));
+                    emit(branch, program)?;
+                    jumps.push(program.len());
+                    program.push(Inst::Jump(0));
+                    let next = program.len();
+                    program[split] = Inst::Split(split + 1, next);
+                } else {
+                    emit(branch, program)?;
+                }
+            }
+            let end = program.len();
+            for jump in jumps {
+                program[jump] = Inst::Jump(end);
+            }
+        }
+        Node::Repeat { node, min, max, greedy } => {
+            for _ in 0..*min {
+                emit(node, program)?;
+            }
+            let split = |program: &mut Vec<Inst>, at: usize, body: usize, after: usize| {
+                program[at] = if *greedy {Inst::Split(body, after)} else {Inst::Split(after, body)};
+            };
+            match max {
+                None => {
+                    let at = program.len();
+                    program.push(Inst::Jump(0));
+                    emit(node, program)?;
+                    program.push(Inst::Jump(at));
+                    let after = program.len();
+                    split(program, at, at + 1, after);
+                }
+                Some(max) => {
+                    let mut splits = vec![];
+                    for _ in *min..*max {
+                        splits.push(program.len());
+                        program.push(Inst::Jump(0));
+                        emit(node, program)?;
+                    }
+                    let after = program.len();
+                    for at in splits {
+                        split(program, at, at + 1, after);
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn found<'a>(pattern: &str, line: &'a str) -> Vec<&'a str> {
+        Pattern::regex(pattern).unwrap().find_all(line).into_iter().map(|(start, end)| &line[start..end]).collect()
+    }
+
+    #[test]
+    fn classes_and_shorthands() {
+        assert_eq!(found("[a-c]+", "abcd cab"), ["abc", "cab"]);
+        assert_eq!(found("[^a-c ]+", "abcd cab"), ["d"]);
+        assert_eq!(found(r"\d+", "id 42, 7"), ["42", "7"]);
+        assert_eq!(found(r"\w+", "snake_case-été"), ["snake_case", "t"]);
+        assert_eq!(found(r"\S+", " a\tbc "), ["a", "bc"]);
+        assert_eq!(found(r"[\d.-]+", "v-1.2 x"), ["-1.2"]);
+        assert_eq!(found("[]a]", "a]"), ["a", "]"]);
+        assert_eq!(found("[a-]", "-b a"), ["-", "a"]);
+        assert_eq!(found("(?i)[A-C]", "aXb"), ["a", "b"]);
+        assert_eq!(found(r"\.\*", "a.*b"), [".*"]);
+    }
+
+    #[test]
+    fn anchors_and_word_boundaries() {
+        assert_eq!(found("^ab", "abab"), ["ab"]);
+        assert_eq!(found("ab$", "abab"), ["ab"]);
+        assert_eq!(Pattern::regex("^ab$").unwrap().find_all("abab"), []);
+        assert_eq!(found(r"\bcat\b", "cat concat cat_ cat."), ["cat", "cat"]);
+        assert_eq!(found(r"\Bcat", "cat concat"), ["cat"]);
+        assert!(Pattern::regex("^$").unwrap().is_match(""));
+    }
+
+    #[test]
+    fn alternation_prefers_the_earlier_branch() {
+        assert_eq!(found("a|ab", "ab"), ["a"]);
+        assert_eq!(found("ab|a", "ab"), ["ab"]);
+        assert_eq!(found("(?:get|post) /(users|orders)", "GET /x post /orders get /users"), ["post /orders", "get /users"]);
+        assert_eq!(found("x(|y)z", "xz xyz"), ["xz", "xyz"]);
+    }
+
+    #[test]
+    fn repetition_greedy_lazy_and_counted() {
+        assert_eq!(found("a+", "caaat"), ["aaa"]);
+        assert_eq!(found("a+?", "aaa"), ["a", "a", "a"]);
+        assert_eq!(found("<.*>", "<a><b>"), ["<a><b>"]);
+        assert_eq!(found("<.*?>", "<a><b>"), ["<a>", "<b>"]);
+        assert_eq!(found("colou?r", "color colour"), ["color", "colour"]);
+        assert_eq!(found(r"\d{2,3}", "1 12 1234"), ["12", "123"]);
+        assert_eq!(found(r"\d{2}", "12345"), ["12", "34"]);
+        assert_eq!(found(r"\d{2,}", "1 123456"), ["123456"]);
+        assert_eq!(found("a{,2}", "a{,2}"), ["a{,2}"]);
+        assert_eq!(found("x*", "axxb"), ["xx"]);
+    }
+
+    #[test]
+    fn nested_stars_stay_linear() {
+        let line = "a".repeat(5000);
+        assert_eq!(Pattern::regex("(a*)*b").unwrap().find_all(&line), []);
+        assert_eq!(Pattern::regex("(a|aa)+$").unwrap().find_all(&line), [(0, 5000)]);
+    }
+
+    #[test]
+    fn byte_ranges_around_multi_byte_chars() {
+        let line = "ü→x→y";
+        let pattern = Pattern::regex("→.").unwrap();
+        assert_eq!(pattern.find_all(line), [(2, 6), (6, 10)]);
+        assert_eq!(Pattern::text("Ü").find_all("aüÜ"), [(3, 5)]);
+        assert_eq!(Pattern::text("ü").find_all("aüÜ"), [(1, 3), (3, 5)]);
+    }
+
+    #[test]
+    fn invalid_patterns_say_what_and_where() {
+        let error = |pattern: &str| Pattern::regex(pattern).err().unwrap();
+        assert_eq!(error("(ab"), "Unclosed ( at 1");
+        assert_eq!(error("ab)"), "Unmatched ) at 3");
+        assert_eq!(error("[ab"), "Unclosed [ at 1");
+        assert_eq!(error("*a"), "Nothing to repeat before * at 1");
+        assert_eq!(error("a**"), "Nothing to repeat at 3");
+        assert_eq!(error("^*"), "Nothing to repeat at 2");
+        assert_eq!(error("a{3,1}"), "Repeat range {3,1} goes backwards");
+        assert_eq!(error("a{1001}"), "Repeat counts are limited to 1000");
+        assert_eq!(error("[z-a]"), "Range z-a goes backwards");
+        assert_eq!(error(r"\q"), r"Unknown escape \q at 1");
+        assert_eq!(error("a\\"), r"Trailing \ at 2");
+        assert_eq!(error("(?=a)"), "Unsupported group syntax at 1");
+        assert_eq!(error("(x{1000}){1000}"), "Pattern is too large");
+    }
+
+    #[test]
+    fn search_reports_matches_by_line_and_wraps_around() {
+        let lines = vec!["no".to_string(), "a1 b22".to_string(), "3".to_string()];
+        let mut search = Search::start(Pattern::regex(r"\d+").unwrap(), lines);
+        while !search.done {
+            thread::sleep(std::time::Duration::from_millis(1));
+            search.collect();
+        }
+        let at: Vec<(usize, usize, usize)> = search.matches.iter().map(|found| (found.line, found.start, found.end)).collect();
+        assert_eq!(at, [(1, 1, 2), (1, 4, 6), (2, 0, 1)]);
+        search.previous();
+        assert_eq!(search.current, 2);
+        search.next();
+        assert_eq!(search.current, 0);
+    }
+}
@@ -0,0 +1,79 @@
+use std::{fs, io, path::Path};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+/// The editor as it was at the last clean exit, restored on the next start unless `--fresh`.
+#[derive(Serialize, Deserialize, Default, Clone, PartialEq, Debug)]
+#[serde(default)]
+pub struct Session {
+    pub method: String,
+    pub url: String,
+    pub headers: String,
+    pub body: String,
+    /// Which editor field had focus, counting from the method tabs at 0.
+    pub field: usize,
+    pub cursor: usize,
+    /// Name of the active environment.
+    pub environment: Option<String>,
+    pub show_collection: bool,
+    pub show_console: bool,
+}
+
+impl Session {
+    /// Whether the request being composed differs, ignoring where the cursor is and what's shown.
+    pub fn edited_since(&self, other: &Session) -> bool {
+        (&self.method, &self.url, &self.headers, &self.body) != (&other.method, &other.url, &other.headers, &other.body)
+    }
+}
+
+/// Editor contents autosaved while editing, offered back on the next start if the app didn't
+/// exit cleanly. A clean exit deletes it.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Draft {
+    /// RFC 3339.
+    pub saved_at: String,
+    pub editor: Session,
+}
+
+/// Loads the session file; a missing file means there's nothing to restore.
+pub fn load(path: &Path) -> Result<Option<Session>, String> {
+    read(path)
+}
+
+pub fn save(path: &Path, session: &Session) -> Result<(), String> {
+    write(path, session)
+}
+
+pub fn load_draft(path: &Path) -> Result<Option<Draft>, String> {
+    read(path)
+}
+
+pub fn save_draft(path: &Path, draft: &Draft) -> Result<(), String> {
+    write(path, draft)
+}
+
+pub fn discard_draft(path: &Path) -> Result<(), String> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(format!("{}: {}", path.display(), e)),
+        _ => Ok(()),
+    }
+}
+
+fn read<T: DeserializeOwned>(path: &Path) -> Result<Option<T>, String> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(format!("{}: {}", path.display(), e)),
+    };
+    serde_json::from_str(&contents).map(Some).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+fn write<T: Serialize>(path: &Path, value: &T) -> Result<(), String> {
+    let write = || -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(value)?)
+    };
+    write().map_err(|e| format!("{}: {}", path.display(), e))
+}
@@ -0,0 +1,373 @@
+use std::path::{Path, PathBuf};
+
+use crate::{config::{Config, HistoryGrouping}, keymap::Keymap, redirect, theme::Preset};
+
+/// One option of `config.toml` as listed on the settings screen.
+pub struct Setting {
+    pub section: &'static str,
+    pub name: &'static str,
+    pub help: &'static str,
+    pub get: fn(&Config) -> String,
+    pub edit: Edit,
+    /// Only read at startup.
+    pub needs_restart: bool,
+}
+
+pub enum Edit {
+    /// Flipped by Enter rather than typed.
+    Toggle(fn(&mut Config)),
+    Text(fn(&mut Config, &str) -> Result<(), String>),
+}
+
+pub const SETTINGS: [Setting; 31] = [
+    Setting {
+        section: "General",
+        name: "no_color",
+        help: "Use the no-color theme even when NO_COLOR isn't set",
+        get: |config| config.no_color.to_string(),
+        edit: Edit::Toggle(|config| config.no_color = !config.no_color),
+        needs_restart: false,
+    },
+    Setting {
+        section: "General",
+        name: "theme",
+        help: "Colors: dark, light, solarized-dark or gruvbox",
+        get: |config| config.theme.name().to_string(),
+        edit: Edit::Text(|config, input| {
+            config.theme = Preset::parse(input)?;
+            Ok(())
+        }),
+        needs_restart: false,
+    },
+    Setting {
+        section: "General",
+        name: "terminal_title",
+        help: "Show the method, path and environment in the terminal's title",
+        get: |config| config.terminal_title.to_string(),
+        edit: Edit::Toggle(|config| config.terminal_title = !config.terminal_title),
+        needs_restart: true,
+    },
+    Setting {
+        section: "General",
+        name: "wrap_responses",
+        help: "Soft-wrap response bodies instead of scrolling them sideways",
+        get: |config| config.wrap_responses.to_string(),
+        edit: Edit::Toggle(|config| config.wrap_responses = !config.wrap_responses),
+        needs_restart: false,
+    },
+    Setting {
+        section: "General",
+        name: "history_grouping",
+        help: "Group the responses pane by none, host or saved request",
+        get: |config| config.history_grouping.name().to_string(),
+        edit: Edit::Text(|config, input| {
+            config.history_grouping = HistoryGrouping::parse(input)?;
+            Ok(())
+        }),
+        needs_restart: false,
+    },
+    Setting {
+        section: "General",
+        name: "secret_headers",
+        help: "More header names to mask like Authorization, comma-separated",
+        get: |config| config.secret_headers.join(", "),
+        edit: Edit::Text(|config, input| {
+            config.secret_headers = input.split(',').map(str::trim).filter(|name| !name.is_empty()).map(String::from).collect();
+            Ok(())
+        }),
+        needs_restart: false,
+    },
+    Setting {
+        section: "Requests",
+        name: "timeout_secs",
+        help: "Seconds to wait for the whole response, 1 to 3600; empty for no limit",
+        get: |config| config.timeout_secs.map_or(String::new(), |seconds| seconds.to_string()),
+        edit: Edit::Text(|config, input| {
+            config.timeout_secs = match input {
+                "" => None,
+                input => Some(number(input, 1, 3600)?),
+            };
+            Ok(())
+        }),
+        needs_restart: false,
+    },
+    Setting {
+        section: "Requests",
+        name: "connect_timeout_secs",
+        help: "Seconds to wait for the connection, 1 to 600; empty for no limit",
+        get: |config| config.connect_timeout_secs.map_or(String::new(), |seconds| seconds.to_string()),
+        edit: Edit::Text(|config, input| {
+            config.connect_timeout_secs = match input {
+                "" => None,
+                input => Some(number(input, 1, 600)?),
+            };
+            Ok(())
+        }),
+        needs_restart: false,
+    },
+    Setting {
+        section: "Requests",
+        name: "connect_budget_secs",
+        help: "Seconds to spend trying a host's addresses in turn, 1 to 600; empty for no limit",
+        get: |config| config.connect_budget_secs.map_or(String::new(), |seconds| seconds.to_string()),
+        edit: Edit::Text(|config, input| {
+            config.connect_budget_secs = match input {
+                "" => None,
+                input => Some(number(input, 1, 600)?),
+            };
+            Ok(())
+        }),
+        needs_restart: false,
+    },
+    Setting {
+        section: "Requests",
+        name: "read_timeout_secs",
+        help: "Seconds the server may go quiet mid-response, 1 to 600; empty for 30",
+        get: |config| config.read_timeout_secs.map_or(String::new(), |seconds| seconds.to_string()),
+        edit: Edit::Text(|config, input| {
+            config.read_timeout_secs = match input {
+                "" => None,
+                input => Some(number(input, 1, 600)?),
+            };
+            Ok(())
+        }),
+        needs_restart: false,
+    },
+    Setting {
+        section: "Requests",
+        name: "redirects",
+        help: "off, max hops, or hops then keep-auth",
+        get: |config| config.redirects.describe(),
+        edit: Edit::Text(|config, input| {
+            config.redirects = redirect::Policy::parse(input)?;
+            Ok(())
+        }),
+        needs_restart: false,
+    },
+    Setting {
+        section: "Requests",
+        name: "request_id",
+        help: "Attach an X-Request-Id with a fresh UUID to requests that don't set one",
+        get: |config| config.request_id.to_string(),
+        edit: Edit::Toggle(|config| config.request_id = !config.request_id),
+        needs_restart: false,
+    },
+    Setting {
+        section: "Responses",
+        name: "max_size_mb",
+        help: "Largest response body kept, in MB, 0 to 4096; 0 keeps any size",
+        get: |config| config.responses.max_size_mb.to_string(),
+        edit: Edit::Text(|config, input| {
+            config.responses.max_size_mb = number(input, 0, 4096)?;
+            Ok(())
+        }),
+        needs_restart: false,
+    },
+    Setting {
+        section: "Responses",
+        name: "collapse_repeats",
+        help: "Fold a request sent several times in a row into one history entry with a run count",
+        get: |config| config.responses.collapse_repeats.to_string(),
+        edit: Edit::Toggle(|config| config.responses.collapse_repeats = !config.responses.collapse_repeats),
+        needs_restart: false,
+    },
+    Setting {
+        section: "Responses",
+        name: "open_with",
+        help: "Pager for \"open response with\", or a command with {} for the file; empty for $PAGER or less -R",
+        get: |config| config.responses.open_with.clone().unwrap_or_default(),
+        edit: Edit::Text(|config, input| {
+            config.responses.open_with = Some(input.trim()).filter(|command| !command.is_empty()).map(String::from);
+            Ok(())
+        }),
+        needs_restart: false,
+    },
+    Setting {
+        section: "Collection runner",
+        name: "stop_on_failure",
+        help: "Stop a run after a response that fails a hook, its baseline or its schema",
+        get: |config| config.runner.stop_on_failure.to_string(),
+        edit: Edit::Toggle(|config| config.runner.stop_on_failure = !config.runner.stop_on_failure),
+        needs_restart: false,
+    },
+    Setting {
+        section: "Collection runner",
+        name: "stop_on_error",
+        help: "Stop a run after a request that got no response",
+        get: |config| config.runner.stop_on_error.to_string(),
+        edit: Edit::Toggle(|config| config.runner.stop_on_error = !config.runner.stop_on_error),
+        needs_restart: false,
+    },
+    Setting {
+        section: "DNS",
+        name: "ttl_secs",
+        help: "Seconds to reuse a looked-up address, 0 to 86400; 0 looks names up every time",
+        get: |config| config.dns.ttl_secs.to_string(),
+        edit: Edit::Text(|config, input| {
+            config.dns.ttl_secs = number(input, 0, 86400)?;
+            Ok(())
+        }),
+        needs_restart: false,
+    },
+    Setting {
+        section: "Rate limiting",
+        name: "max_auto_retries",
+        help: "Times a rate-limited retry is rescheduled without asking, 0 to 20",
+        get: |config| config.rate_limit.max_auto_retries.to_string(),
+        edit: Edit::Text(|config, input| {
+            config.rate_limit.max_auto_retries = number(input, 0, 20)?;
+            Ok(())
+        }),
+        needs_restart: false,
+    },
+    Setting {
+        section: "Request log",
+        name: "enabled",
+        help: "Append every request sent to a JSON-lines file",
+        get: |config| config.request_log.enabled.to_string(),
+        edit: Edit::Toggle(|config| config.request_log.enabled = !config.request_log.enabled),
+        needs_restart: false,
+    },
+    Setting {
+        section: "Request log",
+        name: "path",
+        help: "The log file; its directory must exist",
+        get: |config| config.request_log.path.display().to_string(),
+        edit: Edit::Text(|config, input| {
+            config.request_log.path = existing_parent(input)?;
+            Ok(())
+        }),
+        needs_restart: false,
+    },
+    Setting {
+        section: "Request log",
+        name: "max_megabytes",
+        help: "Size at which the log is rotated, 1 to 1024",
+        get: |config| config.request_log.max_megabytes.to_string(),
+        edit: Edit::Text(|config, input| {
+            config.request_log.max_megabytes = number(input, 1, 1024)?;
+            Ok(())
+        }),
+        needs_restart: false,
+    },
+    Setting {
+        section: "Request log",
+        name: "keep_files",
+        help: "Files kept, counting the one being written, 1 to 100",
+        get: |config| config.request_log.keep_files.to_string(),
+        edit: Edit::Text(|config, input| {
+            config.request_log.keep_files = number(input, 1, 100)?;
+            Ok(())
+        }),
+        needs_restart: false,
+    },
+    Setting {
+        section: "Baseline",
+        name: "ignore_headers",
+        help: "Header names left out of baseline comparisons, comma-separated",
+        get: |config| config.baseline.ignore_headers.join(", "),
+        edit: Edit::Text(|config, input| {
+            config.baseline.ignore_headers = list(input);
+            Ok(())
+        }),
+        needs_restart: false,
+    },
+    Setting {
+        section: "Baseline",
+        name: "ignore_paths",
+        help: "JSON paths like .items[*].id left out of comparisons, comma-separated",
+        get: |config| config.baseline.ignore_paths.join(", "),
+        edit: Edit::Text(|config, input| {
+            config.baseline.ignore_paths = list(input);
+            Ok(())
+        }),
+        needs_restart: false,
+    },
+    Setting {
+        section: "Test server",
+        name: "delay_ms",
+        help: "Milliseconds the built-in server waits before answering, 0 to 60000",
+        get: |config| config.test_server.delay_ms.to_string(),
+        edit: Edit::Text(|config, input| {
+            config.test_server.delay_ms = number(input, 0, 60_000)?;
+            Ok(())
+        }),
+        needs_restart: false,
+    },
+    Setting {
+        section: "Test server",
+        name: "fail_percent",
+        help: "Share of requests the built-in server answers with an error, 0 to 100",
+        get: |config| config.test_server.fail_percent.to_string(),
+        edit: Edit::Text(|config, input| {
+            config.test_server.fail_percent = number(input, 0, 100)?;
+            Ok(())
+        }),
+        needs_restart: false,
+    },
+    Setting {
+        section: "Test server",
+        name: "stall",
+        help: "Hold every request to the built-in server for minutes",
+        get: |config| config.test_server.stall.to_string(),
+        edit: Edit::Toggle(|config| config.test_server.stall = !config.test_server.stall),
+        needs_restart: false,
+    },
+    Setting {
+        section: "Test server",
+        name: "stall_body",
+        help: "Send the headers and half the body, then hold the rest back for minutes",
+        get: |config| config.test_server.stall_body.to_string(),
+        edit: Edit::Toggle(|config| config.test_server.stall_body = !config.test_server.stall_body),
+        needs_restart: false,
+    },
+    Setting {
+        section: "Test server",
+        name: "cut_body",
+        help: "Send the headers and half the body, then close the connection",
+        get: |config| config.test_server.cut_body.to_string(),
+        edit: Edit::Toggle(|config| config.test_server.cut_body = !config.test_server.cut_body),
+        needs_restart: false,
+    },
+    Setting {
+        section: "Keys",
+        name: "keys",
+        help: "Binding overrides like resend_last=ctrl+r, comma-separated",
+        get: |config| config.keys.iter().map(|(action, key)| format!("{}={}", action, key)).collect::<Vec<_>>().join(", "),
+        edit: Edit::Text(|config, input| {
+            let mut keys = std::collections::BTreeMap::new();
+            for pair in list(input) {
+                let Some((action, key)) = pair.split_once('=') else {
+                    return Err(format!("Expected action=key: {}", pair));
+                };
+                keys.insert(action.trim().to_string(), key.trim().to_string());
+            }
+            if let Some(e) = Keymap::from_config(&keys).1.into_iter().next() {
+                return Err(e);
+            }
+            config.keys = keys;
+            Ok(())
+        }),
+        needs_restart: false,
+    },
+];
+
+fn number<T: std::str::FromStr + PartialOrd + std::fmt::Display>(input: &str, min: T, max: T) -> Result<T, String> {
+    match input.parse() {
+        Ok(n) if n >= min && n <= max => Ok(n),
+        _ => Err(format!("Expected a number from {} to {}: {}", min, max, input)),
+    }
+}
+
+fn list(input: &str) -> Vec<String> {
+    input.split(',').map(str::trim).filter(|item| !item.is_empty()).map(String::from).collect()
+}
+
+fn existing_parent(input: &str) -> Result<PathBuf, String> {
+    let path = PathBuf::from(input);
+    match path.parent() {
+        _ if input.is_empty() => Err("Expected a path".to_string()),
+        Some(dir) if dir != Path::new("") && !dir.is_dir() => Err(format!("No such directory: {}", dir.display())),
+        _ => Ok(path),
+    }
+}
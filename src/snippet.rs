@@ -0,0 +1,413 @@
+use serde_json::Value;
+
+use crate::{
+    curl, encoding,
+    request::{header_value, ResolvedRequest},
+};
+
+/// The languages and libraries a request can be exported to.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Language {
+    Python,
+    JavaScript,
+    Rust,
+    Curl,
+}
+
+impl Language {
+    pub const ALL: [Language; 4] = [Language::Python, Language::JavaScript, Language::Rust, Language::Curl];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Language::Python => "Python requests",
+            Language::JavaScript => "JavaScript fetch",
+            Language::Rust => "Rust reqwest",
+            Language::Curl => "curl",
+        }
+    }
+
+    /// The language's double-quoted string literal for `text`.
+    fn quote(self, text: &str) -> String {
+        if self == Language::Rust {
+            // Debug formatting escapes exactly what a Rust literal needs.
+            return format!("{:?}", text);
+        }
+        let mut out = String::with_capacity(text.len() + 2);
+        out.push('"');
+        for c in text.chars() {
+            match c {
+                '"' => out.push_str("\\\""),
+                '\\' => out.push_str("\\\\"),
+                '\n' => out.push_str("\\n"),
+                '\r' => out.push_str("\\r"),
+                '\t' => out.push_str("\\t"),
+                // Line terminators inside string literals in older JavaScript engines.
+                '\u{2028}' | '\u{2029}' if self == Language::JavaScript => out.push_str(&format!("\\u{:04x}", c as u32)),
+                c if c.is_control() => match self {
+                    Language::Python if (c as u32) < 0x100 => out.push_str(&format!("\\x{:02x}", c as u32)),
+                    Language::Python => out.push_str(&format!("\\u{:04x}", c as u32)),
+                    _ => out.push_str(&format!("\\u{:04x}", c as u32)),
+                },
+                c => out.push(c),
+            }
+        }
+        out.push('"');
+        out
+    }
+
+    /// The spelling of JSON's `null`, `true` and `false`.
+    fn keyword(self, value: &Value) -> String {
+        match (self, value) {
+            (Language::Python, Value::Null) => "None".to_string(),
+            (Language::Python, Value::Bool(true)) => "True".to_string(),
+            (Language::Python, Value::Bool(false)) => "False".to_string(),
+            _ => value.to_string(),
+        }
+    }
+
+    fn indent(self) -> &'static str {
+        match self {
+            Language::JavaScript => "  ",
+            _ => "    ",
+        }
+    }
+}
+
+/// How the body goes into the snippet.
+enum Body {
+    None,
+    Json(Value),
+    Form(Vec<(String, String)>),
+    Text(String),
+}
+
+enum Auth {
+    Basic(String, String),
+    Bearer(String),
+}
+
+/// The request taken apart into what the libraries have their own arguments for.
+struct Parts {
+    method: String,
+    /// Without the query when that's in `query`.
+    url: String,
+    query: Vec<(String, String)>,
+    headers: Vec<(String, String)>,
+    auth: Option<Auth>,
+    body: Body,
+}
+
+/// `request` as code for `language`, sent to `scheme://` and printing the response.
+pub fn render(language: Language, request: &ResolvedRequest, scheme: &str) -> String {
+    let parts = || Parts::new(language, request, scheme);
+    match language {
+        Language::Python => python(&parts()),
+        Language::JavaScript => javascript(&parts()),
+        Language::Rust => rust(&parts()),
+        Language::Curl => curl::command(request, scheme),
+    }
+}
+
+impl Parts {
+    fn new(language: Language, request: &ResolvedRequest, scheme: &str) -> Self {
+        let url = format!("{}://{}{}", scheme, request.address, request.endpoint);
+        // fetch takes the query as part of the URL.
+        let (url, query) = match url.split_once('?').and_then(|(base, query)| Some((base, pairs(query, false)?))) {
+            Some((base, query)) if language != Language::JavaScript => (base.to_string(), query),
+            _ => (url, vec![]),
+        };
+        // The libraries work these out for themselves.
+        let mut headers: Vec<(String, String)> = request
+            .headers
+            .iter()
+            .filter(|(name, _)| !["Host", "Content-Length"].iter().any(|own| name.eq_ignore_ascii_case(own)))
+            .cloned()
+            .collect();
+        let auth = header_value(&headers, "Authorization").and_then(|value| auth(language, value));
+        if auth.is_some() {
+            headers.retain(|(name, _)| !name.eq_ignore_ascii_case("Authorization"));
+        }
+        let content_type = header_value(&headers, "Content-Type").unwrap_or_default().to_ascii_lowercase();
+        let body = match &request.body {
+            body if body.is_empty() => Body::None,
+            body if content_type.contains("json") => match serde_json::from_str(body) {
+                Ok(value) => Body::Json(value),
+                Err(_) => Body::Text(body.clone()),
+            },
+            body if content_type.starts_with("application/x-www-form-urlencoded") => match pairs(body, true) {
+                Some(fields) => Body::Form(fields),
+                None => Body::Text(body.clone()),
+            },
+            body => Body::Text(body.clone()),
+        };
+        // Python's `json=` and reqwest's `.json()` set exactly this; form bodies get theirs everywhere.
+        let implied = match &body {
+            Body::Json(_) => language != Language::JavaScript && content_type == "application/json",
+            Body::Form(_) => content_type == "application/x-www-form-urlencoded",
+            _ => false,
+        };
+        if implied {
+            headers.retain(|(name, _)| !name.eq_ignore_ascii_case("Content-Type"));
+        }
+        Self { method: request.method.to_ascii_uppercase(), url, query, headers, auth, body }
+    }
+}
+
+/// `name=value` pairs, percent-decoded. `None` when decoding would change what's sent: a pair
+/// without `=`, text that isn't UTF-8 once decoded, or in a query, a `+` the library would
+/// encode rather than read as a space.
+fn pairs(text: &str, plus_is_space: bool) -> Option<Vec<(String, String)>> {
+    if !plus_is_space && text.contains('+') {
+        return None;
+    }
+    let decode = |part: &str| {
+        let part = if plus_is_space {part.replace('+', " ")} else {part.to_string()};
+        String::from_utf8(encoding::percent_decode(&part).ok()?).ok()
+    };
+    text.split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (name, value) = pair.split_once('=')?;
+            Some((decode(name)?, decode(value)?))
+        })
+        .collect()
+}
+
+/// Basic auth as a user and password, or a bearer token, when the language has a shorthand
+/// for it.
+fn auth(language: Language, value: &str) -> Option<Auth> {
+    let (scheme, credentials) = value.trim().split_once(' ')?;
+    match scheme.to_ascii_lowercase().as_str() {
+        "basic" if language != Language::JavaScript => {
+            let decoded = String::from_utf8(encoding::base64_decode(credentials.trim()).ok()?).ok()?;
+            let (user, password) = decoded.split_once(':')?;
+            Some(Auth::Basic(user.to_string(), password.to_string()))
+        }
+        "bearer" if language == Language::Rust => Some(Auth::Bearer(credentials.trim().to_string())),
+        _ => None,
+    }
+}
+
+/// A JSON value as a literal of the language, indented to sit at `depth`.
+fn literal(language: Language, value: &Value, depth: usize) -> String {
+    let inner = language.indent().repeat(depth + 1);
+    let outer = language.indent().repeat(depth);
+    match value {
+        Value::Array(items) if items.is_empty() => "[]".to_string(),
+        Value::Object(fields) if fields.is_empty() => "{}".to_string(),
+        Value::Array(items) => {
+            let items: Vec<String> = items.iter().map(|item| format!("{}{},\n", inner, literal(language, item, depth + 1))).collect();
+            format!("[\n{}{}]", trailing_comma(language, items), outer)
+        }
+        Value::Object(fields) => {
+            let fields: Vec<String> = fields
+                .iter()
+                .map(|(name, value)| format!("{}{}: {},\n", inner, language.quote(name), literal(language, value, depth + 1)))
+                .collect();
+            format!("{{\n{}{}}}", trailing_comma(language, fields), outer)
+        }
+        Value::String(text) => language.quote(text),
+        other => language.keyword(other),
+    }
+}
+
+/// `json!` takes no comma after the last entry.
+fn trailing_comma(language: Language, mut lines: Vec<String>) -> String {
+    if language == Language::Rust {
+        if let Some(last) = lines.last_mut() {
+            last.remove(last.len() - 2);
+        }
+    }
+    lines.concat()
+}
+
+/// A Python dict of pairs, or a list of tuples when a name repeats.
+fn python_pairs(pairs: &[(String, String)], depth: usize) -> String {
+    let inner = "    ".repeat(depth + 1);
+    let repeated = pairs.iter().enumerate().any(|(i, (name, _))| pairs[..i].iter().any(|(other, _)| other == name));
+    let entries: String = pairs
+        .iter()
+        .map(|(name, value)| match repeated {
+            true => format!("{}({}, {}),\n", inner, Language::Python.quote(name), Language::Python.quote(value)),
+            false => format!("{}{}: {},\n", inner, Language::Python.quote(name), Language::Python.quote(value)),
+        })
+        .collect();
+    let outer = "    ".repeat(depth);
+    match repeated {
+        true => format!("[\n{}{}]", entries, outer),
+        false => format!("{{\n{}{}}}", entries, outer),
+    }
+}
+
+fn python(parts: &Parts) -> String {
+    let call = match parts.method.as_str() {
+        "GET" | "POST" | "PUT" | "PATCH" | "DELETE" | "HEAD" | "OPTIONS" => format!("requests.{}(\n", parts.method.to_ascii_lowercase()),
+        other => format!("requests.request(\n    {},\n", Language::Python.quote(other)),
+    };
+    let mut out = format!("import requests\n\nresponse = {}    {},\n", call, Language::Python.quote(&parts.url));
+    if !parts.query.is_empty() {
+        out.push_str(&format!("    params={},\n", python_pairs(&parts.query, 1)));
+    }
+    if !parts.headers.is_empty() {
+        // A dict holds a name once; repeated headers are joined the way HTTP allows.
+        let mut headers: Vec<(String, String)> = vec![];
+        for (name, value) in &parts.headers {
+            match headers.iter_mut().find(|(own, _)| own.eq_ignore_ascii_case(name)) {
+                Some((_, joined)) => *joined = format!("{}, {}", joined, value),
+                None => headers.push((name.clone(), value.clone())),
+            }
+        }
+        out.push_str(&format!("    headers={},\n", python_pairs(&headers, 1)));
+    }
+    match &parts.body {
+        Body::None => {}
+        Body::Json(value) => out.push_str(&format!("    json={},\n", literal(Language::Python, value, 1))),
+        Body::Form(fields) => out.push_str(&format!("    data={},\n", python_pairs(fields, 1))),
+        Body::Text(text) => out.push_str(&format!("    data={},\n", Language::Python.quote(text))),
+    }
+    if let Some(Auth::Basic(user, password)) = &parts.auth {
+        out.push_str(&format!("    auth=({}, {}),\n", Language::Python.quote(user), Language::Python.quote(password)));
+    }
+    out.push_str(")\n\nprint(response.status_code)\nprint(response.text)\n");
+    out
+}
+
+fn javascript(parts: &Parts) -> String {
+    let js = Language::JavaScript;
+    let mut options = String::new();
+    if parts.method != "GET" {
+        options.push_str(&format!("  method: {},\n", js.quote(&parts.method)));
+    }
+    if !parts.headers.is_empty() {
+        let repeated = parts.headers.iter().enumerate().any(|(i, (name, _))| parts.headers[..i].iter().any(|(other, _)| other.eq_ignore_ascii_case(name)));
+        let entries: String = parts
+            .headers
+            .iter()
+            .map(|(name, value)| match repeated {
+                true => format!("    [{}, {}],\n", js.quote(name), js.quote(value)),
+                false => format!("    {}: {},\n", js.quote(name), js.quote(value)),
+            })
+            .collect();
+        match repeated {
+            true => options.push_str(&format!("  headers: [\n{}  ],\n", entries)),
+            false => options.push_str(&format!("  headers: {{\n{}  }},\n", entries)),
+        }
+    }
+    match &parts.body {
+        Body::None => {}
+        Body::Json(value) => options.push_str(&format!("  body: JSON.stringify({}),\n", literal(js, value, 1))),
+        Body::Form(fields) => {
+            let entries: String = fields.iter().map(|(name, value)| format!("    [{}, {}],\n", js.quote(name), js.quote(value))).collect();
+            options.push_str(&format!("  body: new URLSearchParams([\n{}  ]),\n", entries));
+        }
+        Body::Text(text) => options.push_str(&format!("  body: {},\n", js.quote(text))),
+    }
+    let call = match options.is_empty() {
+        true => format!("fetch({})", js.quote(&parts.url)),
+        false => format!("fetch({}, {{\n{}}})", js.quote(&parts.url), options),
+    };
+    format!("const response = await {};\n\nconsole.log(response.status);\nconsole.log(await response.text());\n", call)
+}
+
+fn rust(parts: &Parts) -> String {
+    let rs = Language::Rust;
+    let call = match parts.method.as_str() {
+        "GET" | "POST" | "PUT" | "PATCH" | "DELETE" | "HEAD" => format!("{}({})", parts.method.to_ascii_lowercase(), rs.quote(&parts.url)),
+        "OPTIONS" => format!("request(reqwest::Method::OPTIONS, {})", rs.quote(&parts.url)),
+        other => format!("request(reqwest::Method::from_bytes({}.as_bytes())?, {})", rs.quote(other), rs.quote(&parts.url)),
+    };
+    let mut out = format!("let client = reqwest::blocking::Client::new();\nlet response = client\n    .{}\n", call);
+    let tuples = |pairs: &[(String, String)]| pairs.iter().map(|(name, value)| format!("({}, {})", rs.quote(name), rs.quote(value))).collect::<Vec<_>>().join(", ");
+    if !parts.query.is_empty() {
+        out.push_str(&format!("    .query(&[{}])\n", tuples(&parts.query)));
+    }
+    for (name, value) in &parts.headers {
+        out.push_str(&format!("    .header({}, {})\n", rs.quote(name), rs.quote(value)));
+    }
+    match &parts.auth {
+        Some(Auth::Basic(user, password)) => out.push_str(&format!("    .basic_auth({}, Some({}))\n", rs.quote(user), rs.quote(password))),
+        Some(Auth::Bearer(token)) => out.push_str(&format!("    .bearer_auth({})\n", rs.quote(token))),
+        None => {}
+    }
+    match &parts.body {
+        Body::None => {}
+        Body::Json(value) => out.push_str(&format!("    .json(&serde_json::json!({}))\n", literal(rs, value, 1))),
+        Body::Form(fields) => out.push_str(&format!("    .form(&[{}])\n", tuples(fields))),
+        Body::Text(text) => out.push_str(&format!("    .body({})\n", rs.quote(text))),
+    }
+    out.push_str("    .send()?;\n\nprintln!(\"{}\", response.status());\nprintln!(\"{}\", response.text()?);\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(headers: &[(&str, &str)], body: &str) -> ResolvedRequest {
+        ResolvedRequest {
+            method: "POST".to_string(),
+            scheme: "http".to_string(),
+            address: "api.test".to_string(),
+            dial_address: "api.test:80".to_string(),
+            connect_to: None,
+            endpoint: "/run?cmd=%24(id)".to_string(),
+            headers: headers.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect(),
+            body: body.to_string(),
+            host_defaults: vec![],
+        }
+    }
+
+    /// Text that's trouble in a string literal or a shell: quotes, a backslash, a newline, a tab,
+    /// a line separator, and `$`, backtick and `;` sequences a shell would run.
+    const AWKWARD: &str = "say \"hi\" it's \\ $(id) `id` ${HOME}; rm\n\tend\u{2028}";
+
+    #[test]
+    fn quoting_per_language() {
+        let escaped = r#""say \"hi\" it's \\ $(id) `id` ${HOME}; rm\n\tend"#;
+        assert_eq!(Language::Python.quote(AWKWARD), format!("{}\u{2028}\"", escaped));
+        assert_eq!(Language::JavaScript.quote(AWKWARD), format!("{}\\u2028\"", escaped));
+        assert_eq!(Language::Rust.quote(AWKWARD), format!("{}\\u{{2028}}\"", escaped));
+        assert_eq!(Language::Python.quote("\u{7}\u{85}\u{10ffff}"), "\"\\x07\\x85\u{10ffff}\"");
+        assert_eq!(Language::JavaScript.quote("\u{1b}"), r#""\u001b""#);
+    }
+
+    #[test]
+    fn text_bodies_and_headers_go_in_as_literals() {
+        let request = request(&[("X-Note", "a \"b\" $(c)"), ("Content-Type", "text/plain")], AWKWARD);
+        let python = render(Language::Python, &request, "http");
+        assert!(python.contains(&format!("    data={},\n", Language::Python.quote(AWKWARD))), "{}", python);
+        assert!(python.contains(r#""X-Note": "a \"b\" $(c)","#), "{}", python);
+        assert!(python.contains("params={\n        \"cmd\": \"$(id)\",\n    },"), "{}", python);
+        let javascript = render(Language::JavaScript, &request, "http");
+        assert!(javascript.contains(r#"fetch("http://api.test/run?cmd=%24(id)", {"#), "{}", javascript);
+        assert!(javascript.contains(r#"  body: "say \"hi\" it's \\ $(id) `id` ${HOME}; rm\n\tend\u2028","#), "{}", javascript);
+        let rust = render(Language::Rust, &request, "http");
+        assert!(rust.contains(r#"    .header("X-Note", "a \"b\" $(c)")"#), "{}", rust);
+        assert!(rust.contains(r#"    .query(&[("cmd", "$(id)")])"#), "{}", rust);
+    }
+
+    #[test]
+    fn json_bodies_become_literals() {
+        let request = request(&[("Content-Type", "application/json")], r#"{"q": "a\"b\nc", "n": null, "ok": true, "list": []}"#);
+        assert_eq!(render(Language::Python, &request, "https"), concat!(
+            "import requests\n\n",
+            "response = requests.post(\n",
+            "    \"https://api.test/run\",\n",
+            "    params={\n        \"cmd\": \"$(id)\",\n    },\n",
+            "    json={\n        \"list\": [],\n        \"n\": None,\n        \"ok\": True,\n        \"q\": \"a\\\"b\\nc\",\n    },\n",
+            ")\n\nprint(response.status_code)\nprint(response.text)\n",
+        ));
+        let rust = render(Language::Rust, &request, "https");
+        assert!(rust.contains("    .json(&serde_json::json!({\n        \"list\": [],\n        \"n\": null,\n        \"ok\": true,\n        \"q\": \"a\\\"b\\nc\"\n    }))\n"), "{}", rust);
+    }
+
+    #[test]
+    fn auth_shorthands() {
+        let basic = request(&[("Authorization", &format!("Basic {}", encoding::base64_encode(b"me:p\"w")))], "");
+        assert!(render(Language::Python, &basic, "http").contains(r#"    auth=("me", "p\"w"),"#));
+        assert!(render(Language::Rust, &basic, "http").contains(r#"    .basic_auth("me", Some("p\"w"))"#));
+        let bearer = request(&[("Authorization", "Bearer [REDACTED]")], "");
+        assert!(render(Language::Rust, &bearer, "http").contains(r#"    .bearer_auth("[REDACTED]")"#));
+        assert!(render(Language::JavaScript, &bearer, "http").contains(r#"    "Authorization": "Bearer [REDACTED]","#));
+    }
+}
@@ -0,0 +1,139 @@
+use std::cmp::Reverse;
+
+use crate::history::HistoryEntry;
+
+/// Exchanges of one method and URL, the URL as written, so saved requests keep their
+/// placeholders.
+pub struct Endpoint {
+    pub method: String,
+    pub url: String,
+    pub runs: usize,
+    /// Exchanges that got no response or a 4xx or 5xx.
+    pub errors: usize,
+    /// Of the exchanges that got a response, sorted.
+    latencies_ms: Vec<u64>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum SortBy {
+    Runs,
+    ErrorRate,
+    Median,
+    P95,
+}
+
+impl SortBy {
+    pub fn next(self) -> Self {
+        match self {
+            SortBy::Runs => SortBy::ErrorRate,
+            SortBy::ErrorRate => SortBy::Median,
+            SortBy::Median => SortBy::P95,
+            SortBy::P95 => SortBy::Runs,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SortBy::Runs => "runs",
+            SortBy::ErrorRate => "error rate",
+            SortBy::Median => "median",
+            SortBy::P95 => "p95",
+        }
+    }
+}
+
+impl Endpoint {
+    /// Percent of runs that were errors.
+    pub fn error_rate(&self) -> f64 {
+        self.errors as f64 * 100.0 / self.runs as f64
+    }
+
+    pub fn min(&self) -> Option<u64> {
+        self.latencies_ms.first().copied()
+    }
+
+    pub fn median(&self) -> Option<u64> {
+        self.percentile(50)
+    }
+
+    pub fn p95(&self) -> Option<u64> {
+        self.percentile(95)
+    }
+
+    fn percentile(&self, p: usize) -> Option<u64> {
+        let sorted = &self.latencies_ms;
+        (!sorted.is_empty()).then(|| sorted[(sorted.len() * p / 100).min(sorted.len() - 1)])
+    }
+
+    pub fn matches(&self, entry: &HistoryEntry) -> bool {
+        entry.request.method == self.method && entry.request.url == self.url
+    }
+}
+
+/// One row per method and URL among `entries`, worst first by `sort`. Pending exchanges are
+/// left out.
+pub fn rollup<'a>(entries: impl Iterator<Item = &'a HistoryEntry>, sort: SortBy) -> Vec<Endpoint> {
+    let mut endpoints: Vec<Endpoint> = vec![];
+    for entry in entries.filter(|entry| !entry.is_pending()) {
+        let index = match endpoints.iter().position(|endpoint| endpoint.matches(entry)) {
+            Some(index) => index,
+            None => {
+                endpoints.push(Endpoint {
+                    method: entry.request.method.clone(),
+                    url: entry.request.url.clone(),
+                    runs: 0,
+                    errors: 0,
+                    latencies_ms: vec![],
+                });
+                endpoints.len() - 1
+            }
+        };
+        let endpoint = &mut endpoints[index];
+        endpoint.runs += 1;
+        let status = entry.response.as_ref().and_then(|response| response.status);
+        if entry.response.is_none() || status.is_some_and(|status| status >= 400) {
+            endpoint.errors += 1;
+        }
+        if let (Some(_), Some(timing)) = (&entry.response, entry.timing) {
+            endpoint.latencies_ms.push(timing.latency_ms);
+        }
+    }
+    for endpoint in &mut endpoints {
+        endpoint.latencies_ms.sort_unstable();
+    }
+    match sort {
+        SortBy::Runs => endpoints.sort_by_key(|endpoint| Reverse(endpoint.runs)),
+        SortBy::ErrorRate => endpoints.sort_by(|a, b| b.error_rate().total_cmp(&a.error_rate())),
+        SortBy::Median => endpoints.sort_by_key(|endpoint| Reverse(endpoint.median())),
+        SortBy::P95 => endpoints.sort_by_key(|endpoint| Reverse(endpoint.p95())),
+    }
+    endpoints
+}
+
+/// The table as CSV, latencies in milliseconds and empty where nothing came back.
+pub fn csv(endpoints: &[Endpoint]) -> String {
+    let mut out = "method,url,runs,errors,error_rate_percent,min_ms,median_ms,p95_ms\n".to_string();
+    let number = |value: Option<u64>| value.map_or(String::new(), |value| value.to_string());
+    for endpoint in endpoints {
+        out.push_str(&format!(
+            "{},{},{},{},{:.1},{},{},{}\n",
+            csv_field(&endpoint.method),
+            csv_field(&endpoint.url),
+            endpoint.runs,
+            endpoint.errors,
+            endpoint.error_rate(),
+            number(endpoint.min()),
+            number(endpoint.median()),
+            number(endpoint.p95()),
+        ));
+    }
+    out
+}
+
+/// Quoted when it holds a comma, quote or line break, with quotes doubled.
+fn csv_field(text: &str) -> String {
+    match text.contains([',', '"', '\n', '\r']) {
+        true => format!("\"{}\"", text.replace('"', "\"\"")),
+        false => text.to_string(),
+    }
+}
@@ -0,0 +1,89 @@
+use std::{
+    env,
+    fs::File,
+    io,
+    path::Path,
+    process::{Command, Stdio},
+};
+
+use ratatui::{
+    crossterm::{
+        event::{DisableBracketedPaste, EnableBracketedPaste},
+        execute,
+        terminal::{enable_raw_mode, EnterAlternateScreen},
+    },
+    DefaultTerminal,
+};
+
+/// Gives the terminal back to the shell and stops the process the way Ctrl+Z would outside raw
+/// mode. Returns once the shell resumes it, with the terminal set up again and cleared so the
+/// next draw repaints everything.
+pub fn suspend(terminal: &mut DefaultTerminal) -> io::Result<()> {
+    execute!(io::stdout(), DisableBracketedPaste)?;
+    ratatui::restore();
+    stop();
+    enable_raw_mode()?;
+    execute!(io::stdout(), EnterAlternateScreen, EnableBracketedPaste)?;
+    terminal.clear()
+}
+
+/// Gives the terminal to `$VISUAL`, else `$EDITOR`, else `vi`, to edit `path`, and takes it
+/// back once the editor exits, set up again and cleared whether or not the editor could be
+/// started. Returns whether it exited successfully.
+pub fn edit_file(terminal: &mut DefaultTerminal, path: &Path) -> io::Result<bool> {
+    let command = env::var("VISUAL")
+        .ok()
+        .filter(|command| !command.trim().is_empty())
+        .or_else(|| env::var("EDITOR").ok().filter(|command| !command.trim().is_empty()))
+        .unwrap_or_else(|| "vi".to_string());
+    // Editors are often set with arguments, like `code --wait`.
+    let mut words = command.split_whitespace();
+    let program = words.next().unwrap_or("vi");
+    let mut command = Command::new(program);
+    command.args(words).arg(path);
+    hand_over(terminal, program, command)
+}
+
+/// Runs `command` on `path`, with the terminal handed over the way `edit_file` does. With `{}`
+/// among its words, the command is given the path there; otherwise it's a pager and reads the
+/// file on stdin.
+pub fn open_file(terminal: &mut DefaultTerminal, command: &str, path: &Path) -> io::Result<bool> {
+    let mut words = command.split_whitespace();
+    let Some(program) = words.next() else {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "no command to run"));
+    };
+    let args: Vec<&str> = words.collect();
+    let mut process = Command::new(program);
+    match command.contains("{}") {
+        true => {
+            process.args(args.iter().map(|arg| arg.replace("{}", &path.to_string_lossy())));
+        }
+        false => {
+            process.args(args).stdin(Stdio::from(File::open(path)?));
+        }
+    }
+    hand_over(terminal, program, process)
+}
+
+fn hand_over(terminal: &mut DefaultTerminal, program: &str, mut command: Command) -> io::Result<bool> {
+    execute!(io::stdout(), DisableBracketedPaste)?;
+    ratatui::restore();
+    let status = command.status();
+    enable_raw_mode()?;
+    execute!(io::stdout(), EnterAlternateScreen, EnableBracketedPaste)?;
+    terminal.clear()?;
+    let status = status.map_err(|e| io::Error::new(e.kind(), format!("couldn't start {}: {}", program, e)))?;
+    Ok(status.success())
+}
+
+#[cfg(unix)]
+fn stop() {
+    // SAFETY: raise has no memory-safety preconditions. The default SIGTSTP action stops the
+    // process until SIGCONT.
+    unsafe {
+        libc::raise(libc::SIGTSTP);
+    }
+}
+
+#[cfg(not(unix))]
+fn stop() {}
@@ -0,0 +1,154 @@
+use std::{collections::HashMap, fmt};
+
+use chrono::{SecondsFormat, Utc};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+/// One `{{$...}}` placeholder and the value it produced, kept so a send can be replayed verbatim.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Generated {
+    pub expression: String,
+    pub value: String,
+}
+
+#[derive(Debug)]
+pub enum TemplateError {
+    UnknownGenerator(String),
+    BadArguments(String),
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TemplateError::UnknownGenerator(name) => write!(f, "unknown dynamic variable {{{{{}}}}}", name),
+            TemplateError::BadArguments(expression) => write!(f, "bad arguments in {{{{{}}}}}", expression),
+        }
+    }
+}
+
+/// A stretch of resolved text: as written, filled in from a placeholder, or a placeholder left
+/// as is because nothing by its name was found.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Piece {
+    Text(String),
+    Substituted { expression: String, value: String },
+    Missing(String),
+}
+
+/// Substitutes `{{...}}` placeholders. Names are looked up in `variables` and left untouched
+/// when missing. Dynamic generators are evaluated fresh unless `reuse` holds the values from an
+/// earlier resolution of the same request, in which case they are replayed in order.
+pub struct Resolver<'a> {
+    variables: &'a HashMap<String, String>,
+    reuse: Option<&'a [Generated]>,
+    pub generated: Vec<Generated>,
+    /// Variables substituted so far, each once, in order of first use.
+    pub used: Vec<(String, String)>,
+}
+
+impl<'a> Resolver<'a> {
+    pub fn new(variables: &'a HashMap<String, String>, reuse: Option<&'a [Generated]>) -> Self {
+        Self { variables, reuse, generated: vec![], used: vec![] }
+    }
+
+    pub fn resolve(&mut self, input: &str) -> Result<String, TemplateError> {
+        let pieces = self.resolve_pieces(input)?;
+        Ok(pieces
+            .into_iter()
+            .map(|piece| match piece {
+                Piece::Text(text) => text,
+                Piece::Substituted { value, .. } => value,
+                Piece::Missing(expression) => format!("{{{{{}}}}}", expression),
+            })
+            .collect())
+    }
+
+    /// Like `resolve`, keeping apart what was written from what each placeholder became.
+    pub fn resolve_pieces(&mut self, input: &str) -> Result<Vec<Piece>, TemplateError> {
+        let mut pieces = vec![];
+        let mut rest = input;
+        while let Some(open) = rest.find("{{") {
+            let Some(close) = rest[open..].find("}}") else { break };
+            if open > 0 {
+                pieces.push(Piece::Text(rest[..open].to_string()));
+            }
+            let placeholder = &rest[open..open + close + 2];
+            let expression = placeholder[2..placeholder.len() - 2].trim();
+            if expression.starts_with('$') {
+                let value = self.dynamic_value(expression)?;
+                pieces.push(Piece::Substituted { expression: expression.to_string(), value });
+            } else if let Some(value) = self.variables.get(expression) {
+                pieces.push(Piece::Substituted { expression: expression.to_string(), value: value.clone() });
+                if !self.used.iter().any(|(name, _)| name == expression) {
+                    self.used.push((expression.to_string(), value.clone()));
+                }
+            } else {
+                // Kept as written, spacing and all, since that's what goes out.
+                pieces.push(Piece::Missing(placeholder[2..placeholder.len() - 2].to_string()));
+            }
+            rest = &rest[open + close + 2..];
+        }
+        if !rest.is_empty() {
+            pieces.push(Piece::Text(rest.to_string()));
+        }
+        Ok(pieces)
+    }
+
+    fn dynamic_value(&mut self, expression: &str) -> Result<String, TemplateError> {
+        let reused = self
+            .reuse
+            .and_then(|values| values.get(self.generated.len()))
+            .filter(|previous| previous.expression == expression);
+        let value = match reused {
+            Some(previous) => previous.value.clone(),
+            None => generate(expression)?,
+        };
+        self.generated.push(Generated { expression: expression.to_string(), value: value.clone() });
+        Ok(value)
+    }
+}
+
+/// Rewrites the expression inside every `{{...}}` with `f`, leaving everything else as is.
+pub fn map_placeholders(input: &str, mut f: impl FnMut(&str) -> String) -> String {
+    let mut out = String::with_capacity(input.len());
+    let mut rest = input;
+    while let Some(open) = rest.find("{{") {
+        let Some(close) = rest[open..].find("}}") else { break };
+        out.push_str(&rest[..open]);
+        out.push_str(&format!("{{{{{}}}}}", f(rest[open + 2..open + close].trim())));
+        rest = &rest[open + close + 2..];
+    }
+    out.push_str(rest);
+    out
+}
+
+fn generate(expression: &str) -> Result<String, TemplateError> {
+    let mut words = expression.split_whitespace();
+    let name = words.next().unwrap_or_default();
+    let args: Vec<&str> = words.collect();
+    match (name, args.as_slice()) {
+        ("$uuid", []) => Ok(uuid_v4()),
+        ("$timestamp", []) => Ok(Utc::now().timestamp().to_string()),
+        ("$isoTimestamp", []) => Ok(Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true)),
+        ("$randomInt", []) => Ok(rand::thread_rng().gen_range(0..=1000).to_string()),
+        ("$randomInt", [min, max]) => {
+            let bad_arguments = || TemplateError::BadArguments(expression.to_string());
+            let min: i64 = min.parse().map_err(|_| bad_arguments())?;
+            let max: i64 = max.parse().map_err(|_| bad_arguments())?;
+            if min > max {
+                return Err(bad_arguments());
+            }
+            Ok(rand::thread_rng().gen_range(min..=max).to_string())
+        }
+        ("$uuid" | "$timestamp" | "$isoTimestamp" | "$randomInt", _) => Err(TemplateError::BadArguments(expression.to_string())),
+        _ => Err(TemplateError::UnknownGenerator(name.to_string())),
+    }
+}
+
+pub fn uuid_v4() -> String {
+    let mut bytes: [u8; 16] = rand::random();
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    format!("{}-{}-{}-{}-{}", &hex[..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..])
+}
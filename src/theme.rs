@@ -0,0 +1,285 @@
+use std::env;
+
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::Span,
+    widgets::{Block, BorderType},
+};
+use serde::{Deserialize, Serialize};
+
+/// How a status or validity badge should read.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Tone {
+    Good,
+    Warning,
+    Bad,
+    Neutral,
+}
+
+/// The built-in color schemes.
+#[derive(Serialize, Deserialize, Clone, Copy, Default, PartialEq, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub enum Preset {
+    #[default]
+    Dark,
+    /// For terminals with a light background.
+    Light,
+    SolarizedDark,
+    Gruvbox,
+}
+
+/// Every style the UI uses. The no-color variant spells out what the colored one only tints:
+/// focused panes get a double border and a `[FOCUS]` title, badges carry words, errors a prefix.
+#[derive(Clone, Copy, Debug)]
+pub struct Theme {
+    pub text: Style,
+    pub focused: Style,
+    pub selected: Style,
+    /// The selection in a pane that doesn't have focus.
+    pub selected_unfocused: Style,
+    pub dim: Style,
+    pub error: Style,
+    pub key: Style,
+    pub heading: Style,
+    pub matched: Style,
+    /// Syntax highlighting of structured bodies.
+    pub tag: Style,
+    pub attribute: Style,
+    pub value: Style,
+    pub comment: Style,
+    good: Style,
+    warning: Style,
+    bad: Style,
+    neutral: Style,
+    /// Tag chips, one picked by the tag's name.
+    chips: [Style; 6],
+    /// Whether color can't be relied on, so cues must also be carried by text or shape.
+    pub no_color: bool,
+}
+
+impl Preset {
+    pub const ALL: [Preset; 4] = [Preset::Dark, Preset::Light, Preset::SolarizedDark, Preset::Gruvbox];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Preset::Dark => "dark",
+            Preset::Light => "light",
+            Preset::SolarizedDark => "solarized-dark",
+            Preset::Gruvbox => "gruvbox",
+        }
+    }
+
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let input = input.trim();
+        Preset::ALL
+            .into_iter()
+            .find(|preset| preset.name() == input)
+            .ok_or_else(|| format!("Expected dark, light, solarized-dark or gruvbox, not {}", input))
+    }
+
+    fn palette(self) -> Palette {
+        match self {
+            Preset::Dark => Palette {
+                text: Color::White,
+                dim: Color::DarkGray,
+                accent: Color::Yellow,
+                on_accent: Color::Black,
+                error: Color::Red,
+                tag: Color::Cyan,
+                attribute: Color::Magenta,
+                value: Color::Green,
+                neutral: Color::Gray,
+                on_badge: Color::Black,
+                chips: [Color::Cyan, Color::Magenta, Color::Blue, Color::Green, Color::LightRed, Color::LightBlue],
+            },
+            // Dark text only; the named colors are too pale on white in many terminals' palettes.
+            Preset::Light => Palette {
+                text: Color::Rgb(40, 40, 40),
+                dim: Color::Rgb(110, 110, 110),
+                accent: Color::Rgb(0, 90, 180),
+                on_accent: Color::Rgb(255, 255, 255),
+                error: Color::Rgb(190, 0, 0),
+                tag: Color::Rgb(0, 110, 130),
+                attribute: Color::Rgb(140, 0, 140),
+                value: Color::Rgb(0, 120, 0),
+                neutral: Color::Rgb(200, 200, 200),
+                on_badge: Color::Rgb(0, 0, 0),
+                chips: [
+                    Color::Rgb(150, 220, 230), Color::Rgb(230, 180, 230), Color::Rgb(170, 200, 250),
+                    Color::Rgb(180, 230, 170), Color::Rgb(250, 190, 170), Color::Rgb(250, 230, 150),
+                ],
+            },
+            Preset::SolarizedDark => Palette {
+                text: Color::Rgb(131, 148, 150),
+                dim: Color::Rgb(88, 110, 117),
+                accent: Color::Rgb(181, 137, 0),
+                on_accent: Color::Rgb(0, 43, 54),
+                error: Color::Rgb(220, 50, 47),
+                tag: Color::Rgb(42, 161, 152),
+                attribute: Color::Rgb(211, 54, 130),
+                value: Color::Rgb(133, 153, 0),
+                neutral: Color::Rgb(147, 161, 161),
+                on_badge: Color::Rgb(0, 43, 54),
+                chips: [
+                    Color::Rgb(42, 161, 152), Color::Rgb(211, 54, 130), Color::Rgb(38, 139, 210),
+                    Color::Rgb(133, 153, 0), Color::Rgb(203, 75, 22), Color::Rgb(108, 113, 196),
+                ],
+            },
+            Preset::Gruvbox => Palette {
+                text: Color::Rgb(235, 219, 178),
+                dim: Color::Rgb(146, 131, 116),
+                accent: Color::Rgb(250, 189, 47),
+                on_accent: Color::Rgb(40, 40, 40),
+                error: Color::Rgb(251, 73, 52),
+                tag: Color::Rgb(142, 192, 124),
+                attribute: Color::Rgb(211, 134, 155),
+                value: Color::Rgb(184, 187, 38),
+                neutral: Color::Rgb(168, 153, 132),
+                on_badge: Color::Rgb(40, 40, 40),
+                chips: [
+                    Color::Rgb(142, 192, 124), Color::Rgb(211, 134, 155), Color::Rgb(131, 165, 152),
+                    Color::Rgb(184, 187, 38), Color::Rgb(254, 128, 25), Color::Rgb(250, 189, 47),
+                ],
+            },
+        }
+    }
+}
+
+/// The colors a preset is made of; the styles built from them are the same for every preset.
+struct Palette {
+    text: Color,
+    dim: Color,
+    /// Focus, selection and keys.
+    accent: Color,
+    /// Text on the accent color.
+    on_accent: Color,
+    error: Color,
+    tag: Color,
+    attribute: Color,
+    value: Color,
+    neutral: Color,
+    /// Text on badges and chips.
+    on_badge: Color,
+    chips: [Color; 6],
+}
+
+impl Theme {
+    pub fn preset(preset: Preset) -> Self {
+        let palette = preset.palette();
+        let badge = |color| Style::default().fg(palette.on_badge).bg(color);
+        Self {
+            text: Style::default().fg(palette.text),
+            focused: Style::default().fg(palette.accent),
+            selected: Style::default().fg(palette.on_accent).bg(palette.accent),
+            selected_unfocused: Style::default().add_modifier(Modifier::REVERSED),
+            dim: Style::default().fg(palette.dim),
+            error: Style::default().fg(palette.error),
+            key: Style::default().fg(palette.accent),
+            heading: Style::default().add_modifier(Modifier::BOLD),
+            matched: Style::default().add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+            tag: Style::default().fg(palette.tag),
+            attribute: Style::default().fg(palette.attribute),
+            value: Style::default().fg(palette.value),
+            comment: Style::default().fg(palette.dim).add_modifier(Modifier::ITALIC),
+            good: badge(palette.value),
+            warning: badge(palette.accent),
+            bad: badge(palette.error),
+            neutral: badge(palette.neutral),
+            chips: palette.chips.map(badge),
+            no_color: false,
+        }
+    }
+
+    /// Only modifiers, which terminals without color still render.
+    pub fn no_color() -> Self {
+        Self {
+            text: Style::default(),
+            focused: Style::default().add_modifier(Modifier::BOLD),
+            selected: Style::default().add_modifier(Modifier::REVERSED | Modifier::BOLD),
+            selected_unfocused: Style::default().add_modifier(Modifier::UNDERLINED),
+            dim: Style::default(),
+            error: Style::default().add_modifier(Modifier::BOLD),
+            key: Style::default().add_modifier(Modifier::BOLD),
+            heading: Style::default().add_modifier(Modifier::BOLD),
+            matched: Style::default().add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+            tag: Style::default().add_modifier(Modifier::BOLD),
+            attribute: Style::default(),
+            value: Style::default(),
+            comment: Style::default().add_modifier(Modifier::ITALIC),
+            good: Style::default().add_modifier(Modifier::REVERSED),
+            warning: Style::default().add_modifier(Modifier::REVERSED),
+            bad: Style::default().add_modifier(Modifier::REVERSED | Modifier::BOLD),
+            neutral: Style::default().add_modifier(Modifier::REVERSED),
+            chips: [Style::default(); 6],
+            no_color: true,
+        }
+    }
+
+    /// The no-color theme when the config asks for it or `NO_COLOR` is set to anything non-empty,
+    /// otherwise the preset.
+    pub fn from_config(no_color: bool, preset: Preset) -> Self {
+        if no_color || env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty()) {
+            Self::no_color()
+        } else {
+            Self::preset(preset)
+        }
+    }
+
+    /// A pane's border. The focused pane's is heavier and bold, with a `●` before its title;
+    /// the others' are dimmed, their contents not.
+    pub fn block<'a>(&self, title: &str, focused: bool) -> Block<'a> {
+        let block = Block::bordered().style(if focused {self.focused} else {self.text});
+        match (focused, self.no_color) {
+            (true, true) => block.border_type(BorderType::Double).title(format!("● {} [FOCUS]", title)),
+            (true, false) => block
+                .border_type(BorderType::Thick)
+                .border_style(self.focused.add_modifier(Modifier::BOLD))
+                .title(format!("● {}", title)),
+            (false, _) => block.border_style(self.dim).title(title.to_string()),
+        }
+    }
+
+    pub fn popup<'a>(&self, title: &str) -> Block<'a> {
+        Block::bordered().title(title.to_string()).style(self.text)
+    }
+
+    pub fn error_text(&self, message: &str) -> String {
+        if self.no_color {format!("ERROR: {}", message)} else {message.to_string()}
+    }
+
+    pub fn badge(&self, label: &str, tone: Tone) -> Span<'static> {
+        let style = match tone {
+            Tone::Good => self.good,
+            Tone::Warning => self.warning,
+            Tone::Bad => self.bad,
+            Tone::Neutral => self.neutral,
+        };
+        Span::styled(format!(" {} ", label), style)
+    }
+
+    /// A tag as a chip whose color is picked from its name, so a tag looks the same everywhere.
+    /// Without color it's spelled `#tag`.
+    pub fn chip(&self, tag: &str) -> Span<'static> {
+        if self.no_color {
+            return Span::styled(format!("#{}", tag), self.tag);
+        }
+        let hash = tag.bytes().fold(0usize, |hash, byte| hash.wrapping_mul(31).wrapping_add(byte as usize));
+        Span::styled(format!(" {} ", tag), self.chips[hash % self.chips.len()])
+    }
+
+    /// A badge for an HTTP status, e.g. ` 200 ` or, without color, ` OK 200 `.
+    pub fn status_badge(&self, code: Option<u16>) -> Span<'static> {
+        let (word, tone) = match code {
+            Some(200..=299) => ("OK", Tone::Good),
+            Some(100..=199 | 300..=399) => ("INFO", Tone::Warning),
+            Some(_) => ("ERR", Tone::Bad),
+            None => ("???", Tone::Neutral),
+        };
+        let code = code.map_or("???".to_string(), |code| code.to_string());
+        if self.no_color {
+            self.badge(&format!("{} {}", word, code), tone)
+        } else {
+            self.badge(&code, tone)
+        }
+    }
+}
@@ -0,0 +1,34 @@
+use std::{
+    io::{self, Write},
+    panic,
+};
+
+use ratatui::crossterm::{execute, terminal::SetTitle};
+
+/// Saves the current title on the terminal's title stack so `restore` can bring it back.
+/// Terminals without a title stack ignore this.
+pub fn save() -> io::Result<()> {
+    let mut stdout = io::stdout();
+    stdout.write_all(b"\x1b[22;0t")?;
+    stdout.flush()
+}
+
+pub fn restore() -> io::Result<()> {
+    let mut stdout = io::stdout();
+    stdout.write_all(b"\x1b[23;0t")?;
+    stdout.flush()
+}
+
+pub fn set(title: &str) -> io::Result<()> {
+    let title: String = title.chars().filter(|c| !c.is_control()).collect();
+    execute!(io::stdout(), SetTitle(title))
+}
+
+/// Restores the title before whatever panic hook is already installed runs.
+pub fn restore_on_panic() {
+    let hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        let _ = restore();
+        hook(info);
+    }));
+}
@@ -28,15 +28,128 @@
 // See also https://github.com/rhysd/tui-textarea and https://github.com/sayanarijit/tui-input/
 
 
-use color_eyre::Result;
+use std::{cmp::Reverse, collections::{BTreeMap, BTreeSet, HashMap, VecDeque}, fs, path::PathBuf, sync::mpsc::Receiver, time::{Duration, Instant}};
+
+use chrono::{DateTime, Local, SecondsFormat, Utc};
+use color_eyre::{eyre::eyre, Result};
 use ratatui::{
-    crossterm::event::{self, Event, KeyCode, KeyEventKind},
-    layout::{Constraint, Layout},
-    style::{Color, Style},
-    widgets::{Block, List, Paragraph, Tabs, Wrap},
+    crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    layout::{Constraint, Flex, Layout, Rect},
+    style::Style,
+    text::{Line, Span, Text},
+    widgets::{Bar, BarChart, BarGroup, Block, Clear, List, ListItem, Paragraph, Sparkline, Tabs, Wrap},
     DefaultTerminal, Frame,
 };
-use rust_http::{client::HttpClient, http::{HttpRequest, HttpResponse, HTTP_METHODS}};
+use rust_http::http::HTTP_METHODS;
+
+use crate::{
+    action::{Action, Scope},
+    app_error::{self, AppError, ErrorKind},
+    baseline,
+    charset::{self, Encoding},
+    clipboard,
+    collection::{self, folder_label, Collection, MatchedField, SavedRequest, TreeRow},
+    conditional::Validators,
+    cors::{self, Preflight},
+    curl,
+    config::{self, BaselineConfig, Config, HistoryGrouping, RunnerConfig, TestServerConfig},
+    editor,
+    encoding::Transform,
+    exchange,
+    expect::ExpectContinue,
+    focus::Focus,
+    environment::{self, Environment},
+    history::{self, HistoryEntry, Repeat, ResponseRecord, Timing},
+    hooks::{self, Hook, HookOutcome, PostHook},
+    host_rules::{self, HostRule, TokenRefresh, TokenSource},
+    html,
+    insomnia,
+    json_tree,
+    net::{self, AddressPreference, Step},
+    plaintext::{self, Format},
+    postman,
+    rate_limit,
+    redirect,
+    jwt::{self, Jwt, Validity, TIME_CLAIMS},
+    keymap::Keymap,
+    kv,
+    load,
+    markdown,
+    mock::{self, Mock},
+    my_test_server::{self, Received},
+    request::{header_value, method_index, method_name, parse_headers, RequestError, RequestSpec, ResolvedRequest},
+    request_log::RequestLog,
+    runner::{self, Outcome},
+    schema::{self, BodySchema},
+    search::{self, Pattern, Search},
+    secrets::{self, Masker},
+    session::{self, Draft, Session},
+    settings::{Edit, SETTINGS},
+    snippet::{self, Language},
+    stats::{self, SortBy},
+    suspend,
+    template::{self, Generated, Piece, Resolver},
+    theme::{Preset, Theme, Tone},
+    title,
+    url,
+    vault::{self, Vault},
+    wire::Progress,
+    worker::{Job, Transport, Worker, WorkerEvent},
+    workspace,
+    xml::{self, Part},
+};
+
+/// Everything that can change the app's state, so the event loop is just translation.
+pub enum AppEvent {
+    Key(KeyEvent),
+    Paste(String),
+    Resize,
+    Worker(WorkerEvent),
+    /// Time passing with no input, for work that waits until typing pauses.
+    Tick(Instant),
+}
+
+/// Lines kept in the console pane before the oldest are dropped.
+const CONSOLE_CAPACITY: usize = 5000;
+
+/// Shown dimmed in empty fields, as a hint at what goes there. They're drawn over the field,
+/// never put in it, so they're not sent, copied or counted by the cursor.
+const URL_PLACEHOLDER: &str = "http://api.example.com/path";
+const HEADERS_PLACEHOLDER: &str = "Header-Name: value (one per line)";
+const BODY_PLACEHOLDER: &str = "{ \"json\": \"body\" }";
+
+/// Requests kept in the server traffic pane before the oldest are dropped.
+const TRAFFIC_CAPACITY: usize = 200;
+
+/// How long typing has to pause before the body is checked again.
+const JSON_CHECK_DELAY: Duration = Duration::from_millis(300);
+
+/// How many requests post-response triggers may chain before the next one is refused.
+const MAX_CHAIN_DEPTH: usize = 5;
+
+/// Characters Left/Right scroll unwrapped response bodies by.
+const WRAP_SCROLL_STEP: usize = 8;
+
+/// How long a confirmation like "Copied" stays in the status line.
+const FLASH_DURATION: Duration = Duration::from_secs(2);
+
+/// Earlier outcomes listed under a request sent several times in a row.
+const REPEATS_SHOWN: usize = 5;
+
+/// How far right of the scroll position a search match may start before the body is scrolled to it.
+const SEARCH_MARGIN: usize = 40;
+
+/// Body lines shown above the current search match.
+const SEARCH_CONTEXT: usize = 3;
+
+/// How many URLs the recent URLs menu offers.
+const RECENT_URLS: usize = 15;
+
+/// How often the editor is autosaved as a draft while it has unsaved edits.
+const DRAFT_INTERVAL: Duration = Duration::from_secs(3);
+
+/// Bytes of a body shown in the raw view; past this the dump stops.
+const RAW_DUMP_LIMIT: usize = 64 * 1024;
 
 /// App holds the state of the application
 pub struct App {
@@ -46,20 +159,364 @@ pub struct App {
     body_input: String,
     /// Position of cursor in the editor area.
     character_index: usize,
+    json_check: JsonCheck,
+    url_check: UrlCheck,
 
-    error_message: Option<String>,
+    error_message: Option<AppError>,
+    /// Requests still waiting on a response, by history id.
+    unanswered: HashMap<u64, Sent>,
+    /// The newest request that got no response, for retrying it.
+    last_failure: Option<(u64, Sent)>,
+    /// The last few errors shown, newest last, for the recent errors list.
+    recent_errors: VecDeque<AppError>,
 
-    client: HttpClient,
-
-    responses: Vec<HttpResponse>,
+    worker: Worker,
+    in_flight: usize,
+    /// Bodies of requests being sent, by history id.
+    transfers: HashMap<u64, Transfer>,
+    /// Bytes sent and received this session, for the status bar, until reset.
+    bytes_sent: u64,
+    bytes_received: u64,
+    /// Where the built-in test server listens.
+    server_addr: String,
 
     method_index: usize,
 
     input_order: Vec<InputMode>,
     input_index: usize, 
+
+    /// Modal overlay that captures all key presses while open.
+    popup: Option<Popup>,
+
+    undo_stack: Vec<UndoEntry>,
+    /// Text cut by Ctrl+U/Ctrl+K, pasted by Ctrl+Y into whichever field is focused.
+    kill_buffer: String,
+
+    /// Every exchange, oldest first; the responses pane is drawn from it.
+    history: Vec<HistoryEntry>,
+    /// Response bodies formatted for display, by history id, so large ones are only formatted once.
+    rendered_bodies: HashMap<u64, Vec<Line<'static>>>,
+    /// Show HTML bodies as text rather than markup.
+    readable_html: bool,
+    json_view: JsonView,
+    /// The tree of the newest response, by history id, while the tree view is on.
+    json_tree: Option<(u64, json_tree::Tree)>,
+    /// The last search of the responses pane, run again on each new response.
+    search_query: String,
+    search_regex: bool,
+    /// Matches of `search_query` in the newest response's body, by history id.
+    search: Option<(u64, Search)>,
+    /// The last request exactly as it went out, for resending.
+    last_sent: Option<ResolvedRequest>,
+    /// Dynamic values shown in the last preview, used by the next send so it matches the preview.
+    previewed_values: Option<Vec<Generated>>,
+
+    collection: Collection,
+    /// Folder and name of the saved request last loaded or saved into the editor.
+    loaded_request: Option<(Vec<String>, String)>,
+    /// Pre-request and post-response hooks of the loaded saved request, kept when it's saved again.
+    request_hooks: Vec<Hook>,
+    response_hooks: Vec<PostHook>,
+    /// Results of post-response hooks by history id.
+    hook_outcomes: HashMap<u64, Vec<HookOutcome>>,
+    /// How many triggers led to each history entry, for entries sent by a trigger.
+    chain_depth: HashMap<u64, usize>,
+    /// The exchange whose response triggered each chained one.
+    chain_parent: HashMap<u64, u64>,
+    baseline_rules: BaselineConfig,
+    /// A response read from a file to compare others with, and the file's path.
+    comparison: Option<(String, ResponseRecord)>,
+    rate_limit: Option<RateLimit>,
+    /// A short confirmation for the status line, and when it goes.
+    flash: Option<(String, Instant)>,
+    max_auto_retries: usize,
+    load: Option<load::Run>,
+    /// Query parameters as last bulk edited, disabled ones included. The URL only holds the
+    /// enabled ones, so these are reused while it still matches them.
+    query_rows: Vec<kv::Row>,
+    /// Preflights waiting on their response, by history id.
+    cors_checks: HashMap<u64, Preflight>,
+    /// The last CORS check asked for, offered again next time.
+    cors_input: String,
+    /// The histogram bar picked out in the load popup.
+    load_bucket: usize,
+    /// The collection run in progress, or the latest one.
+    run: Option<runner::Run>,
+    run_policy: RunnerConfig,
+    /// Differences from the baseline by history id; empty when the response matched.
+    baseline_results: HashMap<u64, Vec<String>>,
+    show_collection: bool,
+    /// Which pane plain keys go to.
+    focus: Pane,
+    response_view: ResponseView,
+    header_table: Option<HeaderTable>,
+    collection_selected: usize,
+    collapsed_folders: BTreeSet<Vec<String>>,
+    /// Narrows the collection pane and the finder to requests carrying this tag.
+    tag_filter: Option<String>,
+
+    environments: Vec<Environment>,
+    active_environment: Option<usize>,
+    /// Variables of the active environment, dotenv values included.
+    variables: HashMap<String, String>,
+    /// How the environments file's secrets are encrypted, once there are any.
+    vault: Option<Vault>,
+    /// Set once the passphrase is given; secret values stay locked until then.
+    vault_key: Option<vault::Key>,
+    /// Narrows the responses pane to exchanges sent with this environment, `Some(None)` being
+    /// those sent with none.
+    history_environment: Option<Option<String>>,
+    history_grouping: HistoryGrouping,
+    history_sort: HistorySort,
+    status_filter: StatusFilter,
+    /// Method and URL as written, from drilling into the statistics.
+    endpoint_filter: Option<(String, String)>,
+    /// Labels of the responses pane's sections that are folded away.
+    collapsed_groups: BTreeSet<String>,
+    /// Highlighted section of the responses pane, while it's grouped.
+    group_selected: usize,
+    /// The exchange j and k moved to in the responses pane, for * to pin. While it's unset or
+    /// no longer listed, that's the newest.
+    exchange_selected: Option<u64>,
+    /// From the config: consecutive exchanges of the same request folded into one entry.
+    collapse_repeats: bool,
+    /// Every earlier run of a folded entry listed for now, not just the last few.
+    expand_repeats: bool,
+
+    /// ETag/Last-Modified validators seen per URL.
+    validators: BTreeMap<String, Validators>,
+    send_conditional: bool,
+    connect_to: Option<String>,
+    /// This request's redirect policy, when it isn't `redirect_default`.
+    redirect_policy: Option<redirect::Policy>,
+    redirect_default: redirect::Policy,
+    /// Likewise for the timeout and rate-limited retries.
+    request_timeout: Option<u64>,
+    default_timeout: Option<u64>,
+    connect_timeout: Option<u64>,
+    connect_budget: Option<u64>,
+    read_timeout: Option<u64>,
+    request_max_retries: Option<usize>,
+    /// Whether this request gets a fresh `X-Request-Id`, when it isn't `request_id_default`.
+    request_id: Option<bool>,
+    request_id_default: bool,
+    body_schema: Option<BodySchema>,
+    response_schema: Option<String>,
+    expect_continue: Option<ExpectContinue>,
+    max_response_mb: Option<u64>,
+    default_max_response_mb: u64,
+    /// Set just before a send that skips both schema checks.
+    skip_schema_checks: bool,
+    /// Schemas responses to requests in flight are to be checked against, by history id.
+    expected_schemas: HashMap<u64, String>,
+    /// Headers and auth for matching hosts, from the config.
+    host_rules: Vec<HostRule>,
+    /// Views showing credentials unmasked, until toggled back. Never saved.
+    revealed: BTreeSet<SecretView>,
+    /// Sent requests whose token a 401 would refresh.
+    refreshable: HashMap<u64, TokenRefresh>,
+    /// Token requests under way, with the rejected request each is for.
+    refreshing: HashMap<u64, (u64, TokenRefresh)>,
+    /// Retries with a refreshed token, with the request each retries.
+    refresh_retries: HashMap<u64, u64>,
+    /// When each request with a timeout gives up, and its timeout in seconds.
+    deadlines: HashMap<u64, (Instant, u64)>,
+    address_preference: AddressPreference,
+
+    /// Verbose transcript of what the worker did, shown in the console pane.
+    console: VecDeque<String>,
+    show_console: bool,
+    /// How many lines the console is scrolled up from its tail.
+    console_scroll: usize,
+    /// Requests the built-in test server received, oldest first.
+    server_traffic: VecDeque<Received>,
+    traffic: Receiver<Received>,
+    show_traffic: bool,
+
+    keymap: Keymap,
+    theme: Theme,
+    /// The preset in the config, which the theme picker previews others over.
+    theme_preset: Preset,
+    /// The terminal title last set, when the config asks for one.
+    terminal_title: Option<String>,
+    /// Set by the suspend action; the event loop owns the terminal, so it does the suspending.
+    suspend_requested: bool,
+    /// The body is to be handed to an external editor before the next draw.
+    external_edit_requested: bool,
+    /// The newest response's body is to be handed to `open_with` before the next draw.
+    open_response_requested: bool,
+    /// From the config; `None` for `$PAGER` or `less -R`.
+    open_with: Option<String>,
+    /// Response bodies written for other programs, kept until exit since some return before
+    /// they've read them.
+    temp_files: Vec<PathBuf>,
+    quit_requested: bool,
+    /// The workspace to open once the event loop stops, `None` inside being the default one.
+    switch_workspace: Option<Option<String>>,
+    /// The editor as last autosaved, to tell when there's something new to save.
+    draft: Session,
+    draft_checked_at: Instant,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Pane {
+    Editor,
+    Collection,
+    Responses,
+}
+
+/// Where credentials are masked until revealed.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum SecretView {
+    Editor,
+    Preview,
+    Responses,
+    Console,
+    /// Code exported from the request, which is copied out of the app.
+    Snippets,
+}
+
+impl SecretView {
+    const ALL: [SecretView; 5] = [SecretView::Editor, SecretView::Preview, SecretView::Responses, SecretView::Console, SecretView::Snippets];
+
+    fn name(self) -> &'static str {
+        match self {
+            SecretView::Editor => "editor",
+            SecretView::Preview => "raw request preview",
+            SecretView::Responses => "responses and server traffic",
+            SecretView::Console => "console",
+            SecretView::Snippets => "exported code",
+        }
+    }
+}
+
+/// The headers field shown as rows. The text stays the source of truth: rows are read from it
+/// and every change is written straight back, so switching views loses nothing.
+struct HeaderTable {
+    selected: usize,
+    column: HeaderColumn,
+    /// The cell being edited and the cursor in it, in characters.
+    editing: Option<(String, usize)>,
+    /// The selected row is new, and only written to the text once it has a name.
+    adding: bool,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum HeaderColumn {
+    Name,
+    Value,
+}
+
+/// How JSON response bodies are shown.
+#[derive(Clone, Copy, PartialEq)]
+enum JsonView {
+    Formatted,
+    Tree,
+    Raw,
+}
+
+/// A request exactly as it went out, to send it again unchanged.
+#[derive(Clone)]
+struct Sent {
+    request: ResolvedRequest,
+    redirects: redirect::Policy,
+    transport: Transport,
+}
+
+/// How far a request's or response's body has got, and when the first report of it came.
+struct Transfer {
+    progress: Progress,
+    since: Instant,
+}
+
+/// The order of exchanges within each section of the responses pane.
+#[derive(Clone, Copy, PartialEq)]
+enum HistorySort {
+    Recency,
+    /// Slowest first.
+    Latency,
+    /// Lowest status first.
+    Status,
+}
+
+impl HistorySort {
+    fn next(self) -> Self {
+        match self {
+            HistorySort::Recency => HistorySort::Latency,
+            HistorySort::Latency => HistorySort::Status,
+            HistorySort::Status => HistorySort::Recency,
+        }
+    }
+
+    /// Newest-first exchanges in this order. Ties stay newest first, and exchanges without a
+    /// response go last.
+    fn apply(self, entries: &mut [&HistoryEntry]) {
+        match self {
+            HistorySort::Recency => {}
+            HistorySort::Latency => entries.sort_by_key(|entry| match (&entry.response, entry.timing) {
+                (Some(_), Some(timing)) => (0, Reverse(timing.latency_ms)),
+                (Some(_), None) => (1, Reverse(0)),
+                (None, _) => (2, Reverse(0)),
+            }),
+            HistorySort::Status => entries.sort_by_key(|entry| match entry.response.as_ref().map(|response| response.status) {
+                Some(Some(status)) => (0, status),
+                Some(None) => (1, 0),
+                None => (2, 0),
+            }),
+        }
+    }
+}
+
+/// Which exchanges the responses pane shows, by how they turned out.
+#[derive(Clone, Copy, PartialEq)]
+enum StatusFilter {
+    All,
+    Success,
+    ClientError,
+    ServerError,
+    /// No response at all: the connection failed, timed out or was cancelled.
+    Transport,
+}
+
+impl StatusFilter {
+    const CLASSES: [StatusFilter; 4] = [StatusFilter::Success, StatusFilter::ClientError, StatusFilter::ServerError, StatusFilter::Transport];
+
+    fn label(self) -> &'static str {
+        match self {
+            StatusFilter::All => "all",
+            StatusFilter::Success => "2xx",
+            StatusFilter::ClientError => "4xx",
+            StatusFilter::ServerError => "5xx",
+            StatusFilter::Transport => "err",
+        }
+    }
+
+    fn matches(self, entry: &HistoryEntry) -> bool {
+        let status = entry.response.as_ref().and_then(|response| response.status);
+        match self {
+            StatusFilter::All => true,
+            StatusFilter::Success => status.is_some_and(|status| (200..300).contains(&status)),
+            StatusFilter::ClientError => status.is_some_and(|status| (400..500).contains(&status)),
+            StatusFilter::ServerError => status.is_some_and(|status| (500..600).contains(&status)),
+            StatusFilter::Transport => entry.response.is_none() && entry.error.is_some(),
+        }
+    }
+}
+
+/// How responses are shown in the responses pane.
+struct ResponseView {
+    expanded: bool,
+    sorted: bool,
+    /// Highlighted header of the newest response, shown while the pane has focus.
+    selected: usize,
+    /// Soft-wrap body lines at the pane width; otherwise they're cut and scroll sideways.
+    wrap: bool,
+    /// Characters scrolled off the left of unwrapped body lines.
+    column: usize,
 }
 
 #[derive(PartialEq)]
+#[allow(clippy::enum_variant_names)]
 enum InputMode {
     EditingUrl,
     EditingHeaders,
@@ -67,22 +524,508 @@ enum InputMode {
     EditingMethod,
 }
 
+enum Popup {
+    Palette { filter: String, selected: usize },
+    Text { title: String, lines: Vec<Line<'static>>, scroll: u16 },
+    Confirm { message: String, on_yes: Confirmed, on_no: Option<Confirmed> },
+    Picker { title: String, items: Vec<String>, selected: usize, target: PickTarget },
+    Validators { selected: usize },
+    Prompt { title: String, input: String, target: PromptTarget },
+    Finder { query: String, selected: usize },
+    /// Progress and results of the load run.
+    Load,
+    /// Progress and results of the collection run, with one of its requests picked out.
+    Run { selected: usize },
+    /// Per-endpoint rollup of history, `selected` a row of it as sorted by `sort`.
+    Stats { selected: usize, sort: SortBy },
+    /// The URL's query parameters as text, one per line, with the errors of the last attempt to apply it.
+    BulkParams { text: String, errors: Vec<String> },
+    /// A saved request in full, with its description and tags editable.
+    Details { index: usize, scroll: u16 },
+    /// A saved request's description being edited.
+    Description { index: usize, text: String },
+    /// This request's overrides of the config.
+    RequestSettings { selected: usize },
+    /// How the built-in test server misbehaves.
+    ServerControl { selected: usize },
+    /// `config.toml` as edited so far, applied and written on save.
+    Settings { config: Box<Config>, selected: usize, changed: bool },
+    Mocks { selected: usize },
+    /// A mock as text, new when there's no index, with the error of the last attempt to save it.
+    MockEditor { index: Option<usize>, text: String, error: Option<String> },
+}
+
+/// What the text entered in a prompt popup is used for.
+enum PromptTarget {
+    ConnectTo,
+    SaveRequest,
+    NewFolder { parent: Vec<String> },
+    RenameFolder { path: Vec<String> },
+    RenameRequest { index: usize },
+    /// Returns to the request's details afterwards.
+    Tags { index: usize },
+    ImportPostman,
+    ImportInsomnia,
+    ExportPostman,
+    ImportRequestFile,
+    ExportPlaintext { format: Format, folder: Vec<String> },
+    GoToLine,
+    /// Which exchanges to export, before asking where to.
+    MarkdownRange,
+    MarkdownDestination { ids: Vec<u64> },
+    SaveExchange { id: u64 },
+    SearchHistory,
+    LoadComparison,
+    LoadPlan,
+    LoadCsv,
+    RunSummary,
+    StatsCsv,
+    RedirectPolicy,
+    /// Returns to the request settings popup afterwards.
+    RequestSetting(RequestSetting),
+    /// Returns to the server control popup afterwards.
+    ServerFault(ServerFault),
+    /// Returns to the settings popup afterwards.
+    Setting { index: usize, config: Box<Config>, changed: bool },
+    CorsCheck,
+    /// With the error of a pattern that didn't compile, shown until the next try.
+    Search { regex: bool, error: Option<String> },
+    /// Typed without being shown.
+    Passphrase { change: bool },
+    NewWorkspace,
+    RenameWorkspace { name: String },
+}
+
+/// What the item chosen in a picker popup applies to.
+enum PickTarget {
+    Environment,
+    MoveRequest { index: usize },
+    TagFilter { tags: Vec<String> },
+    RecentUrl { urls: Vec<String> },
+    HistoryEntry { ids: Vec<u64> },
+    ReplayEnvironment,
+    RevealSecrets,
+    HistoryEnvironment { environments: Vec<Option<String>> },
+    /// The default workspace first, then the named ones, then one to create.
+    Workspace { names: Vec<String> },
+    /// Open, rename or delete.
+    WorkspaceAction { name: String },
+    /// Previewed as the selection moves.
+    Theme,
+    Snippet,
+    /// Newest first.
+    RecentError,
+}
+
+/// Work deferred until the user answers a confirmation popup.
+enum Confirmed {
+    ReplaceRange { start: usize, end: usize, text: String },
+    /// With the variable values recorded in history rather than the current environment's.
+    Replay { reuse_generated: bool, original_environment: bool },
+    DeleteFolder { path: Vec<String> },
+    DeleteRequest { index: usize },
+    SendInvalidJson { skip_schema_checks: bool },
+    RecoverDraft(Box<Session>),
+    ReplaceBaseline,
+    DiscardDraft,
+    /// A curl command pasted into the URL field: imported on yes, pasted as text on no.
+    ImportCurl(String),
+    PasteLiterally(String),
+    /// Load testing a host that isn't this machine.
+    RunLoad { plan: load::Plan, request: Box<ResolvedRequest> },
+    DeleteWorkspace { name: String },
+    /// Pinned exchanges are only cleared on a second confirmation.
+    ClearHistory { pinned_too: bool },
+}
+
+/// The last syntax check of a JSON body.
+#[derive(Default)]
+struct JsonCheck {
+    /// The body as it was checked, to notice edits.
+    checked: String,
+    /// When the body last changed, while a check is waiting on typing to pause.
+    edited_at: Option<Instant>,
+    error: Option<editor::SyntaxError>,
+}
+
+/// The last check of the URL as typed.
+#[derive(Default)]
+struct UrlCheck {
+    checked: String,
+    edited_at: Option<Instant>,
+    /// Why the URL won't do, as shown in the error line.
+    error: Option<String>,
+}
+
+/// A 429 or 503 with a `Retry-After`, until its wait is over or its retry is answered.
+struct RateLimit {
+    /// The history entry that was limited.
+    id: u64,
+    request: ResolvedRequest,
+    until: Instant,
+    /// The history entry of a retry waiting on the worker.
+    scheduled: Option<u64>,
+    /// Retries so far, counting the one scheduled.
+    attempts: usize,
+    /// How many times a retry may be rescheduled without asking.
+    max_retries: usize,
+}
+
+/// A setting of the request in the editor that overrides the config.
+#[derive(Clone, Copy, PartialEq)]
+enum RequestSetting {
+    Timeout,
+    Redirects,
+    Retries,
+    RequestId,
+    BodySchema,
+    ResponseSchema,
+    ExpectContinue,
+    MaxResponse,
+}
+
+impl RequestSetting {
+    const ALL: [RequestSetting; 8] = [
+        RequestSetting::Timeout,
+        RequestSetting::Redirects,
+        RequestSetting::Retries,
+        RequestSetting::RequestId,
+        RequestSetting::BodySchema,
+        RequestSetting::ResponseSchema,
+        RequestSetting::ExpectContinue,
+        RequestSetting::MaxResponse,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            RequestSetting::Timeout => "timeout",
+            RequestSetting::Redirects => "redirects",
+            RequestSetting::Retries => "retries",
+            RequestSetting::RequestId => "request id",
+            RequestSetting::BodySchema => "body schema",
+            RequestSetting::ResponseSchema => "response schema",
+            RequestSetting::ExpectContinue => "expect 100",
+            RequestSetting::MaxResponse => "max response",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum ServerFault {
+    Delay,
+    FailRate,
+    Stall,
+    StallBody,
+    CutBody,
+}
+
+impl ServerFault {
+    const ALL: [ServerFault; 5] = [ServerFault::Delay, ServerFault::FailRate, ServerFault::Stall, ServerFault::StallBody, ServerFault::CutBody];
+
+    fn name(self) -> &'static str {
+        match self {
+            ServerFault::Delay => "delay",
+            ServerFault::FailRate => "fail rate",
+            ServerFault::Stall => "stall",
+            ServerFault::StallBody => "stall body",
+            ServerFault::CutBody => "cut body",
+        }
+    }
+
+    fn describe(self, faults: &TestServerConfig) -> String {
+        match self {
+            ServerFault::Delay => format!("{} ms", faults.delay_ms),
+            ServerFault::FailRate => format!("{}%", faults.fail_percent),
+            ServerFault::Stall => on_off(faults.stall),
+            ServerFault::StallBody => on_off(faults.stall_body),
+            ServerFault::CutBody => on_off(faults.cut_body),
+        }
+    }
+}
+
+fn on_off(on: bool) -> String {
+    if on {"on".to_string()} else {"off".to_string()}
+}
+
+/// Field contents from before an edit, restored by undo.
+struct UndoEntry {
+    input_index: usize,
+    text: String,
+    character_index: usize,
+}
+
 impl App {
-    pub fn new(client: HttpClient, server_addr: String) -> Self {
+    /// `restore_session` brings back the editor as it was at the last clean exit.
+    pub fn new(server_addr: String, restore_session: bool) -> Self {
+        Self::open(|config| Worker::spawn(RequestLog::from_config(&config.request_log)), server_addr, restore_session, false)
+    }
+
+    /// For `--run`: starts from a fresh editor and leaves any draft alone, and rather than ask
+    /// for the secrets passphrase, unlocks with a stored one or leaves them locked.
+    pub fn headless(server_addr: String) -> Self {
+        Self::open(|config| Worker::spawn(RequestLog::from_config(&config.request_log)), server_addr, false, true)
+    }
+
+    /// Loads the active workspace, keeping the worker of the one before if there was one.
+    fn open(worker: impl FnOnce(&Config) -> Worker, server_addr: String, restore_session: bool, headless: bool) -> Self {
         let empty_string = "".to_string();
+        let mut startup_errors = vec![];
+        let config = config::load().unwrap_or_else(|e| {
+            startup_errors.push(format!("Couldn't load config: {}", e));
+            Config::default()
+        });
+        my_test_server::set_faults(config.test_server);
+        secrets::set_extra_headers(&config.secret_headers);
+        let (environments, vault) = environment::load(&config::environments_path()).unwrap_or_else(|e| {
+            startup_errors.push(format!("Couldn't load environments: {}", e));
+            (vec![], None)
+        });
+        let collection = collection::load(&config::collection_path()).unwrap_or_else(|e| {
+            startup_errors.push(format!("Couldn't load collection: {}", e));
+            Collection::default()
+        });
+        let mut history = history::load(&config::history_path()).unwrap_or_else(|e| {
+            startup_errors.push(format!("Couldn't load history: {}", e));
+            vec![]
+        });
+        for entry in history.iter_mut().filter(|entry| entry.is_pending()) {
+            entry.error = Some("No response recorded before the app last closed".to_string());
+        }
+        let theme = Theme::from_config(config.no_color, config.theme);
+        let rendered_bodies = history
+            .iter()
+            .filter_map(|entry| Some((entry.id, render_body(entry.response.as_ref()?, &entry.request.method, &theme, true, JsonView::Formatted))))
+            .collect();
+        // Headless output goes to stdout, where title escapes would end up in the report.
+        let terminal_title = config.terminal_title && !headless;
+        if terminal_title {
+            if let Err(e) = title::save() {
+                startup_errors.push(format!("Couldn't save the terminal title: {}", e));
+            }
+            title::restore_on_panic();
+        }
+        let (keymap, key_errors) = Keymap::from_config(&config.keys);
+        startup_errors.extend(key_errors.into_iter().map(|e| format!("Bad key binding: {}", e)));
+        let session = if restore_session {
+            session::load(&config::session_path()).unwrap_or_else(|e| {
+                startup_errors.push(format!("Couldn't restore the last session, starting fresh: {}", e));
+                None
+            })
+        } else {
+            None
+        };
+        let draft = match headless {
+            true => None,
+            false => session::load_draft(&config::drafts_path()).unwrap_or_else(|e| {
+                startup_errors.push(format!("Couldn't read the unsaved draft: {}", e));
+                None
+            }),
+        };
 
-        Self {
+        let mut app = Self {
             input_order: vec![InputMode::EditingMethod, InputMode::EditingUrl, InputMode::EditingHeaders, InputMode::EditingBody],
             input_index: 3,
             character_index: 0,
             error_message: None,
-            url_input: server_addr,
+            unanswered: HashMap::new(),
+            last_failure: None,
+            recent_errors: VecDeque::new(),
+            url_input: server_addr.clone(),
+            server_addr,
             headers_input: empty_string.clone(),
             body_input: empty_string,
-            client,
-            responses: vec![],
+            json_check: JsonCheck::default(),
+            url_check: UrlCheck::default(),
+            worker: worker(&config),
+            in_flight: 0,
+            transfers: HashMap::new(),
+            bytes_sent: 0,
+            bytes_received: 0,
             method_index: 0,
+            popup: None,
+            undo_stack: vec![],
+            kill_buffer: String::new(),
+            history,
+            rendered_bodies,
+            readable_html: true,
+            json_view: JsonView::Formatted,
+            json_tree: None,
+            search_query: String::new(),
+            search_regex: false,
+            search: None,
+            last_sent: None,
+            previewed_values: None,
+            collection,
+            loaded_request: None,
+            request_hooks: vec![],
+            response_hooks: vec![],
+            hook_outcomes: HashMap::new(),
+            chain_depth: HashMap::new(),
+            chain_parent: HashMap::new(),
+            baseline_rules: config.baseline.clone(),
+            comparison: None,
+            rate_limit: None,
+            flash: None,
+            max_auto_retries: config.rate_limit.max_auto_retries,
+            load: None,
+            query_rows: vec![],
+            cors_checks: HashMap::new(),
+            cors_input: "http://localhost:3000 GET".to_string(),
+            load_bucket: 0,
+            run: None,
+            run_policy: config.runner,
+            baseline_results: HashMap::new(),
+            show_collection: false,
+            focus: Pane::Editor,
+            response_view: ResponseView { expanded: false, sorted: false, selected: 0, wrap: config.wrap_responses, column: 0 },
+            header_table: None,
+            collection_selected: 0,
+            collapsed_folders: BTreeSet::new(),
+            tag_filter: None,
+            environments,
+            active_environment: None,
+            variables: HashMap::new(),
+            vault,
+            vault_key: None,
+            history_environment: None,
+            history_grouping: config.history_grouping,
+            history_sort: HistorySort::Recency,
+            status_filter: StatusFilter::All,
+            endpoint_filter: None,
+            collapsed_groups: BTreeSet::new(),
+            group_selected: 0,
+            exchange_selected: None,
+            collapse_repeats: config.responses.collapse_repeats,
+            expand_repeats: false,
+            validators: BTreeMap::new(),
+            send_conditional: false,
+            connect_to: None,
+            redirect_policy: None,
+            redirect_default: config.redirects,
+            request_timeout: None,
+            default_timeout: config.timeout_secs,
+            connect_timeout: config.connect_timeout_secs,
+            connect_budget: config.connect_budget_secs,
+            read_timeout: config.read_timeout_secs,
+            request_max_retries: None,
+            request_id: None,
+            body_schema: None,
+            response_schema: None,
+            expect_continue: None,
+            max_response_mb: None,
+            default_max_response_mb: config.responses.max_size_mb,
+            skip_schema_checks: false,
+            expected_schemas: HashMap::new(),
+            request_id_default: config.request_id,
+            host_rules: config.hosts.clone(),
+            revealed: BTreeSet::new(),
+            refreshable: HashMap::new(),
+            refreshing: HashMap::new(),
+            refresh_retries: HashMap::new(),
+            deadlines: HashMap::new(),
+            address_preference: AddressPreference::AsResolved,
+            console: VecDeque::new(),
+            show_console: false,
+            console_scroll: 0,
+            server_traffic: VecDeque::new(),
+            traffic: my_test_server::traffic(),
+            show_traffic: false,
+            keymap,
+            theme,
+            theme_preset: config.theme,
+            terminal_title: terminal_title.then(String::new),
+            suspend_requested: false,
+            external_edit_requested: false,
+            open_response_requested: false,
+            open_with: config.responses.open_with.clone(),
+            temp_files: vec![],
+            quit_requested: false,
+            switch_workspace: None,
+            draft: Session::default(),
+            draft_checked_at: Instant::now(),
+        };
+        app.worker.dns.set_ttl(Duration::from_secs(config.dns.ttl_secs));
+        if let Some(session) = session {
+            if let Err(e) = app.restore_session(session) {
+                startup_errors.push(e);
+            }
+        }
+        app.draft = app.session();
+        if let Some(draft) = draft {
+            app.offer_draft(draft);
+        }
+        let vault = if headless {app.unlock_headless()} else {app.start_vault()};
+        if let Err(e) = vault {
+            startup_errors.push(e);
+        }
+        app.error_message = match &startup_errors[..] {
+            [] => None,
+            [error] => Some(AppError::from(error.clone())),
+            errors => Some(AppError::from(errors.join("; ")).detail(errors.join("\n"))),
+        };
+        app
+    }
+
+    fn session(&self) -> Session {
+        Session {
+            method: method_name(self.method_index),
+            url: self.url_input.clone(),
+            headers: self.headers_input.clone(),
+            body: self.body_input.clone(),
+            field: self.input_index,
+            cursor: self.character_index,
+            environment: self.active_environment.map(|i| self.environments[i].name.clone()),
+            show_collection: self.show_collection,
+            show_console: self.show_console,
+        }
+    }
+
+    /// Asks whether to bring back a draft left by a run that didn't exit cleanly.
+    fn offer_draft(&mut self, draft: Draft) {
+        let saved_at = DateTime::parse_from_rfc3339(&draft.saved_at).map(|time| time.with_timezone(&Local));
+        let when = match saved_at {
+            Ok(time) if time.date_naive() == Local::now().date_naive() => time.format("%H:%M").to_string(),
+            Ok(time) => time.format("%Y-%m-%d %H:%M").to_string(),
+            Err(_) => "an earlier run".to_string(),
+        };
+        self.popup = Some(Popup::Confirm {
+            message: format!("Recover unsaved draft from {}?", when),
+            on_yes: Confirmed::RecoverDraft(Box::new(draft.editor)),
+            on_no: Some(Confirmed::DiscardDraft),
+        });
+    }
+
+    /// Hands the editor to the worker to save as a draft, at most every `DRAFT_INTERVAL` and
+    /// only when the request has changed since the last autosave.
+    fn autosave(&mut self, now: Instant) {
+        if now.duration_since(self.draft_checked_at) < DRAFT_INTERVAL {
+            return;
+        }
+        self.draft_checked_at = now;
+        let editor = self.session();
+        if !editor.edited_since(&self.draft) {
+            return;
         }
+        self.draft = editor.clone();
+        let draft = Draft { saved_at: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true), editor };
+        self.worker.submit(Job::SaveDraft { path: config::drafts_path(), draft: Box::new(draft) });
+    }
+
+    fn restore_session(&mut self, session: Session) -> Result<(), String> {
+        self.method_index = method_index(&session.method);
+        self.url_input = session.url;
+        self.headers_input = session.headers;
+        self.body_input = session.body;
+        self.input_index = session.field.min(self.input_order.len() - 1);
+        self.character_index = if *self.get_input_mode() == InputMode::EditingMethod {0} else {self.clamp_cursor(session.cursor)};
+        self.show_collection = session.show_collection;
+        self.show_console = session.show_console;
+        let Some(name) = session.environment else { return Ok(()) };
+        let Some(index) = self.environments.iter().position(|env| env.name == name) else {
+            return Err(format!("The last session's environment {:?} no longer exists", name));
+        };
+        self.active_environment = Some(index);
+        self.load_variables()
     }
 
     fn move_cursor_left(&mut self) {
@@ -184,90 +1127,6101 @@ impl App {
         new_cursor_pos.clamp(0, self.get_current_input().chars().count())
     }
 
-    fn send_req(&mut self) {
-        let (url, endpoint) = self.url_input.split_once("/").unwrap_or((&self.url_input, ""));
-        let req = HttpRequest {
-            method: HTTP_METHODS[self.method_index].clone(),
-            endpoint: format!("/{}",endpoint).to_string(),
-            headers: vec![], // TODO
+    /// Character range of the word touching the cursor, using `is_word_char` to find its edges.
+    fn word_under_cursor(&self, is_word_char: fn(char) -> bool) -> (usize, usize) {
+        let chars: Vec<char> = self.get_current_input().chars().collect();
+        let cursor = self.character_index.min(chars.len());
+        let mut start = cursor;
+        while start > 0 && is_word_char(chars[start - 1]) {
+            start -= 1;
+        }
+        let mut end = cursor;
+        while end < chars.len() && is_word_char(chars[end]) {
+            end += 1;
+        }
+        (start, end)
+    }
+
+    fn current_request(&self) -> RequestSpec {
+        RequestSpec {
+            method: method_name(self.method_index),
+            url: self.url_input.clone(),
+            headers: self.headers_input.clone(),
             body: self.body_input.clone(),
+            connect_to: self.connect_to.clone(),
+            hooks: self.request_hooks.clone(),
+            after_response: self.response_hooks.clone(),
+            redirects: self.redirect_policy,
+            timeout_secs: self.request_timeout,
+            max_retries: self.request_max_retries,
+            request_id: self.request_id,
+            body_schema: self.body_schema.clone(),
+            response_schema: self.response_schema.clone(),
+            expect_continue: self.expect_continue,
+            max_response_mb: self.max_response_mb,
+        }
+    }
+
+    fn send_req(&mut self, skip_schema_checks: bool) {
+        if let Some(e) = self.url_error() {
+            self.error_message = Some(e.into());
+            return;
+        }
+        if let Some(error) = self.body_json_error() {
+            self.popup = Some(Popup::Confirm {
+                message: format!(
+                    "The body isn't valid JSON ({} at line {}, column {}). Send it anyway?",
+                    error.message, error.line, error.column,
+                ),
+                on_yes: Confirmed::SendInvalidJson { skip_schema_checks },
+                on_no: None,
+            });
+            return;
+        }
+        self.send_unchecked(skip_schema_checks);
+    }
+
+    fn send_unchecked(&mut self, skip_schema_checks: bool) {
+        self.skip_schema_checks = skip_schema_checks;
+        let reuse = self.previewed_values.take();
+        self.send_spec(self.current_request(), reuse.as_deref(), self.loaded_request.clone());
+    }
+
+    /// Checks a body about to be sent against its schema. Problems are warned about in the
+    /// console, or shown and the request held back if the schema is strict. Returns whether to send.
+    fn check_body_schema(&mut self, schema: &BodySchema, body: &str) -> bool {
+        let violations = match schema::validate_body(schema, body) {
+            Ok(violations) if violations.is_empty() => return true,
+            Ok(violations) => violations,
+            Err(e) if schema.strict => {
+                self.error_message = Some(format!("Not sent: couldn't check the body against {}: {}", schema.path, e).into());
+                return false;
+            }
+            Err(e) => {
+                self.error_message = Some(format!("Couldn't check the body against {}: {}", schema.path, e).into());
+                return true;
+            }
+        };
+        if schema.strict {
+            let lines = violations.iter().map(|violation| Line::from(violation.to_string())).collect();
+            self.popup = Some(Popup::Text { title: format!("Not sent: the body doesn't match {}", schema.path), lines, scroll: 0 });
+            return false;
+        }
+        let time = Local::now().format("%H:%M:%S%.3f");
+        for violation in &violations {
+            self.log(format!("[{}] schema: {}", time, violation));
+        }
+        self.error_message = Some(format!("The body doesn't match {} ({} problems, listed in the console): {}", schema.path, violations.len(), violations[0]).into());
+        true
+    }
+
+    /// Checks the editor's body against its schema, with placeholders filled in.
+    fn validate_body(&mut self) {
+        let Some(schema) = self.body_schema.clone() else {
+            self.error_message = Some("No schema set; request settings has one under schema".to_string().into());
+            return;
+        };
+        let body = match Resolver::new(&self.variables, None).resolve(&self.body_input) {
+            Ok(body) => body,
+            Err(e) => {
+                self.error_message = Some(format!("Can't fill in the body: {}", e).into());
+                return;
+            }
+        };
+        let lines = match schema::validate_body(&schema, &body) {
+            Ok(violations) if violations.is_empty() => vec![Line::from("The body matches the schema.")],
+            Ok(violations) => violations.iter().map(|violation| Line::from(violation.to_string())).collect(),
+            Err(e) => vec![Line::from(format!("Couldn't check the body: {}", e))],
         };
+        self.popup = Some(Popup::Text { title: format!("Body against {}", schema.describe()), lines, scroll: 0 });
+    }
 
-        match self.client.send(req, &url.to_string()) {
-            Ok(res) => self.responses.insert(0,res),
-            Err(e) => println!("{}", e),
+    /// The body's JSON syntax error, checked now rather than waiting for the live check.
+    fn body_json_error(&self) -> Option<editor::SyntaxError> {
+        let headers = parse_headers(&self.headers_input);
+        if !editor::is_json(header_value(&headers, "Content-Type"), &self.body_input) || self.body_input.trim().is_empty() {
+            return None;
         }
+        editor::check_json(&self.body_input)
     }
 
-    pub fn move_input_mode_up(&mut self) {
-        let index_shift = self.input_index + self.input_order.len() - 1;
-        self.input_index = index_shift % self.input_order.len();
+    /// Rechecks the body once typing has paused for a moment. Huge bodies are left alone so
+    /// typing in them stays quick.
+    fn check_body(&mut self, now: Instant) {
+        if self.json_check.checked != self.body_input && self.json_check.edited_at.is_none() {
+            self.json_check.edited_at = Some(now);
+        }
+        let Some(edited_at) = self.json_check.edited_at else { return };
+        if now.duration_since(edited_at) < JSON_CHECK_DELAY {
+            return;
+        }
+        self.json_check.edited_at = None;
+        self.json_check.checked = self.body_input.clone();
+        self.json_check.error = if self.body_input.len() > editor::CHECK_LIMIT {None} else {self.body_json_error()};
     }
-    pub fn move_input_mode_down(&mut self) {
-        let index_shift = self.input_index + self.input_order.len() + 1;
-        self.input_index = index_shift % self.input_order.len();
+
+    /// Rechecks the URL once typing has paused, showing what's wrong in the error line and
+    /// clearing it again once fixed.
+    fn check_url(&mut self, now: Instant) {
+        if self.url_check.checked != self.url_input && self.url_check.edited_at.is_none() {
+            self.url_check.edited_at = Some(now);
+        }
+        let Some(edited_at) = self.url_check.edited_at else { return };
+        if now.duration_since(edited_at) < JSON_CHECK_DELAY {
+            return;
+        }
+        self.url_check.edited_at = None;
+        self.url_check.checked = self.url_input.clone();
+        let error = self.url_error();
+        if self.error_message.is_some() && self.error_message.as_ref().map(|e| &e.summary) == self.url_check.error.as_ref() {
+            self.error_message = None;
+        }
+        if let Some(error) = &error {
+            self.error_message = Some(AppError::from(error.clone()));
+        }
+        self.url_check.error = error;
     }
 
-    pub fn run(mut self, mut terminal: DefaultTerminal) -> Result<()> {
-        loop {
-            terminal.draw(|frame| self.draw(frame))?;
+    /// What's wrong with the editor's URL, if anything; nothing while it's empty.
+    fn url_error(&self) -> Option<String> {
+        if self.url_input.trim().is_empty() {
+            return None;
+        }
+        url::check(&self.url_input).err().map(|e| format!("Invalid URL: {}", e))
+    }
 
-            if let Event::Key(key) = event::read()? {
-                if key.kind == KeyEventKind::Press {
-                    match key.code {
-                        KeyCode::Enter => self.send_req(),
-                        KeyCode::Char(to_insert) => self.enter_char(to_insert),
-                        KeyCode::Backspace => self.delete_char(),
-                        KeyCode::Left => self.move_cursor_left(),
-                        KeyCode::Right => self.move_cursor_right(),
-                        KeyCode::Down => self.move_input_mode_down(),
-                        KeyCode::Up => self.move_input_mode_up(),
-                        _ => {},
-                    }
-                }
+    /// Resolves and sends `spec`, `saved` naming the saved request it came from. Returns the
+    /// new history id, or `None` when the request couldn't be resolved.
+    fn send_spec(&mut self, spec: RequestSpec, reuse: Option<&[Generated]>, saved: Option<(Vec<String>, String)>) -> Option<u64> {
+        let skip_schema_checks = std::mem::take(&mut self.skip_schema_checks);
+        if let Some(env) = self.active_environment.map(|i| &self.environments[i]) {
+            let mut used = vec![];
+            for text in [&spec.url, &spec.headers, &spec.body] {
+                template::map_placeholders(text, |expression| {
+                    used.push(expression.to_string());
+                    expression.to_string()
+                });
             }
+            if let Some(name) = env.locked().find(|name| used.contains(name)) {
+                self.error_message = Some(format!("Can't send: {{{{{}}}}} is a locked secret; Unlock secrets asks for the passphrase", name).into());
+                return None;
+            }
+        }
+        let mut resolver = Resolver::new(&self.variables, reuse);
+        let mut resolved = match spec.resolve(&mut resolver, &self.host_rules) {
+            Ok(resolved) => resolved,
+            Err(e) => {
+                self.error_message = Some(format!("Can't send: {}", e).into());
+                return None;
+            }
+        };
+        if self.send_conditional {
+            if let Some(validators) = self.validators.get(&resolved.url()) {
+                validators.apply(&mut resolved.headers);
+            }
+        }
+        if let Some(mismatch) = resolved.content_length_mismatch() {
+            self.error_message = Some(format!("Sent as written, though {}", mismatch).into());
+        }
+
+        let variables = secrets::redact_variables(&resolver.used, &self.secret_variables());
+        let refresh = host_rules::refresh_for(&self.host_rules, &resolved.host_defaults).cloned();
+        let generated = resolver.generated;
+        if let Some(schema) = spec.body_schema.as_ref().filter(|_| !skip_schema_checks) {
+            if !self.check_body_schema(schema, &resolved.body) {
+                return None;
+            }
+        }
+        let id = self.submit(resolved, spec, generated, saved, None);
+        if skip_schema_checks {
+            self.expected_schemas.remove(&id);
+        }
+        if let Some(refresh) = refresh {
+            self.refreshable.insert(id, refresh);
+        }
+        if let Some(entry) = self.history.last_mut() {
+            entry.variables = variables;
         }
+        Some(id)
     }
 
-    fn draw(&self, frame: &mut Frame) {
-        let vertical = Layout::vertical([
-            Constraint::Length(3),
-            Constraint::Length(3),
-            Constraint::Length(3),
-            Constraint::Min(1),
-        ]);
-        let [method_area, url_area, header_area, big_area] = vertical.areas(frame.area());
+    /// Hands a resolved request to the worker and records it in history, pending a response.
+    /// With `at`, the worker holds it until then.
+    fn submit(
+        &mut self,
+        request: ResolvedRequest,
+        spec: RequestSpec,
+        generated: Vec<Generated>,
+        saved: Option<(Vec<String>, String)>,
+        at: Option<Instant>,
+    ) -> u64 {
+        let redirects = spec.redirects.unwrap_or(self.redirect_default);
+        let transport = Transport {
+            preference: self.address_preference,
+            expect: spec.expect_continue,
+            connect_timeout: self.connect_timeout.map(Duration::from_secs),
+            connect_budget: self.connect_budget.map(Duration::from_secs),
+            read_timeout: self.read_timeout.map(Duration::from_secs),
+            deadline: spec.timeout_secs.or(self.default_timeout).map(Duration::from_secs),
+            max_body: match spec.max_response_mb.unwrap_or(self.default_max_response_mb) {
+                0 => None,
+                mb => Some((mb as usize).saturating_mul(1 << 20)),
+            },
+        };
+        self.dispatch(request, redirects, transport, spec, generated, saved, at)
+    }
 
-        let horizontal = Layout::horizontal([
-            Constraint::Min(1),
-            Constraint::Min(1),
-        ]);
-        let [body_area, response_area] = horizontal.areas(big_area);
+    /// Records a request in history and hands it to the worker as it is, with its redirect
+    /// policy and transport settings.
+    #[allow(clippy::too_many_arguments)]
+    fn dispatch(
+        &mut self,
+        mut request: ResolvedRequest,
+        redirects: redirect::Policy,
+        transport: Transport,
+        spec: RequestSpec,
+        generated: Vec<Generated>,
+        saved: Option<(Vec<String>, String)>,
+        at: Option<Instant>,
+    ) -> u64 {
+        let id = self.history.last().map_or(0, |entry| entry.id + 1);
+        if let Some(schema) = &spec.response_schema {
+            self.expected_schemas.insert(id, schema.clone());
+        }
+        // A scheduled send's clock starts when it goes out, which isn't tracked.
+        if let (Some(seconds), None) = (spec.timeout_secs.or(self.default_timeout), at) {
+            self.deadlines.insert(id, (Instant::now() + Duration::from_secs(seconds), seconds));
+        }
+        // Kept without the id, so resending gets a fresh one.
+        self.last_sent = Some(request.clone());
+        if spec.request_id.unwrap_or(self.request_id_default) && header_value(&request.headers, "X-Request-Id").is_none() {
+            request.headers.push(("X-Request-Id".to_string(), template::uuid_v4()));
+        }
+        let request_id = header_value(&request.headers, "X-Request-Id").map(String::from);
+        self.history.push(HistoryEntry {
+            id,
+            timestamp: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+            request: spec,
+            generated,
+            host: request.address.clone(),
+            peer: None,
+            overridden: request.connect_to.is_some(),
+            response: None,
+            timing: None,
+            error: None,
+            saved,
+            redirects: vec![],
+            redirect_stopped: None,
+            request_id,
+            correlation: vec![],
+            environment: self.active_environment.map(|i| self.environments[i].name.clone()),
+            variables: vec![],
+            token_refresh: None,
+            schema_check: None,
+            pinned: false,
+            failed_attempts: vec![],
+            repeats: vec![],
+        });
+        self.unanswered.insert(id, Sent { request: request.clone(), redirects, transport });
+        let job = Job::Send { id, request: Box::new(request), redirects, transport };
+        self.worker.submit(match at {
+            Some(at) => Job::Schedule { at, job: Box::new(job) },
+            None => job,
+        });
+        self.in_flight += 1;
+        id
+    }
 
-        let methods = Tabs::new(HTTP_METHODS.iter().map(|method| format!("{:.?}", method)))
-            .block(Block::bordered().title("Methods"))
-            .select(self.method_index)
-            .style(Style::default().fg(if *self.get_input_mode() == InputMode::EditingMethod {Color::Yellow} else {Color::White}));
-        frame.render_widget(methods, method_area);
+    /// Sends the last request again without re-resolving anything, so variables, generated
+    /// values and conditional headers are exactly as they were.
+    fn resend_last(&mut self) {
+        let (Some(request), Some(entry)) = (self.last_sent.clone(), self.history.last()) else {
+            self.send_req(false);
+            return;
+        };
+        let (spec, generated, saved) = (entry.request.clone(), entry.generated.clone(), entry.saved.clone());
+        let environment = (entry.environment.clone(), entry.variables.clone());
+        self.submit(request, spec, generated, saved, None);
+        self.keep_environment(environment);
+    }
 
-        let url_input = Paragraph::new(self.url_input.as_str())
-            .style(Style::default().fg(if *self.get_input_mode() == InputMode::EditingUrl {Color::Yellow} else {Color::White}))
-            .block(Block::bordered().title("Input"));
-        frame.render_widget(url_input, url_area);
+    /// Sends the newest request that got no response again, exactly as it went out. The retry
+    /// takes the failed exchange's place in history, with the failure kept as an earlier attempt.
+    fn retry_last_failure(&mut self) {
+        let Some((id, sent)) = self.last_failure.take() else {
+            self.error_message = Some("Nothing has failed recently, so there's nothing to retry".to_string().into());
+            return;
+        };
+        let Some(index) = self.history.iter().position(|entry| entry.id == id) else {
+            self.error_message = Some("The failed exchange is no longer in history".to_string().into());
+            return;
+        };
+        let failed = self.history.remove(index);
+        let mut attempts = failed.failed_attempts;
+        attempts.push((failed.timestamp, failed.error.unwrap_or_default()));
+        self.error_message = None;
+        self.dispatch(sent.request, sent.redirects, sent.transport, failed.request, failed.generated, failed.saved, None);
+        if let Some(entry) = self.history.last_mut() {
+            entry.environment = failed.environment;
+            entry.variables = failed.variables;
+            entry.pinned = failed.pinned;
+            entry.failed_attempts = attempts;
+            entry.repeats = failed.repeats;
+        }
+        if let Err(e) = history::save(&config::history_path(), &self.history) {
+            self.error_message = Some(format!("Couldn't save history: {}", e).into());
+        }
+    }
 
-        let headers_input = Paragraph::new(self.headers_input.as_str())
-            .style(Style::default().fg(if *self.get_input_mode() == InputMode::EditingHeaders {Color::Yellow} else {Color::White}))
-            .block(Block::bordered().title("Headers"));
-        frame.render_widget(headers_input, header_area);
+    /// Calls off the newest request still out. The worker reports it back as cancelled, with how
+    /// far it got.
+    fn cancel_request(&mut self) {
+        let Some(id) = self.history.iter().rev().find(|entry| entry.is_pending()).map(|entry| entry.id) else {
+            self.error_message = Some("No request is being sent".to_string().into());
+            return;
+        };
+        if self.rate_limit.as_ref().is_some_and(|limit| limit.scheduled == Some(id)) {
+            self.retry_when_allowed();
+            return;
+        }
+        self.worker.cancel(id);
+        self.flash = Some(("Cancelling…".to_string(), Instant::now() + FLASH_DURATION));
+    }
 
-        let body_input = Paragraph::new(self.body_input.as_str())
-            .style(Style::default().fg(if *self.get_input_mode() == InputMode::EditingBody {Color::Yellow} else {Color::White}))
-            .block(Block::bordered().title("Body"))
-            .wrap(Wrap {trim: true});
-        frame.render_widget(body_input, body_area);
-        
-        let response = List::new(self.responses.iter().map(|res| format!("{:#?}\n---------------------------------", res)))
-            .block(Block::bordered().title("Responses"));
-        frame.render_widget(response, response_area);
+    /// Records the newest entry as sent with an earlier one's environment, for requests sent
+    /// again without resolving them anew.
+    fn keep_environment(&mut self, (environment, variables): (Option<String>, Vec<(String, String)>)) {
+        if let Some(entry) = self.history.last_mut() {
+            entry.environment = environment;
+            entry.variables = variables;
+        }
+    }
+
+    /// Schedules the rate-limited request to go out again once its `Retry-After` has passed,
+    /// or cancels the retry if one is already waiting.
+    fn retry_when_allowed(&mut self) {
+        let Some(limit) = &mut self.rate_limit else {
+            self.error_message = Some("The last response wasn't rate limited with a Retry-After".to_string().into());
+            return;
+        };
+        if let Some(scheduled) = limit.scheduled.take() {
+            limit.attempts -= 1;
+            self.worker.submit(Job::CancelScheduled { id: scheduled });
+            self.in_flight = self.in_flight.saturating_sub(1);
+            if let Some(entry) = self.history.iter_mut().rev().find(|entry| entry.id == scheduled) {
+                entry.error = Some("Retry cancelled".to_string());
+            }
+            return;
+        }
+        self.schedule_retry();
+    }
+
+    fn schedule_retry(&mut self) {
+        let Some(limit) = &self.rate_limit else { return };
+        let Some(entry) = self.history.iter().rev().find(|entry| entry.id == limit.id) else { return };
+        let (request, at) = (limit.request.clone(), limit.until);
+        let (spec, generated, saved) = (entry.request.clone(), entry.generated.clone(), entry.saved.clone());
+        let environment = (entry.environment.clone(), entry.variables.clone());
+        let id = self.submit(request, spec, generated, saved, Some(at));
+        self.keep_environment(environment);
+        if let Some(limit) = &mut self.rate_limit {
+            limit.scheduled = Some(id);
+            limit.attempts += 1;
+        }
+    }
+
+    /// Notes a 429 or 503 with a `Retry-After`. A scheduled retry that's limited again is
+    /// rescheduled, up to the configured number of times.
+    fn rate_limited(&mut self, id: u64, request: ResolvedRequest, seconds: u64) {
+        let attempts = self.rate_limit.as_ref().filter(|limit| limit.scheduled == Some(id)).map_or(0, |limit| limit.attempts);
+        let max_retries = self.history.iter().rev()
+            .find(|entry| entry.id == id)
+            .and_then(|entry| entry.request.max_retries)
+            .unwrap_or(self.max_auto_retries);
+        self.rate_limit = Some(RateLimit {
+            id,
+            request,
+            until: Instant::now() + Duration::from_secs(seconds),
+            scheduled: None,
+            attempts,
+            max_retries,
+        });
+        if attempts == 0 {
+            return;
+        }
+        if attempts <= max_retries {
+            self.schedule_retry();
+        } else {
+            self.error_message = Some(format!("Still rate limited after {} retries; not retrying again", attempts).into());
+        }
+    }
+
+    /// The status bar's rate limit notice, while there's one to show.
+    fn rate_limit_notice(&self, now: Instant) -> Option<String> {
+        let limit = self.rate_limit.as_ref()?;
+        let wait = limit.until.saturating_duration_since(now);
+        // Round up so the countdown only says 0s once it's over.
+        let seconds = wait.as_secs() + u64::from(wait.subsec_nanos() > 0);
+        Some(match limit.scheduled {
+            Some(_) => format!("rate limited — retrying in {}s (retry {} of {})", seconds, limit.attempts, limit.max_retries + 1),
+            None => format!("rate limited — retry allowed in {}s", seconds),
+        })
+    }
+
+    fn latest_response(&self) -> Option<&ResponseRecord> {
+        self.history.iter().rev().find_map(|entry| entry.response.as_ref())
+    }
+
+    fn handle_worker_event(&mut self, event: WorkerEvent) {
+        match event {
+            WorkerEvent::Log(line) => self.log(line),
+            WorkerEvent::Reachable { target, steps } => self.show_reachable(&target, &steps),
+            WorkerEvent::KeyDerived { vault, change, key } => self.key_derived(vault, change, key),
+            WorkerEvent::Progress { id, progress } => {
+                // Each direction's rate is counted from its own first report.
+                match self.transfers.get_mut(&id) {
+                    Some(transfer) if std::mem::discriminant(&transfer.progress) == std::mem::discriminant(&progress) => transfer.progress = progress,
+                    _ => {
+                        self.transfers.insert(id, Transfer { progress, since: Instant::now() });
+                    }
+                }
+            }
+            WorkerEvent::SchemaChecked { id, check } => {
+                let Some(entry) = self.history.iter_mut().rev().find(|entry| entry.id == id) else { return };
+                entry.schema_check = Some(check);
+                if let Err(e) = history::save(&config::history_path(), &self.history) {
+                    self.error_message = Some(format!("Couldn't save history: {}", e).into());
+                }
+            }
+            WorkerEvent::Response { id, request, peer, result, timing, redirects, stopped } => {
+                self.deadlines.remove(&id);
+                self.transfers.remove(&id);
+                self.bytes_sent += timing.sent_bytes.unwrap_or_default();
+                self.bytes_received += timing.received_bytes.unwrap_or_default();
+                let Some(entry) = self.history.iter_mut().rev().find(|entry| entry.id == id) else { return };
+                // Timed out or cancelled already; in_flight was settled then.
+                if !entry.is_pending() {
+                    return;
+                }
+                self.in_flight = self.in_flight.saturating_sub(1);
+                entry.peer = Some(peer);
+                entry.redirects = redirects;
+                entry.redirect_stopped = stopped;
+                entry.timing = Some(*timing);
+                match result {
+                    Ok(res) => {
+                        self.unanswered.remove(&id);
+                        if let Some(validators) = Validators::from_headers(&res.headers) {
+                            self.validators.insert(request.url(), validators);
+                        }
+                        let response = *res;
+                        let retry_after = rate_limit::is_rate_limited(response.status)
+                            .then(|| rate_limit::retry_after(&response.headers, Utc::now()))
+                            .flatten();
+                        self.rendered_bodies.insert(id, render_body(&response, &request.method, &self.theme, self.readable_html, self.json_view));
+                        let pinned = entry.saved.as_ref()
+                            .and_then(|(folder, name)| self.collection.position(folder, name))
+                            .and_then(|index| self.collection.requests[index].baseline.as_ref());
+                        if let Some(pinned) = pinned {
+                            self.baseline_results.insert(id, baseline::compare(pinned, &response, &self.baseline_rules));
+                        }
+                        let has_content_type = header_value(&request.headers, "Content-Type").is_some();
+                        if let Some(error) = app_error::response_hint(response.status, &response.body, has_content_type, !request.body.is_empty()) {
+                            self.error_message = Some(error);
+                        }
+                        let after_response = entry.request.after_response.clone();
+                        entry.correlation = history::correlation_ids(&response.headers);
+                        entry.response = Some(response.clone());
+                        if !after_response.is_empty() {
+                            let outcomes = self.run_post_hooks(id, &after_response, &response);
+                            self.hook_outcomes.insert(id, outcomes);
+                        }
+                        if let Some(preflight) = self.cors_checks.remove(&id) {
+                            self.show_cors_verdict(&preflight, &response);
+                        }
+                        match retry_after {
+                            Some(seconds) => self.rate_limited(id, *request, seconds),
+                            None if self.rate_limit.as_ref().is_some_and(|limit| limit.scheduled == Some(id)) => self.rate_limit = None,
+                            None => {}
+                        }
+                        if let Some(schema) = self.expected_schemas.remove(&id) {
+                            self.worker.submit(Job::CheckSchema { id, schema, body: response.body.clone() });
+                        }
+                        self.continue_token_refresh(id, Some(&response));
+                    }
+                    Err(e) => {
+                        if let Some(sent) = self.unanswered.remove(&id) {
+                            self.last_failure = Some((id, sent));
+                        }
+                        entry.error = Some(e.summary.clone());
+                        self.error_message = Some(e);
+                        self.cors_checks.remove(&id);
+                        self.expected_schemas.remove(&id);
+                        if self.rate_limit.as_ref().is_some_and(|limit| limit.scheduled == Some(id)) {
+                            self.rate_limit = None;
+                        }
+                        self.continue_token_refresh(id, None);
+                    }
+                }
+                if self.collapse_repeats {
+                    self.fold_repeat(id);
+                }
+                self.sync_json_tree();
+                if let Err(e) = history::save(&config::history_path(), &self.history) {
+                    self.error_message = Some(format!("Couldn't save history: {}", e).into());
+                }
+            }
+        }
     }
-}
\ No newline at end of file
+
+    /// Folds the run of the same request just before `id` into it, dropping what was kept for
+    /// the folded entry on its own.
+    fn fold_repeat(&mut self, id: u64) {
+        if let Some(folded) = history::fold_repeat(&mut self.history, id) {
+            self.rendered_bodies.remove(&folded);
+            self.baseline_results.remove(&folded);
+            self.hook_outcomes.remove(&folded);
+        }
+    }
+
+    /// Moves automatic token refreshes along once `id` is answered, `response` being `None` when
+    /// it failed. A 401 to a refreshable request gets a new token, which the request is then
+    /// retried with once; neither the token request nor the retry start another refresh.
+    fn continue_token_refresh(&mut self, id: u64, response: Option<&ResponseRecord>) {
+        let status = response.and_then(|response| response.status);
+        if let Some(refresh) = self.refreshable.remove(&id) {
+            if status == Some(401) {
+                self.refresh_token(id, refresh);
+            }
+            return;
+        }
+        if let Some((rejected, refresh)) = self.refreshing.remove(&id) {
+            let token = match response {
+                Some(response) if status.is_some_and(|status| (200..300).contains(&status)) => serde_json::from_str(&response.body)
+                    .map_err(|e| format!("the token response isn't JSON: {}", e))
+                    .and_then(|body| hooks::json_path(&body, &refresh.json_path).cloned())
+                    .map(|token| token.as_str().map_or(token.to_string(), str::to_string)),
+                Some(_) => Err(format!("the token request got {}", status.map_or("no status".to_string(), |status| status.to_string()))),
+                None => Err("the token request failed".to_string()),
+            };
+            match token {
+                Ok(token) => {
+                    self.variables.insert(refresh.variable.clone(), token);
+                    self.retry_with_new_token(rejected);
+                }
+                Err(e) => self.note_token_refresh(rejected, format!("401 → token refresh failed: {}", e)),
+            }
+            return;
+        }
+        if let Some(rejected) = self.refresh_retries.remove(&id) {
+            let outcome = match status {
+                Some(401) => "401 again; not refreshing again".to_string(),
+                Some(status) => status.to_string(),
+                None => "failed".to_string(),
+            };
+            self.note_token_refresh(rejected, format!("401 → refreshed token → {}", outcome));
+        }
+    }
+
+    /// Sends the request that gets a new token for the rejected request `id`.
+    fn refresh_token(&mut self, id: u64, refresh: TokenRefresh) {
+        let (spec, saved) = match &refresh.source {
+            TokenSource::SavedRequest { request } => match self.collection.requests.iter().find(|saved| saved.name == *request) {
+                Some(saved) => (saved.request.clone(), Some((saved.folder.clone(), saved.name.clone()))),
+                None => return self.note_token_refresh(id, format!("401 → can't refresh the token: no saved request called {}", request)),
+            },
+            TokenSource::Endpoint { url, body } => (RequestSpec {
+                method: "POST".to_string(),
+                url: url.clone(),
+                headers: "Content-Type: application/x-www-form-urlencoded".to_string(),
+                body: body.clone(),
+                connect_to: None,
+                hooks: vec![],
+                after_response: vec![],
+                redirects: None,
+                timeout_secs: None,
+                max_retries: None,
+                request_id: None,
+                body_schema: None,
+                response_schema: None,
+                expect_continue: None,
+                max_response_mb: None,
+            }, None),
+        };
+        match self.send_spec(spec, None, saved) {
+            Some(login) => {
+                self.refreshable.remove(&login);
+                self.refreshing.insert(login, (id, refresh));
+                self.note_token_refresh(id, "401 → refreshing the token…".to_string());
+            }
+            None => {
+                let reason = self.error_message.as_ref().map(AppError::to_string).unwrap_or_default();
+                self.note_token_refresh(id, format!("401 → can't refresh the token: {}", reason));
+            }
+        }
+    }
+
+    /// Sends the rejected request `id` again, resolved anew so it picks up the new token.
+    fn retry_with_new_token(&mut self, id: u64) {
+        let Some(entry) = self.history.iter().rev().find(|entry| entry.id == id) else { return };
+        let (spec, saved) = (entry.request.clone(), entry.saved.clone());
+        match self.send_spec(spec, None, saved) {
+            Some(retry) => {
+                self.refreshable.remove(&retry);
+                self.refresh_retries.insert(retry, id);
+                self.note_token_refresh(id, "401 → refreshed token → retrying…".to_string());
+                self.note_token_refresh(retry, "Retry with a refreshed token after a 401".to_string());
+            }
+            None => {
+                let reason = self.error_message.as_ref().map(AppError::to_string).unwrap_or_default();
+                self.note_token_refresh(id, format!("401 → refreshed token → couldn't retry: {}", reason));
+            }
+        }
+    }
+
+    fn note_token_refresh(&mut self, id: u64, note: String) {
+        if let Some(entry) = self.history.iter_mut().rev().find(|entry| entry.id == id) {
+            entry.token_refresh = Some(note);
+        }
+    }
+
+    /// Runs a response's post-response hooks in order. A failing hook is reported and the rest
+    /// still run.
+    fn run_post_hooks(&mut self, id: u64, post_hooks: &[PostHook], response: &ResponseRecord) -> Vec<HookOutcome> {
+        let mut outcomes = vec![];
+        for post_hook in post_hooks {
+            outcomes.push(match post_hook {
+                PostHook::SaveBody { path } => match fs::write(path, &response.body) {
+                    Ok(()) if response.truncated_from.is_some() => HookOutcome::failed(format!("saved only part of the body to {}: {}", path, truncation_note(response))),
+                    Ok(()) => HookOutcome::ok(format!("saved body to {}", path)),
+                    Err(e) => HookOutcome::failed(format!("couldn't save body to {}: {}", path, e)),
+                },
+                PostHook::SetVariable { name, json_path } => {
+                    let value = serde_json::from_str::<serde_json::Value>(&response.body)
+                        .map_err(|e| format!("body isn't JSON: {}", e))
+                        .and_then(|body| hooks::json_path(&body, json_path).cloned());
+                    match value {
+                        Ok(value) => {
+                            let value = value.as_str().map_or(value.to_string(), str::to_string);
+                            let shown: String = value.chars().take(40).collect();
+                            self.variables.insert(name.clone(), value);
+                            HookOutcome::ok(format!("set {} = {}", name, shown))
+                        }
+                        Err(e) => HookOutcome::failed(format!("couldn't set {}: {}", name, e)),
+                    }
+                }
+                PostHook::SetVariableFromHeader { name, header, occurrence } => match occurrence.pick(&response.headers, header) {
+                    Some(value) => {
+                        let shown: String = value.chars().take(40).collect();
+                        self.variables.insert(name.clone(), value);
+                        HookOutcome::ok(format!("set {} = {}", name, shown))
+                    }
+                    None => HookOutcome::failed(format!("couldn't set {}: no {} header in the response", name, header)),
+                },
+                PostHook::Trigger { request, if_status, when } => {
+                    let status = if_status.filter(|status| response.status != Some(*status)).map(|status| format!("status isn't {}", status));
+                    let unmet = status.or_else(|| when.iter().find_map(|condition| condition.unmet(response.status, &response.headers, &response.body)));
+                    match unmet {
+                        // Chained requests stop here, so say why.
+                        Some(reason) if self.chain_parent.contains_key(&id) => HookOutcome::failed(format!("chain stopped before {}: {}", request, reason)),
+                        Some(reason) => HookOutcome::ok(format!("didn't trigger {}: {}", request, reason)),
+                        None => self.trigger(id, request),
+                    }
+                }
+            });
+        }
+        outcomes
+    }
+
+    fn trigger(&mut self, id: u64, name: &str) -> HookOutcome {
+        let depth = self.chain_depth.get(&id).copied().unwrap_or(0) + 1;
+        if depth > MAX_CHAIN_DEPTH {
+            return HookOutcome::failed(format!("didn't trigger {}: already {} requests deep in a chain", name, MAX_CHAIN_DEPTH));
+        }
+        let Some(saved) = self.collection.requests.iter().find(|saved| saved.name == name) else {
+            return HookOutcome::failed(format!("no saved request called {}", name));
+        };
+        let (spec, key) = (saved.request.clone(), (saved.folder.clone(), saved.name.clone()));
+        match self.send_spec(spec, None, Some(key)) {
+            Some(triggered) => {
+                self.chain_depth.insert(triggered, depth);
+                self.chain_parent.insert(triggered, id);
+                HookOutcome::ok(format!("triggered {}", name))
+            }
+            None => HookOutcome::failed(format!("couldn't trigger {}: {}", name, self.error_message.as_ref().map(AppError::to_string).unwrap_or_default())),
+        }
+    }
+
+    /// Each earlier leg of the chain that led to `id`, first one first, with its status and
+    /// latency as they stand.
+    fn chain_legs(&self, id: u64) -> Vec<String> {
+        let mut ids = vec![];
+        let mut current = id;
+        while let Some(&parent) = self.chain_parent.get(&current) {
+            ids.push(parent);
+            current = parent;
+        }
+        ids.iter().rev().filter_map(|id| self.history.iter().find(|entry| entry.id == *id)).map(|entry| {
+            let name = entry.saved.as_ref().map_or(entry.request.url.clone(), |(_, name)| name.clone());
+            let outcome = match (&entry.response, &entry.error) {
+                (Some(response), _) => response.status.map_or(response.reason.clone(), |code| code.to_string()),
+                (None, Some(_)) => "failed".to_string(),
+                (None, None) => "…".to_string(),
+            };
+            let latency = entry.timing.map(|timing| format!(" {} ms", timing.latency_ms)).unwrap_or_default();
+            format!("{} {}{}", name, outcome, latency)
+        }).collect()
+    }
+
+    fn copy_console(&mut self) {
+        let transcript: Vec<&str> = self.console.iter().map(String::as_str).collect();
+        if let Err(e) = clipboard::copy(&transcript.join("\n")) {
+            self.error_message = Some(format!("Couldn't copy the console: {}", e).into());
+        }
+    }
+
+    fn save_mock(&mut self, index: Option<usize>, edited: Mock) {
+        let selected = match index {
+            Some(index) => {
+                self.collection.mocks[index] = edited;
+                index
+            }
+            None => {
+                self.collection.mocks.push(edited);
+                self.collection.mocks.len() - 1
+            }
+        };
+        mock::set(&self.collection.mocks);
+        self.save_collection();
+        self.popup = Some(Popup::Mocks { selected });
+    }
+
+    fn log(&mut self, line: String) {
+        if self.console.len() == CONSOLE_CAPACITY {
+            self.console.pop_front();
+        }
+        self.console.push_back(line);
+    }
+
+    fn save_collection(&mut self) {
+        if let Err(e) = collection::save(&config::collection_path(), &self.collection) {
+            self.error_message = Some(format!("Couldn't save collection: {}", e).into());
+        }
+    }
+
+    /// Replaces the editor contents with a saved request.
+    fn load_saved(&mut self, index: usize) {
+        let Some(saved) = self.collection.requests.get_mut(index) else { return };
+        saved.last_used = Some(Utc::now().timestamp());
+        let request = saved.request.clone();
+        let key = (saved.folder.clone(), saved.name.clone());
+        self.set_editor(request);
+        self.loaded_request = Some(key);
+        self.save_collection();
+    }
+
+    /// Puts a request in the editor, replacing whatever was there.
+    fn set_editor(&mut self, request: RequestSpec) {
+        self.method_index = method_index(&request.method);
+        self.url_input = request.url;
+        self.headers_input = request.headers;
+        self.body_input = request.body;
+        self.connect_to = request.connect_to;
+        self.redirect_policy = request.redirects;
+        self.request_timeout = request.timeout_secs;
+        self.request_max_retries = request.max_retries;
+        self.request_id = request.request_id;
+        self.body_schema = request.body_schema;
+        self.response_schema = request.response_schema;
+        self.expect_continue = request.expect_continue;
+        self.max_response_mb = request.max_response_mb;
+        self.request_hooks = request.hooks;
+        self.response_hooks = request.after_response;
+        self.loaded_request = None;
+        self.character_index = self.clamp_cursor(self.character_index);
+        self.undo_stack.clear();
+        self.previewed_values = None;
+    }
+
+    fn collection_rows(&self) -> Vec<TreeRow> {
+        self.collection.tree(&self.collapsed_folders, self.tag_filter.as_deref())
+    }
+
+    /// Where new requests and folders go: the folder selected in the collection pane, or the
+    /// folder of the selected request.
+    fn selected_folder(&self) -> Vec<String> {
+        if !self.show_collection {
+            return vec![];
+        }
+        match self.collection_rows().into_iter().nth(self.collection_selected) {
+            Some(TreeRow::Folder { path, .. }) => path,
+            Some(TreeRow::Request(index)) => self.collection.requests[index].folder.clone(),
+            None => vec![],
+        }
+    }
+
+    /// The one place keys go to, outside popups.
+    fn focus_target(&self) -> Focus {
+        match (self.focus, self.get_input_mode()) {
+            (Pane::Collection, _) => Focus::Collection,
+            (Pane::Responses, _) => Focus::Responses,
+            (Pane::Editor, InputMode::EditingMethod) => Focus::Method,
+            (Pane::Editor, InputMode::EditingUrl) => Focus::Url,
+            (Pane::Editor, InputMode::EditingHeaders) => Focus::Headers,
+            (Pane::Editor, InputMode::EditingBody) => Focus::Body,
+        }
+    }
+
+    fn set_focus(&mut self, target: Focus) {
+        let mode = match target {
+            Focus::Collection => {
+                self.show_collection = true;
+                self.focus = Pane::Collection;
+                return;
+            }
+            Focus::Responses => {
+                self.focus = Pane::Responses;
+                return;
+            }
+            Focus::Method => InputMode::EditingMethod,
+            Focus::Url => InputMode::EditingUrl,
+            Focus::Headers => InputMode::EditingHeaders,
+            Focus::Body => InputMode::EditingBody,
+        };
+        self.focus = Pane::Editor;
+        self.input_index = self.input_order.iter().position(|own| *own == mode).unwrap_or_default();
+    }
+
+    fn toggle_collection(&mut self) {
+        if !self.show_collection {
+            self.show_collection = true;
+            self.focus = Pane::Collection;
+        } else if self.focus == Pane::Collection {
+            self.show_collection = false;
+            self.focus = Pane::Editor;
+        } else {
+            self.focus = Pane::Collection;
+        }
+    }
+
+    fn clamp_collection_selection(&mut self) {
+        self.collection_selected = self.collection_selected.min(self.collection_rows().len().saturating_sub(1));
+    }
+
+    /// Renames a saved request, keeping history entries sent from it and requests that
+    /// trigger it pointing at it.
+    fn rename_request(&mut self, index: usize, name: &str) {
+        let saved = &self.collection.requests[index];
+        let old = (saved.folder.clone(), saved.name.clone());
+        if name.is_empty() || name == old.1 {
+            return;
+        }
+        if let Err(e) = self.collection.rename_request(index, name.to_string()) {
+            self.error_message = Some(e.into());
+            return;
+        }
+        self.rekey_saved(&old, (old.0.clone(), name.to_string()));
+        // Triggers name a request without its folder, so they're left alone while another
+        // request still answers to the old name.
+        if let Some(other) = self.collection.requests.iter().find(|saved| saved.name == old.1) {
+            self.error_message = Some(format!("Triggers of {} now reach the one in {}", old.1, folder_label(&other.folder)).into());
+        } else {
+            let hooks = self.collection.requests.iter_mut()
+                .flat_map(|saved| saved.request.after_response.iter_mut())
+                .chain(self.response_hooks.iter_mut());
+            for hook in hooks {
+                if let PostHook::Trigger { request, .. } = hook {
+                    if *request == old.1 {
+                        *request = name.to_string();
+                    }
+                }
+            }
+        }
+        self.save_collection();
+    }
+
+    /// Points history entries and the editor at a saved request's new folder or name.
+    fn rekey_saved(&mut self, old: &(Vec<String>, String), new: (Vec<String>, String)) {
+        for entry in &mut self.history {
+            if entry.saved.as_ref() == Some(old) {
+                entry.saved = Some(new.clone());
+            }
+        }
+        if self.loaded_request.as_ref() == Some(old) {
+            self.loaded_request = Some(new);
+        }
+    }
+
+    fn confirm_delete_folder(&mut self, path: Vec<String>) {
+        let names: Vec<&str> = self.collection.requests_under(&path).map(|saved| saved.name.as_str()).collect();
+        let contents = match names.len() {
+            0 => "It holds no requests.".to_string(),
+            n if n > 10 => format!("It holds {} requests: {}, and {} more.", n, names[..10].join(", "), n - 10),
+            n => format!("It holds {} request(s): {}.", n, names.join(", ")),
+        };
+        self.popup = Some(Popup::Confirm {
+            message: format!("Delete folder {} and everything in it? {}", folder_label(&path), contents),
+            on_yes: Confirmed::DeleteFolder { path },
+            on_no: None,
+        });
+    }
+
+    fn handle_collection_key(&mut self, key: KeyEvent) {
+        let rows = self.collection_rows();
+        let selected = rows.get(self.collection_selected).cloned();
+        match key.code {
+            KeyCode::Esc => self.focus = Pane::Editor,
+            KeyCode::Up | KeyCode::Down if key.modifiers.contains(KeyModifiers::SHIFT) => {
+                if let Some(TreeRow::Request(index)) = selected {
+                    if let Some(moved) = self.collection.shift_request(index, key.code == KeyCode::Up) {
+                        self.save_collection();
+                        let rows = self.collection_rows();
+                        self.collection_selected = rows.iter().position(|row| *row == TreeRow::Request(moved)).unwrap_or(self.collection_selected);
+                    }
+                }
+            }
+            KeyCode::Up => self.collection_selected = self.collection_selected.saturating_sub(1),
+            KeyCode::Down => self.collection_selected = (self.collection_selected + 1).min(rows.len().saturating_sub(1)),
+            KeyCode::Left => match selected {
+                Some(TreeRow::Folder { path, collapsed: false }) => {
+                    self.collapsed_folders.insert(path);
+                }
+                Some(row) => {
+                    let parent = match row {
+                        TreeRow::Folder { path, .. } => path[..path.len() - 1].to_vec(),
+                        TreeRow::Request(index) => self.collection.requests[index].folder.clone(),
+                    };
+                    if let Some(i) = rows.iter().position(|row| matches!(row, TreeRow::Folder { path, .. } if *path == parent)) {
+                        self.collection_selected = i;
+                    }
+                }
+                None => {}
+            },
+            KeyCode::Right => {
+                if let Some(TreeRow::Folder { path, collapsed: true }) = selected {
+                    self.collapsed_folders.remove(&path);
+                }
+            }
+            KeyCode::Enter => match selected {
+                Some(TreeRow::Folder { path, collapsed }) => {
+                    if collapsed {
+                        self.collapsed_folders.remove(&path);
+                    } else {
+                        self.collapsed_folders.insert(path);
+                    }
+                }
+                Some(TreeRow::Request(index)) => {
+                    self.load_saved(index);
+                    self.focus = Pane::Editor;
+                }
+                None => {}
+            },
+            KeyCode::Char('n') => self.popup = Some(Popup::Prompt {
+                title: format!("New folder in {}", folder_label(&self.selected_folder())),
+                input: String::new(),
+                target: PromptTarget::NewFolder { parent: self.selected_folder() },
+            }),
+            KeyCode::Char('r') => match selected {
+                Some(TreeRow::Folder { path, .. }) => self.popup = Some(Popup::Prompt {
+                    title: format!("Rename folder {}", folder_label(&path)),
+                    input: path.last().cloned().unwrap_or_default(),
+                    target: PromptTarget::RenameFolder { path },
+                }),
+                Some(TreeRow::Request(index)) => self.popup = Some(Popup::Prompt {
+                    title: format!("Rename request {}", self.collection.requests[index].name),
+                    input: self.collection.requests[index].name.clone(),
+                    target: PromptTarget::RenameRequest { index },
+                }),
+                None => {}
+            },
+            KeyCode::Char('d') | KeyCode::Delete => match selected {
+                Some(TreeRow::Folder { path, .. }) => self.confirm_delete_folder(path),
+                Some(TreeRow::Request(index)) => {
+                    let saved = &self.collection.requests[index];
+                    self.popup = Some(Popup::Confirm {
+                        message: format!("Delete request {} from {}?", saved.name, folder_label(&saved.folder)),
+                        on_yes: Confirmed::DeleteRequest { index },
+                        on_no: None,
+                    });
+                }
+                None => {}
+            },
+            KeyCode::Char('m') => {
+                if let Some(TreeRow::Request(index)) = selected {
+                    let mut items = vec![folder_label(&[])];
+                    items.extend(self.collection.folders.iter().map(|folder| folder_label(folder)));
+                    let current = &self.collection.requests[index].folder;
+                    self.popup = Some(Popup::Picker {
+                        title: format!("Move {} to", self.collection.requests[index].name),
+                        selected: self.collection.folders.iter().position(|folder| folder == current).map_or(0, |i| i + 1),
+                        items,
+                        target: PickTarget::MoveRequest { index },
+                    });
+                }
+            }
+            KeyCode::Char('e') => {
+                if let Some(TreeRow::Request(index)) = selected {
+                    let text = self.collection.requests[index].description.clone().unwrap_or_default();
+                    self.popup = Some(Popup::Description { index, text });
+                }
+            }
+            KeyCode::Char('i') => {
+                if let Some(TreeRow::Request(index)) = selected {
+                    self.popup = Some(Popup::Details { index, scroll: 0 });
+                }
+            }
+            KeyCode::Char('C') => self.collapse_all_folders(),
+            _ => {}
+        }
+    }
+
+    fn request_details(&self, saved: &SavedRequest) -> Vec<Line<'static>> {
+        let theme = &self.theme;
+        let mut tags = vec![Span::styled("Tags    ", theme.key)];
+        if saved.tags.is_empty() {
+            tags.push(Span::styled("none", theme.dim));
+        }
+        for tag in &saved.tags {
+            tags.push(theme.chip(tag));
+            tags.push(Span::raw(" "));
+        }
+        let mut lines = vec![
+            Line::from(vec![Span::styled("Folder  ", theme.key), Span::raw(folder_label(&saved.folder))]),
+            Line::from(tags),
+            Line::from(vec![Span::styled("Request ", theme.key), Span::raw(format!("{} {}", saved.request.method, saved.request.url))]),
+        ];
+        lines.extend(saved.request.headers.lines().map(|line| Line::styled(format!("        {}", line), theme.dim)));
+        lines.push(Line::from(""));
+        match &saved.description {
+            Some(description) => lines.extend(description.lines().map(|line| Line::from(line.to_string()))),
+            None => lines.push(Line::styled("No description", theme.dim)),
+        }
+        lines
+    }
+
+    fn handle_responses_key(&mut self, key: KeyEvent) {
+        let newest = self.history.last().map(|entry| entry.id);
+        if let Some((_, tree)) = self.json_tree.as_mut().filter(|(id, _)| Some(*id) == newest) {
+            match key.code {
+                KeyCode::Up => return tree.up(),
+                KeyCode::Down => return tree.down(),
+                KeyCode::Enter | KeyCode::Char(' ') => return tree.toggle(),
+                KeyCode::Left => return tree.collapse(),
+                KeyCode::Right => return tree.expand(),
+                KeyCode::Char('y') => {
+                    // Strings without their quotes, since that's what gets pasted elsewhere.
+                    let value = match tree.selected_value() {
+                        Some(serde_json::Value::String(text)) => text.clone(),
+                        Some(value) => serde_json::to_string_pretty(value).unwrap_or_default(),
+                        None => return,
+                    };
+                    if let Err(e) = clipboard::copy(&value) {
+                        self.error_message = Some(format!("Couldn't copy the value: {}", e).into());
+                    }
+                    return;
+                }
+                KeyCode::Char('Y') => {
+                    if let Err(e) = clipboard::copy(&tree.selected_path()) {
+                        self.error_message = Some(format!("Couldn't copy the path: {}", e).into());
+                    }
+                    return;
+                }
+                _ => {}
+            }
+        }
+        let count = self.latest_response().map_or(0, |response| response.headers.len());
+        let view = &mut self.response_view;
+        match key.code {
+            KeyCode::Char('t') => self.cycle_json_view(),
+            KeyCode::Char('/') => self.ask_search(),
+            KeyCode::Char('n') => self.move_search(true),
+            KeyCode::Char('N') => self.move_search(false),
+            KeyCode::Esc if self.search.is_some() => {
+                self.search = None;
+                self.search_query.clear();
+            }
+            KeyCode::Esc => self.focus = Pane::Editor,
+            KeyCode::Char('H') => view.expanded = !view.expanded,
+            KeyCode::Char('s') => view.sorted = !view.sorted,
+            KeyCode::Up => view.selected = view.selected.saturating_sub(1),
+            KeyCode::Down => view.selected = (view.selected + 1).min(count.saturating_sub(1)),
+            KeyCode::Char('y') => self.copy_response_header(false),
+            KeyCode::Char('Y') => self.copy_response_header(true),
+            KeyCode::Char('v') => self.toggle_readable_html(),
+            KeyCode::Char('i') => self.copy_request_id(),
+            KeyCode::Char('w') => self.toggle_wrap(),
+            KeyCode::Char('g') => self.cycle_history_grouping(),
+            KeyCode::Char('o') => self.history_sort = self.history_sort.next(),
+            KeyCode::Char('x') => self.show_error_details(),
+            KeyCode::Char('R') => self.retry_last_failure(),
+            KeyCode::Char('2') => self.filter_status(StatusFilter::Success),
+            KeyCode::Char('4') => self.filter_status(StatusFilter::ClientError),
+            KeyCode::Char('5') => self.filter_status(StatusFilter::ServerError),
+            KeyCode::Char('0') => self.filter_status(StatusFilter::Transport),
+            KeyCode::Char('a') => {
+                self.filter_status(StatusFilter::All);
+                self.endpoint_filter = None;
+            }
+            KeyCode::Char('r') => self.expand_repeats = !self.expand_repeats,
+            KeyCode::Char('j') => self.move_exchange_selection(true),
+            KeyCode::Char('k') => self.move_exchange_selection(false),
+            KeyCode::Char('*') => match self.selected_exchange() {
+                Some(id) => self.toggle_pin(id),
+                None => self.error_message = Some("Nothing sent yet".to_string().into()),
+            },
+            KeyCode::Char('[') => self.group_selected = self.group_selected.saturating_sub(1),
+            KeyCode::Char(']') => self.group_selected = (self.group_selected + 1).min(self.history_groups().len().saturating_sub(1)),
+            KeyCode::Enter | KeyCode::Char(' ') if self.history_grouping != HistoryGrouping::None => {
+                let Some((label, _)) = self.history_groups().into_iter().nth(self.group_selected) else { return };
+                if !self.collapsed_groups.remove(&label) {
+                    self.collapsed_groups.insert(label);
+                }
+            }
+            KeyCode::Left if !view.wrap => view.column = view.column.saturating_sub(WRAP_SCROLL_STEP),
+            KeyCode::Right if !view.wrap => view.column += WRAP_SCROLL_STEP,
+            KeyCode::Home => view.column = 0,
+            _ => {}
+        }
+    }
+
+    /// Flips body wrapping and remembers the choice in the config.
+    fn toggle_wrap(&mut self) {
+        let view = &mut self.response_view;
+        view.wrap = !view.wrap;
+        view.column = 0;
+        let wrap = view.wrap;
+        let saved = config::load().and_then(|mut config| {
+            config.wrap_responses = wrap;
+            config::save(&config)
+        });
+        if let Err(e) = saved {
+            self.error_message = Some(format!("Couldn't remember the wrap setting: {}", e).into());
+        }
+    }
+
+    /// Moves on to the next way of grouping history and remembers it in the config.
+    fn cycle_history_grouping(&mut self) {
+        self.history_grouping = self.history_grouping.next();
+        self.collapsed_groups.clear();
+        self.group_selected = 0;
+        let grouping = self.history_grouping;
+        let saved = config::load().and_then(|mut config| {
+            config.history_grouping = grouping;
+            config::save(&config)
+        });
+        if let Err(e) = saved {
+            self.error_message = Some(format!("Couldn't remember the grouping: {}", e).into());
+        }
+    }
+
+    /// The exchanges the responses pane shows, newest first, in sections ordered by their
+    /// newest exchange. Ungrouped, there's one section with an empty label.
+    fn history_groups(&self) -> Vec<(String, Vec<&HistoryEntry>)> {
+        let mut groups: Vec<(String, Vec<&HistoryEntry>)> = vec![];
+        for entry in self.shown_history().filter(|entry| !entry.pinned) {
+            let label = group_label(self.history_grouping, entry);
+            match groups.iter_mut().find(|(existing, _)| *existing == label) {
+                Some((_, entries)) => entries.push(entry),
+                None => groups.push((label, vec![entry])),
+            }
+        }
+        for (_, entries) in &mut groups {
+            self.history_sort.apply(entries);
+        }
+        groups
+    }
+
+    /// The exchanges as listed in the responses pane from the top: pinned ones, then those in
+    /// open sections.
+    fn listed_exchanges(&self) -> Vec<&HistoryEntry> {
+        let newest = self.history.last().map(|entry| entry.id);
+        let mut listed: Vec<&HistoryEntry> = self.shown_history().filter(|entry| entry.pinned).collect();
+        self.history_sort.apply(&mut listed);
+        let grouped = self.history_grouping != HistoryGrouping::None;
+        for (label, entries) in self.history_groups() {
+            let searched = self.search.is_some() && entries.iter().any(|entry| Some(entry.id) == newest);
+            if grouped && self.collapsed_groups.contains(&label) && !searched {
+                continue;
+            }
+            listed.extend(entries);
+        }
+        listed
+    }
+
+    /// The exchange * pins in the responses pane.
+    fn selected_exchange(&self) -> Option<u64> {
+        let listed = self.exchange_selected.filter(|id| self.listed_exchanges().iter().any(|entry| entry.id == *id));
+        listed.or(self.history.last().map(|entry| entry.id))
+    }
+
+    /// Selects the exchange listed below the selected one, or above it.
+    fn move_exchange_selection(&mut self, down: bool) {
+        let listed: Vec<u64> = self.listed_exchanges().iter().map(|entry| entry.id).collect();
+        let Some(last) = listed.len().checked_sub(1) else { return };
+        let at = self.selected_exchange().and_then(|id| listed.iter().position(|listed| *listed == id)).unwrap_or(0);
+        let at = if down {(at + 1).min(last)} else {at.saturating_sub(1)};
+        self.exchange_selected = Some(listed[at]);
+    }
+
+    /// The exchanges the environment and status filters let through, newest first.
+    fn shown_history(&self) -> impl Iterator<Item = &HistoryEntry> {
+        self.environment_history()
+            .filter(|entry| self.status_filter.matches(entry))
+            .filter(|entry| self.endpoint_filter.as_ref().is_none_or(|(method, url)| entry.request.method == *method && entry.request.url == *url))
+    }
+
+    fn environment_history(&self) -> impl Iterator<Item = &HistoryEntry> {
+        self.history.iter().rev().filter(|entry| self.history_environment.as_ref().is_none_or(|filter| *filter == entry.environment))
+    }
+
+    fn filter_status(&mut self, filter: StatusFilter) {
+        self.status_filter = filter;
+        self.group_selected = 0;
+    }
+
+    /// Pins or unpins an exchange and saves history, where the pin is kept.
+    fn toggle_pin(&mut self, id: u64) {
+        let Some(entry) = self.history.iter_mut().find(|entry| entry.id == id) else { return };
+        entry.pinned = !entry.pinned;
+        if let Err(e) = history::save(&config::history_path(), &self.history) {
+            self.error_message = Some(format!("Couldn't save history: {}", e).into());
+        }
+    }
+
+    fn show_pinned(&mut self) {
+        let pinned: Vec<&HistoryEntry> = self.history.iter().rev().filter(|entry| entry.pinned).collect();
+        if pinned.is_empty() {
+            self.error_message = Some("Nothing pinned; * pins the selected exchange in the responses pane".to_string().into());
+            return;
+        }
+        let items = pinned.iter().map(|entry| exchange_item(entry)).collect();
+        let ids = pinned.iter().map(|entry| entry.id).collect();
+        self.popup = Some(Popup::Picker { title: "Pinned exchanges (* unpins)".to_string(), items, selected: 0, target: PickTarget::HistoryEntry { ids } });
+    }
+
+    /// Clears history but for pinned exchanges and those still waiting on a response, then
+    /// asks separately about the pinned ones.
+    fn clear_history(&mut self, pinned_too: bool) {
+        self.history.retain(|entry| entry.is_pending() || (entry.pinned && !pinned_too));
+        self.collapsed_groups.clear();
+        self.group_selected = 0;
+        if let Err(e) = history::save(&config::history_path(), &self.history) {
+            self.error_message = Some(format!("Couldn't save history: {}", e).into());
+            return;
+        }
+        let pinned = self.history.iter().filter(|entry| entry.pinned).count();
+        if pinned > 0 {
+            self.popup = Some(Popup::Confirm {
+                message: format!("Unpin and clear the {} pinned exchanges too?", pinned),
+                on_yes: Confirmed::ClearHistory { pinned_too: true },
+                on_no: None,
+            });
+        }
+    }
+
+    fn toggle_readable_html(&mut self) {
+        self.readable_html = !self.readable_html;
+        self.rerender_bodies();
+    }
+
+    /// Cycles JSON bodies through formatted text, a tree (for the newest response) and the
+    /// body as received, which for a body decoded from another charset is its bytes.
+    fn cycle_json_view(&mut self) {
+        self.json_view = match self.json_view {
+            JsonView::Formatted => JsonView::Tree,
+            JsonView::Tree => JsonView::Raw,
+            JsonView::Raw => JsonView::Formatted,
+        };
+        self.rerender_bodies();
+        self.sync_json_tree();
+    }
+
+    /// Builds the tree of the newest response when the tree view is on and it's a new response.
+    fn sync_json_tree(&mut self) {
+        if self.json_view != JsonView::Tree {
+            self.json_tree = None;
+            return;
+        }
+        let Some(entry) = self.history.last() else { return };
+        if self.json_tree.as_ref().is_some_and(|(id, _)| *id == entry.id) {
+            return;
+        }
+        self.json_tree = entry.response.as_ref()
+            .filter(|response| editor::is_json(header_value(&response.headers, "Content-Type"), &response.body))
+            .and_then(|response| json_tree::Tree::parse(&response.body))
+            .map(|tree| (entry.id, tree));
+    }
+
+    /// Redraws everything in `preset` without saving it, unless color is off.
+    fn preview_theme(&mut self, preset: Preset) {
+        self.theme = Theme::from_config(self.theme.no_color, preset);
+        self.rerender_bodies();
+    }
+
+    fn rerender_bodies(&mut self) {
+        for entry in &self.history {
+            if let Some(response) = &entry.response {
+                let rendered = render_body(response, &entry.request.method, &self.theme, self.readable_html, self.json_view);
+                self.rendered_bodies.insert(entry.id, rendered);
+            }
+        }
+        // Matches are positions in the rendered lines, which just changed.
+        self.search = None;
+        self.sync_search();
+    }
+
+    fn ask_search(&mut self) {
+        if self.json_tree.is_some() {
+            self.error_message = Some("Search works on the text views of a body; t leaves the tree".to_string().into());
+            return;
+        }
+        self.popup = Some(Popup::Prompt {
+            title: "Search responses".to_string(),
+            input: self.search_query.clone(),
+            target: PromptTarget::Search { regex: self.search_regex, error: None },
+        });
+    }
+
+    /// Searches the newest response's body for `search_query`, unless that's already done or
+    /// under way. The search runs on its own thread and is collected on ticks.
+    fn sync_search(&mut self) {
+        let Some(entry) = self.history.last() else { return };
+        if self.search_query.is_empty() || self.search.as_ref().is_some_and(|(id, _)| *id == entry.id) {
+            return;
+        }
+        let pattern = if self.search_regex {
+            match Pattern::regex(&self.search_query) {
+                Ok(pattern) => pattern,
+                Err(_) => return,
+            }
+        } else {
+            Pattern::text(&self.search_query)
+        };
+        let lines: Vec<String> = match self.rendered_bodies.get(&entry.id) {
+            Some(lines) => lines.iter().map(|line| line.spans.iter().map(|span| span.content.as_ref()).collect()).collect(),
+            None => entry.response.as_ref().map_or(vec![], |response| response.body.lines().map(String::from).collect()),
+        };
+        self.search = Some((entry.id, Search::start(pattern, lines)));
+    }
+
+    /// Steps to the next or previous match, scrolling an unwrapped body sideways to show it.
+    fn move_search(&mut self, forward: bool) {
+        let Some((id, search)) = &mut self.search else {
+            self.error_message = Some("No search yet; / starts one".to_string().into());
+            return;
+        };
+        if forward {search.next()} else {search.previous()}
+        let Some(found) = search.matches.get(search.current) else { return };
+        let view = &mut self.response_view;
+        if view.wrap {
+            return;
+        }
+        let line = self.rendered_bodies.get(id).and_then(|lines| lines.get(found.line));
+        let text: String = line.map_or(String::new(), |line| line.spans.iter().map(|span| span.content.as_ref()).collect());
+        let column = text.get(..found.start).map_or(0, |before| before.chars().count());
+        if column < view.column || column >= view.column + SEARCH_MARGIN {
+            view.column = column.saturating_sub(WRAP_SCROLL_STEP);
+        }
+    }
+
+    /// Pins the newest response as the baseline of the saved request it was sent from, asking
+    /// first when that would replace an existing baseline.
+    fn save_baseline(&mut self, confirmed: bool) {
+        let Some(entry) = self.history.last() else {
+            self.error_message = Some("Nothing sent yet".to_string().into());
+            return;
+        };
+        let Some(response) = &entry.response else {
+            self.error_message = Some("The newest exchange has no response to pin".to_string().into());
+            return;
+        };
+        let Some((folder, name)) = &entry.saved else {
+            self.error_message = Some("Baselines are kept with saved requests; save the request and send it again".to_string().into());
+            return;
+        };
+        let Some(index) = self.collection.position(folder, name) else {
+            self.error_message = Some(format!("{} is no longer in the collection", name).into());
+            return;
+        };
+        let saved = &mut self.collection.requests[index];
+        if saved.baseline.is_some() && !confirmed {
+            self.popup = Some(Popup::Confirm {
+                message: format!("Replace the baseline of {} with the newest response?", name),
+                on_yes: Confirmed::ReplaceBaseline,
+                on_no: None,
+            });
+            return;
+        }
+        saved.baseline = Some(response.clone());
+        self.baseline_results.insert(entry.id, vec![]);
+        self.save_collection();
+    }
+
+    /// Copies the id the newest request was sent with, or else the first one its response carried.
+    fn copy_request_id(&mut self) {
+        let Some(entry) = self.history.last() else { return };
+        let Some(id) = entry.request_id.as_ref().or(entry.correlation.first().map(|(_, value)| value)) else {
+            self.error_message = Some("The newest exchange has no request id; turn it on in the request settings".to_string().into());
+            return;
+        };
+        if let Err(e) = clipboard::copy(id) {
+            self.error_message = Some(format!("Couldn't copy the request id: {}", e).into());
+        }
+    }
+
+    /// Copies the highlighted header of the newest response, just its value or as `Name: value`.
+    /// A header sent more than once is highlighted, and copied, one occurrence at a time.
+    fn copy_response_header(&mut self, with_name: bool) {
+        let Some(response) = self.latest_response() else {
+            self.error_message = Some("No response yet".to_string().into());
+            return;
+        };
+        let Some((name, value)) = displayed_headers(response, self.response_view.sorted).get(self.response_view.selected).copied() else {
+            self.error_message = Some("The response has no headers".to_string().into());
+            return;
+        };
+        let (text, copied) = match with_name {
+            true => (format!("{}: {}", name, value), format!("Copied {} header", name)),
+            false => (value.clone(), format!("Copied {} value", name)),
+        };
+        match clipboard::copy(&text) {
+            Ok(()) => self.flash = Some((copied, Instant::now() + FLASH_DURATION)),
+            Err(e) => self.error_message = Some(format!("Couldn't copy the header: {}", e).into()),
+        }
+    }
+
+    fn collapse_all_folders(&mut self) {
+        self.collapsed_folders = self.collection.folders.iter().cloned().collect();
+        self.clamp_collection_selection();
+    }
+
+    fn import_postman(&mut self, path: &str) {
+        let imported = match fs::read_to_string(path).map_err(|e| e.to_string()).and_then(|json| postman::import(&json)) {
+            Ok(imported) => imported,
+            Err(e) => {
+                self.error_message = Some(format!("Couldn't import {}: {}", path, e).into());
+                return;
+            }
+        };
+        let count = self.add_imported(imported.name, imported.collection);
+        if imported.skipped > 0 {
+            self.error_message = Some(format!("Imported {} requests; skipped {} items that were neither folders nor requests", count, imported.skipped).into());
+        }
+    }
+
+    fn import_insomnia(&mut self, path: &str) {
+        let imported = match fs::read_to_string(path).map_err(|e| e.to_string()).and_then(|text| insomnia::import(&text)) {
+            Ok(imported) => imported,
+            Err(e) => {
+                self.error_message = Some(format!("Couldn't import {}: {}", path, e).into());
+                return;
+            }
+        };
+        let count = self.add_imported(imported.name.clone(), imported.collection);
+        let environments = imported.environments.len();
+        for mut env in imported.environments {
+            if self.environments.iter().any(|existing| existing.name == env.name) {
+                env.name = format!("{} ({})", env.name, imported.name);
+            }
+            self.environments.push(env);
+        }
+        if environments > 0 {
+            if let Err(e) = environment::save(&config::environments_path(), &self.environments, self.vault.as_ref(), self.vault_key.as_ref()) {
+                self.error_message = Some(format!("Couldn't save the imported environments: {}", e).into());
+                return;
+            }
+        }
+        if !imported.skipped.is_empty() {
+            let skipped: Vec<String> = imported.skipped.iter().map(|(what, n)| format!("{} {}", n, what)).collect();
+            self.error_message = Some(format!("Imported {} requests and {} environments; skipped {}", count, environments, skipped.join(", ")).into());
+        }
+    }
+
+    /// Adds an imported collection under a folder of its own, returning how many requests it had.
+    fn add_imported(&mut self, name: String, imported: Collection) -> usize {
+        let prefix = vec![name];
+        self.collection.add_folder(&prefix);
+        for folder in &imported.folders {
+            self.collection.add_folder(&[prefix.as_slice(), folder].concat());
+        }
+        let count = imported.requests.len();
+        for saved in imported.requests {
+            let folder = [prefix.as_slice(), &saved.folder].concat();
+            self.collection.requests.push(SavedRequest { folder, ..saved });
+        }
+        self.save_collection();
+        count
+    }
+
+    fn export_postman(&mut self, path: &str) {
+        if let Err(e) = fs::write(path, postman::export(&self.collection, "tui_postman")) {
+            self.error_message = Some(format!("Couldn't export to {}: {}", path, e).into());
+        }
+    }
+
+    /// The URL's query parameters, with any disabled in the last bulk edit back in their places.
+    fn current_query_rows(&self) -> Vec<kv::Row> {
+        let (_, query, _) = kv::split_query(&self.url_input);
+        let rows = kv::query_rows(query);
+        let enabled: Vec<kv::Row> = self.query_rows.iter().filter(|row| row.enabled).cloned().collect();
+        if enabled == rows {self.query_rows.clone()} else {rows}
+    }
+
+    /// Most recent first, as typed rather than resolved, so placeholders survive.
+    fn recent_urls(&self) -> Vec<String> {
+        let mut urls: Vec<String> = vec![];
+        for entry in self.history.iter().rev() {
+            if !urls.contains(&entry.request.url) {
+                urls.push(entry.request.url.clone());
+                if urls.len() == RECENT_URLS {
+                    break;
+                }
+            }
+        }
+        urls
+    }
+
+    /// Lists the exchanges, newest first, whose URL, method, status, environment or sent or
+    /// received request ids contain `query`, ignoring case.
+    fn search_history(&mut self, query: &str) {
+        let query = query.to_lowercase();
+        let found: Vec<&HistoryEntry> = self.history
+            .iter()
+            .rev()
+            .filter(|entry| {
+                let status = entry.response.as_ref().and_then(|response| response.status).map(|code| code.to_string());
+                let matches = [Some(&entry.request.url), Some(&entry.request.method), entry.request_id.as_ref(), status.as_ref(), entry.environment.as_ref()]
+                    .into_iter()
+                    .flatten()
+                    .chain(entry.correlation.iter().map(|(_, value)| value))
+                    .any(|field| field.to_lowercase().contains(&query));
+                matches
+            })
+            .collect();
+        if found.is_empty() {
+            self.error_message = Some(format!("No exchange in the history matches {}", query).into());
+            return;
+        }
+        let items = found.iter().map(|entry| exchange_item(entry)).collect();
+        let ids = found.iter().map(|entry| entry.id).collect();
+        self.popup = Some(Popup::Picker {
+            title: "Matching exchanges".to_string(),
+            items,
+            selected: 0,
+            target: PickTarget::HistoryEntry { ids },
+        });
+    }
+
+    fn show_recent_urls(&mut self) {
+        let urls = self.recent_urls();
+        if urls.is_empty() {
+            self.error_message = Some("No URLs sent yet".to_string().into());
+            return;
+        }
+        self.popup = Some(Popup::Picker {
+            title: "Recent URLs".to_string(),
+            items: urls.clone(),
+            selected: 0,
+            target: PickTarget::RecentUrl { urls },
+        });
+    }
+
+    /// Writes bulk-edited parameters back into the URL, or keeps the popup open with what's wrong.
+    fn apply_bulk_params(&mut self, text: &str) -> Result<(), Vec<String>> {
+        let rows = kv::parse_bulk(text)?;
+        let (base, _, fragment) = kv::split_query(&self.url_input);
+        let query = kv::query_string(&rows);
+        let url = if query.is_empty() {format!("{}{}", base, fragment)} else {format!("{}?{}{}", base, query, fragment)};
+        self.query_rows = rows;
+        self.url_input = url;
+        if *self.get_input_mode() == InputMode::EditingUrl {
+            self.character_index = self.clamp_cursor(self.character_index);
+        }
+        Ok(())
+    }
+
+    fn ask_plaintext_destination(&mut self, format: Format) {
+        let folder = self.selected_folder();
+        let what = if folder.is_empty() {"collection".to_string()} else {folder_label(&folder)};
+        self.popup = Some(Popup::Prompt {
+            title: format!("Export {} to file", what),
+            input: format!("{}.{}", folder.last().map_or("requests", String::as_str), format.extension()),
+            target: PromptTarget::ExportPlaintext { format, folder },
+        });
+    }
+
+    fn export_plaintext(&mut self, path: &str, format: Format, folder: &[String]) {
+        let (text, untranslated) = plaintext::export(&self.collection, folder, format);
+        if let Err(e) = fs::write(path, text) {
+            self.error_message = Some(format!("Couldn't export to {}: {}", path, e).into());
+        } else if !untranslated.is_empty() {
+            let placeholders: Vec<String> = untranslated.iter().map(|expression| format!("{{{{{}}}}}", expression)).collect();
+            self.error_message = Some(format!("Exported, but {} has no equivalent of {}; left as written", format.extension(), placeholders.join(", ")).into());
+        }
+    }
+
+    /// Adds the requests of a REST Client or hurl file under a folder named after the file.
+    fn import_plaintext(&mut self, path: &str) {
+        let imported = match fs::read_to_string(path).map_err(|e| e.to_string()).and_then(|text| plaintext::import(&text, Format::from_path(path))) {
+            Ok(imported) => imported,
+            Err(e) => {
+                self.error_message = Some(format!("Couldn't import {}: {}", path, e).into());
+                return;
+            }
+        };
+        let name = std::path::Path::new(path).file_stem().map_or(path.to_string(), |stem| stem.to_string_lossy().into_owned());
+        let prefix = vec![name];
+        self.collection.add_folder(&prefix);
+        for folder in &imported.folders {
+            self.collection.add_folder(&[prefix.as_slice(), folder].concat());
+        }
+        for saved in imported.requests {
+            let folder = [prefix.as_slice(), &saved.folder].concat();
+            self.collection.requests.push(SavedRequest { folder, ..saved });
+        }
+        self.save_collection();
+    }
+
+    fn pick_environment(&mut self) {
+        if self.environments.is_empty() {
+            self.error_message = Some(format!("No environments defined in {}", config::environments_path().display()).into());
+            return;
+        }
+        let mut items = vec!["(no environment)".to_string()];
+        items.extend(self.environments.iter().map(|env| env.name.clone()));
+        self.popup = Some(Popup::Picker {
+            title: "Environment".to_string(),
+            items,
+            selected: self.active_environment.map_or(0, |i| i + 1),
+            target: PickTarget::Environment,
+        });
+    }
+
+    /// Activates an environment, re-reading its dotenv file so edits are picked up.
+    fn switch_environment(&mut self, index: Option<usize>) {
+        self.active_environment = index;
+        self.error_message = self.load_variables().err().map(AppError::from);
+    }
+
+    /// Fills `variables` from the active environment, keeping just its own variables when its
+    /// dotenv file can't be read.
+    fn load_variables(&mut self) -> Result<(), String> {
+        let Some(env) = self.active_environment.and_then(|i| self.environments.get(i)) else {
+            self.variables.clear();
+            return Ok(());
+        };
+        match env.resolve_variables() {
+            Ok(variables) => {
+                self.variables = variables;
+                Ok(())
+            }
+            Err(e) => {
+                self.variables = env.variables.clone().into_iter().collect();
+                Err(format!("Couldn't read dotenv file {}", e))
+            }
+        }
+    }
+
+    /// Asks whether to replay with the environment the last request went out with, when that
+    /// differs from the current one, then about generated values.
+    fn replay_last(&mut self) {
+        let Some(last) = self.history.last() else {
+            self.error_message = Some("Nothing has been sent yet".to_string().into());
+            return;
+        };
+        let current = self.active_environment.map(|i| self.environments[i].name.clone());
+        let changed = last.variables.iter().any(|(name, value)| value != secrets::REDACTED && self.variables.get(name) != Some(value));
+        if !changed && last.environment == current {
+            self.ask_reuse_generated(false);
+            return;
+        }
+        let label = |environment: &Option<String>| environment.clone().unwrap_or_else(|| "no environment".to_string());
+        let redacted = last.variables.iter().filter(|(_, value)| value == secrets::REDACTED).count();
+        let credentials = match redacted {
+            0 => String::new(),
+            1 => "; 1 credential from the current environment".to_string(),
+            count => format!("; {} credentials from the current environment", count),
+        };
+        self.popup = Some(Popup::Picker {
+            title: "Replay with".to_string(),
+            items: vec![
+                format!("Original environment values ({}{})", label(&last.environment), credentials),
+                format!("Current environment ({})", label(&current)),
+            ],
+            selected: 0,
+            target: PickTarget::ReplayEnvironment,
+        });
+    }
+
+    fn ask_reuse_generated(&mut self, original_environment: bool) {
+        let Some(last) = self.history.last() else { return };
+        if last.generated.is_empty() {
+            self.confirm(Confirmed::Replay { reuse_generated: false, original_environment });
+            return;
+        }
+        let values: Vec<String> = last.generated.iter().map(|g| format!("{} = {}", g.expression, g.value)).collect();
+        self.popup = Some(Popup::Confirm {
+            message: format!("Reuse the generated values from the last send ({})? Otherwise they are regenerated.", values.join(", ")),
+            on_yes: Confirmed::Replay { reuse_generated: true, original_environment },
+            on_no: Some(Confirmed::Replay { reuse_generated: false, original_environment }),
+        });
+    }
+
+    /// Unlocks secret variables with a stored passphrase when there is one, or asks for it.
+    fn start_vault(&mut self) -> Result<(), String> {
+        if self.environments.iter().all(|env| env.secrets.is_empty() && env.encrypted.is_empty()) {
+            return Ok(());
+        }
+        if let Some(passphrase) = vault::stored_passphrase() {
+            return self.unlock(&passphrase);
+        }
+        match self.popup {
+            None => self.ask_passphrase(false),
+            Some(_) => return Err("Secret variables are locked; Unlock secrets asks for the passphrase".to_string()),
+        }
+        Ok(())
+    }
+
+    /// Unlocks the secrets right away with the stored passphrase, as there's no one to ask.
+    fn unlock_headless(&mut self) -> Result<(), String> {
+        if self.environments.iter().all(|env| env.secrets.is_empty() && env.encrypted.is_empty()) {
+            return Ok(());
+        }
+        let Some(passphrase) = vault::stored_passphrase() else {
+            return Err(format!("Secret variables stay locked; set {} to unlock them", vault::PASSPHRASE_VAR));
+        };
+        let vault = self.vault.clone().unwrap_or_else(Vault::generate);
+        let key = vault.key(&passphrase).map_err(|e| format!("Couldn't unlock secrets: {}", e))?;
+        self.finish_unlock(vault, key).map_err(|e| format!("Couldn't unlock secrets: {}", e))
+    }
+
+    fn ask_passphrase(&mut self, change: bool) {
+        let title = match (change, &self.vault) {
+            (true, _) => "New passphrase for secret variables",
+            (false, Some(_)) => "Passphrase for secret variables (Esc: leave them locked)",
+            (false, None) => "Choose a passphrase to encrypt secret variables with",
+        };
+        self.popup = Some(Popup::Prompt { title: title.to_string(), input: String::new(), target: PromptTarget::Passphrase { change } });
+    }
+
+    /// Has the worker derive the key, then `finish_unlock` decrypts the secrets with it.
+    fn unlock(&mut self, passphrase: &str) -> Result<(), String> {
+        if self.vault_key.is_some() {
+            return Err("Secrets are already unlocked".to_string());
+        }
+        let vault = self.vault.clone().unwrap_or_else(Vault::generate);
+        self.worker.submit(Job::DeriveKey { vault, passphrase: passphrase.to_string(), change: false });
+        self.flash = Some(("Unlocking secrets…".to_string(), Instant::now() + FLASH_DURATION));
+        Ok(())
+    }
+
+    fn key_derived(&mut self, vault: Vault, change: bool, key: Result<vault::Key, String>) {
+        let finished = key.and_then(|key| match change {
+            true => self.finish_change(vault, key),
+            false => self.finish_unlock(vault, key),
+        });
+        self.error_message = Some(match (change, finished) {
+            (true, Ok(())) => "Secrets encrypted with the new passphrase".to_string().into(),
+            (true, Err(e)) => format!("Couldn't change the passphrase: {}", e).into(),
+            (false, Ok(())) => return,
+            (false, Err(e)) => format!("Couldn't unlock secrets: {}; they stay locked", e).into(),
+        });
+    }
+
+    /// Decrypts every sealed secret, or none if the passphrase is wrong. Secrets still written
+    /// plainly in the file, new or edited there, are sealed and the file rewritten.
+    fn finish_unlock(&mut self, vault: Vault, key: vault::Key) -> Result<(), String> {
+        if self.vault_key.is_some() {
+            return Err("Secrets are already unlocked".to_string());
+        }
+        let mut opened = vec![];
+        for (i, env) in self.environments.iter().enumerate() {
+            for (name, sealed) in &env.encrypted {
+                opened.push((i, name.clone(), vault::open(&key, sealed)?));
+            }
+        }
+        let mut plain = vec![];
+        for (i, env) in self.environments.iter().enumerate() {
+            plain.extend(env.secrets.iter().filter(|name| env.variables.contains_key(*name)).map(|name| (i, name.clone())));
+        }
+        // A value written plainly in the file is newer than the sealed one.
+        for (i, name, value) in opened {
+            self.environments[i].variables.entry(name).or_insert(value);
+        }
+        for (i, name) in &plain {
+            let env = &mut self.environments[*i];
+            env.encrypted.insert(name.clone(), vault::seal(&key, &env.variables[name]));
+        }
+        if !plain.is_empty() {
+            environment::save(&config::environments_path(), &self.environments, Some(&vault), Some(&key))?;
+        }
+        self.vault = Some(vault);
+        self.vault_key = Some(key);
+        self.load_variables()
+    }
+
+    /// Derives a key from a new passphrase and salt, then `finish_change` seals every secret
+    /// with it.
+    fn change_passphrase(&mut self, passphrase: &str) -> Result<(), String> {
+        if self.vault_key.is_none() && self.environments.iter().any(|env| !env.encrypted.is_empty()) {
+            return Err("Unlock the secrets with the current passphrase first".to_string());
+        }
+        if passphrase.is_empty() {
+            return Err("The passphrase can't be empty".to_string());
+        }
+        self.worker.submit(Job::DeriveKey { vault: Vault::generate(), passphrase: passphrase.to_string(), change: true });
+        self.flash = Some(("Encrypting secrets…".to_string(), Instant::now() + FLASH_DURATION));
+        Ok(())
+    }
+
+    /// Seals every secret under the new key, written in one go.
+    fn finish_change(&mut self, vault: Vault, key: vault::Key) -> Result<(), String> {
+        if self.vault_key.is_none() && self.environments.iter().any(|env| !env.encrypted.is_empty()) {
+            return Err("Unlock the secrets with the current passphrase first".to_string());
+        }
+        let mut environments = self.environments.clone();
+        for env in &mut environments {
+            let names: BTreeSet<String> = env.secrets.iter().chain(env.encrypted.keys()).cloned().collect();
+            env.encrypted = names
+                .into_iter()
+                .filter_map(|name| {
+                    let sealed = vault::seal(&key, env.variables.get(&name)?);
+                    Some((name, sealed))
+                })
+                .collect();
+        }
+        environment::save(&config::environments_path(), &environments, Some(&vault), Some(&key))?;
+        self.environments = environments;
+        self.vault = Some(vault);
+        self.vault_key = Some(key);
+        Ok(())
+    }
+
+    /// Names the active environment flags as secret.
+    fn secret_variables(&self) -> BTreeSet<String> {
+        self.active_environment.map(|i| self.environments[i].secrets.clone()).unwrap_or_default()
+    }
+
+    /// Masks credentials in `view`, unless they've been revealed there.
+    fn masker(&self, view: SecretView) -> Masker {
+        if self.revealed.contains(&view) {
+            return Masker::off();
+        }
+        let flagged = self.secret_variables();
+        Masker::new(self.variables.iter().filter(|(name, _)| secrets::is_secret_variable(name, &flagged)).map(|(_, value)| value.clone()).collect())
+    }
+
+    fn preview_request(&mut self) {
+        let mut resolver = Resolver::new(&self.variables, None);
+        let lines = match self.current_request().resolve(&mut resolver, &self.host_rules) {
+            Ok(resolved) => {
+                let masker = self.masker(SecretView::Preview);
+                let mut lines: Vec<Line> = vec![Line::from(format!("-> {}", resolved.address))];
+                lines.extend(resolved.raw().lines().map(|line| Line::from(masker.line(line))));
+                if let Some(mismatch) = resolved.content_length_mismatch() {
+                    lines.push(Line::from(""));
+                    lines.push(Line::styled(format!("{}; it's sent as written", mismatch), self.theme.error));
+                }
+                if !resolved.host_defaults.is_empty() {
+                    lines.push(Line::from(""));
+                }
+                for contribution in &resolved.host_defaults {
+                    let line = format!("From host rule {}: {}", contribution.pattern, contribution.headers.join(", "));
+                    lines.push(Line::styled(line, self.theme.dim));
+                }
+                if !self.request_hooks.is_empty() {
+                    let headers: Vec<&str> = self.request_hooks.iter().map(Hook::header).collect();
+                    lines.push(Line::from(""));
+                    lines.push(Line::styled(format!("Set by pre-request hooks: {}", headers.join(", ")), self.theme.dim));
+                }
+                if !resolver.generated.is_empty() {
+                    lines.push(Line::from(""));
+                    lines.push(Line::styled("Generated values are reused by the next send", self.theme.dim));
+                }
+                self.previewed_values = Some(resolver.generated);
+                lines
+            }
+            Err(e) => vec![Line::styled(self.theme.error_text(&e.to_string()), self.theme.error)],
+        };
+        self.popup = Some(Popup::Text { title: "Raw request".to_string(), lines, scroll: 0 });
+    }
+
+    /// The editor's request as code, shown and copied to the clipboard.
+    fn export_snippet(&mut self, language: Language) {
+        let code = match self.snippet(language) {
+            Ok(code) => code,
+            Err(e) => {
+                self.error_message = Some(format!("Can't export the request: {}", e).into());
+                return;
+            }
+        };
+        let title = match clipboard::copy(&code) {
+            Ok(()) => format!("{} (copied)", language.name()),
+            Err(e) => format!("{} (couldn't copy: {})", language.name(), e),
+        };
+        let lines = code.lines().map(|line| Line::from(line.to_string())).collect();
+        self.popup = Some(Popup::Text { title, lines, scroll: 0 });
+    }
+
+    /// The editor's request as code. Unless revealed there, credentials are left out for
+    /// whoever runs it to fill in.
+    fn snippet(&self, language: Language) -> Result<String, RequestError> {
+        let mut resolver = Resolver::new(&self.variables, None);
+        let mut resolved = self.current_request().resolve(&mut resolver, &self.host_rules)?;
+        let masker = self.masker(SecretView::Snippets);
+        for (name, value) in &mut resolved.headers {
+            *value = masker.redact_header_value(name, value);
+        }
+        resolved.endpoint = masker.redact(&resolved.endpoint);
+        resolved.body = masker.redact(&resolved.body);
+        Ok(snippet::render(language, &resolved, &resolved.scheme))
+    }
+
+    /// Checks that the current request's host and port can be reached, off the UI thread; the
+    /// outcome replaces the popup when it's in.
+    fn check_connectivity(&mut self) {
+        let spec = self.current_request();
+        let mut resolver = Resolver::new(&self.variables, None);
+        let resolved = match spec.resolve(&mut resolver, &self.host_rules) {
+            Ok(resolved) => resolved,
+            Err(e) => {
+                self.error_message = Some(format!("Can't check connectivity: {}", e).into());
+                return;
+            }
+        };
+        let https = resolved.scheme == "https";
+        let target = resolved.peer().to_string();
+        let transport = Transport {
+            preference: self.address_preference,
+            expect: None,
+            connect_timeout: self.connect_timeout.map(Duration::from_secs),
+            connect_budget: None,
+            read_timeout: None,
+            deadline: None,
+            max_body: None,
+        };
+        self.worker.submit(Job::CheckReachable { target: target.clone(), https, transport });
+        let lines = vec![Line::styled(format!("Checking {}…", target), self.theme.dim)];
+        self.popup = Some(Popup::Text { title: "Connectivity".to_string(), lines, scroll: 0 });
+    }
+
+    fn show_reachable(&mut self, target: &str, steps: &[Step]) {
+        let theme = &self.theme;
+        let mut lines = vec![
+            Line::from(format!("{} (connected directly; there are no proxy settings)", target)),
+            Line::from(""),
+        ];
+        for step in steps {
+            let (mark, style, text) = match &step.outcome {
+                Ok(text) => ("ok  ", theme.value, text),
+                Err(text) => ("fail", theme.error, text),
+            };
+            lines.push(Line::from(vec![
+                Span::styled(format!("{:<4} ", step.name), theme.key),
+                Span::styled(format!("{} ", mark), style),
+                Span::styled(format!("{:>6} ms  ", step.elapsed.as_millis()), theme.dim),
+                Span::raw(text.clone()),
+            ]));
+        }
+        let verdict = match steps.iter().find(|step| step.outcome.is_err() && step.name != "TLS") {
+            Some(step) => format!("Stopped at {}: this is a network problem, not an HTTP one", step.name),
+            None => "Reachable: if requests fail, look at the request or the server".to_string(),
+        };
+        lines.push(Line::from(""));
+        lines.push(Line::from(verdict));
+        self.popup = Some(Popup::Text { title: "Connectivity".to_string(), lines, scroll: 0 });
+    }
+
+    /// The URL, headers and body with each placeholder's value marked, the values used and any
+    /// placeholder nothing was found for. Dynamic values are drawn again when it's sent.
+    fn preview_templating(&mut self) {
+        let spec = self.current_request();
+        let masker = self.masker(SecretView::Preview);
+        let theme = &self.theme;
+        let mut resolver = Resolver::new(&self.variables, None);
+        let mut lines = vec![];
+        let mut missing: Vec<String> = vec![];
+        let parts = [("URL", Some(&spec.url)), ("Connect to", spec.connect_to.as_ref()), ("Headers", Some(&spec.headers)), ("Body", Some(&spec.body))];
+        for (label, text) in parts {
+            let Some(text) = text.filter(|text| !text.trim().is_empty()) else { continue };
+            lines.push(Line::styled(label, theme.heading));
+            match resolver.resolve_pieces(text) {
+                Ok(pieces) => {
+                    for piece in &pieces {
+                        if let Piece::Missing(expression) = piece {
+                            let name = expression.trim().to_string();
+                            if !missing.contains(&name) {
+                                missing.push(name);
+                            }
+                        }
+                    }
+                    lines.extend(piece_lines(&pieces, theme, &masker));
+                }
+                Err(e) => lines.push(Line::styled(theme.error_text(&e.to_string()), theme.error)),
+            }
+            lines.push(Line::from(""));
+        }
+        if resolver.used.is_empty() && resolver.generated.is_empty() {
+            lines.push(Line::styled("No placeholders", theme.dim));
+        } else {
+            lines.push(Line::styled("Values", theme.heading));
+        }
+        for (name, value) in &resolver.used {
+            lines.push(Line::from(vec![Span::styled(format!("  {} → ", name), theme.key), Span::styled(masker.text(value), theme.value)]));
+        }
+        for generated in &resolver.generated {
+            let line = vec![Span::styled(format!("  {} → ", generated.expression), theme.key), Span::styled(generated.value.clone(), theme.value), Span::styled("  (drawn again when sent)", theme.dim)];
+            lines.push(Line::from(line));
+        }
+        if !missing.is_empty() {
+            lines.push(Line::from(""));
+            lines.push(Line::styled(format!("Not found, so sent as written: {}", missing.join(", ")), theme.error));
+        }
+        if let Ok(resolved) = spec.resolve(&mut Resolver::new(&self.variables, Some(&resolver.generated)), &self.host_rules) {
+            if !resolved.host_defaults.is_empty() || !self.request_hooks.is_empty() {
+                lines.push(Line::from(""));
+            }
+            for contribution in &resolved.host_defaults {
+                let line = format!("Added by host rule {}: {}", contribution.pattern, contribution.headers.join(", "));
+                lines.push(Line::styled(line, theme.dim));
+            }
+        }
+        if !self.request_hooks.is_empty() {
+            let headers: Vec<&str> = self.request_hooks.iter().map(Hook::header).collect();
+            lines.push(Line::styled(format!("Set by pre-request hooks: {}", headers.join(", ")), theme.dim));
+        }
+        self.popup = Some(Popup::Text { title: "Resolved templating".to_string(), lines, scroll: 0 });
+    }
+
+    pub fn move_input_mode_up(&mut self) {
+        let index_shift = self.input_index + self.input_order.len() - 1;
+        self.input_index = index_shift % self.input_order.len();
+    }
+    pub fn move_input_mode_down(&mut self) {
+        let index_shift = self.input_index + self.input_order.len() + 1;
+        self.input_index = index_shift % self.input_order.len();
+    }
+
+    /// Replaces the characters `start..end` of the focused field, recording an undo step.
+    fn replace_range(&mut self, start: usize, end: usize, text: String) {
+        let current = self.get_current_input().clone();
+        let replaced: String = current.chars().take(start).chain(text.chars()).chain(current.chars().skip(end)).collect();
+        self.undo_stack.push(UndoEntry {
+            input_index: self.input_index,
+            text: current,
+            character_index: self.character_index,
+        });
+        *self.get_current_input_mut() = replaced;
+        self.character_index = start + text.chars().count();
+        self.error_message = None;
+    }
+
+    /// Cuts from the cursor to the start or the end of its line into the kill buffer.
+    fn kill_line(&mut self, to_end: bool) {
+        if *self.get_input_mode() == InputMode::EditingMethod {
+            return;
+        }
+        let (line_start, line_end) = editor::line_bounds(self.get_current_input(), self.character_index);
+        let (start, end) = if to_end {(self.character_index, line_end)} else {(line_start, self.character_index)};
+        if start == end {
+            return;
+        }
+        self.kill_buffer = self.get_current_input().chars().skip(start).take(end - start).collect();
+        self.replace_range(start, end, String::new());
+    }
+
+    fn yank(&mut self) {
+        if *self.get_input_mode() == InputMode::EditingMethod || self.kill_buffer.is_empty() {
+            return;
+        }
+        let text = if *self.get_input_mode() == InputMode::EditingUrl {self.kill_buffer.replace('\n', "")} else {self.kill_buffer.clone()};
+        self.replace_range(self.character_index, self.character_index, text);
+    }
+
+    fn undo(&mut self) {
+        let Some(entry) = self.undo_stack.pop() else {
+            self.error_message = Some("Nothing to undo".to_string().into());
+            return;
+        };
+        self.input_index = entry.input_index;
+        *self.get_current_input_mut() = entry.text;
+        self.character_index = entry.character_index;
+    }
+
+    fn transform(&mut self, transform: Transform, scope: Scope) {
+        if *self.get_input_mode() == InputMode::EditingMethod {
+            self.error_message = Some("Focus a text field to transform it".to_string().into());
+            return;
+        }
+        let (start, end) = match scope {
+            Scope::Word => self.word_under_cursor(|c| !c.is_whitespace()),
+            Scope::Field => (0, self.get_current_input().chars().count()),
+        };
+        let target: String = self.get_current_input().chars().skip(start).take(end - start).collect();
+        if target.is_empty() {
+            self.error_message = Some(format!("Nothing to {}", transform.name().to_lowercase()).into());
+            return;
+        }
+
+        let bytes = match transform.apply(&target) {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                self.error_message = Some(format!("{} failed: {}", transform.name(), e).into());
+                return;
+            }
+        };
+        match String::from_utf8(bytes) {
+            Ok(text) if !text.chars().any(|c| c.is_control() && !matches!(c, '\n' | '\r' | '\t')) => {
+                self.replace_range(start, end, text);
+            }
+            Ok(text) => self.popup = Some(Popup::Confirm {
+                message: "The decoded text contains control characters. Insert it anyway?".to_string(),
+                on_yes: Confirmed::ReplaceRange { start, end, text },
+                on_no: None,
+            }),
+            Err(e) => self.popup = Some(Popup::Confirm {
+                message: format!("The decoded {} bytes are not valid UTF-8. Insert them lossily?", e.as_bytes().len()),
+                on_yes: Confirmed::ReplaceRange { start, end, text: String::from_utf8_lossy(e.as_bytes()).into_owned() },
+                on_no: None,
+            }),
+        }
+    }
+
+    fn perform(&mut self, action: Action) {
+        match action {
+            Action::CommandPalette => self.popup = Some(Popup::Palette { filter: String::new(), selected: 0 }),
+            Action::Help => self.show_help(),
+            Action::Suspend => self.suspend_requested = true,
+            Action::EditBodyExternally => self.external_edit_requested = true,
+            Action::OpenResponseWith => self.open_response_requested = true,
+            Action::ExportAsCode => {
+                let items = Language::ALL.iter().map(|language| language.name().to_string()).collect();
+                self.popup = Some(Popup::Picker { title: "Export as".to_string(), items, selected: 0, target: PickTarget::Snippet });
+            }
+            Action::Quit => self.quit_requested = true,
+            Action::ResendLast => self.resend_last(),
+            Action::RetryLastFailure => self.retry_last_failure(),
+            Action::CancelRequest => self.cancel_request(),
+            Action::CheckConnectivity => self.check_connectivity(),
+            Action::PreviewTemplating => self.preview_templating(),
+            Action::SendWithoutSchemaChecks => self.send_req(true),
+            Action::RetryWhenAllowed => self.retry_when_allowed(),
+            Action::SaveRequest => self.popup = Some(Popup::Prompt {
+                title: format!("Save request in {} as", folder_label(&self.selected_folder())),
+                input: String::new(),
+                target: PromptTarget::SaveRequest,
+            }),
+            Action::FindRequest => {
+                if self.collection.requests.is_empty() {
+                    self.error_message = Some("No saved requests yet".to_string().into());
+                } else {
+                    self.popup = Some(Popup::Finder { query: String::new(), selected: 0 });
+                }
+            }
+            Action::ToggleCollection => self.toggle_collection(),
+            Action::FocusResponses => {
+                self.focus = if self.focus == Pane::Responses {Pane::Editor} else {Pane::Responses};
+            }
+            Action::ToggleResponseHeaders => self.response_view.expanded = !self.response_view.expanded,
+            Action::SortResponseHeaders => self.response_view.sorted = !self.response_view.sorted,
+            Action::ToggleWrap => self.toggle_wrap(),
+            Action::CycleHistoryGrouping => self.cycle_history_grouping(),
+            Action::CycleJsonView => self.cycle_json_view(),
+            Action::CopyResponseHeader => self.copy_response_header(true),
+            Action::ToggleReadableHtml => self.toggle_readable_html(),
+            Action::SaveBaseline => self.save_baseline(false),
+            Action::LoadComparison => self.popup = Some(Popup::Prompt {
+                title: "Response file to compare with (raw HTTP or just a body)".to_string(),
+                input: self.comparison.as_ref().map(|(path, _)| path.clone()).unwrap_or_default(),
+                target: PromptTarget::LoadComparison,
+            }),
+            Action::CompareWithLoaded => self.show_comparison(),
+            Action::CollapseAllFolders => self.collapse_all_folders(),
+            Action::FilterHistoryByEnvironment => {
+                let mut environments: Vec<Option<String>> = vec![];
+                for entry in &self.history {
+                    if !environments.contains(&entry.environment) {
+                        environments.push(entry.environment.clone());
+                    }
+                }
+                if environments.is_empty() {
+                    self.error_message = Some("Nothing sent yet".to_string().into());
+                    return;
+                }
+                let mut items = vec!["(all environments)".to_string()];
+                items.extend(environments.iter().map(|environment| environment.clone().unwrap_or_else(|| "(no environment)".to_string())));
+                self.popup = Some(Popup::Picker {
+                    title: "Show responses sent with".to_string(),
+                    selected: self.history_environment.as_ref().and_then(|filter| environments.iter().position(|e| e == filter)).map_or(0, |i| i + 1),
+                    items,
+                    target: PickTarget::HistoryEnvironment { environments },
+                });
+            }
+            Action::FilterByTag => {
+                let tags = self.collection.tags();
+                if tags.is_empty() {
+                    self.error_message = Some("No saved request has tags yet".to_string().into());
+                    return;
+                }
+                let mut items = vec!["(all requests)".to_string()];
+                items.extend(tags.iter().cloned());
+                self.popup = Some(Popup::Picker {
+                    title: "Show requests tagged".to_string(),
+                    selected: self.tag_filter.as_ref().and_then(|tag| tags.iter().position(|t| t == tag)).map_or(0, |i| i + 1),
+                    items,
+                    target: PickTarget::TagFilter { tags },
+                });
+            }
+            Action::ImportPostman => self.popup = Some(Popup::Prompt {
+                title: "Import Postman collection from file".to_string(),
+                input: String::new(),
+                target: PromptTarget::ImportPostman,
+            }),
+            Action::ValidateBody => self.validate_body(),
+            Action::ImportInsomnia => self.popup = Some(Popup::Prompt {
+                title: "Import Insomnia export (JSON or YAML) from file".to_string(),
+                input: String::new(),
+                target: PromptTarget::ImportInsomnia,
+            }),
+            Action::ExportPostman => self.popup = Some(Popup::Prompt {
+                title: "Export Postman collection to file".to_string(),
+                input: "collection.postman.json".to_string(),
+                target: PromptTarget::ExportPostman,
+            }),
+            Action::ImportRequestFile => self.popup = Some(Popup::Prompt {
+                title: "Import requests from .http or .hurl file".to_string(),
+                input: String::new(),
+                target: PromptTarget::ImportRequestFile,
+            }),
+            Action::ExportHttpFile => self.ask_plaintext_destination(Format::RestClient),
+            Action::ExportHurl => self.ask_plaintext_destination(Format::Hurl),
+            Action::ExportMarkdown => match self.history.last() {
+                Some(entry) => self.ask_markdown_destination(vec![entry.id]),
+                None => self.error_message = Some("Nothing sent yet".to_string().into()),
+            },
+            Action::SaveExchange => match self.history.last() {
+                Some(entry) => self.popup = Some(Popup::Prompt {
+                    title: "Save exchange to file (.json: history entry; add --secrets to keep credentials)".to_string(),
+                    input: exchange::default_name(entry),
+                    target: PromptTarget::SaveExchange { id: entry.id },
+                }),
+                None => self.error_message = Some("Nothing sent yet".to_string().into()),
+            },
+            Action::PinnedExchanges => self.show_pinned(),
+            Action::ErrorDetails => self.show_error_details(),
+            Action::RecentErrors => self.show_recent_errors(),
+            Action::Statistics => match self.history.iter().all(HistoryEntry::is_pending) {
+                true => self.error_message = Some("Nothing sent yet".to_string().into()),
+                false => self.popup = Some(Popup::Stats { selected: 0, sort: SortBy::P95 }),
+            },
+            Action::ClearHistory => {
+                let unpinned = self.history.iter().filter(|entry| !entry.pinned && !entry.is_pending()).count();
+                let pinned = self.history.iter().filter(|entry| entry.pinned).count();
+                match (unpinned, pinned) {
+                    (0, 0) => self.error_message = Some("Nothing sent yet".to_string().into()),
+                    (0, _) => self.clear_history(false),
+                    _ => self.popup = Some(Popup::Confirm {
+                        message: match pinned {
+                            0 => format!("Clear {} exchanges from history?", unpinned),
+                            pinned => format!("Clear {} exchanges from history? The {} pinned ones are kept", unpinned, pinned),
+                        },
+                        on_yes: Confirmed::ClearHistory { pinned_too: false },
+                        on_no: None,
+                    }),
+                }
+            }
+            Action::SearchHistory => {
+                if self.history.is_empty() {
+                    self.error_message = Some("Nothing sent yet".to_string().into());
+                } else {
+                    self.popup = Some(Popup::Prompt {
+                        title: "Search history (URL, method, status or request id)".to_string(),
+                        input: String::new(),
+                        target: PromptTarget::SearchHistory,
+                    });
+                }
+            }
+            Action::ExportHistoryMarkdown => {
+                if self.history.is_empty() {
+                    self.error_message = Some("Nothing sent yet".to_string().into());
+                } else {
+                    self.popup = Some(Popup::Prompt {
+                        title: "Exchanges to export, newest is 1 (e.g. 1-5, or all)".to_string(),
+                        input: format!("1-{}", self.history.len().min(10)),
+                        target: PromptTarget::MarkdownRange,
+                    });
+                }
+            }
+            Action::BulkEditParams => {
+                let rows = self.current_query_rows();
+                self.popup = Some(Popup::BulkParams { text: kv::to_bulk(&rows, "="), errors: vec![] });
+            }
+            Action::ToggleHeaderTable => {
+                if self.header_table.take().is_some() {
+                    self.character_index = self.character_index.min(self.headers_input.chars().count());
+                } else {
+                    self.header_table = Some(HeaderTable { selected: 0, column: HeaderColumn::Name, editing: None, adding: false });
+                    self.focus = Pane::Editor;
+                    self.input_index = self.input_order.iter().position(|mode| *mode == InputMode::EditingHeaders).unwrap_or(self.input_index);
+                }
+            }
+            Action::GoToLine => self.popup = Some(Popup::Prompt {
+                title: format!("Go to line (1-{})", editor::line_count(&self.body_input)),
+                input: String::new(),
+                target: PromptTarget::GoToLine,
+            }),
+            Action::DecodeJwt => self.decode_jwt(),
+            Action::Transform(transform, scope) => self.transform(transform, scope),
+            Action::Undo => self.undo(),
+            Action::PreviewRequest => self.preview_request(),
+            Action::UnlockSecrets => match (&self.vault_key, self.environments.iter().any(|env| env.locked().next().is_some())) {
+                (Some(_), _) => self.error_message = Some("Secrets are already unlocked".to_string().into()),
+                (None, false) if self.environments.iter().all(|env| env.secrets.is_empty()) => {
+                    self.error_message = Some("No environment flags any variable as secret".to_string().into());
+                }
+                (None, _) => self.ask_passphrase(false),
+            },
+            Action::ChangePassphrase => self.ask_passphrase(true),
+            Action::SwitchWorkspace => self.open_workspaces(),
+            Action::RevealSecrets => {
+                let items = SecretView::ALL
+                    .iter()
+                    .map(|view| format!("{} ({})", view.name(), if self.revealed.contains(view) {"revealed"} else {"masked"}))
+                    .collect();
+                self.popup = Some(Popup::Picker { title: "Reveal or mask secrets in".to_string(), items, selected: 0, target: PickTarget::RevealSecrets });
+            }
+            Action::CorsCheck => self.popup = Some(Popup::Prompt {
+                title: "Origin, method, then any headers (e.g. https://app.example.com PUT Content-Type,Authorization)".to_string(),
+                input: self.cors_input.clone(),
+                target: PromptTarget::CorsCheck,
+            }),
+            Action::ReplayLast => self.replay_last(),
+            Action::LoadTest => match &self.load {
+                Some(run) if !run.is_finished() => self.popup = Some(Popup::Load),
+                _ => self.popup = Some(Popup::Prompt {
+                    title: "Workers and a duration or request count (e.g. 20 10s, or 20 500)".to_string(),
+                    input: "10 10s".to_string(),
+                    target: PromptTarget::LoadPlan,
+                }),
+            },
+            Action::RunFolder => match &self.run {
+                Some(run) if !run.is_finished() => self.popup = Some(Popup::Run { selected: 0 }),
+                _ => self.start_run(),
+            },
+            Action::LastRun => self.show_last_run(),
+            Action::SwitchEnvironment => self.pick_environment(),
+            Action::SwitchTheme => {
+                if self.theme.no_color {
+                    self.error_message = Some("Color is off (no_color or NO_COLOR), so a theme won't show until it's on".to_string().into());
+                }
+                let items = Preset::ALL.iter().map(|preset| preset.name().to_string()).collect();
+                let selected = Preset::ALL.iter().position(|preset| *preset == self.theme_preset).unwrap_or_default();
+                self.popup = Some(Popup::Picker { title: "Theme".to_string(), items, selected, target: PickTarget::Theme });
+            }
+            Action::ToggleConditional => {
+                self.send_conditional = !self.send_conditional;
+            }
+            Action::ShowValidators => self.popup = Some(Popup::Validators { selected: 0 }),
+            Action::Mocks => self.popup = Some(Popup::Mocks { selected: 0 }),
+            Action::ServerControl => self.popup = Some(Popup::ServerControl { selected: 0 }),
+            Action::ToggleConsole => self.show_console = !self.show_console,
+            Action::ToggleServerTraffic => self.show_traffic = !self.show_traffic,
+            Action::ClearServerTraffic => self.server_traffic.clear(),
+            Action::CopyConsole => self.copy_console(),
+            Action::CycleAddressPreference => self.address_preference = self.address_preference.next(),
+            Action::ResetByteCounters => (self.bytes_sent, self.bytes_received) = (0, 0),
+            Action::FlushDnsCache => {
+                let flushed = self.worker.dns.flush();
+                self.log(format!("[{}] Flushed {} cached DNS lookups", Local::now().format("%H:%M:%S%.3f"), flushed));
+            }
+            Action::SetConnectTo => self.popup = Some(Popup::Prompt {
+                title: "Connect to host:port (empty to clear)".to_string(),
+                input: self.connect_to.clone().unwrap_or_default(),
+                target: PromptTarget::ConnectTo,
+            }),
+            Action::SetRedirectPolicy => self.popup = Some(Popup::Prompt {
+                title: format!("Redirects: off, max hops, or hops then keep-auth (empty for the default, {})", self.redirect_default.describe()),
+                input: self.redirect_policy.map(|policy| policy.describe()).unwrap_or_default(),
+                target: PromptTarget::RedirectPolicy,
+            }),
+            Action::RequestSettings => self.popup = Some(Popup::RequestSettings { selected: 0 }),
+            // Read afresh, since the file is the source of truth and may have been edited by hand.
+            Action::Settings => match config::load() {
+                Ok(config) => self.popup = Some(Popup::Settings { config: Box::new(config), selected: 0, changed: false }),
+                Err(e) => self.error_message = Some(format!("Couldn't read the config: {}", e).into()),
+            },
+        }
+    }
+
+    /// Writes the settings to `config.toml` and applies those that can be without a restart.
+    fn save_settings(&mut self, config: &Config) {
+        if let Err(e) = config::save(config) {
+            self.error_message = Some(format!("Couldn't save the config: {}", e).into());
+            return;
+        }
+        self.theme = Theme::from_config(config.no_color, config.theme);
+        self.theme_preset = config.theme;
+        self.rerender_bodies();
+        self.keymap = Keymap::from_config(&config.keys).0;
+        self.response_view.wrap = config.wrap_responses;
+        if config.history_grouping != self.history_grouping {
+            self.history_grouping = config.history_grouping;
+            self.collapsed_groups.clear();
+            self.group_selected = 0;
+        }
+        self.baseline_rules = config.baseline.clone();
+        self.max_auto_retries = config.rate_limit.max_auto_retries;
+        self.redirect_default = config.redirects;
+        self.default_timeout = config.timeout_secs;
+        self.connect_timeout = config.connect_timeout_secs;
+        self.connect_budget = config.connect_budget_secs;
+        self.read_timeout = config.read_timeout_secs;
+        self.default_max_response_mb = config.responses.max_size_mb;
+        self.open_with = config.responses.open_with.clone();
+        self.collapse_repeats = config.responses.collapse_repeats;
+        self.run_policy = config.runner;
+        self.request_id_default = config.request_id;
+        self.host_rules = config.hosts.clone();
+        my_test_server::set_faults(config.test_server);
+        secrets::set_extra_headers(&config.secret_headers);
+        self.worker.dns.set_ttl(Duration::from_secs(config.dns.ttl_secs));
+        self.worker.submit(Job::SetRequestLog(RequestLog::from_config(&config.request_log)));
+        if config.terminal_title != self.terminal_title.is_some() {
+            self.error_message = Some("Saved; terminal_title takes effect after a restart".to_string().into());
+        }
+    }
+
+    fn set_redirect_policy(&mut self, input: &str) {
+        match input {
+            "" => self.redirect_policy = None,
+            input => match redirect::Policy::parse(input) {
+                Ok(policy) => self.redirect_policy = Some(policy),
+                Err(e) => self.error_message = Some(e.into()),
+            },
+        }
+    }
+
+    /// A request setting's own value, if it has one, and the config's.
+    fn request_setting(&self, setting: RequestSetting) -> (Option<String>, String) {
+        let seconds = |timeout: Option<u64>| timeout.map_or("none".to_string(), |seconds| format!("{}s", seconds));
+        let megabytes = |mb: u64| if mb == 0 {"any size".to_string()} else {format!("{} MB", mb)};
+        match setting {
+            RequestSetting::Timeout => (self.request_timeout.map(|t| seconds(Some(t))), seconds(self.default_timeout)),
+            RequestSetting::Redirects => (self.redirect_policy.map(|policy| policy.describe()), self.redirect_default.describe()),
+            RequestSetting::Retries => (self.request_max_retries.map(|r| r.to_string()), self.max_auto_retries.to_string()),
+            RequestSetting::RequestId => (self.request_id.map(on_off), on_off(self.request_id_default)),
+            RequestSetting::BodySchema => (self.body_schema.as_ref().map(BodySchema::describe), "none".to_string()),
+            RequestSetting::ResponseSchema => (self.response_schema.clone(), "none".to_string()),
+            RequestSetting::ExpectContinue => (self.expect_continue.map(|expect| expect.describe()), "off".to_string()),
+            RequestSetting::MaxResponse => (self.max_response_mb.map(megabytes), megabytes(self.default_max_response_mb)),
+        }
+    }
+
+    fn edit_request_setting(&mut self, setting: RequestSetting) {
+        let (own, default) = self.request_setting(setting);
+        let title = match setting {
+            RequestSetting::Timeout => "Seconds to wait for the whole response",
+            RequestSetting::Redirects => "Redirects: off, max hops, or hops then keep-auth",
+            RequestSetting::Retries => "Automatic retries when rate limited",
+            RequestSetting::RequestId => "Attach a fresh X-Request-Id: on or off",
+            RequestSetting::BodySchema => "JSON Schema file, #/pointer or #Component inside it, then strict to refuse bodies that don't match",
+            RequestSetting::ResponseSchema => "JSON Schema responses should match: file, #/pointer or #Component inside it",
+            RequestSetting::MaxResponse => "Largest response body kept, in MB; 0 keeps any size",
+            RequestSetting::ExpectContinue => "Wait for 100 Continue before the body: on or ms to wait, then strict to not send it after a 417 or no answer",
+        };
+        let input = match setting {
+            RequestSetting::BodySchema => self.body_schema.as_ref().map(BodySchema::to_input),
+            RequestSetting::ResponseSchema | RequestSetting::ExpectContinue => own,
+            RequestSetting::MaxResponse => self.max_response_mb.map(|mb| mb.to_string()),
+            _ => own.map(|own| own.trim_end_matches('s').to_string()),
+        };
+        self.popup = Some(Popup::Prompt {
+            title: format!("{} (empty to inherit {})", title, default),
+            input: input.unwrap_or_default(),
+            target: PromptTarget::RequestSetting(setting),
+        });
+    }
+
+    /// The request's settings that differ from the config, for the status bar.
+    fn custom_settings(&self) -> Vec<String> {
+        RequestSetting::ALL.into_iter()
+            .filter_map(|setting| {
+                let own = self.request_setting(setting).0?;
+                Some(format!("{} {}", setting.name(), own))
+            })
+            .collect()
+    }
+
+    fn ask_markdown_destination(&mut self, ids: Vec<u64>) {
+        self.popup = Some(Popup::Prompt {
+            title: "Write Markdown to file (empty: clipboard; add --secrets to keep credentials)".to_string(),
+            input: String::new(),
+            target: PromptTarget::MarkdownDestination { ids },
+        });
+    }
+
+    fn export_markdown(&mut self, ids: &[u64], destination: &str) {
+        let (path, include_secrets) = match destination.strip_suffix("--secrets") {
+            Some(path) => (path.trim(), true),
+            None => (destination, false),
+        };
+        let entries: Vec<(&HistoryEntry, Option<&str>)> = self.history.iter()
+            .filter(|entry| ids.contains(&entry.id))
+            .map(|entry| {
+                let description = entry.saved.as_ref()
+                    .and_then(|(folder, name)| self.collection.position(folder, name))
+                    .and_then(|index| self.collection.requests[index].description.as_deref());
+                (entry, description)
+            })
+            .collect();
+        let document = match entries[..] {
+            [(entry, description)] => markdown::exchange(entry, description, include_secrets),
+            _ => markdown::report(&entries, include_secrets),
+        };
+        let result = if path.is_empty() {
+            clipboard::copy(&document).map_err(|e| format!("Couldn't copy the report: {}", e))
+        } else {
+            fs::write(path, document).map_err(|e| format!("Couldn't write {}: {}", path, e))
+        };
+        if let Err(e) = result {
+            self.error_message = Some(e.into());
+        }
+    }
+
+    fn save_exchange(&mut self, id: u64, destination: &str) {
+        let (path, include_secrets) = match destination.strip_suffix("--secrets") {
+            Some(path) => (path.trim(), true),
+            None => (destination, false),
+        };
+        let Some(entry) = self.history.iter().find(|entry| entry.id == id) else { return };
+        if path.is_empty() {
+            self.error_message = Some("Expected a file name".to_string().into());
+            return;
+        }
+        let contents = if path.to_ascii_lowercase().ends_with(".json") {
+            exchange::json(entry, include_secrets)
+        } else {
+            Ok(exchange::raw(entry, include_secrets))
+        };
+        let result = contents.and_then(|contents| fs::write(path, contents).map_err(|e| e.to_string()));
+        match (result, entry.response.as_ref().filter(|response| response.truncated_from.is_some())) {
+            (Err(e), _) => self.error_message = Some(format!("Couldn't write {}: {}", path, e).into()),
+            (Ok(()), Some(response)) => self.error_message = Some(format!("Wrote {}, but the body in it is partial: {}", path, truncation_note(response)).into()),
+            (Ok(()), None) => {}
+        }
+    }
+
+    /// The differences between the loaded response file and the newest response, with the file
+    /// on the `-` side. A file with just a body is compared by body alone.
+    fn show_comparison(&mut self) {
+        let Some((path, loaded)) = &self.comparison else {
+            self.error_message = Some("No response file loaded".to_string().into());
+            return;
+        };
+        let Some(entry) = self.history.iter().rev().find(|entry| entry.response.is_some()) else {
+            self.error_message = Some("No response to compare with yet".to_string().into());
+            return;
+        };
+        let Some(mut newest) = entry.response.clone() else { return };
+        let status = |response: &ResponseRecord| response.status.map_or("no status line".to_string(), |code| code.to_string());
+        let newest_status = status(&newest);
+        if loaded.status.is_none() {
+            newest.status = None;
+        }
+        if loaded.headers.is_empty() {
+            newest.headers.clear();
+        }
+        let theme = &self.theme;
+        let mut lines = vec![
+            Line::from(vec![Span::styled("- file    ", theme.key), Span::raw(format!("{} ({})", path, status(loaded)))]),
+            Line::from(vec![
+                Span::styled("+ newest  ", theme.key),
+                Span::raw(format!("{} {} ({}, {})", entry.request.method, entry.request.url, newest_status, entry.timestamp)),
+            ]),
+            Line::from(""),
+        ];
+        if loaded.headers.is_empty() {
+            lines.push(Line::styled("The file has no headers, so only bodies are compared", theme.dim));
+        }
+        let differences = baseline::compare(loaded, &newest, &self.baseline_rules);
+        if differences.is_empty() {
+            lines.push(Line::from(theme.badge("same as the file", Tone::Good)));
+        }
+        lines.extend(differences.into_iter().map(Line::from));
+        self.popup = Some(Popup::Text { title: "Loaded file → newest response".to_string(), lines, scroll: 0 });
+    }
+
+    fn go_to_line(&mut self, input: &str) {
+        let line_count = editor::line_count(&self.body_input);
+        let start = input
+            .parse::<usize>()
+            .ok()
+            .and_then(|line| editor::line_start(&self.body_input, line.checked_sub(1)?));
+        let Some(start) = start else {
+            self.error_message = Some(format!("No line {:?} in the body, which has {} lines", input, line_count).into());
+            return;
+        };
+        self.focus = Pane::Editor;
+        self.input_index = self.input_order.iter().position(|mode| *mode == InputMode::EditingBody).unwrap_or(self.input_index);
+        self.character_index = start;
+    }
+
+    fn show_help(&mut self) {
+        let key_style = self.theme.key;
+        let mut lines = vec![];
+        for (binding, action) in self.keymap.bindings() {
+            lines.push(Line::from(vec![
+                Span::styled(format!("{:<12}", binding.to_string()), key_style),
+                Span::raw(action.name()),
+            ]));
+        }
+        for (key, meaning) in [
+            ("Enter", "Send request"),
+            ("Tab", "Move focus to the next pane, Shift+Tab back (not in the header table, which uses Tab itself)"),
+            ("Up/Down", "Move between fields"),
+            ("Left/Right", "Move the cursor, or change the method"),
+            ("Ctrl+Down", "Pick from recently sent URLs, in the URL field"),
+            ("/ n N", "Search the newest response and step through matches, in the responses pane"),
+            ("r", "List every earlier run of a repeated request, or just the last few, in the responses pane"),
+            ("o", "Order the responses pane by recency, latency or status"),
+            ("Up Down y Y", "Pick a response header and copy its value, or the whole line, in the responses pane"),
+            ("x", "The error in the status line in full, in the responses pane"),
+            ("R", "Retry the last request that got no response, in the responses pane"),
+            ("2 4 5 0 a", "Show only 2xx, 4xx, 5xx or failed exchanges, or all again, in the responses pane"),
+            ("j k", "Select the exchange below or above, in the responses pane"),
+            ("*", "Pin or unpin the selected exchange in the responses pane, or the highlighted one in history pickers"),
+            ("Ctrl+U/K", "Cut to the start/end of the line"),
+            ("Ctrl+Y", "Paste the last cut text"),
+            ("PageUp/Down", "Scroll the console"),
+        ] {
+            lines.push(Line::from(vec![Span::styled(format!("{:<12}", key), key_style), Span::raw(meaning)]));
+        }
+        let order: Vec<&str> = Focus::ORDER.iter().map(|focus| focus.name()).collect();
+        lines.push(Line::from(""));
+        lines.push(Line::styled(format!("Focus order: {}, then back to the start; the collection only while it's shown", order.join(" → ")), self.theme.dim));
+        lines.push(Line::styled(
+            format!("Rebind keys in the [keys] section of {}", config::config_path().display()),
+            self.theme.dim,
+        ));
+        self.popup = Some(Popup::Text { title: "Help".to_string(), lines, scroll: 0 });
+    }
+
+    fn confirm(&mut self, confirmed: Confirmed) {
+        match confirmed {
+            Confirmed::ReplaceRange { start, end, text } => self.replace_range(start, end, text),
+            Confirmed::ClearHistory { pinned_too } => self.clear_history(pinned_too),
+            Confirmed::Replay { reuse_generated, original_environment } => {
+                let Some(last) = self.history.last() else { return };
+                let spec = last.request.clone();
+                let generated = last.generated.clone();
+                let saved = last.saved.clone();
+                let environment = last.environment.clone();
+                let mut original = self.variables.clone();
+                // Redacted credentials weren't kept, so the current environment's stand in.
+                original.extend(last.variables.iter().filter(|(_, value)| value != secrets::REDACTED).cloned());
+                self.method_index = method_index(&spec.method);
+                if !original_environment {
+                    self.send_spec(spec, reuse_generated.then_some(generated.as_slice()), saved);
+                    return;
+                }
+                let current = std::mem::replace(&mut self.variables, original);
+                let sent = self.send_spec(spec, reuse_generated.then_some(generated.as_slice()), saved);
+                self.variables = current;
+                if let (Some(_), Some(entry)) = (sent, self.history.last_mut()) {
+                    entry.environment = environment;
+                }
+            }
+            Confirmed::DeleteRequest { index } => {
+                self.collection.requests.remove(index);
+                self.clamp_collection_selection();
+                self.save_collection();
+            }
+            Confirmed::DeleteFolder { path } => {
+                self.collection.delete_folder(&path);
+                self.collapsed_folders.retain(|folder| !folder.starts_with(&path));
+                self.clamp_collection_selection();
+                self.save_collection();
+            }
+            Confirmed::SendInvalidJson { skip_schema_checks } => self.send_unchecked(skip_schema_checks),
+            Confirmed::ImportCurl(command) => match curl::parse(&command) {
+                Ok(request) => self.set_editor(request),
+                Err(e) => self.error_message = Some(format!("Couldn't import the curl command: {}", e).into()),
+            },
+            Confirmed::PasteLiterally(text) => self.insert_pasted(&text),
+            Confirmed::ReplaceBaseline => self.save_baseline(true),
+            Confirmed::RecoverDraft(editor) => {
+                if let Err(e) = self.restore_session(*editor) {
+                    self.error_message = Some(e.into());
+                }
+            }
+            Confirmed::DiscardDraft => self.worker.submit(Job::DiscardDraft { path: config::drafts_path() }),
+            Confirmed::DeleteWorkspace { name } => match workspace::delete(&name) {
+                Ok(()) => self.open_workspaces(),
+                Err(e) => self.error_message = Some(format!("Couldn't delete workspace {}: {}", name, e).into()),
+            },
+            Confirmed::RunLoad { plan, request } => self.start_load(plan, *request),
+        }
+    }
+
+    /// Resolves the editor's request once for every worker, asking first unless it goes to this machine.
+    /// Runs the saved requests under the selected folder, or the whole collection when none is
+    /// selected, in the order the collection pane lists them.
+    fn start_run(&mut self) {
+        match self.new_run(self.selected_folder()) {
+            Ok(run) => self.begin_run(run),
+            Err(e) => self.error_message = Some(e.into()),
+        }
+    }
+
+    fn new_run(&self, folder: Vec<String>) -> Result<runner::Run, String> {
+        let requests: Vec<(Vec<String>, String)> = self.collection.tree(&BTreeSet::new(), None)
+            .into_iter()
+            .filter_map(|row| match row {
+                TreeRow::Request(index) => Some(&self.collection.requests[index]),
+                TreeRow::Folder { .. } => None,
+            })
+            .filter(|saved| saved.folder.starts_with(&folder))
+            .map(|saved| (saved.folder.clone(), saved.name.clone()))
+            .collect();
+        let run = runner::Run::new(folder, requests, Local::now().to_rfc3339());
+        match run.legs.is_empty() {
+            true => Err(format!("Nothing to run: no saved requests in {}", run.label())),
+            false => Ok(run),
+        }
+    }
+
+    /// Runs `folder` without a terminal, for `--run`, in the environment named if any. Returns
+    /// the finished run with the response bodies of the requests that failed, by history id.
+    pub fn run_headless(mut self, folder: Vec<String>, environment: Option<&str>) -> Result<(runner::Run, HashMap<u64, String>), String> {
+        if let Some(e) = self.error_message.take() {
+            eprintln!("{}", e);
+        }
+        if let Some(name) = environment {
+            let index = self.environments.iter().position(|env| env.name == name).ok_or_else(|| format!("no environment named {:?}", name))?;
+            self.active_environment = Some(index);
+            self.load_variables()?;
+        }
+        let run = self.new_run(folder)?;
+        self.begin_run(run);
+        while self.run.as_ref().is_some_and(|run| run.finished.is_none()) {
+            while let Ok(event) = self.worker.events.try_recv() {
+                self.handle_event(AppEvent::Worker(event));
+            }
+            self.handle_event(AppEvent::Tick(Instant::now()));
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        let run = self.run.take().ok_or("the run didn't start")?;
+        let bodies = run.failing()
+            .filter_map(|leg| leg.id)
+            .filter_map(|id| {
+                let entry = self.history.iter().find(|entry| entry.id == id)?;
+                Some((id, entry.response.as_ref()?.body.clone()))
+            })
+            .collect();
+        Ok((run, bodies))
+    }
+
+    fn begin_run(&mut self, run: runner::Run) {
+        self.log(format!("[{}] Running {}, {} requests", Local::now().format("%H:%M:%S%.3f"), run.label(), run.legs.len()));
+        self.run = Some(run);
+        self.popup = Some(Popup::Run { selected: 0 });
+        self.advance_run();
+    }
+
+    /// Moves the collection run along: settles the request in flight once it's answered and its
+    /// response schema checked, then sends the next unless the run stopped. Saves the run once
+    /// it's finished.
+    fn advance_run(&mut self) {
+        let Some(mut run) = self.run.take().filter(|run| run.finished.is_none()) else { return };
+        loop {
+            if let Some(index) = run.running() {
+                let id = run.legs[index].id;
+                let Some(entry) = self.history.iter().rev().find(|entry| Some(entry.id) == id) else {
+                    run.settle(index, Outcome::Error, vec!["no longer in history".to_string()], self.run_policy);
+                    continue;
+                };
+                let checking = entry.response.is_some() && entry.request.response_schema.is_some() && entry.schema_check.is_none();
+                if entry.is_pending() || checking {
+                    break;
+                }
+                run.legs[index].status = entry.response.as_ref().and_then(|response| response.status);
+                run.legs[index].latency_ms = entry.timing.map(|timing| timing.latency_ms);
+                if let Some(e) = &entry.error {
+                    run.settle(index, Outcome::Error, vec![e.clone()], self.run_policy);
+                    continue;
+                }
+                let mut problems: Vec<String> = self.hook_outcomes.get(&entry.id).into_iter().flatten()
+                    .filter(|outcome| !outcome.ok)
+                    .map(|outcome| outcome.message.clone())
+                    .collect();
+                problems.extend(self.baseline_results.get(&entry.id).into_iter().flatten().map(|difference| format!("baseline: {}", difference)));
+                if let Some(check) = entry.schema_check.as_ref().filter(|check| !check.passed()) {
+                    problems.extend(check.error.iter().chain(&check.violations).map(|problem| format!("schema: {}", problem)));
+                }
+                let outcome = if problems.is_empty() {Outcome::Passed} else {Outcome::Failed};
+                run.settle(index, outcome, problems, self.run_policy);
+            }
+            let Some(index) = run.next_waiting() else { break };
+            let (folder, name) = (run.legs[index].folder.clone(), run.legs[index].name.clone());
+            let Some(position) = self.collection.position(&folder, &name) else {
+                run.settle(index, Outcome::Error, vec!["no longer in the collection".to_string()], self.run_policy);
+                continue;
+            };
+            let spec = self.collection.requests[position].request.clone();
+            self.error_message = None;
+            match self.send_spec(spec, None, Some((folder, name))) {
+                Some(id) => {
+                    run.legs[index].id = Some(id);
+                    run.legs[index].outcome = Outcome::Running;
+                    break;
+                }
+                None => {
+                    let reason = self.error_message.as_ref().map_or_else(|| "not sent".to_string(), AppError::to_string);
+                    run.settle(index, Outcome::Error, vec![reason], self.run_policy);
+                }
+            }
+        }
+        if run.is_finished() {
+            run.finished = Some(Local::now().to_rfc3339());
+            let saved = match runner::save(&config::runs_dir(), &run) {
+                Ok(path) => format!("saved to {}", path),
+                Err(e) => format!("couldn't save it: {}", e),
+            };
+            self.log(format!("[{}] Ran {}: {}; {}", Local::now().format("%H:%M:%S%.3f"), run.label(), run.summary(), saved));
+        }
+        self.run = Some(run);
+    }
+
+    /// The exchange a run's request was sent as, in full.
+    fn show_run_exchange(&mut self, id: Option<u64>) {
+        let Some(entry) = id.and_then(|id| self.history.iter().find(|entry| entry.id == id)) else {
+            self.error_message = Some("That request wasn't sent, or its exchange is no longer in history".to_string().into());
+            return;
+        };
+        let title = entry.saved.as_ref().map_or_else(|| entry.request.url.clone(), |(_, name)| name.clone());
+        let lines = markdown::exchange(entry, None, false).lines().map(|line| Line::from(line.to_string())).collect();
+        self.popup = Some(Popup::Text { title, lines, scroll: 0 });
+    }
+
+    fn save_run_summary(&mut self, path: &str) {
+        let Some(run) = &self.run else { return };
+        let document = run.markdown();
+        let result = if path.is_empty() {
+            clipboard::copy(&document).map_err(|e| format!("Couldn't copy the summary: {}", e))
+        } else {
+            fs::write(path, document).map_err(|e| format!("Couldn't write {}: {}", path, e))
+        };
+        if let Err(e) = result {
+            self.error_message = Some(e.into());
+        }
+    }
+
+    fn save_stats_csv(&mut self, path: &str) {
+        let document = stats::csv(&stats::rollup(self.history.iter(), SortBy::P95));
+        let result = if path.is_empty() {
+            clipboard::copy(&document).map_err(|e| format!("Couldn't copy the statistics: {}", e))
+        } else {
+            fs::write(path, document).map_err(|e| format!("Couldn't write {}: {}", path, e))
+        };
+        if let Err(e) = result {
+            self.error_message = Some(e.into());
+        }
+    }
+
+    /// This session's latest run, or the latest saved one.
+    fn show_last_run(&mut self) {
+        if self.run.is_none() {
+            match runner::load_latest(&config::runs_dir()) {
+                Ok(run) => self.run = run,
+                Err(e) => {
+                    self.error_message = Some(format!("Couldn't load the last run: {}", e).into());
+                    return;
+                }
+            }
+        }
+        match self.run {
+            Some(_) => self.popup = Some(Popup::Run { selected: 0 }),
+            None => self.error_message = Some("No collection runs yet; Run folder starts one".to_string().into()),
+        }
+    }
+
+    fn plan_load(&mut self, input: &str) {
+        let plan = match load::parse_plan(input) {
+            Ok(plan) => plan,
+            Err(e) => {
+                self.error_message = Some(e.into());
+                return;
+            }
+        };
+        let mut resolver = Resolver::new(&self.variables, None);
+        let request = match self.current_request().resolve(&mut resolver, &self.host_rules) {
+            Ok(request) => request,
+            Err(e) => {
+                self.error_message = Some(format!("Can't load test: {}", e).into());
+                return;
+            }
+        };
+        match url::parse_authority(request.peer()) {
+            Ok((host, _)) if host.is_local() => self.start_load(plan, request),
+            _ => self.popup = Some(Popup::Confirm {
+                message: format!("Send {} workers' worth of requests to {}, which isn't this machine?", plan.concurrency, request.peer()),
+                on_yes: Confirmed::RunLoad { plan, request: Box::new(request) },
+                on_no: None,
+            }),
+        }
+    }
+
+    /// Sends the preflight a browser would for the editor's URL; the verdict pops up with the response.
+    fn cors_check(&mut self, input: &str) {
+        let preflight = match Preflight::parse(input) {
+            Ok(preflight) => preflight,
+            Err(e) => {
+                self.error_message = Some(e.into());
+                return;
+            }
+        };
+        self.cors_input = input.to_string();
+        let spec = RequestSpec {
+            method: "OPTIONS".to_string(),
+            headers: preflight.request_headers(),
+            body: String::new(),
+            hooks: vec![],
+            after_response: vec![],
+            // Browsers don't follow redirects of a preflight.
+            redirects: Some(redirect::Policy { follow: false, ..self.redirect_default }),
+            ..self.current_request()
+        };
+        if let Some(id) = self.send_spec(spec, None, None) {
+            self.cors_checks.insert(id, preflight);
+        }
+    }
+
+    fn show_cors_verdict(&mut self, preflight: &Preflight, response: &ResponseRecord) {
+        let theme = &self.theme;
+        let mut lines = vec![Line::from(format!("Preflight from {} for {}", preflight.origin, preflight.method)), Line::from("")];
+        match cors::verdict(preflight, &response.headers) {
+            Ok(rows) => lines.extend(rows.into_iter().map(|row| {
+                let badge = match row.allowed {
+                    Some(true) => theme.badge("allowed", Tone::Good),
+                    Some(false) => theme.badge("blocked", Tone::Bad),
+                    None => Span::raw(""),
+                };
+                Line::from(vec![Span::styled(format!("{:<24} ", row.label), theme.key), badge, Span::raw(format!(" {}", row.value))])
+            })),
+            Err(e) => lines.push(Line::from(theme.badge(&e, Tone::Bad))),
+        }
+        self.popup = Some(Popup::Text { title: "CORS check".to_string(), lines, scroll: 0 });
+    }
+
+    fn start_load(&mut self, plan: load::Plan, request: ResolvedRequest) {
+        let (connect_timeout, read_timeout) = (self.connect_timeout.map(Duration::from_secs), self.read_timeout.map(Duration::from_secs));
+        self.load = Some(load::Run::start(plan, request, self.worker.dns.clone(), self.address_preference, connect_timeout, read_timeout));
+        self.load_bucket = 0;
+        self.popup = Some(Popup::Load);
+    }
+
+    fn decode_jwt(&mut self) {
+        let Some((source, token)) = self.find_jwt() else {
+            self.error_message = Some("No JWT under the cursor, in the Authorization header, or in the latest response".to_string().into());
+            return;
+        };
+        let lines = match jwt::decode(&token) {
+            Ok(token) => jwt_lines(&token, Utc::now().timestamp(), &self.theme),
+            Err(e) => vec![Line::styled(self.theme.error_text(&format!("Malformed JWT: {}", e)), self.theme.error)],
+        };
+        self.popup = Some(Popup::Text { title: format!("JWT from {}", source), lines, scroll: 0 });
+    }
+
+    fn find_jwt(&self) -> Option<(String, String)> {
+        if *self.get_input_mode() != InputMode::EditingMethod {
+            let (start, end) = self.word_under_cursor(|c| c.is_ascii_alphanumeric() || "-_.".contains(c));
+            let word: String = self.get_current_input().chars().skip(start).take(end - start).collect();
+            if jwt::looks_like_jwt(&word) {
+                return Some(("token under the cursor".to_string(), word));
+            }
+        }
+        if let Some(token) = jwt::find_in_headers(&self.headers_input) {
+            return Some(("Authorization header".to_string(), token.to_string()));
+        }
+        self.latest_response()
+            .and_then(|response| jwt::find_in_text(&response.body))
+            .map(|token| ("latest response".to_string(), token.to_string()))
+    }
+
+    fn pick(&mut self, target: PickTarget, selected: usize) {
+        match target {
+            PickTarget::Environment => self.switch_environment(selected.checked_sub(1)),
+            PickTarget::RecentUrl { urls } => {
+                if let Some(url) = urls.into_iter().nth(selected) {
+                    let end = self.url_input.chars().count();
+                    self.replace_range(0, end, url);
+                }
+            }
+            PickTarget::HistoryEntry { ids } => {
+                let Some(entry) = ids.get(selected).and_then(|id| self.history.iter().find(|entry| entry.id == *id)) else { return };
+                let lines = markdown::exchange(entry, None, false).lines().map(|line| Line::from(line.to_string())).collect();
+                self.popup = Some(Popup::Text { title: format!("{} {}", entry.request.method, entry.request.url), lines, scroll: 0 });
+            }
+            PickTarget::ReplayEnvironment => self.ask_reuse_generated(selected == 0),
+            PickTarget::Workspace { names } => match names.into_iter().nth(selected) {
+                Some(name) if name == workspace::DEFAULT => self.switch_to_workspace(&name),
+                Some(name) => self.popup = Some(Popup::Picker {
+                    title: format!("Workspace {}", name),
+                    items: vec!["Open".to_string(), "Rename…".to_string(), "Delete…".to_string()],
+                    selected: 0,
+                    target: PickTarget::WorkspaceAction { name },
+                }),
+                None => self.popup = Some(Popup::Prompt { title: "New workspace".to_string(), input: String::new(), target: PromptTarget::NewWorkspace }),
+            },
+            PickTarget::WorkspaceAction { name } => match selected {
+                0 => self.switch_to_workspace(&name),
+                1 => self.popup = Some(Popup::Prompt {
+                    title: format!("Rename workspace {}", name),
+                    input: name.clone(),
+                    target: PromptTarget::RenameWorkspace { name },
+                }),
+                _ => self.popup = Some(Popup::Confirm {
+                    message: format!("Delete workspace {} with its collection, environments and history?", name),
+                    on_yes: Confirmed::DeleteWorkspace { name },
+                    on_no: None,
+                }),
+            },
+            PickTarget::Theme => {
+                let preset = Preset::ALL[selected];
+                self.theme_preset = preset;
+                self.preview_theme(preset);
+                let saved = config::load().and_then(|mut config| {
+                    config.theme = preset;
+                    config::save(&config)
+                });
+                if let Err(e) = saved {
+                    self.error_message = Some(format!("Couldn't remember the theme: {}", e).into());
+                }
+            }
+            PickTarget::RecentError => {
+                if let Some(error) = self.recent_errors.iter().rev().nth(selected).cloned() {
+                    self.show_error(&error);
+                }
+            }
+            PickTarget::Snippet => {
+                if let Some(&language) = Language::ALL.get(selected) {
+                    self.export_snippet(language);
+                }
+            }
+            PickTarget::RevealSecrets => {
+                let Some(view) = SecretView::ALL.get(selected) else { return };
+                if !self.revealed.remove(view) {
+                    self.revealed.insert(*view);
+                }
+            }
+            PickTarget::HistoryEnvironment { environments } => {
+                self.history_environment = selected.checked_sub(1).and_then(|i| environments.into_iter().nth(i));
+            }
+            PickTarget::TagFilter { tags } => {
+                self.tag_filter = selected.checked_sub(1).and_then(|i| tags.into_iter().nth(i));
+                self.clamp_collection_selection();
+            }
+            PickTarget::MoveRequest { index } => {
+                let folder = selected.checked_sub(1).and_then(|i| self.collection.folders.get(i)).cloned().unwrap_or_default();
+                let saved = &self.collection.requests[index];
+                let old = (saved.folder.clone(), saved.name.clone());
+                if folder != old.0 && self.collection.position(&folder, &old.1).is_some() {
+                    self.error_message = Some(format!("{} already holds a request called {}", folder_label(&folder), old.1).into());
+                    return;
+                }
+                self.collection.move_request(index, folder.clone());
+                self.rekey_saved(&old, (folder, old.1.clone()));
+                self.save_collection();
+            }
+        }
+    }
+
+    fn submit_prompt(&mut self, target: PromptTarget, input: String) {
+        match target {
+            PromptTarget::ConnectTo => {
+                let input = input.trim();
+                self.connect_to = (!input.is_empty()).then(|| input.to_string());
+            }
+            PromptTarget::SaveRequest => {
+                let name = input.trim();
+                if name.is_empty() {
+                    self.error_message = Some("A saved request needs a name".to_string().into());
+                    return;
+                }
+                let folder = self.selected_folder();
+                self.collection.save_request(folder.clone(), name.to_string(), self.current_request(), Utc::now().timestamp());
+                self.loaded_request = Some((folder, name.to_string()));
+                self.save_collection();
+            }
+            PromptTarget::NewFolder { mut parent } => {
+                let name = input.trim();
+                if name.is_empty() {
+                    return;
+                }
+                parent.push(name.to_string());
+                self.collection.add_folder(&parent);
+                self.save_collection();
+            }
+            PromptTarget::RenameFolder { path } => {
+                let name = input.trim();
+                if name.is_empty() {
+                    return;
+                }
+                match self.collection.rename_folder(&path, name.to_string()) {
+                    Ok(()) => {
+                        let renamed = [&path[..path.len() - 1], &[name.to_string()]].concat();
+                        self.collapsed_folders = std::mem::take(&mut self.collapsed_folders)
+                            .into_iter()
+                            .map(|folder| if folder.starts_with(&path) {[renamed.as_slice(), &folder[path.len()..]].concat()} else {folder})
+                            .collect();
+                        self.save_collection();
+                    }
+                    Err(e) => self.error_message = Some(e.into()),
+                }
+            }
+            PromptTarget::RenameRequest { index } => self.rename_request(index, input.trim()),
+            PromptTarget::Tags { index } => {
+                let mut tags: Vec<String> = vec![];
+                for tag in input.split([',', ' ']).map(str::trim).filter(|tag| !tag.is_empty()) {
+                    if !tags.iter().any(|own| own == tag) {
+                        tags.push(tag.to_string());
+                    }
+                }
+                if let Some(saved) = self.collection.requests.get_mut(index) {
+                    saved.tags = tags;
+                }
+                if self.tag_filter.as_ref().is_some_and(|tag| !self.collection.tags().contains(tag)) {
+                    self.tag_filter = None;
+                }
+                self.clamp_collection_selection();
+                self.save_collection();
+                self.popup = Some(Popup::Details { index, scroll: 0 });
+            }
+            PromptTarget::ImportPostman => self.import_postman(input.trim()),
+            PromptTarget::ImportInsomnia => self.import_insomnia(input.trim()),
+            PromptTarget::ExportPostman => self.export_postman(input.trim()),
+            PromptTarget::ImportRequestFile => self.import_plaintext(input.trim()),
+            PromptTarget::ExportPlaintext { format, folder } => self.export_plaintext(input.trim(), format, &folder),
+            PromptTarget::GoToLine => self.go_to_line(input.trim()),
+            PromptTarget::MarkdownRange => match history_range(input.trim(), self.history.len()) {
+                Ok(range) => self.ask_markdown_destination(self.history[range].iter().map(|entry| entry.id).collect()),
+                Err(e) => self.error_message = Some(e.into()),
+            },
+            PromptTarget::MarkdownDestination { ids } => self.export_markdown(&ids, input.trim()),
+            PromptTarget::SaveExchange { id } => self.save_exchange(id, input.trim()),
+            PromptTarget::SearchHistory => self.search_history(input.trim()),
+            PromptTarget::NewWorkspace => match workspace::create(input.trim()) {
+                Ok(()) => self.switch_to_workspace(input.trim()),
+                Err(e) => self.error_message = Some(format!("Couldn't create the workspace: {}", e).into()),
+            },
+            PromptTarget::RenameWorkspace { name } => {
+                if let Err(e) = workspace::rename(&name, input.trim()) {
+                    self.error_message = Some(format!("Couldn't rename workspace {}: {}", name, e).into());
+                }
+            }
+            PromptTarget::Passphrase { change: false } => {
+                if let Err(e) = self.unlock(&input) {
+                    self.error_message = Some(format!("Couldn't unlock secrets: {}; they stay locked", e).into());
+                }
+            }
+            PromptTarget::Passphrase { change: true } => {
+                if let Err(e) = self.change_passphrase(&input) {
+                    self.error_message = Some(format!("Couldn't change the passphrase: {}", e).into());
+                }
+            }
+            PromptTarget::LoadComparison => match fs::read(input.trim()) {
+                Ok(bytes) => {
+                    self.comparison = Some((input.trim().to_string(), exchange::read_response(&bytes)));
+                    self.show_comparison();
+                }
+                Err(e) => self.error_message = Some(format!("Couldn't read {}: {}", input.trim(), e).into()),
+            },
+            PromptTarget::LoadPlan => self.plan_load(input.trim()),
+            PromptTarget::RunSummary => self.save_run_summary(input.trim()),
+            PromptTarget::StatsCsv => self.save_stats_csv(input.trim()),
+            PromptTarget::CorsCheck => self.cors_check(input.trim()),
+            // Submitted by its own key handling, which keeps the prompt open on a bad pattern.
+            PromptTarget::Search { .. } => {}
+            PromptTarget::RedirectPolicy => self.set_redirect_policy(input.trim()),
+            PromptTarget::ServerFault(fault) => {
+                let mut faults = my_test_server::faults();
+                let input = input.trim().trim_end_matches(['%', 's', 'm']).trim();
+                match (fault, input.parse::<u64>()) {
+                    (ServerFault::Delay, Ok(delay_ms)) if delay_ms <= 60_000 => faults.delay_ms = delay_ms,
+                    (ServerFault::FailRate, Ok(percent)) if percent <= 100 => faults.fail_percent = percent as u8,
+                    _ => self.error_message = Some(format!("Expected {}: {}", if fault == ServerFault::Delay {"0 to 60000 ms"} else {"0 to 100 percent"}, input).into()),
+                }
+                my_test_server::set_faults(faults);
+                let selected = ServerFault::ALL.iter().position(|f| *f == fault).unwrap_or(0);
+                self.popup = Some(Popup::ServerControl { selected });
+            }
+            PromptTarget::RequestSetting(setting) => {
+                match (setting, input.trim()) {
+                    (RequestSetting::Timeout, "") => self.request_timeout = None,
+                    (RequestSetting::Timeout, input) => match input.trim_end_matches('s').parse() {
+                        Ok(seconds) if seconds > 0 => self.request_timeout = Some(seconds),
+                        _ => self.error_message = Some(format!("Not a number of seconds: {}", input).into()),
+                    },
+                    (RequestSetting::Redirects, input) => self.set_redirect_policy(input),
+                    (RequestSetting::RequestId, "") => self.request_id = None,
+                    (RequestSetting::RequestId, input) => match input.to_ascii_lowercase().as_str() {
+                        "on" | "yes" | "true" => self.request_id = Some(true),
+                        "off" | "no" | "false" => self.request_id = Some(false),
+                        _ => self.error_message = Some(format!("Expected on or off: {}", input).into()),
+                    },
+                    (RequestSetting::BodySchema, "") => self.body_schema = None,
+                    (RequestSetting::BodySchema, input) => match BodySchema::parse(input) {
+                        Ok(schema) => self.body_schema = Some(schema),
+                        Err(e) => self.error_message = Some(e.into()),
+                    },
+                    (RequestSetting::ResponseSchema, "") => self.response_schema = None,
+                    (RequestSetting::ResponseSchema, input) if input.starts_with('#') => {
+                        self.error_message = Some(format!("Expected a schema file: {}", input).into());
+                    }
+                    (RequestSetting::ResponseSchema, input) => self.response_schema = Some(input.to_string()),
+                    (RequestSetting::MaxResponse, "") => self.max_response_mb = None,
+                    (RequestSetting::MaxResponse, input) => match input.trim_end_matches("MB").trim().parse() {
+                        Ok(mb) => self.max_response_mb = Some(mb),
+                        Err(_) => self.error_message = Some(format!("Not a number of MB: {}", input).into()),
+                    },
+                    (RequestSetting::ExpectContinue, "" | "off") => self.expect_continue = None,
+                    (RequestSetting::ExpectContinue, input) => match ExpectContinue::parse(input) {
+                        Ok(expect) => self.expect_continue = Some(expect),
+                        Err(e) => self.error_message = Some(e.into()),
+                    },
+                    (RequestSetting::Retries, "") => self.request_max_retries = None,
+                    (RequestSetting::Retries, input) => match input.parse() {
+                        Ok(retries) => self.request_max_retries = Some(retries),
+                        Err(_) => self.error_message = Some(format!("Not a number of retries: {}", input).into()),
+                    },
+                }
+                let selected = RequestSetting::ALL.iter().position(|s| *s == setting).unwrap_or(0);
+                self.popup = Some(Popup::RequestSettings { selected });
+            }
+            PromptTarget::Setting { index, mut config, mut changed } => {
+                if let Edit::Text(set) = SETTINGS[index].edit {
+                    match set(&mut config, input.trim()) {
+                        Ok(()) => changed = true,
+                        Err(e) => self.error_message = Some(format!("{}: {}", SETTINGS[index].name, e).into()),
+                    }
+                }
+                self.popup = Some(Popup::Settings { config, selected: index, changed });
+            }
+            PromptTarget::LoadCsv => {
+                if let Some(run) = &self.load {
+                    if let Err(e) = fs::write(input.trim(), run.csv()) {
+                        self.error_message = Some(format!("Couldn't export to {}: {}", input.trim(), e).into());
+                    }
+                }
+                self.popup = Some(Popup::Load);
+            }
+        }
+    }
+
+    fn handle_popup_key(&mut self, key: KeyEvent) {
+        let Some(popup) = self.popup.as_mut() else { return };
+        match popup {
+            Popup::Palette { filter, selected } => match key.code {
+                KeyCode::Esc => self.popup = None,
+                KeyCode::Enter => {
+                    let action = Action::matching(filter).get(*selected).copied();
+                    self.popup = None;
+                    if let Some(action) = action {
+                        self.perform(action);
+                    }
+                }
+                KeyCode::Up => *selected = selected.saturating_sub(1),
+                KeyCode::Down => *selected = (*selected + 1).min(Action::matching(filter).len().saturating_sub(1)),
+                KeyCode::Backspace => {
+                    filter.pop();
+                    *selected = 0;
+                }
+                KeyCode::Char(c) => {
+                    filter.push(c);
+                    *selected = 0;
+                }
+                _ => {}
+            },
+            Popup::Text { scroll, .. } => match key.code {
+                KeyCode::Esc | KeyCode::Enter => self.popup = None,
+                KeyCode::Up => *scroll = scroll.saturating_sub(1),
+                KeyCode::Down => *scroll = scroll.saturating_add(1),
+                _ => {}
+            },
+            Popup::Details { index, scroll } => match key.code {
+                KeyCode::Esc | KeyCode::Enter => self.popup = None,
+                KeyCode::Up => *scroll = scroll.saturating_sub(1),
+                KeyCode::Down => *scroll = scroll.saturating_add(1),
+                KeyCode::Char('e') => {
+                    let index = *index;
+                    let text = self.collection.requests[index].description.clone().unwrap_or_default();
+                    self.popup = Some(Popup::Description { index, text });
+                }
+                KeyCode::Char('t') => {
+                    let index = *index;
+                    self.popup = Some(Popup::Prompt {
+                        title: "Tags, separated by commas or spaces".to_string(),
+                        input: self.collection.requests[index].tags.join(", "),
+                        target: PromptTarget::Tags { index },
+                    });
+                }
+                _ => {}
+            },
+            Popup::Confirm { .. } => match key.code {
+                KeyCode::Char('y') | KeyCode::Char('Y') => {
+                    if let Some(Popup::Confirm { on_yes, .. }) = self.popup.take() {
+                        self.confirm(on_yes);
+                    }
+                }
+                KeyCode::Char('n') | KeyCode::Char('N') => {
+                    if let Some(Popup::Confirm { on_no: Some(on_no), .. }) = self.popup.take() {
+                        self.confirm(on_no);
+                    }
+                }
+                KeyCode::Esc => self.popup = None,
+                _ => {}
+            },
+            Popup::Picker { items, selected, target, .. } => match key.code {
+                KeyCode::Esc => {
+                    let previewing = matches!(target, PickTarget::Theme);
+                    self.popup = None;
+                    if previewing {
+                        self.preview_theme(self.theme_preset);
+                    }
+                }
+                KeyCode::Up | KeyCode::Down => {
+                    *selected = match key.code {
+                        KeyCode::Up => selected.saturating_sub(1),
+                        _ => (*selected + 1).min(items.len().saturating_sub(1)),
+                    };
+                    if matches!(target, PickTarget::Theme) {
+                        let preset = Preset::ALL[*selected];
+                        self.preview_theme(preset);
+                    }
+                }
+                KeyCode::Enter => {
+                    if let Some(Popup::Picker { selected, target, .. }) = self.popup.take() {
+                        self.pick(target, selected);
+                    }
+                }
+                KeyCode::Char('*') => {
+                    let PickTarget::HistoryEntry { ids } = target else { return };
+                    let Some(&id) = ids.get(*selected) else { return };
+                    let item = &mut items[*selected];
+                    *item = match item.strip_prefix("★ ") {
+                        Some(rest) => rest.to_string(),
+                        None => format!("★ {}", item),
+                    };
+                    self.toggle_pin(id);
+                }
+                _ => {}
+            },
+            Popup::Settings { config, selected, changed } => match key.code {
+                KeyCode::Esc => self.popup = None,
+                KeyCode::Up => *selected = selected.saturating_sub(1),
+                KeyCode::Down => *selected = (*selected + 1).min(SETTINGS.len() - 1),
+                KeyCode::Enter => match SETTINGS[*selected].edit {
+                    Edit::Toggle(toggle) => {
+                        toggle(config);
+                        *changed = true;
+                    }
+                    Edit::Text(_) => {
+                        if let Some(Popup::Settings { config, selected, changed }) = self.popup.take() {
+                            let setting = &SETTINGS[selected];
+                            self.popup = Some(Popup::Prompt {
+                                title: format!("{}: {}", setting.name, setting.help),
+                                input: (setting.get)(&config),
+                                target: PromptTarget::Setting { index: selected, config, changed },
+                            });
+                        }
+                    }
+                },
+                KeyCode::Char('s') => {
+                    if let Some(Popup::Settings { config, .. }) = self.popup.take() {
+                        self.save_settings(&config);
+                    }
+                }
+                _ => {}
+            },
+            Popup::ServerControl { selected } => match (key.code, ServerFault::ALL[*selected]) {
+                (KeyCode::Esc, _) => self.popup = None,
+                (KeyCode::Up, _) => *selected = selected.saturating_sub(1),
+                (KeyCode::Down, _) => *selected = (*selected + 1).min(ServerFault::ALL.len() - 1),
+                (KeyCode::Enter | KeyCode::Char(' '), fault @ (ServerFault::Stall | ServerFault::StallBody | ServerFault::CutBody)) => {
+                    let mut faults = my_test_server::faults();
+                    let toggled = match fault {
+                        ServerFault::Stall => &mut faults.stall,
+                        ServerFault::StallBody => &mut faults.stall_body,
+                        _ => &mut faults.cut_body,
+                    };
+                    *toggled = !*toggled;
+                    my_test_server::set_faults(faults);
+                }
+                (KeyCode::Enter, fault) => {
+                    let faults = my_test_server::faults();
+                    let input = match fault {
+                        ServerFault::Delay => faults.delay_ms.to_string(),
+                        _ => faults.fail_percent.to_string(),
+                    };
+                    self.popup = Some(Popup::Prompt {
+                        title: format!("Test server {} ({})", fault.name(), if fault == ServerFault::Delay {"milliseconds"} else {"percent of requests"}),
+                        input,
+                        target: PromptTarget::ServerFault(fault),
+                    });
+                }
+                (KeyCode::Char('0'), _) => my_test_server::set_faults(TestServerConfig::default()),
+                (KeyCode::Char('s'), _) => {
+                    let faults = my_test_server::faults();
+                    let saved = config::load().and_then(|mut config| {
+                        config.test_server = faults;
+                        config::save(&config)
+                    });
+                    if let Err(e) = saved {
+                        self.error_message = Some(format!("Couldn't save the config: {}", e).into());
+                    }
+                }
+                _ => {}
+            },
+            Popup::RequestSettings { selected } => match key.code {
+                KeyCode::Esc => self.popup = None,
+                KeyCode::Up => *selected = selected.saturating_sub(1),
+                KeyCode::Down => *selected = (*selected + 1).min(RequestSetting::ALL.len() - 1),
+                KeyCode::Enter => {
+                    let setting = RequestSetting::ALL[*selected];
+                    self.edit_request_setting(setting);
+                }
+                KeyCode::Char('d') | KeyCode::Delete => match RequestSetting::ALL[*selected] {
+                    RequestSetting::Timeout => self.request_timeout = None,
+                    RequestSetting::Redirects => self.redirect_policy = None,
+                    RequestSetting::Retries => self.request_max_retries = None,
+                    RequestSetting::RequestId => self.request_id = None,
+                    RequestSetting::BodySchema => self.body_schema = None,
+                    RequestSetting::ResponseSchema => self.response_schema = None,
+                    RequestSetting::ExpectContinue => self.expect_continue = None,
+                    RequestSetting::MaxResponse => self.max_response_mb = None,
+                },
+                _ => {}
+            },
+            Popup::Validators { selected } => match key.code {
+                KeyCode::Esc | KeyCode::Enter => self.popup = None,
+                KeyCode::Up => *selected = selected.saturating_sub(1),
+                KeyCode::Down => *selected = (*selected + 1).min(self.validators.len().saturating_sub(1)),
+                KeyCode::Char('d') | KeyCode::Delete => {
+                    if let Some(url) = self.validators.keys().nth(*selected).cloned() {
+                        self.validators.remove(&url);
+                        *selected = (*selected).min(self.validators.len().saturating_sub(1));
+                    }
+                }
+                KeyCode::Char('C') => self.validators.clear(),
+                _ => {}
+            },
+            Popup::Prompt { input, target: PromptTarget::Search { regex, error }, .. } => match key.code {
+                KeyCode::Esc => self.popup = None,
+                KeyCode::Char('r') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    *regex = !*regex;
+                    *error = None;
+                }
+                KeyCode::Enter => {
+                    // A bad pattern stays in the prompt with its error, and the previous search stands.
+                    if let Err(e) = regex.then(|| Pattern::regex(input)).transpose() {
+                        *error = Some(e);
+                        return;
+                    }
+                    self.search_query = input.clone();
+                    self.search_regex = *regex;
+                    self.popup = None;
+                    self.search = None;
+                    self.sync_search();
+                }
+                KeyCode::Backspace => {
+                    input.pop();
+                }
+                KeyCode::Char(c) => input.push(c),
+                _ => {}
+            },
+            Popup::Prompt { input, .. } => match key.code {
+                KeyCode::Esc => self.popup = None,
+                KeyCode::Enter => {
+                    if let Some(Popup::Prompt { input, target, .. }) = self.popup.take() {
+                        self.submit_prompt(target, input);
+                    }
+                }
+                KeyCode::Backspace => {
+                    input.pop();
+                }
+                KeyCode::Char(c) => input.push(c),
+                _ => {}
+            },
+            Popup::Description { index, text } => match key.code {
+                KeyCode::Esc => self.popup = None,
+                KeyCode::Char('s') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    let description = Some(text.trim_end().to_string()).filter(|text| !text.is_empty());
+                    if let Some(saved) = self.collection.requests.get_mut(*index) {
+                        saved.description = description;
+                    }
+                    self.popup = None;
+                    self.save_collection();
+                }
+                KeyCode::Enter => text.push('\n'),
+                KeyCode::Backspace => {
+                    text.pop();
+                }
+                KeyCode::Char(c) => text.push(c),
+                _ => {}
+            },
+            Popup::Mocks { selected } => match key.code {
+                KeyCode::Esc => self.popup = None,
+                KeyCode::Up => *selected = selected.saturating_sub(1),
+                KeyCode::Down => *selected = (*selected + 1).min(self.collection.mocks.len().saturating_sub(1)),
+                KeyCode::Char('n') => self.popup = Some(Popup::MockEditor {
+                    index: None,
+                    text: "GET /mock 200\nContent-Type: application/json\n\n{}".to_string(),
+                    error: None,
+                }),
+                KeyCode::Enter => {
+                    if let Some(existing) = self.collection.mocks.get(*selected) {
+                        self.popup = Some(Popup::MockEditor { index: Some(*selected), text: mock::to_text(existing), error: None });
+                    }
+                }
+                KeyCode::Char('d') | KeyCode::Delete if *selected < self.collection.mocks.len() => {
+                    self.collection.mocks.remove(*selected);
+                    *selected = (*selected).min(self.collection.mocks.len().saturating_sub(1));
+                    mock::set(&self.collection.mocks);
+                    self.save_collection();
+                }
+                _ => {}
+            },
+            Popup::MockEditor { index, text, error } => match key.code {
+                KeyCode::Esc => self.popup = Some(Popup::Mocks { selected: index.unwrap_or(0) }),
+                KeyCode::Char('s') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    let index = *index;
+                    let parsed = mock::parse(text).and_then(|parsed| {
+                        let taken = self.collection.mocks.iter().enumerate()
+                            .any(|(i, other)| Some(i) != index && other.method == parsed.method && other.path == parsed.path);
+                        if taken {Err(format!("There's already a mock for {} {}", parsed.method, parsed.path))} else {Ok(parsed)}
+                    });
+                    match parsed {
+                        Ok(parsed) => self.save_mock(index, parsed),
+                        Err(e) => *error = Some(e),
+                    }
+                }
+                KeyCode::Enter => text.push('\n'),
+                KeyCode::Backspace => {
+                    text.pop();
+                }
+                KeyCode::Char(c) => text.push(c),
+                _ => {}
+            },
+            Popup::BulkParams { text, errors } => match key.code {
+                KeyCode::Esc => self.popup = None,
+                KeyCode::Char('s') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    let text = text.clone();
+                    match self.apply_bulk_params(&text) {
+                        Ok(()) => self.popup = None,
+                        Err(found) => {
+                            if let Some(Popup::BulkParams { errors, .. }) = &mut self.popup {
+                                *errors = found;
+                            }
+                        }
+                    }
+                }
+                // The errors were for the text as it was applied, so they go once it changes.
+                KeyCode::Enter => {
+                    text.push('\n');
+                    errors.clear();
+                }
+                KeyCode::Backspace => {
+                    text.pop();
+                    errors.clear();
+                }
+                KeyCode::Char(c) => {
+                    text.push(c);
+                    errors.clear();
+                }
+                _ => {}
+            },
+            Popup::Load => match (key.code, &self.load) {
+                (KeyCode::Esc, Some(run)) if !run.is_finished() => run.stop(),
+                (KeyCode::Esc | KeyCode::Enter, _) => self.popup = None,
+                (KeyCode::Left, _) => self.load_bucket = self.load_bucket.saturating_sub(1),
+                (KeyCode::Right, _) => self.load_bucket = (self.load_bucket + 1).min(load::HISTOGRAM_BUCKETS - 1),
+                (KeyCode::Char('e'), Some(_)) => self.popup = Some(Popup::Prompt {
+                    title: "Save latencies as CSV to".to_string(),
+                    input: "latencies.csv".to_string(),
+                    target: PromptTarget::LoadCsv,
+                }),
+                _ => {}
+            },
+            Popup::Run { selected } => match (key.code, &mut self.run) {
+                (KeyCode::Esc, Some(run)) if !run.is_finished() => {
+                    run.stop("Stopped with Esc".to_string());
+                    self.advance_run();
+                }
+                (KeyCode::Esc, _) | (KeyCode::Char('h'), _) => self.popup = None,
+                (KeyCode::Up, _) => *selected = selected.saturating_sub(1),
+                (KeyCode::Down, Some(run)) => *selected = (*selected + 1).min(run.legs.len().saturating_sub(1)),
+                (KeyCode::Enter, Some(run)) => {
+                    let id = run.legs.get(*selected).and_then(|leg| leg.id);
+                    self.show_run_exchange(id);
+                }
+                (KeyCode::Char('m'), Some(run)) if run.is_finished() => self.popup = Some(Popup::Prompt {
+                    title: "Save the run summary as Markdown to (empty to copy it)".to_string(),
+                    input: "run-summary.md".to_string(),
+                    target: PromptTarget::RunSummary,
+                }),
+                (KeyCode::Char('r'), Some(run)) if run.is_finished() => {
+                    let again = run.failed_again(Local::now().to_rfc3339());
+                    match again.legs.is_empty() {
+                        true => self.error_message = Some("Nothing failed, so there's nothing to run again".to_string().into()),
+                        false => self.begin_run(again),
+                    }
+                }
+                _ => {}
+            },
+            Popup::Stats { selected, sort } => match key.code {
+                KeyCode::Esc => self.popup = None,
+                KeyCode::Up => *selected = selected.saturating_sub(1),
+                KeyCode::Down => *selected = (*selected + 1).min(stats::rollup(self.history.iter(), *sort).len().saturating_sub(1)),
+                KeyCode::Char('s') => *sort = sort.next(),
+                KeyCode::Char('c') => self.popup = Some(Popup::Prompt {
+                    title: "Save the statistics as CSV to (empty to copy them)".to_string(),
+                    input: "endpoints.csv".to_string(),
+                    target: PromptTarget::StatsCsv,
+                }),
+                KeyCode::Enter => {
+                    let endpoints = stats::rollup(self.history.iter(), *sort);
+                    let Some(endpoint) = endpoints.get(*selected) else { return };
+                    self.endpoint_filter = Some((endpoint.method.clone(), endpoint.url.clone()));
+                    self.popup = None;
+                    self.group_selected = 0;
+                    self.focus = Pane::Responses;
+                }
+                _ => {}
+            },
+            Popup::Finder { query, selected } => match key.code {
+                KeyCode::Esc => self.popup = None,
+                KeyCode::Enter => {
+                    let index = self.collection.find(query, self.tag_filter.as_deref()).get(*selected).map(|found| found.index);
+                    self.popup = None;
+                    if let Some(index) = index {
+                        self.load_saved(index);
+                    }
+                }
+                KeyCode::Up => *selected = selected.saturating_sub(1),
+                KeyCode::Down => *selected = (*selected + 1).min(self.collection.find(query, self.tag_filter.as_deref()).len().saturating_sub(1)),
+                KeyCode::Backspace => {
+                    query.pop();
+                    *selected = 0;
+                }
+                KeyCode::Char(c) => {
+                    query.push(c);
+                    *selected = 0;
+                }
+                _ => {}
+            },
+        }
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) {
+        if self.popup.is_some() {
+            self.handle_popup_key(key);
+            return;
+        }
+        if let Some(action) = self.keymap.action_for(&key) {
+            self.perform(action);
+            return;
+        }
+        let in_header_table = self.header_table.is_some() && self.focus_target() == Focus::Headers;
+        if matches!(key.code, KeyCode::Tab | KeyCode::BackTab) && !in_header_table {
+            let next = self.focus_target().cycle(key.code == KeyCode::Tab, self.show_collection);
+            return self.set_focus(next);
+        }
+        match self.focus {
+            Pane::Editor => {}
+            Pane::Collection => return self.handle_collection_key(key),
+            Pane::Responses => return self.handle_responses_key(key),
+        }
+        if self.header_table.is_some() && *self.get_input_mode() == InputMode::EditingHeaders {
+            return self.handle_header_table_key(key);
+        }
+        match key.code {
+            KeyCode::PageUp if self.show_console => {
+                self.console_scroll = (self.console_scroll + 10).min(self.console.len());
+            }
+            KeyCode::PageDown if self.show_console => self.console_scroll = self.console_scroll.saturating_sub(10),
+            KeyCode::Enter => self.send_req(false),
+            KeyCode::Down if key.modifiers.contains(KeyModifiers::CONTROL) && *self.get_input_mode() == InputMode::EditingUrl => {
+                self.show_recent_urls();
+            }
+            // The method is picked with the arrows; there's no text there to edit.
+            KeyCode::Char(_) | KeyCode::Backspace if *self.get_input_mode() == InputMode::EditingMethod => {}
+            KeyCode::Char('u') if key.modifiers.contains(KeyModifiers::CONTROL) => self.kill_line(false),
+            KeyCode::Char('k') if key.modifiers.contains(KeyModifiers::CONTROL) => self.kill_line(true),
+            KeyCode::Char('y') if key.modifiers.contains(KeyModifiers::CONTROL) => self.yank(),
+            KeyCode::Char(to_insert) => self.enter_char(to_insert),
+            KeyCode::Backspace => self.delete_char(),
+            KeyCode::Left => self.move_cursor_left(),
+            KeyCode::Right => self.move_cursor_right(),
+            KeyCode::Down => self.move_input_mode_down(),
+            KeyCode::Up => self.move_input_mode_up(),
+            _ => {},
+        }
+    }
+
+    pub fn handle_event(&mut self, event: AppEvent) {
+        match event {
+            AppEvent::Key(key) => self.handle_key(key),
+            AppEvent::Paste(text) => self.paste(&text),
+            // Layout is recomputed from the frame size on every draw.
+            AppEvent::Resize => {}
+            AppEvent::Worker(event) => self.handle_worker_event(event),
+            AppEvent::Tick(now) => {
+                self.check_body(now);
+                self.check_url(now);
+                self.autosave(now);
+                if self.rate_limit.as_ref().is_some_and(|limit| limit.scheduled.is_none() && limit.until <= now) {
+                    self.rate_limit = None;
+                }
+                if let Some(run) = &mut self.load {
+                    run.collect();
+                }
+                self.expire_requests(now);
+                self.advance_run();
+                if let Some((_, search)) = &mut self.search {
+                    search.collect();
+                }
+                for hit in mock::take_hits() {
+                    self.log(hit);
+                }
+                while let Ok(received) = self.traffic.try_recv() {
+                    if self.server_traffic.len() == TRAFFIC_CAPACITY {
+                        self.server_traffic.pop_front();
+                    }
+                    self.server_traffic.push_back(received);
+                }
+                self.sync_search();
+            }
+        }
+        self.record_error();
+    }
+
+    /// Adds the error being shown to the recent errors, once.
+    fn record_error(&mut self) {
+        let Some(error) = &self.error_message else { return };
+        if self.recent_errors.back().is_some_and(|last| last.at == error.at && last.summary == error.summary) {
+            return;
+        }
+        if self.recent_errors.len() == app_error::RECENT_ERRORS {
+            self.recent_errors.pop_front();
+        }
+        self.recent_errors.push_back(error.clone());
+    }
+
+    /// The error in full: when, what kind, the chain of messages it wraps, detail and hint.
+    fn show_error(&mut self, error: &AppError) {
+        let theme = &self.theme;
+        let mut lines = vec![Line::styled(format!("{}  {}", error.at.format("%H:%M:%S%.3f"), error.kind.label()), theme.dim)];
+        let chain = error.chain();
+        lines.push(Line::styled(chain[0].to_string(), theme.error));
+        for (depth, cause) in chain.iter().enumerate().skip(1) {
+            lines.push(Line::from(format!("{}caused by: {}", "  ".repeat(depth), cause)));
+        }
+        if let Some(detail) = &error.detail {
+            lines.push(Line::from(""));
+            lines.extend(detail.lines().map(|line| Line::from(line.to_string())));
+        }
+        if let Some(hint) = &error.hint {
+            lines.push(Line::from(""));
+            lines.push(Line::from(vec![Span::styled("Hint: ", theme.key), Span::raw(hint.clone())]));
+        }
+        if let Some(key) = self.keymap.binding_for(Action::RetryLastFailure).filter(|_| error.kind == ErrorKind::Network && self.last_failure.is_some()) {
+            lines.push(Line::from(""));
+            lines.push(Line::from(vec![Span::raw("Press "), Span::styled(key.to_string(), theme.key), Span::raw(" (or R in the responses pane) to retry it")]));
+        }
+        self.popup = Some(Popup::Text { title: "Error".to_string(), lines, scroll: 0 });
+    }
+
+    fn show_error_details(&mut self) {
+        match self.error_message.clone().or_else(|| self.recent_errors.back().cloned()) {
+            Some(error) => self.show_error(&error),
+            None => self.popup = Some(Popup::Text { title: "Error".to_string(), lines: vec![Line::from("No errors so far")], scroll: 0 }),
+        }
+    }
+
+    fn show_recent_errors(&mut self) {
+        if self.recent_errors.is_empty() {
+            self.popup = Some(Popup::Text { title: "Recent errors".to_string(), lines: vec![Line::from("No errors so far")], scroll: 0 });
+            return;
+        }
+        let items = self.recent_errors
+            .iter()
+            .rev()
+            .map(|error| format!("{}  {:<8} {}", error.at.format("%H:%M:%S"), error.kind.label(), error.summary))
+            .collect();
+        self.popup = Some(Popup::Picker { title: "Recent errors".to_string(), items, selected: 0, target: PickTarget::RecentError });
+    }
+
+    /// Gives up on requests past their timeout. The worker stops at the same deadline; whatever
+    /// it reports after this is dropped.
+    fn expire_requests(&mut self, now: Instant) {
+        let expired: Vec<(u64, u64)> = self.deadlines.iter()
+            .filter(|(_, (deadline, _))| *deadline <= now)
+            .map(|(id, (_, seconds))| (*id, *seconds))
+            .collect();
+        for (id, seconds) in expired {
+            self.deadlines.remove(&id);
+            let Some(entry) = self.history.iter_mut().rev().find(|entry| entry.id == id) else { continue };
+            if entry.is_pending() {
+                entry.error = Some(format!("Timed out: no full response within the {}s deadline", seconds));
+                self.in_flight = self.in_flight.saturating_sub(1);
+                if let Some(sent) = self.unanswered.remove(&id) {
+                    self.last_failure = Some((id, sent));
+                }
+            }
+        }
+    }
+
+    fn handle_header_table_key(&mut self, key: KeyEvent) {
+        let Some(mut table) = self.header_table.take() else { return };
+        let mut rows = header_table_rows(&self.headers_input, &table);
+        // The text may have changed under the table, e.g. by loading a request.
+        table.selected = table.selected.min(rows.len().saturating_sub(1));
+        let mut changed = false;
+        if let Some((cell, cursor)) = &mut table.editing {
+            match key.code {
+                KeyCode::Esc => {
+                    table.editing = None;
+                    if table.adding {
+                        table.adding = false;
+                        table.selected = table.selected.min(rows.len().saturating_sub(2));
+                    }
+                }
+                KeyCode::Enter | KeyCode::Tab => {
+                    let cell = cell.trim().to_string();
+                    let row = &mut rows[table.selected];
+                    match table.column {
+                        HeaderColumn::Name if cell.is_empty() || cell.contains(|c: char| c == ':' || c.is_whitespace()) => {
+                            self.error_message = Some("A header name can't be empty or have spaces or colons".to_string().into());
+                        }
+                        HeaderColumn::Name => {
+                            row.key = cell;
+                            table.adding = false;
+                            table.editing = None;
+                            changed = true;
+                        }
+                        HeaderColumn::Value => {
+                            row.value = Some(cell);
+                            table.editing = None;
+                            changed = true;
+                        }
+                    }
+                    // Tab carries on to the value, as when typing a new header.
+                    if key.code == KeyCode::Tab && table.editing.is_none() && table.column == HeaderColumn::Name {
+                        let value = row.value.clone().unwrap_or_default();
+                        table.column = HeaderColumn::Value;
+                        table.editing = Some((value.clone(), value.chars().count()));
+                    }
+                }
+                KeyCode::Char(c) => {
+                    let at = cell.char_indices().nth(*cursor).map_or(cell.len(), |(i, _)| i);
+                    cell.insert(at, c);
+                    *cursor += 1;
+                }
+                KeyCode::Backspace if *cursor > 0 => {
+                    *cursor -= 1;
+                    let at = cell.char_indices().nth(*cursor).map_or(cell.len(), |(i, _)| i);
+                    cell.remove(at);
+                }
+                KeyCode::Left => *cursor = cursor.saturating_sub(1),
+                KeyCode::Right => *cursor = (*cursor + 1).min(cell.chars().count()),
+                KeyCode::Home => *cursor = 0,
+                KeyCode::End => *cursor = cell.chars().count(),
+                _ => {}
+            }
+        } else {
+            match key.code {
+                KeyCode::Up if table.selected == 0 => self.move_input_mode_up(),
+                KeyCode::Up => table.selected -= 1,
+                KeyCode::Down if table.selected + 1 >= rows.len() => self.move_input_mode_down(),
+                KeyCode::Down => table.selected += 1,
+                KeyCode::Left => table.column = HeaderColumn::Name,
+                KeyCode::Right => table.column = HeaderColumn::Value,
+                KeyCode::Enter if !rows.is_empty() => {
+                    let row = &rows[table.selected];
+                    let cell = match table.column {
+                        HeaderColumn::Name => row.key.clone(),
+                        HeaderColumn::Value => row.value.clone().unwrap_or_default(),
+                    };
+                    table.editing = Some((cell.clone(), cell.chars().count()));
+                }
+                KeyCode::Enter | KeyCode::Char('n') => {
+                    let selected = if rows.is_empty() {0} else {table.selected + 1};
+                    table = HeaderTable { selected, column: HeaderColumn::Name, editing: Some((String::new(), 0)), adding: true };
+                }
+                KeyCode::Char('d') | KeyCode::Delete if !rows.is_empty() => {
+                    rows.remove(table.selected);
+                    table.selected = table.selected.min(rows.len().saturating_sub(1));
+                    changed = true;
+                }
+                KeyCode::Char(' ') => {
+                    if let Some(row) = rows.get_mut(table.selected) {
+                        row.enabled = !row.enabled;
+                        changed = true;
+                    }
+                }
+                _ => {}
+            }
+        }
+        if changed {
+            self.headers_input = kv::to_bulk(&rows, ": ");
+        }
+        self.header_table = Some(table);
+    }
+
+    /// Inserts pasted text into whatever takes typing: a popup's input, or the focused field.
+    fn paste(&mut self, text: &str) {
+        let single_line: String = text.chars().filter(|c| !matches!(c, '\r' | '\n')).collect();
+        match &mut self.popup {
+            Some(Popup::Palette { filter: input, .. } | Popup::Prompt { input, .. } | Popup::Finder { query: input, .. }) => {
+                input.push_str(&single_line);
+                return;
+            }
+            Some(Popup::BulkParams { text: input, .. } | Popup::Description { text: input, .. } | Popup::MockEditor { text: input, .. }) => {
+                input.push_str(&text.replace("\r\n", "\n"));
+                return;
+            }
+            Some(_) => return,
+            None => {}
+        }
+        if self.focus != Pane::Editor || *self.get_input_mode() == InputMode::EditingMethod {
+            return;
+        }
+        let editing_headers = *self.get_input_mode() == InputMode::EditingHeaders;
+        if let (Some(table), true) = (&mut self.header_table, editing_headers) {
+            match &mut table.editing {
+                Some((cell, cursor)) => {
+                    let at = cell.char_indices().nth(*cursor).map_or(cell.len(), |(i, _)| i);
+                    cell.insert_str(at, &single_line);
+                    *cursor += single_line.chars().count();
+                }
+                // Pasted header lines become rows at the end.
+                None => {
+                    let pasted = text.replace("\r\n", "\n");
+                    if !self.headers_input.is_empty() && !self.headers_input.ends_with('\n') {
+                        self.headers_input.push('\n');
+                    }
+                    self.headers_input.push_str(pasted.trim_end());
+                }
+            }
+            return;
+        }
+        if *self.get_input_mode() == InputMode::EditingUrl && curl::looks_like_curl(text) {
+            self.popup = Some(Popup::Confirm {
+                message: "Detected a curl command — import as request?".to_string(),
+                on_yes: Confirmed::ImportCurl(text.to_string()),
+                on_no: Some(Confirmed::PasteLiterally(text.to_string())),
+            });
+            return;
+        }
+        self.insert_pasted(text);
+    }
+
+    fn insert_pasted(&mut self, text: &str) {
+        let single_line: String = text.chars().filter(|c| !matches!(c, '\r' | '\n')).collect();
+        let text = if *self.get_input_mode() == InputMode::EditingUrl {single_line} else {text.replace("\r\n", "\n")};
+        let index = self.byte_index();
+        self.get_current_input_mut().insert_str(index, &text);
+        self.character_index += text.chars().count();
+        self.error_message = None;
+    }
+
+    /// e.g. `tui_postman — POST /api/users (staging)`.
+    fn title(&self) -> String {
+        let path = url::parse(&self.url_input).map_or(self.url_input.clone(), |url| url.path);
+        let environment = self.active_environment.map(|i| format!(" ({})", self.environments[i].name)).unwrap_or_default();
+        format!("tui_postman — {} {}{}", method_name(self.method_index), path, environment)
+    }
+
+    fn update_title(&mut self) -> Result<()> {
+        let Some(shown) = &self.terminal_title else { return Ok(()) };
+        let title = self.title();
+        if *shown != title {
+            title::set(&title)?;
+            self.terminal_title = Some(title);
+        }
+        Ok(())
+    }
+
+    pub fn run(mut self, mut terminal: DefaultTerminal) -> Result<()> {
+        loop {
+            let result = self.event_loop(&mut terminal);
+            for path in self.temp_files.drain(..) {
+                let _ = fs::remove_file(path);
+            }
+            if self.terminal_title.is_some() {
+                title::restore()?;
+            }
+            result?;
+            session::save(&config::session_path(), &self.session()).map_err(|e| eyre!("Couldn't save the session: {}", e))?;
+            // Everything in the draft is in the session now.
+            session::discard_draft(&config::drafts_path()).map_err(|e| eyre!("Couldn't delete the draft: {}", e))?;
+            let Some(name) = self.switch_workspace.take() else { return Ok(()) };
+            self = self.reopen(name);
+        }
+    }
+
+    /// The app as started in another workspace. If that fails to open, the same one is loaded
+    /// again with the error shown.
+    fn reopen(self, name: Option<String>) -> Self {
+        let App { worker, server_addr, .. } = self;
+        let error = workspace::open(name.as_deref().unwrap_or(workspace::DEFAULT)).err();
+        let mut app = Self::open(|_| worker, server_addr, true, false);
+        mock::set(&app.collection.mocks);
+        if let Some(e) = error {
+            app.error_message = Some(format!("Couldn't open the workspace: {}", e).into());
+        }
+        app
+    }
+
+    fn open_workspaces(&mut self) {
+        let names = match workspace::list() {
+            Ok(names) => names,
+            Err(e) => {
+                self.error_message = Some(format!("Couldn't list workspaces: {}", e).into());
+                return;
+            }
+        };
+        let names: Vec<String> = std::iter::once(workspace::DEFAULT.to_string()).chain(names).collect();
+        let active = workspace::active_name();
+        let mut items: Vec<String> = names.iter().map(|name| if *name == active {format!("{} (active)", name)} else {name.clone()}).collect();
+        items.push("New workspace…".to_string());
+        let selected = names.iter().position(|name| *name == active).unwrap_or_default();
+        self.popup = Some(Popup::Picker { title: "Workspaces".to_string(), items, selected, target: PickTarget::Workspace { names } });
+    }
+
+    /// Saves this workspace's session and loads the other's, once requests in flight are done.
+    fn switch_to_workspace(&mut self, name: &str) {
+        if self.in_flight > 0 {
+            self.error_message = Some("Wait for the requests in flight before switching workspaces".to_string().into());
+            return;
+        }
+        if name == workspace::active_name() {
+            self.error_message = Some(format!("Already in workspace {}", name).into());
+            return;
+        }
+        self.switch_workspace = Some((name != workspace::DEFAULT).then(|| name.to_string()));
+        self.quit_requested = true;
+    }
+
+    /// Stops the process until the shell resumes it. The worker thread carries on meanwhile and
+    /// its results wait in the channel.
+    fn suspend(&mut self, terminal: &mut DefaultTerminal) -> Result<()> {
+        self.suspend_requested = false;
+        if let Some(shown) = &mut self.terminal_title {
+            title::restore()?;
+            shown.clear();
+        }
+        suspend::suspend(terminal)?;
+        if self.terminal_title.is_some() {
+            title::save()?;
+        }
+        Ok(())
+    }
+
+    /// Writes the body to a temporary file named for its Content-Type, lets an external editor
+    /// have the terminal, and takes the file back as the body, as an undoable edit, if the
+    /// editor exits successfully.
+    fn edit_body_externally(&mut self, terminal: &mut DefaultTerminal) -> Result<()> {
+        self.external_edit_requested = false;
+        let extension = extension_for(header_value(&parse_headers(&self.headers_input), "Content-Type"));
+        let path = std::env::temp_dir().join(format!("tui_postman-body-{}.{}", std::process::id(), extension));
+        if let Err(e) = fs::write(&path, &self.body_input) {
+            self.error_message = Some(format!("Couldn't write {}: {}", path.display(), e).into());
+            return Ok(());
+        }
+        if let Some(shown) = &mut self.terminal_title {
+            title::restore()?;
+            shown.clear();
+        }
+        let edited = suspend::edit_file(terminal, &path);
+        if self.terminal_title.is_some() {
+            title::save()?;
+        }
+        let text = match edited {
+            Ok(true) => fs::read_to_string(&path).map_err(|e| format!("Couldn't read {} back: {}", path.display(), e)),
+            Ok(false) => Err("The editor exited with an error; the body is unchanged".to_string()),
+            Err(e) => Err(format!("Couldn't edit the body: {}", e)),
+        };
+        let _ = fs::remove_file(&path);
+        match text {
+            Ok(mut text) => {
+                // Editors end the file with a newline the body didn't have.
+                if text.ends_with('\n') && !self.body_input.ends_with('\n') {
+                    text.pop();
+                    if text.ends_with('\r') {
+                        text.pop();
+                    }
+                }
+                if text != self.body_input {
+                    self.set_focus(Focus::Body);
+                    self.replace_range(0, self.body_input.chars().count(), text);
+                }
+            }
+            Err(e) => self.error_message = Some(e.into()),
+        }
+        Ok(())
+    }
+
+    fn open_response(&mut self, terminal: &mut DefaultTerminal) -> Result<()> {
+        self.open_response_requested = false;
+        let Some(response) = self.latest_response() else {
+            self.error_message = Some("No response yet".to_string().into());
+            return Ok(());
+        };
+        let extension = extension_for(header_value(&response.headers, "Content-Type"));
+        let path = std::env::temp_dir().join(format!("tui_postman-response-{}-{}.{}", std::process::id(), self.temp_files.len(), extension));
+        if let Err(e) = fs::write(&path, &response.body) {
+            self.error_message = Some(format!("Couldn't write {}: {}", path.display(), e).into());
+            return Ok(());
+        }
+        self.temp_files.push(path.clone());
+        let command = self
+            .open_with
+            .clone()
+            .or_else(|| std::env::var("PAGER").ok().filter(|command| !command.trim().is_empty()))
+            .unwrap_or_else(|| "less -R".to_string());
+        if let Some(shown) = &mut self.terminal_title {
+            title::restore()?;
+            shown.clear();
+        }
+        let opened = suspend::open_file(terminal, &command, &path);
+        if self.terminal_title.is_some() {
+            title::save()?;
+        }
+        match opened {
+            Ok(true) => {}
+            Ok(false) => self.error_message = Some(format!("{} exited with an error", command).into()),
+            Err(e) => self.error_message = Some(format!("Couldn't open the response: {}", e).into()),
+        }
+        Ok(())
+    }
+
+    fn event_loop(&mut self, terminal: &mut DefaultTerminal) -> Result<()> {
+        loop {
+            if self.quit_requested {
+                return Ok(());
+            }
+            if self.suspend_requested {
+                self.suspend(terminal)?;
+            }
+            if self.external_edit_requested {
+                self.edit_body_externally(terminal)?;
+            }
+            if self.open_response_requested {
+                self.open_response(terminal)?;
+            }
+            self.update_title()?;
+            terminal.draw(|frame| self.draw(frame))?;
+
+            if event::poll(Duration::from_millis(50))? {
+                let event = match event::read()? {
+                    Event::Key(key) if key.kind == KeyEventKind::Press => Some(AppEvent::Key(key)),
+                    Event::Paste(text) => Some(AppEvent::Paste(text)),
+                    Event::Resize(..) => Some(AppEvent::Resize),
+                    _ => None,
+                };
+                if let Some(event) = event {
+                    self.handle_event(event);
+                }
+            }
+            while let Ok(event) = self.worker.events.try_recv() {
+                self.handle_event(AppEvent::Worker(event));
+            }
+            self.handle_event(AppEvent::Tick(Instant::now()));
+        }
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let header_rows = self.header_table.as_ref().map(|table| header_table_rows(&self.headers_input, table));
+        // The table's column titles and up to seven rows.
+        let header_height = header_rows.as_ref().map_or(3, |rows| (rows.len() as u16 + 1).clamp(2, 8) + 2);
+        let vertical = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Length(header_height),
+            Constraint::Min(1),
+            Constraint::Length(1),
+        ]);
+        let [method_area, url_area, header_area, big_area, status_area] = vertical.areas(frame.area());
+
+        let horizontal = Layout::horizontal([
+            Constraint::Min(1),
+            Constraint::Min(1),
+        ]);
+        let (collection_area, big_area) = if self.show_collection {
+            let [collection_area, big_area] = Layout::horizontal([Constraint::Length(36), Constraint::Min(1)]).areas(big_area);
+            (Some(collection_area), big_area)
+        } else {
+            (None, big_area)
+        };
+        let [body_area, response_area] = horizontal.areas(big_area);
+        let (response_area, console_area) = if self.show_console {
+            let [response_area, console_area] = Layout::vertical([Constraint::Percentage(60), Constraint::Percentage(40)]).areas(response_area);
+            (response_area, Some(console_area))
+        } else {
+            (response_area, None)
+        };
+        let (response_area, traffic_area) = if self.show_traffic {
+            let [response_area, traffic_area] = Layout::vertical([Constraint::Percentage(50), Constraint::Percentage(50)]).areas(response_area);
+            (response_area, Some(traffic_area))
+        } else {
+            (response_area, None)
+        };
+
+        let theme = &self.theme;
+        let focus = self.focus_target();
+        let focused = |target: Focus| focus == target;
+        let style = |focused: bool| if focused {theme.focused} else {theme.text};
+
+        let methods = Tabs::new(HTTP_METHODS.iter().map(|method| format!("{:.?}", method)))
+            .block(theme.block("Methods", focused(Focus::Method)))
+            .select(self.method_index)
+            .highlight_style(theme.selected)
+            .style(style(focused(Focus::Method)));
+        frame.render_widget(methods, method_area);
+
+        let masker = self.masker(SecretView::Editor);
+        let url_text = match self.url_input.is_empty() {
+            true => Text::styled(URL_PLACEHOLDER, theme.dim),
+            false => Text::raw(masker.text(&self.url_input)),
+        };
+        // Only once checked, so the mark doesn't flicker while typing.
+        let url_mark = match (&self.url_check.error, self.url_input.trim().is_empty() || self.url_check.checked != self.url_input) {
+            (_, true) => None,
+            (None, false) => Some(Line::styled(if theme.no_color {" valid "} else {" ✓ "}, theme.value)),
+            (Some(_), false) => Some(Line::styled(if theme.no_color {" invalid "} else {" ✗ "}, theme.error)),
+        };
+        let mut url_block = theme.block("Input", focused(Focus::Url));
+        if let Some(mark) = url_mark {
+            url_block = url_block.title_top(mark.right_aligned());
+        }
+        let url_input = Paragraph::new(url_text)
+            .style(style(focused(Focus::Url)))
+            .block(url_block);
+        frame.render_widget(url_input, url_area);
+
+        match (&self.header_table, &header_rows) {
+            (Some(table), Some(rows)) => self.draw_header_table(frame, header_area, table, rows, focused(Focus::Headers)),
+            _ => {
+                let headers: Vec<Line> = match self.headers_input.is_empty() {
+                    true => vec![Line::styled(HEADERS_PLACEHOLDER, theme.dim)],
+                    false => self.headers_input.lines().map(|line| Line::from(masker.line(line))).collect(),
+                };
+                let headers_input = Paragraph::new(headers)
+                    .style(style(focused(Focus::Headers)))
+                    .block(theme.block("Headers", focused(Focus::Headers)));
+                frame.render_widget(headers_input, header_area);
+            }
+        }
+
+        self.draw_body(frame, body_area, focused(Focus::Body));
+        
+        if let Some(collection_area) = collection_area {
+            self.draw_collection(frame, collection_area);
+        }
+
+        let width = response_area.width.saturating_sub(2) as usize;
+        let newest = self.history.last().map(|entry| entry.id);
+        let tree = self.json_tree.as_ref().filter(|(id, _)| Some(*id) == newest).map(|(_, tree)| tree);
+        // Leaves room above for the status line, headers summary and the like.
+        let tree_lines = tree.map(|tree| {
+            let window = (response_area.height as usize).saturating_sub(12).max(5);
+            json_tree_lines(tree, theme, self.focus == Pane::Responses, window)
+        });
+        let searched = self.search.as_ref()
+            .filter(|(id, search)| Some(*id) == newest && tree.is_none() && !search.matches.is_empty())
+            .and_then(|(id, search)| Some(search_lines(self.rendered_bodies.get(id)?, search, theme)));
+        let masker = self.masker(SecretView::Responses);
+        let selected = self.exchange_selected.and(self.selected_exchange()).filter(|_| self.focus == Pane::Responses);
+        let entry_item = |entry: &HistoryEntry| {
+            let newest = Some(entry.id) == newest;
+            let highlight = (newest && self.focus == Pane::Responses).then_some(self.response_view.selected);
+            let body = match (&tree_lines, &searched) {
+                (Some(lines), _) | (_, Some(lines)) if newest => Some(lines.as_slice()),
+                _ => self.rendered_bodies.get(&entry.id).map(Vec::as_slice),
+            };
+            let notes = Notes {
+                chain: self.chain_legs(entry.id),
+                baseline: self.baseline_results.get(&entry.id).map(Vec::as_slice),
+                hooks: self.hook_outcomes.get(&entry.id).map_or(&[], Vec::as_slice),
+                masker: &masker,
+                each_repeat: self.expand_repeats,
+                selected: selected == Some(entry.id),
+            };
+            ListItem::new(Text::from(entry_lines(entry, body, &notes, theme, &self.response_view, highlight, width)))
+        };
+        let mut items = vec![];
+        let mut pinned: Vec<&HistoryEntry> = self.shown_history().filter(|entry| entry.pinned).collect();
+        self.history_sort.apply(&mut pinned);
+        if !pinned.is_empty() {
+            items.push(ListItem::new(Line::styled(format!("★ Pinned ({})", pinned.len()), theme.heading)));
+            items.extend(pinned.iter().map(|entry| entry_item(entry)));
+        }
+        let grouped = self.history_grouping != HistoryGrouping::None;
+        let groups = self.history_groups();
+        let group_selected = self.group_selected.min(groups.len().saturating_sub(1));
+        for (i, (label, entries)) in groups.iter().enumerate() {
+            // The searched response stays in view, so n and N never step through hidden matches.
+            let searched = self.search.is_some() && entries.iter().any(|entry| Some(entry.id) == newest);
+            let collapsed = grouped && self.collapsed_groups.contains(label) && !searched;
+            if grouped {
+                let marker = if collapsed {"▸"} else {"▾"};
+                let style = if i == group_selected && self.focus == Pane::Responses {theme.selected} else {theme.heading};
+                items.push(ListItem::new(Line::styled(format!("{} {} ({})", marker, label, entries.len()), style)));
+            }
+            if collapsed {
+                continue;
+            }
+            items.extend(entries.iter().map(|entry| entry_item(entry)));
+        }
+        let response = List::new(items)
+            .block(match tree {
+                Some(tree) => theme.block(&self.responses_title(), self.focus == Pane::Responses).title_bottom(tree.selected_path()),
+                None => theme.block(&self.responses_title(), self.focus == Pane::Responses),
+            });
+        frame.render_widget(response, response_area);
+
+        if let Some(traffic_area) = traffic_area {
+            let width = traffic_area.width.saturating_sub(2) as usize;
+            let mut lines: Vec<Line> = vec![];
+            for received in self.server_traffic.iter().rev() {
+                lines.extend(received_lines(received, theme, &masker, width));
+            }
+            if lines.is_empty() {
+                lines.push(Line::styled(format!("Nothing received yet at http://{}", self.server_addr), theme.dim));
+            }
+            let title = format!("Server traffic ({})", self.server_traffic.len());
+            frame.render_widget(Paragraph::new(lines).block(theme.block(&title, false)), traffic_area);
+        }
+
+        if let Some(console_area) = console_area {
+            let console_masker = self.masker(SecretView::Console);
+            let visible = console_area.height.saturating_sub(2) as usize;
+            let end = self.console.len().saturating_sub(self.console_scroll);
+            let lines: Vec<Line> = self.console
+                .range(end.saturating_sub(visible)..end)
+                .map(|line| Line::from(console_masker.line(line)))
+                .collect();
+            let title = if self.console_scroll > 0 {format!("Console (-{} lines)", self.console_scroll)} else {"Console".to_string()};
+            frame.render_widget(Paragraph::new(lines).block(theme.block(&title, false)), console_area);
+        }
+
+        let status = match (&self.error_message, self.rate_limit_notice(Instant::now())) {
+            (Some(error), _) => {
+                let mut spans = vec![Span::raw(theme.error_text(&error.summary))];
+                let cut = error.summary.chars().count() + 12 > status_area.width as usize;
+                if let Some(key) = self.keymap.binding_for(Action::ErrorDetails).filter(|_| error.has_more() || cut) {
+                    spans.insert(0, Span::styled(format!("[{}: more] ", key), theme.dim));
+                }
+                let retryable = self.last_failure.as_ref().is_some_and(|(id, _)| self.history.last().is_some_and(|entry| entry.id == *id));
+                if let Some(key) = self.keymap.binding_for(Action::RetryLastFailure).filter(|_| retryable) {
+                    spans.insert(0, Span::styled(format!("[{}: retry] ", key), theme.dim));
+                }
+                Paragraph::new(Line::from(spans)).style(theme.error)
+            }
+            (None, Some(notice)) => {
+                let label = if self.rate_limit.as_ref().is_some_and(|limit| limit.scheduled.is_some()) {"cancel retry"} else {"retry when allowed"};
+                let key = self.keymap.binding_for(Action::RetryWhenAllowed)
+                    .map(|binding| Span::styled(format!("  {}: {}", binding, label), theme.dim));
+                Paragraph::new(Line::from_iter([Some(theme.badge(&notice, Tone::Warning)), key].into_iter().flatten()))
+            }
+            (None, None) if self.flash.as_ref().is_some_and(|(_, until)| *until > Instant::now()) => {
+                let message = self.flash.as_ref().map_or("", |(message, _)| message.as_str());
+                Paragraph::new(Line::from(theme.badge(message, Tone::Good)))
+            }
+            (None, None) => {
+                let environment = match self.active_environment.map(|i| &self.environments[i]) {
+                    Some(env) => match env.locked().count() {
+                        0 => env.name.clone(),
+                        locked => format!("{} ({} secrets locked)", env.name, locked),
+                    },
+                    None => "none".to_string(),
+                };
+                let conditional = if self.send_conditional {"  |  conditional"} else {""};
+                let traffic = match (self.bytes_sent, self.bytes_received) {
+                    (0, 0) => String::new(),
+                    (sent, received) => format!("  |  ↑{} ↓{}", byte_size(sent), byte_size(received)),
+                };
+                let connect_to = self.connect_to.as_ref().map(|addr| format!("  |  connect-to: {}", addr)).unwrap_or_default();
+                let custom = self.custom_settings();
+                let custom = if custom.is_empty() {String::new()} else {format!("  |  custom: {}", custom.join(", "))};
+                let in_flight = match self.in_flight {
+                    0 => String::new(),
+                    count => {
+                        // Bodies report how far they've got; otherwise the wait is all there is to show.
+                        let oldest = self.history.iter().find(|entry| entry.is_pending());
+                        let transfer = oldest.and_then(|entry| self.transfers.get(&entry.id));
+                        let sent = oldest.and_then(|entry| DateTime::parse_from_rfc3339(&entry.timestamp).ok());
+                        let waited = match (transfer, sent) {
+                            (Some(transfer), _) => format!(", {}", transfer_label(transfer)),
+                            (None, Some(sent)) => format!(", {}s", (Utc::now() - sent.to_utc()).num_seconds().max(0)),
+                            (None, None) => String::new(),
+                        };
+                        format!("  |  sending ({}{})", count, waited)
+                    }
+                };
+                let run = match &self.run {
+                    Some(run) if !run.is_finished() => format!("  |  run {}", run.summary()),
+                    _ => String::new(),
+                };
+                let preference = match self.address_preference {
+                    AddressPreference::AsResolved => String::new(),
+                    preference => format!("  |  {}", preference.label()),
+                };
+                let keys: Vec<String> = [(Action::CommandPalette, "commands"), (Action::Help, "help")]
+                    .into_iter()
+                    .filter_map(|(action, label)| self.keymap.binding_for(action).map(|binding| format!("  |  {}: {}", binding, label)))
+                    .collect();
+                let position = if self.focus == Pane::Editor && *self.get_input_mode() == InputMode::EditingBody {
+                    let (line, column) = editor::position(&self.body_input, self.character_index);
+                    format!("Ln {}, Col {}  |  ", line + 1, column + 1)
+                } else {
+                    String::new()
+                };
+                Paragraph::new(format!(
+                    "{}workspace: {}  |  env: {}{}{}{}{}{}{}{}{}",
+                    position,
+                    workspace::active_name(),
+                    environment,
+                    in_flight,
+                    run,
+                    traffic,
+                    conditional,
+                    connect_to,
+                    custom,
+                    preference,
+                    keys.concat()
+                ))
+                .style(theme.dim)
+            }
+        };
+        frame.render_widget(status, status_area);
+
+        self.draw_popup(frame);
+    }
+
+    fn responses_title(&self) -> String {
+        let mut title = match (self.response_view.wrap, self.response_view.column) {
+            (true, _) => "Responses (wrap)".to_string(),
+            (false, 0) => "Responses".to_string(),
+            (false, column) => format!("Responses (col {})", column + 1),
+        };
+        if let Some((_, search)) = &self.search {
+            let delimiter = if self.search_regex {"/"} else {"\""};
+            let progress = match search.matches.len() {
+                _ if !search.done => "searching…".to_string(),
+                0 => "no matches".to_string(),
+                count if count >= search::MAX_MATCHES => format!("{} of {}+", search.current + 1, count),
+                count => format!("{} of {}", search.current + 1, count),
+            };
+            title.push_str(&format!("  {}{}{} {}", delimiter, self.search_query, delimiter, progress));
+        }
+        if let Some(environment) = &self.history_environment {
+            title.push_str(&format!("  env: {}", environment.as_deref().unwrap_or("none")));
+        }
+        if self.history_grouping != HistoryGrouping::None {
+            title.push_str(&format!("  by {}", self.history_grouping.name()));
+        }
+        if let Some((method, url)) = &self.endpoint_filter {
+            title.push_str(&format!("  {} {}", method, url));
+        }
+        if self.status_filter != StatusFilter::All || self.endpoint_filter.is_some() {
+            let total = self.environment_history().count();
+            title.push_str(&format!("  [{}] {}/{}", self.status_filter.label(), self.shown_history().count(), total));
+        }
+        let counts: Vec<String> = StatusFilter::CLASSES
+            .iter()
+            .filter_map(|class| match self.environment_history().filter(|entry| class.matches(entry)).count() {
+                0 => None,
+                count => Some(format!("{} {}", class.label(), count)),
+            })
+            .collect();
+        if !counts.is_empty() {
+            title.push_str(&format!("  ({})", counts.join(" · ")));
+        }
+        match self.history_sort {
+            HistorySort::Recency => {}
+            HistorySort::Latency => title.push_str("  slowest first"),
+            HistorySort::Status => title.push_str("  by status"),
+        }
+        if self.expand_repeats {
+            title.push_str("  each repeat");
+        }
+        title
+    }
+
+    fn draw_header_table(&self, frame: &mut Frame, area: Rect, table: &HeaderTable, rows: &[kv::Row], focused: bool) {
+        let theme = &self.theme;
+        let masker = self.masker(SecretView::Editor);
+        let width = area.width.saturating_sub(2) as usize;
+        let name_width = rows.iter().map(|row| row.key.chars().count() + 1).max().unwrap_or(0).clamp(8, (width / 3).max(8));
+        let value_width = width.saturating_sub(4 + name_width);
+        let mut lines = vec![Line::styled(format!("    {:<name_width$}Value", "Name"), theme.dim)];
+        let visible = area.height.saturating_sub(3) as usize;
+        for (i, row) in rows.iter().enumerate().skip((table.selected + 1).saturating_sub(visible)).take(visible) {
+            let base = if row.enabled {theme.text} else {theme.dim};
+            let cell_style = |column| if focused && i == table.selected && table.column == column {theme.selected} else {base};
+            let cursor = |column| table.editing.as_ref().filter(|_| focused && i == table.selected && table.column == column);
+            let name = match cursor(HeaderColumn::Name) {
+                Some((cell, at)) => cell_spans(cell, name_width.saturating_sub(1), Some(*at), base, theme.selected),
+                None => cell_spans(&row.key, name_width.saturating_sub(1), None, cell_style(HeaderColumn::Name), theme.selected),
+            };
+            let value = match (cursor(HeaderColumn::Value), &row.value) {
+                (Some((cell, at)), _) => cell_spans(cell, value_width, Some(*at), base, theme.selected),
+                (None, Some(value)) => {
+                    cell_spans(&masker.header_value(&row.key, value), value_width, None, cell_style(HeaderColumn::Value), theme.selected)
+                }
+                (None, None) => vec![Span::styled("(no value, not sent)", cell_style(HeaderColumn::Value).patch(theme.dim))],
+            };
+            let mut spans = vec![Span::styled(if row.enabled {"[x] "} else {"[ ] "}, base)];
+            spans.extend(name);
+            spans.push(Span::raw(" "));
+            spans.extend(value);
+            lines.push(Line::from(spans));
+        }
+        if rows.is_empty() {
+            lines.push(Line::styled("No headers — n: add", theme.dim));
+        }
+        let title = if focused {"Headers (n: add  d: delete  Space: toggle  Enter: edit)"} else {"Headers"};
+        frame.render_widget(Paragraph::new(lines).block(theme.block(title, focused)), area);
+    }
+
+    /// The body with a line-number gutter, scrolled so the cursor stays in view. Lines aren't
+    /// wrapped, so a line on screen is always a line of the body and columns line up with the cursor.
+    /// A JSON syntax error gets a line under the text and its line number marked.
+    fn draw_body(&self, frame: &mut Frame, area: Rect, focused: bool) {
+        let theme = &self.theme;
+        let block = theme.block("Body", focused);
+        let mut inner = block.inner(area);
+        frame.render_widget(block, area);
+        let error = self.json_check.error.as_ref();
+        if let Some(error) = error {
+            let [text_area, message_area] = Layout::vertical([Constraint::Min(1), Constraint::Length(1)]).areas(inner);
+            inner = text_area;
+            let message = format!("Ln {}, Col {}: {}", error.line, error.column, error.message);
+            frame.render_widget(Paragraph::new(theme.error_text(&message)).style(theme.dim), message_area);
+        }
+
+        let line_count = editor::line_count(&self.body_input);
+        let [gutter_area, text_area] = Layout::horizontal([
+            Constraint::Length(editor::gutter_width(line_count)),
+            Constraint::Min(1),
+        ]).areas(inner);
+        let (line, column) = editor::position(&self.body_input, self.character_index);
+        let first_line = line.saturating_sub((text_area.height as usize).saturating_sub(1));
+        let first_column = column.saturating_sub((text_area.width as usize).saturating_sub(1));
+
+        let number_width = gutter_area.width.saturating_sub(1) as usize;
+        let numbers: Vec<Line> = (first_line..line_count.min(first_line + gutter_area.height as usize))
+            .map(|i| {
+                let style = if error.is_some_and(|error| error.line == i + 1) {theme.error} else {theme.dim};
+                Line::styled(format!("{:>width$}", i + 1, width = number_width), style)
+            })
+            .collect();
+        frame.render_widget(Paragraph::new(numbers), gutter_area);
+
+        let text = match self.body_input.is_empty() {
+            true => Paragraph::new(Line::styled(BODY_PLACEHOLDER, theme.dim)),
+            false => Paragraph::new(self.body_input.as_str()).style(if focused {theme.focused} else {theme.text}),
+        };
+        let text = text
+            .scroll((first_line as u16, first_column as u16));
+        frame.render_widget(text, text_area);
+        if focused && self.popup.is_none() {
+            frame.set_cursor_position((
+                text_area.x + (column - first_column) as u16,
+                text_area.y + (line - first_line) as u16,
+            ));
+        }
+    }
+
+    fn draw_collection(&self, frame: &mut Frame, area: Rect) {
+        let rows = self.collection_rows();
+        let visible = area.height.saturating_sub(2) as usize;
+        let first = self.collection_selected.saturating_sub(visible.saturating_sub(1));
+        let mut lines: Vec<Line> = rows
+            .iter()
+            .enumerate()
+            .skip(first)
+            .take(visible)
+            .map(|(i, row)| {
+                let indent = "  ".repeat(row.depth(&self.collection));
+                let mut tags = &[][..];
+                let (text, description) = match row {
+                    TreeRow::Folder { path, collapsed } => {
+                        (format!("{}{} {}", indent, if *collapsed {"▸"} else {"▾"}, path.last().map_or("", String::as_str)), None)
+                    }
+                    TreeRow::Request(index) => {
+                        let saved = &self.collection.requests[*index];
+                        let description = saved.description.as_deref().and_then(|text| text.lines().next());
+                        tags = &saved.tags;
+                        (format!("{}  {} {}", indent, saved.request.method, saved.name), description)
+                    }
+                };
+                let style = match (i == self.collection_selected, self.focus == Pane::Collection) {
+                    (true, true) => self.theme.selected,
+                    (true, false) => self.theme.selected_unfocused,
+                    _ => self.theme.text,
+                };
+                let mut line = Line::styled(text, style);
+                for tag in tags {
+                    line.push_span(Span::raw(" "));
+                    line.push_span(self.theme.chip(tag));
+                }
+                if let Some(description) = description {
+                    line.push_span(Span::styled(format!("  {}", description), self.theme.dim));
+                }
+                line
+            })
+            .collect();
+        if rows.is_empty() {
+            lines.push(Line::styled("No saved requests", self.theme.dim));
+        }
+        let title = match &self.tag_filter {
+            Some(tag) => format!("Collection (tagged {})", tag),
+            None => "Collection".to_string(),
+        };
+        frame.render_widget(Paragraph::new(lines).block(self.theme.block(&title, self.focus == Pane::Collection)), area);
+    }
+
+    fn draw_popup(&self, frame: &mut Frame) {
+        let Some(popup) = &self.popup else { return };
+        let theme = &self.theme;
+        let area = match popup {
+            Popup::Prompt { .. } => popup_area(frame.area(), 60, 20),
+            Popup::Load | Popup::Run { .. } => popup_area(frame.area(), 80, 80),
+            _ => popup_area(frame.area(), 70, 60),
+        };
+        frame.render_widget(Clear, area);
+        match popup {
+            Popup::Palette { filter, selected } => {
+                let mut lines = vec![Line::from(format!("> {}", filter)), Line::from("")];
+                for (i, action) in Action::matching(filter).iter().enumerate() {
+                    let style = if i == *selected {theme.selected} else {theme.text};
+                    let binding = self.keymap.binding_for(*action).map(|binding| format!(" ({})", binding)).unwrap_or_default();
+                    lines.push(Line::from(vec![
+                        Span::styled(format!("{}{}", action.name(), binding), style),
+                        Span::styled(format!("  {}", action.description()), theme.dim),
+                    ]));
+                }
+                frame.render_widget(Paragraph::new(lines).block(theme.popup("Commands")), area);
+            }
+            Popup::Text { title, lines, scroll } => {
+                let popup = Paragraph::new(lines.clone())
+                    .block(theme.popup(title.as_str()))
+                    .scroll((*scroll, 0));
+                frame.render_widget(popup, area);
+            }
+            Popup::Picker { title, items, selected, .. } => {
+                let lines: Vec<Line> = items
+                    .iter()
+                    .enumerate()
+                    .map(|(i, item)| {
+                        let style = if i == *selected {theme.selected} else {theme.text};
+                        Line::styled(item.as_str(), style)
+                    })
+                    .collect();
+                frame.render_widget(Paragraph::new(lines).block(theme.popup(title.as_str())), area);
+            }
+            Popup::Mocks { selected } => {
+                let mut lines: Vec<Line> = self.collection.mocks
+                    .iter()
+                    .enumerate()
+                    .map(|(i, mock)| {
+                        let style = if i == *selected {theme.selected} else {theme.text};
+                        Line::styled(format!("{:<7} {} -> {}", mock.method, mock.path, mock.status), style)
+                    })
+                    .collect();
+                if lines.is_empty() {
+                    lines.push(Line::from("No mocks yet"));
+                }
+                lines.push(Line::from(""));
+                lines.push(Line::styled(format!("Served at http://{}", self.server_addr), theme.dim));
+                lines.push(Line::styled("n: new  Enter: edit  d: delete  Esc: close", theme.dim));
+                frame.render_widget(Paragraph::new(lines).block(theme.popup("Mock endpoints")), area);
+            }
+            Popup::MockEditor { index, text, error } => {
+                let mut lines: Vec<Line> = text.split('\n').map(|line| Line::from(line.to_string())).collect();
+                if let Some(last) = lines.last_mut() {
+                    last.push_span(Span::styled("█", theme.dim));
+                }
+                lines.push(Line::from(""));
+                lines.extend(error.iter().map(|error| Line::styled(theme.error_text(error), theme.error)));
+                lines.push(Line::styled("METHOD /path STATUS, then headers, a blank line and the body  Ctrl+S: save  Esc: cancel", theme.dim));
+                let title = if index.is_some() {"Edit mock"} else {"New mock"};
+                frame.render_widget(Paragraph::new(lines).wrap(Wrap { trim: false }).block(theme.popup(title)), area);
+            }
+            Popup::Validators { selected } => {
+                let mut lines: Vec<Line> = self.validators
+                    .iter()
+                    .enumerate()
+                    .flat_map(|(i, (url, validators))| {
+                        let style = if i == *selected {theme.selected} else {theme.text};
+                        let mut lines = vec![Line::styled(url.clone(), style)];
+                        if let Some(etag) = &validators.etag {
+                            lines.push(Line::from(format!("  ETag: {}", etag)));
+                        }
+                        if let Some(last_modified) = &validators.last_modified {
+                            lines.push(Line::from(format!("  Last-Modified: {}", last_modified)));
+                        }
+                        lines
+                    })
+                    .collect();
+                if lines.is_empty() {
+                    lines.push(Line::from("No validators stored yet"));
+                }
+                lines.push(Line::from(""));
+                lines.push(Line::styled("d: clear selected  C: clear all  Esc: close", theme.dim));
+                frame.render_widget(Paragraph::new(lines).block(theme.popup("Cached validators")), area);
+            }
+            Popup::Settings { config, selected, changed } => {
+                let mut lines = vec![];
+                let mut section = "";
+                for (i, setting) in SETTINGS.iter().enumerate() {
+                    if setting.section != section {
+                        section = setting.section;
+                        if !lines.is_empty() {
+                            lines.push(Line::from(""));
+                        }
+                        lines.push(Line::styled(section, theme.heading));
+                    }
+                    let style = if i == *selected {theme.selected} else {theme.text};
+                    let restart = if setting.needs_restart {"  (after a restart)"} else {""};
+                    lines.push(Line::from(vec![
+                        Span::styled(format!("  {:<18}", setting.name), style),
+                        Span::styled((setting.get)(config), theme.text),
+                        Span::styled(restart, theme.dim),
+                    ]));
+                }
+                lines.push(Line::from(""));
+                lines.push(Line::styled(SETTINGS[*selected].help, theme.dim));
+                lines.push(Line::styled("Saving rewrites config.toml; comments in it are lost", theme.dim));
+                let unsaved = if *changed {"  (unsaved changes)"} else {""};
+                lines.push(Line::styled(format!("Enter: edit  s: save and apply  Esc: close without saving{}", unsaved), theme.dim));
+                let title = format!("Settings — {}", config::config_path().display());
+                frame.render_widget(Paragraph::new(lines).block(theme.popup(title.as_str())), area);
+            }
+            Popup::ServerControl { selected } => {
+                let faults = my_test_server::faults();
+                let mut lines: Vec<Line> = ServerFault::ALL.into_iter()
+                    .enumerate()
+                    .map(|(i, fault)| {
+                        let style = if i == *selected {theme.selected} else {theme.text};
+                        Line::from(vec![Span::styled(format!("{:<10}", fault.name()), style), Span::styled(fault.describe(&faults), theme.text)])
+                    })
+                    .collect();
+                lines.push(Line::from(""));
+                lines.push(Line::styled("Per request: ?delay_ms=500, ?fail=30, ?stall=1, ?stall_body=1 or ?cut=1", theme.dim));
+                lines.push(Line::styled("Failures answer 500 with X-Injected-Failure.", theme.dim));
+                lines.push(Line::from(""));
+                lines.push(Line::styled("Enter: edit  0: all off  s: save as default  Esc: close", theme.dim));
+                frame.render_widget(Paragraph::new(lines).wrap(Wrap { trim: false }).block(theme.popup("Test server faults")), area);
+            }
+            Popup::RequestSettings { selected } => {
+                let mut lines: Vec<Line> = RequestSetting::ALL.into_iter()
+                    .enumerate()
+                    .map(|(i, setting)| {
+                        let style = if i == *selected {theme.selected} else {theme.text};
+                        let value = match self.request_setting(setting) {
+                            (Some(own), _) => own,
+                            (None, default) => format!("inherit ({})", default),
+                        };
+                        Line::from(vec![Span::styled(format!("{:<16}", setting.name()), style), Span::styled(value, theme.text)])
+                    })
+                    .collect();
+                lines.push(Line::from(""));
+                lines.push(Line::styled("Enter: edit  d: inherit  Esc: close  Saved with the request", theme.dim));
+                frame.render_widget(Paragraph::new(lines).block(theme.popup("Request settings")), area);
+            }
+            Popup::Prompt { title, input, target: PromptTarget::Search { regex, error } } => {
+                let mut lines = vec![Line::from(format!("> {}", input))];
+                if let Some(error) = error {
+                    lines.push(Line::styled(theme.error_text(error), theme.error));
+                }
+                lines.push(Line::from(""));
+                let mode = if *regex {"regex"} else {"text"};
+                lines.push(Line::styled(format!("Ctrl+R: text/regex  Enter: search  Esc: cancel  Searching as {}", mode), theme.dim));
+                frame.render_widget(Paragraph::new(lines).wrap(Wrap { trim: false }).block(theme.popup(title.as_str())), area);
+            }
+            Popup::Prompt { title, input, target: PromptTarget::Passphrase { .. } } => {
+                let dots = "•".repeat(input.chars().count());
+                frame.render_widget(Paragraph::new(format!("> {}", dots)).block(theme.popup(title.as_str())), area);
+            }
+            Popup::Prompt { title, input, .. } => {
+                frame.render_widget(Paragraph::new(format!("> {}", input)).block(theme.popup(title.as_str())), area);
+            }
+            Popup::Details { index, scroll } => {
+                let Some(saved) = self.collection.requests.get(*index) else { return };
+                let mut lines = self.request_details(saved);
+                lines.push(Line::from(""));
+                lines.push(Line::styled("e: edit description  t: edit tags  Esc: close", theme.dim));
+                let popup = Paragraph::new(lines).block(theme.popup(saved.name.as_str())).scroll((*scroll, 0));
+                frame.render_widget(popup, area);
+            }
+            Popup::Description { index, text } => {
+                let mut lines: Vec<Line> = text.split('\n').map(|line| Line::from(line.to_string())).collect();
+                if let Some(last) = lines.last_mut() {
+                    last.push_span(Span::styled("█", theme.dim));
+                }
+                lines.push(Line::from(""));
+                lines.push(Line::styled("Ctrl+S: save  Esc: cancel", theme.dim));
+                let title = format!("Description of {}", self.collection.requests.get(*index).map_or("", |saved| saved.name.as_str()));
+                frame.render_widget(Paragraph::new(lines).block(theme.popup(title.as_str())), area);
+            }
+            Popup::BulkParams { text, errors } => {
+                let mut lines: Vec<Line> = text.split('\n').map(|line| Line::from(line.to_string())).collect();
+                if let Some(last) = lines.last_mut() {
+                    last.push_span(Span::styled("█", theme.dim));
+                }
+                lines.push(Line::from(""));
+                lines.extend(errors.iter().map(|error| Line::styled(theme.error_text(error), theme.error)));
+                lines.push(Line::styled("One key=value per line, // disables  Ctrl+S: apply  Esc: cancel", theme.dim));
+                frame.render_widget(Paragraph::new(lines).block(theme.popup("Query parameters")), area);
+            }
+            Popup::Finder { query, selected } => {
+                let mut lines = vec![Line::from(format!("> {}", query)), Line::from("")];
+                let visible = area.height.saturating_sub(4) as usize;
+                let found = self.collection.find(query, self.tag_filter.as_deref());
+                for (i, found) in found.iter().enumerate().skip(selected.saturating_sub(visible.saturating_sub(1))).take(visible) {
+                    let saved = &self.collection.requests[found.index];
+                    let base = if i == *selected {theme.selected} else {theme.text};
+                    let dim = if i == *selected {base} else {theme.dim};
+                    let positions = |field| if found.field == field {found.positions.as_slice()} else {&[]};
+                    let mut spans = highlighted(&format!("{:<7}", saved.request.method), positions(MatchedField::Method), dim, theme.matched);
+                    if !saved.folder.is_empty() {
+                        spans.push(Span::styled(format!("{} / ", saved.folder.join(" / ")), dim));
+                    }
+                    spans.extend(highlighted(&saved.name, positions(MatchedField::Name), base, theme.matched));
+                    spans.push(Span::styled("  ", base));
+                    spans.extend(highlighted(&saved.request.url, positions(MatchedField::Url), dim, theme.matched));
+                    if !saved.tags.is_empty() {
+                        spans.push(Span::styled("  ", base));
+                        spans.extend(highlighted(&saved.tags.join(" "), positions(MatchedField::Tags), theme.tag, theme.matched));
+                    }
+                    lines.push(Line::from(spans));
+                }
+                if found.is_empty() {
+                    lines.push(Line::styled("No matches", theme.dim));
+                }
+                let title = match &self.tag_filter {
+                    Some(tag) => format!("Find saved request tagged {}", tag),
+                    None => "Find saved request".to_string(),
+                };
+                frame.render_widget(Paragraph::new(lines).block(theme.popup(title.as_str())), area);
+            }
+            Popup::Load => self.draw_load(frame, area),
+            Popup::Run { selected } => self.draw_run(frame, area, *selected),
+            Popup::Stats { selected, sort } => self.draw_stats(frame, area, *selected, *sort),
+            Popup::Confirm { message, .. } => {
+                let popup = Paragraph::new(vec![Line::from(message.as_str()), Line::from(""), Line::from("(y/n)")])
+                    .block(theme.popup("Confirm"))
+                    .wrap(Wrap { trim: true });
+                frame.render_widget(popup, area);
+            }
+        }
+    }
+
+    /// The load popup: progress or the summary, then a latency histogram whose selected bar is
+    /// spelled out, and a sparkline of latency over the run.
+    fn draw_run(&self, frame: &mut Frame, area: Rect, selected: usize) {
+        let Some(run) = &self.run else { return };
+        let theme = &self.theme;
+        let mut lines = vec![Line::styled(format!("{}: {}", run.label(), run.summary()), theme.heading)];
+        if let Some(stopped) = &run.stopped {
+            lines.push(Line::styled(theme.error_text(stopped), theme.error));
+        }
+        if let Some(ms) = run.wall_ms() {
+            let checks: usize = run.failing().filter(|leg| leg.outcome == Outcome::Failed).map(|leg| leg.problems.len()).sum();
+            lines.push(Line::from(format!("Took {:.1}s; {} failed checks", ms as f64 / 1000.0, checks)));
+            let slowest: Vec<String> = run.slowest(runner::SLOWEST).iter()
+                .map(|leg| format!("{} {} ms", run.leg_label(leg), leg.latency_ms.unwrap_or_default()))
+                .collect();
+            if !slowest.is_empty() {
+                lines.push(Line::from(vec![Span::styled("Slowest: ", theme.key), Span::raw(slowest.join(", "))]));
+            }
+        }
+        lines.push(Line::from(""));
+        let mut selected_line = 0;
+        for (i, leg) in run.legs.iter().enumerate() {
+            let style = match leg.outcome {
+                Outcome::Failed | Outcome::Error => theme.error,
+                Outcome::Waiting | Outcome::Skipped => theme.dim,
+                Outcome::Running | Outcome::Passed => theme.text,
+            };
+            let name_style = if i == selected {
+                selected_line = lines.len();
+                theme.selected
+            } else {
+                theme.text
+            };
+            let mut spans = vec![Span::styled(format!("{:<8} ", leg.outcome.label()), style), Span::styled(run.leg_label(leg), name_style)];
+            if let Some(status) = leg.status {
+                spans.push(Span::styled(format!("  {}", status), theme.key));
+            }
+            if let Some(latency) = leg.latency_ms {
+                spans.push(Span::styled(format!("  {} ms", latency), theme.dim));
+            }
+            lines.push(Line::from(spans));
+            lines.extend(leg.problems.iter().map(|problem| Line::styled(format!("         {}", theme.error_text(problem)), theme.error)));
+        }
+        lines.push(Line::from(""));
+        let keys = match run.is_finished() {
+            true => "Esc: close  Enter: the exchange  m: save as Markdown  r: run the failed again",
+            false => "Esc: stop the run  h: hide, the run goes on  Enter: the exchange",
+        };
+        lines.push(Line::styled(keys, theme.dim));
+        // Keeps the selected request in view, with the summary above it while there's room.
+        let height = area.height.saturating_sub(2) as usize;
+        let scroll = (selected_line + 2).saturating_sub(height);
+        frame.render_widget(Paragraph::new(lines).block(theme.popup("Collection run")).scroll((scroll as u16, 0)), area);
+    }
+
+    /// The endpoint table. Endpoints with errors are in the error color, and the one slowest at
+    /// p95 is marked.
+    fn draw_stats(&self, frame: &mut Frame, area: Rect, selected: usize, sort: SortBy) {
+        let theme = &self.theme;
+        let endpoints = stats::rollup(self.history.iter(), sort);
+        let selected = selected.min(endpoints.len().saturating_sub(1));
+        let slowest = endpoints.iter().enumerate().filter(|(_, endpoint)| endpoint.p95().is_some()).max_by_key(|(_, endpoint)| endpoint.p95()).map(|(i, _)| i);
+        let url_width = (area.width as usize).saturating_sub(52).max(20);
+        let ms = |value: Option<u64>| value.map_or("-".to_string(), |value| value.to_string());
+        let mut lines = vec![
+            Line::styled(format!("Sorted by {}, worst first", sort.label()), theme.dim),
+            Line::styled(
+                format!("{:<7} {:<width$} {:>5} {:>6} {:>6} {:>7} {:>6}", "Method", "URL", "Runs", "Errors", "Min", "Median", "p95", width = url_width),
+                theme.heading,
+            ),
+        ];
+        for (i, endpoint) in endpoints.iter().enumerate() {
+            let url: String = match endpoint.url.chars().count() > url_width {
+                true => endpoint.url.chars().take(url_width.saturating_sub(1)).chain(['…']).collect(),
+                false => endpoint.url.clone(),
+            };
+            let row = format!(
+                "{:<7} {:<width$} {:>5} {:>5.0}% {:>6} {:>7} {:>6}",
+                endpoint.method, url, endpoint.runs, endpoint.error_rate(), ms(endpoint.min()), ms(endpoint.median()), ms(endpoint.p95()),
+                width = url_width,
+            );
+            let style = match (i == selected, endpoint.errors > 0) {
+                (true, _) => theme.selected,
+                (false, true) => theme.error,
+                (false, false) => theme.text,
+            };
+            let mut spans = vec![Span::styled(row, style)];
+            if Some(i) == slowest && endpoints.len() > 1 {
+                spans.extend([Span::raw(" "), theme.badge("slowest", Tone::Warning)]);
+            }
+            lines.push(Line::from(spans));
+        }
+        lines.push(Line::from(""));
+        lines.push(Line::styled("Esc: close  s: sort by  Enter: show its exchanges  c: save as CSV", theme.dim));
+        let height = area.height.saturating_sub(2) as usize;
+        let scroll = (selected + 4).saturating_sub(height);
+        frame.render_widget(Paragraph::new(lines).block(theme.popup("Endpoint statistics")).scroll((scroll as u16, 0)), area);
+    }
+
+    fn draw_load(&self, frame: &mut Frame, area: Rect) {
+        let Some(run) = &self.load else { return };
+        let theme = &self.theme;
+        let now = Instant::now();
+        let mut lines = vec![Line::from(format!("-> {}", run.target)), Line::from(run.progress(now))];
+        lines.extend(run.problems.iter().map(|problem| Line::styled(theme.error_text(problem), theme.error)));
+        if run.is_finished() {
+            lines.push(Line::styled(format!("Finished after {:.1}s", run.elapsed(now).as_secs_f64()), theme.heading));
+            lines.extend(run.summary().into_iter().map(Line::from));
+        }
+        let latencies = run.latencies_ms();
+        let histogram = load::histogram(&latencies, load::HISTOGRAM_BUCKETS);
+        if let Some(bucket) = histogram.get(self.load_bucket) {
+            lines.push(Line::from(format!(
+                "Bar {}: {}-{} ms, {} requests",
+                self.load_bucket + 1, bucket.from_ms, bucket.to_ms.saturating_sub(1), bucket.count,
+            )));
+        }
+        let keys = if run.is_finished() {"Esc: close"} else {"Esc: stop every worker"};
+        lines.push(Line::styled(format!("{}  Left/Right: pick a bar  e: save as CSV", keys), theme.dim));
+
+        let block = theme.popup("Load test");
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+        let [text_area, histogram_area, sparkline_area] = Layout::vertical([
+            Constraint::Length(lines.len() as u16),
+            Constraint::Min(6),
+            Constraint::Length(5),
+        ]).areas(inner);
+        frame.render_widget(Paragraph::new(lines), text_area);
+        if histogram.is_empty() {
+            return;
+        }
+
+        let bar_width = (histogram_area.width / load::HISTOGRAM_BUCKETS as u16).saturating_sub(1).max(1);
+        let bars: Vec<Bar> = histogram
+            .iter()
+            .enumerate()
+            .map(|(i, bucket)| {
+                let style = if i == self.load_bucket {theme.selected} else {theme.text};
+                Bar::default().value(bucket.count).text_value(String::new()).label(Line::from(bucket.from_ms.to_string())).style(style)
+            })
+            .collect();
+        let chart = BarChart::default()
+            .block(Block::new().title("Latency (ms)"))
+            .data(BarGroup::default().bars(&bars))
+            .bar_width(bar_width)
+            .bar_gap(1);
+        frame.render_widget(chart, histogram_area);
+
+        let points = load::over_time(&latencies, sparkline_area.width as usize);
+        let sparkline = Sparkline::default().block(Block::new().title("Latency over the run")).data(&points).style(theme.text);
+        frame.render_widget(sparkline, sparkline_area);
+    }
+}
+
+/// Indices into history, oldest first, for a range typed newest-first like `1-5`, `3`, or `all`.
+fn history_range(input: &str, len: usize) -> Result<std::ops::Range<usize>, String> {
+    if input.eq_ignore_ascii_case("all") {
+        return Ok(0..len);
+    }
+    let (from, to) = input.split_once('-').unwrap_or((input, input));
+    let parse = |n: &str| n.trim().parse::<usize>().ok().filter(|n| (1..=len).contains(n));
+    let (Some(from), Some(to)) = (parse(from), parse(to)) else {
+        return Err(format!("{:?} isn't a range of exchanges between 1 and {}", input, len));
+    };
+    let (newest, oldest) = (from.min(to), from.max(to));
+    Ok(len - oldest..len - newest + 1)
+}
+
+fn popup_area(area: Rect, percent_x: u16, percent_y: u16) -> Rect {
+    let [area] = Layout::vertical([Constraint::Percentage(percent_y)]).flex(Flex::Center).areas(area);
+    let [area] = Layout::horizontal([Constraint::Percentage(percent_x)]).flex(Flex::Center).areas(area);
+    area
+}
+
+/// What became of a response after it arrived: its baseline comparison and post-response hooks.
+/// Along with how to mask the credentials it shows.
+struct Notes<'a> {
+    /// The legs of the chain before this exchange, if it was triggered by one.
+    chain: Vec<String>,
+    baseline: Option<&'a [String]>,
+    hooks: &'a [HookOutcome],
+    masker: &'a Masker,
+    /// Every earlier run of a repeated request listed, rather than the last few.
+    each_repeat: bool,
+    /// Picked with j and k in the responses pane.
+    selected: bool,
+}
+
+/// Where the time went, as a bar split by phase followed by the numbers. Entries from before
+/// phases were recorded have only DNS and the rest, or nothing.
+fn timing_line(timing: &Timing, body_bytes: usize, theme: &Theme) -> Option<Line<'static>> {
+    const BAR_WIDTH: u64 = 30;
+    let (dns, exchange) = (timing.dns_ms?, timing.exchange_ms?);
+    let phases = match (timing.connect_ms, timing.ttfb_ms, timing.download_ms) {
+        (Some(connect), Some(ttfb), Some(download)) => vec![
+            ("DNS", dns, theme.key),
+            ("connect", connect, theme.attribute),
+            ("TTFB", ttfb, theme.value),
+            ("download", download, theme.tag),
+        ],
+        _ => vec![("DNS", dns, theme.key), ("connect to last byte", exchange, theme.value)],
+    };
+    let total = phases.iter().map(|(_, ms, _)| ms).sum::<u64>().max(1);
+    let mut spans = vec![];
+    let (mut elapsed, mut drawn) = (0, 0);
+    for (_, ms, style) in &phases {
+        elapsed += ms;
+        let width = (elapsed * BAR_WIDTH).div_ceil(total).min(BAR_WIDTH);
+        spans.push(Span::styled("█".repeat((width - drawn) as usize), *style));
+        drawn = width;
+    }
+    let numbers: Vec<String> = phases.iter()
+        .map(|(label, ms, _)| format!("{} {} ms", label, ms))
+        .enumerate()
+        .flat_map(|(i, phase)| if i == 1 {vec!["TLS none".to_string(), phase]} else {vec![phase]})
+        .collect();
+    let traffic = match (timing.sent_bytes, timing.received_bytes) {
+        (Some(sent), Some(received)) => format!("  sent {}, received {}", byte_size(sent), byte_size(received)),
+        _ => String::new(),
+    };
+    // Older entries only have the whole exchange to average over.
+    let reading = timing.download_ms.unwrap_or(exchange).max(1);
+    let rate = match body_bytes {
+        0 => String::new(),
+        bytes => format!("  {} at {}/s", byte_size(bytes as u64), byte_size(bytes as u64 * 1000 / reading)),
+    };
+    spans.push(Span::styled(format!("  {}{}{}", numbers.join("  "), rate, traffic), theme.dim));
+    Some(Line::from(spans))
+}
+
+/// Like `↑ 12.0 MB of 200.0 MB (6%) at 3.1 MB/s`, or `↓ 3.2 MB at 1.1 MB/s` for a response
+/// of unknown length.
+fn transfer_label(transfer: &Transfer) -> String {
+    let elapsed = (transfer.since.elapsed().as_millis() as u64).max(1);
+    let (arrow, done, total) = match transfer.progress {
+        Progress::Upload { sent, total } => ("↑", sent, Some(total)),
+        Progress::Download { received, total } => ("↓", received, total),
+    };
+    let share = match total {
+        None | Some(0) => String::new(),
+        Some(total) => format!(" of {} ({}%)", byte_size(total), done * 100 / total),
+    };
+    format!("{} {}{} at {}/s", arrow, byte_size(done), share, byte_size(done * 1000 / elapsed))
+}
+
+fn byte_size(bytes: u64) -> String {
+    match bytes {
+        0..1024 => format!("{} B", bytes),
+        1024..1_048_576 => format!("{:.1} KB", bytes as f64 / 1024.0),
+        1_048_576..1_073_741_824 => format!("{:.1} MB", bytes as f64 / 1_048_576.0),
+        _ => format!("{:.1} GB", bytes as f64 / 1_073_741_824.0),
+    }
+}
+
+/// Like `truncated at 50.0 MB (server indicated 1.2 GB)`, for a response cut to the size limit.
+fn truncation_note(response: &ResponseRecord) -> String {
+    let received = response.truncated_from.unwrap_or(response.body.len());
+    let declared = header_value(&response.headers, "Content-Length").and_then(|length| length.trim().parse::<u64>().ok());
+    let size = match declared {
+        Some(declared) => format!("server indicated {}", byte_size(declared)),
+        None => format!("{} received", byte_size(received as u64)),
+    };
+    format!("truncated at {} ({})", byte_size(response.body.len() as u64), size)
+}
+
+/// The charset a response declares, when it isn't UTF-8. The body has been decoded by it
+/// already when it's one known here.
+fn charset_note(response: &ResponseRecord) -> Option<String> {
+    let declared = charset::declared(header_value(&response.headers, "Content-Type"), &response.body)?;
+    let note = match declared.encoding {
+        Some(Encoding::Utf8) => return None,
+        Some(_) if response.body.contains('\u{FFFD}') => "; bytes that don't fit it show as \u{FFFD}",
+        Some(_) => "",
+        None => "; not a charset known here, shown as UTF-8",
+    };
+    Some(format!("Charset {} (from {}){}", declared.name, declared.source, note))
+}
+
+/// An exchange as listed in history pickers: time, status, method, URL and request id.
+fn exchange_item(entry: &HistoryEntry) -> String {
+    let status = entry.response.as_ref().and_then(|response| response.status).map_or("---".to_string(), |code| code.to_string());
+    let id = entry.request_id.as_ref().or(entry.correlation.first().map(|(_, value)| value)).map_or(String::new(), |id| format!("  {}", id));
+    let pin = if entry.pinned {"★ "} else {""};
+    format!("{}{} {} {} {}{}", pin, entry.timestamp.get(11..19).unwrap_or_default(), status, entry.request.method, entry.request.url, id)
+}
+
+/// The section of the responses pane an exchange goes in.
+fn group_label(grouping: HistoryGrouping, entry: &HistoryEntry) -> String {
+    match grouping {
+        HistoryGrouping::None => String::new(),
+        HistoryGrouping::Host => entry.host.clone(),
+        HistoryGrouping::Saved => match &entry.saved {
+            Some((folder, name)) if folder.is_empty() => name.clone(),
+            Some((folder, name)) => format!("{} / {}", folder.join(" / "), name),
+            None => "(not saved)".to_string(),
+        },
+    }
+}
+
+/// How a history entry appears in the responses pane, `highlight` being the selected header.
+fn entry_lines(
+    entry: &HistoryEntry,
+    body: Option<&[Line<'static>]>,
+    notes: &Notes,
+    theme: &Theme,
+    view: &ResponseView,
+    highlight: Option<usize>,
+    width: usize,
+) -> Vec<Line<'static>> {
+    let badge = match (&entry.response, &entry.error) {
+        (Some(response), _) => theme.status_badge(response.status),
+        (None, Some(_)) => theme.badge("FAILED", Tone::Bad),
+        (None, None) => theme.badge("…", Tone::Neutral),
+    };
+    let latency = entry.timing.map(|timing| format!("  {} ms", timing.latency_ms)).unwrap_or_default();
+    let mut first = vec![badge];
+    if entry.pinned {
+        first.insert(0, Span::styled("★ ", theme.key));
+    }
+    if notes.selected {
+        first.insert(0, Span::styled("▶ ", theme.selected));
+    }
+    if let Some(environment) = &entry.environment {
+        first.extend([Span::raw(" "), theme.badge(environment, Tone::Neutral)]);
+    }
+    first.push(Span::raw(format!(" {} {}{}", entry.request.method, notes.masker.text(&entry.request.url), latency)));
+    let mut lines = vec![Line::from(first)];
+    if !entry.failed_attempts.is_empty() {
+        let attempts: Vec<String> = entry.failed_attempts
+            .iter()
+            .map(|(sent, error)| format!("{} {}", sent.get(11..19).unwrap_or_default(), error))
+            .collect();
+        lines.push(Line::styled(format!("Attempt {}; earlier: {}", attempts.len() + 1, attempts.join("; ")), theme.dim));
+    }
+    if !entry.repeats.is_empty() {
+        let outcome = |repeat: &Repeat| {
+            let outcome = match (repeat.status, &repeat.error) {
+                (Some(status), _) => status.to_string(),
+                (None, Some(_)) => "failed".to_string(),
+                (None, None) => repeat.reason.clone(),
+            };
+            match repeat.latency_ms {
+                Some(latency) => format!("{} {} ms", outcome, latency),
+                None => outcome,
+            }
+        };
+        match notes.each_repeat {
+            true => {
+                lines.push(Line::styled(format!("×{} in a row; before:", entry.runs()), theme.dim));
+                for repeat in &entry.repeats {
+                    let sent = repeat.timestamp.get(11..19).unwrap_or_default();
+                    lines.push(Line::styled(format!("  {} {}", sent, outcome(repeat)), theme.dim));
+                }
+            }
+            false => {
+                let before: Vec<String> = entry.repeats.iter().take(REPEATS_SHOWN).map(outcome).collect();
+                let more = if entry.repeats.len() > REPEATS_SHOWN {", …"} else {""};
+                lines.push(Line::styled(format!("×{} in a row; before: {}{}", entry.runs(), before.join(", "), more), theme.dim));
+            }
+        }
+    }
+    if entry.request_id.is_some() || !entry.correlation.is_empty() {
+        let mut spans = vec![];
+        if let Some(id) = &entry.request_id {
+            spans.extend([Span::styled("Sent id ", theme.key), Span::styled(id.clone(), theme.heading)]);
+        }
+        for (name, value) in &entry.correlation {
+            let echoed = if entry.request_id.as_deref() == Some(value.as_str()) {" (echoed)"} else {""};
+            spans.extend([Span::styled(format!("  {} ", name), theme.key), Span::raw(format!("{}{}", value, echoed))]);
+        }
+        lines.push(Line::from(spans));
+    }
+    if !notes.chain.is_empty() {
+        lines.push(Line::styled(format!("Chain: {} → this", notes.chain.join(" → ")), theme.dim));
+    }
+    if let Some(note) = &entry.token_refresh {
+        lines.push(Line::from(vec![Span::styled("Auth ", theme.key), Span::raw(note.clone())]));
+    }
+    if let Some(peer) = &entry.peer {
+        let family = peer.parse().map(|addr| format!(" ({})", net::family(&addr))).unwrap_or_default();
+        let cached = if entry.timing.is_some_and(|timing| timing.dns_cached) {" (cached)"} else {""};
+        let override_note = if entry.overridden {" (connect-to override)"} else {""};
+        lines.push(Line::styled(format!("Host: {}  Peer: {}{}{}{}  TLS: none", entry.host, peer, family, cached, override_note), theme.dim));
+        // The client only speaks plain HTTP, so there's no handshake or certificate to show.
+        if entry.request.url.trim_start().get(..8).is_some_and(|scheme| scheme.eq_ignore_ascii_case("https://")) {
+            lines.push(Line::from(theme.badge("https:// was sent as plain HTTP; no TLS details to show", Tone::Warning)));
+        }
+    }
+    if let Some(timing) = entry.timing {
+        let body_bytes = entry.response.as_ref().map_or(0, |response| response.body.len());
+        if let Some(line) = timing_line(&timing, body_bytes, theme) {
+            lines.push(line);
+        }
+    }
+    for hop in &entry.redirects {
+        let dropped = if hop.dropped.is_empty() {String::new()} else {format!(" (host changed, dropped {})", hop.dropped.join(", "))};
+        lines.push(Line::styled(format!("{} {} -> {}{}", hop.status, hop.from, hop.to, dropped), theme.dim));
+    }
+    if let Some(stopped) = &entry.redirect_stopped {
+        lines.push(Line::from(theme.badge(stopped, Tone::Warning)));
+    }
+    if let Some(error) = &entry.error {
+        lines.push(Line::styled(theme.error_text(error), theme.error));
+    }
+    if let Some(response) = &entry.response {
+        if response.status == Some(304) {
+            lines.push(Line::from("Not Modified — cached representation still valid"));
+        }
+        lines.push(Line::styled(response.reason.clone(), theme.heading));
+        if response.truncated_from.is_some() {
+            lines.push(Line::from(theme.badge(&truncation_note(response), Tone::Warning)));
+        }
+        if let Some(note) = charset_note(response) {
+            lines.push(Line::styled(note, theme.dim));
+        }
+        match notes.baseline {
+            Some([]) => lines.push(Line::from(theme.badge("matches baseline", Tone::Good))),
+            Some(differences) => {
+                lines.push(Line::from(theme.badge("differs from baseline", Tone::Warning)));
+                lines.extend(differences.iter().map(|difference| Line::from(format!("  {}", difference))));
+            }
+            None => {}
+        }
+        for outcome in notes.hooks {
+            let line = if outcome.ok {
+                Line::styled(format!("after: {}", outcome.message), theme.dim)
+            } else {
+                Line::styled(theme.error_text(&format!("after: {}", outcome.message)), theme.error)
+            };
+            lines.push(line);
+        }
+        match &entry.schema_check {
+            Some(check) if check.passed() => {
+                lines.push(Line::from(vec![theme.badge("schema pass", Tone::Good), Span::styled(format!(" {}", check.schema), theme.dim)]));
+            }
+            Some(check) => {
+                lines.push(Line::from(vec![theme.badge("schema fail", Tone::Bad), Span::styled(format!(" {}", check.schema), theme.dim)]));
+                if let Some(error) = &check.error {
+                    lines.push(Line::styled(theme.error_text(&format!("  couldn't check: {}", error)), theme.error));
+                }
+                lines.extend(check.violations.iter().map(|violation| Line::from(format!("  {}", violation))));
+            }
+            None => {}
+        }
+        if view.expanded {
+            let order = if view.sorted {", sorted"} else {""};
+            lines.push(Line::styled(format!("Headers ({}{})", response.headers.len(), order), theme.heading));
+            for (i, (name, value)) in displayed_headers(response, view.sorted).into_iter().enumerate() {
+                lines.extend(header_lines(name, &notes.masker.header_value(name, value), width, theme, highlight == Some(i)));
+            }
+        } else {
+            lines.push(Line::styled(format!("Headers ({}) — H to expand", response.headers.len()), theme.dim));
+        }
+        lines.push(Line::from(""));
+        let raw: Vec<Line<'static>>;
+        let body = match body {
+            Some(body) => body,
+            None => {
+                raw = response.body.lines().map(|line| Line::from(line.to_string())).collect();
+                &raw
+            }
+        };
+        for line in body {
+            let masked;
+            let line = match line.spans.iter().any(|span| notes.masker.touches(&span.content)) {
+                true => {
+                    masked = Line::from(line.spans.iter().map(|span| Span::styled(notes.masker.text(&span.content), span.style)).collect::<Vec<_>>());
+                    &masked
+                }
+                false => line,
+            };
+            if view.wrap {
+                lines.extend(wrap_line(line, width, theme.dim));
+            } else {
+                lines.push(shift_line(line, view.column));
+            }
+        }
+    }
+    lines.push(Line::from("---------------------------------"));
+    lines
+}
+
+/// Body lines from a little above the current search match on, with every match picked out.
+/// The pane doesn't scroll, so this is how `n` and `N` bring a match into view.
+fn search_lines(lines: &[Line<'static>], search: &Search, theme: &Theme) -> Vec<Line<'static>> {
+    let current = search.matches[search.current];
+    let first = current.line.saturating_sub(SEARCH_CONTEXT);
+    let mut matches = search.matches.iter().skip_while(|found| found.line < first).peekable();
+    lines[first.min(lines.len())..].iter().enumerate().map(|(offset, line)| {
+        let index = first + offset;
+        let mut ranges = vec![];
+        while let Some(found) = matches.next_if(|found| found.line == index) {
+            let style = if *found == current {theme.selected} else {theme.matched};
+            ranges.push((found.start, found.end, style));
+        }
+        if ranges.is_empty() {line.clone()} else {restyle(line, &ranges)}
+    }).collect()
+}
+
+/// `line` with the given byte ranges of its text in their own styles.
+fn restyle(line: &Line<'static>, ranges: &[(usize, usize, Style)]) -> Line<'static> {
+    let mut spans = vec![];
+    let mut offset = 0;
+    for span in &line.spans {
+        let text = span.content.as_ref();
+        let end = offset + text.len();
+        let mut at = offset;
+        for &(start, stop, style) in ranges.iter().filter(|(start, stop, _)| *start < end && *stop > offset) {
+            let (start, stop) = (start.max(offset), stop.min(end));
+            if start > at {
+                spans.push(Span::styled(text[at - offset..start - offset].to_string(), span.style));
+            }
+            spans.push(Span::styled(text[start - offset..stop - offset].to_string(), span.style.patch(style)));
+            at = stop;
+        }
+        if at < end {
+            spans.push(Span::styled(text[at - offset..].to_string(), span.style));
+        }
+        offset = end;
+    }
+    Line::from(spans).style(line.style)
+}
+
+/// `00000010  48 65 6c 6c 6f  |Hello|`, sixteen bytes a line.
+fn hex_dump(bytes: &[u8]) -> Vec<String> {
+    bytes
+        .chunks(16)
+        .enumerate()
+        .map(|(row, chunk)| {
+            let hex: Vec<String> = chunk.iter().map(|byte| format!("{:02x}", byte)).collect();
+            let text: String = chunk.iter().map(|&byte| if byte.is_ascii_graphic() || byte == b' ' {byte as char} else {'.'}).collect();
+            format!("{:08x}  {:<47}  |{}|", row * 16, hex.join(" "), text)
+        })
+        .collect()
+}
+
+/// The body as shown in the responses pane: XML reindented and highlighted, HTML optionally
+/// reduced to its text, anything else as is. `method` is what was sent, so a HEAD response
+/// gets a note rather than an empty pane.
+fn render_body(response: &ResponseRecord, method: &str, theme: &Theme, readable_html: bool, json_view: JsonView) -> Vec<Line<'static>> {
+    if let Some(reason) = response.no_body_reason(method) {
+        return vec![Line::styled(format!("(no body — {})", reason), theme.dim)];
+    }
+    if let (JsonView::Raw, Some(bytes)) = (json_view, &response.raw) {
+        let mut lines = vec![Line::styled("Bytes as received, before decoding (t shows the text)", theme.dim)];
+        lines.extend(hex_dump(&bytes[..bytes.len().min(RAW_DUMP_LIMIT)]).into_iter().map(Line::from));
+        if bytes.len() > RAW_DUMP_LIMIT {
+            lines.push(Line::styled(format!("… {} more", byte_size((bytes.len() - RAW_DUMP_LIMIT) as u64)), theme.dim));
+        }
+        return lines;
+    }
+    let raw = || response.body.lines().map(|line| Line::from(line.to_string()));
+    let content_type = header_value(&response.headers, "Content-Type");
+    if json_view != JsonView::Raw && editor::is_json(content_type, &response.body) {
+        // The tree is drawn separately; older responses show formatted text meanwhile.
+        if let Ok(formatted) = serde_json::from_str::<serde_json::Value>(&response.body).and_then(|value| serde_json::to_string_pretty(&value)) {
+            return formatted.lines().map(|line| Line::from(line.to_string())).collect();
+        }
+    }
+    if html::looks_like_html(content_type, &response.body) {
+        if !readable_html {
+            return raw().collect();
+        }
+        let mut lines = vec![Line::styled("Readable view of HTML (v in the responses pane shows the markup)", theme.dim)];
+        lines.extend(html::readable(&response.body).into_iter().map(|block| match block {
+            html::Block::Heading(text) => Line::styled(text, theme.heading),
+            html::Block::Item(text) => Line::from(format!("  • {}", text)),
+            html::Block::Text(text) => Line::from(text),
+        }));
+        return lines;
+    }
+    if !xml::looks_like_xml(content_type, &response.body) {
+        return raw().collect();
+    }
+    match xml::format(&response.body) {
+        Ok(formatted) => formatted
+            .into_iter()
+            .map(|line| {
+                let mut spans = vec![Span::raw("  ".repeat(line.depth))];
+                spans.extend(line.parts.into_iter().map(|(part, text)| {
+                    let style = match part {
+                        Part::Markup => theme.dim,
+                        Part::TagName => theme.tag,
+                        Part::AttrName => theme.attribute,
+                        Part::AttrValue => theme.value,
+                        Part::Text => theme.text,
+                        Part::Comment => theme.comment,
+                    };
+                    Span::styled(text, style)
+                }));
+                Line::from(spans)
+            })
+            .collect(),
+        Err(e) => {
+            let mut lines = vec![Line::styled(format!("Not well-formed XML ({}); shown as received", e), theme.dim)];
+            lines.extend(raw());
+            lines
+        }
+    }
+}
+
+/// A file extension for a body of this type, so editors and viewers pick the right mode.
+fn extension_for(content_type: Option<&str>) -> &'static str {
+    match content_type.unwrap_or_default().to_ascii_lowercase().as_str() {
+        t if t.contains("json") => "json",
+        t if t.contains("xml") => "xml",
+        t if t.contains("html") => "html",
+        t if t.contains("yaml") => "yaml",
+        t if t.contains("graphql") => "graphql",
+        _ => "txt",
+    }
+}
+
+fn displayed_headers(response: &ResponseRecord, sorted: bool) -> Vec<&(String, String)> {
+    let mut headers: Vec<&(String, String)> = response.headers.iter().collect();
+    if sorted {
+        headers.sort_by_key(|(name, _)| name.to_lowercase());
+    }
+    headers
+}
+
+/// Resolved text as lines, values filled in from placeholders marked and placeholders with
+/// nothing found for them in the error style.
+fn piece_lines(pieces: &[Piece], theme: &Theme, masker: &Masker) -> Vec<Line<'static>> {
+    let mut lines = vec![];
+    let mut spans: Vec<Span<'static>> = vec![];
+    for piece in pieces {
+        let (text, style) = match piece {
+            Piece::Text(text) => (masker.text(text), Style::default()),
+            Piece::Substituted { value, .. } => (masker.text(value), theme.value.patch(theme.matched)),
+            Piece::Missing(expression) => (format!("{{{{{}}}}}", expression), theme.error.patch(theme.matched)),
+        };
+        for (i, part) in text.split('\n').enumerate() {
+            if i > 0 {
+                lines.push(Line::from(std::mem::take(&mut spans)));
+            }
+            if !part.is_empty() {
+                spans.push(Span::styled(part.trim_end_matches('\r').to_string(), style));
+            }
+        }
+    }
+    lines.push(Line::from(spans));
+    lines
+}
+
+/// `Name: value` wrapped to `width`, continuation lines indented under the name.
+fn header_lines(name: &str, value: &str, width: usize, theme: &Theme, selected: bool) -> Vec<Line<'static>> {
+    const INDENT: usize = 4;
+    let width = width.max(INDENT + 10);
+    let patch = |style: Style| if selected {style.patch(theme.selected)} else {style};
+    let value: Vec<char> = value.chars().collect();
+    let first = width.saturating_sub(name.chars().count() + 2).max(10).min(value.len());
+    let mut lines = vec![Line::from(vec![
+        Span::styled(format!("{}: ", name), patch(theme.key)),
+        Span::styled(value[..first].iter().collect::<String>(), patch(theme.text)),
+    ])];
+    for chunk in value[first..].chunks(width - INDENT) {
+        lines.push(Line::from(vec![
+            Span::styled(" ".repeat(INDENT), patch(theme.text)),
+            Span::styled(chunk.iter().collect::<String>(), patch(theme.text)),
+        ]));
+    }
+    lines
+}
+
+/// A request the test server received, laid out like a response in the responses pane.
+fn received_lines(received: &Received, theme: &Theme, masker: &Masker, width: usize) -> Vec<Line<'static>> {
+    let mut lines = vec![Line::from(vec![
+        theme.badge(&received.method, Tone::Neutral),
+        Span::raw(format!(" {}", received.path)),
+        Span::styled(format!("  {}", received.timestamp), theme.dim),
+    ])];
+    lines.push(Line::styled(format!("Headers ({})", received.headers.len()), theme.heading));
+    for (name, value) in &received.headers {
+        lines.extend(header_lines(name, &masker.header_value(name, value), width, theme, false));
+    }
+    if !received.body.is_empty() {
+        lines.push(Line::from(""));
+        let body = serde_json::from_str::<serde_json::Value>(&received.body)
+            .and_then(|value| serde_json::to_string_pretty(&value))
+            .unwrap_or_else(|_| received.body.clone());
+        for line in body.lines() {
+            lines.extend(wrap_line(&Line::from(masker.text(line)), width, theme.dim));
+        }
+    }
+    lines.push(Line::from("---------------------------------"));
+    lines
+}
+
+/// Up to `window` rows of the tree around the selection, with counts of those cut off.
+fn json_tree_lines(tree: &json_tree::Tree, theme: &Theme, focused: bool, window: usize) -> Vec<Line<'static>> {
+    let rows = tree.rows();
+    let first = (tree.selected + 1).saturating_sub(window);
+    let mut lines = vec![Line::styled("JSON tree — Enter/Space folds, y copies the value, Y the path, t switches view", theme.dim)];
+    if first > 0 {
+        lines.push(Line::styled(format!("↑ {} more", first), theme.dim));
+    }
+    for (i, row) in rows.iter().enumerate().skip(first).take(window) {
+        let marker = match (row.container, row.expanded) {
+            (false, _) => "  ",
+            (true, true) => "▾ ",
+            (true, false) => "▸ ",
+        };
+        let label = match row.path.last() {
+            Some(json_tree::Segment::Key(key)) => format!("{}: ", key),
+            Some(json_tree::Segment::Index(index)) => format!("[{}] ", index),
+            None => String::new(),
+        };
+        let selected = i == tree.selected;
+        let style = |style: Style| match (selected, focused) {
+            (true, true) => theme.selected,
+            (true, false) => theme.selected_unfocused,
+            _ => style,
+        };
+        lines.push(Line::from(vec![
+            Span::styled(format!("{}{}", "  ".repeat(row.path.len()), marker), style(theme.dim)),
+            Span::styled(label, style(theme.key)),
+            Span::styled(row.summary.clone(), style(if row.container {theme.dim} else {theme.value})),
+        ]));
+    }
+    let after = rows.len().saturating_sub(first + window);
+    if after > 0 {
+        lines.push(Line::styled(format!("↓ {} more", after), theme.dim));
+    }
+    lines
+}
+
+/// `line` broken into rows of `width` characters, keeping its styles. Rows after the first
+/// start with a continuation marker.
+fn wrap_line(line: &Line<'static>, width: usize, marker: Style) -> Vec<Line<'static>> {
+    const MARKER: &str = "↪ ";
+    let width = width.max(MARKER.chars().count() + 1);
+    let mut rows = vec![Line::default().style(line.style)];
+    let mut room = width;
+    for span in &line.spans {
+        let mut rest: &str = &span.content;
+        while !rest.is_empty() {
+            if room == 0 {
+                rows.push(Line::from(Span::styled(MARKER, marker)).style(line.style));
+                room = width - MARKER.chars().count();
+            }
+            let split = rest.char_indices().nth(room).map_or(rest.len(), |(i, _)| i);
+            let (taken, after) = rest.split_at(split);
+            room -= taken.chars().count();
+            if let Some(row) = rows.last_mut() {
+                row.push_span(Span::styled(taken.to_string(), span.style));
+            }
+            rest = after;
+        }
+    }
+    rows
+}
+
+/// `line` without its first `column` characters.
+fn shift_line(line: &Line<'static>, column: usize) -> Line<'static> {
+    let mut skip = column;
+    let mut shifted = Line::default().style(line.style);
+    for span in &line.spans {
+        let count = span.content.chars().count();
+        if skip >= count {
+            skip -= count;
+            continue;
+        }
+        shifted.push_span(Span::styled(span.content.chars().skip(skip).collect::<String>(), span.style));
+        skip = 0;
+    }
+    shifted
+}
+
+fn header_table_rows(text: &str, table: &HeaderTable) -> Vec<kv::Row> {
+    let mut rows = kv::header_rows(text);
+    if table.adding {
+        rows.insert(table.selected.min(rows.len()), kv::Row { enabled: true, key: String::new(), value: Some(String::new()) });
+    }
+    rows
+}
+
+/// `text` fitted to `width` columns: cut short with an ellipsis or, while it's being edited,
+/// scrolled so the cursor stays in view.
+fn cell_spans(text: &str, width: usize, cursor: Option<usize>, style: Style, cursor_style: Style) -> Vec<Span<'static>> {
+    let width = width.max(1);
+    let chars: Vec<char> = text.chars().collect();
+    let Some(cursor) = cursor else {
+        let shown: String = if chars.len() > width {
+            chars[..width.saturating_sub(1)].iter().chain(['…'].iter()).collect()
+        } else {
+            text.to_string()
+        };
+        return vec![Span::styled(format!("{:<width$}", shown), style)];
+    };
+    let start = (cursor + 1).saturating_sub(width);
+    let end = (start + width).min(chars.len());
+    let before: String = chars[start..cursor].iter().collect();
+    let at = chars.get(cursor).map_or(" ".to_string(), char::to_string);
+    let after: String = chars.get(cursor + 1..end).map_or(String::new(), |after| after.iter().collect());
+    let padding = width.saturating_sub(cursor - start + 1 + after.chars().count());
+    vec![
+        Span::styled(before, style),
+        Span::styled(at, cursor_style),
+        Span::styled(format!("{}{}", after, " ".repeat(padding)), style),
+    ]
+}
+
+/// Splits `text` into spans, adding the `matched` style to the chars at `positions`.
+fn highlighted(text: &str, positions: &[usize], style: Style, matched: Style) -> Vec<Span<'static>> {
+    let matched = style.patch(matched);
+    text.chars()
+        .enumerate()
+        .map(|(i, c)| Span::styled(c.to_string(), if positions.contains(&i) {matched} else {style}))
+        .collect()
+}
+
+fn jwt_lines(token: &Jwt, now: i64, theme: &Theme) -> Vec<Line<'static>> {
+    let (badge, tone) = match token.validity(now) {
+        Validity::Valid => ("VALID", Tone::Good),
+        Validity::Expired => ("EXPIRED", Tone::Bad),
+        Validity::NotYetValid => ("NOT YET VALID", Tone::Warning),
+        Validity::NoExpiry => ("NO EXPIRY", Tone::Neutral),
+    };
+    let mut lines = vec![Line::from(theme.badge(badge, tone))];
+    for claim in TIME_CLAIMS {
+        if let Some(secs) = token.claim_time(claim) {
+            lines.push(Line::from(format!("{}: {}", claim, jwt::format_timestamp(secs, now))));
+        }
+    }
+    for (title, value) in [("Header", &token.header), ("Payload", &token.payload)] {
+        lines.push(Line::from(""));
+        lines.push(Line::styled(title, theme.heading));
+        let pretty = serde_json::to_string_pretty(value).unwrap_or_default();
+        lines.extend(pretty.lines().map(|line| Line::from(line.to_string())));
+    }
+    lines
+}
+#[cfg(test)]
+mod tests {
+    use std::{env, process, sync::{Mutex, MutexGuard}};
+
+    use ratatui::{backend::TestBackend, Terminal};
+
+    use super::*;
+
+    /// A test's hold on the config directory, which is deleted when the test is done.
+    struct Turn {
+        _guard: MutexGuard<'static, ()>,
+        dir: PathBuf,
+    }
+
+    impl Drop for Turn {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    /// An empty config directory of the tests' own, so nothing on the machine running them is
+    /// read or written. The directory is in the environment, which every test shares, so they
+    /// take turns: each holds the returned `Turn` until it's done.
+    fn turn() -> Turn {
+        static CONFIG: Mutex<()> = Mutex::new(());
+        let guard = CONFIG.lock().unwrap_or_else(|e| e.into_inner());
+        let dir = env::temp_dir().join(format!("tui_postman_tests_{}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        env::set_var("XDG_CONFIG_HOME", &dir);
+        env::remove_var(vault::PASSPHRASE_VAR);
+        fs::create_dir_all(config::config_dir()).unwrap();
+        Turn { _guard: guard, dir }
+    }
+
+    fn app() -> (App, Turn) {
+        let turn = turn();
+        (App::new("127.0.0.1:1".to_string(), false), turn)
+    }
+
+    fn press(app: &mut App, code: KeyCode) {
+        app.handle_event(AppEvent::Key(KeyEvent::new(code, KeyModifiers::NONE)));
+    }
+
+    fn type_text(app: &mut App, text: &str) {
+        text.chars().for_each(|c| press(app, KeyCode::Char(c)));
+    }
+
+    fn screen(app: &App, width: u16, height: u16) -> Vec<String> {
+        let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+        terminal.draw(|frame| app.draw(frame)).unwrap();
+        let buffer = terminal.backend().buffer();
+        (0..height).map(|y| (0..width).map(|x| buffer[(x, y)].symbol()).collect::<String>().trim_end().to_string()).collect()
+    }
+
+    fn ctrl(app: &mut App, c: char) {
+        app.handle_event(AppEvent::Key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL)));
+    }
+
+    #[test]
+    fn typing_goes_to_the_focused_field() {
+        let (mut app, _turn) = app();
+        type_text(&mut app, "{\"a\":12}");
+        press(&mut app, KeyCode::Left);
+        press(&mut app, KeyCode::Backspace);
+        press(&mut app, KeyCode::Up);
+        type_text(&mut app, "Accept: */*");
+        assert_eq!(app.body_input, "{\"a\":1}");
+        assert_eq!(app.headers_input, "Accept: */*");
+        assert_eq!(app.focus_target(), Focus::Headers);
+    }
+
+    fn alt(app: &mut App, c: char) {
+        app.handle_event(AppEvent::Key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::ALT)));
+    }
+
+    /// The body set to `text` with the cursor `at` chars in.
+    fn body_at(app: &mut App, text: &str, at: usize) {
+        app.set_focus(Focus::Body);
+        app.body_input = text.to_string();
+        app.character_index = at;
+    }
+
+    #[test]
+    fn kill_to_line_end_and_yank_it_back_elsewhere() {
+        let (mut app, _turn) = app();
+        body_at(&mut app, "{\n  \"a\": 1,\n  \"b\": 2\n}", 9);
+        ctrl(&mut app, 'k');
+        assert_eq!(app.body_input, "{\n  \"a\": \n  \"b\": 2\n}");
+        assert_eq!(app.kill_buffer, "1,");
+        press(&mut app, KeyCode::Down);
+        press(&mut app, KeyCode::Down);
+        app.url_input = "http://x/".to_string();
+        app.character_index = 9;
+        ctrl(&mut app, 'y');
+        ctrl(&mut app, 'y');
+        assert_eq!(app.url_input, "http://x/1,1,");
+        assert_eq!(app.character_index, 13);
+    }
+
+    #[test]
+    fn kill_to_line_start_stops_at_the_newline() {
+        let (mut app, _turn) = app();
+        body_at(&mut app, "one\ntwo three", 8);
+        ctrl(&mut app, 'u');
+        assert_eq!((app.body_input.as_str(), app.kill_buffer.as_str(), app.character_index), ("one\nthree", "two ", 4));
+        // Nothing before the cursor on its line: the buffer keeps what it had.
+        ctrl(&mut app, 'u');
+        assert_eq!((app.body_input.as_str(), app.kill_buffer.as_str()), ("one\nthree", "two "));
+        app.character_index = 3;
+        ctrl(&mut app, 'k');
+        assert_eq!(app.kill_buffer, "two ");
+    }
+
+    #[test]
+    fn kill_and_yank_count_chars_not_bytes() {
+        let (mut app, _turn) = app();
+        body_at(&mut app, "é→👍x\nñ", 2);
+        ctrl(&mut app, 'k');
+        assert_eq!((app.body_input.as_str(), app.kill_buffer.as_str()), ("é→\nñ", "👍x"));
+        app.character_index = 4;
+        ctrl(&mut app, 'y');
+        assert_eq!(app.body_input, "é→\nñ👍x");
+        assert_eq!(app.character_index, 6);
+        type_text(&mut app, "ü");
+        press(&mut app, KeyCode::Left);
+        press(&mut app, KeyCode::Left);
+        ctrl(&mut app, 'u');
+        assert_eq!((app.body_input.as_str(), app.kill_buffer.as_str()), ("é→\nxü", "ñ👍"));
+    }
+
+    #[test]
+    fn undo_takes_back_kills_and_yanks_in_their_own_field() {
+        let (mut app, _turn) = app();
+        body_at(&mut app, "abc def", 3);
+        ctrl(&mut app, 'k');
+        ctrl(&mut app, 'y');
+        ctrl(&mut app, 'y');
+        assert_eq!(app.body_input, "abc def def");
+        app.set_focus(Focus::Headers);
+        alt(&mut app, 'z');
+        assert_eq!(app.focus_target(), Focus::Body);
+        assert_eq!((app.body_input.as_str(), app.character_index), ("abc def", 7));
+        alt(&mut app, 'z');
+        alt(&mut app, 'z');
+        assert_eq!((app.body_input.as_str(), app.character_index), ("abc def", 3));
+        assert_eq!(app.kill_buffer, " def");
+        alt(&mut app, 'z');
+        assert_eq!(app.error_message.as_ref().unwrap().summary, "Nothing to undo");
+    }
+
+    #[test]
+    fn tab_cycles_focus_and_skips_the_hidden_collection() {
+        let (mut app, _turn) = app();
+        let mut seen = vec![];
+        for _ in 0..6 {
+            press(&mut app, KeyCode::Tab);
+            seen.push(app.focus_target());
+        }
+        assert_eq!(seen, [Focus::Responses, Focus::Method, Focus::Url, Focus::Headers, Focus::Body, Focus::Responses]);
+        press(&mut app, KeyCode::BackTab);
+        assert_eq!(app.focus_target(), Focus::Body);
+    }
+
+    #[test]
+    fn collection_pane_opens_focused_and_esc_returns_to_the_editor() {
+        let (mut app, _turn) = app();
+        press(&mut app, KeyCode::F(3));
+        assert!(app.show_collection);
+        assert_eq!(app.focus_target(), Focus::Collection);
+        press(&mut app, KeyCode::Esc);
+        assert_eq!(app.focus_target(), Focus::Body);
+        assert!(app.show_collection);
+    }
+
+    #[test]
+    fn arrows_on_the_method_pick_the_method() {
+        let (mut app, _turn) = app();
+        app.set_focus(Focus::Method);
+        press(&mut app, KeyCode::Right);
+        press(&mut app, KeyCode::Right);
+        press(&mut app, KeyCode::Left);
+        assert_eq!(method_name(app.method_index), "POST");
+        press(&mut app, KeyCode::Left);
+        press(&mut app, KeyCode::Left);
+        assert_eq!(method_name(app.method_index), "OPTIONS");
+        type_text(&mut app, "x");
+        assert_eq!(app.url_input, "127.0.0.1:1");
+        assert_eq!(app.body_input, "");
+    }
+
+    #[test]
+    fn paste_keeps_newlines_in_the_body_but_not_the_url() {
+        let (mut app, _turn) = app();
+        app.set_focus(Focus::Url);
+        app.url_input.clear();
+        app.handle_event(AppEvent::Paste("http://x/a\nb".to_string()));
+        app.set_focus(Focus::Body);
+        app.handle_event(AppEvent::Paste("{\r\n}".to_string()));
+        assert_eq!(app.url_input, "http://x/ab");
+        assert_eq!(app.body_input, "{\n}");
+    }
+
+    #[test]
+    fn palette_opens_filters_and_closes() {
+        let (mut app, _turn) = app();
+        ctrl(&mut app, 'p');
+        type_text(&mut app, "toggle console");
+        assert!(matches!(&app.popup, Some(Popup::Palette { filter, .. }) if filter == "toggle console"));
+        press(&mut app, KeyCode::Enter);
+        assert!(app.popup.is_none());
+        assert!(app.show_console);
+    }
+
+    #[test]
+    fn a_refused_connection_lands_in_history_as_an_error() {
+        let (mut app, _turn) = app();
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(app.in_flight, 1);
+        while app.in_flight > 0 {
+            let event = app.worker.events.recv_timeout(Duration::from_secs(10)).expect("no answer from the worker");
+            app.handle_event(AppEvent::Worker(event));
+        }
+        let entry = app.history.last().unwrap();
+        assert!(entry.response.is_none());
+        assert!(entry.error.is_some());
+        assert!(app.error_message.is_some());
+    }
+
+    #[test]
+    fn star_pins_the_exchange_selected_with_j_and_k() {
+        let (mut app, _turn) = app();
+        press(&mut app, KeyCode::Enter);
+        while app.in_flight > 0 {
+            let event = app.worker.events.recv_timeout(Duration::from_secs(10)).expect("no answer from the worker");
+            app.handle_event(AppEvent::Worker(event));
+        }
+        for (id, path) in [(101, "/older"), (102, "/newest")] {
+            let mut entry = app.history[0].clone();
+            entry.id = id;
+            entry.request.url = format!("http://127.0.0.1:1{}", path);
+            app.history.push(entry);
+        }
+        let first = app.history[0].id;
+        app.focus = Pane::Responses;
+        press(&mut app, KeyCode::Char('*'));
+        assert_eq!(app.history.iter().filter(|entry| entry.pinned).map(|entry| entry.id).collect::<Vec<_>>(), [102], "the newest until one is picked");
+        press(&mut app, KeyCode::Char('*'));
+
+        press(&mut app, KeyCode::Char('j'));
+        press(&mut app, KeyCode::Char('j'));
+        press(&mut app, KeyCode::Char('j'));
+        assert_eq!(app.selected_exchange(), Some(first), "stops at the oldest");
+        press(&mut app, KeyCode::Char('k'));
+        press(&mut app, KeyCode::Char('*'));
+        assert_eq!(app.history.iter().filter(|entry| entry.pinned).map(|entry| entry.id).collect::<Vec<_>>(), [101]);
+        assert_eq!(app.listed_exchanges()[0].id, 101, "pinned ones go to the top");
+        assert!(screen(&app, 100, 40).iter().any(|line| line.contains("▶ ★") && line.contains("/older")));
+    }
+
+    /// A session, a draft and a locked secret, all of which a start in the terminal asks about.
+    fn leave_state_behind() {
+        let editor = Session { url: "http://left.over".to_string(), environment: Some("dev".to_string()), ..Session::default() };
+        session::save(&config::session_path(), &editor).unwrap();
+        session::save_draft(&config::drafts_path(), &Draft { saved_at: "2026-01-01T00:00:00Z".to_string(), editor }).unwrap();
+        let environments = r#"[{"name": "dev", "variables": {"host": "dev.test", "token": "t"}, "secrets": ["token"]}]"#;
+        fs::write(config::environments_path(), environments).unwrap();
+    }
+
+    #[test]
+    fn terminal_start_restores_and_asks() {
+        let _turn = turn();
+        leave_state_behind();
+        let app = App::new("127.0.0.1:1".to_string(), true);
+        assert_eq!(app.url_input, "http://left.over");
+        assert!(matches!(&app.popup, Some(Popup::Confirm { message, .. }) if message.starts_with("Recover unsaved draft")));
+    }
+
+    #[test]
+    fn headless_start_is_fresh_and_asks_nothing() {
+        let _turn = turn();
+        leave_state_behind();
+        config::save(&Config { terminal_title: true, ..Config::default() }).unwrap();
+        let app = App::headless("127.0.0.1:1".to_string());
+        assert_eq!(app.url_input, "127.0.0.1:1");
+        assert_eq!(app.terminal_title, None, "no title escapes in the report on stdout");
+        assert_eq!(app.active_environment, None);
+        assert!(app.popup.is_none());
+        let error = app.error_message.as_ref().unwrap().summary.clone();
+        assert_eq!(error, "Secret variables stay locked; set TUI_POSTMAN_PASSPHRASE to unlock them");
+        assert!(config::drafts_path().exists());
+        let error = app.run_headless(vec![], Some("staging")).err().unwrap();
+        assert_eq!(error, "no environment named \"staging\"");
+        assert_eq!(session::load(&config::session_path()).unwrap().unwrap().url, "http://left.over");
+    }
+
+    #[test]
+    fn exported_code_leaves_credentials_out_until_revealed() {
+        let (mut app, _turn) = app();
+        app.variables.insert("api_token".to_string(), "s3cr3t-value".to_string());
+        app.url_input = "http://api.test/x?key={{api_token}}".to_string();
+        app.headers_input = "Authorization: Bearer {{api_token}}\nCookie: id=1".to_string();
+        app.body_input = "{\"token\": \"{{api_token}}\"}".to_string();
+        let code = app.snippet(Language::Rust).unwrap();
+        assert!(!code.contains("s3cr3t-value") && !code.contains("id=1"), "{}", code);
+        assert!(code.contains(r#".query(&[("key", "[REDACTED]")])"#), "{}", code);
+        assert!(code.contains(r#".header("Cookie", "[REDACTED]")"#), "{}", code);
+        assert!(code.contains(r#".bearer_auth("[REDACTED]")"#), "{}", code);
+        assert!(code.contains(r#".body("{\"token\": \"[REDACTED]\"}")"#), "{}", code);
+        app.revealed.insert(SecretView::Snippets);
+        let code = app.snippet(Language::Rust).unwrap();
+        assert!(code.contains(r#".bearer_auth("s3cr3t-value")"#), "{}", code);
+        assert!(!code.contains(secrets::REDACTED), "{}", code);
+    }
+
+    #[test]
+    fn draws_the_editor_with_the_focused_field_marked() {
+        let (mut app, _turn) = app();
+        press(&mut app, KeyCode::Up);
+        press(&mut app, KeyCode::Up);
+        assert_eq!(screen(&app, 60, 14), [
+            "┌Methods───────────────────────────────────────────────────┐",
+            "│ GET │ POST │ PUT │ DELETE │ PATCH │ HEAD │ OPTIONS       │",
+            "└──────────────────────────────────────────────────────────┘",
+            "┏● Input━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━┓",
+            "┃127.0.0.1:1                                               ┃",
+            "┗━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━┛",
+            "┌Headers───────────────────────────────────────────────────┐",
+            "│Header-Name: value (one per line)                         │",
+            "└──────────────────────────────────────────────────────────┘",
+            "┌Body────────────────────────┐┌Responses───────────────────┐",
+            "│1 { \"json\": \"body\" }        ││                            │",
+            "│                            ││                            │",
+            "└────────────────────────────┘└────────────────────────────┘",
+            "workspace: default  |  env: none  |  Ctrl+P: commands  |  F1",
+        ]);
+    }
+
+    #[test]
+    fn draws_the_palette_over_the_editor() {
+        let (mut app, _turn) = app();
+        ctrl(&mut app, 'p');
+        type_text(&mut app, "console");
+        assert_eq!(screen(&app, 60, 14), [
+            "┌Methods───────────────────────────────────────────────────┐",
+            "│ GET │ POST │ PUT │ DELETE │ PATCH │ HEAD │ OPTIONS       │",
+            "└──────────────────────────────────────────────────────────┘",
+            "┌Input───┌Commands────────────────────────────────┐────────┐",
+            "│127.0.0.│> console                               │        │",
+            "└────────│                                        │────────┘",
+            "┌Headers─│Toggle console (F2)  Show the verbose tr│────────┐",
+            "│Header-N│Copy console  Copy the whole console tra│        │",
+            "└────────│                                        │────────┘",
+            "┏● Body━━│                                        │────────┐",
+            "┃1 { \"jso└────────────────────────────────────────┘        │",
+            "┃                            ┃│                            │",
+            "┗━━━━━━━━━━━━━━━━━━━━━━━━━━━━┛└────────────────────────────┘",
+            "Ln 1, Col 1  |  workspace: default  |  env: none  |  Ctrl+P:",
+        ]);
+    }
+}
@@ -0,0 +1,260 @@
+use std::{
+    fmt,
+    net::{Ipv4Addr, Ipv6Addr},
+};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Host {
+    Name(String),
+    Ipv4(Ipv4Addr),
+    Ipv6(Ipv6Addr),
+}
+
+impl fmt::Display for Host {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Host::Name(name) => write!(f, "{}", name),
+            Host::Ipv4(ip) => write!(f, "{}", ip),
+            Host::Ipv6(ip) => write!(f, "[{}]", ip),
+        }
+    }
+}
+
+/// A parsed request URL. The scheme is optional since `127.0.0.1:8004/echo` has always worked.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Url {
+    pub scheme: Option<String>,
+    pub host: Host,
+    pub port: Option<u16>,
+    /// Path and query, always starting with `/`.
+    pub path: String,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum UrlError {
+    EmptyHost,
+    UnclosedBracket,
+    InvalidIpv6(String),
+    UnbracketedIpv6(String),
+    ZoneIdUnsupported(String),
+    InvalidPort(String),
+}
+
+impl fmt::Display for UrlError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            UrlError::EmptyHost => write!(f, "the URL has no host"),
+            UrlError::UnclosedBracket => write!(f, "IPv6 address is missing its closing ']'"),
+            UrlError::InvalidIpv6(literal) => write!(f, "{:?} is not a valid IPv6 address", literal),
+            UrlError::UnbracketedIpv6(host) => write!(f, "IPv6 addresses must be bracketed, e.g. [{}]", host),
+            UrlError::ZoneIdUnsupported(literal) => write!(f, "IPv6 zone IDs are unsupported ({})", literal),
+            UrlError::InvalidPort(port) => write!(f, "{:?} is not a valid port", port),
+        }
+    }
+}
+
+pub fn parse(input: &str) -> Result<Url, UrlError> {
+    let input = input.trim();
+    let (scheme, rest) = match input.split_once("://") {
+        Some((scheme, rest)) => (Some(scheme.to_ascii_lowercase()), rest),
+        None => (None, input),
+    };
+    let (authority, path) = match rest.find(['/', '?']) {
+        Some(i) => (&rest[..i], &rest[i..]),
+        None => (rest, ""),
+    };
+    let (host, port) = parse_authority(authority)?;
+    let path = if path.starts_with('/') {path.to_string()} else {format!("/{}", path)};
+    Ok(Url { scheme, host, port, path })
+}
+
+/// Parses `host`, `host:port`, `[v6]` or `[v6]:port`, ignoring any `user@` prefix.
+pub fn parse_authority(authority: &str) -> Result<(Host, Option<u16>), UrlError> {
+    let authority = authority.rsplit_once('@').map_or(authority, |(_, host)| host);
+    if let Some(rest) = authority.strip_prefix('[') {
+        let (literal, after) = rest.split_once(']').ok_or(UrlError::UnclosedBracket)?;
+        if literal.contains('%') {
+            return Err(UrlError::ZoneIdUnsupported(literal.to_string()));
+        }
+        let ip = literal.parse().map_err(|_| UrlError::InvalidIpv6(literal.to_string()))?;
+        let port = match after {
+            "" => None,
+            after => {
+                let port = after.strip_prefix(':').ok_or_else(|| UrlError::InvalidPort(after.to_string()))?;
+                Some(parse_port(port)?)
+            }
+        };
+        return Ok((Host::Ipv6(ip), port));
+    }
+    if authority.matches(':').count() > 1 {
+        if authority.contains('%') {
+            return Err(UrlError::ZoneIdUnsupported(authority.to_string()));
+        }
+        return Err(UrlError::UnbracketedIpv6(authority.to_string()));
+    }
+
+    let (host, port) = match authority.split_once(':') {
+        Some((host, port)) => (host, Some(parse_port(port)?)),
+        None => (authority, None),
+    };
+    if host.is_empty() {
+        return Err(UrlError::EmptyHost);
+    }
+    let host = match host.parse() {
+        Ok(ip) => Host::Ipv4(ip),
+        Err(_) => Host::Name(host.to_string()),
+    };
+    Ok((host, port))
+}
+
+impl Host {
+    /// Whether the host is this machine: a loopback address or `localhost`.
+    pub fn is_local(&self) -> bool {
+        match self {
+            Host::Name(name) => {
+                let name = name.to_ascii_lowercase();
+                name == "localhost" || name.ends_with(".localhost")
+            }
+            Host::Ipv4(ip) => ip.is_loopback(),
+            Host::Ipv6(ip) => ip.is_loopback(),
+        }
+    }
+}
+
+fn parse_port(port: &str) -> Result<u16, UrlError> {
+    port.parse().map_err(|_| UrlError::InvalidPort(port.to_string()))
+}
+
+impl Url {
+    /// `host[:port]` as written, which is what the Host header carries.
+    pub fn authority(&self) -> String {
+        match self.port {
+            Some(port) => format!("{}:{}", self.host, port),
+            None => self.host.to_string(),
+        }
+    }
+
+    /// The port to dial, falling back to the scheme's default.
+    pub fn effective_port(&self) -> Option<u16> {
+        self.port.or(match self.scheme.as_deref() {
+            Some("http") => Some(80),
+            Some("https") => Some(443),
+            _ => None,
+        })
+    }
+
+    pub fn dial_address(&self) -> String {
+        match self.effective_port() {
+            Some(port) => format!("{}:{}", self.host, port),
+            None => self.host.to_string(),
+        }
+    }
+}
+
+/// Why `https://` URLs aren't sent: the request would go out as plain HTTP, credentials and all.
+pub const NO_TLS: &str = "https:// can't be sent: there's no TLS yet, so the request and its headers would cross the network unencrypted; use http:// for servers that speak plain HTTP";
+
+/// Stands in for a `{{variable}}` while checking a URL as typed; valid as a host, a port, a
+/// path segment or a query value.
+const PLACEHOLDER_VALUE: &str = "1";
+
+/// Checks a URL as typed for what would stop it being sent, or make it go somewhere else than
+/// meant. `{{variables}}` are taken to hold something that fits where they are; a scheme that
+/// is a variable isn't checked.
+pub fn check(input: &str) -> Result<(), String> {
+    let input = input.trim();
+    let mut filled = String::with_capacity(input.len());
+    let mut rest = input;
+    while let Some(open) = rest.find("{{") {
+        let Some(close) = rest[open..].find("}}") else {
+            return Err("a {{ isn't closed with }}".to_string());
+        };
+        filled.push_str(&rest[..open]);
+        filled.push_str(PLACEHOLDER_VALUE);
+        rest = &rest[open + close + 2..];
+    }
+    filled.push_str(rest);
+
+    if let Some(c) = filled.chars().find(|c| c.is_whitespace() || c.is_control() || "<>\"`{}|\\^".contains(*c)) {
+        let what = match c {
+            ' ' => "a space".to_string(),
+            c if c.is_whitespace() || c.is_control() => format!("{:?}", c),
+            c => format!("'{}'", c),
+        };
+        return Err(format!("{} can't be in a URL unencoded", what));
+    }
+    let written_scheme = input.split_once("://").map(|(scheme, _)| scheme);
+    let url = parse(&filled).map_err(|e| e.to_string())?;
+    match (written_scheme, url.scheme.as_deref()) {
+        (Some(written), _) if written.contains("{{") => {}
+        (_, Some("http") | None) => {}
+        (_, Some("https")) => return Err(NO_TLS.to_string()),
+        (_, Some(scheme)) => return Err(format!("{}:// isn't supported; only http://", scheme)),
+    }
+    if url.port == Some(0) {
+        return Err("port 0 can't be connected to".to_string());
+    }
+    if let Host::Name(name) = &url.host {
+        check_host_name(name)?;
+    }
+    Ok(())
+}
+
+/// Letters, digits, hyphens and underscores in dot-separated labels of up to 63 characters.
+fn check_host_name(name: &str) -> Result<(), String> {
+    if name.len() > 253 {
+        return Err("the host name is longer than 253 characters".to_string());
+    }
+    let name = name.strip_suffix('.').unwrap_or(name);
+    for label in name.split('.') {
+        if label.is_empty() {
+            return Err(format!("{:?} has an empty label between dots", name));
+        }
+        if label.len() > 63 {
+            return Err(format!("{:?} is longer than 63 characters, too long for part of a host name", label));
+        }
+        if let Some(c) = label.chars().find(|c| !c.is_ascii_alphanumeric() && !matches!(c, '-' | '_')) {
+            return Err(format!("'{}' can't be in a host name ({})", c, name));
+        }
+        if label.starts_with('-') || label.ends_with('-') {
+            return Err(format!("{:?} starts or ends with a hyphen, which host names can't", label));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_bracketed_ipv6_with_and_without_port() {
+        let url = parse("http://[::1]:8080/health").unwrap();
+        assert_eq!(url.host, Host::Ipv6(Ipv6Addr::LOCALHOST));
+        assert_eq!(url.authority(), "[::1]:8080");
+        assert_eq!(parse("http://[::1]/").unwrap().dial_address(), "[::1]:80");
+    }
+
+    #[test]
+    fn refuses_zone_ids_and_bare_ipv6() {
+        assert_eq!(parse("http://[fe80::1%eth0]:80/"), Err(UrlError::ZoneIdUnsupported("fe80::1%eth0".to_string())));
+        assert_eq!(parse("http://fe80::1%eth0/"), Err(UrlError::ZoneIdUnsupported("fe80::1%eth0".to_string())));
+        assert_eq!(parse("http://::1/"), Err(UrlError::UnbracketedIpv6("::1".to_string())));
+    }
+
+    #[test]
+    fn check_refuses_https_until_there_is_tls() {
+        assert_eq!(check("https://api.example.com/users"), Err(NO_TLS.to_string()));
+        assert_eq!(check("HTTPS://api.example.com"), Err(NO_TLS.to_string()));
+        assert!(check("http://api.example.com/users").is_ok());
+        assert!(check("localhost:8004/echo").is_ok());
+        assert!(check("ftp://example.com").is_err());
+    }
+
+    #[test]
+    fn check_accepts_placeholders() {
+        assert!(check("http://{{host}}:{{port}}/users/{{id}}").is_ok());
+        assert!(check("{{base}}/users").is_ok());
+        assert!(check("http://{{host}/users").is_err());
+    }
+}
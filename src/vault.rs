@@ -0,0 +1,137 @@
+use std::{env, process::Command};
+
+use chacha20poly1305::{aead::Aead, ChaCha20Poly1305, KeyInit, Nonce};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+use crate::encoding;
+
+/// PBKDF2 rounds for new vaults. Kept with the vault so it can be raised later.
+const ITERATIONS: u32 = 200_000;
+
+/// Prefix of sealed values, for telling formats apart should this ever change.
+const VERSION: &str = "v2:";
+
+const NONCE_LEN: usize = 12;
+
+/// Where a passphrase comes from when it isn't typed: this environment variable, then the OS
+/// keyring through `secret-tool` or `security`, under this service name.
+pub const PASSPHRASE_VAR: &str = "TUI_POSTMAN_PASSPHRASE";
+const KEYRING_SERVICE: &str = "tui_postman";
+
+/// How keys are derived from the passphrase. Only this, never the key, is saved.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Vault {
+    /// Base64.
+    pub salt: String,
+    pub iterations: u32,
+}
+
+/// Derived from the passphrase, for ChaCha20-Poly1305.
+pub struct Key([u8; 32]);
+
+impl Vault {
+    /// A vault with a fresh salt, for a new or changed passphrase.
+    pub fn generate() -> Self {
+        let mut salt = [0u8; 16];
+        rand::thread_rng().fill_bytes(&mut salt);
+        Self { salt: encoding::base64_encode(&salt), iterations: ITERATIONS }
+    }
+
+    /// PBKDF2-HMAC-SHA256 (RFC 8018), which takes a moment on purpose, so it's left to the worker.
+    pub fn key(&self, passphrase: &str) -> Result<Key, String> {
+        let salt = encoding::base64_decode(&self.salt).map_err(|e| format!("the vault's salt is damaged: {}", e))?;
+        let mut key = [0u8; 32];
+        pbkdf2::pbkdf2_hmac::<Sha256>(passphrase.as_bytes(), &salt, self.iterations, &mut key);
+        Ok(Key(key))
+    }
+}
+
+/// Encrypts and authenticates with ChaCha20-Poly1305 under a random nonce. Returns `v2:` and the
+/// base64 of nonce and ciphertext, tag included.
+pub fn seal(key: &Key, plaintext: &str) -> String {
+    let mut nonce = [0u8; NONCE_LEN];
+    rand::thread_rng().fill_bytes(&mut nonce);
+    let ciphertext = ChaCha20Poly1305::new(&key.0.into())
+        .encrypt(Nonce::from_slice(&nonce), plaintext.as_bytes())
+        .expect("encrypting to memory doesn't fail");
+    let mut sealed = nonce.to_vec();
+    sealed.extend(ciphertext);
+    format!("{}{}", VERSION, encoding::base64_encode(&sealed))
+}
+
+/// The plaintext of a sealed value, or an error if the key is wrong or the value was altered.
+pub fn open(key: &Key, sealed: &str) -> Result<String, String> {
+    let encoded = sealed.strip_prefix(VERSION).ok_or("not a sealed value")?;
+    let bytes = encoding::base64_decode(encoded).map_err(|e| e.to_string())?;
+    if bytes.len() < NONCE_LEN {
+        return Err("the sealed value is too short".to_string());
+    }
+    let (nonce, ciphertext) = bytes.split_at(NONCE_LEN);
+    let plaintext = ChaCha20Poly1305::new(&key.0.into())
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| "wrong passphrase".to_string())?;
+    String::from_utf8(plaintext).map_err(|_| "the decrypted value isn't text".to_string())
+}
+
+/// A passphrase that doesn't have to be typed, if one is set up.
+pub fn stored_passphrase() -> Option<String> {
+    if let Ok(passphrase) = env::var(PASSPHRASE_VAR) {
+        return Some(passphrase);
+    }
+    let keyrings: [(&str, &[&str]); 2] = [
+        ("secret-tool", &["lookup", "service", KEYRING_SERVICE]),
+        ("security", &["find-generic-password", "-s", KEYRING_SERVICE, "-w"]),
+    ];
+    keyrings.iter().find_map(|(program, args)| {
+        let output = Command::new(program).args(*args).output().ok()?;
+        let passphrase = String::from_utf8(output.stdout).ok()?;
+        let passphrase = passphrase.trim_end_matches(['\r', '\n']);
+        (output.status.success() && !passphrase.is_empty()).then(|| passphrase.to_string())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The PBKDF2-HMAC-SHA256 vectors commonly paired with RFC 6070's, which covers SHA-1 only.
+    #[test]
+    fn derives_pbkdf2_hmac_sha256_vectors() {
+        let vault = |iterations| Vault { salt: encoding::base64_encode(b"salt"), iterations };
+        let hex = |key: Key| crate::digest::hex(&key.0);
+        assert_eq!(hex(vault(1).key("password").unwrap()), "120fb6cffcf8b32c43e7225256c4f837a86548c92ccc35480805987cb70be17b");
+        assert_eq!(hex(vault(2).key("password").unwrap()), "ae4d0c95af6b46d32d0adff928f06dd02a303f8ef3c251dfd6e2d85a95474c43");
+        assert_eq!(hex(vault(4096).key("password").unwrap()), "c5e478d59288c841aa530db6845c4c8d962893a001ce4e11a4963873aa98134a");
+    }
+
+    fn quick_key(passphrase: &str) -> Key {
+        Vault { iterations: 1, ..Vault::generate() }.key(passphrase).unwrap()
+    }
+
+    #[test]
+    fn opens_what_it_seals() {
+        let key = quick_key("passphrase");
+        for plaintext in ["", "hunter2", "ünïcødé and a long enough value to span several blocks of keystream"] {
+            let sealed = seal(&key, plaintext);
+            assert!(sealed.starts_with(VERSION));
+            assert!(!sealed.contains(plaintext) || plaintext.is_empty());
+            assert_eq!(open(&key, &sealed).unwrap(), plaintext);
+        }
+        assert_ne!(seal(&key, "same"), seal(&key, "same"), "nonces should differ");
+    }
+
+    #[test]
+    fn rejects_a_wrong_key_or_altered_value() {
+        let sealed = seal(&quick_key("right"), "hunter2");
+        assert_eq!(open(&quick_key("wrong"), &sealed).unwrap_err(), "wrong passphrase");
+        let key = quick_key("right");
+        let sealed = seal(&key, "hunter2");
+        let mut bytes = encoding::base64_decode(&sealed[VERSION.len()..]).unwrap();
+        bytes[NONCE_LEN] ^= 1;
+        let altered = format!("{}{}", VERSION, encoding::base64_encode(&bytes));
+        assert_eq!(open(&key, &altered).unwrap_err(), "wrong passphrase");
+        assert!(open(&key, "v1:AAAA").is_err());
+    }
+}
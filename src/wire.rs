@@ -0,0 +1,669 @@
+use std::{
+    fmt,
+    io::{self, ErrorKind, Read, Write},
+    net::{Shutdown, TcpStream},
+    sync::{atomic::{AtomicBool, Ordering}, Arc},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    charset,
+    history::ResponseRecord,
+    net,
+    request::{header_value, ResolvedRequest},
+    status,
+};
+
+/// Response heads bigger than this are taken for a server that isn't speaking HTTP.
+const MAX_HEAD: usize = 64 * 1024;
+
+/// Body bytes written at a time, so progress shows as they go.
+const CHUNK: usize = 64 * 1024;
+
+/// Longest a read or write blocks before looking whether the exchange was cancelled.
+const POLL: Duration = Duration::from_millis(100);
+
+/// Why an exchange on a connection stopped. Its `Display` is the message for it.
+#[derive(Debug)]
+pub enum WireError {
+    /// Called off before the request went out.
+    Cancelled,
+    /// Called off partway through the request body.
+    CancelledUpload { sent: u64, total: u64 },
+    /// Called off after the request went out, before the whole response was in.
+    CancelledResponse,
+    /// No full response within the deadline, which was this long.
+    Deadline(Duration),
+    ReadTimeout(Duration),
+    WriteTimeout(Duration),
+    Closed(Closed),
+    /// What the server sent isn't HTTP, said how.
+    Malformed(String),
+    Io(io::Error),
+}
+
+/// Where the server was in the exchange when it closed the connection.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Closed {
+    /// Before taking all of the request.
+    Request,
+    Unanswered,
+    Head,
+    Body { received: usize, length: u64 },
+    Chunks { received: usize },
+}
+
+impl fmt::Display for WireError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WireError::Cancelled => write!(f, "Cancelled"),
+            WireError::CancelledUpload { sent, total } => write!(f, "Cancelled after sending {} of {} body bytes", sent, total),
+            WireError::CancelledResponse => write!(f, "Cancelled after the request went out, before the whole response was in"),
+            WireError::Deadline(timeout) => write!(f, "Timed out: no full response within the {}s deadline", timeout.as_secs()),
+            WireError::ReadTimeout(idle) => write!(f, "Read timeout: nothing from the server for {} ms", idle.as_millis()),
+            WireError::WriteTimeout(idle) => write!(f, "Write timeout: the server took none of the request for {} ms", idle.as_millis()),
+            WireError::Closed(Closed::Request) => write!(f, "the server stopped taking the request"),
+            WireError::Closed(Closed::Unanswered) => write!(f, "the server closed the connection without answering"),
+            WireError::Closed(Closed::Head) => write!(f, "the server closed the connection in the middle of the response headers"),
+            WireError::Closed(Closed::Body { received, length }) => write!(f, "the connection closed after {} of {} body bytes", received, length),
+            WireError::Closed(Closed::Chunks { received }) => write!(f, "the connection closed in the middle of a chunked body, after {} bytes", received),
+            WireError::Malformed(what) => write!(f, "{}", what),
+            WireError::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for WireError {}
+
+impl From<io::Error> for WireError {
+    fn from(e: io::Error) -> Self {
+        WireError::Io(e)
+    }
+}
+
+impl WireError {
+    /// The message for an exchange with `peer` that stopped on this. Running out of time and
+    /// being cancelled are said as plainly as they can be already.
+    pub fn describe(&self, peer: &str) -> String {
+        match self {
+            WireError::Cancelled | WireError::CancelledUpload { .. } | WireError::CancelledResponse | WireError::Deadline(_) => self.to_string(),
+            WireError::Io(e) => net::describe_failure(e, peer),
+            _ => format!("Request to {} failed: {}", peer, self),
+        }
+    }
+}
+
+/// How far a body has got.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Progress {
+    Upload { sent: u64, total: u64 },
+    /// `total` is the `Content-Length`, when the response has one.
+    Download { received: u64, total: Option<u64> },
+}
+
+/// A status line and headers, of an interim or a final response.
+pub struct Head {
+    pub status: u16,
+    pub reason: String,
+    pub headers: Vec<(String, String)>,
+    /// The server takes another request on the connection after this response.
+    pub keep_alive: bool,
+}
+
+impl Head {
+    pub fn into_response(self, body: Body) -> ResponseRecord {
+        let text = charset::decode_body(header_value(&self.headers, "Content-Type"), &body.bytes);
+        let raw = (text.as_bytes() != body.bytes).then_some(body.bytes);
+        ResponseRecord { status: Some(self.status), reason: self.reason, headers: self.headers, body: text, raw, truncated_from: body.truncated_from }
+    }
+}
+
+/// A response body as read, up to the size limit.
+pub struct Body {
+    pub bytes: Vec<u8>,
+    /// How big the body was when it was cut off at the size limit: its `Content-Length`, or the
+    /// bytes in by then when it has none.
+    pub truncated_from: Option<usize>,
+}
+
+impl Body {
+    fn whole(bytes: Vec<u8>) -> Self {
+        Self { bytes, truncated_from: None }
+    }
+}
+
+/// How long a connection may go quiet, when the exchange has to be over, and whether it's been
+/// called off.
+#[derive(Clone, Default)]
+pub struct Limits {
+    /// Longest the server may go without sending a byte, or taking one.
+    pub idle: Option<Duration>,
+    /// When the whole response has to be in, and the timeout that was counted from.
+    pub deadline: Option<(Instant, Duration)>,
+    /// Set from another thread to give up on the exchange.
+    pub cancel: Arc<AtomicBool>,
+    /// Most response body bytes to read. The rest is left unread and the connection closed.
+    pub max_body: Option<usize>,
+}
+
+impl Limits {
+    /// Whether to go on at `now`.
+    fn check(&self, now: Instant) -> Result<(), WireError> {
+        if self.cancel.load(Ordering::Relaxed) {
+            return Err(WireError::Cancelled);
+        }
+        match self.deadline {
+            Some((at, timeout)) if now >= at => Err(WireError::Deadline(timeout)),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// HTTP/1.1 on a connection of our own, so a request's head and body go out on the same one
+/// and the response is read as it comes.
+pub struct Connection {
+    stream: TcpStream,
+    limits: Limits,
+    /// Read but not parsed yet.
+    buffer: Vec<u8>,
+    /// Bytes written and read, start lines and headers included.
+    pub sent: u64,
+    pub received: u64,
+    /// When the latest request started going out, and when the head of its final response was in.
+    pub started: Option<Instant>,
+    pub answered: Option<Instant>,
+}
+
+impl Connection {
+    pub fn new(stream: TcpStream, limits: Limits) -> Self {
+        Self { stream, limits, buffer: vec![], sent: 0, received: 0, started: None, answered: None }
+    }
+
+    fn write(&mut self, mut bytes: &[u8]) -> Result<(), WireError> {
+        let mut stalled_since = Instant::now();
+        while !bytes.is_empty() {
+            let now = Instant::now();
+            self.limits.check(now)?;
+            let idle_left = self.limits.idle.map(|idle| idle.saturating_sub(now - stalled_since));
+            if let (Some(idle), Some(Duration::ZERO)) = (self.limits.idle, idle_left) {
+                return Err(WireError::WriteTimeout(idle));
+            }
+            let wait = shortest([Some(POLL), idle_left, self.limits.deadline.map(|(at, _)| at - now)]);
+            self.stream.set_write_timeout(wait)?;
+            match self.stream.write(bytes) {
+                Ok(0) => return Err(WireError::Closed(Closed::Request)),
+                Ok(written) => {
+                    bytes = &bytes[written..];
+                    self.sent += written as u64;
+                    stalled_since = Instant::now();
+                }
+                Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut | ErrorKind::Interrupted) => {}
+                Err(e) => return Err(WireError::Io(e)),
+            }
+        }
+        Ok(())
+    }
+
+    /// The request line and headers, with `Expect: 100-continue` in place of any `Expect` the
+    /// request has when `expect` is set.
+    pub fn write_head(&mut self, request: &ResolvedRequest, expect: bool) -> Result<(), WireError> {
+        (self.started, self.answered) = (Some(Instant::now()), None);
+        let mut head = format!("{} {} HTTP/1.1\r\n", request.method, request.endpoint);
+        for (name, value) in request.headers.iter().filter(|(name, _)| !expect || !name.eq_ignore_ascii_case("Expect")) {
+            head.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        if expect {
+            head.push_str("Expect: 100-continue\r\n");
+        }
+        head.push_str("\r\n");
+        self.write(head.as_bytes())
+    }
+
+    /// Writes `body` a chunk at a time, telling `progress` how far it's got after each.
+    pub fn write_body(&mut self, body: &[u8], progress: &mut dyn FnMut(Progress)) -> Result<(), WireError> {
+        let (before, total) = (self.sent, body.len() as u64);
+        for chunk in body.chunks(CHUNK) {
+            if let Err(e) = self.write(chunk) {
+                return Err(match e {
+                    WireError::Cancelled => WireError::CancelledUpload { sent: self.sent - before, total },
+                    e => e,
+                });
+            }
+            progress(Progress::Upload { sent: self.sent - before, total });
+        }
+        Ok(self.stream.flush()?)
+    }
+
+    /// Reads more from the server. `Ok(None)` when `until` passes first, `Ok(Some(0))` when the
+    /// server closed the connection. Fails once the server has been quiet for the idle timeout,
+    /// or the deadline passes.
+    fn fill(&mut self, until: Option<Instant>) -> Result<Option<usize>, WireError> {
+        let mut chunk = [0u8; 16 * 1024];
+        let quiet_since = Instant::now();
+        loop {
+            let now = Instant::now();
+            if until.is_some_and(|until| now >= until) {
+                return Ok(None);
+            }
+            self.limits.check(now)?;
+            let idle_left = self.limits.idle.map(|idle| idle.saturating_sub(now - quiet_since));
+            if let (Some(idle), Some(Duration::ZERO)) = (self.limits.idle, idle_left) {
+                return Err(WireError::ReadTimeout(idle));
+            }
+            let wait = shortest([Some(POLL), until.map(|until| until - now), self.limits.deadline.map(|(at, _)| at - now), idle_left]);
+            self.stream.set_read_timeout(wait)?;
+            match self.stream.read(&mut chunk) {
+                Ok(read) => {
+                    self.buffer.extend_from_slice(&chunk[..read]);
+                    self.received += read as u64;
+                    return Ok(Some(read));
+                }
+                Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut | ErrorKind::Interrupted) => {}
+                Err(e) => return Err(WireError::Io(e)),
+            }
+        }
+    }
+
+    /// The next response head, or `None` when `until` passes before it's in.
+    pub fn read_head(&mut self, until: Option<Instant>) -> Result<Option<Head>, WireError> {
+        let end = loop {
+            if let Some(end) = find(&self.buffer, b"\r\n\r\n") {
+                break end;
+            }
+            if self.buffer.len() > MAX_HEAD {
+                return Err(WireError::Malformed(format!("the response headers run past {} KB", MAX_HEAD / 1024)));
+            }
+            match self.fill(until)? {
+                None => return Ok(None),
+                Some(0) if self.buffer.is_empty() => return Err(WireError::Closed(Closed::Unanswered)),
+                Some(0) => return Err(WireError::Closed(Closed::Head)),
+                Some(_) => {}
+            }
+        };
+        let head = String::from_utf8_lossy(&self.buffer[..end]).into_owned();
+        self.buffer.drain(..end + 4);
+        let head = parse_head(&head)?;
+        if head.status >= 200 {
+            self.answered = Some(Instant::now());
+        }
+        Ok(Some(head))
+    }
+
+    /// The final response to a `method` request, skipping interim ones.
+    pub fn read_response(&mut self, method: &str, progress: &mut dyn FnMut(Progress)) -> Result<ResponseRecord, WireError> {
+        self.read_final(method, progress).map_err(|e| match e {
+            WireError::Cancelled => WireError::CancelledResponse,
+            e => e,
+        })
+    }
+
+    fn read_final(&mut self, method: &str, progress: &mut dyn FnMut(Progress)) -> Result<ResponseRecord, WireError> {
+        loop {
+            let head = self.read_head(None)?.ok_or(WireError::Closed(Closed::Unanswered))?;
+            if (100..200).contains(&head.status) {
+                continue;
+            }
+            let body = self.read_body(method, &head, progress)?;
+            return Ok(head.into_response(body));
+        }
+    }
+
+    /// The body that follows `head`, framed by chunked encoding, `Content-Length` or the end of
+    /// the connection, and cut off at the size limit. `progress` hears how much is in after
+    /// every read.
+    pub fn read_body(&mut self, method: &str, head: &Head, progress: &mut dyn FnMut(Progress)) -> Result<Body, WireError> {
+        if method.eq_ignore_ascii_case("HEAD") || matches!(head.status, 100..200 | 204 | 304) {
+            return Ok(Body::whole(vec![]));
+        }
+        let max = self.limits.max_body.unwrap_or(usize::MAX);
+        let chunked = header_value(&head.headers, "Transfer-Encoding").is_some_and(|coding| coding.to_ascii_lowercase().contains("chunked"));
+        if chunked {
+            return self.read_chunked(max, progress);
+        }
+        match header_value(&head.headers, "Content-Length").map(|length| length.trim().parse::<u64>()) {
+            Some(Ok(length)) => {
+                let wanted = length.min(max as u64) as usize;
+                while self.buffer.len() < wanted {
+                    if self.fill(None)? == Some(0) {
+                        return Err(WireError::Closed(Closed::Body { received: self.buffer.len(), length }));
+                    }
+                    progress(Progress::Download { received: (self.buffer.len() as u64).min(length), total: Some(length) });
+                }
+                let bytes = self.buffer.drain(..wanted).collect();
+                match (wanted as u64) < length {
+                    true => Ok(self.cut_off(bytes, length as usize)),
+                    false => Ok(Body::whole(bytes)),
+                }
+            }
+            Some(Err(_)) => Err(WireError::Malformed(format!("the Content-Length {:?} isn't a number", header_value(&head.headers, "Content-Length").unwrap_or_default()))),
+            None => loop {
+                if self.buffer.len() > max {
+                    let received = self.buffer.len();
+                    let bytes = self.buffer[..max].to_vec();
+                    return Ok(self.cut_off(bytes, received));
+                }
+                if self.fill(None)? == Some(0) {
+                    return Ok(Body::whole(std::mem::take(&mut self.buffer)));
+                }
+                progress(Progress::Download { received: self.buffer.len() as u64, total: None });
+            },
+        }
+    }
+
+    /// Stops reading a body over the size limit, keeping `bytes` of it. The rest is still on its
+    /// way, so the connection is closed rather than used again.
+    fn cut_off(&mut self, bytes: Vec<u8>, from: usize) -> Body {
+        let _ = self.stream.shutdown(Shutdown::Both);
+        self.buffer.clear();
+        Body { bytes, truncated_from: Some(from) }
+    }
+
+    fn read_chunked(&mut self, max: usize, progress: &mut dyn FnMut(Progress)) -> Result<Body, WireError> {
+        let mut body = vec![];
+        loop {
+            let line = self.line(body.len())?;
+            let size = line.split(';').next().unwrap_or_default().trim();
+            let size = usize::from_str_radix(size, 16).map_err(|_| WireError::Malformed(format!("{:?} isn't a chunk size", size)))?;
+            if size == 0 {
+                // Trailers, up to the blank line that ends the body.
+                while !self.line(body.len())?.is_empty() {}
+                return Ok(Body::whole(body));
+            }
+            loop {
+                let received = body.len() + self.buffer.len().min(size);
+                if received > max {
+                    body.extend(self.buffer.drain(..max - body.len()));
+                    return Ok(self.cut_off(body, received));
+                }
+                if self.buffer.len() >= size + 2 {
+                    break;
+                }
+                if self.fill(None)? == Some(0) {
+                    return Err(WireError::Closed(Closed::Chunks { received: body.len() + self.buffer.len() }));
+                }
+                progress(Progress::Download { received: (body.len() + self.buffer.len().min(size)) as u64, total: None });
+            }
+            body.extend(self.buffer.drain(..size));
+            self.buffer.drain(..2);
+        }
+    }
+
+    /// One CRLF-terminated line of a chunked body, `received` so far being for the error.
+    fn line(&mut self, received: usize) -> Result<String, WireError> {
+        loop {
+            if let Some(end) = find(&self.buffer, b"\r\n") {
+                let line = String::from_utf8_lossy(&self.buffer[..end]).into_owned();
+                self.buffer.drain(..end + 2);
+                return Ok(line);
+            }
+            if self.fill(None)? == Some(0) {
+                return Err(WireError::Closed(Closed::Chunks { received }));
+            }
+        }
+    }
+}
+
+/// The shortest of some waits, none of them zero since a zero socket timeout isn't allowed.
+fn shortest<const N: usize>(waits: [Option<Duration>; N]) -> Option<Duration> {
+    waits.into_iter().flatten().min().map(|wait| wait.max(Duration::from_millis(1)))
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|window| window == needle)
+}
+
+fn parse_head(head: &str) -> Result<Head, WireError> {
+    let mut lines = head.split("\r\n");
+    let status_line = lines.next().unwrap_or_default();
+    let mut parts = status_line.splitn(3, ' ');
+    let version = parts.next().unwrap_or_default();
+    let status: u16 = parts
+        .next()
+        .filter(|_| version.starts_with("HTTP/"))
+        .and_then(|code| code.parse().ok())
+        .ok_or_else(|| WireError::Malformed(format!("not an HTTP status line: {:?}", status_line)))?;
+    let reason = parts.next().map(str::to_string).or_else(|| status::reason(status).map(str::to_string)).unwrap_or_default();
+    let headers: Vec<(String, String)> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+        .collect();
+    let connection = header_value(&headers, "Connection").unwrap_or_default().to_ascii_lowercase();
+    let keep_alive = match version {
+        "HTTP/1.0" => connection.contains("keep-alive"),
+        _ => !connection.contains("close"),
+    };
+    Ok(Head { status, reason, headers, keep_alive })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::Write, net::TcpListener, thread};
+
+    use super::*;
+
+    /// A connection to a server that writes `reply` and closes.
+    fn replying(reply: &'static [u8]) -> Connection {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            stream.write_all(reply).unwrap();
+        });
+        Connection::new(TcpStream::connect(addr).unwrap(), Limits::default())
+    }
+
+    #[test]
+    fn reads_a_body_by_content_length() {
+        let mut connection = replying(b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhelloEXTRA");
+        let response = connection.read_response("GET", &mut |_| {}).unwrap();
+        assert_eq!((response.status, response.reason.as_str(), response.body.as_str()), (Some(200), "OK", "hello"));
+    }
+
+    #[test]
+    fn reads_a_chunked_body_with_trailers() {
+        let mut connection = replying(b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n4;ext=1\r\nWiki\r\n5\r\npedia\r\n0\r\nX-Trailer: 1\r\n\r\n");
+        assert_eq!(connection.read_response("GET", &mut |_| {}).unwrap().body, "Wikipedia");
+    }
+
+    #[test]
+    fn reads_to_the_end_without_framing() {
+        let mut connection = replying(b"HTTP/1.0 200 OK\r\n\r\nall of it");
+        assert_eq!(connection.read_response("GET", &mut |_| {}).unwrap().body, "all of it");
+    }
+
+    #[test]
+    fn skips_interim_responses_and_bodies_that_cant_exist() {
+        let mut connection = replying(b"HTTP/1.1 103 Early Hints\r\nLink: </a.css>\r\n\r\nHTTP/1.1 204 No Content\r\nContent-Length: 10\r\n\r\n");
+        let response = connection.read_response("GET", &mut |_| {}).unwrap();
+        assert_eq!((response.status, response.body.as_str()), (Some(204), ""));
+
+        let mut connection = replying(b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\n");
+        assert_eq!(connection.read_response("HEAD", &mut |_| {}).unwrap().body, "");
+    }
+
+    #[test]
+    fn a_body_cut_short_is_an_error() {
+        let mut connection = replying(b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\nhalf");
+        assert_eq!(connection.read_response("GET", &mut |_| {}).unwrap_err().to_string(), "the connection closed after 4 of 10 body bytes");
+
+        let mut connection = replying(b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\na\r\nhal");
+        let error = connection.read_response("GET", &mut |_| {}).unwrap_err();
+        assert!(matches!(error, WireError::Closed(Closed::Chunks { received: 3 })), "{}", error);
+    }
+
+    #[test]
+    fn describes_an_error_by_its_kind_not_its_wording() {
+        let peer = "10.0.0.1:80";
+        let reset = WireError::Io(io::Error::new(ErrorKind::ConnectionReset, "os error 9999"));
+        assert_eq!(reset.describe(peer), "Connection reset by 10.0.0.1:80 while reading the response");
+        let worded = WireError::Io(io::Error::other("connection refused"));
+        assert_eq!(worded.describe(peer), "Request to 10.0.0.1:80 failed: connection refused");
+        assert_eq!(WireError::ReadTimeout(Duration::from_millis(5)).describe(peer), "Request to 10.0.0.1:80 failed: Read timeout: nothing from the server for 5 ms");
+        assert_eq!(WireError::Deadline(Duration::from_secs(3)).describe(peer), "Timed out: no full response within the 3s deadline");
+        assert_eq!(WireError::CancelledResponse.describe(peer), "Cancelled after the request went out, before the whole response was in");
+    }
+
+    #[test]
+    fn knows_when_the_connection_stays_open() {
+        assert!(parse_head("HTTP/1.1 200 OK").unwrap().keep_alive);
+        assert!(!parse_head("HTTP/1.1 200 OK\r\nConnection: close").unwrap().keep_alive);
+        assert!(!parse_head("HTTP/1.0 200 OK").unwrap().keep_alive);
+        assert!(parse_head("HTTP/1.0 200 OK\r\nConnection: Keep-Alive").unwrap().keep_alive);
+        assert!(parse_head("SSH-2.0-OpenSSH").is_err());
+    }
+
+    /// A connection to a server that writes each of `replies` a while apart, then holds the
+    /// connection open.
+    fn trickling(replies: &'static [&'static [u8]], every: Duration, limits: Limits) -> Connection {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            for reply in replies {
+                if stream.write_all(reply).is_err() {
+                    return;
+                }
+                thread::sleep(every);
+            }
+            thread::sleep(Duration::from_secs(5));
+        });
+        Connection::new(TcpStream::connect(addr).unwrap(), limits)
+    }
+
+    #[test]
+    fn a_server_that_stalls_after_the_headers_hits_the_read_timeout() {
+        let limits = Limits { idle: Some(Duration::from_millis(100)), ..Limits::default() };
+        let mut connection = trickling(&[b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\nhal"], Duration::ZERO, limits);
+        let started = Instant::now();
+        let error = connection.read_response("GET", &mut |_| {}).unwrap_err();
+        assert!(matches!(error, WireError::ReadTimeout(idle) if idle == Duration::from_millis(100)), "{}", error);
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+
+    #[test]
+    fn a_server_that_trickles_hits_the_deadline() {
+        let deadline = Duration::from_secs(1);
+        let limits = Limits { idle: Some(Duration::from_secs(1)), deadline: Some((Instant::now() + deadline, deadline)), ..Limits::default() };
+        let body: &[&[u8]] = &[b"HTTP/1.1 200 OK\r\nContent-Length: 100\r\n\r\n", b"a", b"b", b"c", b"d", b"e", b"f", b"g", b"h"];
+        let mut connection = trickling(body, Duration::from_millis(200), limits);
+        assert_eq!(connection.read_response("GET", &mut |_| {}).unwrap_err().to_string(), "Timed out: no full response within the 1s deadline");
+    }
+
+    #[test]
+    fn reports_upload_progress_chunk_by_chunk() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let _ = std::io::copy(&mut stream, &mut std::io::sink());
+        });
+        let mut connection = Connection::new(TcpStream::connect(addr).unwrap(), Limits::default());
+        let body = vec![b'x'; CHUNK * 3 + 10];
+        let mut reports = vec![];
+        connection.write_body(&body, &mut |progress| reports.push(progress)).unwrap();
+        let total = body.len() as u64;
+        assert_eq!(reports.len(), 4);
+        assert_eq!(reports[0], Progress::Upload { sent: CHUNK as u64, total });
+        assert_eq!(reports[3], Progress::Upload { sent: total, total });
+    }
+
+    #[test]
+    fn cancelling_stops_an_upload_the_server_isnt_taking() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            let _held = listener.accept().unwrap();
+            thread::sleep(Duration::from_secs(10));
+        });
+        let limits = Limits::default();
+        let cancel = limits.cancel.clone();
+        let mut connection = Connection::new(TcpStream::connect(addr).unwrap(), limits);
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(200));
+            cancel.store(true, Ordering::Relaxed);
+        });
+        let body = vec![b'x'; 64 << 20];
+        let started = Instant::now();
+        let error = connection.write_body(&body, &mut |_| {}).unwrap_err();
+        assert!(started.elapsed() < Duration::from_secs(2));
+        assert!(matches!(error, WireError::CancelledUpload { total, .. } if total == body.len() as u64), "{}", error);
+    }
+
+    #[test]
+    fn reports_download_progress_as_the_body_comes_in() {
+        let parts: &[&[u8]] = &[b"HTTP/1.1 200 OK\r\nContent-Length: 6\r\n\r\nab", b"cd", b"ef"];
+        let mut connection = trickling(parts, Duration::from_millis(50), Limits::default());
+        let mut reports = vec![];
+        connection.read_response("GET", &mut |progress| reports.push(progress)).unwrap();
+        assert_eq!(reports.last(), Some(&Progress::Download { received: 6, total: Some(6) }));
+        assert!(reports.len() >= 2, "{:?}", reports);
+
+        let mut connection = replying(b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nabc\r\n0\r\n\r\n");
+        let mut reports = vec![];
+        connection.read_response("GET", &mut |progress| reports.push(progress)).unwrap();
+        assert!(reports.iter().all(|progress| matches!(progress, Progress::Download { total: None, .. })));
+    }
+
+    /// A connection, limited to `max_body`, to a server that writes `head` and then body bytes
+    /// for as long as they're taken. Also returns whether the server saw the connection close.
+    fn endless(head: &'static [u8], chunked: bool, max_body: usize) -> (Connection, std::sync::mpsc::Receiver<()>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (closed, closing) = std::sync::mpsc::channel();
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            stream.write_all(head).unwrap();
+            let piece = vec![b'x'; 4096];
+            loop {
+                let written = match chunked {
+                    true => stream.write_all(b"1000\r\n").and_then(|_| stream.write_all(&piece)).and_then(|_| stream.write_all(b"\r\n")),
+                    false => stream.write_all(&piece),
+                };
+                if written.is_err() {
+                    let _ = closed.send(());
+                    return;
+                }
+            }
+        });
+        let limits = Limits { idle: Some(Duration::from_secs(5)), max_body: Some(max_body), ..Limits::default() };
+        (Connection::new(TcpStream::connect(addr).unwrap(), limits), closing)
+    }
+
+    #[test]
+    fn stops_reading_a_body_at_the_size_limit() {
+        let (mut connection, closing) = endless(b"HTTP/1.1 200 OK\r\nContent-Length: 1073741824\r\n\r\n", false, 10_000);
+        let response = connection.read_response("GET", &mut |_| {}).unwrap();
+        assert_eq!((response.body.len(), response.truncated_from), (10_000, Some(1 << 30)));
+        closing.recv_timeout(Duration::from_secs(5)).expect("the connection stayed open");
+
+        let (mut connection, closing) = endless(b"HTTP/1.0 200 OK\r\n\r\n", false, 10_000);
+        let response = connection.read_response("GET", &mut |_| {}).unwrap();
+        assert_eq!(response.body.len(), 10_000);
+        assert!(response.truncated_from.is_some_and(|from| from > 10_000), "{:?}", response.truncated_from);
+        closing.recv_timeout(Duration::from_secs(5)).expect("the connection stayed open");
+
+        let (mut connection, closing) = endless(b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n", true, 10_000);
+        let response = connection.read_response("GET", &mut |_| {}).unwrap();
+        assert_eq!(response.body, "x".repeat(10_000));
+        assert!(response.truncated_from.is_some_and(|from| from > 10_000), "{:?}", response.truncated_from);
+        closing.recv_timeout(Duration::from_secs(5)).expect("the connection stayed open");
+    }
+
+    #[test]
+    fn a_body_within_the_limit_is_whole() {
+        let mut connection = replying(b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello");
+        connection.limits.max_body = Some(5);
+        let response = connection.read_response("GET", &mut |_| {}).unwrap();
+        assert_eq!((response.body.as_str(), response.truncated_from), ("hello", None));
+    }
+
+    #[test]
+    fn decodes_the_body_by_its_charset_and_keeps_the_bytes() {
+        let mut connection = replying(b"HTTP/1.1 200 OK\r\nContent-Type: text/plain; charset=iso-8859-1\r\nContent-Length: 4\r\n\r\ncaf\xe9");
+        let response = connection.read_response("GET", &mut |_| {}).unwrap();
+        assert_eq!((response.body.as_str(), response.raw.as_deref()), ("café", Some(&b"caf\xe9"[..])));
+
+        let mut connection = replying(b"HTTP/1.1 200 OK\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Length: 5\r\n\r\ncaf\xc3\xa9");
+        assert_eq!(connection.read_response("GET", &mut |_| {}).unwrap().raw, None);
+    }
+}
@@ -0,0 +1,718 @@
+use std::{
+    collections::HashMap,
+    io::ErrorKind,
+    net::{SocketAddr, TcpStream},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, RecvTimeoutError, Sender},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use chrono::{Local, SecondsFormat, Utc};
+
+use crate::{
+    app_error::{self, AppError, ErrorKind as AppErrorKind},
+    expect::{self, Answer, ExpectContinue},
+    history::{ResponseRecord, Timing},
+    net::{self, AddressPreference, DnsCache, ResolveError, Step},
+    redirect::{self, Hop, Policy},
+    request::{header_value, ResolvedRequest},
+    request_log::{LogEntry, RequestLog},
+    schema::{self, SchemaCheck},
+    session::{self, Draft},
+    url,
+    vault::{Key, Vault},
+    wire::{Connection, Limits, Progress, WireError},
+};
+
+/// How long each address gets when there are several to try and no connect timeout is set.
+pub const ATTEMPT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long the server may go quiet mid-exchange when no read timeout is set.
+pub const DEFAULT_READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Least time between two progress reports of a send.
+const PROGRESS_EVERY: Duration = Duration::from_millis(100);
+
+pub enum Job {
+    /// `id` is echoed back on the response so it can be matched to its history entry.
+    Send { id: u64, request: Box<ResolvedRequest>, redirects: Policy, transport: Transport },
+    /// Autosaves are written here so a slow disk never stalls typing.
+    SaveDraft { path: PathBuf, draft: Box<Draft> },
+    DiscardDraft { path: PathBuf },
+    /// Holds `job` until `at`; other jobs carry on meanwhile.
+    Schedule { at: Instant, job: Box<Job> },
+    /// Drops a scheduled send that hasn't gone out yet.
+    CancelScheduled { id: u64 },
+    /// Replaces the request log after its settings change.
+    SetRequestLog(Option<RequestLog>),
+    /// Checks a response body against a schema, which takes a while for big ones.
+    CheckSchema { id: u64, schema: String, body: String },
+    /// Derives the key of `vault`, which is slow on purpose. `change` is passed back, for telling
+    /// unlocking from changing the passphrase.
+    DeriveKey { vault: Vault, passphrase: String, change: bool },
+    /// Checks that `target` can be reached, without sending a request.
+    CheckReachable { target: String, https: bool, transport: Transport },
+}
+
+/// How a request is put on the wire, apart from redirects.
+#[derive(Clone, Copy)]
+pub struct Transport {
+    pub preference: AddressPreference,
+    pub expect: Option<ExpectContinue>,
+    pub connect_timeout: Option<Duration>,
+    /// For trying every resolved address in turn.
+    pub connect_budget: Option<Duration>,
+    /// Longest the server may go without sending or taking a byte once connected.
+    pub read_timeout: Option<Duration>,
+    /// For the whole exchange, redirects included.
+    pub deadline: Option<Duration>,
+    /// Most response body bytes to read; the rest is dropped as it would be cut anyway.
+    pub max_body: Option<usize>,
+}
+
+pub enum WorkerEvent {
+    /// A timestamped line for the console pane.
+    Log(String),
+    Response {
+        id: u64,
+        /// The last request of the redirect chain.
+        request: Box<ResolvedRequest>,
+        peer: String,
+        result: Result<Box<ResponseRecord>, AppError>,
+        timing: Box<Timing>,
+        redirects: Vec<Hop>,
+        /// Why a redirect response wasn't followed.
+        stopped: Option<String>,
+    },
+    SchemaChecked { id: u64, check: SchemaCheck },
+    Reachable { target: String, steps: Vec<Step> },
+    KeyDerived { vault: Vault, change: bool, key: Result<Key, String> },
+    /// How far the request or response body of send `id` has got, at most every
+    /// `PROGRESS_EVERY`.
+    Progress { id: u64, progress: Progress },
+}
+
+/// Flags for calling off sends, by id, shared with the worker thread since it's busy with the
+/// send when one is called off.
+type Cancels = Arc<Mutex<HashMap<u64, Arc<AtomicBool>>>>;
+
+/// Does the networking on a background thread so the UI never blocks on it.
+pub struct Worker {
+    jobs: Sender<Job>,
+    pub events: Receiver<WorkerEvent>,
+    cancels: Cancels,
+    pub dns: Arc<DnsCache>,
+}
+
+impl Worker {
+    pub fn spawn(request_log: Option<RequestLog>) -> Self {
+        let (jobs, job_rx) = mpsc::channel();
+        let (event_tx, events) = mpsc::channel();
+        let cancels = Cancels::default();
+        let dns = Arc::new(DnsCache::default());
+        let (shared, worker_dns) = (cancels.clone(), dns.clone());
+        thread::spawn(move || run(request_log, job_rx, event_tx, shared, worker_dns));
+        Self { jobs, events, cancels, dns }
+    }
+
+    pub fn submit(&self, job: Job) {
+        // The worker only exits once this sender is dropped, so the send can't fail.
+        let _ = self.jobs.send(job);
+    }
+
+    /// Calls off send `id`, closing its connection wherever it's got to. It still reports back,
+    /// with how far it got.
+    pub fn cancel(&self, id: u64) {
+        self.cancels.lock().unwrap_or_else(|e| e.into_inner()).entry(id).or_default().store(true, Ordering::Relaxed);
+    }
+}
+
+fn log(events: &Sender<WorkerEvent>, message: impl AsRef<str>) {
+    let _ = events.send(WorkerEvent::Log(format!("[{}] {}", Local::now().format("%H:%M:%S%.3f"), message.as_ref())));
+}
+
+fn run(mut request_log: Option<RequestLog>, jobs: Receiver<Job>, events: Sender<WorkerEvent>, cancels: Cancels, dns: Arc<DnsCache>) {
+    let mut scheduled: Vec<(Instant, Job)> = vec![];
+    loop {
+        let next = scheduled.iter().map(|(at, _)| *at).min();
+        let received = match next {
+            Some(at) => jobs.recv_timeout(at.saturating_duration_since(Instant::now())),
+            None => jobs.recv().map_err(|_| RecvTimeoutError::Disconnected),
+        };
+        let job = match received {
+            Ok(Job::Schedule { at, job }) => {
+                scheduled.push((at, *job));
+                continue;
+            }
+            Ok(Job::CancelScheduled { id }) => {
+                scheduled.retain(|(_, job)| !matches!(job, Job::Send { id: scheduled_id, .. } if *scheduled_id == id));
+                continue;
+            }
+            Ok(job) => job,
+            Err(RecvTimeoutError::Timeout) => {
+                let now = Instant::now();
+                let Some(due) = scheduled.iter().position(|(at, _)| *at <= now) else { continue };
+                scheduled.swap_remove(due).1
+            }
+            Err(RecvTimeoutError::Disconnected) => return,
+        };
+        match job {
+            Job::Send { id, request, redirects, transport } => {
+                let timestamp = Utc::now();
+                let started = Instant::now();
+                let mut timing = Timing::default();
+                let limits = Limits {
+                    idle: Some(transport.read_timeout.unwrap_or(DEFAULT_READ_TIMEOUT)),
+                    deadline: transport.deadline.map(|timeout| (started + timeout, timeout)),
+                    cancel: cancels.lock().unwrap_or_else(|e| e.into_inner()).entry(id).or_default().clone(),
+                    max_body: transport.max_body,
+                };
+                let mut reported: Option<Instant> = None;
+                let mut progress = |progress: Progress| {
+                    let done = match progress {
+                        Progress::Upload { sent, total } => sent == total,
+                        Progress::Download { received, total } => total == Some(received),
+                    };
+                    if done || reported.is_none_or(|at| at.elapsed() >= PROGRESS_EVERY) {
+                        reported = Some(Instant::now());
+                        let _ = events.send(WorkerEvent::Progress { id, progress });
+                    }
+                };
+                let Followed { request, peer, result, hops, stopped } =
+                    send_following(*request, transport, &dns, &limits, &redirects, &mut timing, &mut progress, &events);
+                cancels.lock().unwrap_or_else(|e| e.into_inner()).remove(&id);
+                let latency_ms = started.elapsed().as_millis() as u64;
+                timing.latency_ms = latency_ms;
+                if let Some(request_log) = &request_log {
+                    let entry = LogEntry {
+                        timestamp: timestamp.to_rfc3339_opts(SecondsFormat::Millis, true),
+                        method: request.method.clone(),
+                        url: request.url(),
+                        request_headers: request.headers.clone(),
+                        status: result.as_ref().ok().and_then(|res| res.status),
+                        error: result.as_ref().err().map(|e| e.summary.clone()),
+                        latency_ms,
+                        response_bytes: result.as_ref().ok().map(|res| res.body.len()),
+                    };
+                    if let Err(e) = request_log.append(&entry) {
+                        log(&events, format!("Couldn't write the request log: {}", e));
+                    }
+                }
+                let _ = events.send(WorkerEvent::Response {
+                    id,
+                    request: Box::new(request),
+                    peer,
+                    result: result.map(Box::new),
+                    timing: Box::new(timing),
+                    redirects: hops,
+                    stopped,
+                });
+            }
+            Job::SaveDraft { path, draft } => {
+                if let Err(e) = session::save_draft(&path, &draft) {
+                    log(&events, format!("Couldn't autosave the draft: {}", e));
+                }
+            }
+            Job::DiscardDraft { path } => {
+                if let Err(e) = session::discard_draft(&path) {
+                    log(&events, format!("Couldn't delete the draft: {}", e));
+                }
+            }
+            Job::SetRequestLog(log) => request_log = log,
+            Job::CheckSchema { id, schema, body } => {
+                let _ = events.send(WorkerEvent::SchemaChecked { id, check: schema::check_response(&schema, &body) });
+            }
+            Job::DeriveKey { vault, passphrase, change } => {
+                let key = vault.key(&passphrase);
+                let _ = events.send(WorkerEvent::KeyDerived { vault, change, key });
+            }
+            Job::CheckReachable { target, https, transport } => {
+                log(&events, format!("Checking that {} can be reached", target));
+                let timeout = transport.connect_timeout.unwrap_or(ATTEMPT_TIMEOUT);
+                let steps = net::check_reachable(&target, https, transport.preference, timeout);
+                let _ = events.send(WorkerEvent::Reachable { target, steps });
+            }
+            Job::Schedule { .. } | Job::CancelScheduled { .. } => unreachable!("handled when received"),
+        }
+    }
+}
+
+/// The outcome of a send and any redirects followed from it.
+struct Followed {
+    request: ResolvedRequest,
+    peer: String,
+    result: Result<ResponseRecord, AppError>,
+    hops: Vec<Hop>,
+    stopped: Option<String>,
+}
+
+#[allow(clippy::too_many_arguments)]
+fn send_following(
+    mut request: ResolvedRequest,
+    transport: Transport,
+    dns: &DnsCache,
+    limits: &Limits,
+    policy: &Policy,
+    timing: &mut Timing,
+    progress: &mut dyn FnMut(Progress),
+    events: &Sender<WorkerEvent>,
+) -> Followed {
+    let mut hops: Vec<Hop> = vec![];
+    loop {
+        let (peer, result) = send(&request, transport, dns, limits, timing, progress, events);
+        let redirected = result.as_ref().ok().and_then(|res| {
+            let status = res.status.filter(|code| redirect::is_redirect(Some(*code)))?;
+            Some((status, header_value(&res.headers, "Location").map(str::to_string)))
+        });
+        let Some((status, location)) = redirected else {
+            return Followed { request, peer, result, hops, stopped: None };
+        };
+        let stopped = if !policy.follow {
+            "Not followed: redirects are off for this request".to_string()
+        } else if hops.len() >= policy.max_hops {
+            format!("Stopped after {} hops, the most this request follows", hops.len())
+        } else if let Some(location) = location {
+            match redirect::follow(&request, status, &location, policy) {
+                Ok((_, hop)) if hop.to == hop.from || hops.iter().any(|earlier| earlier.from == hop.to) => {
+                    format!("Stopped: {} redirects back to a URL already visited", hop.to)
+                }
+                Ok((next, hop)) => {
+                    log(events, format!("Following {} to {}", status, hop.to));
+                    if !hop.dropped.is_empty() {
+                        log(events, format!("Host changed, dropping {}", hop.dropped.join(", ")));
+                    }
+                    hops.push(hop);
+                    request = next;
+                    continue;
+                }
+                Err(e) => format!("Stopped: {}", e),
+            }
+        } else {
+            "Stopped: the redirect has no Location header".to_string()
+        };
+        log(events, &stopped);
+        return Followed { request, peer, result, hops, stopped: Some(stopped) };
+    }
+}
+
+fn send(
+    request: &ResolvedRequest,
+    transport: Transport,
+    dns: &DnsCache,
+    limits: &Limits,
+    timing: &mut Timing,
+    progress: &mut dyn FnMut(Progress),
+    events: &Sender<WorkerEvent>,
+) -> (String, Result<ResponseRecord, AppError>) {
+    let Transport { preference, connect_timeout, connect_budget, .. } = transport;
+    let target = request.peer();
+    if request.scheme == "https" {
+        log(events, url::NO_TLS);
+        return (target.to_string(), Err(AppError::new(AppErrorKind::Url, url::NO_TLS)));
+    }
+    log(events, format!("Resolving {} ({})", target, preference.label()));
+    let resolving = Instant::now();
+    let resolved = dns.resolve(target, preference);
+    *timing.dns_ms.get_or_insert(0) += resolving.elapsed().as_millis() as u64;
+    let connecting = Instant::now();
+    let (peer, stream) = match resolved {
+        Ok(resolved) if resolved.addresses.is_empty() => {
+            log(events, format!("No port to resolve with, connecting to {} as written", target));
+            (target.to_string(), TcpStream::connect(target).map_err(|e| connect_error(e.to_string(), app_error::io_hint(e.kind()))))
+        }
+        Ok(resolved) => {
+            let listed: Vec<String> = resolved.addresses.iter().map(|addr| addr.to_string()).collect();
+            let cached = if resolved.cached {" (cached)"} else {""};
+            log(events, format!("Resolved to {}{}", listed.join(", "), cached));
+            timing.dns_cached = resolved.cached;
+            match connect(&resolved.addresses, connect_timeout, connect_budget, events) {
+                Ok(stream) => (stream.peer_addr().map_or(listed[0].clone(), |addr| addr.to_string()), Ok(stream)),
+                Err(e) => (listed[0].clone(), Err(e)),
+            }
+        }
+        Err(e) => {
+            match e.detail() {
+                Some(detail) => log(events, format!("{} ({})", e, detail)),
+                None => log(events, e.to_string()),
+            }
+            let error = match &e {
+                ResolveError::Lookup { .. } => AppError::new(AppErrorKind::Network, e.to_string()).hint(app_error::LOOKUP_HINT),
+                ResolveError::Url(_) => AppError::new(AppErrorKind::Url, e.to_string()),
+            };
+            return (target.to_string(), Err(error));
+        }
+    };
+    let stream = match stream {
+        Ok(stream) => {
+            *timing.connect_ms.get_or_insert(0) += connecting.elapsed().as_millis() as u64;
+            stream
+        }
+        Err(e) => {
+            *timing.exchange_ms.get_or_insert(0) += connecting.elapsed().as_millis() as u64;
+            return (peer, Err(e));
+        }
+    };
+    log(events, format!("Connected to {}", peer));
+    log(events, "TLS: none (plain HTTP)");
+    log(events, format!("> {} {} HTTP/1.1", request.method, request.endpoint));
+    for (name, value) in &request.headers {
+        log(events, format!("> {}: {}", name, value));
+    }
+    log(events, format!("> ({} body bytes)", request.body.len()));
+
+    let started = connecting;
+    let result = exchange(request, stream, transport, limits, timing, progress, events);
+    *timing.exchange_ms.get_or_insert(0) += started.elapsed().as_millis() as u64;
+    match &result {
+        Ok(res) => {
+            let code = res.status.map_or("???".to_string(), |code| code.to_string());
+            log(events, format!("< {} {} after {} ms", code, res.reason, started.elapsed().as_millis()));
+            for (name, value) in &res.headers {
+                log(events, format!("< {}: {}", name, value));
+            }
+            log(events, format!("< ({} body bytes)", res.body.len()));
+        }
+        Err(e) => log(events, &e.summary),
+    }
+    (peer, result)
+}
+
+/// Sends `request` on `stream` and reads the response, counting the bytes either way in
+/// `timing`.
+fn exchange(
+    request: &ResolvedRequest,
+    stream: TcpStream,
+    transport: Transport,
+    limits: &Limits,
+    timing: &mut Timing,
+    progress: &mut dyn FnMut(Progress),
+    events: &Sender<WorkerEvent>,
+) -> Result<ResponseRecord, AppError> {
+    let peer = stream.peer_addr().map_err(|e| connect_error(e.to_string(), app_error::io_hint(e.kind())))?;
+    let mut connection = Connection::new(stream, limits.clone());
+    let result = exchange_on(&mut connection, request, transport.expect, progress, events).map_err(|e| e.said_to(&peer, events));
+    record(timing, &connection);
+    if let Some(response) = result? {
+        return Ok(response);
+    }
+    // The server turned the expectation down and closed the connection, so the request goes
+    // again without it on a new one.
+    log(events, format!("Reconnecting to {}", peer));
+    let mut connection = Connection::new(connect(&[peer], transport.connect_timeout, None, events)?, limits.clone());
+    let result = send_plain(&mut connection, request, progress).map_err(|e| Failure::Wire(e).said_to(&peer, events));
+    record(timing, &connection);
+    result
+}
+
+/// Why an exchange stopped: on the connection, or because of how the server took the
+/// expectation.
+enum Failure {
+    Wire(WireError),
+    /// `None` when the server didn't answer the expectation in time.
+    Expect { wait_ms: u64, wire: Option<WireError> },
+}
+
+impl Failure {
+    /// The error for an exchange with `peer`, after logging what it stopped on.
+    fn said_to(self, peer: &SocketAddr, events: &Sender<WorkerEvent>) -> AppError {
+        let peer = peer.to_string();
+        match self {
+            Failure::Wire(e) => {
+                log(events, format!("Request failed: {}", e));
+                AppError::wire(&e, &peer)
+            }
+            Failure::Expect { wait_ms, wire: None } => AppError::new(AppErrorKind::Network, format!("No 100 Continue within {} ms; the body wasn't sent", wait_ms)),
+            Failure::Expect { wire: Some(e), .. } => AppError {
+                summary: format!("No answer to Expect: 100-continue: {}; the body wasn't sent", e),
+                ..AppError::wire(&e, &peer)
+            },
+        }
+    }
+}
+
+impl From<WireError> for Failure {
+    fn from(e: WireError) -> Self {
+        Failure::Wire(e)
+    }
+}
+
+/// Adds what went over `connection` to `timing`, once it's done with. Waiting and downloading
+/// are those of the request that got the response.
+fn record(timing: &mut Timing, connection: &Connection) {
+    *timing.sent_bytes.get_or_insert(0) += connection.sent;
+    *timing.received_bytes.get_or_insert(0) += connection.received;
+    if let (Some(started), Some(answered)) = (connection.started, connection.answered) {
+        timing.ttfb_ms = Some(answered.duration_since(started).as_millis() as u64);
+        timing.download_ms = Some(answered.elapsed().as_millis() as u64);
+    }
+}
+
+fn send_plain(connection: &mut Connection, request: &ResolvedRequest, progress: &mut dyn FnMut(Progress)) -> Result<ResponseRecord, WireError> {
+    connection.write_head(request, false)?;
+    connection.write_body(request.body.as_bytes(), progress)?;
+    connection.read_response(&request.method, progress)
+}
+
+/// Sends `request`, first asking the server whether it wants the body when `expect` is set and
+/// there's a body. Head and body go out on `connection` either way. `None` when the request has
+/// to go again, without the expectation, on a new connection.
+fn exchange_on(
+    connection: &mut Connection,
+    request: &ResolvedRequest,
+    expect: Option<ExpectContinue>,
+    progress: &mut dyn FnMut(Progress),
+    events: &Sender<WorkerEvent>,
+) -> Result<Option<ResponseRecord>, Failure> {
+    let Some(expect) = expect.filter(|_| !request.body.is_empty()) else {
+        return Ok(send_plain(connection, request, progress).map(Some)?);
+    };
+    connection.write_head(request, true)?;
+    log(events, format!("> Expect: 100-continue, holding the body for up to {} ms", expect.wait_ms));
+    let started = Instant::now();
+    match expect::await_continue(connection, Duration::from_millis(expect.wait_ms)) {
+        Ok(Answer::Continue) => log(events, format!("< 100 Continue after {} ms, sending the body", started.elapsed().as_millis())),
+        Ok(Answer::Silent) if expect.fallback => log(events, format!("No 100 Continue within {} ms, sending the body anyway", expect.wait_ms)),
+        Ok(Answer::Silent) => return Err(Failure::Expect { wait_ms: expect.wait_ms, wire: None }),
+        Ok(Answer::Final(head)) if head.status == 417 && expect.fallback => {
+            log(events, "< 417 Expectation Failed, sending the request again without Expect");
+            // A refusal too big to read whole leaves the connection closed.
+            let refusal = connection.read_body(&request.method, &head, progress)?;
+            return match head.keep_alive && refusal.truncated_from.is_none() {
+                true => Ok(send_plain(connection, request, progress).map(Some)?),
+                false => Ok(None),
+            };
+        }
+        Ok(Answer::Final(head)) => {
+            log(events, format!("< {} {} after {} ms, so the body wasn't sent", head.status, head.reason, started.elapsed().as_millis()));
+            let body = connection.read_body(&request.method, &head, progress)?;
+            return Ok(Some(head.into_response(body)));
+        }
+        Err(e) => return Err(Failure::Expect { wait_ms: expect.wait_ms, wire: Some(e) }),
+    }
+    connection.write_body(request.body.as_bytes(), progress)?;
+    Ok(connection.read_response(&request.method, progress).map(Some)?)
+}
+
+/// A connection to the first of `addresses` that accepts one, each tried within the connect
+/// timeout and all of them within the budget. A lone address with neither gets as long as the
+/// system gives a connect.
+fn connect(addresses: &[SocketAddr], timeout: Option<Duration>, budget: Option<Duration>, events: &Sender<WorkerEvent>) -> Result<TcpStream, AppError> {
+    let started = Instant::now();
+    let mut failures = vec![];
+    for addr in addresses {
+        let mut attempt = timeout.or((addresses.len() > 1).then_some(ATTEMPT_TIMEOUT));
+        if let Some(budget) = budget {
+            let left = budget.saturating_sub(started.elapsed());
+            if left.is_zero() {
+                log(events, format!("Out of the {}s connect budget, not trying {}", budget.as_secs(), addr));
+                break;
+            }
+            attempt = Some(attempt.map_or(left, |attempt| attempt.min(left)));
+        }
+        log(events, format!("Trying {} ({})", addr, net::family(addr)));
+        let connected = match attempt {
+            Some(attempt) => TcpStream::connect_timeout(addr, attempt),
+            None => TcpStream::connect(addr),
+        };
+        match connected {
+            Ok(stream) => {
+                if !failures.is_empty() {
+                    log(events, format!("Connected to {} after {} failed", addr, failures.len()));
+                }
+                return Ok(stream);
+            }
+            Err(e) => {
+                let (error, hint) = match (e.kind(), attempt) {
+                    (ErrorKind::TimedOut | ErrorKind::WouldBlock, Some(attempt)) => (format!("Connect timeout: no connection to {} within {} ms", addr, attempt.as_millis()), None),
+                    _ => (net::describe_failure(&e, &addr.to_string()), app_error::io_hint(e.kind())),
+                };
+                log(events, &error);
+                failures.push((error, hint));
+            }
+        }
+    }
+    let summary = match &failures[..] {
+        [] => "No address was tried within the connect budget".to_string(),
+        [(failure, _)] => failure.clone(),
+        _ => format!("None of {} addresses accepted a connection: {}", failures.len(), failures.iter().map(|(error, _)| error.as_str()).collect::<Vec<_>>().join("; ")),
+    };
+    Err(connect_error(summary, failures.first().and_then(|(_, hint)| *hint)))
+}
+
+/// A connection that couldn't be made, with what might get it through.
+fn connect_error(summary: String, hint: Option<&str>) -> AppError {
+    let error = AppError::new(AppErrorKind::Network, summary);
+    match hint {
+        Some(hint) => error.hint(hint),
+        None => error,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::{BufRead, BufReader, Read, Write}, net::TcpListener};
+
+    use super::*;
+
+    fn request(body: &str) -> ResolvedRequest {
+        ResolvedRequest {
+            method: "POST".to_string(),
+            scheme: "http".to_string(),
+            address: "localhost".to_string(),
+            dial_address: "localhost:80".to_string(),
+            connect_to: None,
+            endpoint: "/upload".to_string(),
+            headers: vec![("Host".to_string(), "localhost".to_string()), ("Content-Length".to_string(), body.len().to_string())],
+            body: body.to_string(),
+            host_defaults: vec![],
+        }
+    }
+
+    /// Reads a request head off `reader`, returning its lines.
+    fn head(reader: &mut BufReader<TcpStream>) -> Vec<String> {
+        let mut lines = vec![];
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            if line.trim_end().is_empty() {
+                return lines;
+            }
+            lines.push(line.trim_end().to_string());
+        }
+    }
+
+    /// Runs `server` on the one connection a test makes, returning its address.
+    fn serve(server: impl FnOnce(BufReader<TcpStream>) + Send + 'static) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        thread::spawn(move || server(BufReader::new(listener.accept().unwrap().0)));
+        addr
+    }
+
+    fn send_to(peer: &str, expect: Option<ExpectContinue>, timing: &mut Timing, events: &Sender<WorkerEvent>) -> Result<ResponseRecord, AppError> {
+        let transport = Transport { preference: AddressPreference::AsResolved, expect, connect_timeout: None, connect_budget: None, read_timeout: None, deadline: None, max_body: None };
+        exchange(&request("hello"), TcpStream::connect(peer).unwrap(), transport, &Limits::default(), timing, &mut |_| {}, events)
+    }
+
+    fn expect(fallback: bool) -> Option<ExpectContinue> {
+        Some(ExpectContinue { wait_ms: 2000, fallback })
+    }
+
+    #[test]
+    fn sends_the_body_after_100_continue_on_the_same_connection() {
+        let peer = serve(|mut reader| {
+            assert!(head(&mut reader).contains(&"Expect: 100-continue".to_string()));
+            reader.get_mut().write_all(b"HTTP/1.1 100 Continue\r\n\r\n").unwrap();
+            let mut body = [0u8; 5];
+            reader.read_exact(&mut body).unwrap();
+            assert_eq!(&body, b"hello");
+            reader.get_mut().write_all(b"HTTP/1.1 201 Created\r\nContent-Length: 2\r\n\r\nok").unwrap();
+        });
+        let (events, _log) = mpsc::channel();
+        let response = send_to(&peer, expect(false), &mut Timing::default(), &events).unwrap();
+        assert_eq!((response.status, response.body.as_str()), (Some(201), "ok"));
+    }
+
+    #[test]
+    fn an_early_final_response_is_the_response_and_the_body_stays() {
+        let peer = serve(|mut reader| {
+            head(&mut reader);
+            reader.get_mut().write_all(b"HTTP/1.1 413 Payload Too Large\r\nContent-Length: 7\r\nConnection: close\r\n\r\ntoo big").unwrap();
+            let mut rest = vec![];
+            reader.read_to_end(&mut rest).unwrap();
+            assert!(rest.is_empty(), "the body was sent anyway");
+        });
+        let (events, _log) = mpsc::channel();
+        let mut timing = Timing::default();
+        let response = send_to(&peer, expect(true), &mut timing, &events).unwrap();
+        assert_eq!((response.status, response.body.as_str()), (Some(413), "too big"));
+    }
+
+    #[test]
+    fn after_a_417_the_request_goes_again_without_expect() {
+        let peer = serve(|mut reader| {
+            head(&mut reader);
+            reader.get_mut().write_all(b"HTTP/1.1 417 Expectation Failed\r\nContent-Length: 0\r\n\r\n").unwrap();
+            assert!(!head(&mut reader).iter().any(|line| line.starts_with("Expect")));
+            let mut body = [0u8; 5];
+            reader.read_exact(&mut body).unwrap();
+            reader.get_mut().write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n").unwrap();
+        });
+        let (events, _log) = mpsc::channel();
+        let response = send_to(&peer, expect(true), &mut Timing::default(), &events).unwrap();
+        assert_eq!(response.status, Some(200));
+    }
+
+    #[test]
+    fn strict_gives_up_without_an_answer() {
+        let peer = serve(|mut reader| {
+            head(&mut reader);
+            let mut rest = vec![];
+            reader.read_to_end(&mut rest).unwrap();
+            assert!(rest.is_empty(), "the body was sent anyway");
+        });
+        let (events, _log) = mpsc::channel();
+        let strict = Some(ExpectContinue { wait_ms: 50, fallback: false });
+        let error = send_to(&peer, strict, &mut Timing::default(), &events).unwrap_err();
+        assert_eq!(error.summary, "No 100 Continue within 50 ms; the body wasn't sent");
+    }
+
+    #[test]
+    fn the_connection_tried_is_the_one_the_request_goes_on() {
+        let refused = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let live = listener.local_addr().unwrap();
+        let (events, _log) = mpsc::channel();
+        let mut stream = connect(&[refused, live], Some(Duration::from_secs(2)), None, &events).unwrap();
+        assert_eq!(stream.peer_addr().unwrap(), live);
+        stream.write_all(b"ping").unwrap();
+        let (mut accepted, _) = listener.accept().unwrap();
+        let mut received = [0u8; 4];
+        accepted.read_exact(&mut received).unwrap();
+        assert_eq!(&received, b"ping");
+        listener.set_nonblocking(true).unwrap();
+        assert!(listener.accept().is_err(), "a second connection was opened");
+    }
+
+    #[test]
+    fn the_budget_bounds_every_attempt() {
+        let refused = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        let (events, _log) = mpsc::channel();
+        let error = connect(&[refused, refused], None, Some(Duration::ZERO), &events).unwrap_err();
+        assert_eq!((error.summary.as_str(), error.hint), ("No address was tried within the connect budget", None));
+    }
+
+    #[test]
+    fn a_refused_connection_says_so_with_a_hint() {
+        let refused = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        let (events, _log) = mpsc::channel();
+        let error = connect(&[refused], None, None, &events).unwrap_err();
+        assert_eq!(error.summary, format!("Connection refused — nothing is listening on {}", refused));
+        assert_eq!(error.hint.as_deref(), app_error::io_hint(ErrorKind::ConnectionRefused));
+        assert!(error.hint.is_some());
+    }
+
+    #[test]
+    fn times_waiting_and_downloading_apart() {
+        let peer = serve(|mut reader| {
+            head(&mut reader);
+            let mut body = [0u8; 5];
+            reader.read_exact(&mut body).unwrap();
+            thread::sleep(Duration::from_millis(150));
+            reader.get_mut().write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 4\r\n\r\nab").unwrap();
+            thread::sleep(Duration::from_millis(150));
+            reader.get_mut().write_all(b"cd").unwrap();
+        });
+        let (events, _log) = mpsc::channel();
+        let mut timing = Timing::default();
+        send_to(&peer, None, &mut timing, &events).unwrap();
+        let (ttfb, download) = (timing.ttfb_ms.unwrap(), timing.download_ms.unwrap());
+        assert!((150..300).contains(&ttfb), "TTFB {} ms", ttfb);
+        assert!((150..300).contains(&download), "download {} ms", download);
+    }
+}
@@ -0,0 +1,105 @@
+use std::{fs, io, path::PathBuf, sync::Mutex};
+
+use crate::config;
+
+/// The workspace kept right in the config directory, as everything was before workspaces.
+pub const DEFAULT: &str = "default";
+
+/// Whose collection, environments, history and session are read and written; `None` for the
+/// default workspace. Global since every data file's path goes through it.
+static ACTIVE: Mutex<Option<String>> = Mutex::new(None);
+
+pub fn active() -> Option<String> {
+    ACTIVE.lock().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+pub fn active_name() -> String {
+    active().unwrap_or_else(|| DEFAULT.to_string())
+}
+
+/// Switches to a workspace, `default` included, creating it if it's new.
+pub fn open(name: &str) -> Result<(), String> {
+    let name = name.trim();
+    if name == DEFAULT {
+        *ACTIVE.lock().unwrap_or_else(|e| e.into_inner()) = None;
+        return Ok(());
+    }
+    validate(name)?;
+    let dir = dir(name);
+    fs::create_dir_all(&dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+    *ACTIVE.lock().unwrap_or_else(|e| e.into_inner()) = Some(name.to_string());
+    Ok(())
+}
+
+/// Where the active workspace keeps its files.
+pub fn data_dir() -> PathBuf {
+    match active() {
+        Some(name) => dir(&name),
+        None => config::config_dir(),
+    }
+}
+
+fn dir(name: &str) -> PathBuf {
+    config::config_dir().join("workspaces").join(name)
+}
+
+/// Named workspaces, sorted, without the default one.
+pub fn list() -> Result<Vec<String>, String> {
+    let root = config::config_dir().join("workspaces");
+    let entries = match fs::read_dir(&root) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(format!("{}: {}", root.display(), e)),
+    };
+    let mut names: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_ok_and(|kind| kind.is_dir()))
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .collect();
+    names.sort();
+    Ok(names)
+}
+
+/// Names become directory names, so they're kept to letters, digits, `-`, `_` and `.`.
+fn validate(name: &str) -> Result<(), String> {
+    if name.is_empty() || name.starts_with('.') || !name.chars().all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | '.')) {
+        return Err(format!("A workspace name is letters, digits, - _ and ., not starting with a dot: {:?}", name));
+    }
+    if name == DEFAULT {
+        return Err(format!("{} is the workspace kept in the config directory itself", DEFAULT));
+    }
+    Ok(())
+}
+
+pub fn create(name: &str) -> Result<(), String> {
+    validate(name)?;
+    let dir = dir(name);
+    if dir.exists() {
+        return Err(format!("Workspace {} already exists", name));
+    }
+    fs::create_dir_all(&dir).map_err(|e| format!("{}: {}", dir.display(), e))
+}
+
+/// Renames a named workspace, following it if it's the active one.
+pub fn rename(name: &str, new_name: &str) -> Result<(), String> {
+    validate(new_name)?;
+    let target = dir(new_name);
+    if target.exists() {
+        return Err(format!("Workspace {} already exists", new_name));
+    }
+    fs::rename(dir(name), &target).map_err(|e| format!("{}: {}", target.display(), e))?;
+    let mut active = ACTIVE.lock().unwrap_or_else(|e| e.into_inner());
+    if active.as_deref() == Some(name) {
+        *active = Some(new_name.to_string());
+    }
+    Ok(())
+}
+
+/// Deletes a named workspace and every file in it.
+pub fn delete(name: &str) -> Result<(), String> {
+    if active().as_deref() == Some(name) {
+        return Err("Switch to another workspace before deleting this one".to_string());
+    }
+    let dir = dir(name);
+    fs::remove_dir_all(&dir).map_err(|e| format!("{}: {}", dir.display(), e))
+}
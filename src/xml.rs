@@ -0,0 +1,242 @@
+/// What a piece of a formatted line is, for highlighting.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Part {
+    Markup,
+    TagName,
+    AttrName,
+    AttrValue,
+    Text,
+    Comment,
+}
+
+pub struct XmlLine {
+    pub depth: usize,
+    pub parts: Vec<(Part, String)>,
+}
+
+/// Elements whose only content is text up to this long stay on one line.
+const INLINE_TEXT: usize = 80;
+
+/// Whether a body should be shown as XML: an XML media type, or an XML declaration up front.
+pub fn looks_like_xml(content_type: Option<&str>, body: &str) -> bool {
+    let by_type = content_type.is_some_and(|content_type| {
+        let mime = content_type.split(';').next().unwrap_or("").trim().to_ascii_lowercase();
+        mime.ends_with("/xml") || mime.ends_with("+xml")
+    });
+    by_type || body.trim_start().starts_with("<?xml")
+}
+
+/// Reindents `xml` one element per line. Works through the text in a single loop with an
+/// explicit stack of open elements, so deep or large documents can't overflow the call stack.
+pub fn format(xml: &str) -> Result<Vec<XmlLine>, String> {
+    let mut lines = vec![];
+    let mut open: Vec<&str> = vec![];
+    let mut rest = xml;
+    while !rest.is_empty() {
+        let depth = open.len();
+        if let Some(after) = rest.strip_prefix("<!--") {
+            let end = after.find("-->").ok_or("unterminated comment")?;
+            lines.push(XmlLine { depth, parts: vec![(Part::Comment, format!("<!--{}-->", &after[..end]))] });
+            rest = &after[end + 3..];
+        } else if let Some(after) = rest.strip_prefix("<![CDATA[") {
+            let end = after.find("]]>").ok_or("unterminated CDATA section")?;
+            lines.push(XmlLine { depth, parts: vec![(Part::Text, format!("<![CDATA[{}]]>", &after[..end]))] });
+            rest = &after[end + 3..];
+        } else if rest.starts_with("<?") || rest.starts_with("<!") {
+            let terminator = if rest.starts_with("<?") {"?>"} else {">"};
+            let end = rest.find(terminator).ok_or("unterminated declaration")? + terminator.len();
+            lines.push(XmlLine { depth, parts: vec![(Part::Markup, rest[..end].to_string())] });
+            rest = &rest[end..];
+        } else if let Some(after) = rest.strip_prefix("</") {
+            let end = after.find('>').ok_or("unterminated closing tag")?;
+            let name = after[..end].trim();
+            match open.pop() {
+                Some(expected) if expected == name => {}
+                Some(expected) => return Err(format!("</{}> closes <{}>", name, expected)),
+                None => return Err(format!("</{}> has no opening tag", name)),
+            }
+            lines.push(XmlLine { depth: open.len(), parts: closing(name) });
+            rest = &after[end + 1..];
+        } else if rest.starts_with('<') {
+            let tag = parse_tag(rest)?;
+            let mut parts = tag.parts;
+            rest = &rest[tag.len..];
+            if tag.self_closing {
+                lines.push(XmlLine { depth, parts });
+                continue;
+            }
+            // Keep `<name>short text</name>` together.
+            let text_end = rest.find('<').unwrap_or(rest.len());
+            let close = format!("</{}>", tag.name);
+            let text = rest[..text_end].trim();
+            if rest[text_end..].starts_with(&close) && text.len() <= INLINE_TEXT && !text.contains('\n') {
+                if !text.is_empty() {
+                    parts.push((Part::Text, text.to_string()));
+                }
+                parts.extend(closing(tag.name));
+                lines.push(XmlLine { depth, parts });
+                rest = &rest[text_end + close.len()..];
+            } else {
+                lines.push(XmlLine { depth, parts });
+                open.push(tag.name);
+            }
+        } else {
+            let end = rest.find('<').unwrap_or(rest.len());
+            let text = rest[..end].trim();
+            if !text.is_empty() {
+                lines.push(XmlLine { depth, parts: vec![(Part::Text, text.to_string())] });
+            }
+            rest = &rest[end..];
+        }
+    }
+    match open.last() {
+        Some(name) => Err(format!("<{}> is never closed", name)),
+        None => Ok(lines),
+    }
+}
+
+fn closing(name: &str) -> Vec<(Part, String)> {
+    vec![(Part::Markup, "</".to_string()), (Part::TagName, name.to_string()), (Part::Markup, ">".to_string())]
+}
+
+struct Tag<'a> {
+    name: &'a str,
+    parts: Vec<(Part, String)>,
+    self_closing: bool,
+    /// Bytes of input the tag took up, brackets included.
+    len: usize,
+}
+
+/// Parses an opening or empty-element tag at the start of `input`.
+fn parse_tag(input: &str) -> Result<Tag<'_>, String> {
+    let body = &input[1..];
+    let name_end = body
+        .find(|c: char| c.is_whitespace() || c == '>' || c == '/')
+        .ok_or("unterminated tag")?;
+    let name = &body[..name_end];
+    if name.is_empty() {
+        return Err("a tag has no name".to_string());
+    }
+    let mut parts = vec![(Part::Markup, "<".to_string()), (Part::TagName, name.to_string())];
+    let mut i = name_end;
+    loop {
+        i += body[i..].len() - body[i..].trim_start().len();
+        let rest = &body[i..];
+        if rest.starts_with("/>") {
+            parts.push((Part::Markup, " />".to_string()));
+            return Ok(Tag { name, parts, self_closing: true, len: 1 + i + 2 });
+        }
+        if rest.starts_with('>') {
+            parts.push((Part::Markup, ">".to_string()));
+            return Ok(Tag { name, parts, self_closing: false, len: 1 + i + 1 });
+        }
+        if rest.is_empty() {
+            return Err(format!("<{}> is missing its '>'", name));
+        }
+        let attr_end = rest
+            .find(|c: char| c == '=' || c.is_whitespace() || c == '>' || c == '/')
+            .unwrap_or(rest.len());
+        if attr_end == 0 {
+            return Err(format!("unexpected '{}' in <{}>", &rest[..1], name));
+        }
+        parts.push((Part::Markup, " ".to_string()));
+        parts.push((Part::AttrName, rest[..attr_end].to_string()));
+        i += attr_end;
+        i += body[i..].len() - body[i..].trim_start().len();
+        if body[i..].starts_with('=') {
+            i += 1;
+            i += body[i..].len() - body[i..].trim_start().len();
+            let value = &body[i..];
+            let quote = value.chars().next().filter(|c| *c == '"' || *c == '\'');
+            let quote = quote.ok_or_else(|| format!("an attribute value in <{}> isn't quoted", name))?;
+            let close = value[1..].find(quote).ok_or_else(|| format!("unterminated attribute value in <{}>", name))?;
+            parts.push((Part::Markup, "=".to_string()));
+            parts.push((Part::AttrValue, value[..close + 2].to_string()));
+            i += close + 2;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::*;
+
+    fn text(line: &XmlLine) -> String {
+        line.parts.iter().map(|(_, text)| text.as_str()).collect()
+    }
+
+    #[test]
+    fn one_element_per_line_with_parts_marked() {
+        let lines = format(r#"<?xml version="1.0"?><feed xmlns='urn:x'><!-- new --><entry id="1"><title>Hi &amp; bye</title><empty/></entry></feed>"#).unwrap();
+        let shown: Vec<(usize, String)> = lines.iter().map(|line| (line.depth, text(line))).collect();
+        let expected = [
+            (0, r#"<?xml version="1.0"?>"#),
+            (0, "<feed xmlns='urn:x'>"),
+            (1, "<!-- new -->"),
+            (1, r#"<entry id="1">"#),
+            (2, "<title>Hi &amp; bye</title>"),
+            (2, "<empty />"),
+            (1, "</entry>"),
+            (0, "</feed>"),
+        ];
+        assert_eq!(shown, expected.map(|(depth, text)| (depth, text.to_string())));
+        assert!(lines[3].parts.contains(&(Part::AttrName, "id".to_string())));
+        assert!(lines[3].parts.contains(&(Part::AttrValue, "\"1\"".to_string())));
+        assert!(lines[4].parts.contains(&(Part::Text, "Hi &amp; bye".to_string())));
+    }
+
+    #[test]
+    fn deep_nesting_is_worked_through_without_recursion() {
+        let depth = 200_000;
+        let xml = format!("{}{}", "<a>".repeat(depth), "</a>".repeat(depth));
+        let lines = format(&xml).unwrap();
+        assert_eq!(lines.len(), 2 * depth - 1);
+        assert_eq!(lines.iter().map(|line| line.depth).max(), Some(depth - 1));
+    }
+
+    #[test]
+    fn a_five_megabyte_document_formats_in_one_pass() {
+        let item = r#"<item id="12345" kind='plain'><name>Widget &amp; gadget</name><note><![CDATA[<raw> & ]]></note></item>"#;
+        let count = 5 * 1024 * 1024 / item.len();
+        let xml = format!("<?xml version=\"1.0\"?><items>{}</items>", item.repeat(count));
+        assert!(xml.len() >= 5_000_000);
+        let started = Instant::now();
+        let lines = format(&xml).unwrap();
+        // A single pass takes about a second unoptimised; rescanning would take minutes.
+        assert!(started.elapsed() < Duration::from_secs(10), "took {:?}", started.elapsed());
+        // Per item: <item>, the inline <name>, <note>, the CDATA, </note> and </item>.
+        assert_eq!(lines.len(), 3 + 6 * count);
+        assert_eq!(text(&lines[lines.len() - 2]), "</item>");
+    }
+
+    #[test]
+    fn malformed_documents_say_what_is_wrong() {
+        let errors = [
+            ("<a><b></a>", "</a> closes <b>"),
+            ("<a></a></b>", "</b> has no opening tag"),
+            ("<a><b/>", "<a> is never closed"),
+            ("<a><!-- no end", "unterminated comment"),
+            ("<a><![CDATA[ no end", "unterminated CDATA section"),
+            ("<?xml version=\"1.0\"", "unterminated declaration"),
+            ("<a></a", "unterminated closing tag"),
+            ("<a x=1></a>", "an attribute value in <a> isn't quoted"),
+            ("<a x=\"1></a>", "unterminated attribute value in <a>"),
+            ("<a x", "<a> is missing its '>'"),
+            ("< a></a>", "a tag has no name"),
+            ("<a =\"1\"></a>", "unexpected '=' in <a>"),
+        ];
+        for (xml, error) in errors {
+            assert_eq!(format(xml).err().as_deref(), Some(error), "{}", xml);
+        }
+    }
+
+    #[test]
+    fn xml_is_recognised_by_type_or_declaration() {
+        assert!(looks_like_xml(Some("application/soap+xml; charset=utf-8"), ""));
+        assert!(looks_like_xml(Some("Text/XML"), ""));
+        assert!(looks_like_xml(None, "\n  <?xml version=\"1.0\"?><a/>"));
+        assert!(!looks_like_xml(Some("text/html"), "<a/>"));
+    }
+}
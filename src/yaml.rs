@@ -0,0 +1,614 @@
+use std::collections::HashMap;
+
+use serde_json::{Map, Number, Value};
+
+/// Parses the block-style YAML that tools like Insomnia export: nested mappings and sequences,
+/// plain, quoted and block (`|`, `>`) scalars, and flow collections written on one line.
+/// Anchors go on values and on blocks starting on the next line, aliases anywhere a value
+/// goes, and `<<` merges an aliased mapping in. Tags and multiple documents aren't supported.
+pub fn parse(text: &str) -> Result<Value, String> {
+    let lines = text.lines().map(|line| line.trim_end_matches('\r').to_string()).collect();
+    let mut parser = Parser { lines, pos: 0, anchors: HashMap::new() };
+    parser.skip_blank();
+    while parser.lines.get(parser.pos).is_some_and(|line| line.starts_with('%') || line.trim_end() == "---") {
+        parser.pos += 1;
+        parser.skip_blank();
+    }
+    let value = parser.node(0)?;
+    parser.skip_blank();
+    match parser.lines.get(parser.pos) {
+        Some(line) if line.trim_end() != "..." => Err(format!("line {}: unexpected {:?}", parser.pos + 1, line.trim())),
+        _ => Ok(value),
+    }
+}
+
+struct Parser {
+    lines: Vec<String>,
+    pos: usize,
+    anchors: HashMap<String, Value>,
+}
+
+fn indent(line: &str) -> usize {
+    line.len() - line.trim_start_matches(' ').len()
+}
+
+/// Empty or only a comment.
+fn is_blank(line: &str) -> bool {
+    let line = line.trim();
+    line.is_empty() || line.starts_with('#')
+}
+
+fn is_item(content: &str) -> bool {
+    content == "-" || content.starts_with("- ")
+}
+
+impl Parser {
+    fn skip_blank(&mut self) {
+        while self.lines.get(self.pos).is_some_and(|line| is_blank(line)) {
+            self.pos += 1;
+        }
+    }
+
+    fn error(&self, message: &str) -> String {
+        format!("line {}: {}", self.pos.min(self.lines.len()) + 1, message)
+    }
+
+    /// The node on the next lines indented at least `min`, or null if there's none.
+    fn node(&mut self, min: usize) -> Result<Value, String> {
+        self.skip_blank();
+        let Some(line) = self.lines.get(self.pos) else { return Ok(Value::Null) };
+        let indent = indent(line);
+        if indent < min {
+            return Ok(Value::Null);
+        }
+        let content = line[indent..].to_string();
+        if is_item(&content) {
+            self.sequence(indent)
+        } else if key_split(&content).is_some() {
+            self.mapping(indent)
+        } else {
+            self.pos += 1;
+            self.value(&content, min)
+        }
+    }
+
+    fn sequence(&mut self, indent: usize) -> Result<Value, String> {
+        let mut items = vec![];
+        loop {
+            self.skip_blank();
+            let Some(line) = self.lines.get(self.pos) else { break };
+            let content = &line[indent.min(line.len())..];
+            if self::indent(line) != indent || !is_item(content) {
+                break;
+            }
+            match content.strip_prefix("- ").map(str::trim_start).filter(|rest| !rest.is_empty()) {
+                // An item starting on the dash's line is read as if it had a line of its own.
+                Some(rest) => {
+                    let column = line.len() - rest.len();
+                    self.lines[self.pos] = format!("{}{}", " ".repeat(column), rest);
+                }
+                None => self.pos += 1,
+            }
+            items.push(self.node(indent + 1)?);
+        }
+        Ok(Value::Array(items))
+    }
+
+    fn mapping(&mut self, indent: usize) -> Result<Value, String> {
+        let mut map = Map::new();
+        loop {
+            self.skip_blank();
+            let Some(line) = self.lines.get(self.pos) else { break };
+            if self::indent(line) != indent {
+                break;
+            }
+            let Some((key, rest)) = key_split(&line[indent..]) else { break };
+            let rest = rest.to_string();
+            self.pos += 1;
+            let mut value = if is_blank(&rest) {
+                self.skip_blank();
+                match self.lines.get(self.pos) {
+                    Some(next) if self::indent(next) > indent => self.node(indent + 1)?,
+                    // A sequence may sit at its key's own indentation.
+                    Some(next) if self::indent(next) == indent && is_item(&next[indent..]) => self.sequence(indent)?,
+                    _ => Value::Null,
+                }
+            } else {
+                self.value(&rest, indent + 1)?
+            };
+            if key == "<<" {
+                // Keys of the mapping's own win over merged ones, wherever they're written.
+                let merged = match value.take() {
+                    Value::Array(items) => items,
+                    single => vec![single],
+                };
+                for item in merged {
+                    let Value::Object(entries) = item else { return Err(self.error("<< takes a mapping or a list of them")) };
+                    for (key, value) in entries {
+                        map.entry(key).or_insert(value);
+                    }
+                }
+                continue;
+            }
+            map.insert(key, value);
+        }
+        Ok(Value::Object(map))
+    }
+
+    /// A scalar or flow collection starting with `text`, on the line just read. Block scalars
+    /// and plain or double-quoted scalars carry on over following lines indented at least `min`.
+    fn value(&mut self, text: &str, min: usize) -> Result<Value, String> {
+        let text = text.trim();
+        if let Some(name) = text.strip_prefix('&') {
+            let (name, rest) = name.split_once(' ').unwrap_or((name, ""));
+            let value = match is_blank(rest) {
+                true => self.node(min)?,
+                false => self.value(rest, min)?,
+            };
+            self.anchors.insert(name.to_string(), value.clone());
+            return Ok(value);
+        }
+        if text.starts_with('*') {
+            return self.inline(text);
+        }
+        if let Some(header) = BlockHeader::parse(text) {
+            return Ok(Value::String(self.block(header, min)));
+        }
+        if text.starts_with('"') && quoted_end(text).is_none() {
+            let mut joined = text.to_string();
+            while quoted_end(&joined).is_none() {
+                let Some(line) = self.lines.get(self.pos) else { return Err(self.error("unterminated string")) };
+                joined.push(' ');
+                joined.push_str(line.trim());
+                self.pos += 1;
+            }
+            return self.inline(&joined);
+        }
+        if text.starts_with(['"', '\'', '[', '{']) {
+            return self.inline(text);
+        }
+        let mut plain = strip_comment(text).to_string();
+        while let Some(line) = self.lines.get(self.pos) {
+            if is_blank(line) || indent(line) < min {
+                break;
+            }
+            plain.push(' ');
+            plain.push_str(strip_comment(line.trim()));
+            self.pos += 1;
+        }
+        Ok(plain_scalar(&plain))
+    }
+
+    fn inline(&self, text: &str) -> Result<Value, String> {
+        let mut flow = Flow { chars: text.chars().collect(), pos: 0, anchors: &self.anchors };
+        let value = flow.value(false).map_err(|e| self.error(&e))?;
+        flow.skip_space();
+        match flow.chars.get(flow.pos) {
+            None | Some('#') => Ok(value),
+            Some(_) => Err(self.error(&format!("unexpected text after {}", text))),
+        }
+    }
+
+    fn block(&mut self, header: BlockHeader, min: usize) -> String {
+        let mut content_indent = header.indent.map(|indent| min.saturating_sub(1) + indent);
+        let mut lines: Vec<String> = vec![];
+        while let Some(line) = self.lines.get(self.pos) {
+            if line.trim().is_empty() {
+                lines.push(String::new());
+                self.pos += 1;
+                continue;
+            }
+            let expected = *content_indent.get_or_insert(indent(line));
+            if indent(line) < expected.max(min) {
+                break;
+            }
+            lines.push(line[expected..].to_string());
+            self.pos += 1;
+        }
+        let mut text = String::new();
+        for (i, line) in lines.iter().enumerate() {
+            if i > 0 {
+                let previous = &lines[i - 1];
+                if line.is_empty() || !header.folded || line.starts_with(' ') || previous.starts_with(' ') {
+                    text.push('\n');
+                } else if !previous.is_empty() {
+                    text.push(' ');
+                }
+            }
+            text.push_str(line);
+        }
+        match header.chomp {
+            Chomp::Keep => text + "\n",
+            Chomp::Clip if !text.trim_end_matches('\n').is_empty() => text.trim_end_matches('\n').to_string() + "\n",
+            _ => text.trim_end_matches('\n').to_string(),
+        }
+    }
+}
+
+enum Chomp {
+    Clip,
+    Strip,
+    Keep,
+}
+
+/// `|` or `>`, then optionally an indentation digit and `-` or `+`, in either order.
+struct BlockHeader {
+    folded: bool,
+    chomp: Chomp,
+    indent: Option<usize>,
+}
+
+impl BlockHeader {
+    fn parse(text: &str) -> Option<Self> {
+        let text = strip_comment(text);
+        let folded = match text.chars().next()? {
+            '|' => false,
+            '>' => true,
+            _ => return None,
+        };
+        let mut header = Self { folded, chomp: Chomp::Clip, indent: None };
+        for c in text[1..].chars() {
+            match c {
+                '-' => header.chomp = Chomp::Strip,
+                '+' => header.chomp = Chomp::Keep,
+                '1'..='9' => header.indent = c.to_digit(10).map(|digit| digit as usize),
+                _ => return None,
+            }
+        }
+        Some(header)
+    }
+}
+
+/// A `key: value` line split at the colon, the key unquoted.
+fn key_split(content: &str) -> Option<(String, &str)> {
+    if content.starts_with(['"', '\'']) {
+        let end = quoted_end(content)?;
+        let key = Flow { chars: content[..end].chars().collect(), pos: 0, anchors: &HashMap::new() }.quoted().ok()?;
+        let rest = content[end..].trim_start().strip_prefix(':')?;
+        return (rest.is_empty() || rest.starts_with(' ')).then_some((key, rest));
+    }
+    if content.starts_with(['[', '{', '#', '&', '*']) || is_item(content) {
+        return None;
+    }
+    let colon = content.char_indices().find(|&(i, c)| c == ':' && content[i + 1..].chars().next().is_none_or(|next| next == ' '))?.0;
+    Some((content[..colon].trim_end().to_string(), &content[colon + 1..]))
+}
+
+/// The byte just past the closing quote of a quoted scalar at the start of `text`.
+fn quoted_end(text: &str) -> Option<usize> {
+    let quote = text.chars().next()?;
+    let mut chars = text.char_indices().skip(1);
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' if quote == '"' => {
+                chars.next();
+            }
+            // Two single quotes are an escaped one.
+            '\'' if quote == '\'' && text[i + 1..].starts_with('\'') => {
+                chars.next();
+            }
+            c if c == quote => return Some(i + 1),
+            _ => {}
+        }
+    }
+    None
+}
+
+fn strip_comment(text: &str) -> &str {
+    match text.find(" #") {
+        Some(i) => text[..i].trim_end(),
+        None if text.starts_with('#') => "",
+        None => text,
+    }
+}
+
+fn plain_scalar(text: &str) -> Value {
+    match text {
+        "" | "~" | "null" | "Null" | "NULL" => Value::Null,
+        "true" | "True" | "TRUE" => Value::Bool(true),
+        "false" | "False" | "FALSE" => Value::Bool(false),
+        _ => {
+            if let Ok(integer) = text.parse::<i64>() {
+                return Value::Number(integer.into());
+            }
+            let numeric = text.trim_start_matches(['-', '+']).trim_start_matches('.').starts_with(|c: char| c.is_ascii_digit());
+            match text.parse::<f64>().ok().filter(|_| numeric).and_then(Number::from_f64) {
+                Some(number) => Value::Number(number),
+                None => Value::String(text.to_string()),
+            }
+        }
+    }
+}
+
+/// Scalars and collections in flow style, like `[a, "b"]` or `{ name: x }`.
+struct Flow<'a> {
+    chars: Vec<char>,
+    pos: usize,
+    anchors: &'a HashMap<String, Value>,
+}
+
+impl Flow<'_> {
+    fn skip_space(&mut self) {
+        while self.chars.get(self.pos).is_some_and(|c| c.is_whitespace()) {
+            self.pos += 1;
+        }
+    }
+
+    /// Inside a collection, plain scalars end at `,`, `]`, `}` and, for keys, `: `.
+    fn value(&mut self, nested: bool) -> Result<Value, String> {
+        self.skip_space();
+        match self.chars.get(self.pos) {
+            Some('[') => {
+                self.pos += 1;
+                let mut items = vec![];
+                loop {
+                    self.skip_space();
+                    if self.chars.get(self.pos) == Some(&']') {
+                        self.pos += 1;
+                        return Ok(Value::Array(items));
+                    }
+                    items.push(self.value(true)?);
+                    self.separator(']')?;
+                }
+            }
+            Some('{') => {
+                self.pos += 1;
+                let mut map = Map::new();
+                loop {
+                    self.skip_space();
+                    if self.chars.get(self.pos) == Some(&'}') {
+                        self.pos += 1;
+                        return Ok(Value::Object(map));
+                    }
+                    let key = match self.value(true)? {
+                        Value::String(key) => key,
+                        key => key.to_string(),
+                    };
+                    self.skip_space();
+                    let value = match self.chars.get(self.pos) {
+                        Some(':') => {
+                            self.pos += 1;
+                            self.value(true)?
+                        }
+                        _ => Value::Null,
+                    };
+                    map.insert(key, value);
+                    self.separator('}')?;
+                }
+            }
+            Some('"' | '\'') => self.quoted().map(Value::String),
+            Some('*') => {
+                self.pos += 1;
+                let start = self.pos;
+                while self.chars.get(self.pos).is_some_and(|c| !c.is_whitespace() && !matches!(c, ',' | ']' | '}')) {
+                    self.pos += 1;
+                }
+                let name: String = self.chars[start..self.pos].iter().collect();
+                self.anchors.get(&name).cloned().ok_or_else(|| format!("unknown alias *{}", name))
+            }
+            Some(_) => {
+                let start = self.pos;
+                while let Some(&c) = self.chars.get(self.pos) {
+                    let ends_key = c == ':' && self.chars.get(self.pos + 1).is_none_or(|next| next.is_whitespace());
+                    if nested && (matches!(c, ',' | ']' | '}') || ends_key) {
+                        break;
+                    }
+                    self.pos += 1;
+                }
+                let text: String = self.chars[start..self.pos].iter().collect();
+                Ok(plain_scalar(strip_comment(text.trim())))
+            }
+            None => Ok(Value::Null),
+        }
+    }
+
+    fn separator(&mut self, close: char) -> Result<(), String> {
+        self.skip_space();
+        match self.chars.get(self.pos) {
+            Some(',') => {
+                self.pos += 1;
+                Ok(())
+            }
+            Some(&c) if c == close => Ok(()),
+            _ => Err(format!("expected , or {}", close)),
+        }
+    }
+
+    fn quoted(&mut self) -> Result<String, String> {
+        let quote = self.chars[self.pos];
+        self.pos += 1;
+        let mut out = String::new();
+        loop {
+            let Some(&c) = self.chars.get(self.pos) else { return Err("unterminated string".to_string()) };
+            self.pos += 1;
+            match c {
+                '\'' if quote == '\'' && self.chars.get(self.pos) == Some(&'\'') => {
+                    out.push('\'');
+                    self.pos += 1;
+                }
+                c if c == quote => return Ok(out),
+                '\\' if quote == '"' => {
+                    let Some(&escape) = self.chars.get(self.pos) else { return Err("unterminated string".to_string()) };
+                    self.pos += 1;
+                    match escape {
+                        'n' => out.push('\n'),
+                        't' => out.push('\t'),
+                        'r' => out.push('\r'),
+                        '0' => out.push('\0'),
+                        'e' => out.push('\u{1b}'),
+                        '_' => out.push('\u{a0}'),
+                        'x' | 'u' | 'U' => {
+                            let digits = match escape {
+                                'x' => 2,
+                                'u' => 4,
+                                _ => 8,
+                            };
+                            let hex: String = self.chars.iter().skip(self.pos).take(digits).collect();
+                            self.pos += digits;
+                            let code = u32::from_str_radix(&hex, 16).map_err(|_| format!("bad escape \\{}{}", escape, hex))?;
+                            out.push(char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER));
+                        }
+                        other => out.push(other),
+                    }
+                }
+                c => out.push(c),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::insomnia;
+
+    const EXPORT: &str = r#"_type: export
+__export_format: 4
+__export_source: insomnia.desktop.app:v2023.5.8
+resources:
+  - _id: wrk_1
+    _type: workspace
+    name: 'Shop: "staging"'
+  - _id: fld_1
+    _type: request_group
+    parentId: wrk_1
+    name: Orders   # a trailing comment
+  - _id: req_1
+    _type: request
+    parentId: fld_1
+    metaSortKey: -2
+    name: Create order
+    method: POST
+    url: "{{ _.base }}/orders?note=a\tb"
+    headers: &json_headers
+      - name: Accept
+        value: application/json
+      - {name: X-Trace, value: '#not-a-comment', disabled: false}
+    parameters: [{name: page, value: "1"}, {name: debug, value: 'it''s on', disabled: true}]
+    body:
+      mimeType: application/json
+      text: |
+        {
+          "sku": "A-1",
+          "qty": 2
+        }
+    description: >-
+      Creates an order
+      for the shop.
+
+      Needs a token.
+  - _id: req_2
+    _type: request
+    parentId: fld_1
+    metaSortKey: -1
+    name: List orders
+    method: GET
+    url: '{{ _.base }}/orders'
+    headers: *json_headers
+    authentication: {type: bearer, token: "{{ _.token }}"}
+  - _id: env_1
+    _type: environment
+    parentId: wrk_1
+    name: Base Environment
+    data: &base
+      base: https://shop.test
+      retries: 3
+      tags: [a, "b, c", 'd']
+  - _id: env_2
+    _type: environment
+    parentId: env_1
+    name: Staging
+    data:
+      <<: *base
+      base: https://staging.shop.test
+      token: "s3cr\u0065t"
+"#;
+
+    #[test]
+    fn reads_an_insomnia_export() {
+        let headers = json!([
+            {"name": "Accept", "value": "application/json"},
+            {"name": "X-Trace", "value": "#not-a-comment", "disabled": false},
+        ]);
+        let expected = json!({
+            "_type": "export",
+            "__export_format": 4,
+            "__export_source": "insomnia.desktop.app:v2023.5.8",
+            "resources": [
+                {"_id": "wrk_1", "_type": "workspace", "name": "Shop: \"staging\""},
+                {"_id": "fld_1", "_type": "request_group", "parentId": "wrk_1", "name": "Orders"},
+                {
+                    "_id": "req_1", "_type": "request", "parentId": "fld_1", "metaSortKey": -2, "name": "Create order",
+                    "method": "POST", "url": "{{ _.base }}/orders?note=a\tb", "headers": headers,
+                    "parameters": [{"name": "page", "value": "1"}, {"name": "debug", "value": "it's on", "disabled": true}],
+                    "body": {"mimeType": "application/json", "text": "{\n  \"sku\": \"A-1\",\n  \"qty\": 2\n}\n"},
+                    "description": "Creates an order for the shop.\nNeeds a token.",
+                },
+                {
+                    "_id": "req_2", "_type": "request", "parentId": "fld_1", "metaSortKey": -1, "name": "List orders",
+                    "method": "GET", "url": "{{ _.base }}/orders", "headers": headers,
+                    "authentication": {"type": "bearer", "token": "{{ _.token }}"},
+                },
+                {
+                    "_id": "env_1", "_type": "environment", "parentId": "wrk_1", "name": "Base Environment",
+                    "data": {"base": "https://shop.test", "retries": 3, "tags": ["a", "b, c", "d"]},
+                },
+                {
+                    "_id": "env_2", "_type": "environment", "parentId": "env_1", "name": "Staging",
+                    "data": {"base": "https://staging.shop.test", "retries": 3, "tags": ["a", "b, c", "d"], "token": "s3cret"},
+                },
+            ],
+        });
+        assert_eq!(parse(EXPORT).unwrap(), expected);
+    }
+
+    #[test]
+    fn yaml_export_imports_as_its_json_does() {
+        let json = serde_json::to_string_pretty(&parse(EXPORT).unwrap()).unwrap();
+        let (from_yaml, from_json) = (insomnia::import(EXPORT).unwrap(), insomnia::import(&json).unwrap());
+        assert_eq!(from_yaml.name, "Shop: \"staging\"");
+        assert_eq!(serde_json::to_value(&from_yaml.collection).unwrap(), serde_json::to_value(&from_json.collection).unwrap());
+        let requests: Vec<_> = from_yaml.collection.requests.iter().map(|saved| (saved.name.as_str(), saved.request.headers.as_str())).collect();
+        assert_eq!(requests, [
+            ("Create order", "Accept: application/json\nX-Trace: #not-a-comment\nContent-Type: application/json"),
+            ("List orders", "Accept: application/json\nX-Trace: #not-a-comment\nAuthorization: Bearer {{token}}"),
+        ]);
+        assert_eq!(from_yaml.collection.requests[0].request.url, "{{base}}/orders?note=a\tb&page=1");
+        let staging = &from_yaml.environments[0];
+        assert_eq!((staging.name.as_str(), staging.variables["base"].as_str(), staging.variables["token"].as_str()), ("Staging", "https://staging.shop.test", "s3cret"));
+    }
+
+    #[test]
+    fn block_scalar_chomping_and_indentation() {
+        let parsed = parse("keep: |+\n  a\n\nstrip: >-\n  a\n  b\n\n  c\nclip: |2\n    indented\n  b\n").unwrap();
+        assert_eq!(parsed, json!({"keep": "a\n\n", "strip": "a b\nc", "clip": "  indented\nb\n"}));
+    }
+
+    #[test]
+    fn quoting() {
+        let parsed = parse("a: 'it''s: # here'\nb: \"tab\\there \\u00e9\"\n\"c: d\": \"multi\n  line\"\ne: '007'\nf: 007\n").unwrap();
+        assert_eq!(parsed, json!({"a": "it's: # here", "b": "tab\there é", "c: d": "multi line", "e": "007", "f": 7}));
+    }
+
+    #[test]
+    fn nested_flow_collections() {
+        let parsed = parse("x: {a: [1, {b: null}], 'c': [], d: {}}  # comment\n").unwrap();
+        assert_eq!(parsed, json!({"x": {"a": [1, {"b": null}], "c": [], "d": {}}}));
+    }
+
+    #[test]
+    fn merged_keys_lose_to_the_mappings_own() {
+        let parsed = parse("base: &b {x: 1, y: 2}\nmore: &m\n  z: 3\nboth:\n  x: 9\n  <<: [*b, *m]\n").unwrap();
+        assert_eq!(parsed["both"], json!({"x": 9, "y": 2, "z": 3}));
+    }
+
+    #[test]
+    fn reports_bad_input() {
+        assert_eq!(parse("a: *missing").unwrap_err(), "line 2: unknown alias *missing");
+        assert_eq!(parse("a: \"open\nb: c").unwrap_err(), "line 3: unterminated string");
+        assert_eq!(parse("a: [1, 2\n").unwrap_err(), "line 2: expected , or ]");
+        assert_eq!(parse("a: &s 1\n<<: *s").unwrap_err(), "line 3: << takes a mapping or a list of them");
+    }
+}